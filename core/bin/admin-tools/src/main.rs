@@ -44,6 +44,8 @@ enum ProverCommand {
     Stats,
     /// List specific jobs
     Ls(ProverLsCommand),
+    /// Set priority of the FRI scheduler witness job for an L1 batch.
+    SchedulerPriority(SchedulerPriorityCommand),
 }
 
 #[derive(Subcommand)]
@@ -123,6 +125,21 @@ fn prover_ls<'a>(cmd: &ProverLsCommand) -> Result<AppFnBox<'a>, AppError> {
     }))
 }
 
+#[derive(Args)]
+struct SchedulerPriorityCommand {
+    /// L1 batch number of the job.
+    l1_batch_number: u32,
+    /// Jobs with higher priority are picked up first; the default priority is 0.
+    #[arg(allow_negative_numbers = true)]
+    priority: i32,
+}
+
+fn prover_scheduler_priority<'a>(cmd: &SchedulerPriorityCommand) -> AppFnBox<'a> {
+    let l1_batch_number = L1BatchNumber(cmd.l1_batch_number);
+    let priority = cmd.priority;
+    Box::new(move |app| prover::set_scheduler_job_priority(app, l1_batch_number, priority))
+}
+
 #[derive(Args)]
 struct BlockShowCommand {
     id: u32,
@@ -139,6 +156,7 @@ fn match_prover_cmd(cmd: &ProverCommand) -> CmdMatch {
     match cmd {
         ProverCommand::Stats => Ok(prover_stats()),
         ProverCommand::Ls(x) => prover_ls(x),
+        ProverCommand::SchedulerPriority(x) => Ok(prover_scheduler_priority(x)),
     }
 }
 
@@ -167,3 +167,28 @@ Status: {}",
 
     Ok(())
 }
+
+pub fn set_scheduler_job_priority(
+    app: &mut App,
+    l1_batch_number: L1BatchNumber,
+    priority: i32,
+) -> Result<(), AppError> {
+    let handle = app.tokio.handle();
+    let is_updated = handle.block_on(
+        app.db
+            .fri_witness_generator_dal()
+            .set_scheduler_job_priority(l1_batch_number, priority),
+    );
+    if !is_updated {
+        return Err(AppError::Command(format!(
+            "No unfinished scheduler job for L1 batch {}",
+            l1_batch_number
+        )));
+    }
+
+    println!(
+        "Set priority of scheduler job for L1 batch {} to {}",
+        l1_batch_number, priority
+    );
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! Tool removing blobs produced by FRI witness generators and provers for L1 batches
+//! that were proven on L1 long enough ago. Removed blobs are recorded in the `pruned_blobs_log`
+//! table of the prover DB.
+
+use anyhow::Context as _;
+use structopt::StructOpt;
+
+use std::time::Duration;
+
+use prometheus_exporter::run_prometheus_exporter;
+use zksync_config::configs::PrometheusConfig;
+use zksync_core::house_keeper::fri_blob_cleaner::get_l1_batch_blob_keys;
+use zksync_dal::{connection::DbVariant, ConnectionPool};
+use zksync_object_store::ObjectStoreFactory;
+use zksync_types::L1BatchNumber;
+
+use crate::pruner::prune_blobs;
+
+mod pruner;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "zksync_blob_pruner",
+    about = "Removes FRI prover blobs for L1 batches proven on L1"
+)]
+struct Opt {
+    /// Min age of the L1 prove transaction for blobs of an L1 batch to be removed.
+    #[structopt(long, default_value = "7")]
+    retention_days: u64,
+    /// Max number of L1 batches, blobs for which are removed in a single run.
+    #[structopt(long, default_value = "10")]
+    max_batches: u32,
+    /// Print blobs that would be removed without removing them.
+    #[structopt(long)]
+    dry_run: bool,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    vlog::init();
+    let opt = Opt::from_args();
+    let prometheus_config = PrometheusConfig::from_env();
+    let push_interval = prometheus_config.push_interval();
+    // The tool is short-lived, so metrics are pushed rather than scraped.
+    run_prometheus_exporter(
+        prometheus_config.listener_port,
+        Some((prometheus_config.pushgateway_url.clone(), push_interval)),
+    );
+
+    let connection_pool = ConnectionPool::new(Some(1), DbVariant::Master).await;
+    let prover_connection_pool = ConnectionPool::new(Some(1), DbVariant::Prover).await;
+    let object_store = ObjectStoreFactory::from_env()
+        .create_store()
+        .await
+        .context("failed initializing object store")?;
+
+    let mut prover_storage = prover_connection_pool.access_storage().await;
+    let first_l1_batch = prover_storage
+        .fri_prover_jobs_dal()
+        .min_l1_batch_with_uncleaned_blobs()
+        .await;
+    let retention = Duration::from_secs(opt.retention_days * 24 * 60 * 60);
+    let last_l1_batch = connection_pool
+        .access_storage()
+        .await
+        .blocks_dal()
+        .get_number_of_last_block_proven_on_eth_before(retention)
+        .await;
+    let (first_l1_batch, last_l1_batch) = match (first_l1_batch, last_l1_batch) {
+        (Some(first), Some(last)) if opt.max_batches > 0 => {
+            (first.0, last.0.min(first.0 + opt.max_batches - 1))
+        }
+        _ => {
+            vlog::info!("No L1 batches to prune blobs for");
+            return Ok(());
+        }
+    };
+
+    for l1_batch_number in first_l1_batch..=last_l1_batch {
+        let l1_batch_number = L1BatchNumber(l1_batch_number);
+        let keys_by_bucket = get_l1_batch_blob_keys(&mut prover_storage, l1_batch_number).await;
+        let pruned_blobs = prune_blobs(&*object_store, &keys_by_bucket, opt.dry_run)
+            .await
+            .with_context(|| format!("failed pruning blobs for L1 batch {l1_batch_number}"))?;
+
+        if opt.dry_run {
+            for blob in &pruned_blobs {
+                println!(
+                    "{l1_batch_number}\t{}\t{}\t{}",
+                    blob.bucket, blob.key, blob.size_bytes
+                );
+            }
+            continue;
+        }
+
+        let mut transaction = prover_storage.start_transaction().await;
+        transaction
+            .pruned_blobs_log_dal()
+            .insert_pruned_blobs(l1_batch_number, &pruned_blobs)
+            .await;
+        transaction
+            .fri_prover_jobs_dal()
+            .mark_l1_batch_blobs_as_cleaned(l1_batch_number)
+            .await;
+        transaction.commit().await;
+
+        let bytes_deleted: u64 = pruned_blobs.iter().map(|blob| blob.size_bytes).sum();
+        vlog::info!(
+            "Pruned {} blobs ({bytes_deleted} bytes) for L1 batch {l1_batch_number}",
+            pruned_blobs.len()
+        );
+        for blob in pruned_blobs {
+            metrics::increment_counter!(
+                "blob_pruner.deleted_objects",
+                "bucket" => blob.bucket.clone()
+            );
+            metrics::counter!(
+                "blob_pruner.deleted_bytes",
+                blob.size_bytes,
+                "bucket" => blob.bucket
+            );
+        }
+        metrics::gauge!(
+            "blob_pruner.last_pruned_l1_batch",
+            f64::from(l1_batch_number.0)
+        );
+    }
+
+    // Give the exporter a chance to push the final metric values.
+    tokio::time::sleep(push_interval * 2).await;
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! Removal of blobs for a single L1 batch.
+
+use futures::{stream, StreamExt, TryStreamExt};
+
+use std::collections::HashMap;
+
+use zksync_dal::pruned_blobs_log_dal::PrunedBlob;
+use zksync_object_store::{Bucket, ObjectStore, ObjectStoreError};
+
+/// Max number of concurrent requests for blob sizes.
+const MAX_CONCURRENT_SIZE_REQUESTS: usize = 16;
+
+/// Finds blobs with the specified keys that are present in the store and removes them,
+/// unless `dry_run` is set. Returns the found blobs ordered by the bucket; blobs that are
+/// already removed are skipped.
+pub async fn prune_blobs(
+    object_store: &dyn ObjectStore,
+    keys_by_bucket: &HashMap<Bucket, Vec<String>>,
+    dry_run: bool,
+) -> Result<Vec<PrunedBlob>, ObjectStoreError> {
+    let mut buckets: Vec<_> = keys_by_bucket.keys().copied().collect();
+    buckets.sort_unstable_by_key(|bucket| bucket.to_string());
+
+    let mut pruned_blobs = vec![];
+    for bucket in buckets {
+        let keys = &keys_by_bucket[&bucket];
+        let sizes: Vec<_> = stream::iter(keys)
+            .map(|key| async move {
+                match object_store.get_raw_size(bucket, key).await {
+                    Ok(size) => Ok(Some(size)),
+                    Err(ObjectStoreError::KeyNotFound(_)) => Ok(None),
+                    Err(err) => Err(err),
+                }
+            })
+            .buffered(MAX_CONCURRENT_SIZE_REQUESTS)
+            .try_collect()
+            .await?;
+        let present_keys: Vec<_> = keys
+            .iter()
+            .zip(sizes)
+            .filter_map(|(key, size)| Some((key.clone(), size?)))
+            .collect();
+        if present_keys.is_empty() {
+            continue;
+        }
+
+        if !dry_run {
+            let keys: Vec<_> = present_keys.iter().map(|(key, _)| key.clone()).collect();
+            object_store.remove_raw_many(bucket, &keys).await?;
+        }
+        let blobs = present_keys
+            .into_iter()
+            .map(|(key, size_bytes)| PrunedBlob {
+                bucket: bucket.to_string(),
+                key,
+                size_bytes,
+            });
+        pruned_blobs.extend(blobs);
+    }
+    Ok(pruned_blobs)
+}
+
+#[cfg(test)]
+mod tests {
+    use zksync_object_store::ObjectStoreFactory;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn blobs_are_pruned() {
+        let factory = ObjectStoreFactory::mock();
+        let object_store = factory.create_store().await.unwrap();
+        object_store
+            .put_raw(Bucket::ProverJobsFri, "circuit.bin", vec![0; 10])
+            .await
+            .unwrap();
+        object_store
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", vec![0; 5])
+            .await
+            .unwrap();
+        let keys_by_bucket = HashMap::from([
+            (Bucket::ProverJobsFri, vec!["circuit.bin".to_owned()]),
+            (
+                Bucket::ProofsFri,
+                vec!["proof_1.bin".to_owned(), "proof_2.bin".to_owned()],
+            ),
+        ]);
+
+        let blobs = prune_blobs(&*object_store, &keys_by_bucket, true)
+            .await
+            .unwrap();
+        let expected_blobs = [
+            PrunedBlob {
+                bucket: "proofs_fri".to_owned(),
+                key: "proof_1.bin".to_owned(),
+                size_bytes: 5,
+            },
+            PrunedBlob {
+                bucket: "prover_jobs_fri".to_owned(),
+                key: "circuit.bin".to_owned(),
+                size_bytes: 10,
+            },
+        ];
+        assert_eq!(blobs, expected_blobs);
+        // Blobs are not removed in the dry-run mode.
+        assert_eq!(factory.mock_keys(Bucket::ProofsFri).await, ["proof_1.bin"]);
+
+        let blobs = prune_blobs(&*object_store, &keys_by_bucket, false)
+            .await
+            .unwrap();
+        assert_eq!(blobs, expected_blobs);
+        assert!(factory.mock_keys(Bucket::ProofsFri).await.is_empty());
+        assert!(factory.mock_keys(Bucket::ProverJobsFri).await.is_empty());
+
+        let blobs = prune_blobs(&*object_store, &keys_by_bucket, false)
+            .await
+            .unwrap();
+        assert!(blobs.is_empty());
+    }
+}
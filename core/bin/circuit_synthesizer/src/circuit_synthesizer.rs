@@ -25,13 +25,14 @@ use zksync_dal::ConnectionPool;
 use zksync_object_store::{CircuitKey, ObjectStore, ObjectStoreError, ObjectStoreFactory};
 use zksync_prover_utils::numeric_index_to_circuit_name;
 use zksync_prover_utils::region_fetcher::{get_region, get_zone};
-use zksync_queued_job_processor::{async_trait, JobProcessor};
+use zksync_queued_job_processor::{async_trait, JobError, JobProcessor};
 
 #[derive(Debug)]
 pub enum CircuitSynthesizerError {
     InvalidGroupCircuits(u8),
     InvalidCircuitId(u8),
     InputLoadFailed(ObjectStoreError),
+    ObjectStoreInitFailed(ObjectStoreError),
 }
 
 pub struct CircuitSynthesizer {
@@ -74,9 +75,13 @@ impl CircuitSynthesizer {
             config.prover_group_id
         );
 
+        let blob_store = store_factory
+            .create_store()
+            .await
+            .map_err(CircuitSynthesizerError::ObjectStoreInitFailed)?;
         Ok(Self {
             config,
-            blob_store: store_factory.create_store().await,
+            blob_store,
             allowed_circuit_types: allowed_circuit_types
                 .map(|x| x.into_iter().map(|x| x.1).collect()),
             region: get_region().await,
@@ -119,7 +124,7 @@ impl JobProcessor for CircuitSynthesizer {
     type JobArtifacts = (ProvingAssembly, u8);
     const SERVICE_NAME: &'static str = "CircuitSynthesizer";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
         vlog::trace!(
             "Attempting to fetch job types: {:?}",
             self.allowed_circuit_types
@@ -134,7 +139,11 @@ impl JobProcessor for CircuitSynthesizer {
                     .await
             }
             None => storage.prover_dal().get_next_prover_job().await,
-        }?;
+        };
+        let prover_job = match prover_job {
+            Some(prover_job) => prover_job,
+            None => return Ok(None),
+        };
 
         let circuit_key = CircuitKey {
             block_number: prover_job.block_number,
@@ -146,23 +155,24 @@ impl JobProcessor for CircuitSynthesizer {
             .blob_store
             .get(circuit_key)
             .await
-            .map_err(CircuitSynthesizerError::InputLoadFailed)
-            .unwrap_or_else(|err| panic!("{err:?}"));
+            .map_err(|err| {
+                anyhow::anyhow!("{:?}", CircuitSynthesizerError::InputLoadFailed(err))
+            })?;
 
-        Some((prover_job.id, input))
+        Ok(Some((prover_job.id, input)))
     }
 
     async fn save_failure(
         &self,
         job_id: Self::JobId,
         _started_at: Instant,
-        error: String,
+        error: JobError,
     ) {
         self.prover_connection_pool
             .access_storage()
             .await
             .prover_dal()
-            .save_proof_error(job_id, error, self.config.max_attempts)
+            .save_proof_error(job_id, error.to_string(), self.config.max_attempts)
             .await;
     }
 
@@ -170,8 +180,8 @@ impl JobProcessor for CircuitSynthesizer {
         &self,
         job: Self::Job,
         _started_at: Instant,
-    ) -> JoinHandle<Self::JobArtifacts> {
-        tokio::task::spawn_blocking(move || Self::synthesize(job))
+    ) -> JoinHandle<anyhow::Result<Self::JobArtifacts>> {
+        tokio::task::spawn_blocking(move || Ok(Self::synthesize(job)))
     }
 
     async fn save_result(
@@ -179,7 +189,7 @@ impl JobProcessor for CircuitSynthesizer {
         job_id: Self::JobId,
         _started_at: Instant,
         (assembly, circuit_id): Self::JobArtifacts,
-    ) {
+    ) -> anyhow::Result<()> {
         vlog::trace!(
             "Finished circuit synthesis for job: {job_id} in region: {}",
             self.region
@@ -224,7 +234,7 @@ impl JobProcessor for CircuitSynthesizer {
                 .await;
 
                 if result.is_ok() {
-                    return;
+                    return Ok(());
                 }
                 // We'll retry with another prover again, no point in dropping the results.
 
@@ -242,6 +252,7 @@ impl JobProcessor for CircuitSynthesizer {
         vlog::trace!(
             "Not able to get any free prover instance for sending assembly for job: {job_id}"
         );
+        Ok(())
     }
 }
 
@@ -61,7 +61,12 @@ async fn main() {
     };
     let tasks = vec![
         run_prometheus_exporter(prometheus_config.listener_port, None),
-        tokio::spawn(circuit_synthesizer.run(stop_receiver, opt.number_of_iterations)),
+        tokio::spawn(async move {
+            circuit_synthesizer
+                .run(stop_receiver, opt.number_of_iterations)
+                .await
+                .unwrap_or_else(|err| panic!("Circuit synthesizer failed: {err:#}"))
+        }),
     ];
 
     let particular_crypto_alerts = Some(AlertsConfig::from_env().sporadic_crypto_errors_substrs);
@@ -162,7 +162,12 @@ async fn main() {
     let tasks = vec![
         // The prover connection pool is not used by the contract verifier, but we need to pass it
         // since `JobProcessor` trait requires it.
-        tokio::spawn(contract_verifier.run(stop_receiver, opt.jobs_number)),
+        tokio::spawn(async move {
+            contract_verifier
+                .run(stop_receiver, opt.jobs_number)
+                .await
+                .unwrap_or_else(|err| panic!("Contract verifier failed: {:#}", err))
+        }),
         prometheus_exporter::run_prometheus_exporter(prometheus_config.listener_port, None),
     ];
 
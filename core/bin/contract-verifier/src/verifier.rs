@@ -3,6 +3,7 @@ use std::env;
 use std::path::Path;
 use std::time::{Duration, Instant};
 
+use anyhow::Context as _;
 use chrono::Utc;
 use ethabi::{Contract, Token};
 use lazy_static::lazy_static;
@@ -11,7 +12,7 @@ use tokio::time;
 
 use zksync_config::ContractVerifierConfig;
 use zksync_dal::{ConnectionPool, StorageProcessor};
-use zksync_queued_job_processor::{async_trait, JobProcessor};
+use zksync_queued_job_processor::{async_trait, JobError, JobProcessor};
 use zksync_types::{
     explorer_api::{
         CompilationArtifacts, CompilerType, DeployContractCalldata, SourceCodeData,
@@ -456,7 +457,7 @@ impl JobProcessor for ContractVerifier {
     const SERVICE_NAME: &'static str = "contract_verifier";
     const BACKOFF_MULTIPLIER: u64 = 1;
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
         let mut connection = self.connection_pool.access_storage().await;
 
         // Time overhead for all operations except for compilation.
@@ -470,12 +471,12 @@ impl JobProcessor for ContractVerifier {
             .contract_verification_dal()
             .get_next_queued_verification_request(self.config.compilation_timeout() + TIME_OVERHEAD)
             .await
-            .unwrap();
+            .context("failed fetching next verification request")?;
 
-        job.map(|job| (job.id, job))
+        Ok(job.map(|job| (job.id, job)))
     }
 
-    async fn save_failure(&self, job_id: usize, _started_at: Instant, error: String) {
+    async fn save_failure(&self, job_id: usize, _started_at: Instant, error: JobError) {
         let mut connection = self.connection_pool.access_storage().await;
 
         connection
@@ -485,7 +486,7 @@ impl JobProcessor for ContractVerifier {
                 job_id,
                 "Internal error".to_string(),
                 serde_json::Value::Array(Vec::new()),
-                Some(error),
+                Some(error.to_string()),
             )
             .await
             .unwrap();
@@ -496,7 +497,7 @@ impl JobProcessor for ContractVerifier {
         &self,
         job: VerificationRequest,
         started_at: Instant,
-    ) -> tokio::task::JoinHandle<()> {
+    ) -> tokio::task::JoinHandle<anyhow::Result<()>> {
         let connection_pool = self.connection_pool.clone();
         tokio::task::spawn(async move {
             vlog::info!("Started to process request with id = {}", job.id);
@@ -512,10 +513,17 @@ impl JobProcessor for ContractVerifier {
                 "api.contract_verifier.request_processing_time",
                 started_at.elapsed()
             );
+            Ok(())
         })
     }
 
-    async fn save_result(&self, _: Self::JobId, _: Instant, _: Self::JobArtifacts) {
+    async fn save_result(
+        &self,
+        _: Self::JobId,
+        _: Instant,
+        _: Self::JobArtifacts,
+    ) -> anyhow::Result<()> {
         // Do nothing
+        Ok(())
     }
 }
@@ -129,7 +129,8 @@ async fn init_tasks(
         max_block_batch: config.optional.max_blocks_per_tree_batch(),
         throttle_interval: config.optional.merkle_tree_throttle(),
     })
-    .await;
+    .await
+    .expect("failed initializing metadata calculator");
     healthchecks.push(Box::new(metadata_calculator.tree_health_check()));
 
     let consistency_checker = ConsistencyChecker::new(
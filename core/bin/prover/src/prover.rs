@@ -34,7 +34,9 @@ impl ProverReporter {
             pool,
             config,
             processed_by: env::var("POD_NAME").unwrap_or("Unknown".to_string()),
-            object_store: rt_handle.block_on(store_factory.create_store()),
+            object_store: rt_handle
+                .block_on(store_factory.create_store())
+                .expect("failed initializing object store"),
             rt_handle,
         }
     }
@@ -9,6 +9,7 @@ use zksync_config::configs::fri_prover_group::{CircuitIdRoundTuple, FriProverGro
 use zksync_config::configs::{FriProverConfig, PrometheusConfig};
 use zksync_config::{ApiConfig, ObjectStoreConfig};
 use zksync_dal::connection::DbVariant;
+use zksync_dal::fri_prover_job_notifications::NewProverJobsListener;
 use zksync_dal::ConnectionPool;
 use zksync_object_store::ObjectStoreFactory;
 use zksync_queued_job_processor::JobProcessor;
@@ -46,10 +47,14 @@ async fn main() {
     .expect("Error setting Ctrl+C handler");
 
     let (stop_sender, stop_receiver) = tokio::sync::watch::channel(false);
-    let blob_store = ObjectStoreFactory::from_env().create_store().await;
+    let blob_store = ObjectStoreFactory::from_env()
+        .create_store()
+        .await
+        .expect("failed initializing object store");
     let public_blob_store = ObjectStoreFactory::new(ObjectStoreConfig::public_from_env())
         .create_store()
-        .await;
+        .await
+        .expect("failed initializing public object store");
 
     vlog::info!("Starting FRI proof generation");
     let pool = ConnectionPool::new(None, DbVariant::Prover).await;
@@ -57,6 +62,15 @@ async fn main() {
         .get_circuit_ids_for_group_id(prover_config.specialized_group_id)
         .unwrap_or(vec![]);
 
+    let new_jobs_listener = if prover_config.listen_for_new_jobs() {
+        vlog::info!("Listening for notifications about new prover jobs");
+        NewProverJobsListener::new(&pool)
+            .await
+            .expect("failed listening for notifications about new prover jobs")
+    } else {
+        None
+    };
+
     let setup_load_mode = build_prover_setup_load_mode_using_config(&prover_config);
     let prover = Prover::new(
         blob_store,
@@ -65,10 +79,16 @@ async fn main() {
         pool,
         setup_load_mode,
         circuit_ids_for_round_to_be_proven,
+        new_jobs_listener,
     );
     let tasks = vec![
         prometheus_exporter::run_prometheus_exporter(prometheus_config.listener_port, None),
-        tokio::spawn(prover.run(stop_receiver, None)),
+        tokio::spawn(async move {
+            prover
+                .run(stop_receiver, None)
+                .await
+                .unwrap_or_else(|err| panic!("FRI prover failed: {err:#}"))
+        }),
     ];
 
     let particular_crypto_alerts = None;
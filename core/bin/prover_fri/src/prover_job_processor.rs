@@ -1,6 +1,10 @@
+use anyhow::Context as _;
 use std::collections::HashMap;
-use std::{sync::Arc, time::Instant};
-use tokio::task::JoinHandle;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::Mutex, task::JoinHandle};
 
 use circuit_definitions::aux_definitions::witness_oracle::VmWitnessOracle;
 use circuit_definitions::boojum::algebraic_props::round_function::AbsorptionModeOverwrite;
@@ -23,9 +27,10 @@ use zkevm_test_harness::prover_utils::{
     verify_recursion_layer_proof,
 };
 use zksync_config::configs::FriProverConfig;
+use zksync_dal::fri_prover_job_notifications::{NewProverJobNotification, NewProverJobsListener};
 use zksync_dal::ConnectionPool;
 use zksync_object_store::{FriCircuitKey, ObjectStore};
-use zksync_queued_job_processor::{async_trait, JobProcessor};
+use zksync_queued_job_processor::{async_trait, JobError, JobProcessor};
 use zksync_types::L1BatchNumber;
 
 use zksync_config::configs::fri_prover_group::CircuitIdRoundTuple;
@@ -64,6 +69,8 @@ pub struct Prover {
     // Only pick jobs for the configured circuit id and aggregation rounds.
     // Empty means all jobs are picked.
     circuit_ids_for_round_to_be_proven: Vec<CircuitIdRoundTuple>,
+    // If set, the prover waits for notifications about new jobs while the job queue is empty.
+    new_jobs_listener: Option<Mutex<NewProverJobsListener>>,
 }
 
 impl Prover {
@@ -74,6 +81,7 @@ impl Prover {
         prover_connection_pool: ConnectionPool,
         setup_load_mode: SetupLoadMode,
         circuit_ids_for_round_to_be_proven: Vec<CircuitIdRoundTuple>,
+        new_jobs_listener: Option<NewProverJobsListener>,
     ) -> Self {
         Prover {
             blob_store,
@@ -82,9 +90,21 @@ impl Prover {
             prover_connection_pool,
             setup_load_mode,
             circuit_ids_for_round_to_be_proven,
+            new_jobs_listener: new_jobs_listener.map(Mutex::new),
         }
     }
 
+    /// Returns `true` if the job from the notification can be picked up by this prover.
+    fn is_proven_by_this_prover(&self, notification: &NewProverJobNotification) -> bool {
+        self.circuit_ids_for_round_to_be_proven.is_empty()
+            || self
+                .circuit_ids_for_round_to_be_proven
+                .contains(&CircuitIdRoundTuple::new(
+                    notification.circuit_id,
+                    notification.aggregation_round as u8,
+                ))
+    }
+
     fn get_setup_data(&self, key: ProverServiceDataKey) -> Arc<GoldilocksProverSetupData> {
         match &self.setup_load_mode {
             SetupLoadMode::FromMemory(cache) => cache
@@ -226,6 +246,7 @@ impl Prover {
     }
 }
 
+#[derive(Clone)]
 pub struct ProverJob {
     block_number: L1BatchNumber,
     job_id: u32,
@@ -270,7 +291,7 @@ impl JobProcessor for Prover {
     type JobArtifacts = ProverArtifacts;
     const SERVICE_NAME: &'static str = "FriProver";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
         let mut storage = self.prover_connection_pool.access_storage().await;
         let prover_job = match self.circuit_ids_for_round_to_be_proven.is_empty() {
             false => {
@@ -284,7 +305,11 @@ impl JobProcessor for Prover {
                 // Generalized prover: proving all circuits.
                 storage.fri_prover_jobs_dal().get_next_job().await
             }
-        }?;
+        };
+        let prover_job = match prover_job {
+            Some(prover_job) => prover_job,
+            None => return Ok(None),
+        };
         vlog::info!("Started processing prover job: {:?}", prover_job);
 
         let circuit_key = FriCircuitKey {
@@ -295,11 +320,10 @@ impl JobProcessor for Prover {
             depth: prover_job.depth,
         };
         let started_at = Instant::now();
-        let input = self
-            .blob_store
-            .get(circuit_key)
-            .await
-            .unwrap_or_else(|err| panic!("{err:?}"));
+        let input =
+            self.blob_store.get(circuit_key).await.with_context(|| {
+                format!("failed loading circuit for prover job {}", prover_job.id)
+            })?;
         metrics::histogram!(
                 "prover_fri.prover.blob_fetch_time",
                 started_at.elapsed(),
@@ -311,7 +335,7 @@ impl JobProcessor for Prover {
             round: prover_job.aggregation_round,
         };
 
-        Some((
+        Ok(Some((
             prover_job.id,
             ProverJob::new(
                 prover_job.block_number,
@@ -319,15 +343,44 @@ impl JobProcessor for Prover {
                 input,
                 setup_data_key,
             ),
-        ))
+        )))
+    }
+
+    async fn wait_for_new_jobs(&self, timeout: Duration) {
+        let listener = match &self.new_jobs_listener {
+            Some(listener) => listener,
+            None => return tokio::time::sleep(timeout).await,
+        };
+        let deadline = Instant::now() + timeout;
+        let mut listener = listener.lock().await;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match listener.wait(remaining).await {
+                Ok(Some(notification)) if self.is_proven_by_this_prover(&notification) => {
+                    vlog::debug!("Notified about new prover job: {:?}", notification);
+                    return;
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => return,
+                Err(err) => {
+                    vlog::warn!(
+                        "Failed waiting for notifications about new prover jobs, falling back \
+                         to polling: {}",
+                        err
+                    );
+                    tokio::time::sleep(remaining).await;
+                    return;
+                }
+            }
+        }
     }
 
-    async fn save_failure(&self, job_id: Self::JobId, _started_at: Instant, error: String) {
+    async fn save_failure(&self, job_id: Self::JobId, _started_at: Instant, error: JobError) {
         self.prover_connection_pool
             .access_storage()
             .await
             .fri_prover_jobs_dal()
-            .save_proof_error(job_id, error)
+            .save_proof_error(job_id, error.to_string())
             .await;
     }
 
@@ -335,10 +388,10 @@ impl JobProcessor for Prover {
         &self,
         job: Self::Job,
         _started_at: Instant,
-    ) -> JoinHandle<Self::JobArtifacts> {
+    ) -> JoinHandle<anyhow::Result<Self::JobArtifacts>> {
         let config = Arc::clone(&self.config);
         let setup_data = self.get_setup_data(job.setup_data_key.clone());
-        tokio::task::spawn_blocking(move || Self::prove(job, config, setup_data))
+        tokio::task::spawn_blocking(move || Ok(Self::prove(job, config, setup_data)))
     }
 
     async fn save_result(
@@ -346,7 +399,7 @@ impl JobProcessor for Prover {
         job_id: Self::JobId,
         started_at: Instant,
         artifacts: Self::JobArtifacts,
-    ) {
+    ) -> anyhow::Result<()> {
         vlog::info!(
             "Successfully proven job: {}, took: {:?}",
             job_id,
@@ -363,7 +416,7 @@ impl JobProcessor for Prover {
                     self.public_blob_store
                         .put(artifacts.block_number.0, &proof)
                         .await
-                        .unwrap();
+                        .context("failed saving scheduler proof to public bucket")?;
                     recursive_circuit.numeric_circuit_type()
                 }
                 _ => recursive_circuit.numeric_circuit_type(),
@@ -371,7 +424,11 @@ impl JobProcessor for Prover {
         };
 
         let blob_save_started_at = Instant::now();
-        let blob_url = self.blob_store.put(job_id, &proof).await.unwrap();
+        let blob_url = self
+            .blob_store
+            .put(job_id, &proof)
+            .await
+            .with_context(|| format!("failed saving proof for prover job {job_id}"))?;
         metrics::histogram!(
                 "prover_fri.prover.blob_save_time",
                 blob_save_started_at.elapsed(),
@@ -395,5 +452,6 @@ impl JobProcessor for Prover {
                 .await;
         }
         transaction.commit().await;
+        Ok(())
     }
 }
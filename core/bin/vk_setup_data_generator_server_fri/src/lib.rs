@@ -2,6 +2,7 @@
 use std::fs::File;
 use std::io::Read;
 
+use anyhow::Context as _;
 use circuit_definitions::boojum::cs::implementations::hints::{
     DenseVariablesCopyHint, DenseWitnessCopyHint,
 };
@@ -87,8 +88,21 @@ pub fn get_base_vk_path() -> String {
     )
 }
 
-fn get_file_path(key: ProverServiceDataKey, service_data_type: ProverServiceDataType) -> String {
-    let name = match key.round {
+/// Returns the directory with verification keys for the specified protocol version within
+/// `base_vk_path`. Keys for the protocol version of the setup data (`None`) are stored
+/// in the base directory, and keys for other versions in its `v<version>` subdirectories.
+pub fn get_vk_path_for_protocol_version(
+    base_vk_path: &str,
+    protocol_version: Option<u16>,
+) -> String {
+    match protocol_version {
+        Some(version) => format!("{}/v{}", base_vk_path, version),
+        None => base_vk_path.to_owned(),
+    }
+}
+
+fn get_file_name(key: &ProverServiceDataKey) -> String {
+    match key.round {
         AggregationRound::BasicCircuits => {
             format!("basic_{}", key.circuit_id)
         }
@@ -97,16 +111,21 @@ fn get_file_path(key: ProverServiceDataKey, service_data_type: ProverServiceData
         }
         AggregationRound::NodeAggregation => "node".to_string(),
         AggregationRound::Scheduler => "scheduler".to_string(),
-    };
+    }
+}
+
+fn get_vk_file_path(vk_path: &str, key: &ProverServiceDataKey) -> String {
+    format!("{}/verification_{}_key.json", vk_path, get_file_name(key))
+}
+
+fn get_file_path(key: ProverServiceDataKey, service_data_type: ProverServiceDataType) -> String {
     match service_data_type {
-        ProverServiceDataType::VerificationKey => {
-            format!("{}/verification_{}_key.json", get_base_vk_path(), name)
-        }
+        ProverServiceDataType::VerificationKey => get_vk_file_path(&get_base_vk_path(), &key),
         ProverServiceDataType::SetupData => {
             format!(
                 "{}/setup_{}_data.bin",
                 FriProverConfig::from_env().setup_data_path,
-                name
+                get_file_name(&key)
             )
         }
     }
@@ -128,19 +147,46 @@ pub fn get_base_layer_vk_for_circuit_type(circuit_type: u8) -> ZkSyncBaseLayerVe
     })
 }
 
+/// Fallible version of [`get_base_layer_vk_for_circuit_type()`] loading the key from
+/// the specified directory (see [`get_vk_path_for_protocol_version()`]).
+pub fn try_get_base_layer_vk_from_dir(
+    vk_path: &str,
+    circuit_type: u8,
+) -> anyhow::Result<ZkSyncBaseLayerVerificationKey> {
+    let key = ProverServiceDataKey::new(circuit_type, AggregationRound::BasicCircuits);
+    read_vk(&get_vk_file_path(vk_path, &key))
+}
+
 pub fn get_recursive_layer_vk_for_circuit_type(
     circuit_type: u8,
 ) -> ZkSyncRecursionLayerVerificationKey {
+    try_get_recursive_layer_vk_for_circuit_type(circuit_type).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Fallible version of [`get_recursive_layer_vk_for_circuit_type()`].
+pub fn try_get_recursive_layer_vk_for_circuit_type(
+    circuit_type: u8,
+) -> anyhow::Result<ZkSyncRecursionLayerVerificationKey> {
+    try_get_recursive_layer_vk_from_dir(&get_base_vk_path(), circuit_type)
+}
+
+/// Same as [`try_get_recursive_layer_vk_for_circuit_type()`], but loads the key from
+/// the specified directory (see [`get_vk_path_for_protocol_version()`]).
+pub fn try_get_recursive_layer_vk_from_dir(
+    vk_path: &str,
+    circuit_type: u8,
+) -> anyhow::Result<ZkSyncRecursionLayerVerificationKey> {
     let round = get_round_for_recursive_circuit_type(circuit_type);
-    let filepath = get_file_path(
-        ProverServiceDataKey::new(circuit_type, round),
-        ProverServiceDataType::VerificationKey,
-    );
+    let key = ProverServiceDataKey::new(circuit_type, round);
+    read_vk(&get_vk_file_path(vk_path, &key))
+}
+
+fn read_vk<T: DeserializeOwned>(filepath: &str) -> anyhow::Result<T> {
     vlog::info!("Fetching verification key from path: {}", filepath);
-    let text = std::fs::read_to_string(&filepath)
-        .unwrap_or_else(|_| panic!("Failed reading verification key from path: {}", filepath));
-    serde_json::from_str::<ZkSyncRecursionLayerVerificationKey>(&text).unwrap_or_else(|_| {
-        panic!(
+    let text = std::fs::read_to_string(filepath)
+        .with_context(|| format!("Failed reading verification key from path: {}", filepath))?;
+    serde_json::from_str::<T>(&text).with_context(|| {
+        format!(
             "Failed deserializing verification key from path: {}",
             filepath
         )
@@ -1,5 +1,7 @@
 use crate::{
-    get_base_layer_vk_for_circuit_type, get_base_path, get_recursive_layer_vk_for_circuit_type,
+    get_base_layer_vk_for_circuit_type, get_base_path, get_base_vk_path,
+    get_recursive_layer_vk_for_circuit_type, try_get_base_layer_vk_from_dir,
+    try_get_recursive_layer_vk_from_dir,
 };
 use circuit_definitions::aux_definitions::witness_oracle::VmWitnessOracle;
 use circuit_definitions::boojum::field::goldilocks::{GoldilocksExt2, GoldilocksField};
@@ -194,6 +196,14 @@ fn get_recursive_layer_proofs() -> Vec<ZkSyncRecursionProof> {
 }
 
 pub fn get_leaf_vk_params() -> Vec<(u8, RecursionLeafParametersWitness<GoldilocksField>)> {
+    try_get_leaf_vk_params_from_dir(&get_base_vk_path()).unwrap_or_else(|err| panic!("{err:#}"))
+}
+
+/// Fallible version of [`get_leaf_vk_params()`] loading verification keys from the specified
+/// directory (see [`crate::get_vk_path_for_protocol_version()`]).
+pub fn try_get_leaf_vk_params_from_dir(
+    vk_path: &str,
+) -> anyhow::Result<Vec<(u8, RecursionLeafParametersWitness<GoldilocksField>)>> {
     let mut leaf_vk_commits = vec![];
 
     for circuit_type in
@@ -202,12 +212,12 @@ pub fn get_leaf_vk_params() -> Vec<(u8, RecursionLeafParametersWitness<Goldilock
         let recursive_circuit_type = base_circuit_type_into_recursive_leaf_circuit_type(
             BaseLayerCircuitType::from_numeric_value(circuit_type),
         );
-        let base_vk = get_base_layer_vk_for_circuit_type(circuit_type);
-        let leaf_vk = get_recursive_layer_vk_for_circuit_type(recursive_circuit_type as u8);
+        let base_vk = try_get_base_layer_vk_from_dir(vk_path, circuit_type)?;
+        let leaf_vk = try_get_recursive_layer_vk_from_dir(vk_path, recursive_circuit_type as u8)?;
         let params = compute_leaf_params(circuit_type, base_vk, leaf_vk);
         leaf_vk_commits.push((circuit_type, params));
     }
-    leaf_vk_commits
+    Ok(leaf_vk_commits)
 }
 
 fn get_circuits(
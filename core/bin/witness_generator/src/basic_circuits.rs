@@ -3,9 +3,10 @@ use std::sync::Arc;
 use std::{
     collections::{hash_map::DefaultHasher, HashMap, HashSet},
     hash::Hasher,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
+use anyhow::Context as _;
 use async_trait::async_trait;
 use circuit_definitions::ZkSyncDefaultRoundFunction;
 use rand::Rng;
@@ -26,9 +27,11 @@ use zksync_config::configs::FriWitnessGeneratorConfig;
 use zksync_dal::fri_witness_generator_dal::FriWitnessJobStatus;
 use zksync_dal::ConnectionPool;
 use zksync_object_store::{
-    Bucket, ClosedFormInputKey, ObjectStore, ObjectStoreFactory, StoredObject,
+    Bucket, ClosedFormInputKey, ObjectStore, ObjectStoreError, StoredObject,
+};
+use zksync_queued_job_processor::{
+    JobError, JobMetadata, JobProcessor, PendingJobsCount, SharedJobProcessorStatus,
 };
-use zksync_queued_job_processor::JobProcessor;
 use zksync_state::{PostgresStorage, StorageView};
 use zksync_types::proofs::AggregationRound;
 use zksync_types::{
@@ -37,10 +40,14 @@ use zksync_types::{
 };
 use zksync_utils::{bytes_to_chunks, h256_to_u256, u256_to_h256};
 
+use crate::peak_memory::track_peak_memory;
 use crate::precalculated_merkle_paths_provider::PrecalculatedMerklePathsProvider;
+use crate::sealed_latency::report_sealed_to_saved_latency;
 use crate::utils::{
-    expand_bootloader_contents, get_recursive_layer_circuit_id_for_base_layer,
-    save_base_prover_input_artifacts, AuxOutputWitnessWrapper, ClosedFormInputWrapper,
+    classify_store_unavailable, count_pending_witness_jobs, expand_bootloader_contents,
+    get_recursive_layer_circuit_id_for_base_layer, get_verified, put_artifact,
+    put_artifact_section, save_base_prover_input_artifacts, ArtifactSection,
+    AuxOutputWitnessWrapper, BasicArtifactsManifest, BasicCircuitsSummary, ClosedFormInputWrapper,
     SchedulerPartialInputWrapper,
 };
 
@@ -62,6 +69,7 @@ struct BlobUrls {
     circuit_ids_and_urls: Vec<(u8, String)>,
     closed_form_inputs_and_urls: Vec<(u8, String, usize)>,
     scheduler_witness_url: String,
+    scheduler_witness_size: u64,
 }
 
 #[derive(Clone)]
@@ -77,22 +85,28 @@ pub struct BasicWitnessGenerator {
     public_blob_store: Box<dyn ObjectStore>,
     connection_pool: ConnectionPool,
     prover_connection_pool: ConnectionPool,
+    status: SharedJobProcessorStatus,
+    /// Fan-in of node aggregation circuits recorded for processed L1 batches.
+    node_aggregation_fan_in: usize,
 }
 
 impl BasicWitnessGenerator {
-    pub async fn new(
+    pub fn new(
         config: FriWitnessGeneratorConfig,
-        store_factory: &ObjectStoreFactory,
+        object_store: Arc<dyn ObjectStore>,
         public_blob_store: Box<dyn ObjectStore>,
         connection_pool: ConnectionPool,
         prover_connection_pool: ConnectionPool,
+        node_aggregation_fan_in: usize,
     ) -> Self {
         Self {
             config: Arc::new(config),
-            object_store: store_factory.create_store().await.into(),
+            object_store,
             public_blob_store,
             connection_pool,
             prover_connection_pool,
+            status: SharedJobProcessorStatus::default(),
+            node_aggregation_fan_in,
         }
     }
 
@@ -103,7 +117,7 @@ impl BasicWitnessGenerator {
         basic_job: BasicWitnessGeneratorJob,
         started_at: Instant,
         config: Arc<FriWitnessGeneratorConfig>,
-    ) -> Option<BasicCircuitArtifacts> {
+    ) -> anyhow::Result<Option<BasicCircuitArtifacts>> {
         let BasicWitnessGeneratorJob { block_number, job } = basic_job;
         let shall_force_process_block = config
             .force_process_block
@@ -127,7 +141,7 @@ impl BasicWitnessGenerator {
                     .fri_witness_generator_dal()
                     .mark_witness_job(FriWitnessJobStatus::Skipped, block_number)
                     .await;
-                return None;
+                return Ok(None);
             }
         }
 
@@ -138,17 +152,17 @@ impl BasicWitnessGenerator {
             block_number.0
         );
 
-        Some(
-            process_basic_circuits_job(
-                &*object_store,
-                config,
-                connection_pool,
-                started_at,
-                block_number,
-                job,
-            )
-            .await,
+        let artifacts = process_basic_circuits_job(
+            &*object_store,
+            config,
+            connection_pool,
+            started_at,
+            block_number,
+            job,
         )
+        .await
+        .map_err(classify_store_unavailable)?;
+        Ok(Some(artifacts))
     }
 }
 
@@ -161,39 +175,79 @@ impl JobProcessor for BasicWitnessGenerator {
 
     const SERVICE_NAME: &'static str = "fri_basic_circuit_witness_generator";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
+        let next_job = self.get_next_job_with_metadata().await?;
+        Ok(next_job.map(|(block_number, job, _)| (block_number, job)))
+    }
+
+    async fn get_next_job_with_metadata(
+        &self,
+    ) -> anyhow::Result<Option<(Self::JobId, Self::Job, JobMetadata)>> {
         let mut prover_connection = self.prover_connection_pool.access_storage().await;
-        let last_l1_batch_to_process = self.config.last_l1_batch_to_process();
+        let batch_range = self.config.batch_range();
+        let last_l1_batch_to_process = L1BatchNumber(self.config.last_l1_batch_to_process());
+        let batch_range = *batch_range.start()..=(*batch_range.end()).min(last_l1_batch_to_process);
 
         match prover_connection
             .fri_witness_generator_dal()
-            .get_next_basic_circuit_witness_job(last_l1_batch_to_process)
+            .get_next_basic_circuit_witness_job(&batch_range)
             .await
         {
-            Some(block_number) => {
+            Some((block_number, attempts)) => {
                 vlog::info!(
                     "Processing FRI basic witness-gen for block {}",
                     block_number
                 );
                 let started_at = Instant::now();
-                let job = get_artifacts(block_number, &*self.object_store).await;
+                let job = get_artifacts(block_number, &*self.object_store).await?;
                 metrics::histogram!(
                     "prover_fri.witness_generation.blob_fetch_time",
                     started_at.elapsed(),
                     "aggregation_round" => format!("{:?}", AggregationRound::BasicCircuits),
                 );
-                Some((block_number, job))
+                Ok(Some((block_number, job, JobMetadata::new(Some(attempts)))))
             }
-            None => None,
+            None => Ok(None),
         }
     }
 
-    async fn save_failure(&self, job_id: L1BatchNumber, _started_at: Instant, error: String) -> () {
+    fn max_jobs_to_process(&self) -> Option<u64> {
+        self.config.max_jobs_to_process
+    }
+
+    fn max_pickups_per_minute(&self) -> Option<u32> {
+        self.config.max_job_pickups_per_minute
+    }
+
+    fn drain_after_empty_polls(&self) -> Option<u32> {
+        self.config.drain_after_empty_polls()
+    }
+
+    fn status(&self) -> Option<&SharedJobProcessorStatus> {
+        Some(&self.status)
+    }
+
+    async fn pending_jobs_count(&self) -> Option<PendingJobsCount> {
+        Some(
+            count_pending_witness_jobs(
+                &self.prover_connection_pool,
+                AggregationRound::BasicCircuits,
+            )
+            .await,
+        )
+    }
+
+    async fn save_failure(
+        &self,
+        job_id: L1BatchNumber,
+        _started_at: Instant,
+        error: JobError,
+    ) -> () {
         self.prover_connection_pool
             .access_storage()
             .await
             .fri_witness_generator_dal()
-            .mark_witness_job_failed(&error, job_id)
+            .mark_witness_job_failed(&error.to_json(), job_id, error.retryable)
             .await;
     }
 
@@ -202,7 +256,7 @@ impl JobProcessor for BasicWitnessGenerator {
         &self,
         job: BasicWitnessGeneratorJob,
         started_at: Instant,
-    ) -> tokio::task::JoinHandle<Option<BasicCircuitArtifacts>> {
+    ) -> tokio::task::JoinHandle<anyhow::Result<Option<BasicCircuitArtifacts>>> {
         let config = Arc::clone(&self.config);
         let object_store = Arc::clone(&self.object_store);
         tokio::spawn(Self::process_job_impl(
@@ -220,7 +274,7 @@ impl JobProcessor for BasicWitnessGenerator {
         job_id: L1BatchNumber,
         started_at: Instant,
         optional_artifacts: Option<BasicCircuitArtifacts>,
-    ) {
+    ) -> anyhow::Result<()> {
         match optional_artifacts {
             None => (),
             Some(artifacts) => {
@@ -231,15 +285,35 @@ impl JobProcessor for BasicWitnessGenerator {
                     &*self.object_store,
                     &*self.public_blob_store,
                 )
-                .await;
+                .await
+                .context("failed saving basic circuit artifacts")
+                .map_err(classify_store_unavailable)?;
                 metrics::histogram!(
                     "prover_fri.witness_generation.blob_save_time",
                     blob_started_at.elapsed(),
                     "aggregation_round" => format!("{:?}", AggregationRound::BasicCircuits),
                 );
-                update_database(&self.prover_connection_pool, started_at, job_id, blob_urls).await;
+                update_database(
+                    &self.prover_connection_pool,
+                    started_at,
+                    job_id,
+                    blob_urls,
+                    self.config.scheduler_job_deadline(),
+                    self.config.protocol_version,
+                    self.node_aggregation_fan_in,
+                )
+                .await;
+                report_sealed_to_saved_latency(
+                    &self.connection_pool,
+                    &self.prover_connection_pool,
+                    AggregationRound::BasicCircuits,
+                    job_id,
+                    job_id.0,
+                )
+                .await;
             }
         }
+        Ok(())
     }
 }
 
@@ -250,7 +324,7 @@ async fn process_basic_circuits_job(
     started_at: Instant,
     block_number: L1BatchNumber,
     job: PrepareBasicCircuitsJob,
-) -> BasicCircuitArtifacts {
+) -> anyhow::Result<BasicCircuitArtifacts> {
     let witness_gen_input =
         build_basic_circuits_witness_generator_input(&connection_pool, job, block_number).await;
     let (
@@ -259,7 +333,7 @@ async fn process_basic_circuits_job(
         per_circuit_closed_form_inputs,
         scheduler_witness,
         aux_output_witness,
-    ) = generate_witness(object_store, config, connection_pool, witness_gen_input).await;
+    ) = generate_witness(object_store, config, connection_pool, witness_gen_input).await?;
     metrics::histogram!(
         "prover_fri.witness_generation.witness_generation_time",
         started_at.elapsed(),
@@ -271,13 +345,13 @@ async fn process_basic_circuits_job(
         started_at.elapsed()
     );
 
-    BasicCircuitArtifacts {
+    Ok(BasicCircuitArtifacts {
         basic_circuits,
         basic_circuits_inputs,
         per_circuit_closed_form_inputs,
         scheduler_witness,
         aux_output_witness,
-    }
+    })
 }
 
 async fn update_database(
@@ -285,6 +359,9 @@ async fn update_database(
     started_at: Instant,
     block_number: L1BatchNumber,
     blob_urls: BlobUrls,
+    scheduler_job_deadline: Option<Duration>,
+    protocol_version: Option<u16>,
+    node_aggregation_fan_in: usize,
 ) {
     let mut prover_connection = prover_connection_pool.access_storage().await;
 
@@ -304,8 +381,23 @@ async fn update_database(
             &blob_urls.closed_form_inputs_and_urls,
             &blob_urls.scheduler_witness_url,
             get_recursive_layer_circuit_id_for_base_layer,
+            scheduler_job_deadline,
         )
         .await;
+    prover_connection
+        .fri_witness_generator_dal()
+        .set_node_aggregation_fan_in(block_number, node_aggregation_fan_in)
+        .await;
+    prover_connection
+        .fri_witness_generator_dal()
+        .set_scheduler_partial_input_blob_size(block_number, blob_urls.scheduler_witness_size)
+        .await;
+    if let Some(protocol_version) = protocol_version {
+        prover_connection
+            .fri_witness_generator_dal()
+            .set_scheduler_job_protocol_version(block_number, protocol_version)
+            .await;
+    }
     prover_connection
         .fri_witness_generator_dal()
         .mark_witness_job_as_successful(block_number, started_at.elapsed())
@@ -315,9 +407,11 @@ async fn update_database(
 async fn get_artifacts(
     block_number: L1BatchNumber,
     object_store: &dyn ObjectStore,
-) -> BasicWitnessGeneratorJob {
-    let job = object_store.get(block_number).await.unwrap();
-    BasicWitnessGeneratorJob { block_number, job }
+) -> anyhow::Result<BasicWitnessGeneratorJob> {
+    let job = get_verified(object_store, block_number)
+        .await
+        .with_context(|| format!("basic circuits input missing for l1 batch {block_number}"))?;
+    Ok(BasicWitnessGeneratorJob { block_number, job })
 }
 
 async fn save_artifacts(
@@ -325,35 +419,48 @@ async fn save_artifacts(
     artifacts: BasicCircuitArtifacts,
     object_store: &dyn ObjectStore,
     public_object_store: &dyn ObjectStore,
-) -> BlobUrls {
-    let circuit_ids_and_urls = save_base_prover_input_artifacts(
-        block_number,
-        artifacts.basic_circuits,
-        object_store,
-        AggregationRound::BasicCircuits,
-    )
-    .await;
+) -> Result<BlobUrls, ObjectStoreError> {
+    let circuit_ids_and_urls =
+        save_base_prover_input_artifacts(block_number, artifacts.basic_circuits, object_store)
+            .await?;
+    let mut sections = vec![];
     let closed_form_inputs_and_urls = save_leaf_aggregation_artifacts(
         block_number,
         artifacts.basic_circuits_inputs,
         artifacts.per_circuit_closed_form_inputs,
         object_store,
+        &mut sections,
     )
-    .await;
-    let scheduler_witness_url = save_scheduler_artifacts(
+    .await?;
+    let (scheduler_witness_url, scheduler_witness_size) = save_scheduler_artifacts(
         block_number,
         artifacts.scheduler_witness,
         artifacts.aux_output_witness,
         object_store,
         public_object_store,
+        &mut sections,
     )
-    .await;
+    .await?;
+    let summary = BasicCircuitsSummary {
+        l1_batch_number: block_number,
+        circuits: circuit_ids_and_urls.clone(),
+        closed_form_inputs: closed_form_inputs_and_urls.clone(),
+        scheduler_witness_key: scheduler_witness_url.clone(),
+    };
+    put_artifact(object_store, block_number, &summary).await?;
+    // The manifest is saved last, so that its presence means that all sections are saved.
+    let manifest = BasicArtifactsManifest {
+        l1_batch_number: block_number,
+        sections,
+    };
+    put_artifact(object_store, block_number, &manifest).await?;
 
-    BlobUrls {
+    Ok(BlobUrls {
         circuit_ids_and_urls,
         closed_form_inputs_and_urls,
         scheduler_witness_url,
-    }
+        scheduler_witness_size,
+    })
 }
 
 async fn save_scheduler_artifacts(
@@ -366,18 +473,23 @@ async fn save_scheduler_artifacts(
     aux_output_witness: BlockAuxilaryOutputWitness<GoldilocksField>,
     object_store: &dyn ObjectStore,
     public_object_store: &dyn ObjectStore,
-) -> String {
+    sections: &mut Vec<ArtifactSection>,
+) -> Result<(String, u64), ObjectStoreError> {
     let aux_output_witness_wrapper = AuxOutputWitnessWrapper(aux_output_witness);
-    public_object_store
-        .put(block_number, &aux_output_witness_wrapper)
-        .await
-        .unwrap();
-    object_store
-        .put(block_number, &aux_output_witness_wrapper)
-        .await
-        .unwrap();
+    put_artifact(
+        public_object_store,
+        block_number,
+        &aux_output_witness_wrapper,
+    )
+    .await?;
+    let (section, _) =
+        put_artifact_section(object_store, block_number, &aux_output_witness_wrapper).await?;
+    sections.push(section);
     let wrapper = SchedulerPartialInputWrapper(scheduler_partial_input);
-    object_store.put(block_number, &wrapper).await.unwrap()
+    let (section, size) = put_artifact_section(object_store, block_number, &wrapper).await?;
+    let blob_url = section.key.clone();
+    sections.push(section);
+    Ok((blob_url, size))
 }
 
 async fn save_leaf_aggregation_artifacts(
@@ -385,7 +497,8 @@ async fn save_leaf_aggregation_artifacts(
     basic_circuits_inputs: BlockBasicCircuitsPublicInputs<GoldilocksField>,
     per_circuit_closed_form_inputs: BlockBasicCircuitsPublicCompactFormsWitnesses<GoldilocksField>,
     object_store: &dyn ObjectStore,
-) -> Vec<(u8, String, usize)> {
+    sections: &mut Vec<ArtifactSection>,
+) -> Result<Vec<(u8, String, usize)>, ObjectStoreError> {
     let round_function = ZkSyncDefaultRoundFunction::default();
     let queues = basic_circuits_inputs
         .into_recursion_queues(per_circuit_closed_form_inputs, &round_function);
@@ -398,10 +511,11 @@ async fn save_leaf_aggregation_artifacts(
         };
         let basic_circuit_count = inputs.len();
         let wrapper = ClosedFormInputWrapper(inputs, recursion_queue_simulator);
-        let blob_url = object_store.put(key, &wrapper).await.unwrap();
-        circuit_id_urls_with_count.push((circuit_id, blob_url, basic_circuit_count))
+        let (section, _) = put_artifact_section(object_store, key, &wrapper).await?;
+        circuit_id_urls_with_count.push((circuit_id, section.key.clone(), basic_circuit_count));
+        sections.push(section);
     }
-    circuit_id_urls_with_count
+    Ok(circuit_id_urls_with_count)
 }
 
 // If making changes to this method, consider moving this logic to the DAL layer and make
@@ -443,7 +557,7 @@ async fn generate_witness(
     config: Arc<FriWitnessGeneratorConfig>,
     connection_pool: ConnectionPool,
     input: BasicCircuitWitnessGeneratorInput,
-) -> (
+) -> anyhow::Result<(
     BlockBasicCircuits<GoldilocksField, ZkSyncDefaultRoundFunction>,
     BlockBasicCircuitsPublicInputs<GoldilocksField>,
     BlockBasicCircuitsPublicCompactFormsWitnesses<GoldilocksField>,
@@ -453,7 +567,7 @@ async fn generate_witness(
         GoldilocksExt2,
     >,
     BlockAuxilaryOutputWitness<GoldilocksField>,
-) {
+)> {
     let mut connection = connection_pool.access_storage().await;
     let header = connection
         .blocks_dal()
@@ -537,34 +651,44 @@ async fn generate_witness(
             geometry_config,
             tree.clone(),
         )
-        .await;
+        .await
+        .context("failed saving run_with_fixed_params arguments")?;
     }
 
     // The following part is CPU-heavy, so we move it to a separate thread.
     let rt_handle = tokio::runtime::Handle::current();
-    tokio::task::spawn_blocking(move || {
-        let connection = rt_handle.block_on(connection_pool.access_storage());
-        let storage = PostgresStorage::new(rt_handle, connection, last_miniblock_number, true);
-        let storage_view = &mut StorageView::new(storage);
-        let storage_oracle: StorageOracle<HistoryDisabled> =
-            StorageOracle::new(storage_view.as_ptr());
-        zkevm_test_harness::external_calls::run_with_fixed_params(
-            Address::zero(),
-            BOOTLOADER_ADDRESS,
-            bootloader_code,
-            bootloader_contents,
-            false,
-            account_code_hash,
-            used_bytecodes,
-            Vec::default(),
-            MAX_CYCLES_FOR_TX as usize,
-            geometry_config,
-            storage_oracle,
-            &mut tree,
-        )
+    let l1_batch_number = input.block_number;
+    // Blocking tasks don't inherit the current span, so it's entered explicitly.
+    let span = tracing::Span::current();
+    let witness = tokio::task::spawn_blocking(move || {
+        span.in_scope(|| {
+            track_peak_memory(AggregationRound::BasicCircuits, l1_batch_number, || {
+                let connection = rt_handle.block_on(connection_pool.access_storage());
+                let storage =
+                    PostgresStorage::new(rt_handle, connection, last_miniblock_number, true);
+                let storage_view = &mut StorageView::new(storage);
+                let storage_oracle: StorageOracle<HistoryDisabled> =
+                    StorageOracle::new(storage_view.as_ptr());
+                zkevm_test_harness::external_calls::run_with_fixed_params(
+                    Address::zero(),
+                    BOOTLOADER_ADDRESS,
+                    bootloader_code,
+                    bootloader_contents,
+                    false,
+                    account_code_hash,
+                    used_bytecodes,
+                    Vec::default(),
+                    MAX_CYCLES_FOR_TX as usize,
+                    geometry_config,
+                    storage_oracle,
+                    &mut tree,
+                )
+            })
+        })
     })
     .await
-    .unwrap()
+    .unwrap();
+    Ok(witness)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -583,7 +707,7 @@ async fn save_run_with_fixed_params_args_to_gcs(
     cycle_limit: usize,
     geometry: GeometryConfig,
     tree: PrecalculatedMerklePathsProvider,
-) {
+) -> Result<(), ObjectStoreError> {
     let run_with_fixed_params_input = RunWithFixedParamsInput {
         l1_batch_number,
         last_miniblock_number,
@@ -601,8 +725,8 @@ async fn save_run_with_fixed_params_args_to_gcs(
     };
     object_store
         .put(L1BatchNumber(l1_batch_number), &run_with_fixed_params_input)
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
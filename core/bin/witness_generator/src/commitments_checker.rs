@@ -0,0 +1,157 @@
+//! Checker comparing commitments exposed by scheduler circuits with commitments computed
+//! by the server.
+
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use zksync_dal::ConnectionPool;
+use zksync_types::proofs::SchedulerCommitments;
+use zksync_types::L1BatchNumber;
+
+/// Max number of L1 batches checked at once.
+const CHECKED_BATCHES_LIMIT: usize = 100;
+
+/// Periodically compares commitments recorded for scheduler jobs with the commitments computed
+/// by the server for the same L1 batches, so that mismatches are noticed before the batches
+/// are submitted to L1. Mismatches are logged and counted in the
+/// `prover_fri.witness_generation.scheduler_commitment_mismatches` metric.
+///
+/// Checked batches are only tracked in memory, so batches are checked anew after a restart.
+#[derive(Debug)]
+pub struct SchedulerCommitmentsChecker {
+    connection_pool: ConnectionPool,
+    prover_connection_pool: ConnectionPool,
+    check_interval: Duration,
+    next_l1_batch_number: L1BatchNumber,
+}
+
+impl SchedulerCommitmentsChecker {
+    pub fn new(
+        connection_pool: ConnectionPool,
+        prover_connection_pool: ConnectionPool,
+        check_interval: Duration,
+    ) -> Self {
+        Self {
+            connection_pool,
+            prover_connection_pool,
+            check_interval,
+            next_l1_batch_number: L1BatchNumber(0),
+        }
+    }
+
+    pub async fn run(mut self, stop_receiver: watch::Receiver<bool>) {
+        while !*stop_receiver.borrow() {
+            let checked_batches = self.check_next_batches().await;
+            if checked_batches < CHECKED_BATCHES_LIMIT {
+                tokio::time::sleep(self.check_interval).await;
+            }
+        }
+    }
+
+    /// Checks commitments for the next L1 batches with recorded scheduler commitments and returns
+    /// the number of checked batches. Checking stops at the first batch without commitments
+    /// computed by the server; it's checked on the next call.
+    async fn check_next_batches(&mut self) -> usize {
+        let scheduler_commitments = self
+            .prover_connection_pool
+            .access_storage()
+            .await
+            .fri_witness_generator_dal()
+            .get_scheduler_commitments(self.next_l1_batch_number, CHECKED_BATCHES_LIMIT)
+            .await;
+        let (first_l1_batch_number, last_l1_batch_number) =
+            match (scheduler_commitments.first(), scheduler_commitments.last()) {
+                (Some((first, _)), Some((last, _))) => (*first, *last),
+                _ => return 0,
+            };
+        let expected_commitments = self
+            .connection_pool
+            .access_storage()
+            .await
+            .blocks_dal()
+            .get_expected_scheduler_commitments(first_l1_batch_number, last_l1_batch_number)
+            .await;
+
+        let mut checked_batches = 0;
+        for (l1_batch_number, commitments) in &scheduler_commitments {
+            let expected = match expected_commitments.get(l1_batch_number) {
+                Some(expected) => expected,
+                None => break,
+            };
+            for commitment in mismatched_commitments(commitments, expected) {
+                vlog::error!(
+                    "Commitment `{}` exposed by scheduler circuit for l1 batch {} doesn't match \
+                     the one computed by the server; scheduler: {:?}, server: {:?}",
+                    commitment,
+                    l1_batch_number,
+                    commitments,
+                    expected
+                );
+                metrics::counter!(
+                    "prover_fri.witness_generation.scheduler_commitment_mismatches",
+                    1,
+                    "commitment" => commitment
+                );
+            }
+            self.next_l1_batch_number = *l1_batch_number + 1;
+            checked_batches += 1;
+        }
+        if checked_batches > 0 {
+            metrics::gauge!(
+                "prover_fri.witness_generation.scheduler_commitments_checked_l1_batch",
+                (self.next_l1_batch_number.0 - 1) as f64
+            );
+        }
+        checked_batches
+    }
+}
+
+/// Returns names of scheduler circuit commitments that differ from the expected ones.
+fn mismatched_commitments(
+    commitments: &SchedulerCommitments,
+    expected: &SchedulerCommitments,
+) -> Vec<&'static str> {
+    let mut mismatches = vec![];
+    if commitments.meta_parameters_hash != expected.meta_parameters_hash {
+        mismatches.push("meta_parameters_hash");
+    }
+    if commitments.pass_through_data_hash != expected.pass_through_data_hash {
+        mismatches.push("pass_through_data_hash");
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use zksync_types::H256;
+
+    use super::*;
+
+    #[test]
+    fn mismatched_commitments_are_reported() {
+        let commitments = SchedulerCommitments {
+            meta_parameters_hash: H256::repeat_byte(1),
+            pass_through_data_hash: H256::repeat_byte(2),
+        };
+        assert!(mismatched_commitments(&commitments, &commitments).is_empty());
+
+        let expected = SchedulerCommitments {
+            pass_through_data_hash: H256::repeat_byte(3),
+            ..commitments
+        };
+        assert_eq!(
+            mismatched_commitments(&commitments, &expected),
+            ["pass_through_data_hash"]
+        );
+
+        let expected = SchedulerCommitments {
+            meta_parameters_hash: H256::zero(),
+            pass_through_data_hash: H256::zero(),
+        };
+        assert_eq!(
+            mismatched_commitments(&commitments, &expected),
+            ["meta_parameters_hash", "pass_through_data_hash"]
+        );
+    }
+}
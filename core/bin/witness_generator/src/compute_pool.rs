@@ -0,0 +1,113 @@
+//! Dedicated thread pool for CPU-heavy witness generation.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use zksync_config::configs::FriWitnessGeneratorConfig;
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// Fixed-size thread pool that doesn't share threads with the tokio blocking pool, so that
+/// long-running witness generation doesn't starve blocking IO (e.g., object store requests).
+#[derive(Debug)]
+pub struct ComputePool {
+    aggregation_round: &'static str,
+    sender: Mutex<mpsc::Sender<Task>>,
+}
+
+impl ComputePool {
+    pub fn new(aggregation_round: &'static str, size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Task>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..size.max(1) {
+            let receiver = receiver.clone();
+            std::thread::Builder::new()
+                .name(format!("witness-compute-{}", i))
+                .spawn(move || loop {
+                    // The lock is released before the task is run.
+                    let task = receiver.lock().unwrap().recv();
+                    match task {
+                        Ok(task) => task(),
+                        // The pool is dropped.
+                        Err(_) => break,
+                    }
+                })
+                .expect("failed spawning compute pool thread");
+        }
+        Self {
+            aggregation_round,
+            sender: Mutex::new(sender),
+        }
+    }
+
+    /// Creates a pool if it's enabled in the config. Unless specified in the config, the pool size
+    /// is equal to the max number of jobs processed concurrently.
+    pub fn from_config(
+        config: &FriWitnessGeneratorConfig,
+        aggregation_round: &'static str,
+        max_jobs_in_flight: usize,
+    ) -> Option<Self> {
+        if !config.dedicated_compute_pool() {
+            return None;
+        }
+        let size = config.compute_pool_size.unwrap_or(max_jobs_in_flight);
+        vlog::info!(
+            "Using dedicated compute pool with {} threads for {} witness generation",
+            size,
+            aggregation_round
+        );
+        Some(Self::new(aggregation_round, size))
+    }
+
+    /// Runs the task on the pool. The returned handle resolves once the task completes; if the task
+    /// panics, the handle reports the panic in the same way as for tasks spawned by tokio.
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        task: impl FnOnce() -> T + Send + 'static,
+    ) -> JoinHandle<T> {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let aggregation_round = self.aggregation_round;
+        let queued_at = Instant::now();
+        let task: Task = Box::new(move || {
+            metrics::histogram!(
+                "prover_fri.witness_generation.compute_pool_wait_time",
+                queued_at.elapsed(),
+                "aggregation_round" => aggregation_round
+            );
+            let result = panic::catch_unwind(AssertUnwindSafe(task));
+            // The receiver is dropped if the job was aborted, e.g. on timeout.
+            result_sender.send(result).ok();
+        });
+        self.sender
+            .lock()
+            .unwrap()
+            .send(task)
+            .expect("compute pool threads have exited");
+
+        tokio::spawn(async move {
+            match result_receiver.await {
+                Ok(Ok(output)) => output,
+                Ok(Err(panic)) => panic::resume_unwind(panic),
+                Err(_) => panic!("compute pool task was dropped without completing"),
+            }
+        })
+    }
+}
+
+/// Runs CPU-heavy `task` on the compute pool if it's provided, or on the tokio blocking pool
+/// otherwise. Neither inherits the current span, so it's entered explicitly.
+pub fn spawn_compute_task<T: Send + 'static>(
+    pool: Option<&ComputePool>,
+    task: impl FnOnce() -> T + Send + 'static,
+) -> JoinHandle<T> {
+    let span = tracing::Span::current();
+    let task = move || span.in_scope(task);
+    match pool {
+        Some(pool) => pool.spawn(task),
+        None => tokio::task::spawn_blocking(task),
+    }
+}
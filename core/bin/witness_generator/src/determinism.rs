@@ -0,0 +1,209 @@
+//! Determinism check re-running witness generation for sampled jobs and comparing hashes
+//! of the produced artifacts.
+
+use rand::Rng;
+
+use zksync_config::configs::FriWitnessGeneratorConfig;
+use zksync_object_store::{Bucket, ObjectStore};
+use zksync_types::proofs::AggregationRound;
+use zksync_types::web3::signing::keccak256;
+use zksync_types::{L1BatchNumber, H256};
+
+/// Settings of the determinism check.
+#[derive(Debug, Clone, Copy)]
+pub struct DeterminismCheck {
+    /// Percentage of checked jobs in the range `0..=100`.
+    percentage: u8,
+    upload_divergent_artifacts: bool,
+}
+
+impl DeterminismCheck {
+    /// Returns the check settings, or `None` if the check is disabled.
+    pub fn from_config(config: &FriWitnessGeneratorConfig) -> Option<Self> {
+        Some(Self {
+            percentage: config.determinism_check_percentage()?,
+            upload_divergent_artifacts: config.determinism_check_upload_artifacts(),
+        })
+    }
+
+    fn is_sampled(&self) -> bool {
+        rand::thread_rng().gen_range(0..100) < self.percentage
+    }
+}
+
+/// Hashes of artifacts produced by two runs of witness generation for the same job.
+#[derive(Debug)]
+pub struct DeterminismMismatch {
+    aggregation_round: AggregationRound,
+    l1_batch_number: L1BatchNumber,
+    first_hash: H256,
+    second_hash: H256,
+    /// Serialized artifacts produced by both runs; only retained if they are uploaded.
+    artifacts: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl DeterminismMismatch {
+    /// Uploads artifacts produced by both runs under the `debug/` prefix of the bucket, so that
+    /// they can be diffed offline. Upload errors are logged, but don't fail the job.
+    pub async fn upload_artifacts(
+        self,
+        object_store: &dyn ObjectStore,
+        bucket: Bucket,
+        job_id: u32,
+    ) {
+        let (first, second) = match self.artifacts {
+            Some(artifacts) => artifacts,
+            None => return,
+        };
+        for (hash, artifacts) in [(self.first_hash, first), (self.second_hash, second)] {
+            let key = format!(
+                "debug/determinism_{:?}_{}_{}_{:?}.bin",
+                self.aggregation_round, self.l1_batch_number, job_id, hash
+            );
+            match object_store.put_raw(bucket, &key, artifacts).await {
+                Ok(()) => vlog::info!("Uploaded divergent artifacts to {}/{}", bucket, key),
+                Err(err) => vlog::warn!(
+                    "Failed uploading divergent artifacts to {}/{}: {}",
+                    bucket,
+                    key,
+                    err
+                ),
+            }
+        }
+    }
+}
+
+/// Runs `generate` for the job. If the determinism check is enabled and the job is sampled,
+/// `generate` is run once more for a copy of the job, and hashes of the artifacts serialized
+/// with `serialize` are compared; artifacts that cannot be serialized (e.g., because the job
+/// has failed) are not compared. Artifacts of the first run are returned together with
+/// the mismatch if the hashes differ.
+///
+/// Mismatches are logged as errors and counted in the
+/// `prover_fri.witness_generation.determinism_mismatches` metric. The check doubles CPU time
+/// of sampled jobs.
+pub fn run_with_determinism_check<J: Clone, A>(
+    check: Option<DeterminismCheck>,
+    aggregation_round: AggregationRound,
+    l1_batch_number: L1BatchNumber,
+    job: J,
+    generate: impl Fn(J) -> A,
+    serialize: impl Fn(&A) -> Option<Vec<u8>>,
+) -> (A, Option<DeterminismMismatch>) {
+    let check = match check {
+        Some(check) if check.is_sampled() => check,
+        _ => return (generate(job), None),
+    };
+    let artifacts = generate(job.clone());
+    let first = serialize(&artifacts);
+    let second = serialize(&generate(job));
+    let (first, second) = match (first, second) {
+        (Some(first), Some(second)) => (first, second),
+        _ => return (artifacts, None),
+    };
+    metrics::counter!(
+        "prover_fri.witness_generation.determinism_checks",
+        1,
+        "aggregation_round" => format!("{:?}", aggregation_round),
+    );
+    let first_hash = H256(keccak256(&first));
+    let second_hash = H256(keccak256(&second));
+    if first_hash == second_hash {
+        return (artifacts, None);
+    }
+
+    vlog::error!(
+        "Witness generation of type {:?} for l1 batch {} is nondeterministic: artifacts \
+         of two runs have hashes {:?} and {:?}",
+        aggregation_round,
+        l1_batch_number,
+        first_hash,
+        second_hash
+    );
+    metrics::counter!(
+        "prover_fri.witness_generation.determinism_mismatches",
+        1,
+        "aggregation_round" => format!("{:?}", aggregation_round),
+    );
+    let mismatch = DeterminismMismatch {
+        aggregation_round,
+        l1_batch_number,
+        first_hash,
+        second_hash,
+        artifacts: check.upload_divergent_artifacts.then(|| (first, second)),
+    };
+    (artifacts, Some(mismatch))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn sampled_jobs_are_checked() {
+        let output = AtomicU8::new(0);
+        // The output is incremented by the job on each run, so it's deterministic for a zero job.
+        let generate = |increment: u8| output.fetch_add(increment, Ordering::SeqCst);
+        let serialize = |output: &u8| Some(vec![*output]);
+        let round = AggregationRound::LeafAggregation;
+        let check = DeterminismCheck {
+            percentage: 100,
+            upload_divergent_artifacts: false,
+        };
+        let (_, mismatch) = run_with_determinism_check(
+            Some(check),
+            round,
+            L1BatchNumber(1),
+            0,
+            generate,
+            serialize,
+        );
+        assert!(mismatch.is_none());
+
+        let (artifacts, mismatch) = run_with_determinism_check(
+            Some(check),
+            round,
+            L1BatchNumber(1),
+            1,
+            generate,
+            serialize,
+        );
+        assert_eq!(artifacts, 0);
+        let mismatch = mismatch.unwrap();
+        assert_ne!(mismatch.first_hash, mismatch.second_hash);
+        assert!(mismatch.artifacts.is_none());
+
+        let check = DeterminismCheck {
+            upload_divergent_artifacts: true,
+            ..check
+        };
+        let (_, mismatch) = run_with_determinism_check(
+            Some(check),
+            round,
+            L1BatchNumber(1),
+            1,
+            generate,
+            serialize,
+        );
+        assert_eq!(mismatch.unwrap().artifacts, Some((vec![2], vec![3])));
+    }
+
+    #[test]
+    fn jobs_are_generated_once_if_not_sampled() {
+        let generated_count = AtomicU8::new(0);
+        let generate = |()| generated_count.fetch_add(1, Ordering::SeqCst);
+        let check = DeterminismCheck {
+            percentage: 0,
+            upload_divergent_artifacts: true,
+        };
+        for check in [None, Some(check)] {
+            let round = AggregationRound::NodeAggregation;
+            let (_, mismatch) =
+                run_with_determinism_check(check, round, L1BatchNumber(1), (), generate, |_| None);
+            assert!(mismatch.is_none());
+        }
+        assert_eq!(generated_count.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,124 @@
+use std::net::SocketAddr;
+use std::sync::PoisonError;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
+use serde::Serialize;
+
+use zksync_health_check::{CheckHealth, CheckHealthStatus};
+use zksync_queued_job_processor::SharedJobProcessorStatus;
+
+struct HealthCheckState {
+    checks: Vec<Box<dyn CheckHealth>>,
+    statuses: Vec<SharedJobProcessorStatus>,
+    max_inactivity: Duration,
+}
+
+#[derive(Serialize)]
+struct LivenessResponse {
+    message: String,
+}
+
+/// Status of the witness generator; timestamps are in seconds since the UNIX epoch.
+#[derive(Serialize)]
+struct ReadinessResponse {
+    ready: bool,
+    last_activity_at: Option<u64>,
+    last_saved_job_at: Option<u64>,
+    last_error: Option<String>,
+}
+
+fn unix_timestamp(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+/// Checks that the process is up, and the DB and the object store are reachable.
+#[get("/healthz")]
+async fn liveness(state: web::Data<HealthCheckState>) -> impl Responder {
+    for check in &state.checks {
+        if let CheckHealthStatus::NotReady(message) = check.check_health().await {
+            return HttpResponse::ServiceUnavailable().json(LivenessResponse { message });
+        }
+    }
+    HttpResponse::Ok().json(LivenessResponse {
+        message: "Everything is working fine".to_string(),
+    })
+}
+
+/// Checks that the witness generator has made progress recently. If the process runs multiple
+/// rounds, the status of the least recently active one is reported.
+#[get("/readyz")]
+async fn readiness(state: web::Data<HealthCheckState>) -> impl Responder {
+    let least_active_status = state
+        .statuses
+        .iter()
+        .map(|status| {
+            status
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        })
+        .min_by_key(|status| status.last_activity_at);
+    let response = match least_active_status {
+        Some(status) => ReadinessResponse {
+            ready: !status.is_stale(state.max_inactivity),
+            last_activity_at: unix_timestamp(status.last_activity_at),
+            last_saved_job_at: status.last_saved_job_at.and_then(unix_timestamp),
+            last_error: status.last_error,
+        },
+        None => ReadinessResponse {
+            ready: true,
+            last_activity_at: None,
+            last_saved_job_at: None,
+            last_error: None,
+        },
+    };
+    if response.ready {
+        HttpResponse::Ok().json(response)
+    } else {
+        HttpResponse::ServiceUnavailable().json(response)
+    }
+}
+
+/// Starts the HTTP server exposing liveness and readiness probes in a separate thread.
+/// The server runs until the process exits.
+pub fn start_server_thread_detached(
+    addr: SocketAddr,
+    checks: Vec<Box<dyn CheckHealth>>,
+    statuses: Vec<SharedJobProcessorStatus>,
+    max_inactivity: Duration,
+) {
+    let state = HealthCheckState {
+        checks,
+        statuses,
+        max_inactivity,
+    };
+    std::thread::Builder::new()
+        .name("healthcheck".to_string())
+        .spawn(move || {
+            actix_rt::System::new().block_on(async move {
+                let state = web::Data::new(state);
+                let server = HttpServer::new(move || {
+                    App::new()
+                        .app_data(state.clone())
+                        .service(liveness)
+                        .service(readiness)
+                })
+                .workers(1)
+                .bind(addr);
+                let server = match server {
+                    Ok(server) => server,
+                    Err(err) => {
+                        vlog::error!("Failed binding health check server to {}: {}", addr, err);
+                        return;
+                    }
+                };
+                if let Err(err) = server.run().await {
+                    vlog::error!("Health check server crashed: {}", err);
+                }
+            });
+        })
+        .expect("Failed to spawn thread for health check server");
+}
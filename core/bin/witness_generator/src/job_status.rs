@@ -0,0 +1,43 @@
+//! Admin command reporting the status of the scheduler job for an L1 batch.
+
+use anyhow::Context as _;
+
+use zksync_dal::ConnectionPool;
+use zksync_types::L1BatchNumber;
+
+/// Logs the status of the scheduler job for the L1 batch together with its error history,
+/// i.e. all failed attempts to process the job, oldest first.
+pub async fn report_scheduler_job_status(
+    prover_connection_pool: &ConnectionPool,
+    l1_batch_number: L1BatchNumber,
+) -> anyhow::Result<()> {
+    let mut prover_connection = prover_connection_pool.access_storage().await;
+    let mut dal = prover_connection.fri_witness_generator_dal();
+    let status = dal
+        .get_scheduler_job_status(l1_batch_number)
+        .await
+        .with_context(|| format!("no scheduler job for l1 batch {l1_batch_number}"))?;
+    let error_history = dal
+        .get_scheduler_job_error_history(l1_batch_number)
+        .await
+        .unwrap_or_default();
+    vlog::info!(
+        "Scheduler job for l1 batch {} is {}; failed attempts: {}",
+        l1_batch_number,
+        status,
+        error_history.len()
+    );
+    for entry in &error_history {
+        vlog::info!(
+            "Attempt {} failed at {} on {} with `{}` error: {}",
+            entry
+                .attempt
+                .map_or_else(|| "?".to_owned(), |attempt| attempt.to_string()),
+            entry.timestamp,
+            entry.hostname.as_deref().unwrap_or("unknown host"),
+            entry.error_kind,
+            entry.error
+        );
+    }
+    Ok(())
+}
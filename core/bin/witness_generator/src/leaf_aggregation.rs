@@ -2,8 +2,11 @@ use zkevm_test_harness::witness::recursive_aggregation::{
     compute_leaf_params, create_leaf_witnesses,
 };
 
+use std::collections::hash_map::{Entry, HashMap};
+use std::sync::Arc;
 use std::time::Instant;
 
+use anyhow::Context as _;
 use async_trait::async_trait;
 use circuit_definitions::circuit_definitions::base_layer::{
     ZkSyncBaseLayerClosedFormInput, ZkSyncBaseLayerProof, ZkSyncBaseLayerVerificationKey,
@@ -15,16 +18,24 @@ use zksync_vk_setup_data_server_fri::{
     get_base_layer_vk_for_circuit_type, get_recursive_layer_vk_for_circuit_type,
 };
 
+use crate::compute_pool::{spawn_compute_task, ComputePool};
+use crate::determinism::{run_with_determinism_check, DeterminismCheck, DeterminismMismatch};
+use crate::peak_memory::track_peak_memory;
+use crate::sealed_latency::report_sealed_to_saved_latency;
 use crate::utils::{
-    get_recursive_layer_circuit_id_for_base_layer, load_proofs_for_job_ids,
-    save_node_aggregations_artifacts, save_recursive_layer_prover_input_artifacts,
-    ClosedFormInputWrapper, FriProofWrapper,
+    classify_store_unavailable, count_pending_witness_jobs, get_basic_artifact_section,
+    get_basic_artifacts_manifest, get_recursive_layer_circuit_id_for_base_layer,
+    load_proofs_for_job_ids, report_job_pickup, save_node_aggregations_artifacts,
+    save_recursive_layer_prover_input_artifacts, AggregationWrapper, BasicArtifactsManifest,
+    CircuitPosition, ClosedFormInputWrapper, FriProofWrapper,
 };
 use zkevm_test_harness::zkevm_circuits::recursion::leaf_layer::input::RecursionLeafParametersWitness;
 use zksync_config::configs::FriWitnessGeneratorConfig;
 use zksync_dal::ConnectionPool;
-use zksync_object_store::{ClosedFormInputKey, ObjectStore, ObjectStoreFactory};
-use zksync_queued_job_processor::JobProcessor;
+use zksync_object_store::{ClosedFormInputKey, ObjectStore, ObjectStoreError, StoredObject};
+use zksync_queued_job_processor::{
+    JobError, JobMetadata, JobProcessor, PendingJobsCount, SharedJobProcessorStatus,
+};
 use zksync_types::proofs::{AggregationRound, LeafAggregationJobMetadata};
 use zksync_types::L1BatchNumber;
 
@@ -38,6 +49,7 @@ pub struct LeafAggregationArtifacts {
     )>,
     #[allow(dead_code)]
     closed_form_inputs: Vec<ZkSyncBaseLayerClosedFormInput<GoldilocksField>>,
+    determinism_mismatch: Option<DeterminismMismatch>,
 }
 
 #[derive(Debug)]
@@ -46,6 +58,7 @@ struct BlobUrls {
     aggregations_urls: String,
 }
 
+#[derive(Clone)]
 pub struct LeafAggregationWitnessGeneratorJob {
     circuit_id: u8,
     block_number: L1BatchNumber,
@@ -57,22 +70,35 @@ pub struct LeafAggregationWitnessGeneratorJob {
 
 #[derive(Debug)]
 pub struct LeafAggregationWitnessGenerator {
-    #[allow(dead_code)]
     config: FriWitnessGeneratorConfig,
-    object_store: Box<dyn ObjectStore>,
+    object_store: Arc<dyn ObjectStore>,
+    connection_pool: ConnectionPool,
     prover_connection_pool: ConnectionPool,
+    status: SharedJobProcessorStatus,
+    /// Pool running witness generation; if not set, the tokio blocking pool is used.
+    compute_pool: Option<ComputePool>,
+    /// IDs of circuits jobs are picked up for; if not set, jobs for all circuits are picked up.
+    circuit_ids: Option<Vec<u8>>,
 }
 
 impl LeafAggregationWitnessGenerator {
-    pub async fn new(
+    pub fn new(
         config: FriWitnessGeneratorConfig,
-        store_factory: &ObjectStoreFactory,
+        object_store: Arc<dyn ObjectStore>,
+        connection_pool: ConnectionPool,
         prover_connection_pool: ConnectionPool,
+        base_layer_circuit_ids: Option<Vec<u8>>,
     ) -> Self {
+        let compute_pool =
+            ComputePool::from_config(&config, "LeafAggregation", config.leaf_max_jobs_in_flight());
         Self {
             config,
-            object_store: store_factory.create_store().await,
+            object_store,
+            connection_pool,
             prover_connection_pool,
+            status: SharedJobProcessorStatus::default(),
+            compute_pool,
+            circuit_ids: base_layer_circuit_ids,
         }
     }
 
@@ -98,25 +124,133 @@ impl JobProcessor for LeafAggregationWitnessGenerator {
 
     const SERVICE_NAME: &'static str = "fri_leaf_aggregation_witness_generator";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
         let mut prover_connection = self.prover_connection_pool.access_storage().await;
-        let metadata = prover_connection
+        let metadata = match prover_connection
             .fri_witness_generator_dal()
-            .get_next_leaf_aggregation_job()
-            .await?;
+            .get_next_leaf_aggregation_job(&self.config.batch_range(), self.circuit_ids.as_deref())
+            .await
+        {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+        report_job_pickup(AggregationRound::LeafAggregation, metadata.circuit_id);
         vlog::info!("Processing node aggregation job {:?}", metadata.id);
-        Some((
+        let manifest = get_manifest(metadata.block_number, &*self.object_store).await?;
+        Ok(Some((
             metadata.id,
-            prepare_leaf_aggregation_job(metadata, &*self.object_store).await,
-        ))
+            prepare_leaf_aggregation_job(
+                metadata,
+                manifest.as_ref(),
+                &*self.object_store,
+                self.config.proof_fetch_concurrency(),
+            )
+            .await?,
+        )))
+    }
+
+    async fn get_next_jobs(
+        &self,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(Self::JobId, Self::Job, JobMetadata)>> {
+        let jobs_metadata = self
+            .prover_connection_pool
+            .access_storage()
+            .await
+            .fri_witness_generator_dal()
+            .get_next_leaf_aggregation_jobs(
+                limit,
+                &self.config.batch_range(),
+                self.circuit_ids.as_deref(),
+            )
+            .await;
+        let mut jobs = Vec::with_capacity(jobs_metadata.len());
+        // Claimed jobs are often for the same batch, so manifests are shared among them.
+        let mut manifests = HashMap::new();
+        for metadata in jobs_metadata {
+            let job_id = metadata.id;
+            let job_metadata = JobMetadata::new(Some(metadata.attempts));
+            report_job_pickup(AggregationRound::LeafAggregation, metadata.circuit_id);
+            vlog::info!("Processing leaf aggregation job {:?}", job_id);
+            let manifest = match manifests.entry(metadata.block_number) {
+                Entry::Occupied(entry) => Ok(entry.into_mut()),
+                Entry::Vacant(entry) => get_manifest(metadata.block_number, &*self.object_store)
+                    .await
+                    .map(|manifest| entry.insert(manifest)),
+            };
+            let job = match manifest {
+                Ok(manifest) => {
+                    prepare_leaf_aggregation_job(
+                        metadata,
+                        manifest.as_ref(),
+                        &*self.object_store,
+                        self.config.proof_fetch_concurrency(),
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            };
+            match job {
+                Ok(job) => jobs.push((job_id, job, job_metadata)),
+                // Other claimed jobs are returned regardless, so the failure is persisted right away.
+                Err(err) => {
+                    vlog::error!(
+                        "Failed preparing leaf aggregation job {}: {:#}",
+                        job_id,
+                        err
+                    );
+                    self.save_failure(job_id, Instant::now(), err.into()).await;
+                }
+            }
+        }
+        Ok(jobs)
+    }
+
+    fn job_pickup_batch_size(&self) -> usize {
+        self.config.job_pickup_batch_size()
+    }
+
+    fn max_jobs_to_process(&self) -> Option<u64> {
+        self.config.max_jobs_to_process
+    }
+
+    fn max_pickups_per_minute(&self) -> Option<u32> {
+        self.config.max_job_pickups_per_minute
+    }
+
+    fn drain_after_empty_polls(&self) -> Option<u32> {
+        self.config.drain_after_empty_polls()
+    }
+
+    fn status(&self) -> Option<&SharedJobProcessorStatus> {
+        Some(&self.status)
+    }
+
+    async fn pending_jobs_count(&self) -> Option<PendingJobsCount> {
+        Some(
+            count_pending_witness_jobs(
+                &self.prover_connection_pool,
+                AggregationRound::LeafAggregation,
+            )
+            .await,
+        )
+    }
+
+    async fn save_failure(&self, job_id: u32, _started_at: Instant, error: JobError) -> () {
+        self.prover_connection_pool
+            .access_storage()
+            .await
+            .fri_witness_generator_dal()
+            .mark_leaf_aggregation_job_failed(&error.to_json(), job_id, error.retryable)
+            .await;
     }
 
-    async fn save_failure(&self, job_id: u32, _started_at: Instant, error: String) -> () {
+    async fn release_job(&self, job_id: u32) {
         self.prover_connection_pool
             .access_storage()
             .await
             .fri_witness_generator_dal()
-            .mark_leaf_aggregation_job_failed(&error, job_id)
+            .release_leaf_aggregation_job(job_id)
             .await;
     }
 
@@ -125,19 +259,43 @@ impl JobProcessor for LeafAggregationWitnessGenerator {
         &self,
         job: LeafAggregationWitnessGeneratorJob,
         started_at: Instant,
-    ) -> tokio::task::JoinHandle<LeafAggregationArtifacts> {
-        tokio::task::spawn_blocking(move || Self::process_job_sync(job, started_at))
+    ) -> tokio::task::JoinHandle<anyhow::Result<LeafAggregationArtifacts>> {
+        let determinism_check = DeterminismCheck::from_config(&self.config);
+        spawn_compute_task(self.compute_pool.as_ref(), move || {
+            let l1_batch_number = job.block_number;
+            track_peak_memory(AggregationRound::LeafAggregation, l1_batch_number, || {
+                let (mut artifacts, determinism_mismatch) = run_with_determinism_check(
+                    determinism_check,
+                    AggregationRound::LeafAggregation,
+                    l1_batch_number,
+                    job,
+                    |job| Self::process_job_sync(job, started_at),
+                    |artifacts| bincode::serialize(&artifacts.aggregations).ok(),
+                );
+                artifacts.determinism_mismatch = determinism_mismatch;
+                Ok(artifacts)
+            })
+        })
     }
 
     async fn save_result(
         &self,
         job_id: u32,
         started_at: Instant,
-        artifacts: LeafAggregationArtifacts,
-    ) {
+        mut artifacts: LeafAggregationArtifacts,
+    ) -> anyhow::Result<()> {
+        if let Some(mismatch) = artifacts.determinism_mismatch.take() {
+            let bucket = AggregationWrapper::BUCKET;
+            mismatch
+                .upload_artifacts(&*self.object_store, bucket, job_id)
+                .await;
+        }
         let block_number = artifacts.block_number;
         let circuit_id = artifacts.circuit_id;
-        let blob_urls = save_artifacts(artifacts, &*self.object_store).await;
+        let blob_urls = save_artifacts(artifacts, &*self.object_store)
+            .await
+            .context("failed saving leaf aggregation artifacts")
+            .map_err(classify_store_unavailable)?;
         update_database(
             &self.prover_connection_pool,
             started_at,
@@ -147,16 +305,35 @@ impl JobProcessor for LeafAggregationWitnessGenerator {
             circuit_id,
         )
         .await;
+        report_sealed_to_saved_latency(
+            &self.connection_pool,
+            &self.prover_connection_pool,
+            AggregationRound::LeafAggregation,
+            block_number,
+            job_id,
+        )
+        .await;
+        Ok(())
     }
 }
 
 async fn prepare_leaf_aggregation_job(
     metadata: LeafAggregationJobMetadata,
+    manifest: Option<&BasicArtifactsManifest>,
     object_store: &dyn ObjectStore,
-) -> LeafAggregationWitnessGeneratorJob {
+    proof_fetch_concurrency: usize,
+) -> anyhow::Result<LeafAggregationWitnessGeneratorJob> {
     let started_at = Instant::now();
-    let closed_form_input = get_artifacts(&metadata, object_store).await;
-    let proofs = load_proofs_for_job_ids(&metadata.prover_job_ids_for_proofs, object_store).await;
+    let closed_form_input = get_artifacts(&metadata, manifest, object_store).await?;
+    let proofs = load_proofs_for_job_ids(
+        metadata.block_number,
+        &metadata.prover_job_ids_for_proofs,
+        object_store,
+        proof_fetch_concurrency,
+        AggregationRound::LeafAggregation,
+    )
+    .await
+    .with_context(|| format!("failed loading proofs for leaf agg {}", metadata.id))?;
     metrics::histogram!(
         "prover_fri.witness_generation.blob_fetch_time",
         started_at.elapsed(),
@@ -170,26 +347,26 @@ async fn prepare_leaf_aggregation_job(
     let base_proofs = proofs
         .into_iter()
         .map(|wrapper| match wrapper {
-            FriProofWrapper::Base(base_proof) => base_proof,
+            FriProofWrapper::Base(base_proof) => Ok(base_proof),
             FriProofWrapper::Recursive(_) => {
-                panic!("Expected only base proofs for leaf agg {}", metadata.id)
+                anyhow::bail!("Expected only base proofs for leaf agg {}", metadata.id)
             }
         })
-        .collect::<Vec<_>>();
+        .collect::<anyhow::Result<Vec<_>>>()?;
     let leaf_params = compute_leaf_params(metadata.circuit_id, base_vk.clone(), leaf_vk);
     metrics::histogram!(
         "prover_fri.witness_generation.prepare_job_time",
         started_at.elapsed(),
         "aggregation_round" => format!("{:?}", AggregationRound::LeafAggregation),
     );
-    LeafAggregationWitnessGeneratorJob {
+    Ok(LeafAggregationWitnessGeneratorJob {
         circuit_id: metadata.circuit_id,
         block_number: metadata.block_number,
         closed_form_inputs: closed_form_input,
         proofs: base_proofs,
         base_vk,
         leaf_params,
-    }
+    })
 }
 
 pub fn process_leaf_aggregation_job(
@@ -222,6 +399,7 @@ pub fn process_leaf_aggregation_job(
         block_number: job.block_number,
         aggregations,
         closed_form_inputs,
+        determinism_mismatch: None,
     }
 }
 
@@ -263,49 +441,59 @@ async fn update_database(
     transaction.commit().await;
 }
 
+async fn get_manifest(
+    block_number: L1BatchNumber,
+    object_store: &dyn ObjectStore,
+) -> anyhow::Result<Option<BasicArtifactsManifest>> {
+    get_basic_artifacts_manifest(object_store, block_number)
+        .await
+        .with_context(|| {
+            format!("failed loading basic artifacts manifest for block {block_number}")
+        })
+}
+
 async fn get_artifacts(
     metadata: &LeafAggregationJobMetadata,
+    manifest: Option<&BasicArtifactsManifest>,
     object_store: &dyn ObjectStore,
-) -> ClosedFormInputWrapper {
+) -> anyhow::Result<ClosedFormInputWrapper> {
     let key = ClosedFormInputKey {
         block_number: metadata.block_number,
         circuit_id: metadata.circuit_id,
     };
-    object_store
-        .get(key)
+    let aggregation_round = AggregationRound::LeafAggregation;
+    get_basic_artifact_section(object_store, manifest, key, aggregation_round)
         .await
-        .unwrap_or_else(|_| panic!("leaf aggregation job artifacts missing: {:?}", key))
+        .with_context(|| format!("leaf aggregation job artifacts missing: {:?}", key))
 }
 
 async fn save_artifacts(
     artifacts: LeafAggregationArtifacts,
     object_store: &dyn ObjectStore,
-) -> BlobUrls {
+) -> Result<BlobUrls, ObjectStoreError> {
     let started_at = Instant::now();
-    let aggregations_urls = save_node_aggregations_artifacts(
+    let circuit_ids_and_urls = save_recursive_layer_prover_input_artifacts(
         artifacts.block_number,
-        get_recursive_layer_circuit_id_for_base_layer(artifacts.circuit_id),
-        0,
-        artifacts.aggregations.clone(),
+        &artifacts.aggregations,
         object_store,
+        |sequence_number| CircuitPosition::Leaf { sequence_number },
     )
-    .await;
-    let circuit_ids_and_urls = save_recursive_layer_prover_input_artifacts(
+    .await?;
+    let aggregations_urls = save_node_aggregations_artifacts(
         artifacts.block_number,
-        artifacts.aggregations,
-        AggregationRound::LeafAggregation,
+        get_recursive_layer_circuit_id_for_base_layer(artifacts.circuit_id),
         0,
+        artifacts.aggregations,
         object_store,
-        None,
     )
-    .await;
+    .await?;
     metrics::histogram!(
         "prover_fri.witness_generation.blob_save_time",
         started_at.elapsed(),
         "aggregation_round" => format!("{:?}", AggregationRound::LeafAggregation),
     );
-    BlobUrls {
+    Ok(BlobUrls {
         circuit_ids_and_urls,
         aggregations_urls,
-    }
+    })
 }
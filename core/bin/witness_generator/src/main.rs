@@ -1,30 +1,57 @@
 #![feature(generic_const_exprs)]
 
+use anyhow::Context as _;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use futures::FutureExt;
 use prometheus_exporter::run_prometheus_exporter;
+use std::future::Future;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Instant;
 use structopt::StructOpt;
 use tokio::sync::watch;
+use tokio::task::{JoinError, JoinHandle};
 use zksync_config::configs::{AlertsConfig, FriWitnessGeneratorConfig, PrometheusConfig};
 use zksync_config::ObjectStoreConfig;
+use zksync_dal::healthcheck::ConnectionPoolHealthCheck;
 use zksync_dal::{connection::DbVariant, ConnectionPool};
-use zksync_object_store::ObjectStoreFactory;
+use zksync_health_check::CheckHealth;
+use zksync_object_store::{Bucket, ObjectStore, ObjectStoreFactory, ObjectStoreHealthCheck};
 use zksync_prover_utils::get_stop_signal_receiver;
-use zksync_queued_job_processor::JobProcessor;
+use zksync_queued_job_processor::{JobProcessor, SharedJobProcessorStatus};
 use zksync_types::proofs::AggregationRound;
 use zksync_types::web3::futures::StreamExt;
+use zksync_types::L1BatchNumber;
+use zksync_utils::panic_extractor::try_extract_panic_message;
 use zksync_utils::wait_for_tasks::wait_for_tasks;
 
 use crate::basic_circuits::BasicWitnessGenerator;
+use crate::commitments_checker::SchedulerCommitmentsChecker;
 use crate::leaf_aggregation::LeafAggregationWitnessGenerator;
 use crate::node_aggregation::NodeAggregationWitnessGenerator;
 use crate::scheduler::SchedulerWitnessGenerator;
 
 mod basic_circuits;
+mod commitments_checker;
+mod compute_pool;
+mod determinism;
+mod health_check;
+mod job_status;
 mod leaf_aggregation;
 mod node_aggregation;
+mod peak_memory;
 mod precalculated_merkle_paths_provider;
+mod priority_batches;
+mod requeue;
+mod resources;
+mod retry;
 mod scheduler;
+mod sealed_latency;
+mod skip;
 mod utils;
+mod vk_cache;
 
 #[derive(Debug, StructOpt)]
 #[structopt(
@@ -35,13 +62,117 @@ struct Opt {
     /// Number of times witness generator should be run.
     #[structopt(short = "b", long = "batch_size")]
     batch_size: Option<usize>,
-    /// aggregation round for the witness generator.
-    #[structopt(short = "r", long = "round")]
-    round: AggregationRound,
+    /// aggregation round for the witness generator; required unless `--rounds` or a command is
+    /// specified.
+    #[structopt(short = "r", long = "round", conflicts_with = "rounds")]
+    round: Option<AggregationRound>,
+    /// Comma-separated aggregation rounds run concurrently in a single process,
+    /// e.g. `basic,leaf,node,scheduler`.
+    #[structopt(long = "rounds", use_delimiter = true, parse(try_from_str = parse_round))]
+    rounds: Vec<AggregationRound>,
+    /// Process jobs without persisting their outcomes; only supported for the scheduler round.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+    /// Exit once the job queue is drained instead of waiting for new jobs.
+    #[structopt(long = "drain")]
+    drain: bool,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Requeue the scheduler job for an L1 batch, so that its witness is regenerated.
+    RequeueScheduler {
+        /// L1 batch number of the job.
+        #[structopt(long = "batch")]
+        batch: u32,
+        /// Remove the previously saved scheduler circuit, so that the regenerated one is uploaded.
+        #[structopt(long = "delete-circuit")]
+        delete_circuit: bool,
+        /// Requeue the job even if the scheduler proof was already generated.
+        #[structopt(long = "force")]
+        force: bool,
+    },
+    /// Report the status of the scheduler job for an L1 batch together with its failed attempts.
+    SchedulerJobStatus {
+        /// L1 batch number of the job.
+        #[structopt(long = "batch")]
+        batch: u32,
+    },
+    /// Mark witness jobs of all rounds for an L1 batch as skipped, e.g. if the batch was reverted.
+    SkipBatch {
+        /// L1 batch number of the jobs.
+        #[structopt(long = "batch")]
+        batch: u32,
+    },
+}
+
+/// Parses an aggregation round, allowing short names (e.g., `leaf` for `leaf_aggregation`).
+fn parse_round(s: &str) -> Result<AggregationRound, String> {
+    match s {
+        "basic" => Ok(AggregationRound::BasicCircuits),
+        "leaf" => Ok(AggregationRound::LeafAggregation),
+        "node" => Ok(AggregationRound::NodeAggregation),
+        other => other.parse(),
+    }
+}
+
+/// Returns the rounds to run, checking that they are specified and unique.
+fn rounds_to_run(
+    round: Option<AggregationRound>,
+    rounds: Vec<AggregationRound>,
+) -> anyhow::Result<Vec<AggregationRound>> {
+    let rounds = match round {
+        Some(round) => vec![round],
+        None => rounds,
+    };
+    if rounds.is_empty() {
+        anyhow::bail!("--round or --rounds must be specified unless a command is specified");
+    }
+    for (i, round) in rounds.iter().enumerate() {
+        if rounds[..i].contains(round) {
+            anyhow::bail!("{:?} round is specified multiple times", round);
+        }
+    }
+    Ok(rounds)
+}
+
+async fn run_command(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::RequeueScheduler {
+            batch,
+            delete_circuit,
+            force,
+        } => {
+            let object_store = ObjectStoreFactory::from_env()
+                .create_store()
+                .await
+                .context("failed initializing object store")?;
+            let prover_connection_pool = ConnectionPool::new(None, DbVariant::Prover).await;
+            requeue::requeue_scheduler_job(
+                &prover_connection_pool,
+                &*object_store,
+                L1BatchNumber(batch),
+                delete_circuit,
+                force,
+            )
+            .await
+        }
+        Command::SchedulerJobStatus { batch } => {
+            let prover_connection_pool = ConnectionPool::new(None, DbVariant::Prover).await;
+            job_status::report_scheduler_job_status(&prover_connection_pool, L1BatchNumber(batch))
+                .await
+        }
+        Command::SkipBatch { batch } => {
+            let prover_connection_pool = ConnectionPool::new(None, DbVariant::Prover).await;
+            skip::skip_l1_batch(&prover_connection_pool, L1BatchNumber(batch)).await
+        }
+    }
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     vlog::init();
     let sentry_guard = vlog::init_sentry();
     match sentry_guard {
@@ -53,90 +184,406 @@ async fn main() {
     }
 
     let opt = Opt::from_args();
+    if let Some(command) = opt.command {
+        return run_command(command).await;
+    }
+    let rounds = rounds_to_run(opt.round, opt.rounds)?;
     let started_at = Instant::now();
     vlog::info!(
-        "initializing the {:?} witness generator, batch size: {:?}",
-        opt.round,
+        "initializing the {:?} witness generator(s), batch size: {:?}",
+        rounds,
         opt.batch_size
     );
     let use_push_gateway = opt.batch_size.is_some();
 
     let store_factory = ObjectStoreFactory::from_env();
-    let config = FriWitnessGeneratorConfig::from_env();
+    let mut config = FriWitnessGeneratorConfig::from_env();
+    if opt.dry_run {
+        config.dry_run = Some(true);
+    }
+    if opt.drain {
+        config.drain = Some(true);
+    }
+    // Other rounds lock jobs when picking them up, so they would be left in progress in dry-run mode.
+    if config.dry_run() && rounds != [AggregationRound::Scheduler] {
+        anyhow::bail!(
+            "dry-run mode is only supported for the scheduler round, got {:?}",
+            rounds
+        );
+    }
+    if let Some((first, last)) = config.batch_range {
+        vlog::info!(
+            "Only picking up {:?} jobs for l1 batches {}..={}",
+            rounds,
+            first,
+            last
+        );
+    }
     let prometheus_config = PrometheusConfig::from_env();
     let connection_pool = ConnectionPool::new(None, DbVariant::Master).await;
     let prover_connection_pool = ConnectionPool::new(None, DbVariant::Prover).await;
     let (stop_sender, stop_receiver) = watch::channel(false);
+    let health_check_port = config.health_check_port;
+    let health_check_max_inactivity = config.health_check_max_inactivity();
+    // Fail fast if the store is misconfigured, rather than on the first processed job. The store is
+    // shared by all rounds, so that its bandwidth limits and circuit breaker apply to them jointly.
+    let object_store: Arc<dyn ObjectStore> = store_factory
+        .create_store()
+        .await
+        .context("failed initializing object store")?
+        .into();
+    let mut checked_buckets = vec![];
+    for &round in &rounds {
+        for &bucket in required_buckets(round) {
+            if checked_buckets.contains(&bucket) {
+                continue;
+            }
+            object_store
+                .health_check(bucket)
+                .await
+                .with_context(|| format!("object store bucket `{}` is unavailable", bucket))?;
+            checked_buckets.push(bucket);
+        }
+    }
+    let health_checks: Vec<Box<dyn CheckHealth>> = vec![
+        Box::new(ConnectionPoolHealthCheck::new(
+            prover_connection_pool.clone(),
+        )),
+        // All rounds read proofs or their inputs from the same store, so any bucket will do.
+        Box::new(ObjectStoreHealthCheck::new(
+            Box::new(Arc::clone(&object_store)),
+            Bucket::ProofsFri,
+        )),
+    ];
+
+    // All generators are initialized before any of them is spawned, so that no jobs are
+    // interrupted if initialization fails.
+    let mut background_tasks = vec![];
+    let mut witness_generator_tasks = vec![];
+    let mut statuses = vec![];
+    for &round in &rounds {
+        let (task, status) = create_witness_generator_task(
+            round,
+            config.clone(),
+            Arc::clone(&object_store),
+            connection_pool.clone(),
+            prover_connection_pool.clone(),
+            stop_receiver.clone(),
+            opt.batch_size,
+            &mut background_tasks,
+        )
+        .await?;
+        witness_generator_tasks.push((round, task));
+        statuses.extend(status);
+    }
+    if let Some(port) = health_check_port {
+        health_check::start_server_thread_detached(
+            SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)),
+            health_checks,
+            statuses,
+            health_check_max_inactivity,
+        );
+    }
+    let mut witness_generator_handles: FuturesUnordered<_> = witness_generator_tasks
+        .into_iter()
+        .map(|(round, task)| {
+            let handle = tokio::spawn(task);
+            async move { (round, handle.await) }
+        })
+        .collect();
+    let mut tasks = vec![run_prometheus_exporter(
+        prometheus_config.listener_port,
+        use_push_gateway.then(|| {
+            (
+                prometheus_config.pushgateway_url.clone(),
+                prometheus_config.push_interval(),
+            )
+        }),
+    )];
+    tasks.extend(background_tasks);
+    vlog::info!(
+        "initialized {:?} witness generator(s) in {:?}",
+        rounds,
+        started_at.elapsed()
+    );
+    for round in &rounds {
+        metrics::gauge!(
+            "server.init.latency",
+            started_at.elapsed(),
+            "stage" => format!("fri_witness_generator_{:?}", round)
+        );
+    }
+
+    let mut stop_signal_receiver = get_stop_signal_receiver();
+    let particular_crypto_alerts = Some(AlertsConfig::from_env().sporadic_crypto_errors_substrs);
+    let graceful_shutdown = None::<futures::future::Ready<()>>;
+    let tasks_allowed_to_finish = false;
+    let mut result = Ok(());
+    tokio::select! {
+        _ = wait_for_tasks(tasks, particular_crypto_alerts, graceful_shutdown, tasks_allowed_to_finish) => {},
+        generators_result = wait_for_witness_generators(&mut witness_generator_handles) => {
+            result = generators_result;
+        }
+        _ = stop_signal_receiver.next() => {
+            vlog::info!("Stop signal received, shutting down");
+        }
+    }
+
+    stop_sender.send(true).ok();
+    // Let the witness generators finish the jobs they're currently processing so that the jobs
+    // are not left `in_progress` until they're requeued.
+    while let Some((round, generator_result)) = witness_generator_handles.next().await {
+        let generator_result = check_witness_generator_result(round, generator_result);
+        if result.is_ok() {
+            result = generator_result;
+        }
+    }
+    result
+}
 
-    let witness_generator_task = match opt.round {
+/// Initializes the witness generator for the round. Returns its job processing loop together
+/// with its status; background tasks needed by the generator are spawned into `background_tasks`.
+#[allow(clippy::too_many_arguments)]
+async fn create_witness_generator_task(
+    round: AggregationRound,
+    config: FriWitnessGeneratorConfig,
+    object_store: Arc<dyn ObjectStore>,
+    connection_pool: ConnectionPool,
+    prover_connection_pool: ConnectionPool,
+    stop_receiver: watch::Receiver<bool>,
+    batch_size: Option<usize>,
+    background_tasks: &mut Vec<JoinHandle<()>>,
+) -> anyhow::Result<(
+    BoxFuture<'static, anyhow::Result<()>>,
+    Option<SharedJobProcessorStatus>,
+)> {
+    let max_jobs_in_flight = max_jobs_in_flight(&config, round);
+    Ok(match round {
         AggregationRound::BasicCircuits => {
             let public_blob_store = ObjectStoreFactory::new(ObjectStoreConfig::public_from_env())
                 .create_store()
-                .await;
+                .await
+                .context("failed initializing public object store")?;
+            let node_aggregation_fan_in =
+                node_aggregation::configured_node_aggregation_fan_in(&config)?;
+            vlog::info!("Node aggregation fan-in: {}", node_aggregation_fan_in);
             let generator = BasicWitnessGenerator::new(
                 config,
-                &store_factory,
+                object_store,
                 public_blob_store,
                 connection_pool,
                 prover_connection_pool,
-            )
-            .await;
-            generator.run(stop_receiver, opt.batch_size)
+                node_aggregation_fan_in,
+            );
+            let status = generator.status().cloned();
+            let task = generator.run_concurrent(stop_receiver, batch_size, max_jobs_in_flight);
+            (task.boxed(), status)
         }
         AggregationRound::LeafAggregation => {
+            let circuit_ids = utils::configured_circuit_ids(&config)?;
+            if let Some(circuit_ids) = &circuit_ids {
+                vlog::info!(
+                    "Only picking up leaf aggregation jobs for circuits {:?}",
+                    circuit_ids
+                );
+            }
             let generator = LeafAggregationWitnessGenerator::new(
                 config,
-                &store_factory,
+                object_store,
+                connection_pool,
                 prover_connection_pool,
-            )
-            .await;
-            generator.run(stop_receiver, opt.batch_size)
+                circuit_ids,
+            );
+            let status = generator.status().cloned();
+            let task = generator.run_concurrent(stop_receiver, batch_size, max_jobs_in_flight);
+            (task.boxed(), status)
         }
         AggregationRound::NodeAggregation => {
-            let generator =
-                NodeAggregationWitnessGenerator::new(&store_factory, prover_connection_pool).await;
-            generator.run(stop_receiver, opt.batch_size)
+            let circuit_ids = utils::configured_circuit_ids(&config)?;
+            if let Some(circuit_ids) = &circuit_ids {
+                vlog::info!(
+                    "Only picking up node aggregation jobs for circuits {:?}",
+                    circuit_ids
+                );
+            }
+            let generator = NodeAggregationWitnessGenerator::new(
+                config,
+                object_store,
+                connection_pool,
+                prover_connection_pool,
+                circuit_ids,
+            );
+            let status = generator.status().cloned();
+            let task = generator.run_concurrent(stop_receiver, batch_size, max_jobs_in_flight);
+            (task.boxed(), status)
         }
         AggregationRound::Scheduler => {
-            let generator =
-                SchedulerWitnessGenerator::new(&store_factory, prover_connection_pool).await;
-            generator.run(stop_receiver, opt.batch_size)
+            let mut initial_priority_batches = config.priority_batches();
+            let priority_batches_file = config.priority_batches_file.clone().map(PathBuf::from);
+            if let Some(path) = &priority_batches_file {
+                initial_priority_batches = priority_batches::load_priority_batches(path)?;
+            }
+            vlog::info!("Priority batches: {:?}", initial_priority_batches);
+            let (priority_batches_sender, priority_batches_receiver) =
+                watch::channel(initial_priority_batches);
+            if let Some(path) = priority_batches_file {
+                priority_batches::spawn_reload_on_sighup(path, priority_batches_sender)?;
+            }
+            if !config.dry_run() {
+                let checker = SchedulerCommitmentsChecker::new(
+                    connection_pool.clone(),
+                    prover_connection_pool.clone(),
+                    config.scheduler_commitments_check_interval(),
+                );
+                background_tasks.push(tokio::spawn(checker.run(stop_receiver.clone())));
+            }
+            let generator = SchedulerWitnessGenerator::new(
+                config,
+                object_store,
+                connection_pool,
+                prover_connection_pool,
+                priority_batches_receiver,
+            );
+            let status = generator.status().cloned();
+            let task = generator.run_concurrent(stop_receiver, batch_size, max_jobs_in_flight);
+            (task.boxed(), status)
         }
-    };
-    let tasks = vec![
-        run_prometheus_exporter(
-            prometheus_config.listener_port,
-            use_push_gateway.then(|| {
-                (
-                    prometheus_config.pushgateway_url.clone(),
-                    prometheus_config.push_interval(),
-                )
-            }),
-        ),
-        tokio::spawn(witness_generator_task),
-    ];
-    vlog::info!(
-        "initialized {:?} witness generator in {:?}",
-        opt.round,
-        started_at.elapsed()
-    );
-    metrics::gauge!(
-        "server.init.latency",
-        started_at.elapsed(),
-        "stage" => format!("fri_witness_generator_{:?}", opt.round)
-    );
+    })
+}
 
-    let mut stop_signal_receiver = get_stop_signal_receiver();
-    let particular_crypto_alerts = Some(AlertsConfig::from_env().sporadic_crypto_errors_substrs);
-    let graceful_shutdown = None::<futures::future::Ready<()>>;
-    let tasks_allowed_to_finish = false;
-    tokio::select! {
-        _ = wait_for_tasks(tasks, particular_crypto_alerts, graceful_shutdown, tasks_allowed_to_finish) => {},
-        _ = stop_signal_receiver.next() => {
-            vlog::info!("Stop signal received, shutting down");
+/// Returns the max number of jobs the witness generator for the round processes concurrently.
+fn max_jobs_in_flight(config: &FriWitnessGeneratorConfig, round: AggregationRound) -> usize {
+    match round {
+        AggregationRound::BasicCircuits => config.basic_max_jobs_in_flight(),
+        AggregationRound::LeafAggregation => config.leaf_max_jobs_in_flight(),
+        AggregationRound::NodeAggregation => config.node_max_jobs_in_flight(),
+        AggregationRound::Scheduler => config.scheduler_max_jobs_in_flight(),
+    }
+}
+
+/// Round of a finished witness generator together with the outcome of its task.
+type WitnessGeneratorOutcome = (AggregationRound, Result<anyhow::Result<()>, JoinError>);
+
+/// Waits until all witness generators finish, or until one of them fails, in which case
+/// the failure is returned.
+async fn wait_for_witness_generators(
+    handles: &mut FuturesUnordered<impl Future<Output = WitnessGeneratorOutcome>>,
+) -> anyhow::Result<()> {
+    while let Some((round, result)) = handles.next().await {
+        check_witness_generator_result(round, result)?;
+    }
+    Ok(())
+}
+
+/// Returns buckets the witness generator for the specified round reads from or writes to.
+fn required_buckets(round: AggregationRound) -> &'static [Bucket] {
+    match round {
+        AggregationRound::BasicCircuits => &[
+            Bucket::WitnessInput,
+            Bucket::ProverJobsFri,
+            Bucket::LeafAggregationWitnessJobsFri,
+            Bucket::SchedulerWitnessJobsFri,
+        ],
+        AggregationRound::LeafAggregation => &[
+            Bucket::LeafAggregationWitnessJobsFri,
+            Bucket::ProofsFri,
+            Bucket::ProverJobsFri,
+            Bucket::NodeAggregationWitnessJobsFri,
+        ],
+        AggregationRound::NodeAggregation => &[
+            Bucket::NodeAggregationWitnessJobsFri,
+            Bucket::ProofsFri,
+            Bucket::ProverJobsFri,
+        ],
+        AggregationRound::Scheduler => &[
+            Bucket::SchedulerWitnessJobsFri,
+            Bucket::ProofsFri,
+            Bucket::ProverJobsFri,
+        ],
+    }
+}
+
+/// Logs the outcome of the witness generator task and converts it into the process result,
+/// so that the binary exits with a non-zero status if the generator failed.
+fn check_witness_generator_result(
+    round: AggregationRound,
+    result: Result<anyhow::Result<()>, JoinError>,
+) -> anyhow::Result<()> {
+    match result {
+        Ok(Ok(())) => {
+            vlog::info!("Finished {:?} witness generation", round);
+            Ok(())
+        }
+        Ok(Err(err)) => {
+            vlog::error!("{:?} witness generator failed: {:#}", round, err);
+            Err(err.context(format!("{:?} witness generator failed", round)))
+        }
+        Err(err) => {
+            let panic_message = try_extract_panic_message(err);
+            vlog::error!(
+                "{:?} witness generator task panicked: {}",
+                round,
+                panic_message
+            );
+            Err(anyhow::anyhow!(
+                "{:?} witness generator task panicked: {}",
+                round,
+                panic_message
+            ))
         }
     }
+}
 
-    stop_sender.send(true).ok();
-    vlog::info!("Finished witness generation");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_are_parsed() {
+        let opt = Opt::from_iter([
+            "witness_generator",
+            "--rounds",
+            "basic,leaf_aggregation,node",
+        ]);
+        assert_eq!(
+            opt.rounds,
+            [
+                AggregationRound::BasicCircuits,
+                AggregationRound::LeafAggregation,
+                AggregationRound::NodeAggregation,
+            ]
+        );
+        assert_eq!(
+            rounds_to_run(opt.round, opt.rounds).unwrap(),
+            [
+                AggregationRound::BasicCircuits,
+                AggregationRound::LeafAggregation,
+                AggregationRound::NodeAggregation,
+            ]
+        );
+
+        let opt = Opt::from_iter(["witness_generator", "--round", "scheduler"]);
+        assert_eq!(
+            rounds_to_run(opt.round, opt.rounds).unwrap(),
+            [AggregationRound::Scheduler]
+        );
+
+        let result = Opt::from_iter_safe(["witness_generator", "--rounds", "basic,leaves"]);
+        assert!(result.is_err());
+        let result = Opt::from_iter_safe([
+            "witness_generator",
+            "--round",
+            "scheduler",
+            "--rounds",
+            "basic",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rounds_must_be_specified_once() {
+        assert!(rounds_to_run(None, vec![]).is_err());
+        let rounds = vec![AggregationRound::Scheduler, AggregationRound::Scheduler];
+        assert!(rounds_to_run(None, rounds).is_err());
+    }
 }
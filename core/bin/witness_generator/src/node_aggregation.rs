@@ -1,10 +1,12 @@
+use std::sync::Arc;
 use std::time::Instant;
 
+use anyhow::Context as _;
 use async_trait::async_trait;
 use circuit_definitions::boojum::field::goldilocks::GoldilocksField;
 use circuit_definitions::circuit_definitions::recursion_layer::{
     ZkSyncRecursionLayerProof, ZkSyncRecursionLayerStorageType,
-    ZkSyncRecursionLayerVerificationKey, ZkSyncRecursiveLayerCircuit,
+    ZkSyncRecursionLayerVerificationKey, ZkSyncRecursiveLayerCircuit, RECURSION_ARITY,
 };
 use circuit_definitions::encodings::recursion_request::RecursionQueueSimulator;
 
@@ -15,13 +17,24 @@ use zkevm_test_harness::zkevm_circuits::recursion::leaf_layer::input::RecursionL
 use zksync_vk_setup_data_server_fri::get_recursive_layer_vk_for_circuit_type;
 use zksync_vk_setup_data_server_fri::utils::get_leaf_vk_params;
 
+use crate::compute_pool::{spawn_compute_task, ComputePool};
+use crate::determinism::{run_with_determinism_check, DeterminismCheck, DeterminismMismatch};
+use crate::peak_memory::track_peak_memory;
+use crate::sealed_latency::report_sealed_to_saved_latency;
 use crate::utils::{
-    load_proofs_for_job_ids, save_node_aggregations_artifacts,
-    save_recursive_layer_prover_input_artifacts, AggregationWrapper, FriProofWrapper,
+    classify_store_unavailable, count_pending_witness_jobs,
+    get_recursive_layer_circuit_id_for_base_layer, get_verified, load_proofs_for_job_ids,
+    report_job_pickup, save_node_aggregations_artifacts,
+    save_recursive_layer_prover_input_artifacts, AggregationWrapper, CircuitPosition,
+    FriProofWrapper,
 };
+use zksync_config::configs::FriWitnessGeneratorConfig;
+use zksync_dal::pruned_blobs_log_dal::PrunedBlob;
 use zksync_dal::ConnectionPool;
-use zksync_object_store::{AggregationsKey, ObjectStore, ObjectStoreFactory};
-use zksync_queued_job_processor::JobProcessor;
+use zksync_object_store::{AggregationsKey, ObjectStore, ObjectStoreError, StoredObject};
+use zksync_queued_job_processor::{
+    JobError, JobErrorKind, JobMetadata, JobProcessor, PendingJobsCount, SharedJobProcessorStatus,
+};
 use zksync_types::proofs::NodeAggregationJobMetadata;
 use zksync_types::{proofs::AggregationRound, L1BatchNumber};
 
@@ -29,11 +42,13 @@ pub struct NodeAggregationArtifacts {
     circuit_id: u8,
     block_number: L1BatchNumber,
     depth: u16,
+    fan_in: usize,
     next_aggregations: Vec<(
         u64,
         RecursionQueueSimulator<GoldilocksField>,
         ZkSyncRecursiveLayerCircuit,
     )>,
+    determinism_mismatch: Option<DeterminismMismatch>,
 }
 
 #[derive(Debug)]
@@ -47,6 +62,8 @@ pub struct NodeAggregationWitnessGeneratorJob {
     circuit_id: u8,
     block_number: L1BatchNumber,
     depth: u16,
+    /// Max number of child proofs aggregated by a single node circuit.
+    fan_in: usize,
     aggregations: Vec<(
         u64,
         RecursionQueueSimulator<GoldilocksField>,
@@ -60,18 +77,41 @@ pub struct NodeAggregationWitnessGeneratorJob {
 
 #[derive(Debug)]
 pub struct NodeAggregationWitnessGenerator {
-    object_store: Box<dyn ObjectStore>,
+    config: FriWitnessGeneratorConfig,
+    object_store: Arc<dyn ObjectStore>,
+    connection_pool: ConnectionPool,
     prover_connection_pool: ConnectionPool,
+    status: SharedJobProcessorStatus,
+    /// Pool running witness generation; if not set, the tokio blocking pool is used.
+    compute_pool: Option<ComputePool>,
+    /// IDs of circuits jobs are picked up for; if not set, jobs for all circuits are picked up.
+    circuit_ids: Option<Vec<u8>>,
 }
 
 impl NodeAggregationWitnessGenerator {
-    pub async fn new(
-        store_factory: &ObjectStoreFactory,
+    pub fn new(
+        config: FriWitnessGeneratorConfig,
+        object_store: Arc<dyn ObjectStore>,
+        connection_pool: ConnectionPool,
         prover_connection_pool: ConnectionPool,
+        base_layer_circuit_ids: Option<Vec<u8>>,
     ) -> Self {
+        let compute_pool =
+            ComputePool::from_config(&config, "NodeAggregation", config.node_max_jobs_in_flight());
+        // Node aggregation jobs are keyed by IDs of the leaf circuits they aggregate.
+        let circuit_ids = base_layer_circuit_ids.map(|ids| {
+            ids.into_iter()
+                .map(get_recursive_layer_circuit_id_for_base_layer)
+                .collect()
+        });
         Self {
-            object_store: store_factory.create_store().await,
+            config,
+            object_store,
+            connection_pool,
             prover_connection_pool,
+            status: SharedJobProcessorStatus::default(),
+            compute_pool,
+            circuit_ids,
         }
     }
 
@@ -91,17 +131,36 @@ impl NodeAggregationWitnessGenerator {
             0 => job.leaf_vk,
             _ => job.node_vk,
         };
-        let next_aggregations = create_node_witnesses(
-            job.aggregations,
-            job.proofs,
-            vk,
-            node_vk_commitment,
-            &job.all_leafs_layer_params,
-        );
+        // `create_node_witnesses()` aggregates up to `RECURSION_ARITY` children per circuit,
+        // so smaller fan-in is achieved by feeding it chunks of at most `fan_in` children.
+        let next_aggregations = if job.fan_in == RECURSION_ARITY {
+            create_node_witnesses(
+                job.aggregations,
+                job.proofs,
+                vk,
+                node_vk_commitment,
+                &job.all_leafs_layer_params,
+            )
+        } else {
+            let all_leafs_layer_params = &job.all_leafs_layer_params;
+            split_into_node_chunks(job.aggregations, job.proofs, job.fan_in)
+                .into_iter()
+                .flat_map(|(aggregations, proofs)| {
+                    create_node_witnesses(
+                        aggregations,
+                        proofs,
+                        vk.clone(),
+                        node_vk_commitment,
+                        all_leafs_layer_params,
+                    )
+                })
+                .collect()
+        };
         metrics::histogram!(
                     "prover_fri.witness_generation.witness_generation_time",
                     started_at.elapsed(),
                     "aggregation_round" => format!("{:?}", AggregationRound::NodeAggregation),
+                    "depth" => job.depth.to_string(),
         );
         vlog::info!(
         "Node witness generation for block {} with circuit id {} at depth {} with {} next_aggregations jobs completed in {:?}.",
@@ -115,8 +174,10 @@ impl NodeAggregationWitnessGenerator {
         NodeAggregationArtifacts {
             circuit_id: job.circuit_id,
             block_number: job.block_number,
-            depth: job.depth + 1,
+            depth: next_aggregation_depth(job.depth),
+            fan_in: job.fan_in,
             next_aggregations,
+            determinism_mismatch: None,
         }
     }
 }
@@ -129,25 +190,119 @@ impl JobProcessor for NodeAggregationWitnessGenerator {
 
     const SERVICE_NAME: &'static str = "fri_node_aggregation_witness_generator";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
         let mut prover_connection = self.prover_connection_pool.access_storage().await;
-        let metadata = prover_connection
+        let metadata = match prover_connection
             .fri_witness_generator_dal()
-            .get_next_node_aggregation_job()
-            .await?;
+            .get_next_node_aggregation_job(&self.config.batch_range(), self.circuit_ids.as_deref())
+            .await
+        {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+        report_job_pickup(AggregationRound::NodeAggregation, metadata.circuit_id);
         vlog::info!("Processing node aggregation job {:?}", metadata.id);
-        Some((
+        Ok(Some((
             metadata.id,
-            prepare_job(metadata, &*self.object_store).await,
-        ))
+            prepare_job(
+                metadata,
+                &*self.object_store,
+                &self.prover_connection_pool,
+                self.config.proof_fetch_concurrency(),
+            )
+            .await?,
+        )))
+    }
+
+    async fn get_next_jobs(
+        &self,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(Self::JobId, Self::Job, JobMetadata)>> {
+        let jobs_metadata = self
+            .prover_connection_pool
+            .access_storage()
+            .await
+            .fri_witness_generator_dal()
+            .get_next_node_aggregation_jobs(
+                limit,
+                &self.config.batch_range(),
+                self.circuit_ids.as_deref(),
+            )
+            .await;
+        let mut jobs = Vec::with_capacity(jobs_metadata.len());
+        for metadata in jobs_metadata {
+            let job_id = metadata.id;
+            let job_metadata = JobMetadata::new(Some(metadata.attempts));
+            report_job_pickup(AggregationRound::NodeAggregation, metadata.circuit_id);
+            vlog::info!("Processing node aggregation job {:?}", job_id);
+            match prepare_job(
+                metadata,
+                &*self.object_store,
+                &self.prover_connection_pool,
+                self.config.proof_fetch_concurrency(),
+            )
+            .await
+            {
+                Ok(job) => jobs.push((job_id, job, job_metadata)),
+                // Other claimed jobs are returned regardless, so the failure is persisted right away.
+                Err(err) => {
+                    vlog::error!(
+                        "Failed preparing node aggregation job {}: {:#}",
+                        job_id,
+                        err
+                    );
+                    self.save_failure(job_id, Instant::now(), err.into()).await;
+                }
+            }
+        }
+        Ok(jobs)
+    }
+
+    fn job_pickup_batch_size(&self) -> usize {
+        self.config.job_pickup_batch_size()
+    }
+
+    fn max_jobs_to_process(&self) -> Option<u64> {
+        self.config.max_jobs_to_process
+    }
+
+    fn max_pickups_per_minute(&self) -> Option<u32> {
+        self.config.max_job_pickups_per_minute
+    }
+
+    fn drain_after_empty_polls(&self) -> Option<u32> {
+        self.config.drain_after_empty_polls()
+    }
+
+    fn status(&self) -> Option<&SharedJobProcessorStatus> {
+        Some(&self.status)
+    }
+
+    async fn pending_jobs_count(&self) -> Option<PendingJobsCount> {
+        Some(
+            count_pending_witness_jobs(
+                &self.prover_connection_pool,
+                AggregationRound::NodeAggregation,
+            )
+            .await,
+        )
     }
 
-    async fn save_failure(&self, job_id: u32, _started_at: Instant, error: String) -> () {
+    async fn save_failure(&self, job_id: u32, _started_at: Instant, error: JobError) -> () {
         self.prover_connection_pool
             .access_storage()
             .await
             .fri_witness_generator_dal()
-            .mark_node_aggregation_job_failed(&error, job_id)
+            .mark_node_aggregation_job_failed(&error.to_json(), job_id, error.retryable)
+            .await;
+    }
+
+    async fn release_job(&self, job_id: u32) {
+        self.prover_connection_pool
+            .access_storage()
+            .await
+            .fri_witness_generator_dal()
+            .release_node_aggregation_job(job_id)
             .await;
     }
 
@@ -156,21 +311,46 @@ impl JobProcessor for NodeAggregationWitnessGenerator {
         &self,
         job: NodeAggregationWitnessGeneratorJob,
         started_at: Instant,
-    ) -> tokio::task::JoinHandle<NodeAggregationArtifacts> {
-        tokio::task::spawn_blocking(move || Self::process_job_sync(job, started_at))
+    ) -> tokio::task::JoinHandle<anyhow::Result<NodeAggregationArtifacts>> {
+        let determinism_check = DeterminismCheck::from_config(&self.config);
+        spawn_compute_task(self.compute_pool.as_ref(), move || {
+            let l1_batch_number = job.block_number;
+            track_peak_memory(AggregationRound::NodeAggregation, l1_batch_number, || {
+                let (mut artifacts, determinism_mismatch) = run_with_determinism_check(
+                    determinism_check,
+                    AggregationRound::NodeAggregation,
+                    l1_batch_number,
+                    job,
+                    |job| Self::process_job_sync(job, started_at),
+                    |artifacts| bincode::serialize(&artifacts.next_aggregations).ok(),
+                );
+                artifacts.determinism_mismatch = determinism_mismatch;
+                Ok(artifacts)
+            })
+        })
     }
 
     async fn save_result(
         &self,
         job_id: u32,
         started_at: Instant,
-        artifacts: NodeAggregationArtifacts,
-    ) {
+        mut artifacts: NodeAggregationArtifacts,
+    ) -> anyhow::Result<()> {
+        if let Some(mismatch) = artifacts.determinism_mismatch.take() {
+            let bucket = AggregationWrapper::BUCKET;
+            mismatch
+                .upload_artifacts(&*self.object_store, bucket, job_id)
+                .await;
+        }
         let block_number = artifacts.block_number;
         let circuit_id = artifacts.circuit_id;
         let depth = artifacts.depth;
+        let fan_in = artifacts.fan_in;
         let shall_continue_node_aggregations = artifacts.next_aggregations.len() > 1;
-        let blob_urls = save_artifacts(artifacts, &*self.object_store).await;
+        let blob_urls = save_artifacts(artifacts, &*self.object_store)
+            .await
+            .context("failed saving node aggregation artifacts")
+            .map_err(classify_store_unavailable)?;
         update_database(
             &self.prover_connection_pool,
             started_at,
@@ -178,24 +358,112 @@ impl JobProcessor for NodeAggregationWitnessGenerator {
             block_number,
             depth,
             circuit_id,
+            fan_in,
             blob_urls,
             shall_continue_node_aggregations,
         )
         .await;
+        if !shall_continue_node_aggregations && self.config.prune_leaf_aggregation_artifacts() {
+            // The job is already marked as successful, so pruning errors don't fail it.
+            if let Err(err) = prune_leaf_aggregation_artifacts(
+                &self.prover_connection_pool,
+                &*self.object_store,
+                block_number,
+                circuit_id,
+            )
+            .await
+            {
+                vlog::warn!(
+                    "Failed pruning leaf aggregation artifacts for l1 batch {} / circuit {}: {:#}",
+                    block_number,
+                    circuit_id,
+                    err
+                );
+            }
+        }
+        report_sealed_to_saved_latency(
+            &self.connection_pool,
+            &self.prover_connection_pool,
+            AggregationRound::NodeAggregation,
+            block_number,
+            job_id,
+        )
+        .await;
+        Ok(())
+    }
+}
+
+/// Returns the depth of circuits and aggregations produced by a node aggregation job at `depth`.
+/// They're keyed by the depth of the node aggregation job consuming them, which loads proofs
+/// of prover jobs at its own depth (or of the leaf aggregation round for depth 0).
+fn next_aggregation_depth(depth: u16) -> u16 {
+    depth + 1
+}
+
+/// Returns the fan-in of node aggregation circuits configured for new L1 batches.
+pub fn configured_node_aggregation_fan_in(
+    config: &FriWitnessGeneratorConfig,
+) -> anyhow::Result<usize> {
+    check_node_aggregation_fan_in(config.node_aggregation_fan_in.unwrap_or(RECURSION_ARITY))
+}
+
+fn check_node_aggregation_fan_in(fan_in: usize) -> anyhow::Result<usize> {
+    if !(2..=RECURSION_ARITY).contains(&fan_in) {
+        anyhow::bail!(
+            "node aggregation fan-in {} is out of bounds; expected a value in 2..={}",
+            fan_in,
+            RECURSION_ARITY
+        );
+    }
+    Ok(fan_in)
+}
+
+/// Splits children of node circuits into chunks of at most `fan_in` children, each of which
+/// is aggregated by a single node circuit.
+fn split_into_node_chunks<A, P>(
+    aggregations: Vec<A>,
+    proofs: Vec<P>,
+    fan_in: usize,
+) -> Vec<(Vec<A>, Vec<P>)> {
+    assert_eq!(aggregations.len(), proofs.len());
+    let mut aggregations = aggregations.into_iter();
+    let mut proofs = proofs.into_iter();
+    let mut chunks = vec![];
+    loop {
+        let chunk: Vec<_> = aggregations.by_ref().take(fan_in).collect();
+        if chunk.is_empty() {
+            return chunks;
+        }
+        let chunk_proofs = proofs.by_ref().take(chunk.len()).collect();
+        chunks.push((chunk, chunk_proofs));
     }
 }
 
 async fn prepare_job(
     metadata: NodeAggregationJobMetadata,
     object_store: &dyn ObjectStore,
-) -> NodeAggregationWitnessGeneratorJob {
+    prover_connection_pool: &ConnectionPool,
+    proof_fetch_concurrency: usize,
+) -> anyhow::Result<NodeAggregationWitnessGeneratorJob> {
+    // Jobs created before fan-in was recorded use the capacity of the node circuit.
+    let fan_in = check_node_aggregation_fan_in(metadata.fan_in.unwrap_or(RECURSION_ARITY))
+        .with_context(|| format!("invalid fan-in recorded for node agg {}", metadata.id))?;
     let started_at = Instant::now();
-    let artifacts = get_artifacts(&metadata, object_store).await;
-    let proofs = load_proofs_for_job_ids(&metadata.prover_job_ids_for_proofs, object_store).await;
+    let artifacts = get_artifacts(&metadata, object_store, prover_connection_pool).await?;
+    let proofs = load_proofs_for_job_ids(
+        metadata.block_number,
+        &metadata.prover_job_ids_for_proofs,
+        object_store,
+        proof_fetch_concurrency,
+        AggregationRound::NodeAggregation,
+    )
+    .await
+    .with_context(|| format!("failed loading proofs for node agg {}", metadata.id))?;
     metrics::histogram!(
                     "prover_fri.witness_generation.blob_fetch_time",
                     started_at.elapsed(),
                     "aggregation_round" => format!("{:?}", AggregationRound::NodeAggregation),
+                    "depth" => metadata.depth.to_string(),
     );
     let started_at = Instant::now();
     let leaf_vk = get_recursive_layer_vk_for_circuit_type(metadata.circuit_id);
@@ -207,30 +475,31 @@ async fn prepare_job(
         .into_iter()
         .map(|wrapper| match wrapper {
             FriProofWrapper::Base(_) => {
-                panic!(
+                anyhow::bail!(
                     "Expected only recursive proofs for node agg {}",
                     metadata.id
                 )
             }
-            FriProofWrapper::Recursive(recursive_proof) => recursive_proof,
+            FriProofWrapper::Recursive(recursive_proof) => Ok(recursive_proof),
         })
-        .collect::<Vec<_>>();
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
     metrics::histogram!(
                     "prover_fri.witness_generation.job_preparation_time",
                     started_at.elapsed(),
                     "aggregation_round" => format!("{:?}", AggregationRound::NodeAggregation),
     );
-    NodeAggregationWitnessGeneratorJob {
+    Ok(NodeAggregationWitnessGeneratorJob {
         circuit_id: metadata.circuit_id,
         block_number: metadata.block_number,
         depth: metadata.depth,
+        fan_in,
         aggregations: artifacts.0,
         proofs: recursive_proofs,
         leaf_vk,
         node_vk,
         all_leafs_layer_params: get_leaf_vk_params(),
-    }
+    })
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -241,6 +510,7 @@ async fn update_database(
     block_number: L1BatchNumber,
     depth: u16,
     circuit_id: u8,
+    fan_in: usize,
     blob_urls: BlobUrls,
     shall_continue_node_aggregations: bool,
 ) {
@@ -266,6 +536,7 @@ async fn update_database(
                     Some(dependent_jobs as i32),
                     depth,
                     &blob_urls.node_aggregations_url,
+                    Some(fan_in),
                 )
                 .await;
         }
@@ -297,47 +568,236 @@ async fn update_database(
 async fn get_artifacts(
     metadata: &NodeAggregationJobMetadata,
     object_store: &dyn ObjectStore,
-) -> AggregationWrapper {
+    prover_connection_pool: &ConnectionPool,
+) -> anyhow::Result<AggregationWrapper> {
     let key = AggregationsKey {
         block_number: metadata.block_number,
         circuit_id: metadata.circuit_id,
         depth: metadata.depth,
     };
+    let err = match get_verified(object_store, key).await {
+        Ok(artifacts) => return Ok(artifacts),
+        Err(err) => err,
+    };
+    let is_missing = matches!(err, ObjectStoreError::KeyNotFound(_));
+    if is_missing && metadata.depth == 0 {
+        let encoded_key = AggregationWrapper::encode_key(key);
+        let is_pruned = prover_connection_pool
+            .access_storage()
+            .await
+            .pruned_blobs_log_dal()
+            .is_blob_pruned(
+                metadata.block_number,
+                &AggregationWrapper::BUCKET.to_string(),
+                &encoded_key,
+            )
+            .await;
+        if is_pruned {
+            let message = format!(
+                "inputs pruned, rerun leaf round: leaf aggregation artifacts for l1 batch {} \
+                 and circuit {} were removed after node aggregation for the circuit completed",
+                metadata.block_number, metadata.circuit_id
+            );
+            let mut err =
+                JobError::new(JobErrorKind::MissingInput, message).with_blob_url(encoded_key);
+            // The artifacts won't appear unless the leaf aggregation round is rerun.
+            err.retryable = false;
+            return Err(err.into());
+        }
+    }
+    Err(anyhow::Error::from(err))
+        .with_context(|| format!("node aggregation job artifacts missing: {:?}", key))
+}
+
+/// Removes artifacts of the leaf aggregation round (i.e., inputs of the depth 0 node aggregation
+/// job) for the circuit if all node aggregation jobs for the circuit are successful, and records
+/// them in the `pruned_blobs_log` table. Artifacts are recorded before being removed, so that
+/// a requeued depth 0 job fails with a clear error if they are missing; repeated calls are no-op.
+async fn prune_leaf_aggregation_artifacts(
+    prover_connection_pool: &ConnectionPool,
+    object_store: &dyn ObjectStore,
+    block_number: L1BatchNumber,
+    circuit_id: u8,
+) -> anyhow::Result<()> {
+    let mut prover_connection = prover_connection_pool.access_storage().await;
+    let is_complete = prover_connection
+        .fri_witness_generator_dal()
+        .are_node_aggregation_jobs_successful(block_number, circuit_id)
+        .await;
+    if !is_complete {
+        return Ok(());
+    }
+
+    let key = AggregationsKey {
+        block_number,
+        circuit_id,
+        depth: 0,
+    };
+    let bucket = AggregationWrapper::BUCKET;
+    let encoded_key = AggregationWrapper::encode_key(key);
+    let size_bytes = match object_store.get_raw_size(bucket, &encoded_key).await {
+        Ok(size_bytes) => size_bytes,
+        // The artifacts are already removed.
+        Err(ObjectStoreError::KeyNotFound(_)) => return Ok(()),
+        Err(err) => return Err(err).context("failed getting size of leaf aggregation artifacts"),
+    };
+    let mut pruned_blobs_log = prover_connection.pruned_blobs_log_dal();
+    let is_recorded = pruned_blobs_log
+        .is_blob_pruned(block_number, &bucket.to_string(), &encoded_key)
+        .await;
+    if !is_recorded {
+        let blob = PrunedBlob {
+            bucket: bucket.to_string(),
+            key: encoded_key.clone(),
+            size_bytes,
+        };
+        pruned_blobs_log
+            .insert_pruned_blobs(block_number, &[blob])
+            .await;
+    }
+    drop(prover_connection);
+
     object_store
-        .get(key)
+        .remove_many::<AggregationWrapper>([key])
         .await
-        .unwrap_or_else(|_| panic!("node aggregation job artifacts missing: {:?}", key))
+        .context("failed removing leaf aggregation artifacts")?;
+    vlog::info!(
+        "Pruned leaf aggregation artifacts {} ({} bytes) for l1 batch {} and circuit {}",
+        encoded_key,
+        size_bytes,
+        block_number,
+        circuit_id
+    );
+    metrics::counter!(
+        "prover_fri.witness_generation.pruned_leaf_aggregation_artifacts_bytes",
+        size_bytes
+    );
+    Ok(())
 }
 
 async fn save_artifacts(
     artifacts: NodeAggregationArtifacts,
     object_store: &dyn ObjectStore,
-) -> BlobUrls {
+) -> Result<BlobUrls, ObjectStoreError> {
     let started_at = Instant::now();
-    let aggregations_urls = save_node_aggregations_artifacts(
+    let (base_layer_circuit_id, depth) = (artifacts.circuit_id, artifacts.depth);
+    let circuit_ids_and_urls = save_recursive_layer_prover_input_artifacts(
         artifacts.block_number,
-        artifacts.circuit_id,
-        artifacts.depth,
-        artifacts.next_aggregations.clone(),
+        &artifacts.next_aggregations,
         object_store,
+        |sequence_number| CircuitPosition::Node {
+            base_layer_circuit_id,
+            depth,
+            sequence_number,
+        },
     )
-    .await;
-    let circuit_ids_and_urls = save_recursive_layer_prover_input_artifacts(
+    .await?;
+    let aggregations_urls = save_node_aggregations_artifacts(
         artifacts.block_number,
-        artifacts.next_aggregations,
-        AggregationRound::NodeAggregation,
+        artifacts.circuit_id,
         artifacts.depth,
+        artifacts.next_aggregations,
         object_store,
-        Some(artifacts.circuit_id),
     )
-    .await;
+    .await?;
     metrics::histogram!(
                     "prover_fri.witness_generation.blob_save_time",
                     started_at.elapsed(),
                     "aggregation_round" => format!("{:?}", AggregationRound::NodeAggregation),
     );
-    BlobUrls {
+    Ok(BlobUrls {
         node_aggregations_url: aggregations_urls,
         circuit_ids_and_urls,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zksync_object_store::ObjectStoreFactory;
+
+    #[tokio::test]
+    async fn artifacts_of_depth_1_job_are_keyed_by_next_depth() {
+        let factory = ObjectStoreFactory::mock();
+        let object_store = factory.create_store().await.unwrap();
+        let artifacts = NodeAggregationArtifacts {
+            circuit_id: 3,
+            block_number: L1BatchNumber(1),
+            depth: next_aggregation_depth(1),
+            fan_in: RECURSION_ARITY,
+            next_aggregations: vec![],
+            determinism_mismatch: None,
+        };
+        let blob_urls = save_artifacts(artifacts, &*object_store).await.unwrap();
+
+        // Outputs of the depth 1 job are inputs of the depth 2 job.
+        let expected_key = AggregationWrapper::encode_key(AggregationsKey {
+            block_number: L1BatchNumber(1),
+            circuit_id: 3,
+            depth: 2,
+        });
+        assert_eq!(blob_urls.node_aggregations_url, expected_key);
+        let keys = factory.mock_keys(AggregationWrapper::BUCKET).await;
+        assert_eq!(keys, [expected_key]);
+
+        let position = CircuitPosition::Node {
+            base_layer_circuit_id: 3,
+            depth: next_aggregation_depth(1),
+            sequence_number: 0,
+        };
+        let circuit_key = position.circuit_key(L1BatchNumber(1), 2);
+        assert_eq!(circuit_key.circuit_id, 3);
+        assert_eq!(
+            circuit_key.aggregation_round,
+            AggregationRound::NodeAggregation
+        );
+        assert_eq!(circuit_key.depth, 2);
+    }
+
+    #[test]
+    fn node_aggregation_fan_in_is_bounded_by_node_circuit_capacity() {
+        assert_eq!(check_node_aggregation_fan_in(2).unwrap(), 2);
+        assert_eq!(
+            check_node_aggregation_fan_in(RECURSION_ARITY).unwrap(),
+            RECURSION_ARITY
+        );
+        check_node_aggregation_fan_in(1).unwrap_err();
+        check_node_aggregation_fan_in(RECURSION_ARITY + 1).unwrap_err();
+    }
+
+    #[test]
+    fn children_are_split_into_chunks_with_fan_in_2() {
+        let chunks = split_into_node_chunks(vec![0, 1, 2, 3, 4], vec![10, 11, 12, 13, 14], 2);
+        assert_eq!(
+            chunks,
+            [
+                (vec![0, 1], vec![10, 11]),
+                (vec![2, 3], vec![12, 13]),
+                (vec![4], vec![14]),
+            ]
+        );
+        // Aggregation is complete once a single node circuit remains.
+        let mut circuit_count = 5;
+        let mut depth = 0;
+        while circuit_count > 1 {
+            circuit_count =
+                split_into_node_chunks(vec![(); circuit_count], vec![(); circuit_count], 2).len();
+            depth += 1;
+        }
+        assert_eq!(depth, 3);
+    }
+
+    #[test]
+    fn children_are_split_into_chunks_with_max_fan_in() {
+        let children: Vec<_> = (0..RECURSION_ARITY + 1).collect();
+        let chunks = split_into_node_chunks(children.clone(), children, RECURSION_ARITY);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].0.len(), RECURSION_ARITY);
+        assert_eq!(chunks[0].0, chunks[0].1);
+        assert_eq!(chunks[1].0, [RECURSION_ARITY]);
+
+        let chunks = split_into_node_chunks(vec![0; 3], vec![0; 3], RECURSION_ARITY);
+        assert_eq!(chunks.len(), 1);
+        assert!(split_into_node_chunks::<u8, u8>(vec![], vec![], RECURSION_ARITY).is_empty());
     }
 }
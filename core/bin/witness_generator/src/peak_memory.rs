@@ -0,0 +1,166 @@
+//! Tracking of peak memory usage of witness generation jobs for capacity planning.
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use sysinfo::{get_current_pid, ProcessExt, ProcessRefreshKind, System, SystemExt};
+
+use zksync_types::proofs::AggregationRound;
+use zksync_types::L1BatchNumber;
+
+/// Interval between RSS samples taken during a job. Reading RSS of a single process is cheap,
+/// so sampling has negligible overhead.
+const SAMPLING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Runs CPU-heavy `job` while sampling RSS of the process on a background thread. Once the job
+/// completes, reports the peak RSS in the `prover_fri.witness_generation.peak_memory_bytes` metric
+/// and logs it. RSS is process-wide, so if jobs are processed concurrently, the peak includes
+/// memory used by other jobs; RSS before the job is logged to tell them apart.
+pub fn track_peak_memory<T>(
+    aggregation_round: AggregationRound,
+    l1_batch_number: L1BatchNumber,
+    job: impl FnOnce() -> T,
+) -> T {
+    let sampler = MemorySampler::start(rss_sampler(), SAMPLING_INTERVAL);
+    let output = job();
+    let usage = sampler.stop();
+    if let Some(peak_bytes) = usage.peak_bytes {
+        metrics::histogram!(
+            "prover_fri.witness_generation.peak_memory_bytes",
+            peak_bytes as f64,
+            "aggregation_round" => format!("{:?}", aggregation_round),
+        );
+        vlog::info!(
+            "Peak RSS during {:?} job for l1 batch {}: {} bytes (before the job: {:?} bytes)",
+            aggregation_round,
+            l1_batch_number,
+            peak_bytes,
+            usage.initial_bytes
+        );
+    }
+    output
+}
+
+/// Returns a function reading RSS of the current process in bytes.
+fn rss_sampler() -> impl FnMut() -> Option<u64> + Send + 'static {
+    let pid = get_current_pid().ok();
+    let mut system = System::new();
+    move || {
+        let pid = pid?;
+        system.refresh_process_specifics(pid, ProcessRefreshKind::new());
+        system.process(pid).map(ProcessExt::memory)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MemoryUsage {
+    initial_bytes: Option<u64>,
+    peak_bytes: Option<u64>,
+}
+
+/// Samples memory usage on a background thread until stopped. If the sampler is dropped without
+/// being stopped (e.g., because the job panicked), the thread exits after taking the last sample.
+#[derive(Debug)]
+struct MemorySampler {
+    initial_bytes: Option<u64>,
+    stop_sender: mpsc::Sender<()>,
+    handle: thread::JoinHandle<Option<u64>>,
+}
+
+impl MemorySampler {
+    fn start(mut sample: impl FnMut() -> Option<u64> + Send + 'static, interval: Duration) -> Self {
+        let initial_bytes = sample();
+        let (stop_sender, stop_receiver) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("witness-memory-sampler".to_owned())
+            .spawn(move || {
+                let mut peak_bytes = initial_bytes;
+                loop {
+                    let is_stopped = !matches!(
+                        stop_receiver.recv_timeout(interval),
+                        Err(mpsc::RecvTimeoutError::Timeout)
+                    );
+                    peak_bytes = peak_bytes.max(sample());
+                    if is_stopped {
+                        return peak_bytes;
+                    }
+                }
+            })
+            .expect("failed spawning memory sampler thread");
+        Self {
+            initial_bytes,
+            stop_sender,
+            handle,
+        }
+    }
+
+    /// Stops sampling after taking the last sample and returns the observed memory usage.
+    fn stop(self) -> MemoryUsage {
+        self.stop_sender.send(()).ok();
+        // The thread only panics if sampling panics, in which case the peak is unknown.
+        let peak_bytes = self.handle.join().unwrap_or(None);
+        MemoryUsage {
+            initial_bytes: self.initial_bytes,
+            peak_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn sampler_reports_peak_usage() {
+        let mut samples = vec![100, 300, 200].into_iter();
+        let sampler = MemorySampler::start(move || samples.next(), Duration::from_millis(1));
+        thread::sleep(Duration::from_millis(50));
+        let usage = sampler.stop();
+        assert_eq!(
+            usage,
+            MemoryUsage {
+                initial_bytes: Some(100),
+                peak_bytes: Some(300),
+            }
+        );
+
+        // The last sample is taken when the sampler is stopped.
+        let mut samples = vec![100, 300].into_iter();
+        let sampler = MemorySampler::start(move || samples.next(), Duration::from_secs(60));
+        assert_eq!(sampler.stop().peak_bytes, Some(300));
+
+        let sampler = MemorySampler::start(|| None, Duration::from_millis(1));
+        assert_eq!(sampler.stop().peak_bytes, None);
+    }
+
+    #[test]
+    fn sampler_stops_sampling_when_stopped() {
+        let sample_count = Arc::new(AtomicUsize::new(0));
+        let sample = {
+            let sample_count = sample_count.clone();
+            move || Some(sample_count.fetch_add(1, Ordering::SeqCst) as u64)
+        };
+        let sampler = MemorySampler::start(sample, Duration::from_millis(1));
+        thread::sleep(Duration::from_millis(10));
+        sampler.stop();
+        let final_count = sample_count.load(Ordering::SeqCst);
+        assert!(final_count >= 2, "{}", final_count);
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(sample_count.load(Ordering::SeqCst), final_count);
+    }
+
+    #[test]
+    fn rss_of_current_process_is_sampled() {
+        let mut sample_rss = rss_sampler();
+        let rss = sample_rss().unwrap();
+        assert!(rss > 0);
+        assert_eq!(
+            track_peak_memory(AggregationRound::Scheduler, L1BatchNumber(1), || 42),
+            42
+        );
+    }
+}
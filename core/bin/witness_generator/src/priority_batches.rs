@@ -0,0 +1,51 @@
+//! L1 batches that the scheduler witness generator processes before other batches.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+use zksync_types::L1BatchNumber;
+
+/// Parses L1 batch numbers separated by commas or whitespace.
+fn parse_priority_batches(contents: &str) -> anyhow::Result<Vec<L1BatchNumber>> {
+    contents
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(|number| {
+            number
+                .parse()
+                .with_context(|| format!("invalid L1 batch number `{}`", number))
+        })
+        .collect()
+}
+
+pub fn load_priority_batches(path: &Path) -> anyhow::Result<Vec<L1BatchNumber>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed reading priority batches from {:?}", path))?;
+    parse_priority_batches(&contents)
+        .with_context(|| format!("failed parsing priority batches from {:?}", path))
+}
+
+/// Re-reads priority batches from the file every time the process receives SIGHUP. If the file
+/// cannot be read, the error is logged and the previous batches are kept.
+pub fn spawn_reload_on_sighup(
+    path: PathBuf,
+    sender: watch::Sender<Vec<L1BatchNumber>>,
+) -> anyhow::Result<()> {
+    let mut hangups = signal(SignalKind::hangup()).context("failed subscribing to SIGHUP")?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match load_priority_batches(&path) {
+                Ok(batches) => {
+                    vlog::info!("Reloaded priority batches: {:?}", batches);
+                    // The generator holds a receiver for as long as it runs.
+                    sender.send(batches).ok();
+                }
+                Err(err) => vlog::error!("Failed reloading priority batches: {:#}", err),
+            }
+        }
+    });
+    Ok(())
+}
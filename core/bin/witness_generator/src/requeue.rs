@@ -0,0 +1,99 @@
+//! Admin command requeueing the scheduler job for an L1 batch.
+
+use anyhow::Context as _;
+
+use crate::scheduler::scheduler_circuit_key;
+use crate::utils::{find_missing_proofs, CircuitWrapper, DEFAULT_FETCH_CONCURRENCY};
+use zksync_dal::ConnectionPool;
+use zksync_object_store::{ObjectStore, ObjectStoreError, StoredObject};
+use zksync_types::L1BatchNumber;
+
+/// Returns the scheduler job for the L1 batch to the queue with its attempts and error cleared,
+/// so that the scheduler witness is regenerated, e.g. after a bad node proof was fixed.
+/// The prover job for the previous scheduler circuit is removed, and is queued anew once
+/// the circuit is regenerated.
+///
+/// If `delete_circuit` is set, the previously saved scheduler circuit is removed; otherwise,
+/// the regenerated circuit is not uploaded if it differs from the saved one. The job is not
+/// requeued if the scheduler proof was already generated, unless `force` is set.
+pub async fn requeue_scheduler_job(
+    prover_connection_pool: &ConnectionPool,
+    object_store: &dyn ObjectStore,
+    l1_batch_number: L1BatchNumber,
+    delete_circuit: bool,
+    force: bool,
+) -> anyhow::Result<()> {
+    let mut prover_connection = prover_connection_pool.access_storage().await;
+    let status = prover_connection
+        .fri_witness_generator_dal()
+        .get_scheduler_job_status(l1_batch_number)
+        .await
+        .with_context(|| format!("no scheduler job for l1 batch {l1_batch_number}"))?;
+    let proof_status = prover_connection
+        .fri_prover_jobs_dal()
+        .get_scheduler_prover_job_status(l1_batch_number)
+        .await;
+    if proof_status.as_deref() == Some("successful") && !force {
+        anyhow::bail!(
+            "scheduler proof for l1 batch {} was already generated and may have been submitted; \
+             pass --force to requeue the job anyway",
+            l1_batch_number
+        );
+    }
+
+    let proof_jobs = prover_connection
+        .fri_scheduler_dependency_tracker_dal()
+        .try_get_final_prover_job_ids_for(l1_batch_number)
+        .await
+        .with_context(|| {
+            format!("final node prover jobs for l1 batch {l1_batch_number} are not tracked")
+        })?;
+    let proof_job_ids: Vec<_> = proof_jobs.iter().map(|&(_, job_id)| job_id).collect();
+    let missing_job_ids =
+        find_missing_proofs(&proof_job_ids, object_store, DEFAULT_FETCH_CONCURRENCY)
+            .await
+            .context("failed checking node proofs")?;
+    if !missing_job_ids.is_empty() {
+        anyhow::bail!(
+            "proofs for final node prover jobs {:?} of l1 batch {} are missing",
+            missing_job_ids,
+            l1_batch_number
+        );
+    }
+
+    // The circuit is removed before the job is requeued, so that it cannot remove
+    // a regenerated circuit.
+    if delete_circuit {
+        let key = CircuitWrapper::encode_key(scheduler_circuit_key(l1_batch_number));
+        match object_store.remove_raw(CircuitWrapper::BUCKET, &key).await {
+            Ok(()) | Err(ObjectStoreError::KeyNotFound(_)) => {
+                vlog::info!("Removed scheduler circuit {}", key);
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed removing circuit {key}"));
+            }
+        }
+    }
+
+    let mut transaction = prover_connection.start_transaction().await;
+    transaction
+        .fri_witness_generator_dal()
+        .requeue_scheduler_job(l1_batch_number)
+        .await;
+    let removed_prover_job = transaction
+        .fri_prover_jobs_dal()
+        .remove_scheduler_prover_job(l1_batch_number)
+        .await;
+    transaction.commit().await;
+    vlog::info!(
+        "Requeued scheduler job for l1 batch {} (previous status: {}, scheduler prover job {})",
+        l1_batch_number,
+        status,
+        if removed_prover_job {
+            "removed"
+        } else {
+            "not found"
+        }
+    );
+    Ok(())
+}
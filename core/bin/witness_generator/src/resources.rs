@@ -0,0 +1,124 @@
+//! Pre-flight checks of resources available to the witness generator.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use sysinfo::{DiskExt, System, SystemExt};
+
+/// Resources available to the witness generator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvailableResources {
+    pub memory_bytes: u64,
+    /// Free space on the disk holding the working directory, if the disk is known.
+    pub disk_bytes: Option<u64>,
+}
+
+/// Source of [`AvailableResources`]. Abstracted so that resource checks don't depend on the machine.
+pub trait ResourceMonitor: fmt::Debug + Send + Sync {
+    fn available_resources(&self) -> AvailableResources;
+}
+
+/// [`ResourceMonitor`] measuring the resources of the machine the process runs on.
+#[derive(Debug)]
+pub struct SysinfoResourceMonitor {
+    working_dir: PathBuf,
+    system: Mutex<System>,
+}
+
+impl SysinfoResourceMonitor {
+    pub fn new() -> Self {
+        Self {
+            working_dir: std::env::current_dir().unwrap_or_default(),
+            system: Mutex::new(System::new()),
+        }
+    }
+}
+
+impl Default for SysinfoResourceMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResourceMonitor for SysinfoResourceMonitor {
+    fn available_resources(&self) -> AvailableResources {
+        let mut system = self.system.lock().unwrap();
+        system.refresh_memory();
+        system.refresh_disks_list();
+        // Mount points may be nested, so the disk with the longest matching mount point is used.
+        let disk_bytes = system
+            .disks()
+            .iter()
+            .filter(|disk| self.working_dir.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len())
+            .map(|disk| disk.available_space());
+        AvailableResources {
+            memory_bytes: system.available_memory(),
+            disk_bytes,
+        }
+    }
+}
+
+/// Minimum resources required to accept a job.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceRequirements {
+    pub min_memory_bytes: Option<u64>,
+    pub min_disk_bytes: Option<u64>,
+}
+
+impl ResourceRequirements {
+    pub fn is_empty(&self) -> bool {
+        self.min_memory_bytes.is_none() && self.min_disk_bytes.is_none()
+    }
+
+    /// Returns `true` if the resources are sufficient. Free disk space is not checked
+    /// if the disk is unknown.
+    pub fn are_satisfied_by(&self, resources: &AvailableResources) -> bool {
+        let has_memory = self
+            .min_memory_bytes
+            .map_or(true, |min_bytes| resources.memory_bytes >= min_bytes);
+        let has_disk_space = match (self.min_disk_bytes, resources.disk_bytes) {
+            (Some(min_bytes), Some(disk_bytes)) => disk_bytes >= min_bytes,
+            _ => true,
+        };
+        has_memory && has_disk_space
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1 << 30;
+
+    #[test]
+    fn resource_requirements_are_checked() {
+        let requirements = ResourceRequirements {
+            min_memory_bytes: Some(64 * GB),
+            min_disk_bytes: Some(100 * GB),
+        };
+        let resources = AvailableResources {
+            memory_bytes: 80 * GB,
+            disk_bytes: Some(200 * GB),
+        };
+        assert!(requirements.are_satisfied_by(&resources));
+
+        let low_memory = AvailableResources {
+            memory_bytes: 16 * GB,
+            ..resources
+        };
+        assert!(!requirements.are_satisfied_by(&low_memory));
+        let low_disk_space = AvailableResources {
+            disk_bytes: Some(10 * GB),
+            ..resources
+        };
+        assert!(!requirements.are_satisfied_by(&low_disk_space));
+        let unknown_disk = AvailableResources {
+            disk_bytes: None,
+            ..resources
+        };
+        assert!(requirements.are_satisfied_by(&unknown_disk));
+        assert!(ResourceRequirements::default().are_satisfied_by(&low_memory));
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::future::Future;
+
+use zksync_queued_job_processor::RetryPolicy;
+
+/// Runs `operation` until it succeeds, fails with an error that is not transient,
+/// or the attempts allowed by the `policy` are exhausted. Returns the last error in the latter cases.
+pub async fn retry_transient<T, E, Fut>(
+    policy: RetryPolicy,
+    description: &str,
+    is_transient: impl Fn(&E) -> bool,
+    mut operation: impl FnMut() -> Fut,
+) -> Result<T, E>
+where
+    E: fmt::Display,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        let err = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        if !is_transient(&err) || attempt >= policy.max_attempts {
+            return Err(err);
+        }
+        let backoff = policy.backoff_after(attempt);
+        vlog::warn!(
+            "Transient error {} (attempt {}/{}), retrying in {:?}: {}",
+            description,
+            attempt,
+            policy.max_attempts,
+            backoff,
+            err
+        );
+        metrics::counter!("prover_fri.witness_generation.transient_error_retries", 1);
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use zksync_object_store::{Bucket, ObjectStore, ObjectStoreError, ObjectStoreFactory};
+
+    use super::*;
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        base_backoff: Duration::from_millis(1),
+    };
+
+    /// Object store failing the first `failures` puts with the specified error.
+    #[derive(Debug)]
+    struct FlakyObjectStore {
+        inner: Box<dyn ObjectStore>,
+        failures: u32,
+        error: fn() -> ObjectStoreError,
+        put_attempts: AtomicU32,
+    }
+
+    impl FlakyObjectStore {
+        async fn new(failures: u32, error: fn() -> ObjectStoreError) -> Self {
+            Self {
+                inner: ObjectStoreFactory::mock().create_store().await.unwrap(),
+                failures,
+                error,
+                put_attempts: AtomicU32::new(0),
+            }
+        }
+
+        async fn put_with_retries(&self) -> Result<(), ObjectStoreError> {
+            retry_transient(
+                POLICY,
+                "saving test blob",
+                ObjectStoreError::is_transient,
+                || self.put_raw(Bucket::ProverJobsFri, "test.bin", vec![1, 2, 3]),
+            )
+            .await
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStore for FlakyObjectStore {
+        async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+            self.inner.get_raw(bucket, key).await
+        }
+
+        async fn put_raw(
+            &self,
+            bucket: Bucket,
+            key: &str,
+            value: Vec<u8>,
+        ) -> Result<(), ObjectStoreError> {
+            let attempt = self.put_attempts.fetch_add(1, Ordering::SeqCst);
+            if attempt < self.failures {
+                return Err((self.error)());
+            }
+            self.inner.put_raw(bucket, key, value).await
+        }
+
+        async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+            self.inner.remove_raw(bucket, key).await
+        }
+    }
+
+    fn network_error() -> ObjectStoreError {
+        ObjectStoreError::Transport("503 Service Unavailable".into())
+    }
+
+    fn serialization_error() -> ObjectStoreError {
+        ObjectStoreError::Serialization("invalid blob".into())
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried() {
+        let store = FlakyObjectStore::new(2, network_error).await;
+        store.put_with_retries().await.unwrap();
+
+        assert_eq!(store.put_attempts.load(Ordering::SeqCst), 3);
+        let blob = store
+            .get_raw(Bucket::ProverJobsFri, "test.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn retries_are_bounded() {
+        let store = FlakyObjectStore::new(3, network_error).await;
+        let err = store.put_with_retries().await.unwrap_err();
+
+        assert!(matches!(err, ObjectStoreError::Transport(_)), "{err}");
+        assert_eq!(store.put_attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let store = FlakyObjectStore::new(1, serialization_error).await;
+        let err = store.put_with_retries().await.unwrap_err();
+
+        assert!(matches!(err, ObjectStoreError::Serialization(_)), "{err}");
+        assert_eq!(store.put_attempts.load(Ordering::SeqCst), 1);
+    }
+}
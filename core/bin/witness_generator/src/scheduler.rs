@@ -1,153 +1,854 @@
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
+use std::panic::{self, AssertUnwindSafe};
 
-use std::time::Instant;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use tokio::sync::watch;
+
+use anyhow::Context as _;
 use async_trait::async_trait;
+use chrono::Utc;
+use circuit_definitions::boojum::config::DevCSConfig;
+use circuit_definitions::boojum::cs::cs_builder::new_builder;
+use circuit_definitions::boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+use circuit_definitions::boojum::cs::implementations::pow::NoPow;
 use circuit_definitions::boojum::field::goldilocks::{GoldilocksExt2, GoldilocksField};
 use circuit_definitions::boojum::gadgets::recursion::recursive_tree_hasher::CircuitGoldilocksPoseidon2Sponge;
-use circuit_definitions::circuit_definitions::recursion_layer::scheduler::SchedulerCircuit;
+use circuit_definitions::boojum::worker::Worker;
+use circuit_definitions::circuit_definitions::recursion_layer::scheduler::{
+    SchedulerCircuit, ZkSyncSchedulerCircuit,
+};
 use circuit_definitions::circuit_definitions::recursion_layer::{
     ZkSyncRecursionLayerStorageType, ZkSyncRecursionLayerVerificationKey, ZkSyncRecursionProof,
     ZkSyncRecursiveLayerCircuit, SCHEDULER_CAPACITY,
 };
 use circuit_definitions::recursion_layer_proof_config;
+use circuit_definitions::zkevm_circuits::recursion::leaf_layer::input::RecursionLeafParametersWitness;
+use circuit_definitions::zkevm_circuits::scheduler::aux::BaseLayerCircuitType;
 use circuit_definitions::zkevm_circuits::scheduler::input::SchedulerCircuitInstanceWitness;
 use circuit_definitions::zkevm_circuits::scheduler::SchedulerConfig;
-use zksync_vk_setup_data_server_fri::get_recursive_layer_vk_for_circuit_type;
-use zksync_vk_setup_data_server_fri::utils::get_leaf_vk_params;
+use zkevm_test_harness::prover_utils::verify_recursion_layer_proof_for_type;
+use zksync_vk_setup_data_server_fri::utils::try_get_leaf_vk_params_from_dir;
+use zksync_vk_setup_data_server_fri::{
+    get_base_vk_path, get_vk_path_for_protocol_version, try_get_recursive_layer_vk_from_dir,
+};
 
+use crate::compute_pool::{spawn_compute_task, ComputePool};
+use crate::determinism::{run_with_determinism_check, DeterminismCheck, DeterminismMismatch};
+use crate::peak_memory::track_peak_memory;
+use crate::resources::{ResourceMonitor, ResourceRequirements, SysinfoResourceMonitor};
+use crate::retry::retry_transient;
+use crate::sealed_latency::report_sealed_to_saved_latency;
 use crate::utils::{
-    load_proofs_for_job_ids, CircuitWrapper, FriProofWrapper, SchedulerPartialInputWrapper,
+    classify_store_unavailable, count_pending_witness_jobs, find_missing_proofs,
+    get_basic_artifact_section, get_basic_artifacts_manifest, is_store_unavailable,
+    load_bundled_proofs, load_proofs_for_job_ids, put_artifact, report_circuits_size,
+    BasicArtifactsManifest, CircuitPosition, CircuitWrapper, FriProofWrapper,
+    SchedulerPartialInputWrapper,
+};
+use crate::vk_cache::VkCache;
+use zksync_config::configs::FriWitnessGeneratorConfig;
+use zksync_dal::fri_prover_job_notifications::NewProverJobNotification;
+use zksync_dal::fri_witness_generator_dal::FriWitnessJobStatus;
+use zksync_dal::{ConnectionPool, SqlxError};
+use zksync_object_store::{FriCircuitKey, ObjectStore, ObjectStoreError, StoredObject};
+use zksync_queued_job_processor::{
+    JobContext, JobError, JobErrorKind, JobFailureContext, JobMetadata, JobMetrics, JobProcessor,
+    PendingJobsCount, PollingBackoff, RetryPolicy, SharedJobProcessorStatus,
 };
-use zksync_dal::ConnectionPool;
-use zksync_object_store::{FriCircuitKey, ObjectStore, ObjectStoreFactory};
-use zksync_queued_job_processor::JobProcessor;
-use zksync_types::proofs::AggregationRound;
-use zksync_types::L1BatchNumber;
+use zksync_types::commitment::BlockMetaParameters;
+use zksync_types::proofs::{
+    AggregationRound, JobErrorHistoryEntry, SchedulerCommitments, SchedulerJobLease,
+    SchedulerProofWitnessSource,
+};
+use zksync_types::{L1BatchNumber, H256};
+
+/// Statuses of final node prover jobs that block picking scheduler jobs.
+const BLOCKING_PROVER_JOB_STATUSES: &[&str] = &["queued", "in_progress", "failed"];
 
 pub struct SchedulerArtifacts {
     scheduler_circuit: ZkSyncRecursiveLayerCircuit,
+    proof_witness_sources: Vec<SchedulerProofWitnessSource>,
+    commitments: SchedulerCommitments,
+    determinism_mismatch: Option<DeterminismMismatch>,
+}
+
+/// Verification keys used to generate scheduler witnesses for a certain protocol version.
+struct SchedulerVks {
+    node_vk: ZkSyncRecursionLayerVerificationKey,
+    leaf_layer_parameters: Vec<(u8, RecursionLeafParametersWitness<GoldilocksField>)>,
 }
 
 #[derive(Clone)]
 pub struct SchedulerWitnessGeneratorJob {
     block_number: L1BatchNumber,
-    scheduler_witness: SchedulerCircuitInstanceWitness<
-        GoldilocksField,
-        CircuitGoldilocksPoseidon2Sponge,
-        GoldilocksExt2,
-    >,
+    scheduler_witness: SchedulerWitness,
     node_vk: ZkSyncRecursionLayerVerificationKey,
+    proof_witness_sources: Vec<SchedulerProofWitnessSource>,
 }
 
 #[derive(Debug)]
 pub struct SchedulerWitnessGenerator {
-    object_store: Box<dyn ObjectStore>,
+    config: FriWitnessGeneratorConfig,
+    object_store: Arc<dyn ObjectStore>,
+    connection_pool: ConnectionPool,
     prover_connection_pool: ConnectionPool,
+    status: SharedJobProcessorStatus,
+    /// Pool running witness generation; if not set, the tokio blocking pool is used.
+    compute_pool: Option<ComputePool>,
+    resource_monitor: Box<dyn ResourceMonitor>,
+    resource_requirements: ResourceRequirements,
+    /// L1 batches, jobs for which are picked up before other jobs; may be updated at runtime.
+    priority_batches: watch::Receiver<Vec<L1BatchNumber>>,
+    /// L1 batch to start looking for the next job from in dry-run mode, in which jobs aren't locked.
+    next_dry_run_l1_batch: AtomicU32,
+    vks: VkCache<SchedulerVks>,
 }
 
 impl SchedulerWitnessGenerator {
-    pub async fn new(
-        store_factory: &ObjectStoreFactory,
+    pub fn new(
+        config: FriWitnessGeneratorConfig,
+        object_store: Arc<dyn ObjectStore>,
+        connection_pool: ConnectionPool,
         prover_connection_pool: ConnectionPool,
+        priority_batches: watch::Receiver<Vec<L1BatchNumber>>,
     ) -> Self {
+        const GB: u64 = 1 << 30;
+
+        let resource_requirements = ResourceRequirements {
+            min_memory_bytes: config
+                .scheduler_min_available_memory_gb
+                .map(|gb| gb.saturating_mul(GB)),
+            min_disk_bytes: config
+                .scheduler_min_free_disk_space_gb
+                .map(|gb| gb.saturating_mul(GB)),
+        };
+        let compute_pool =
+            ComputePool::from_config(&config, "Scheduler", config.scheduler_max_jobs_in_flight());
         Self {
-            object_store: store_factory.create_store().await,
+            config,
+            object_store,
+            connection_pool,
             prover_connection_pool,
+            status: SharedJobProcessorStatus::default(),
+            compute_pool,
+            resource_monitor: Box::new(SysinfoResourceMonitor::new()),
+            resource_requirements,
+            priority_batches,
+            next_dry_run_l1_batch: AtomicU32::new(0),
+            vks: VkCache::new("scheduler"),
+        }
+    }
+
+    /// Saves the scheduler circuit and queues a prover job for it, retrying on transient errors.
+    /// Returns `Ok(false)` if the lease was lost.
+    async fn try_save_result(
+        &self,
+        job_id: &SchedulerJobLease,
+        started_at: Instant,
+        artifacts: SchedulerArtifacts,
+    ) -> anyhow::Result<bool> {
+        let SchedulerArtifacts {
+            scheduler_circuit,
+            proof_witness_sources,
+            commitments,
+            ..
+        } = artifacts;
+        // If the lease turns out to be lost below, the circuit is overwritten by the replica
+        // holding the lease; it's generated from the same inputs, so it's fine.
+        let (circuit_blob_url, circuit_blob_size) = save_scheduler_circuit(
+            job_id.l1_batch_number,
+            scheduler_circuit,
+            &*self.object_store,
+        )
+        .await
+        .context("failed saving scheduler circuit")?;
+        report_circuits_size(
+            job_id.l1_batch_number,
+            AggregationRound::Scheduler,
+            1,
+            circuit_blob_size,
+        );
+        let retry_policy = RetryPolicy {
+            max_attempts: self.config.save_result_max_attempts(),
+            base_backoff: self.config.save_result_retry_backoff(),
+        };
+        retry_transient(
+            retry_policy,
+            "saving scheduler job result to DB",
+            zksync_dal::is_transient_error,
+            || {
+                self.save_prover_job(
+                    job_id,
+                    started_at,
+                    &circuit_blob_url,
+                    circuit_blob_size,
+                    &proof_witness_sources,
+                    &commitments,
+                )
+            },
+        )
+        .await
+        .context("failed saving scheduler job result to DB")
+    }
+
+    /// Marks the job as successful, records the sources of its proof witnesses and commitments
+    /// exposed by the circuit, and queues the prover job for the scheduler circuit, notifying
+    /// provers about it if configured. Returns `Ok(false)` if the lease was lost, in which case
+    /// nothing is updated.
+    async fn save_prover_job(
+        &self,
+        job_id: &SchedulerJobLease,
+        started_at: Instant,
+        circuit_blob_url: &str,
+        circuit_blob_size: u64,
+        proof_witness_sources: &[SchedulerProofWitnessSource],
+        commitments: &SchedulerCommitments,
+    ) -> Result<bool, SqlxError> {
+        let mut prover_connection = self.prover_connection_pool.access_storage().await;
+        let mut transaction = prover_connection.try_start_transaction().await?;
+        // The job is updated first, so that no prover job is inserted if the lease is lost.
+        let has_lease = transaction
+            .fri_witness_generator_dal()
+            .try_mark_scheduler_job_as_successful(job_id, started_at.elapsed())
+            .await?;
+        if !has_lease {
+            // The transaction is rolled back on drop.
+            return Ok(false);
         }
+        transaction
+            .fri_witness_generator_dal()
+            .try_set_scheduler_proof_witness_sources(job_id.l1_batch_number, proof_witness_sources)
+            .await?;
+        transaction
+            .fri_witness_generator_dal()
+            .try_save_scheduler_commitments(job_id.l1_batch_number, commitments)
+            .await?;
+        // Marking the job and inserting the prover job are idempotent, so it's fine to retry them
+        // if the commit has succeeded, but its outcome has been lost.
+        let circuit_key = scheduler_circuit_key(job_id.l1_batch_number);
+        transaction
+            .fri_prover_jobs_dal()
+            .try_insert_prover_job(
+                circuit_key.block_number,
+                circuit_key.circuit_id,
+                circuit_key.depth,
+                circuit_key.sequence_number,
+                circuit_key.aggregation_round,
+                circuit_blob_url,
+                false,
+            )
+            .await?;
+        transaction
+            .fri_prover_jobs_dal()
+            .try_set_circuit_blob_size(
+                circuit_key.block_number,
+                circuit_key.circuit_id,
+                circuit_key.depth,
+                circuit_key.sequence_number,
+                circuit_key.aggregation_round,
+                circuit_blob_size,
+            )
+            .await?;
+        if self.config.notify_new_prover_jobs() {
+            // The notification is only delivered once the transaction is committed.
+            transaction
+                .fri_prover_jobs_dal()
+                .try_notify_new_prover_job(NewProverJobNotification {
+                    l1_batch_number: circuit_key.block_number,
+                    circuit_id: circuit_key.circuit_id,
+                    aggregation_round: circuit_key.aggregation_round,
+                })
+                .await?;
+        }
+        transaction.try_commit().await?;
+        Ok(true)
     }
 
+    /// If `check_circuit` is set, the scheduler circuit is checked to be satisfied by its witness
+    /// before it's returned; the check roughly doubles CPU time of the job.
     fn process_job_sync(
         job: SchedulerWitnessGeneratorJob,
         started_at: Instant,
-    ) -> SchedulerArtifacts {
+        check_circuit: bool,
+        context: &JobContext,
+    ) -> anyhow::Result<SchedulerArtifacts> {
         vlog::info!(
-            "Starting fri witness generation of type {:?} for block {}",
-            AggregationRound::Scheduler,
+            aggregation_round = ?AggregationRound::Scheduler,
+            attempts = ?context.metadata().attempts,
+            "Starting fri witness generation for block {}",
+            job.block_number.0
+        );
+        let (scheduler_circuit, commitments) =
+            build_scheduler_circuit(job.scheduler_witness, job.node_vk);
+        vlog::info!(
+            "Commitments exposed by scheduler circuit for block {}: {:?}",
+            job.block_number.0,
+            commitments
+        );
+        context.check_abort("circuit assembly")?;
+        if check_circuit {
+            check_scheduler_circuit(job.block_number, &scheduler_circuit)?;
+            context.check_abort("circuit check")?;
+        }
+        vlog::info!(
+            elapsed = ?started_at.elapsed(),
+            "Scheduler generation for block {} is complete",
             job.block_number.0
         );
-        let config = SchedulerConfig {
-            proof_config: recursion_layer_proof_config(),
-            vk_fixed_parameters: job.node_vk.into_inner().fixed_parameters,
-            capacity: SCHEDULER_CAPACITY,
-            _marker: std::marker::PhantomData,
-        };
 
-        let scheduler_circuit = SchedulerCircuit {
-            witness: job.scheduler_witness,
-            config,
-            transcript_params: (),
-            _marker: std::marker::PhantomData,
-        };
-        metrics::histogram!(
-                    "prover_fri.witness_generation.witness_generation_time",
-                    started_at.elapsed(),
-                    "aggregation_round" => format!("{:?}", AggregationRound::Scheduler),
+        let scheduler_circuit = ZkSyncRecursiveLayerCircuit::SchedulerCircuit(scheduler_circuit);
+        context.check_abort("witness generation")?;
+        Ok(SchedulerArtifacts {
+            scheduler_circuit,
+            proof_witness_sources: job.proof_witness_sources,
+            commitments,
+            determinism_mismatch: None,
+        })
+    }
+
+    /// Loads proofs for the specified prover jobs, with up to `proof_fetch_concurrency` proofs
+    /// being fetched at a time.
+    async fn load_proofs(
+        &self,
+        l1_batch_number: L1BatchNumber,
+        proof_job_ids: &[u32],
+    ) -> Result<Vec<FriProofWrapper>, JobError> {
+        let concurrency = self.config.proof_fetch_concurrency();
+        // Fail fast listing all missing proofs rather than downloading proofs until
+        // the first missing one.
+        let missing_job_ids = find_missing_proofs(proof_job_ids, &*self.object_store, concurrency)
+            .await
+            .map_err(|err| {
+                let message =
+                    format!("failed checking proofs for scheduler l1 batch {l1_batch_number}");
+                object_store_job_error(err.inner, message).with_blob_url(err.key)
+            })?;
+        if let Some(&first_missing_job_id) = missing_job_ids.first() {
+            let message = format!(
+                "proofs for {} of {} prover jobs are missing for scheduler l1 batch {}; \
+                 missing job IDs: {:?}",
+                missing_job_ids.len(),
+                proof_job_ids.len(),
+                l1_batch_number,
+                missing_job_ids
+            );
+            return Err(JobError::new(JobErrorKind::MissingInput, message)
+                .with_blob_url(FriProofWrapper::encode_key(first_missing_job_id)));
+        }
+
+        load_proofs_for_job_ids(
+            l1_batch_number,
+            proof_job_ids,
+            &*self.object_store,
+            concurrency,
+            AggregationRound::Scheduler,
+        )
+        .await
+        .map_err(|err| {
+            let message = format!("failed loading proofs for scheduler l1 batch {l1_batch_number}");
+            object_store_job_error(err.inner, message).with_blob_url(err.key)
+        })
+    }
+
+    /// Returns `true` if the job has failed because its partial input was removed by the blob
+    /// pruner, in which case it can never succeed. The pruner only removes blobs of L1 batches
+    /// proven on L1, so the job isn't needed anyway.
+    async fn is_partial_input_pruned(
+        &self,
+        l1_batch_number: L1BatchNumber,
+        err: &JobError,
+    ) -> bool {
+        let key = SchedulerPartialInputWrapper::encode_key(l1_batch_number);
+        if err.kind != JobErrorKind::MissingInput || err.blob_url.as_deref() != Some(key.as_str()) {
+            return false;
+        }
+        self.prover_connection_pool
+            .access_storage()
+            .await
+            .pruned_blobs_log_dal()
+            .is_blob_pruned(
+                l1_batch_number,
+                &SchedulerPartialInputWrapper::BUCKET.to_string(),
+                &key,
+            )
+            .await
+    }
+
+    /// Handles a picked scheduler job with incomplete final prover job IDs in the dependency
+    /// tracker, e.g. after the tracker was edited manually. Depending on the configuration,
+    /// the job is either marked as failed, or returned to the queue so that it's retried once
+    /// the tracker is fixed; in the latter case, the job is picked again on every poll.
+    async fn handle_incomplete_dependencies(
+        &self,
+        lease: SchedulerJobLease,
+        message: String,
+    ) -> anyhow::Result<Option<(SchedulerJobLease, SchedulerWitnessGeneratorJob, JobMetadata)>>
+    {
+        let l1_batch_number = lease.l1_batch_number;
+        let fail_job = self.config.scheduler_fail_on_incomplete_dependencies();
+        metrics::counter!(
+            "prover_fri.witness_generation.incomplete_scheduler_dependencies",
+            1,
+            "action" => if fail_job { "failed" } else { "left_queued" }
         );
+        if fail_job {
+            vlog::error!(
+                "Failing scheduler job for l1 batch {}: {}",
+                l1_batch_number,
+                message
+            );
+            if !self.config.dry_run() {
+                let err = JobError::new(JobErrorKind::InvalidInput, message);
+                self.save_failure(lease, Instant::now(), err).await;
+            }
+            return Ok(None);
+        }
+        vlog::warn!(
+            "Leaving scheduler job for l1 batch {} queued: {}",
+            l1_batch_number,
+            message
+        );
+        self.release_job(lease).await;
+        Ok(None)
+    }
 
-        vlog::info!(
-            "Scheduler generation for block {} is complete in {:?}",
-            job.block_number.0,
-            started_at.elapsed()
+    /// Returns protocol versions of scheduler jobs that can be processed by this witness generator
+    /// besides jobs without a recorded version. Jobs for other versions are left queued.
+    fn served_protocol_versions(&self) -> Vec<u16> {
+        let mut protocol_versions = self.config.supported_protocol_versions();
+        protocol_versions.extend(self.config.protocol_version);
+        protocol_versions
+    }
+
+    /// Loads the job for final node prover jobs specified as `(circuit_id, job_id)` pairs.
+    async fn load_job(
+        &self,
+        l1_batch_number: L1BatchNumber,
+        protocol_version: Option<u16>,
+        proof_jobs: &[(u8, u32)],
+        prover_jobs: &HashMap<u32, (u8, Option<String>)>,
+    ) -> Result<SchedulerWitnessGeneratorJob, JobError> {
+        let vks = self.vks.get_or_load(protocol_version, || {
+            let vk_path = scheduler_vk_path(
+                &get_base_vk_path(),
+                self.config.protocol_version,
+                protocol_version,
+            );
+            load_scheduler_vks(l1_batch_number, protocol_version, &vk_path)
+        })?;
+        // Checked before loading proofs, so that jobs with inconsistent inputs fail fast.
+        let expected_circuit_ids: Vec<_> = vks
+            .leaf_layer_parameters
+            .iter()
+            .map(|&(circuit_id, _)| circuit_id)
+            .collect();
+        let proof_job_ids =
+            &order_final_prover_job_ids(l1_batch_number, proof_jobs, &expected_circuit_ids)?;
+        let proof_witness_sources = proof_witness_sources(
+            l1_batch_number,
+            proof_job_ids,
+            prover_jobs,
+            &expected_circuit_ids,
+        )?;
+        let started_at = Instant::now();
+        let bundled_proofs = if self.config.load_proof_bundles() {
+            load_bundled_proofs(
+                l1_batch_number,
+                proof_job_ids,
+                &*self.object_store,
+                AggregationRound::Scheduler,
+            )
+            .await
+            .map_err(|err| {
+                let message =
+                    format!("failed loading proof bundle for scheduler l1 batch {l1_batch_number}");
+                object_store_job_error(err.inner, message).with_blob_url(err.key)
+            })?
+        } else {
+            None
+        };
+        let (proofs, source) = match bundled_proofs {
+            Some(proofs) => (proofs, "bundle"),
+            None => (
+                self.load_proofs(l1_batch_number, proof_job_ids).await?,
+                "proofs",
+            ),
+        };
+        // Superseded by `blob_download_time` and `blob_deserialization_time` reported for each
+        // proof blob or bundle; kept for one release, so that dashboards can be migrated.
+        metrics::histogram!(
+            "prover_fri.witness_generation.blob_fetch_time",
+            started_at.elapsed(),
+            "aggregation_round" => format!("{:?}", AggregationRound::Scheduler),
+            "source" => source,
         );
 
-        SchedulerArtifacts {
-            scheduler_circuit: ZkSyncRecursiveLayerCircuit::SchedulerCircuit(scheduler_circuit),
+        if proofs.len() != proof_witness_sources.len() {
+            let message = format!(
+                "Loaded {} proofs for scheduler l1 batch {}, expected {}",
+                proofs.len(),
+                l1_batch_number,
+                proof_witness_sources.len()
+            );
+            return Err(JobError::new(JobErrorKind::InvalidInput, message));
         }
+        let recursive_proofs = into_recursive_proofs(l1_batch_number, proofs, proof_job_ids)?;
+        let recursive_proofs = if self.config.scheduler_skip_proof_verification() {
+            recursive_proofs
+        } else {
+            self.verify_node_proofs(
+                l1_batch_number,
+                recursive_proofs,
+                proof_job_ids,
+                &vks.node_vk,
+            )
+            .await?
+        };
+        prepare_job(
+            l1_batch_number,
+            recursive_proofs,
+            &vks,
+            proof_witness_sources,
+            &*self.object_store,
+        )
+        .await
+    }
+
+    /// Verifies final node proofs against the node VK, so that a stale or corrupted proof fails
+    /// the job right away rather than the much more expensive scheduler proof. Returns the proofs
+    /// back to the caller.
+    async fn verify_node_proofs(
+        &self,
+        l1_batch_number: L1BatchNumber,
+        proofs: Vec<ZkSyncRecursionProof>,
+        proof_job_ids: &[u32],
+        node_vk: &ZkSyncRecursionLayerVerificationKey,
+    ) -> Result<Vec<ZkSyncRecursionProof>, JobError> {
+        let started_at = Instant::now();
+        let node_vk = node_vk.clone();
+        let proof_job_ids = proof_job_ids.to_vec();
+        let verification = spawn_compute_task(self.compute_pool.as_ref(), move || {
+            let result = check_node_proofs(l1_batch_number, &proofs, &proof_job_ids, |proof| {
+                verify_recursion_layer_proof_for_type::<NoPow>(
+                    ZkSyncRecursionLayerStorageType::NodeLayerCircuit,
+                    proof,
+                    &node_vk,
+                )
+            });
+            result.map(|()| proofs)
+        });
+        let result = verification.await.unwrap_or_else(|err| {
+            let message = format!(
+                "verifying node proofs for scheduler l1 batch {l1_batch_number} panicked: {err}"
+            );
+            Err(JobError::new(JobErrorKind::Other, message))
+        });
+        metrics::histogram!(
+            "prover_fri.witness_generation.proof_verification_time",
+            started_at.elapsed(),
+            "aggregation_round" => format!("{:?}", AggregationRound::Scheduler),
+        );
+        result
     }
 }
 
 #[async_trait]
 impl JobProcessor for SchedulerWitnessGenerator {
     type Job = SchedulerWitnessGeneratorJob;
-    type JobId = L1BatchNumber;
+    type JobId = SchedulerJobLease;
     type JobArtifacts = SchedulerArtifacts;
 
     const SERVICE_NAME: &'static str = "fri_scheduler_witness_generator";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
+        let next_job = self.get_next_job_with_metadata().await?;
+        Ok(next_job.map(|(lease, job, _)| (lease, job)))
+    }
+
+    async fn get_next_job_with_metadata(
+        &self,
+    ) -> anyhow::Result<Option<(Self::JobId, Self::Job, JobMetadata)>> {
         let mut prover_connection = self.prover_connection_pool.access_storage().await;
 
-        let l1_batch_number = prover_connection
+        let protocol_versions = self.served_protocol_versions();
+        let batch_range = self.config.batch_range();
+        let lease = if self.config.dry_run() {
+            let min_l1_batch_number =
+                L1BatchNumber(self.next_dry_run_l1_batch.load(Ordering::Relaxed));
+            let batch_range = (*batch_range.start()).max(min_l1_batch_number)..=*batch_range.end();
+            // Jobs aren't locked in dry-run mode, so no lease is taken.
+            prover_connection
+                .fri_witness_generator_dal()
+                .peek_next_scheduler_witness_job(&batch_range, &protocol_versions)
+                .await
+                .map(|(l1_batch_number, protocol_version)| SchedulerJobLease {
+                    l1_batch_number,
+                    lease_id: String::new(),
+                    attempts: 0,
+                    protocol_version,
+                })
+        } else {
+            if self.config.scheduler_strict_deadline() {
+                let skipped_batches = prover_connection
+                    .fri_witness_generator_dal()
+                    .mark_queued_scheduler_jobs_past_deadline()
+                    .await;
+                for l1_batch_number in skipped_batches {
+                    vlog::error!(
+                        "Scheduler job for l1 batch {} is past its deadline, skipping it; \
+                         it requires manual intervention",
+                        l1_batch_number
+                    );
+                    report_over_deadline_job("skipped");
+                }
+            }
+            let priority_batches = self.priority_batches.borrow().clone();
+            let priority_lease = if priority_batches.is_empty() {
+                None
+            } else {
+                prover_connection
+                    .fri_witness_generator_dal()
+                    .get_next_scheduler_witness_job_for_batches(
+                        &priority_batches,
+                        &protocol_versions,
+                        &batch_range,
+                    )
+                    .await
+            };
+            match priority_lease {
+                Some(lease) => {
+                    vlog::info!(
+                        "Picked scheduler job for priority l1 batch {}",
+                        lease.l1_batch_number
+                    );
+                    Some(lease)
+                }
+                None => {
+                    prover_connection
+                        .fri_witness_generator_dal()
+                        .get_next_scheduler_witness_job_for_protocol_versions(
+                            &protocol_versions,
+                            &batch_range,
+                        )
+                        .await
+                }
+            }
+        };
+        let lease = match lease {
+            Some(lease) => lease,
+            None => return Ok(None),
+        };
+        let l1_batch_number = lease.l1_batch_number;
+        let is_past_deadline = prover_connection
             .fri_witness_generator_dal()
-            .get_next_scheduler_witness_job()
-            .await?;
-        let proof_job_ids = prover_connection
+            .is_scheduler_job_past_deadline(l1_batch_number)
+            .await;
+        if is_past_deadline {
+            vlog::warn!(
+                "Picked scheduler job for l1 batch {} past its deadline",
+                l1_batch_number
+            );
+            report_over_deadline_job("picked");
+        }
+        self.next_dry_run_l1_batch
+            .store(l1_batch_number.0 + 1, Ordering::Relaxed);
+        let job_id_states = prover_connection
             .fri_scheduler_dependency_tracker_dal()
-            .get_final_prover_job_ids_for(l1_batch_number)
+            .get_final_prover_job_id_states(l1_batch_number)
             .await;
-        let started_at = Instant::now();
-        let proofs = load_proofs_for_job_ids(&proof_job_ids, &*self.object_store).await;
-        metrics::histogram!(
-                    "prover_fri.witness_generation.blob_fetch_time",
-                    started_at.elapsed(),
-                    "aggregation_round" => format!("{:?}", AggregationRound::Scheduler),
-        );
-        let recursive_proofs = proofs
-            .into_iter()
-            .map(|wrapper| match wrapper {
-                FriProofWrapper::Base(_) => {
-                    panic!(
-                        "Expected only recursive proofs for scheduler l1 batch {}",
-                        l1_batch_number
-                    )
+        let proof_jobs = match validate_final_prover_job_ids(l1_batch_number, job_id_states) {
+            Ok(proof_jobs) => proof_jobs,
+            Err(message) => {
+                drop(prover_connection);
+                return self.handle_incomplete_dependencies(lease, message).await;
+            }
+        };
+        let proof_job_ids: Vec<_> = proof_jobs.iter().map(|&(_, job_id)| job_id).collect();
+        let prover_jobs = prover_connection
+            .fri_prover_jobs_dal()
+            .get_circuit_ids_and_proof_blob_urls(&proof_job_ids)
+            .await;
+        drop(prover_connection);
+
+        match self
+            .load_job(
+                l1_batch_number,
+                lease.protocol_version,
+                &proof_jobs,
+                &prover_jobs,
+            )
+            .await
+        {
+            Ok(job) => {
+                // Attempts aren't incremented in dry-run mode.
+                let attempts = Some(lease.attempts).filter(|_| !self.config.dry_run());
+                Ok(Some((lease, job, JobMetadata::new(attempts))))
+            }
+            Err(err) if self.is_partial_input_pruned(l1_batch_number, &err).await => {
+                vlog::warn!(
+                    "Partial input for scheduler job for l1 batch {} was removed by the blob \
+                     pruner, skipping the job: {}",
+                    l1_batch_number,
+                    err
+                );
+                if !self.config.dry_run() {
+                    self.prover_connection_pool
+                        .access_storage()
+                        .await
+                        .fri_witness_generator_dal()
+                        .mark_scheduler_job_as_skipped(l1_batch_number)
+                        .await;
                 }
-                FriProofWrapper::Recursive(recursive_proof) => recursive_proof.into_inner(),
-            })
-            .collect::<Vec<_>>();
-        Some((
-            l1_batch_number,
-            prepare_job(l1_batch_number, recursive_proofs, &*self.object_store).await,
-        ))
+                metrics::counter!(
+                    "prover_fri.witness_generation.skipped_scheduler_jobs",
+                    1,
+                    "reason" => "pruned_input"
+                );
+                Ok(None)
+            }
+            Err(err) if err.kind == JobErrorKind::Unavailable => {
+                // The job isn't at fault, so it's returned to the queue, and job pickup is retried
+                // after a backoff.
+                self.release_job(lease).await;
+                Err(anyhow::Error::from(err)).with_context(|| {
+                    format!("failed preparing scheduler job for l1 batch {l1_batch_number}")
+                })
+            }
+            Err(err) => {
+                vlog::error!(
+                    "Failed preparing scheduler job for l1 batch {}: {}",
+                    l1_batch_number,
+                    err
+                );
+                // The job is locked at this point, so its failure is persisted right away
+                // rather than left for the stuck job requeuer. Since the failure is handled,
+                // the processor goes on picking up other jobs.
+                if !self.config.dry_run() {
+                    self.save_failure(lease, Instant::now(), err).await;
+                }
+                Ok(None)
+            }
+        }
     }
 
-    async fn save_failure(&self, job_id: L1BatchNumber, _started_at: Instant, error: String) -> () {
-        self.prover_connection_pool
+    fn max_jobs_to_process(&self) -> Option<u64> {
+        self.config.max_jobs_to_process
+    }
+
+    fn max_pickups_per_minute(&self) -> Option<u32> {
+        self.config.max_job_pickups_per_minute
+    }
+
+    fn drain_after_empty_polls(&self) -> Option<u32> {
+        self.config.drain_after_empty_polls()
+    }
+
+    fn status(&self) -> Option<&SharedJobProcessorStatus> {
+        Some(&self.status)
+    }
+
+    async fn pending_jobs_count(&self) -> Option<PendingJobsCount> {
+        let protocol_versions = self.served_protocol_versions();
+        let mut prover_connection = self.prover_connection_pool.access_storage().await;
+        let unserved_job_counts: Vec<_> = prover_connection
+            .fri_witness_generator_dal()
+            .count_queued_scheduler_jobs_by_protocol_version()
+            .await
+            .into_iter()
+            .filter_map(|(version, count)| Some((version?, count)))
+            .filter(|(version, _)| !protocol_versions.contains(version))
+            .collect();
+        let mut blocked_job_counts = prover_connection
+            .fri_witness_generator_dal()
+            .count_blocked_scheduler_jobs()
+            .await;
+        drop(prover_connection);
+        let unserved_job_count: usize = unserved_job_counts.iter().map(|(_, count)| count).sum();
+        if unserved_job_count > 0 {
+            vlog::warn!(
+                "Queued scheduler jobs for protocol versions not served by this witness generator \
+                 (version: job count): {:?}",
+                unserved_job_counts
+            );
+        }
+        metrics::gauge!(
+            "prover_fri.witness_generation.unserved_scheduler_jobs",
+            unserved_job_count as f64
+        );
+        if !blocked_job_counts.is_empty() {
+            vlog::warn!(
+                "Queued scheduler jobs are blocked by final node prover jobs that aren't \
+                 successful (prover job status: job count): {:?}",
+                blocked_job_counts
+            );
+        }
+        // Gauges for the usual statuses are always reported, so that they are reset once jobs
+        // are unblocked.
+        for &status in BLOCKING_PROVER_JOB_STATUSES {
+            blocked_job_counts.entry(status.to_owned()).or_insert(0);
+        }
+        for (status, count) in blocked_job_counts {
+            metrics::gauge!(
+                "prover_fri.witness_generation.blocked_scheduler_jobs",
+                count as f64,
+                "prover_job_status" => status
+            );
+        }
+
+        Some(
+            count_pending_witness_jobs(&self.prover_connection_pool, AggregationRound::Scheduler)
+                .await,
+        )
+    }
+
+    async fn save_failure(&self, job_id: SchedulerJobLease, started_at: Instant, error: JobError) {
+        let metadata = JobMetadata::new(Some(job_id.attempts));
+        let context = JobFailureContext::new(metadata, 1);
+        self.save_failure_with_context(job_id, started_at, error, context)
+            .await;
+    }
+
+    async fn save_failure_with_context(
+        &self,
+        job_id: SchedulerJobLease,
+        _started_at: Instant,
+        error: JobError,
+        context: JobFailureContext,
+    ) {
+        let failure = JobErrorHistoryEntry {
+            timestamp: Utc::now(),
+            attempt: context.metadata.attempts,
+            error_kind: error.kind.as_str().to_owned(),
+            error: error.to_json(),
+            hostname: context.hostname,
+        };
+        let status = self
+            .prover_connection_pool
             .access_storage()
             .await
             .fri_witness_generator_dal()
-            .mark_scheduler_job_failed(&error, job_id)
+            .mark_scheduler_job_failed(
+                &failure,
+                error.retryable,
+                self.config.scheduler_max_attempts(),
+                &job_id,
+            )
             .await;
+        match status {
+            Some(FriWitnessJobStatus::Queued) => {
+                vlog::info!(
+                    "Requeued scheduler job for l1 batch {} after a retryable {} failure",
+                    job_id.l1_batch_number,
+                    error.kind
+                );
+            }
+            Some(_) => {}
+            None => report_lost_lease(&job_id, "failure"),
+        }
     }
 
     #[allow(clippy::async_yields_async)]
@@ -155,91 +856,1274 @@ impl JobProcessor for SchedulerWitnessGenerator {
         &self,
         job: SchedulerWitnessGeneratorJob,
         started_at: Instant,
-    ) -> tokio::task::JoinHandle<SchedulerArtifacts> {
-        tokio::task::spawn_blocking(move || Self::process_job_sync(job, started_at))
+    ) -> tokio::task::JoinHandle<anyhow::Result<SchedulerArtifacts>> {
+        self.process_job_with_context(job, started_at, JobContext::default())
+            .await
     }
 
-    async fn save_result(
+    #[allow(clippy::async_yields_async)]
+    async fn process_job_with_context(
         &self,
-        job_id: L1BatchNumber,
+        job: SchedulerWitnessGeneratorJob,
         started_at: Instant,
-        artifacts: SchedulerArtifacts,
-    ) {
-        let key = FriCircuitKey {
-            block_number: job_id,
-            circuit_id: 1,
-            sequence_number: 0,
-            depth: 0,
-            aggregation_round: AggregationRound::Scheduler,
-        };
-        let blob_save_started_at = Instant::now();
-        let scheduler_circuit_blob_url = self
-            .object_store
-            .put(key, &CircuitWrapper::Recursive(artifacts.scheduler_circuit))
-            .await
-            .unwrap();
-        metrics::histogram!(
-                    "prover_fri.witness_generation.blob_save_time",
-                    blob_save_started_at.elapsed(),
-                    "aggregation_round" => format!("{:?}", AggregationRound::Scheduler),
+        context: JobContext,
+    ) -> tokio::task::JoinHandle<anyhow::Result<SchedulerArtifacts>> {
+        let check_circuit = self.config.check_scheduler_circuit();
+        let determinism_check = DeterminismCheck::from_config(&self.config);
+        spawn_compute_task(self.compute_pool.as_ref(), move || {
+            let l1_batch_number = job.block_number;
+            track_peak_memory(AggregationRound::Scheduler, l1_batch_number, || {
+                let (artifacts, determinism_mismatch) = run_with_determinism_check(
+                    determinism_check,
+                    AggregationRound::Scheduler,
+                    l1_batch_number,
+                    job,
+                    |job| Self::process_job_sync(job, started_at, check_circuit, &context),
+                    |artifacts| {
+                        let artifacts = artifacts.as_ref().ok()?;
+                        bincode::serialize(&artifacts.scheduler_circuit).ok()
+                    },
+                );
+                let mut artifacts = artifacts?;
+                artifacts.determinism_mismatch = determinism_mismatch;
+                Ok(artifacts)
+            })
+        })
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.config.dry_run()
+    }
+
+    fn artifacts_size(&self, artifacts: &SchedulerArtifacts) -> Option<u64> {
+        bincode::serialized_size(&artifacts.scheduler_circuit).ok()
+    }
+
+    fn job_metrics(&self) -> JobMetrics {
+        JobMetrics::fri_witness_generation("Scheduler")
+    }
+
+    fn polling_backoff(&self) -> PollingBackoff {
+        PollingBackoff {
+            initial_interval: self.config.scheduler_polling_interval(),
+            max_interval: self.config.scheduler_max_polling_interval(),
+            multiplier: self.config.scheduler_polling_backoff_multiplier(),
+        }
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.config.scheduler_retry_max_attempts(),
+            base_backoff: self.config.scheduler_retry_base_backoff(),
+        }
+    }
+
+    fn job_timeout(&self) -> Option<Duration> {
+        Some(self.config.scheduler_job_timeout())
+    }
+
+    fn exit_on_panic(&self) -> bool {
+        self.config.shutdown_on_job_panic()
+    }
+
+    fn heartbeat_interval(&self) -> Option<Duration> {
+        Some(self.config.scheduler_heartbeat_interval())
+    }
+
+    async fn can_accept_job(&self, job_id: &SchedulerJobLease) -> bool {
+        if self.resource_requirements.is_empty() {
+            return true;
+        }
+        let resources = self.resource_monitor.available_resources();
+        if self.resource_requirements.are_satisfied_by(&resources) {
+            return true;
+        }
+        vlog::warn!(
+            "Insufficient resources to process scheduler job for l1 batch {}: available {:?}, \
+             required {:?}",
+            job_id.l1_batch_number,
+            resources,
+            self.resource_requirements
+        );
+        metrics::counter!(
+            "prover_fri.witness_generation.rejected_jobs",
+            1,
+            "aggregation_round" => "Scheduler",
+            "reason" => "insufficient_resources"
         );
+        false
+    }
 
-        let mut prover_connection = self.prover_connection_pool.access_storage().await;
-        let mut transaction = prover_connection.start_transaction().await;
-        transaction
-            .fri_prover_jobs_dal()
-            .insert_prover_job(
-                job_id,
-                1,
-                0,
-                0,
-                AggregationRound::Scheduler,
-                &scheduler_circuit_blob_url,
-                false,
-            )
+    async fn release_job(&self, job_id: SchedulerJobLease) {
+        // Jobs aren't locked in dry-run mode, so there's nothing to release.
+        if self.config.dry_run() {
+            return;
+        }
+        let is_released = self
+            .prover_connection_pool
+            .access_storage()
+            .await
+            .fri_witness_generator_dal()
+            .release_scheduler_job(&job_id)
             .await;
+        if !is_released {
+            report_lost_lease(&job_id, "release");
+        }
+    }
 
-        transaction
+    async fn heartbeat(&self, job_id: &SchedulerJobLease) {
+        // Jobs aren't locked in dry-run mode, so there's no lease to keep.
+        if self.config.dry_run() {
+            return;
+        }
+        let is_touched = self
+            .prover_connection_pool
+            .access_storage()
+            .await
             .fri_witness_generator_dal()
-            .mark_scheduler_job_as_successful(job_id, started_at.elapsed())
+            .touch_scheduler_job(job_id)
             .await;
+        if !is_touched {
+            report_lost_lease(job_id, "heartbeat");
+        }
+    }
 
-        transaction.commit().await;
+    async fn is_job_cancelled(&self, job_id: &SchedulerJobLease) -> bool {
+        self.prover_connection_pool
+            .access_storage()
+            .await
+            .fri_witness_generator_dal()
+            .is_scheduler_job_cancelled(job_id.l1_batch_number)
+            .await
     }
+
+    async fn save_cancelled(&self, job_id: SchedulerJobLease, _started_at: Instant) {
+        self.prover_connection_pool
+            .access_storage()
+            .await
+            .fri_witness_generator_dal()
+            .mark_scheduler_job_as_skipped(job_id.l1_batch_number)
+            .await;
+    }
+
+    async fn save_result(
+        &self,
+        job_id: SchedulerJobLease,
+        started_at: Instant,
+        mut artifacts: SchedulerArtifacts,
+    ) -> anyhow::Result<()> {
+        if let Some(mismatch) = artifacts.determinism_mismatch.take() {
+            let bucket = CircuitWrapper::BUCKET;
+            let job_number = job_id.l1_batch_number.0;
+            mismatch
+                .upload_artifacts(&*self.object_store, bucket, job_number)
+                .await;
+        }
+        match self.try_save_result(&job_id, started_at, artifacts).await {
+            Ok(true) => {
+                report_sealed_to_saved_latency(
+                    &self.connection_pool,
+                    &self.prover_connection_pool,
+                    AggregationRound::Scheduler,
+                    job_id.l1_batch_number,
+                    job_id.l1_batch_number.0,
+                )
+                .await;
+            }
+            Ok(false) => report_lost_lease(&job_id, "result"),
+            // The job is released by the caller and processed from scratch later.
+            Err(err) if is_store_unavailable(&err) => return Err(classify_store_unavailable(err)),
+            Err(err) => {
+                let message = format!(
+                    "failed saving result of scheduler job for l1 batch {}: {:#}",
+                    job_id.l1_batch_number, err
+                );
+                vlog::error!("{}", message);
+                // The job will be processed from scratch, so no bookkeeping is lost.
+                self.save_failure(
+                    job_id,
+                    started_at,
+                    JobError::new(JobErrorKind::Other, message),
+                )
+                .await;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Saves the scheduler circuit for the specified L1 batch. Returns the key and the size
+/// of the saved blob.
+async fn save_scheduler_circuit(
+    l1_batch_number: L1BatchNumber,
+    scheduler_circuit: ZkSyncRecursiveLayerCircuit,
+    object_store: &dyn ObjectStore,
+) -> Result<(String, u64), ObjectStoreError> {
+    let circuit = CircuitWrapper::Recursive(scheduler_circuit);
+    let key = circuit.circuit_key(l1_batch_number, CircuitPosition::Scheduler);
+    // Transient object store errors are retried by the store itself.
+    put_artifact(object_store, key, &circuit).await
+}
+
+/// Returns the key of the scheduler circuit for the L1 batch, which can be computed without
+/// the circuit since there's a single scheduler circuit type.
+pub(crate) fn scheduler_circuit_key(l1_batch_number: L1BatchNumber) -> FriCircuitKey {
+    let circuit_type = ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8;
+    CircuitPosition::Scheduler.circuit_key(l1_batch_number, circuit_type)
+}
+
+/// Reports that the job outcome is discarded because the job was picked up by another replica.
+fn report_lost_lease(lease: &SchedulerJobLease, outcome: &'static str) {
+    vlog::warn!(
+        "Lease {} for scheduler job for l1 batch {} is lost; discarding its {}",
+        lease.lease_id,
+        lease.l1_batch_number,
+        outcome
+    );
+    metrics::counter!(
+        "prover_fri.witness_generation.lost_leases",
+        1,
+        "aggregation_round" => "Scheduler",
+        "outcome" => outcome
+    );
+}
+
+/// Reports a scheduler job that has passed its deadline; such jobs should be escalated.
+/// L1 batches of such jobs are logged by callers rather than used as labels to keep
+/// the number of time series bounded.
+fn report_over_deadline_job(action: &'static str) {
+    metrics::counter!(
+        "prover_fri.witness_generation.over_deadline",
+        1,
+        "aggregation_round" => "Scheduler",
+        "action" => action
+    );
+}
+
+/// Checks `(circuit_id, job_id)` pairs for final prover jobs recorded by the dependency tracker
+/// for an L1 batch (`None` if the batch is not tracked). Unset, zero and duplicate IDs don't point
+/// to final node proofs of the corresponding circuits, so they are rejected with an error listing
+/// such circuits.
+fn validate_final_prover_job_ids(
+    l1_batch_number: L1BatchNumber,
+    job_ids: Option<[(u8, Option<u32>); 13]>,
+) -> Result<Vec<(u8, u32)>, String> {
+    let job_ids = job_ids.ok_or_else(|| {
+        format!("l1 batch {l1_batch_number} is not tracked by the scheduler dependency tracker")
+    })?;
+
+    let mut unset_circuits = vec![];
+    let mut zero_circuits = vec![];
+    let mut circuits_by_job_id = BTreeMap::<_, Vec<_>>::new();
+    for (circuit_id, job_id) in job_ids {
+        match job_id {
+            None => unset_circuits.push(circuit_id),
+            Some(0) => zero_circuits.push(circuit_id),
+            Some(job_id) => circuits_by_job_id
+                .entry(job_id)
+                .or_default()
+                .push(circuit_id),
+        }
+    }
+    let duplicates: Vec<_> = circuits_by_job_id
+        .into_iter()
+        .filter(|(_, circuits)| circuits.len() > 1)
+        .map(|(job_id, mut circuits)| {
+            circuits.sort_unstable();
+            (job_id, circuits)
+        })
+        .collect();
+    if unset_circuits.is_empty() && zero_circuits.is_empty() && duplicates.is_empty() {
+        let job_ids = job_ids
+            .iter()
+            .map(|&(circuit_id, job_id)| (circuit_id, job_id.unwrap()))
+            .collect();
+        return Ok(job_ids);
+    }
+    unset_circuits.sort_unstable();
+    zero_circuits.sort_unstable();
+
+    let mut circuits_without_proofs: Vec<_> = unset_circuits
+        .iter()
+        .chain(&zero_circuits)
+        .chain(duplicates.iter().flat_map(|(_, circuits)| circuits))
+        .copied()
+        .collect();
+    circuits_without_proofs.sort_unstable();
+    let mut details = vec![];
+    if !unset_circuits.is_empty() {
+        details.push(format!("unset job IDs for circuits {unset_circuits:?}"));
+    }
+    if !zero_circuits.is_empty() {
+        details.push(format!("zero job IDs for circuits {zero_circuits:?}"));
+    }
+    for (job_id, circuits) in &duplicates {
+        details.push(format!("job ID {job_id} shared by circuits {circuits:?}"));
+    }
+    Err(format!(
+        "final node proofs for l1 batch {} are missing for circuits {:?} in the scheduler \
+         dependency tracker: {}",
+        l1_batch_number,
+        circuits_without_proofs,
+        details.join(", ")
+    ))
+}
+
+/// Orders `(circuit_id, job_id)` pairs for final node prover jobs by the position of their circuits
+/// in `expected_circuit_ids`, which is the order of proof witnesses expected by the scheduler
+/// circuit, and returns the job IDs. Proofs in any other order would produce an unsatisfiable
+/// witness, so jobs that don't match the expected circuits one-to-one fail the job.
+fn order_final_prover_job_ids(
+    l1_batch_number: L1BatchNumber,
+    proof_jobs: &[(u8, u32)],
+    expected_circuit_ids: &[u8],
+) -> Result<Vec<u32>, JobError> {
+    let mut ordered_jobs = proof_jobs.to_vec();
+    ordered_jobs.sort_by_key(|&(circuit_id, _)| {
+        expected_circuit_ids
+            .iter()
+            .position(|&expected_id| expected_id == circuit_id)
+            .unwrap_or(usize::MAX)
+    });
+    let circuit_ids: Vec<_> = ordered_jobs
+        .iter()
+        .map(|&(circuit_id, _)| circuit_id)
+        .collect();
+    if circuit_ids != expected_circuit_ids {
+        let recorded_circuit_ids: Vec<_> = proof_jobs
+            .iter()
+            .map(|&(circuit_id, _)| circuit_id)
+            .collect();
+        let message = format!(
+            "Final node prover jobs for scheduler l1 batch {} are recorded for circuits {:?}, \
+             which don't match the circuit sequence {:?} expected by the scheduler circuit",
+            l1_batch_number, recorded_circuit_ids, expected_circuit_ids
+        );
+        let mut err = JobError::new(JobErrorKind::InvalidInput, message);
+        if let Some(&circuit_id) = expected_circuit_ids
+            .iter()
+            .find(|circuit_id| !circuit_ids.contains(circuit_id))
+        {
+            err = err.with_circuit_id(circuit_id);
+        }
+        return Err(err);
+    }
+    Ok(ordered_jobs.into_iter().map(|(_, job_id)| job_id).collect())
+}
+
+/// Unwraps recursive proofs for a scheduler job. A base proof among them (e.g., if a prover job
+/// ID was recorded incorrectly) fails the job rather than the witness generator.
+fn into_recursive_proofs(
+    l1_batch_number: L1BatchNumber,
+    proofs: Vec<FriProofWrapper>,
+    proof_job_ids: &[u32],
+) -> Result<Vec<ZkSyncRecursionProof>, JobError> {
+    proofs
+        .into_iter()
+        .zip(proof_job_ids)
+        .map(|(wrapper, &proof_job_id)| match wrapper {
+            FriProofWrapper::Base(base_proof) => {
+                let circuit_id = base_proof.numeric_circuit_type();
+                let message = format!(
+                    "Expected only recursive proofs for scheduler l1 batch {}, got base proof \
+                     for circuit {} from prover job {}",
+                    l1_batch_number, circuit_id, proof_job_id
+                );
+                Err(JobError::new(JobErrorKind::InvalidInput, message)
+                    .with_circuit_id(circuit_id)
+                    .with_blob_url(FriProofWrapper::encode_key(proof_job_id)))
+            }
+            FriProofWrapper::Recursive(recursive_proof) => Ok(recursive_proof.into_inner()),
+        })
+        .collect()
+}
+
+type SchedulerWitness = SchedulerCircuitInstanceWitness<
+    GoldilocksField,
+    CircuitGoldilocksPoseidon2Sponge,
+    GoldilocksExt2,
+>;
+
+/// Constructs the scheduler circuit from its witness and the node VK; returns the circuit together
+/// with the commitments it exposes.
+fn build_scheduler_circuit(
+    witness: SchedulerWitness,
+    node_vk: ZkSyncRecursionLayerVerificationKey,
+) -> (ZkSyncSchedulerCircuit, SchedulerCommitments) {
+    let config = SchedulerConfig {
+        proof_config: recursion_layer_proof_config(),
+        vk_fixed_parameters: node_vk.into_inner().fixed_parameters,
+        capacity: SCHEDULER_CAPACITY,
+        _marker: std::marker::PhantomData,
+    };
+    let commitments = scheduler_commitments(&witness);
+    let circuit = SchedulerCircuit {
+        witness,
+        config,
+        transcript_params: (),
+        _marker: std::marker::PhantomData,
+    };
+    (circuit, commitments)
+}
+
+/// Computes commitments to the L1 batch exposed by the scheduler circuit from its witness.
+fn scheduler_commitments(witness: &SchedulerWitness) -> SchedulerCommitments {
+    let meta_parameters = &witness.block_meta_parameters;
+    let meta_parameters = BlockMetaParameters {
+        zkporter_is_available: meta_parameters.zkporter_is_available,
+        bootloader_code_hash: H256(meta_parameters.bootloader_code_hash),
+        default_aa_code_hash: H256(meta_parameters.default_aa_code_hash),
+    };
+    let storage_output = &witness.storage_application_observable_output;
+    // The counter is represented as low and high 32-bit words.
+    let [low, high] = storage_output.new_next_enumeration_counter;
+    let next_enumeration_index = (u64::from(high) << 32) | u64::from(low);
+    SchedulerCommitments::new(
+        &meta_parameters,
+        next_enumeration_index,
+        H256(storage_output.new_root_hash),
+    )
+}
+
+/// Checks that the scheduler circuit is satisfied by its witness by synthesizing it into
+/// a reference constraint system with debug checks enabled, so that a broken witness fails
+/// the job rather than a proving attempt. Synthesis takes about as long as generating
+/// the witness, so the check roughly doubles CPU time of the scheduler round.
+fn check_scheduler_circuit(
+    l1_batch_number: L1BatchNumber,
+    circuit: &ZkSyncSchedulerCircuit,
+) -> Result<(), JobError> {
+    let started_at = Instant::now();
+    let outcome = check_satisfied(circuit);
+    metrics::histogram!(
+        "prover_fri.witness_generation.scheduler_circuit_check_time",
+        started_at.elapsed()
+    );
+
+    match outcome {
+        Ok(()) => {
+            vlog::info!(
+                "Scheduler circuit for l1 batch {} is satisfied, checked in {:?}",
+                l1_batch_number,
+                started_at.elapsed()
+            );
+            Ok(())
+        }
+        Err(details) => {
+            let message = format!(
+                "Scheduler circuit for l1 batch {} is not satisfied by its witness: {}",
+                l1_batch_number, details
+            );
+            Err(JobError::new(JobErrorKind::InvalidInput, message))
+        }
+    }
+}
+
+/// Synthesizes the circuit and checks that it's satisfied; returns details of the failure
+/// otherwise.
+fn check_satisfied(circuit: &ZkSyncSchedulerCircuit) -> Result<(), String> {
+    let circuit = circuit.clone();
+    // Debug checks of the constraint system panic on the first violated constraint.
+    match panic::catch_unwind(AssertUnwindSafe(|| is_satisfied(circuit))) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("unsatisfied gates are logged by the witness generator".to_owned()),
+        Err(panic) => Err(match panic.downcast::<String>() {
+            Ok(message) => *message,
+            Err(panic) => match panic.downcast::<&'static str>() {
+                Ok(message) => (*message).to_owned(),
+                Err(_) => "synthesis panicked".to_owned(),
+            },
+        }),
+    }
+}
+
+fn is_satisfied(circuit: ZkSyncSchedulerCircuit) -> bool {
+    let geometry = ZkSyncSchedulerCircuit::geometry();
+    let (max_trace_len, num_vars) = circuit.size_hint();
+    let builder_impl =
+        CsReferenceImplementationBuilder::<GoldilocksField, GoldilocksField, DevCSConfig>::new(
+            geometry,
+            num_vars.expect("scheduler circuit doesn't specify the number of variables"),
+            max_trace_len.expect("scheduler circuit doesn't specify the trace length"),
+        );
+    let builder = new_builder::<_, GoldilocksField>(builder_impl);
+    let builder = circuit.configure_builder_proxy(builder);
+    let mut cs = builder.build(());
+    circuit.add_tables(&mut cs);
+    circuit.synthesize_into_cs(&mut cs);
+    cs.pad_and_shrink();
+    let mut assembly = cs.into_assembly();
+    assembly.check_if_satisfied(&Worker::new())
+}
+
+/// Returns the directory with verification keys for a scheduler job of the specified protocol
+/// version. Jobs without a recorded version and jobs for the version of the setup data use keys
+/// from the base directory.
+fn scheduler_vk_path(
+    base_vk_path: &str,
+    setup_data_protocol_version: Option<u16>,
+    protocol_version: Option<u16>,
+) -> String {
+    let protocol_version =
+        protocol_version.filter(|&version| Some(version) != setup_data_protocol_version);
+    get_vk_path_for_protocol_version(base_vk_path, protocol_version)
+}
+
+/// Loads the node VK and leaf layer parameters for a scheduler job from `vk_path`.
+fn load_scheduler_vks(
+    l1_batch_number: L1BatchNumber,
+    protocol_version: Option<u16>,
+    vk_path: &str,
+) -> Result<SchedulerVks, JobError> {
+    let vk_error = |err: anyhow::Error| {
+        let version = match protocol_version {
+            Some(version) => format!("protocol version {}", version),
+            None => "no recorded protocol version".to_owned(),
+        };
+        let message = format!(
+            "Verification keys for scheduler l1 batch {} ({}) are not available \
+             on this witness generator at {}: {:#}",
+            l1_batch_number, version, vk_path, err
+        );
+        JobError::new(JobErrorKind::VerificationKey, message)
+    };
+
+    vlog::info!(
+        "Loading verification keys for scheduler l1 batch {} (protocol version {:?}) from {}",
+        l1_batch_number,
+        protocol_version,
+        vk_path
+    );
+    let node_vk_circuit_id = ZkSyncRecursionLayerStorageType::NodeLayerCircuit as u8;
+    let node_vk = try_get_recursive_layer_vk_from_dir(vk_path, node_vk_circuit_id)
+        .map_err(|err| vk_error(err).with_circuit_id(node_vk_circuit_id))?;
+    let leaf_layer_parameters = try_get_leaf_vk_params_from_dir(vk_path).map_err(vk_error)?;
+    Ok(SchedulerVks {
+        node_vk,
+        leaf_layer_parameters,
+    })
+}
+
+/// Checks final node proofs for a scheduler job with `verify`. Proofs are ordered by circuit ID
+/// starting from 1, and `proof_job_ids` are the IDs of prover jobs that produced them. If any
+/// proofs are invalid, returns an error listing all of them and referring to the first one.
+fn check_node_proofs<P>(
+    l1_batch_number: L1BatchNumber,
+    proofs: &[P],
+    proof_job_ids: &[u32],
+    verify: impl Fn(&P) -> bool,
+) -> Result<(), JobError> {
+    let invalid_proofs: Vec<_> = proofs
+        .iter()
+        .zip(proof_job_ids)
+        .enumerate()
+        .filter(|(_, (proof, _))| !verify(proof))
+        .map(|(i, (_, &proof_job_id))| (i as u8 + 1, proof_job_id))
+        .collect();
+    let (first_circuit_id, first_job_id) = match invalid_proofs.first() {
+        Some(&first) => first,
+        None => return Ok(()),
+    };
+    let invalid_proofs_str = invalid_proofs
+        .iter()
+        .map(|(circuit_id, job_id)| format!("circuit {circuit_id} (prover job {job_id})"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = format!(
+        "{} of {} node proofs for scheduler l1 batch {} failed verification against the node VK; \
+         the node aggregation jobs producing them should be requeued. Invalid proofs: {}",
+        invalid_proofs.len(),
+        proofs.len(),
+        l1_batch_number,
+        invalid_proofs_str
+    );
+    Err(JobError::new(JobErrorKind::InvalidInput, message)
+        .with_circuit_id(first_circuit_id)
+        .with_blob_url(FriProofWrapper::encode_key(first_job_id)))
+}
+
+async fn load_scheduler_partial_input(
+    l1_batch_number: L1BatchNumber,
+    object_store: &dyn ObjectStore,
+) -> Result<SchedulerPartialInputWrapper, JobError> {
+    let manifest = get_basic_artifacts_manifest(object_store, l1_batch_number)
+        .await
+        .map_err(|err| {
+            let message =
+                format!("failed loading basic artifacts manifest for l1 batch {l1_batch_number}");
+            let key = BasicArtifactsManifest::encode_key(l1_batch_number);
+            object_store_job_error(err, message).with_blob_url(key)
+        })?;
+    let key = SchedulerPartialInputWrapper::encode_key(l1_batch_number);
+    let round = AggregationRound::Scheduler;
+    let manifest = manifest.as_ref();
+    match get_basic_artifact_section(object_store, manifest, l1_batch_number, round).await {
+        Ok(partial_input) => Ok(partial_input),
+        Err(ObjectStoreError::KeyNotFound(_)) => {
+            let message = format!(
+                "scheduler partial input for batch {l1_batch_number} not found at key {key}; \
+                 basic circuits witness generation for the batch needs to be rerun"
+            );
+            let mut err = JobError::new(JobErrorKind::MissingInput, message).with_blob_url(key);
+            // Unlike proofs, the partial input is saved before the scheduler job is queued,
+            // so it won't appear on its own.
+            err.retryable = false;
+            Err(err)
+        }
+        Err(err) => {
+            let message =
+                format!("failed loading scheduler partial input for l1 batch {l1_batch_number}");
+            Err(object_store_job_error(err, message).with_blob_url(key))
+        }
+    }
+}
+
+/// Classifies an error returned by the object store.
+fn object_store_job_error(err: ObjectStoreError, message: String) -> JobError {
+    let kind = match &err {
+        ObjectStoreError::KeyNotFound(_) => JobErrorKind::MissingInput,
+        ObjectStoreError::Serialization(_) => JobErrorKind::Serialization,
+        // The object was written by an incompatible binary, so retrying the job won't help.
+        ObjectStoreError::UnsupportedVersion { .. } => JobErrorKind::InvalidInput,
+        // Objects that fail checksum verification are re-downloaded before the job fails.
+        ObjectStoreError::ChecksumMismatch(_) | ObjectStoreError::DecryptionFailed(_) => {
+            JobErrorKind::InvalidInput
+        }
+        ObjectStoreError::Transport(_)
+        | ObjectStoreError::Timeout(_)
+        | ObjectStoreError::Auth(_)
+        | ObjectStoreError::Unsupported(_)
+        | ObjectStoreError::Other(_) => JobErrorKind::Other,
+        // The job is released rather than failed, so that it's retried once the store recovers.
+        ObjectStoreError::CircuitOpen(_) => JobErrorKind::Unavailable,
+    };
+    JobError::new(kind, format!("{message}: {err}"))
 }
 
 async fn prepare_job(
     l1_batch_number: L1BatchNumber,
     proofs: Vec<ZkSyncRecursionProof>,
+    vks: &SchedulerVks,
+    proof_witness_sources: Vec<SchedulerProofWitnessSource>,
     object_store: &dyn ObjectStore,
-) -> SchedulerWitnessGeneratorJob {
+) -> Result<SchedulerWitnessGeneratorJob, JobError> {
     let started_at = Instant::now();
-    let node_vk = get_recursive_layer_vk_for_circuit_type(
-        ZkSyncRecursionLayerStorageType::NodeLayerCircuit as u8,
-    );
     let SchedulerPartialInputWrapper(mut scheduler_witness) =
-        object_store.get(l1_batch_number).await.unwrap();
-    scheduler_witness.node_layer_vk_witness = node_vk.clone().into_inner();
+        load_scheduler_partial_input(l1_batch_number, object_store).await?;
+    scheduler_witness.node_layer_vk_witness = vks.node_vk.clone().into_inner();
 
+    if proofs.len() != vks.leaf_layer_parameters.len() {
+        let message = format!(
+            "Got {} proofs for scheduler l1 batch {}, expected one for each of {} circuits \
+             with leaf layer parameters",
+            proofs.len(),
+            l1_batch_number,
+            vks.leaf_layer_parameters.len()
+        );
+        return Err(JobError::new(JobErrorKind::InvalidInput, message));
+    }
     scheduler_witness.proof_witnesses = proofs.into();
 
-    let leaf_vk_commits = get_leaf_vk_params();
-    let leaf_layer_params = leaf_vk_commits
+    let leaf_layer_params = vks
+        .leaf_layer_parameters
         .iter()
         .map(|el| el.1.clone())
-        .collect::<Vec<_>>()
-        .try_into()
-        .unwrap();
-    scheduler_witness.leaf_layer_parameters = leaf_layer_params;
+        .collect::<Vec<_>>();
+    let leaf_layer_params_count = leaf_layer_params.len();
+    scheduler_witness.leaf_layer_parameters = leaf_layer_params.try_into().map_err(|_| {
+        let message = format!(
+            "Got leaf layer parameters for {} circuits for scheduler l1 batch {}, \
+             which doesn't match the scheduler input layout",
+            leaf_layer_params_count, l1_batch_number
+        );
+        JobError::new(JobErrorKind::VerificationKey, message)
+    })?;
     metrics::histogram!(
                 "prover_fri.witness_generation.prepare_job_time",
                 started_at.elapsed(),
                 "aggregation_round" => format!("{:?}", AggregationRound::Scheduler),
     );
 
-    SchedulerWitnessGeneratorJob {
+    Ok(SchedulerWitnessGeneratorJob {
         block_number: l1_batch_number,
         scheduler_witness,
-        node_vk,
+        node_vk: vks.node_vk.clone(),
+        proof_witness_sources,
+    })
+}
+
+/// Maps positions of proof witnesses of the scheduler job to the final node prover jobs
+/// producing them, checking that there is a single job for each of `expected_circuit_ids`
+/// in the order of circuit IDs.
+fn proof_witness_sources(
+    l1_batch_number: L1BatchNumber,
+    proof_job_ids: &[u32],
+    prover_jobs: &HashMap<u32, (u8, Option<String>)>,
+    expected_circuit_ids: &[u8],
+) -> Result<Vec<SchedulerProofWitnessSource>, JobError> {
+    let mut duplicate_job_ids: Vec<_> = proof_job_ids
+        .iter()
+        .enumerate()
+        .filter(|&(i, job_id)| proof_job_ids[..i].contains(job_id))
+        .map(|(_, &job_id)| job_id)
+        .collect();
+    duplicate_job_ids.sort_unstable();
+    duplicate_job_ids.dedup();
+    if !duplicate_job_ids.is_empty() {
+        let message = format!(
+            "Final node prover jobs {:?} are listed more than once for scheduler l1 batch {}; \
+             the scheduler dependency tracker for the batch needs to be fixed",
+            duplicate_job_ids, l1_batch_number
+        );
+        return Err(JobError::new(JobErrorKind::InvalidInput, message));
+    }
+
+    let mut circuit_ids = Vec::with_capacity(proof_job_ids.len());
+    for &prover_job_id in proof_job_ids {
+        match prover_jobs.get(&prover_job_id) {
+            Some(&(circuit_id, _)) => circuit_ids.push(circuit_id),
+            None => {
+                let message = format!(
+                    "Final node prover job {} for scheduler l1 batch {} doesn't exist",
+                    prover_job_id, l1_batch_number
+                );
+                let mut err = JobError::new(JobErrorKind::InvalidInput, message);
+                // The position of the job is only meaningful if the other jobs are in place.
+                if proof_job_ids.len() == expected_circuit_ids.len() {
+                    err = err.with_circuit_id(expected_circuit_ids[circuit_ids.len()]);
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    let missing_circuit_ids: Vec<_> = expected_circuit_ids
+        .iter()
+        .filter(|circuit_id| !circuit_ids.contains(circuit_id))
+        .copied()
+        .collect();
+    let extra_jobs: Vec<_> = proof_job_ids
+        .iter()
+        .zip(&circuit_ids)
+        .enumerate()
+        .filter(|&(i, (_, circuit_id))| {
+            !expected_circuit_ids.contains(circuit_id) || circuit_ids[..i].contains(circuit_id)
+        })
+        .map(|(_, (job_id, circuit_id))| format!("{} (circuit {})", job_id, circuit_id))
+        .collect();
+    if !missing_circuit_ids.is_empty() || !extra_jobs.is_empty() {
+        let message = format!(
+            "Got {} final node prover jobs for scheduler l1 batch {}, expected {} \
+             (one for each circuit); missing circuits: {:?}, extra prover jobs: [{}]",
+            proof_job_ids.len(),
+            l1_batch_number,
+            expected_circuit_ids.len(),
+            missing_circuit_ids,
+            extra_jobs.join(", ")
+        );
+        let mut err = JobError::new(JobErrorKind::InvalidInput, message);
+        if let Some(&circuit_id) = missing_circuit_ids.first() {
+            err = err.with_circuit_id(circuit_id);
+        }
+        return Err(err);
+    }
+
+    let sources = proof_job_ids
+        .iter()
+        .zip(expected_circuit_ids)
+        .enumerate()
+        .map(|(position, (&prover_job_id, &expected_circuit_id))| {
+            let (circuit_id, proof_blob_url) = &prover_jobs[&prover_job_id];
+            if *circuit_id != expected_circuit_id {
+                let message = format!(
+                    "Final node prover job {} at position {} for scheduler l1 batch {} is \
+                     for circuit {}, expected circuit {}",
+                    prover_job_id, position, l1_batch_number, circuit_id, expected_circuit_id
+                );
+                return Err(
+                    JobError::new(JobErrorKind::InvalidInput, message).with_circuit_id(*circuit_id)
+                );
+            }
+            Ok(SchedulerProofWitnessSource {
+                position,
+                prover_job_id,
+                circuit_id: *circuit_id,
+                proof_blob_url: proof_blob_url
+                    .clone()
+                    .unwrap_or_else(|| FriProofWrapper::encode_key(prover_job_id)),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mapping = sources
+        .iter()
+        .map(|source| {
+            format!(
+                "{}: circuit {} (prover job {}, {})",
+                source.position, source.circuit_id, source.prover_job_id, source.proof_blob_url
+            )
+        })
+        .collect::<Vec<_>>();
+    vlog::info!(
+        "Proof witnesses for scheduler l1 batch {}: {}",
+        l1_batch_number,
+        mapping.join(", ")
+    );
+    Ok(sources)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path};
+
+    use circuit_definitions::base_layer_proof_config;
+    use circuit_definitions::boojum::cs::implementations::proof::Proof;
+    use circuit_definitions::circuit_definitions::base_layer::ZkSyncBaseLayerProof;
+    use zksync_config::{configs::object_store::ObjectStoreMode, ObjectStoreConfig};
+    use zksync_object_store::ObjectStoreFactory;
+    use zksync_types::commitment::BlockCommitment;
+    use zksync_vk_setup_data_server_fri::try_get_recursive_layer_vk_for_circuit_type;
+
+    use super::*;
+
+    /// Creates a GCS-based store pointed at the emulator at `GCS_TEST_ENDPOINT` (`localhost:4443`
+    /// by default; see the GCS integration tests in `zksync_object_store` for how to start it).
+    async fn create_gcs_emulator_store() -> Box<dyn ObjectStore> {
+        let endpoint =
+            env::var("GCS_TEST_ENDPOINT").unwrap_or_else(|_| "http://localhost:4443".to_owned());
+        let bucket = env::var("GCS_TEST_BUCKET").unwrap_or_else(|_| "zksync-test".to_owned());
+        let mut config = ObjectStoreConfig::new(ObjectStoreMode::GCS, bucket);
+        config.max_retries = 2;
+        config.write_checksums = Some(true);
+        config.gcs_endpoint_override = Some(endpoint);
+        config.gcs_use_anonymous_credentials = Some(true);
+        ObjectStoreFactory::new(config)
+            .create_store()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn base_proof_fails_job() {
+        let object_store = ObjectStoreFactory::mock().create_store().await.unwrap();
+        // The proof contents don't matter since proofs aren't verified.
+        let base_proof = Proof {
+            proof_config: base_layer_proof_config(),
+            public_inputs: vec![],
+            witness_oracle_cap: vec![],
+            stage_2_oracle_cap: vec![],
+            quotient_oracle_cap: vec![],
+            final_fri_monomials: [vec![], vec![]],
+            values_at_z: vec![],
+            values_at_z_omega: vec![],
+            values_at_0: vec![],
+            fri_base_oracle_cap: vec![],
+            fri_intermediate_oracles_caps: vec![],
+            queries_per_fri_repetition: vec![],
+            pow_challenge: 0,
+            _marker: std::marker::PhantomData,
+        };
+        let wrapper = FriProofWrapper::Base(ZkSyncBaseLayerProof::from_inner(3, base_proof));
+        object_store.put(7, &wrapper).await.unwrap();
+
+        let proofs = load_proofs_for_job_ids(
+            L1BatchNumber(1),
+            &[7],
+            &*object_store,
+            1,
+            AggregationRound::Scheduler,
+        )
+        .await
+        .unwrap();
+        let err = into_recursive_proofs(L1BatchNumber(1), proofs, &[7]).unwrap_err();
+        assert_eq!(err.kind, JobErrorKind::InvalidInput);
+        assert!(!err.retryable);
+        assert_eq!(err.circuit_id, Some(3));
+        assert_eq!(err.blob_url.as_deref(), Some("proof_7.bin"));
+        assert_eq!(
+            err.message,
+            "Expected only recursive proofs for scheduler l1 batch 1, got base proof \
+             for circuit 3 from prover job 7"
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_scheduler_partial_input_fails_job() {
+        let object_store = ObjectStoreFactory::mock().create_store().await.unwrap();
+        let err = load_scheduler_partial_input(L1BatchNumber(5), &*object_store)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, JobErrorKind::MissingInput);
+        assert!(!err.retryable);
+        assert_eq!(err.blob_url.as_deref(), Some("scheduler_witness_5.bin"));
+
+        // This is the error persisted in the DB.
+        let persisted_error: serde_json::Value = serde_json::from_str(&err.to_json()).unwrap();
+        assert_eq!(
+            persisted_error["message"],
+            "scheduler partial input for batch 5 not found at key scheduler_witness_5.bin; \
+             basic circuits witness generation for the batch needs to be rerun"
+        );
+        assert_eq!(persisted_error["retryable"], false);
+    }
+
+    #[test]
+    fn corrupted_node_proof_is_reported() {
+        let l1_batch_number = L1BatchNumber(42);
+        let proof_job_ids: Vec<u32> = (100..113).collect();
+        let mut proofs = vec!["valid"; 13];
+        proofs[3] = "corrupted";
+        check_node_proofs(l1_batch_number, &proofs, &proof_job_ids, |_| true).unwrap();
+
+        let err = check_node_proofs(l1_batch_number, &proofs, &proof_job_ids, |proof| {
+            *proof == "valid"
+        })
+        .unwrap_err();
+        assert_eq!(err.kind, JobErrorKind::InvalidInput);
+        assert!(!err.retryable);
+        assert_eq!(err.circuit_id, Some(4));
+        assert_eq!(err.blob_url, Some(FriProofWrapper::encode_key(103)));
+        assert_eq!(
+            err.message,
+            "1 of 13 node proofs for scheduler l1 batch 42 failed verification \
+             against the node VK; the node aggregation jobs producing them should be requeued. \
+             Invalid proofs: circuit 4 (prover job 103)"
+        );
+    }
+
+    #[test]
+    fn incomplete_final_prover_job_ids_are_reported() {
+        let l1_batch_number = L1BatchNumber(42);
+        let mut job_ids = [(0, None); 13];
+        for ((job_id, circuit_id), id) in job_ids.iter_mut().zip(1..).zip(100..) {
+            *job_id = (circuit_id, Some(id));
+        }
+        let validated = validate_final_prover_job_ids(l1_batch_number, Some(job_ids)).unwrap();
+        assert_eq!(validated[12], (13, 112));
+
+        let err = validate_final_prover_job_ids(l1_batch_number, None).unwrap_err();
+        assert_eq!(
+            err,
+            "l1 batch 42 is not tracked by the scheduler dependency tracker"
+        );
+
+        job_ids[1].1 = None;
+        job_ids[4].1 = Some(0);
+        job_ids[9].1 = Some(107);
+        // Reported circuits don't depend on the order of recorded jobs.
+        job_ids.reverse();
+        let err = validate_final_prover_job_ids(l1_batch_number, Some(job_ids)).unwrap_err();
+        assert_eq!(
+            err,
+            "final node proofs for l1 batch 42 are missing for circuits [2, 5, 8, 10] in the \
+             scheduler dependency tracker: unset job IDs for circuits [2], zero job IDs for \
+             circuits [5], job ID 107 shared by circuits [8, 10]"
+        );
+    }
+
+    #[test]
+    fn final_prover_jobs_are_ordered_by_expected_circuits() {
+        let l1_batch_number = L1BatchNumber(42);
+        let expected_circuit_ids: Vec<u8> = (1..=13).collect();
+        let proof_jobs: Vec<(u8, u32)> = (1..=13).zip(100..).collect();
+        let mut shuffled_jobs = proof_jobs.clone();
+        shuffled_jobs.reverse();
+        shuffled_jobs.swap(0, 5);
+        shuffled_jobs.swap(3, 11);
+
+        let expected_job_ids: Vec<u32> = (100..113).collect();
+        for jobs in [&proof_jobs, &shuffled_jobs] {
+            let job_ids =
+                order_final_prover_job_ids(l1_batch_number, jobs, &expected_circuit_ids).unwrap();
+            assert_eq!(job_ids, expected_job_ids);
+        }
+
+        let mut jobs_with_duplicate_circuit = shuffled_jobs;
+        jobs_with_duplicate_circuit[2].0 = 1;
+        let err = order_final_prover_job_ids(
+            l1_batch_number,
+            &jobs_with_duplicate_circuit,
+            &expected_circuit_ids,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, JobErrorKind::InvalidInput);
+        assert_eq!(err.circuit_id, Some(11));
+        assert_eq!(
+            err.message,
+            "Final node prover jobs for scheduler l1 batch 42 are recorded for circuits \
+             [8, 12, 1, 2, 9, 13, 7, 6, 5, 4, 3, 10, 1], which don't match the circuit sequence \
+             [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13] expected by the scheduler circuit"
+        );
+
+        let err =
+            order_final_prover_job_ids(l1_batch_number, &proof_jobs[1..], &expected_circuit_ids)
+                .unwrap_err();
+        assert_eq!(err.circuit_id, Some(1));
+    }
+
+    /// Returns IDs of final node prover jobs for circuits 1..=13 and their circuit IDs.
+    fn final_node_prover_jobs() -> (Vec<u32>, HashMap<u32, (u8, Option<String>)>) {
+        let proof_job_ids: Vec<u32> = (100..113).collect();
+        let prover_jobs = proof_job_ids
+            .iter()
+            .zip(1_u8..)
+            .map(|(&id, circuit_id)| (id, (circuit_id, None)))
+            .collect();
+        (proof_job_ids, prover_jobs)
+    }
+
+    #[test]
+    fn commitments_are_computed_from_witness() {
+        let mut witness = SchedulerCircuitInstanceWitness::default();
+        witness.block_meta_parameters.bootloader_code_hash = [1; 32];
+        witness.block_meta_parameters.default_aa_code_hash = [2; 32];
+        witness.storage_application_observable_output.new_root_hash = [3; 32];
+        witness
+            .storage_application_observable_output
+            .new_next_enumeration_counter = [42, 1];
+
+        let commitments = scheduler_commitments(&witness);
+        let expected = BlockCommitment::new(
+            vec![],
+            (1 << 32) + 42,
+            H256([3; 32]),
+            vec![],
+            vec![],
+            H256([1; 32]),
+            H256([2; 32]),
+        )
+        .hash();
+        assert_eq!(commitments.meta_parameters_hash, expected.meta_parameters);
+        assert_eq!(
+            commitments.pass_through_data_hash,
+            expected.pass_through_data
+        );
+    }
+
+    #[test]
+    fn proof_witness_sources_are_checked() {
+        let l1_batch_number = L1BatchNumber(42);
+        let expected_circuit_ids: Vec<u8> = (1..=13).collect();
+        let (proof_job_ids, mut prover_jobs) = final_node_prover_jobs();
+        prover_jobs.insert(100, (1, Some("custom_proof.bin".to_owned())));
+
+        let sources = proof_witness_sources(
+            l1_batch_number,
+            &proof_job_ids,
+            &prover_jobs,
+            &expected_circuit_ids,
+        )
+        .unwrap();
+        assert_eq!(sources.len(), 13);
+        assert_eq!(sources[0].proof_blob_url, "custom_proof.bin");
+        assert_eq!(
+            sources[12],
+            SchedulerProofWitnessSource {
+                position: 12,
+                prover_job_id: 112,
+                circuit_id: 13,
+                proof_blob_url: FriProofWrapper::encode_key(112),
+            }
+        );
+
+        let mut swapped_job_ids = proof_job_ids.clone();
+        swapped_job_ids.swap(3, 4);
+        let err = proof_witness_sources(
+            l1_batch_number,
+            &swapped_job_ids,
+            &prover_jobs,
+            &expected_circuit_ids,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, JobErrorKind::InvalidInput);
+        assert_eq!(err.circuit_id, Some(5));
+        assert_eq!(
+            err.message,
+            "Final node prover job 104 at position 3 for scheduler l1 batch 42 is \
+             for circuit 5, expected circuit 4"
+        );
+
+        prover_jobs.remove(&107);
+        let err = proof_witness_sources(
+            l1_batch_number,
+            &proof_job_ids,
+            &prover_jobs,
+            &expected_circuit_ids,
+        )
+        .unwrap_err();
+        assert_eq!(err.circuit_id, Some(8));
+        assert_eq!(
+            err.message,
+            "Final node prover job 107 for scheduler l1 batch 42 doesn't exist"
+        );
+    }
+
+    #[test]
+    fn missing_duplicate_and_extra_proofs_are_reported() {
+        let l1_batch_number = L1BatchNumber(42);
+        let expected_circuit_ids: Vec<u8> = (1..=13).collect();
+        let (proof_job_ids, mut prover_jobs) = final_node_prover_jobs();
+
+        let mut missing_job_ids = proof_job_ids.clone();
+        missing_job_ids.remove(4);
+        let err = proof_witness_sources(
+            l1_batch_number,
+            &missing_job_ids,
+            &prover_jobs,
+            &expected_circuit_ids,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, JobErrorKind::InvalidInput);
+        assert!(!err.retryable);
+        assert_eq!(err.circuit_id, Some(5));
+        assert_eq!(
+            err.message,
+            "Got 12 final node prover jobs for scheduler l1 batch 42, expected 13 \
+             (one for each circuit); missing circuits: [5], extra prover jobs: []"
+        );
+
+        let mut duplicate_job_ids = proof_job_ids.clone();
+        duplicate_job_ids[4] = 103;
+        let err = proof_witness_sources(
+            l1_batch_number,
+            &duplicate_job_ids,
+            &prover_jobs,
+            &expected_circuit_ids,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, JobErrorKind::InvalidInput);
+        assert_eq!(
+            err.message,
+            "Final node prover jobs [103] are listed more than once for scheduler l1 batch 42; \
+             the scheduler dependency tracker for the batch needs to be fixed"
+        );
+
+        let mut extra_job_ids = proof_job_ids;
+        extra_job_ids.push(200);
+        prover_jobs.insert(200, (5, None));
+        let err = proof_witness_sources(
+            l1_batch_number,
+            &extra_job_ids,
+            &prover_jobs,
+            &expected_circuit_ids,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, JobErrorKind::InvalidInput);
+        assert_eq!(err.circuit_id, None);
+        assert_eq!(
+            err.message,
+            "Got 14 final node prover jobs for scheduler l1 batch 42, expected 13 \
+             (one for each circuit); missing circuits: [], extra prover jobs: [200 (circuit 5)]"
+        );
+    }
+
+    /// Copies verification keys from the base directory to `dir`, replacing the node VK.
+    fn copy_vks(dir: &Path, node_vk: &ZkSyncRecursionLayerVerificationKey) {
+        fs::create_dir_all(dir).unwrap();
+        for entry in fs::read_dir(get_base_vk_path()).unwrap() {
+            let path = entry.unwrap().path();
+            let file_name = path.file_name().unwrap().to_str().unwrap();
+            if file_name.starts_with("verification_") && file_name.ends_with("_key.json") {
+                fs::copy(&path, dir.join(file_name)).unwrap();
+            }
+        }
+        let node_vk = serde_json::to_string_pretty(node_vk).unwrap();
+        fs::write(dir.join("verification_node_key.json"), node_vk).unwrap();
+    }
+
+    #[test]
+    fn vks_are_picked_for_protocol_version() {
+        let node_vk_circuit_id = ZkSyncRecursionLayerStorageType::NodeLayerCircuit as u8;
+        let node_vk = try_get_recursive_layer_vk_for_circuit_type(node_vk_circuit_id).unwrap();
+        let mut old_node_vk = node_vk.clone().into_inner();
+        old_node_vk.setup_merkle_tree_cap.reverse();
+        let old_node_vk = ZkSyncRecursionLayerVerificationKey::NodeLayerCircuit(old_node_vk);
+
+        // Keys for the current version 2 are in the base directory, and keys for version 1
+        // are in a versioned subdirectory.
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let base_vk_path = temp_dir.path().to_str().unwrap();
+        copy_vks(temp_dir.path(), &node_vk);
+        copy_vks(&temp_dir.path().join("v1"), &old_node_vk);
+
+        let l1_batch_number = L1BatchNumber(1);
+        for (protocol_version, expected_node_vk) in [
+            (None, &node_vk),
+            (Some(1), &old_node_vk),
+            (Some(2), &node_vk),
+        ] {
+            let vk_path = scheduler_vk_path(base_vk_path, Some(2), protocol_version);
+            let vks = load_scheduler_vks(l1_batch_number, protocol_version, &vk_path).unwrap();
+            assert_eq!(
+                serde_json::to_value(&vks.node_vk).unwrap(),
+                serde_json::to_value(expected_node_vk).unwrap(),
+                "{:?}",
+                protocol_version
+            );
+            assert_eq!(vks.leaf_layer_parameters.len(), 13);
+        }
+
+        let vk_path = scheduler_vk_path(base_vk_path, Some(2), Some(3));
+        assert_eq!(vk_path, format!("{}/v3", base_vk_path));
+        let err = match load_scheduler_vks(l1_batch_number, Some(3), &vk_path) {
+            Ok(_) => panic!("keys for protocol version 3 should be absent"),
+            Err(err) => err,
+        };
+        assert_eq!(err.kind, JobErrorKind::VerificationKey);
+        assert!(!err.retryable);
+        assert!(
+            err.message.starts_with(&format!(
+                "Verification keys for scheduler l1 batch 1 (protocol version 3) are not \
+                 available on this witness generator at {}/v3: ",
+                base_vk_path
+            )),
+            "{}",
+            err.message
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a GCS emulator"]
+    async fn scheduler_circuit_is_saved_to_gcs() {
+        let object_store = create_gcs_emulator_store().await;
+        let node_vk_circuit_id = ZkSyncRecursionLayerStorageType::NodeLayerCircuit as u8;
+        let node_vk = try_get_recursive_layer_vk_for_circuit_type(node_vk_circuit_id).unwrap();
+        let scheduler_circuit = SchedulerCircuit {
+            witness: SchedulerCircuitInstanceWitness::default(),
+            config: SchedulerConfig {
+                proof_config: recursion_layer_proof_config(),
+                vk_fixed_parameters: node_vk.into_inner().fixed_parameters,
+                capacity: SCHEDULER_CAPACITY,
+                _marker: std::marker::PhantomData,
+            },
+            transcript_params: (),
+            _marker: std::marker::PhantomData,
+        };
+        let scheduler_circuit = ZkSyncRecursiveLayerCircuit::SchedulerCircuit(scheduler_circuit);
+
+        let l1_batch_number = L1BatchNumber(1);
+        let (key, size) =
+            save_scheduler_circuit(l1_batch_number, scheduler_circuit, &*object_store)
+                .await
+                .unwrap();
+        assert_eq!(key, "1_0_1_Scheduler_0.bin");
+        let raw_size = object_store
+            .get_raw_size(CircuitWrapper::BUCKET, &key)
+            .await
+            .unwrap();
+        assert_eq!(raw_size, size);
+
+        let circuit: CircuitWrapper = object_store
+            .get(scheduler_circuit_key(l1_batch_number))
+            .await
+            .unwrap();
+        assert!(matches!(
+            circuit,
+            CircuitWrapper::Recursive(ZkSyncRecursiveLayerCircuit::SchedulerCircuit(_))
+        ));
+        object_store
+            .remove_raw(CircuitWrapper::BUCKET, &key)
+            .await
+            .unwrap();
     }
 }
@@ -0,0 +1,112 @@
+//! Latency between sealing L1 batches and saving results of their witness jobs, which allows
+//! charting the waterfall of the witness generation pipeline.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use zksync_dal::ConnectionPool;
+use zksync_types::proofs::AggregationRound;
+use zksync_types::L1BatchNumber;
+
+/// Reports the latency between sealing the L1 batch and saving the result of its witness job
+/// in the `prover_fri.witness_generation.sealed_to_<round>_saved_seconds` metric and records it
+/// for the job, so that it can be queried for SLO reporting. `job_id` is the L1 batch number
+/// for basic circuits and scheduler jobs.
+///
+/// The sealing time is recorded by the server, so the latency is affected by clock skew between
+/// the server DB and the witness generator; negative latencies are clamped to zero and counted
+/// in the `prover_fri.witness_generation.sealed_latency_clock_skew` metric.
+pub async fn report_sealed_to_saved_latency(
+    connection_pool: &ConnectionPool,
+    prover_connection_pool: &ConnectionPool,
+    aggregation_round: AggregationRound,
+    l1_batch_number: L1BatchNumber,
+    job_id: u32,
+) {
+    let sealed_at = connection_pool
+        .access_storage()
+        .await
+        .blocks_dal()
+        .get_l1_batch_sealed_at(l1_batch_number)
+        .await;
+    let sealed_at = match sealed_at {
+        Some(sealed_at) => sealed_at,
+        None => {
+            vlog::warn!(
+                "L1 batch {} is absent in the server DB, not reporting its {:?} latency",
+                l1_batch_number,
+                aggregation_round
+            );
+            return;
+        }
+    };
+    let saved_at = Utc::now();
+    let latency = match latency_between(sealed_at, saved_at) {
+        Some(latency) => latency,
+        None => {
+            vlog::warn!(
+                "L1 batch {} was sealed at {} after its {:?} job result was saved at {}; \
+                 clocks of the server DB and the witness generator are skewed",
+                l1_batch_number,
+                sealed_at,
+                aggregation_round,
+                saved_at
+            );
+            metrics::counter!(
+                "prover_fri.witness_generation.sealed_latency_clock_skew",
+                1,
+                "aggregation_round" => format!("{:?}", aggregation_round),
+            );
+            Duration::ZERO
+        }
+    };
+
+    metrics::histogram!(latency_metric_name(aggregation_round), latency);
+    prover_connection_pool
+        .access_storage()
+        .await
+        .fri_witness_generator_dal()
+        .set_sealed_to_saved_latency(aggregation_round, job_id, latency)
+        .await;
+}
+
+fn latency_metric_name(aggregation_round: AggregationRound) -> &'static str {
+    match aggregation_round {
+        AggregationRound::BasicCircuits => {
+            "prover_fri.witness_generation.sealed_to_basic_circuits_saved_seconds"
+        }
+        AggregationRound::LeafAggregation => {
+            "prover_fri.witness_generation.sealed_to_leaf_aggregation_saved_seconds"
+        }
+        AggregationRound::NodeAggregation => {
+            "prover_fri.witness_generation.sealed_to_node_aggregation_saved_seconds"
+        }
+        AggregationRound::Scheduler => {
+            "prover_fri.witness_generation.sealed_to_scheduler_saved_seconds"
+        }
+    }
+}
+
+/// Returns the latency between the timestamps, or `None` if `saved_at` precedes `sealed_at`.
+fn latency_between(sealed_at: DateTime<Utc>, saved_at: DateTime<Utc>) -> Option<Duration> {
+    (saved_at - sealed_at).to_std().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_is_computed_between_timestamps() {
+        let sealed_at = Utc::now();
+        let saved_at = sealed_at + chrono::Duration::milliseconds(90_500);
+        assert_eq!(
+            latency_between(sealed_at, saved_at),
+            Some(Duration::from_millis(90_500))
+        );
+        assert_eq!(latency_between(sealed_at, sealed_at), Some(Duration::ZERO));
+        // Timestamps are reversed if the clocks are skewed.
+        assert_eq!(latency_between(saved_at, sealed_at), None);
+    }
+}
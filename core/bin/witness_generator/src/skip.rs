@@ -0,0 +1,32 @@
+//! Admin command marking witness jobs for an L1 batch as skipped.
+
+use zksync_dal::ConnectionPool;
+use zksync_types::L1BatchNumber;
+
+/// Marks witness jobs of all aggregation rounds for the L1 batch as skipped, e.g. after the batch
+/// was reverted or its blobs were pruned, so that witness generators stop picking them up.
+/// Successful jobs are left intact.
+pub async fn skip_l1_batch(
+    prover_connection_pool: &ConnectionPool,
+    l1_batch_number: L1BatchNumber,
+) -> anyhow::Result<()> {
+    let skipped_rounds = prover_connection_pool
+        .access_storage()
+        .await
+        .fri_witness_generator_dal()
+        .mark_l1_batch_witness_jobs_as_skipped(l1_batch_number)
+        .await;
+    if skipped_rounds.is_empty() {
+        anyhow::bail!(
+            "no witness jobs to skip for l1 batch {}; jobs are either absent, successful \
+             or already skipped",
+            l1_batch_number
+        );
+    }
+    vlog::info!(
+        "Skipped witness jobs for l1 batch {} in rounds {:?}",
+        l1_batch_number,
+        skipped_rounds
+    );
+    Ok(())
+}
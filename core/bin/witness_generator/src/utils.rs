@@ -17,13 +17,26 @@ use zkevm_test_harness::boojum::field::goldilocks::GoldilocksField;
 use zkevm_test_harness::witness::full_block_artifact::BlockBasicCircuits;
 
 use zkevm_test_harness::zkevm_circuits::scheduler::block_header::BlockAuxilaryOutputWitness;
+use zksync_config::configs::FriWitnessGeneratorConfig;
 use zksync_config::constants::USED_BOOTLOADER_MEMORY_BYTES;
+use zksync_dal::fri_witness_generator_dal::FriWitnessJobStatus;
+use zksync_dal::ConnectionPool;
 use zksync_object_store::{
-    serialize_using_bincode, AggregationsKey, Bucket, ClosedFormInputKey, FriCircuitKey,
-    ObjectStore, StoredObject,
+    deserialize_versioned, impl_stored_object, serialize_versioned, AggregationsKey,
+    ClosedFormInputKey, FriCircuitKey, ObjectStore, ObjectStoreError, ProofBundle, PutOutcome,
+    StoredObject,
 };
+use zksync_queued_job_processor::{JobError, JobErrorKind, PendingJobsCount, RetryPolicy};
 use zksync_types::proofs::AggregationRound;
-use zksync_types::{L1BatchNumber, U256};
+use zksync_types::web3::signing::keccak256;
+use zksync_types::{L1BatchNumber, H256, U256};
+
+use futures::{stream, StreamExt, TryStreamExt};
+
+use std::{
+    error, fmt,
+    time::{Duration, Instant},
+};
 
 pub fn expand_bootloader_contents(packed: &[(usize, U256)]) -> Vec<u8> {
     let mut result: Vec<u8> = Vec::new();
@@ -36,7 +49,7 @@ pub fn expand_bootloader_contents(packed: &[(usize, U256)]) -> Vec<u8> {
     result.to_vec()
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub enum CircuitWrapper {
     Base(
         ZkSyncBaseLayerCircuit<
@@ -48,44 +61,106 @@ pub enum CircuitWrapper {
     Recursive(ZkSyncRecursiveLayerCircuit),
 }
 
-impl StoredObject for CircuitWrapper {
-    const BUCKET: Bucket = Bucket::ProverJobsFri;
-    type Key<'a> = FriCircuitKey;
+impl_stored_object!(CircuitWrapper {
+    bucket: ProverJobsFri,
+    key: FriCircuitKey {
+        block_number,
+        sequence_number,
+        circuit_id,
+        aggregation_round,
+        depth,
+    },
+    key_format: "{block_number}_{sequence_number}_{circuit_id}_{aggregation_round:?}_{depth}.bin",
+    codec: bincode,
+});
+
+impl CircuitWrapper {
+    pub fn numeric_circuit_type(&self) -> u8 {
+        match self {
+            Self::Base(circuit) => circuit.numeric_circuit_type(),
+            Self::Recursive(circuit) => circuit.numeric_circuit_type(),
+        }
+    }
 
-    fn encode_key(key: Self::Key<'_>) -> String {
-        let FriCircuitKey {
+    /// Returns the key under which this circuit is stored at the specified position.
+    pub fn circuit_key(
+        &self,
+        block_number: L1BatchNumber,
+        position: CircuitPosition,
+    ) -> FriCircuitKey {
+        position.circuit_key(block_number, self.numeric_circuit_type())
+    }
+}
+
+/// Position of a circuit among the circuits generated for an L1 batch. Together with the numeric
+/// circuit type, it determines the key of the stored circuit, which is mirrored by the fields
+/// of the prover job for the circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitPosition {
+    /// Basic circuit with the specified index among all basic circuits of the batch.
+    Basic { sequence_number: usize },
+    /// Leaf aggregation circuit with the specified index among leaf circuits of the same type.
+    Leaf { sequence_number: usize },
+    /// Node aggregation circuit at the specified depth of the aggregation tree for base layer
+    /// circuits of the specified type. All node circuits have the same type, so their keys use
+    /// the base layer circuit type instead.
+    Node {
+        base_layer_circuit_id: u8,
+        depth: u16,
+        sequence_number: usize,
+    },
+    /// The only scheduler circuit of the batch.
+    Scheduler,
+}
+
+impl CircuitPosition {
+    pub fn aggregation_round(self) -> AggregationRound {
+        match self {
+            Self::Basic { .. } => AggregationRound::BasicCircuits,
+            Self::Leaf { .. } => AggregationRound::LeafAggregation,
+            Self::Node { .. } => AggregationRound::NodeAggregation,
+            Self::Scheduler => AggregationRound::Scheduler,
+        }
+    }
+
+    /// Returns the key of a circuit with the specified numeric type at this position.
+    pub fn circuit_key(self, block_number: L1BatchNumber, circuit_type: u8) -> FriCircuitKey {
+        let (circuit_id, sequence_number, depth) = match self {
+            Self::Basic { sequence_number } | Self::Leaf { sequence_number } => {
+                (circuit_type, sequence_number, 0)
+            }
+            Self::Node {
+                base_layer_circuit_id,
+                depth,
+                sequence_number,
+            } => (base_layer_circuit_id, sequence_number, depth),
+            Self::Scheduler => (circuit_type, 0, 0),
+        };
+        FriCircuitKey {
             block_number,
             sequence_number,
             circuit_id,
-            aggregation_round,
+            aggregation_round: self.aggregation_round(),
             depth,
-        } = key;
-        format!("{block_number}_{sequence_number}_{circuit_id}_{aggregation_round:?}_{depth}.bin")
+        }
     }
-
-    serialize_using_bincode!();
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct ClosedFormInputWrapper(
     pub(crate) Vec<ZkSyncBaseLayerClosedFormInput<GoldilocksField>>,
     pub(crate) RecursionQueueSimulator<GoldilocksField>,
 );
 
-impl StoredObject for ClosedFormInputWrapper {
-    const BUCKET: Bucket = Bucket::LeafAggregationWitnessJobsFri;
-    type Key<'a> = ClosedFormInputKey;
-
-    fn encode_key(key: Self::Key<'_>) -> String {
-        let ClosedFormInputKey {
-            block_number,
-            circuit_id,
-        } = key;
-        format!("closed_form_inputs_{block_number}_{circuit_id}.bin")
-    }
-
-    serialize_using_bincode!();
-}
+impl_stored_object!(ClosedFormInputWrapper {
+    bucket: LeafAggregationWitnessJobsFri,
+    key: ClosedFormInputKey {
+        block_number,
+        circuit_id
+    },
+    key_format: "closed_form_inputs_{block_number}_{circuit_id}.bin",
+    codec: bincode,
+});
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct AggregationWrapper(
@@ -96,21 +171,16 @@ pub struct AggregationWrapper(
     )>,
 );
 
-impl StoredObject for AggregationWrapper {
-    const BUCKET: Bucket = Bucket::NodeAggregationWitnessJobsFri;
-    type Key<'a> = AggregationsKey;
-
-    fn encode_key(key: Self::Key<'_>) -> String {
-        let AggregationsKey {
-            block_number,
-            circuit_id,
-            depth,
-        } = key;
-        format!("aggregations_{block_number}_{circuit_id}_{depth}.bin")
-    }
-
-    serialize_using_bincode!();
-}
+impl_stored_object!(AggregationWrapper {
+    bucket: NodeAggregationWitnessJobsFri,
+    key: AggregationsKey {
+        block_number,
+        circuit_id,
+        depth,
+    },
+    key_format: "aggregations_{block_number}_{circuit_id}_{depth}.bin",
+    codec: bincode,
+});
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct SchedulerPartialInputWrapper(
@@ -121,16 +191,12 @@ pub struct SchedulerPartialInputWrapper(
     >,
 );
 
-impl StoredObject for SchedulerPartialInputWrapper {
-    const BUCKET: Bucket = Bucket::SchedulerWitnessJobsFri;
-    type Key<'a> = L1BatchNumber;
-
-    fn encode_key(key: Self::Key<'_>) -> String {
-        format!("scheduler_witness_{key}.bin")
-    }
-
-    serialize_using_bincode!();
-}
+impl_stored_object!(SchedulerPartialInputWrapper {
+    bucket: SchedulerWitnessJobsFri,
+    key: L1BatchNumber as block_number,
+    key_format: "scheduler_witness_{block_number}.bin",
+    codec: bincode,
+});
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub enum FriProofWrapper {
@@ -138,86 +204,195 @@ pub enum FriProofWrapper {
     Recursive(ZkSyncRecursionLayerProof),
 }
 
-impl StoredObject for FriProofWrapper {
-    const BUCKET: Bucket = Bucket::ProofsFri;
-    type Key<'a> = u32;
+impl_stored_object!(FriProofWrapper {
+    bucket: ProofsFri,
+    key: u32 as job_id,
+    key_format: "proof_{job_id}.bin",
+    codec: bincode,
+});
 
-    fn encode_key(key: Self::Key<'_>) -> String {
-        format!("proof_{key}.bin")
-    }
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct AuxOutputWitnessWrapper(pub BlockAuxilaryOutputWitness<GoldilocksField>);
+
+impl_stored_object!(AuxOutputWitnessWrapper {
+    bucket: SchedulerWitnessJobsFri,
+    key: L1BatchNumber as block_number,
+    key_format: "aux_output_witness_{block_number}.bin",
+    codec: bincode,
+});
 
-    serialize_using_bincode!();
+/// Summary of the artifacts saved by the basic witness generator for an L1 batch. Unlike
+/// the artifacts themselves, the summary is stored as JSON, so that it can be inspected
+/// with standard tools when debugging.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BasicCircuitsSummary {
+    pub l1_batch_number: L1BatchNumber,
+    /// Circuit IDs and keys of base layer circuits.
+    pub circuits: Vec<(u8, String)>,
+    /// Circuit IDs, keys and basic circuit counts of closed form inputs.
+    pub closed_form_inputs: Vec<(u8, String, usize)>,
+    pub scheduler_witness_key: String,
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct AuxOutputWitnessWrapper(pub BlockAuxilaryOutputWitness<GoldilocksField>);
+impl_stored_object!(BasicCircuitsSummary {
+    bucket: SchedulerWitnessJobsFri,
+    key: L1BatchNumber as block_number,
+    key_format: "basic_circuits_summary_{block_number}.json",
+    codec: json,
+});
+
+/// Manifest of the sections of artifacts consumed by later rounds (closed form inputs for each
+/// circuit, the scheduler partial input and the aux output witness) saved by the basic witness
+/// generator for an L1 batch. The manifest is saved after all sections, and consumers check
+/// each fetched section against its hash in the manifest.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BasicArtifactsManifest {
+    pub l1_batch_number: L1BatchNumber,
+    pub sections: Vec<ArtifactSection>,
+}
+
+impl BasicArtifactsManifest {
+    fn section_hash(&self, key: &str) -> Option<H256> {
+        let section = self.sections.iter().find(|section| section.key == key)?;
+        Some(section.hash)
+    }
+}
+
+impl_stored_object!(BasicArtifactsManifest {
+    bucket: SchedulerWitnessJobsFri,
+    key: L1BatchNumber as block_number,
+    key_format: "basic_artifacts_manifest_{block_number}.json",
+    codec: json,
+});
 
-impl StoredObject for AuxOutputWitnessWrapper {
-    const BUCKET: Bucket = Bucket::SchedulerWitnessJobsFri;
-    type Key<'a> = L1BatchNumber;
+/// Section of artifacts listed in [`BasicArtifactsManifest`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ArtifactSection {
+    /// Encoded key of the section.
+    pub key: String,
+    /// Keccak-256 hash of the serialized section, as returned by the store.
+    pub hash: H256,
+}
 
-    fn encode_key(key: Self::Key<'_>) -> String {
-        format!("aux_output_witness_{key}.bin")
+/// Stores an artifact unless it's already stored (e.g., by a previous attempt to save the same
+/// job result), so that retried saves don't overwrite artifacts. Returns the key and the size
+/// of the serialized artifact.
+pub async fn put_artifact<V: StoredObject>(
+    object_store: &dyn ObjectStore,
+    key: V::Key<'_>,
+    value: &V,
+) -> Result<(String, u64), ObjectStoreError> {
+    let (key, size, outcome) = object_store.put_if_absent_with_size(key, value).await?;
+    if outcome != PutOutcome::Created {
+        vlog::info!(
+            "Artifact {} in bucket {} is already stored ({:?}); keeping the stored artifact",
+            key,
+            V::BUCKET,
+            outcome
+        );
     }
+    Ok((key, size))
+}
 
-    serialize_using_bincode!();
+/// Same as [`put_artifact()`], but also returns the [`ArtifactSection`] describing the stored
+/// artifact. If an artifact is already stored under the key, the section describes the stored one.
+pub async fn put_artifact_section<V: StoredObject>(
+    object_store: &dyn ObjectStore,
+    key: V::Key<'_>,
+    value: &V,
+) -> Result<(ArtifactSection, u64), ObjectStoreError> {
+    let key = V::encode_key(key);
+    let blob = serialize_versioned(value)?;
+    let size = blob.len() as u64;
+    let mut hash = H256(keccak256(&blob));
+    let outcome = object_store
+        .put_raw_if_absent(V::BUCKET, &key, blob)
+        .await?;
+    if outcome != PutOutcome::Created {
+        let stored_blob = object_store.get_raw(V::BUCKET, &key).await?;
+        let stored_hash = H256(keccak256(&stored_blob));
+        if stored_hash == hash {
+            vlog::info!(
+                "Artifact {} in bucket {} is already stored; keeping the stored artifact",
+                key,
+                V::BUCKET
+            );
+        } else {
+            vlog::warn!(
+                "Artifact {} in bucket {} differs from the stored one (hash {:?} vs {:?} stored); \
+                 keeping the stored artifact",
+                key,
+                V::BUCKET,
+                hash,
+                stored_hash
+            );
+            metrics::counter!(
+                "server.object_store.put_if_absent_conflicts",
+                1,
+                "bucket" => V::BUCKET.as_str()
+            );
+            hash = stored_hash;
+        }
+    }
+    Ok((ArtifactSection { key, hash }, size))
 }
 
 pub async fn save_base_prover_input_artifacts(
     block_number: L1BatchNumber,
     circuits: BlockBasicCircuits<GoldilocksField, ZkSyncDefaultRoundFunction>,
     object_store: &dyn ObjectStore,
-    aggregation_round: AggregationRound,
-) -> Vec<(u8, String)> {
+) -> Result<Vec<(u8, String)>, ObjectStoreError> {
     let circuits = circuits.into_flattened_set();
     let mut ids_and_urls = Vec::with_capacity(circuits.len());
+    let mut total_size = 0;
     for (sequence_number, circuit) in circuits.into_iter().enumerate() {
-        let circuit_id = circuit.numeric_circuit_type();
-        let circuit_key = FriCircuitKey {
-            block_number,
-            sequence_number,
-            circuit_id,
-            aggregation_round,
-            depth: 0,
-        };
-        let blob_url = object_store
-            .put(circuit_key, &CircuitWrapper::Base(circuit))
-            .await
-            .unwrap();
+        let circuit = CircuitWrapper::Base(circuit);
+        let circuit_key =
+            circuit.circuit_key(block_number, CircuitPosition::Basic { sequence_number });
+        let circuit_id = circuit_key.circuit_id;
+        let (blob_url, size) = put_artifact(object_store, circuit_key, &circuit).await?;
         ids_and_urls.push((circuit_id, blob_url));
+        total_size += size;
     }
-    ids_and_urls
+    report_circuits_size(
+        block_number,
+        AggregationRound::BasicCircuits,
+        ids_and_urls.len(),
+        total_size,
+    );
+    Ok(ids_and_urls)
 }
 
+/// Saves recursive layer circuits; `position` maps the index of a circuit in `aggregations`
+/// to its position. Circuits embed the proofs they aggregate, so they are copied one at a time
+/// rather than together with `aggregations`.
 pub async fn save_recursive_layer_prover_input_artifacts(
     block_number: L1BatchNumber,
-    aggregations: Vec<(
+    aggregations: &[(
         u64,
         RecursionQueueSimulator<GoldilocksField>,
         ZkSyncRecursiveLayerCircuit,
-    )>,
-    aggregation_round: AggregationRound,
-    depth: u16,
+    )],
     object_store: &dyn ObjectStore,
-    base_layer_circuit_id: Option<u8>,
-) -> Vec<(u8, String)> {
+    position: impl Fn(usize) -> CircuitPosition,
+) -> Result<Vec<(u8, String)>, ObjectStoreError> {
     let mut ids_and_urls = Vec::with_capacity(aggregations.len());
-    for (sequence_number, (_, _, circuit)) in aggregations.into_iter().enumerate() {
-        let circuit_id = base_layer_circuit_id.unwrap_or_else(|| circuit.numeric_circuit_type());
-        let circuit_key = FriCircuitKey {
-            block_number,
-            sequence_number,
-            circuit_id,
-            aggregation_round,
-            depth,
-        };
-        let blob_url = object_store
-            .put(circuit_key, &CircuitWrapper::Recursive(circuit))
-            .await
-            .unwrap();
+    let mut total_size = 0;
+    for (sequence_number, (_, _, circuit)) in aggregations.iter().enumerate() {
+        let circuit = CircuitWrapper::Recursive(circuit.clone());
+        let circuit_key = circuit.circuit_key(block_number, position(sequence_number));
+        let circuit_id = circuit_key.circuit_id;
+        let (blob_url, size) = put_artifact(object_store, circuit_key, &circuit).await?;
         ids_and_urls.push((circuit_id, blob_url));
+        total_size += size;
     }
-    ids_and_urls
+    report_circuits_size(
+        block_number,
+        position(0).aggregation_round(),
+        ids_and_urls.len(),
+        total_size,
+    );
+    Ok(ids_and_urls)
 }
 
 pub async fn save_node_aggregations_artifacts(
@@ -230,16 +405,14 @@ pub async fn save_node_aggregations_artifacts(
         ZkSyncRecursiveLayerCircuit,
     )>,
     object_store: &dyn ObjectStore,
-) -> String {
+) -> Result<String, ObjectStoreError> {
     let key = AggregationsKey {
         block_number,
         circuit_id,
         depth,
     };
-    object_store
-        .put(key, &AggregationWrapper(aggregations))
-        .await
-        .unwrap()
+    let (blob_url, _) = put_artifact(object_store, key, &AggregationWrapper(aggregations)).await?;
+    Ok(blob_url)
 }
 
 pub fn get_recursive_layer_circuit_id_for_base_layer(base_layer_circuit_id: u8) -> u8 {
@@ -253,13 +426,877 @@ pub fn get_base_layer_circuit_id_for_recursive_layer(recursive_layer_circuit_id:
     recursive_layer_circuit_id - ZkSyncRecursionLayerStorageType::NodeLayerCircuit as u8
 }
 
+/// Returns base layer circuit IDs, leaf and node aggregation jobs for which are picked up according
+/// to the allowlist and the denylist in the config, or `None` if jobs for all circuits are picked up.
+/// Returns an error if the lists contain unknown circuit IDs or exclude all circuits.
+pub fn configured_circuit_ids(
+    config: &FriWitnessGeneratorConfig,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    filter_circuit_ids(
+        config.circuit_ids_allowlist.as_deref(),
+        config.circuit_ids_denylist.as_deref(),
+    )
+}
+
+fn filter_circuit_ids(
+    allowlist: Option<&[u8]>,
+    denylist: Option<&[u8]>,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    if allowlist.is_none() && denylist.is_none() {
+        return Ok(None);
+    }
+    let known_circuit_ids =
+        (BaseLayerCircuitType::VM as u8)..=(BaseLayerCircuitType::L1MessagesHasher as u8);
+    let all_ids = allowlist.into_iter().chain(denylist).flatten();
+    if let Some(id) = all_ids.copied().find(|id| !known_circuit_ids.contains(id)) {
+        anyhow::bail!(
+            "circuit ID {} is out of range; expected a base layer circuit ID in {:?}",
+            id,
+            known_circuit_ids
+        );
+    }
+
+    let circuit_ids: Vec<_> = known_circuit_ids
+        .filter(|id| allowlist.map_or(true, |ids| ids.contains(id)))
+        .filter(|id| !denylist.map_or(false, |ids| ids.contains(id)))
+        .collect();
+    if circuit_ids.is_empty() {
+        anyhow::bail!(
+            "circuit IDs allowlist {:?} and denylist {:?} exclude all circuits",
+            allowlist,
+            denylist
+        );
+    }
+    Ok(Some(circuit_ids))
+}
+
+/// Reports a picked up job for the circuit, so that partitioning of jobs by circuit IDs
+/// among witness generator instances can be observed.
+pub fn report_job_pickup(aggregation_round: AggregationRound, circuit_id: u8) {
+    metrics::counter!(
+        "prover_fri.witness_generation.picked_up_jobs",
+        1,
+        "aggregation_round" => format!("{:?}", aggregation_round),
+        "circuit_id" => circuit_id.to_string(),
+    );
+}
+
+/// Retry policy for re-downloading objects that have failed checksum verification.
+const REDOWNLOAD_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 3,
+    base_backoff: Duration::from_secs(1),
+};
+
+/// Fetches an object from the store. If the object fails checksum verification (e.g., it was
+/// corrupted in transit), it's re-downloaded a few times before giving up.
+pub async fn get_verified<V: StoredObject>(
+    object_store: &dyn ObjectStore,
+    key: V::Key<'_>,
+) -> Result<V, ObjectStoreError> {
+    get_verified_by_encoded_key(object_store, &V::encode_key(key)).await
+}
+
+async fn get_verified_by_encoded_key<V: StoredObject>(
+    object_store: &dyn ObjectStore,
+    key: &str,
+) -> Result<V, ObjectStoreError> {
+    Ok(get_verified_timed(object_store, key).await?.object)
+}
+
+/// Object fetched by [`get_verified_timed()`].
+struct FetchedObject<V> {
+    object: V,
+    size: u64,
+    /// Time spent downloading the blob, including re-downloads after failed verification.
+    download_time: Duration,
+    deserialization_time: Duration,
+}
+
+impl<V> FetchedObject<V> {
+    fn latency(&self) -> Duration {
+        self.download_time + self.deserialization_time
+    }
+
+    fn report_timings(&self, aggregation_round: AggregationRound) {
+        metrics::histogram!(
+            "prover_fri.witness_generation.blob_download_time",
+            self.download_time,
+            "aggregation_round" => format!("{:?}", aggregation_round),
+        );
+        metrics::histogram!(
+            "prover_fri.witness_generation.blob_deserialization_time",
+            self.deserialization_time,
+            "aggregation_round" => format!("{:?}", aggregation_round),
+        );
+    }
+}
+
+/// Same as [`get_verified_by_encoded_key()`], but also returns the size of the fetched blob
+/// and the time spent downloading and deserializing it.
+async fn get_verified_timed<V: StoredObject>(
+    object_store: &dyn ObjectStore,
+    key: &str,
+) -> Result<FetchedObject<V>, ObjectStoreError> {
+    get_verified_timed_with_hash(object_store, key, None).await
+}
+
+/// Same as [`get_verified_timed()`], but if `expected_hash` is specified, the fetched blob
+/// additionally fails verification if its Keccak-256 hash doesn't match the expected one.
+async fn get_verified_timed_with_hash<V: StoredObject>(
+    object_store: &dyn ObjectStore,
+    key: &str,
+    expected_hash: Option<H256>,
+) -> Result<FetchedObject<V>, ObjectStoreError> {
+    let started_at = Instant::now();
+    // This module is also compiled as a standalone library, so it cannot use `crate::retry`.
+    let mut attempt = 1;
+    let blob = loop {
+        let blob = object_store.get_raw(V::BUCKET, key).await;
+        match blob.and_then(|blob| check_blob_hash(key, blob, expected_hash)) {
+            Err(err @ ObjectStoreError::ChecksumMismatch(_))
+                if attempt < REDOWNLOAD_POLICY.max_attempts =>
+            {
+                let backoff = REDOWNLOAD_POLICY.backoff_after(attempt);
+                vlog::warn!(
+                    "Object {} failed verification (attempt {}/{}), re-downloading in {:?}: {}",
+                    key,
+                    attempt,
+                    REDOWNLOAD_POLICY.max_attempts,
+                    backoff,
+                    err
+                );
+                metrics::counter!("prover_fri.witness_generation.transient_error_retries", 1);
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            result => break result?,
+        }
+    };
+    let download_time = started_at.elapsed();
+    let size = blob.len() as u64;
+    let started_at = Instant::now();
+    let object = deserialize_versioned(blob)?;
+    Ok(FetchedObject {
+        object,
+        size,
+        download_time,
+        deserialization_time: started_at.elapsed(),
+    })
+}
+
+fn check_blob_hash(
+    key: &str,
+    blob: Vec<u8>,
+    expected_hash: Option<H256>,
+) -> Result<Vec<u8>, ObjectStoreError> {
+    let Some(expected_hash) = expected_hash else {
+        return Ok(blob);
+    };
+    let hash = H256(keccak256(&blob));
+    if hash != expected_hash {
+        let message = format!("object {key} has hash {hash:?}, expected {expected_hash:?}");
+        return Err(ObjectStoreError::ChecksumMismatch(message.into()));
+    }
+    Ok(blob)
+}
+
+/// Fetches the [`BasicArtifactsManifest`] for the L1 batch. Returns `None` for batches saved
+/// before manifests were introduced.
+pub async fn get_basic_artifacts_manifest(
+    object_store: &dyn ObjectStore,
+    block_number: L1BatchNumber,
+) -> Result<Option<BasicArtifactsManifest>, ObjectStoreError> {
+    match get_verified::<BasicArtifactsManifest>(object_store, block_number).await {
+        Ok(manifest) => Ok(Some(manifest)),
+        Err(ObjectStoreError::KeyNotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Fetches a section of artifacts saved by the basic witness generator for an L1 batch (as per
+/// [`get_verified()`]) and checks it against the `manifest` of the batch, which is fetched
+/// once per job with [`get_basic_artifacts_manifest()`]. If the batch has no manifest,
+/// the section is fetched without the check. The download and deserialization time
+/// of the section is reported in the `prover_fri.witness_generation.blob_download_time` and
+/// `prover_fri.witness_generation.blob_deserialization_time` metrics.
+pub async fn get_basic_artifact_section<V: StoredObject>(
+    object_store: &dyn ObjectStore,
+    manifest: Option<&BasicArtifactsManifest>,
+    key: V::Key<'_>,
+    aggregation_round: AggregationRound,
+) -> Result<V, ObjectStoreError> {
+    let key = V::encode_key(key);
+    let expected_hash = match manifest {
+        Some(manifest) => {
+            let hash = manifest.section_hash(&key).ok_or_else(|| {
+                let message = format!(
+                    "section {key} is not listed in the manifest for l1 batch {}",
+                    manifest.l1_batch_number
+                );
+                ObjectStoreError::Other(message.into())
+            })?;
+            Some(hash)
+        }
+        None => None,
+    };
+    let fetched = get_verified_timed_with_hash(object_store, &key, expected_hash).await?;
+    fetched.report_timings(aggregation_round);
+    Ok(fetched.object)
+}
+
+/// Default max number of objects concurrently fetched by [`get_many()`].
+pub const DEFAULT_FETCH_CONCURRENCY: usize = 8;
+
+/// Error fetching one of the objects in [`get_many()`].
+#[derive(Debug)]
+pub struct GetManyError {
+    /// Encoded key of the object that has failed to be fetched.
+    pub key: String,
+    pub inner: ObjectStoreError,
+}
+
+impl fmt::Display for GetManyError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { key, inner } = self;
+        write!(formatter, "failed fetching object `{key}`: {inner}")
+    }
+}
+
+impl error::Error for GetManyError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.inner)
+    }
+}
+
+/// Fetches objects with the specified keys (as per [`get_verified()`]), with at most `concurrency`
+/// objects being fetched at a time. Objects are returned in the order of `keys`. Fails on the first
+/// error encountered; the remaining fetches are cancelled in this case.
+pub async fn get_many<'a, V: StoredObject>(
+    object_store: &dyn ObjectStore,
+    keys: impl IntoIterator<Item = V::Key<'a>>,
+    concurrency: usize,
+) -> Result<Vec<V>, GetManyError> {
+    let keys: Vec<_> = keys.into_iter().map(V::encode_key).collect();
+    let objects = get_many_timed(object_store, keys, concurrency).await?;
+    Ok(objects.into_iter().map(|fetched| fetched.object).collect())
+}
+
+/// Same as [`get_many()`], but accepts encoded keys and returns the fetch timings and the blob
+/// size of each object along with it.
+async fn get_many_timed<V: StoredObject>(
+    object_store: &dyn ObjectStore,
+    keys: Vec<String>,
+    concurrency: usize,
+) -> Result<Vec<FetchedObject<V>>, GetManyError> {
+    let mut objects: Vec<Option<FetchedObject<V>>> = keys.iter().map(|_| None).collect();
+    // Objects are fetched out of order, so that an error for any of them is returned immediately.
+    let mut fetches = stream::iter(keys.into_iter().enumerate())
+        .map(|(i, key)| async move {
+            // Futures are only polled once there's a free slot, so timings don't include
+            // time spent waiting for other fetches.
+            match get_verified_timed(object_store, &key).await {
+                Ok(object) => Ok((i, object)),
+                Err(inner) => Err(GetManyError { key, inner }),
+            }
+        })
+        .buffer_unordered(concurrency.max(1));
+    while let Some((i, object)) = fetches.try_next().await? {
+        objects[i] = Some(object);
+    }
+    Ok(objects.into_iter().map(Option::unwrap).collect())
+}
+
+/// Loads proofs for the specified prover jobs of a job for the L1 batch in the order of `job_ids`
+/// (as per [`get_many()`]). The fetch latency of each proof (as a whole and split into
+/// the download and deserialization time) and the total size of proofs are reported,
+/// and the slowest proof is logged.
 pub async fn load_proofs_for_job_ids(
+    l1_batch_number: L1BatchNumber,
+    job_ids: &[u32],
+    object_store: &dyn ObjectStore,
+    concurrency: usize,
+    aggregation_round: AggregationRound,
+) -> Result<Vec<FriProofWrapper>, GetManyError> {
+    let keys = job_ids
+        .iter()
+        .copied()
+        .map(FriProofWrapper::encode_key)
+        .collect();
+    let proofs = get_many_timed::<FriProofWrapper>(object_store, keys, concurrency).await?;
+    for proof in &proofs {
+        metrics::histogram!(
+            "prover_fri.witness_generation.proof_fetch_time",
+            proof.latency(),
+            "aggregation_round" => format!("{:?}", aggregation_round),
+        );
+        proof.report_timings(aggregation_round);
+    }
+    let total_size: u64 = proofs.iter().map(|proof| proof.size).sum();
+    metrics::histogram!(
+        "prover_fri.witness_generation.proofs_size_bytes",
+        total_size as f64,
+        "aggregation_round" => format!("{:?}", aggregation_round),
+    );
+    vlog::info!(
+        "Loaded {} proofs ({} bytes) for {:?} round of l1 batch {}",
+        proofs.len(),
+        total_size,
+        aggregation_round,
+        l1_batch_number
+    );
+    let slowest_proof = job_ids
+        .iter()
+        .zip(&proofs)
+        .max_by_key(|(_, proof)| proof.latency());
+    if let Some((&job_id, proof)) = slowest_proof {
+        vlog::debug!(
+            "The slowest proof {} for {:?} round of l1 batch {} took {:?}",
+            FriProofWrapper::encode_key(job_id),
+            aggregation_round,
+            l1_batch_number,
+            proof.latency()
+        );
+    }
+    Ok(proofs.into_iter().map(|proof| proof.object).collect())
+}
+
+/// Reports the total size of serialized circuits saved by a job for the L1 batch.
+pub fn report_circuits_size(
+    l1_batch_number: L1BatchNumber,
+    aggregation_round: AggregationRound,
+    circuit_count: usize,
+    total_size: u64,
+) {
+    metrics::histogram!(
+        "prover_fri.witness_generation.artifact_size_bytes",
+        total_size as f64,
+        "aggregation_round" => format!("{:?}", aggregation_round),
+    );
+    vlog::info!(
+        "Saved {} circuits ({} bytes) for {:?} round of l1 batch {}",
+        circuit_count,
+        total_size,
+        aggregation_round,
+        l1_batch_number
+    );
+}
+
+/// Loads proofs for the specified prover jobs from the proof bundle for the L1 batch. Returns `None`
+/// if the bundle is missing or contains proofs produced by other prover jobs (e.g., if proofs were
+/// regenerated after bundling), in which case proofs should be loaded using
+/// [`load_proofs_for_job_ids()`]. The deserialization time of the bundle includes the time
+/// spent deserializing the bundled proofs.
+pub async fn load_bundled_proofs<V: StoredObject>(
+    l1_batch_number: L1BatchNumber,
+    job_ids: &[u32],
+    object_store: &dyn ObjectStore,
+    aggregation_round: AggregationRound,
+) -> Result<Option<Vec<V>>, GetManyError> {
+    let key = ProofBundle::encode_key(l1_batch_number);
+    let mut fetched = match get_verified_timed::<ProofBundle>(object_store, &key).await {
+        Ok(fetched) => fetched,
+        Err(ObjectStoreError::KeyNotFound(_)) => return Ok(None),
+        Err(inner) => return Err(GetManyError { key, inner }),
+    };
+    let bundled_job_ids = fetched.object.job_ids();
+    if bundled_job_ids != job_ids {
+        vlog::warn!(
+            "Proof bundle {} contains proofs for prover jobs {:?}, expected {:?}; ignoring it",
+            key,
+            bundled_job_ids,
+            job_ids
+        );
+        return Ok(None);
+    }
+    let started_at = Instant::now();
+    let proofs = fetched.object.deserialize_proofs();
+    fetched.deserialization_time += started_at.elapsed();
+    fetched.report_timings(aggregation_round);
+    match proofs {
+        Ok(proofs) => Ok(Some(proofs)),
+        Err(inner) => Err(GetManyError { key, inner }),
+    }
+}
+
+/// Checks which proofs for the specified prover jobs are missing in the store without downloading
+/// them, with at most `concurrency` checks performed at a time. Returns IDs of the jobs with
+/// missing proofs in the order of `job_ids`.
+pub async fn find_missing_proofs(
     job_ids: &[u32],
     object_store: &dyn ObjectStore,
-) -> Vec<FriProofWrapper> {
-    let mut proofs = Vec::with_capacity(job_ids.len());
-    for &job_id in job_ids {
-        proofs.push(object_store.get(job_id).await.unwrap());
+    concurrency: usize,
+) -> Result<Vec<u32>, GetManyError> {
+    let checks = stream::iter(job_ids)
+        .map(|&job_id| async move {
+            match object_store.contains::<FriProofWrapper>(job_id).await {
+                Ok(exists) => Ok((!exists).then_some(job_id)),
+                Err(inner) => Err(GetManyError {
+                    key: FriProofWrapper::encode_key(job_id),
+                    inner,
+                }),
+            }
+        })
+        .buffered(concurrency.max(1));
+    let missing_job_ids: Vec<_> = checks.try_collect().await?;
+    Ok(missing_job_ids.into_iter().flatten().collect())
+}
+
+/// Counts pending witness jobs for the specified aggregation round.
+pub async fn count_pending_witness_jobs(
+    prover_connection_pool: &ConnectionPool,
+    aggregation_round: AggregationRound,
+) -> PendingJobsCount {
+    let mut prover_connection = prover_connection_pool.access_storage().await;
+    let mut dal = prover_connection.fri_witness_generator_dal();
+    PendingJobsCount {
+        queued: dal
+            .count_witness_jobs_with_status(aggregation_round, FriWitnessJobStatus::Queued)
+            .await,
+        in_progress: dal
+            .count_witness_jobs_with_status(aggregation_round, FriWitnessJobStatus::InProgress)
+            .await,
+    }
+}
+
+/// Returns `true` if the error is caused by the object store being temporarily unavailable,
+/// i.e., by its circuit breaker being open.
+pub fn is_store_unavailable(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<ObjectStoreError>(),
+            Some(ObjectStoreError::CircuitOpen(_))
+        )
+    })
+}
+
+/// Classifies errors caused by the object store being temporarily unavailable
+/// as [`JobErrorKind::Unavailable`], so that jobs are released back to the queue rather
+/// than failed. Other errors are returned as is.
+pub fn classify_store_unavailable(err: anyhow::Error) -> anyhow::Error {
+    if is_store_unavailable(&err) {
+        JobError::new(JobErrorKind::Unavailable, format!("{:#}", err)).into()
+    } else {
+        err
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use std::convert::TryInto;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use zksync_object_store::{proof_key, Bucket, ObjectStoreFactory};
+
+    use super::*;
+
+    type BoxedError = Box<dyn error::Error + Send + Sync>;
+
+    /// Checks that keys match the ones encoded before keys were encoded by `impl_stored_object!`,
+    /// so that previously stored blobs remain readable.
+    #[test]
+    fn stored_object_keys_are_compatible() {
+        let circuit_key = FriCircuitKey {
+            block_number: L1BatchNumber(1),
+            sequence_number: 2,
+            circuit_id: 3,
+            aggregation_round: AggregationRound::NodeAggregation,
+            depth: 4,
+        };
+        assert_eq!(
+            CircuitWrapper::encode_key(circuit_key),
+            "1_2_3_NodeAggregation_4.bin"
+        );
+        assert_eq!(CircuitWrapper::BUCKET, Bucket::ProverJobsFri);
+
+        let closed_form_input_key = ClosedFormInputKey {
+            block_number: L1BatchNumber(1),
+            circuit_id: 2,
+        };
+        assert_eq!(
+            ClosedFormInputWrapper::encode_key(closed_form_input_key),
+            "closed_form_inputs_1_2.bin"
+        );
+        assert_eq!(
+            ClosedFormInputWrapper::BUCKET,
+            Bucket::LeafAggregationWitnessJobsFri
+        );
+
+        let aggregations_key = AggregationsKey {
+            block_number: L1BatchNumber(1),
+            circuit_id: 2,
+            depth: 3,
+        };
+        assert_eq!(
+            AggregationWrapper::encode_key(aggregations_key),
+            "aggregations_1_2_3.bin"
+        );
+        assert_eq!(
+            AggregationWrapper::BUCKET,
+            Bucket::NodeAggregationWitnessJobsFri
+        );
+
+        assert_eq!(
+            SchedulerPartialInputWrapper::encode_key(L1BatchNumber(5)),
+            "scheduler_witness_5.bin"
+        );
+        assert_eq!(
+            SchedulerPartialInputWrapper::BUCKET,
+            Bucket::SchedulerWitnessJobsFri
+        );
+        assert_eq!(
+            AuxOutputWitnessWrapper::encode_key(L1BatchNumber(5)),
+            "aux_output_witness_5.bin"
+        );
+        assert_eq!(
+            AuxOutputWitnessWrapper::BUCKET,
+            Bucket::SchedulerWitnessJobsFri
+        );
+        assert_eq!(FriProofWrapper::encode_key(7), "proof_7.bin");
+        assert_eq!(FriProofWrapper::BUCKET, Bucket::ProofsFri);
+        // Proof bundles are assembled from proofs stored by provers.
+        assert_eq!(proof_key(7), FriProofWrapper::encode_key(7));
+        assert_eq!(ProofBundle::BUCKET, FriProofWrapper::BUCKET);
+    }
+    /// Checks that keys derived from circuit positions match the keys and prover job fields
+    /// that were previously hardcoded for each round.
+    #[test]
+    fn circuit_keys_follow_round_conventions() {
+        let block_number = L1BatchNumber(5);
+        let scheduler_circuit_type = ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8;
+        let scheduler_key =
+            CircuitPosition::Scheduler.circuit_key(block_number, scheduler_circuit_type);
+        assert_eq!(scheduler_key.block_number, block_number);
+        assert_eq!(scheduler_key.circuit_id, 1);
+        assert_eq!(scheduler_key.sequence_number, 0);
+        assert_eq!(scheduler_key.depth, 0);
+        assert_eq!(scheduler_key.aggregation_round, AggregationRound::Scheduler);
+        assert_eq!(
+            CircuitWrapper::encode_key(scheduler_key),
+            "5_0_1_Scheduler_0.bin"
+        );
+
+        let basic_key = CircuitPosition::Basic { sequence_number: 2 }.circuit_key(block_number, 3);
+        assert_eq!(
+            CircuitWrapper::encode_key(basic_key),
+            "5_2_3_BasicCircuits_0.bin"
+        );
+        let leaf_key = CircuitPosition::Leaf { sequence_number: 2 }.circuit_key(block_number, 7);
+        assert_eq!(
+            CircuitWrapper::encode_key(leaf_key),
+            "5_2_7_LeafAggregation_0.bin"
+        );
+        let node_position = CircuitPosition::Node {
+            base_layer_circuit_id: 3,
+            depth: 4,
+            sequence_number: 2,
+        };
+        let node_circuit_type = ZkSyncRecursionLayerStorageType::NodeLayerCircuit as u8;
+        let node_key = node_position.circuit_key(block_number, node_circuit_type);
+        assert_eq!(
+            CircuitWrapper::encode_key(node_key),
+            "5_2_3_NodeAggregation_4.bin"
+        );
+    }
+
+    #[tokio::test]
+    async fn node_aggregations_are_saved_by_key() {
+        let factory = ObjectStoreFactory::mock();
+        let object_store = factory.create_store().await.unwrap();
+        let key = save_node_aggregations_artifacts(L1BatchNumber(1), 2, 0, vec![], &*object_store)
+            .await
+            .unwrap();
+
+        assert_eq!(key, "aggregations_1_2_0.bin");
+        assert_eq!(
+            factory
+                .mock_keys(Bucket::NodeAggregationWitnessJobsFri)
+                .await,
+            [key]
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_proofs_are_reported() {
+        let object_store = ObjectStoreFactory::mock().create_store().await.unwrap();
+        let err = load_proofs_for_job_ids(
+            L1BatchNumber(1),
+            &[1],
+            &*object_store,
+            DEFAULT_FETCH_CONCURRENCY,
+            AggregationRound::Scheduler,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.key, "proof_1.bin");
+        assert!(
+            matches!(err.inner, ObjectStoreError::KeyNotFound(_)),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_proofs_are_found_without_downloads() {
+        let object_store = ObjectStoreFactory::mock().create_store().await.unwrap();
+        // Proofs are not deserialized, so their contents don't matter.
+        for job_id in [1, 3] {
+            let key = FriProofWrapper::encode_key(job_id);
+            object_store
+                .put_raw(FriProofWrapper::BUCKET, &key, vec![0])
+                .await
+                .unwrap();
+        }
+        let missing_job_ids = find_missing_proofs(&[1, 2, 3, 4], &*object_store, 2)
+            .await
+            .unwrap();
+        assert_eq!(missing_job_ids, [2, 4]);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct TestObject(u32);
+
+    impl StoredObject for TestObject {
+        const BUCKET: Bucket = Bucket::ProofsFri;
+        type Key<'a> = u32;
+
+        fn encode_key(key: Self::Key<'_>) -> String {
+            format!("test_{key}.bin")
+        }
+
+        fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
+            Ok(self.0.to_le_bytes().to_vec())
+        }
+
+        fn deserialize(bytes: Vec<u8>) -> Result<Self, BoxedError> {
+            let bytes = bytes.try_into().map_err(|_| "invalid test object")?;
+            Ok(Self(u32::from_le_bytes(bytes)))
+        }
+    }
+
+    #[tokio::test]
+    async fn proofs_are_loaded_from_matching_bundle() {
+        const ROUND: AggregationRound = AggregationRound::Scheduler;
+
+        let object_store = ObjectStoreFactory::mock().create_store().await.unwrap();
+        let proofs: Option<Vec<TestObject>> =
+            load_bundled_proofs(L1BatchNumber(1), &[3, 5], &*object_store, ROUND)
+                .await
+                .unwrap();
+        assert!(proofs.is_none());
+
+        let mut bundle = ProofBundle::default();
+        for job_id in [3_u32, 5] {
+            bundle.push(job_id as u8, job_id, &job_id.to_le_bytes());
+        }
+        object_store.put(L1BatchNumber(1), &bundle).await.unwrap();
+        let proofs: Option<Vec<TestObject>> =
+            load_bundled_proofs(L1BatchNumber(1), &[3, 5], &*object_store, ROUND)
+                .await
+                .unwrap();
+        assert_eq!(proofs.unwrap(), [TestObject(3), TestObject(5)]);
+
+        // The bundle is ignored if proofs were regenerated by other prover jobs.
+        let proofs: Option<Vec<TestObject>> =
+            load_bundled_proofs(L1BatchNumber(1), &[3, 6], &*object_store, ROUND)
+                .await
+                .unwrap();
+        assert!(proofs.is_none());
+    }
+
+    #[tokio::test]
+    async fn basic_artifact_sections_are_checked_against_manifest() {
+        const ROUND: AggregationRound = AggregationRound::LeafAggregation;
+
+        let object_store = ObjectStoreFactory::mock().create_store().await.unwrap();
+        let block_number = L1BatchNumber(1);
+        // Sections of batches without a manifest are fetched without the check.
+        put_artifact(&*object_store, 1, &TestObject(1))
+            .await
+            .unwrap();
+        let manifest = get_basic_artifacts_manifest(&*object_store, block_number)
+            .await
+            .unwrap();
+        assert!(manifest.is_none());
+        let object: TestObject = get_basic_artifact_section(&*object_store, None, 1, ROUND)
+            .await
+            .unwrap();
+        assert_eq!(object, TestObject(1));
+
+        let (section, size) = put_artifact_section(&*object_store, 2, &TestObject(2))
+            .await
+            .unwrap();
+        assert_eq!(section.key, "test_2.bin");
+        assert_eq!(size, 4);
+        // If a different artifact is already stored, the section describes the stored one.
+        let (stored_section, _) = put_artifact_section(&*object_store, 2, &TestObject(3))
+            .await
+            .unwrap();
+        assert_eq!(stored_section, section);
+        let manifest = BasicArtifactsManifest {
+            l1_batch_number: block_number,
+            sections: vec![section],
+        };
+        object_store.put(block_number, &manifest).await.unwrap();
+        let manifest = get_basic_artifacts_manifest(&*object_store, block_number)
+            .await
+            .unwrap();
+        let manifest = manifest.as_ref();
+        assert!(manifest.is_some());
+        let object: TestObject = get_basic_artifact_section(&*object_store, manifest, 2, ROUND)
+            .await
+            .unwrap();
+        assert_eq!(object, TestObject(2));
+
+        let err = get_basic_artifact_section::<TestObject>(&*object_store, manifest, 1, ROUND)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Other(_)), "{err:?}");
+
+        let corrupted_blob = serialize_versioned(&TestObject(4)).unwrap();
+        object_store
+            .put_raw(TestObject::BUCKET, "test_2.bin", corrupted_blob)
+            .await
+            .unwrap();
+        let err = get_basic_artifact_section::<TestObject>(&*object_store, manifest, 2, ROUND)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ObjectStoreError::ChecksumMismatch(_)),
+            "{err:?}"
+        );
+    }
+
+    /// Object store tracking the max number of concurrent fetches. Fetches of objects stored
+    /// earlier take longer, so that fetches complete out of order.
+    #[derive(Debug)]
+    struct SlowObjectStore {
+        inner: Box<dyn ObjectStore>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl SlowObjectStore {
+        async fn new(object_count: u32) -> Self {
+            let inner = ObjectStoreFactory::mock().create_store().await.unwrap();
+            for i in 0..object_count {
+                inner.put(i, &TestObject(i)).await.unwrap();
+            }
+            Self {
+                inner,
+                in_flight: AtomicUsize::new(0),
+                max_in_flight: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStore for SlowObjectStore {
+        async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            let blob = self.inner.get_raw(bucket, key).await;
+            let delay = blob.as_ref().map_or(0, |blob| 20 - u64::from(blob[0]));
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            blob
+        }
+
+        async fn put_raw(
+            &self,
+            bucket: Bucket,
+            key: &str,
+            value: Vec<u8>,
+        ) -> Result<(), ObjectStoreError> {
+            self.inner.put_raw(bucket, key, value).await
+        }
+
+        async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+            self.inner.remove_raw(bucket, key).await
+        }
+    }
+
+    #[tokio::test]
+    async fn objects_are_fetched_concurrently_in_order() {
+        let object_store = SlowObjectStore::new(20).await;
+        let objects: Vec<TestObject> = get_many(&object_store, 0..20, 4).await.unwrap();
+
+        let expected: Vec<_> = (0..20).map(TestObject).collect();
+        assert_eq!(objects, expected);
+        assert_eq!(object_store.in_flight.load(Ordering::SeqCst), 0);
+        assert_eq!(object_store.max_in_flight.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn fetched_objects_are_timed_and_sized() {
+        let object_store = SlowObjectStore::new(5).await;
+        let keys = (0..5).map(TestObject::encode_key).collect();
+        let objects = get_many_timed::<TestObject>(&object_store, keys, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(objects.len(), 5);
+        for (i, fetched) in objects.iter().enumerate() {
+            assert_eq!(fetched.object, TestObject(i as u32));
+            assert_eq!(fetched.size, 4);
+            // `SlowObjectStore` delays downloads, but not deserialization.
+            assert!(fetched.download_time >= Duration::from_millis(20 - i as u64));
+            assert!(fetched.latency() >= fetched.download_time);
+        }
+    }
+
+    #[tokio::test]
+    async fn fetching_objects_fails_on_missing_object() {
+        let object_store = SlowObjectStore::new(10).await;
+        let keys = (0..5).chain([15]).chain(5..10);
+        let err = get_many::<TestObject>(&object_store, keys, 4)
+            .await
+            .unwrap_err();
+        assert_eq!(err.key, "test_15.bin");
+        assert!(
+            matches!(err.inner, ObjectStoreError::KeyNotFound(_)),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn unavailable_store_errors_are_classified() {
+        let err = GetManyError {
+            key: "test_1.bin".to_owned(),
+            inner: ObjectStoreError::CircuitOpen("requests are suspended".into()),
+        };
+        let err = classify_store_unavailable(anyhow::Error::new(err).context("failed loading"));
+        let job_error = JobError::from(err);
+        assert_eq!(job_error.kind, JobErrorKind::Unavailable);
+        assert!(job_error.retryable);
+
+        let err = anyhow::Error::new(ObjectStoreError::KeyNotFound("missing".into()));
+        assert!(!is_store_unavailable(&err));
+        let job_error = JobError::from(classify_store_unavailable(err));
+        assert_eq!(job_error.kind, JobErrorKind::Other);
+    }
+
+    #[test]
+    fn circuit_ids_are_filtered_by_allowlist_and_denylist() {
+        assert_eq!(filter_circuit_ids(None, None).unwrap(), None);
+        assert_eq!(
+            filter_circuit_ids(Some(&[1, 3]), None).unwrap(),
+            Some(vec![1, 3])
+        );
+        assert_eq!(
+            filter_circuit_ids(Some(&[1, 3]), Some(&[3])).unwrap(),
+            Some(vec![1])
+        );
+        let expected_ids: Vec<u8> = (2..=13).collect();
+        assert_eq!(
+            filter_circuit_ids(None, Some(&[1])).unwrap(),
+            Some(expected_ids)
+        );
+
+        filter_circuit_ids(Some(&[0]), None).unwrap_err();
+        filter_circuit_ids(None, Some(&[14])).unwrap_err();
+        filter_circuit_ids(Some(&[1]), Some(&[1])).unwrap_err();
     }
-    proofs
 }
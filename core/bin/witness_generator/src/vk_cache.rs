@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Per-process cache of verification keys (or values derived from them) keyed by protocol version.
+/// Keys only change with protocol upgrades, so entries are never evicted; keys for a new version
+/// are cached under a separate entry.
+pub struct VkCache<T> {
+    name: &'static str,
+    entries: Mutex<HashMap<Option<u16>, Arc<T>>>,
+}
+
+impl<T> fmt::Debug for VkCache<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries.lock().expect("VK cache is poisoned");
+        let mut protocol_versions: Vec<_> = entries.keys().collect();
+        protocol_versions.sort_unstable();
+        formatter
+            .debug_struct("VkCache")
+            .field("name", &self.name)
+            .field("protocol_versions", &protocol_versions)
+            .finish()
+    }
+}
+
+impl<T> VkCache<T> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Returns the cached value for the protocol version, or loads it with `load` and caches it
+    /// if it isn't cached yet. Errors are not cached, so loading is retried on the next call.
+    /// Concurrent calls for the same version wait for a single load.
+    pub fn get_or_load<E>(
+        &self,
+        protocol_version: Option<u16>,
+        load: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Arc<T>, E> {
+        let mut entries = self.entries.lock().expect("VK cache is poisoned");
+        if let Some(value) = entries.get(&protocol_version) {
+            metrics::counter!(
+                "prover_fri.witness_generation.vk_cache_hits",
+                1,
+                "cache" => self.name
+            );
+            return Ok(Arc::clone(value));
+        }
+        metrics::counter!(
+            "prover_fri.witness_generation.vk_cache_misses",
+            1,
+            "cache" => self.name
+        );
+        let value = Arc::new(load()?);
+        entries.insert(protocol_version, Arc::clone(&value));
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn values_are_loaded_once_per_protocol_version() {
+        let cache = VkCache::new("test");
+        let load_count = Cell::new(0);
+        let load = |value: u32| {
+            load_count.set(load_count.get() + 1);
+            Ok::<_, ()>(value)
+        };
+
+        for _ in 0..3 {
+            let value = cache.get_or_load(Some(1), || load(1)).unwrap();
+            assert_eq!(*value, 1);
+        }
+        assert_eq!(load_count.get(), 1);
+
+        let value = cache.get_or_load(Some(2), || load(2)).unwrap();
+        assert_eq!(*value, 2);
+        let value = cache.get_or_load(None, || load(0)).unwrap();
+        assert_eq!(*value, 0);
+        assert_eq!(load_count.get(), 3);
+        // Values for other versions are retained.
+        let value = cache.get_or_load(Some(1), || load(1)).unwrap();
+        assert_eq!(*value, 1);
+        assert_eq!(load_count.get(), 3);
+    }
+
+    #[test]
+    fn load_errors_are_not_cached() {
+        let cache = VkCache::new("test");
+        cache
+            .get_or_load(Some(1), || Err("keys are absent"))
+            .unwrap_err();
+        let value = cache.get_or_load(Some(1), || Ok::<_, &str>(1)).unwrap();
+        assert_eq!(*value, 1);
+    }
+}
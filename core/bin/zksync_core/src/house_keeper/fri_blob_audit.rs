@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use zksync_dal::StorageProcessor;
+use zksync_object_store::{Bucket, ObjectMeta, ObjectStore, ObjectStoreError};
+use zksync_types::proofs::AggregationRound;
+
+/// Discrepancies between scheduler circuit blobs in the object store and FRI prover jobs
+/// in the database.
+#[derive(Debug, Default)]
+pub struct SchedulerBlobsAudit {
+    /// Number of scheduler circuit blobs found in the object store.
+    pub listed_blobs: usize,
+    /// Blobs without a corresponding prover job, or belonging to jobs with blobs marked
+    /// as removed.
+    pub orphaned_blobs: Vec<ObjectMeta>,
+    /// Circuit blob URLs of prover jobs that have no corresponding blob in the object store,
+    /// although the blobs were not removed.
+    pub missing_blobs: Vec<String>,
+}
+
+/// Lists all scheduler circuit blobs in the object store and cross-references them
+/// with the scheduler-round FRI prover jobs. The bucket is listed page by page, so only
+/// the scheduler blobs (rather than all blobs in the bucket) are held in memory.
+pub async fn audit_scheduler_blobs(
+    object_store: &dyn ObjectStore,
+    prover_storage: &mut StorageProcessor<'_>,
+) -> Result<SchedulerBlobsAudit, ObjectStoreError> {
+    let blob_urls = prover_storage
+        .fri_prover_jobs_dal()
+        .get_circuit_blob_urls_for_round(AggregationRound::Scheduler)
+        .await;
+    // Maps URLs of blobs that must be present to whether they were found in the store.
+    let mut uncleaned_urls: HashMap<_, _> = blob_urls
+        .into_iter()
+        .filter_map(|(blob_url, is_blob_cleaned)| (!is_blob_cleaned).then_some((blob_url, false)))
+        .collect();
+
+    // Circuit keys have the `{l1_batch}_{sequence}_{circuit_id}_{round:?}_{depth}.bin` format.
+    let scheduler_marker = format!("_{:?}_", AggregationRound::Scheduler);
+    let mut audit = SchedulerBlobsAudit::default();
+    let mut continuation_token = None;
+    loop {
+        let (objects, next_token) = object_store
+            .list_raw(Bucket::ProverJobsFri, "", continuation_token)
+            .await?;
+        for object in objects {
+            if !object.key.contains(&scheduler_marker) {
+                continue;
+            }
+            audit.listed_blobs += 1;
+            match uncleaned_urls.get_mut(&object.key) {
+                Some(is_found) => *is_found = true,
+                None => audit.orphaned_blobs.push(object),
+            }
+        }
+
+        continuation_token = next_token;
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+
+    audit.missing_blobs = uncleaned_urls
+        .into_iter()
+        .filter_map(|(blob_url, is_found)| (!is_found).then_some(blob_url))
+        .collect();
+    audit.missing_blobs.sort_unstable();
+    vlog::info!(
+        "Audited {} scheduler circuit blobs: {} orphaned, {} missing",
+        audit.listed_blobs,
+        audit.orphaned_blobs.len(),
+        audit.missing_blobs.len()
+    );
+    Ok(audit)
+}
@@ -0,0 +1,193 @@
+use async_trait::async_trait;
+use futures::{future, stream, StreamExt, TryStreamExt};
+
+use std::{collections::HashMap, time::Duration};
+
+use zksync_dal::{ConnectionPool, StorageProcessor};
+use zksync_object_store::{
+    Bucket, ObjectStore, ObjectStoreError, ObjectStoreFactory, ProofBundle, StoredObject,
+};
+use zksync_types::{proofs::AggregationRound, L1BatchNumber};
+
+use crate::house_keeper::periodic_job::PeriodicJob;
+
+/// Max number of L1 batches, blobs for which are removed in a single run.
+const MAX_L1_BATCHES_PER_RUN: u32 = 10;
+/// Max number of concurrent requests for blob sizes.
+const MAX_CONCURRENT_SIZE_REQUESTS: usize = 16;
+
+/// Removes blobs produced by FRI witness generators and provers for L1 batches that were proven
+/// on L1 long enough ago.
+#[derive(Debug)]
+pub struct FriBlobCleaner {
+    object_store: Box<dyn ObjectStore>,
+    connection_pool: ConnectionPool,
+    prover_connection_pool: ConnectionPool,
+    retention: Duration,
+    cleaning_interval_ms: u64,
+}
+
+impl FriBlobCleaner {
+    pub async fn new(
+        store_factory: &ObjectStoreFactory,
+        connection_pool: ConnectionPool,
+        prover_connection_pool: ConnectionPool,
+        retention: Duration,
+        cleaning_interval_ms: u64,
+    ) -> Result<Self, ObjectStoreError> {
+        Ok(Self {
+            object_store: store_factory.create_store().await?,
+            connection_pool,
+            prover_connection_pool,
+            retention,
+            cleaning_interval_ms,
+        })
+    }
+
+    async fn cleanup_blobs(&self) -> Result<(), ObjectStoreError> {
+        let mut prover_storage = self.prover_connection_pool.access_storage().await;
+        let first_l1_batch = prover_storage
+            .fri_prover_jobs_dal()
+            .min_l1_batch_with_uncleaned_blobs()
+            .await;
+        let first_l1_batch = match first_l1_batch {
+            Some(number) => number,
+            None => return Ok(()),
+        };
+        let last_l1_batch = self
+            .connection_pool
+            .access_storage()
+            .await
+            .blocks_dal()
+            .get_number_of_last_block_proven_on_eth_before(self.retention)
+            .await;
+        let last_l1_batch = match last_l1_batch {
+            Some(number) => number.0.min(first_l1_batch.0 + MAX_L1_BATCHES_PER_RUN - 1),
+            None => return Ok(()),
+        };
+
+        for l1_batch_number in first_l1_batch.0..=last_l1_batch {
+            let l1_batch_number = L1BatchNumber(l1_batch_number);
+            let bytes_freed =
+                remove_l1_batch_blobs(&*self.object_store, &mut prover_storage, l1_batch_number)
+                    .await?;
+            prover_storage
+                .fri_prover_jobs_dal()
+                .mark_l1_batch_blobs_as_cleaned(l1_batch_number)
+                .await;
+
+            vlog::info!(
+                "Removed FRI prover blobs for l1 batch {}, freeing {} bytes",
+                l1_batch_number,
+                bytes_freed
+            );
+            metrics::counter!("server.fri_blob_cleaner.bytes_freed", bytes_freed);
+            metrics::gauge!(
+                "server.fri_blob_cleaner.last_cleaned_l1_batch",
+                l1_batch_number.0 as f64
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Returns the bucket storing inputs for witness jobs of the specified round.
+fn witness_input_bucket(aggregation_round: AggregationRound) -> Bucket {
+    match aggregation_round {
+        AggregationRound::BasicCircuits => Bucket::WitnessInput,
+        AggregationRound::LeafAggregation => Bucket::LeafAggregationWitnessJobsFri,
+        AggregationRound::NodeAggregation => Bucket::NodeAggregationWitnessJobsFri,
+        AggregationRound::Scheduler => Bucket::SchedulerWitnessJobsFri,
+    }
+}
+
+/// Returns keys of all blobs produced by FRI witness generators and provers for the specified
+/// L1 batch grouped by the bucket: inputs of witness jobs, circuits and proofs for all aggregation
+/// rounds. Some of the blobs may be missing in the store (e.g., if they were already removed).
+pub async fn get_l1_batch_blob_keys(
+    prover_storage: &mut StorageProcessor<'_>,
+    l1_batch_number: L1BatchNumber,
+) -> HashMap<Bucket, Vec<String>> {
+    let mut keys_by_bucket = HashMap::<_, Vec<_>>::new();
+    let witness_input_urls = prover_storage
+        .fri_witness_generator_dal()
+        .get_input_blob_urls_for_l1_batch(l1_batch_number)
+        .await;
+    for (aggregation_round, blob_url) in witness_input_urls {
+        let bucket = witness_input_bucket(aggregation_round);
+        keys_by_bucket.entry(bucket).or_default().push(blob_url);
+    }
+    // The auxiliary output witness, the basic circuits summary and the basic artifacts manifest
+    // are not tracked in the DB, so their keys are derived here.
+    let scheduler_witness_keys = keys_by_bucket
+        .entry(Bucket::SchedulerWitnessJobsFri)
+        .or_default();
+    scheduler_witness_keys.push(format!("aux_output_witness_{}.bin", l1_batch_number));
+    scheduler_witness_keys.push(format!("basic_circuits_summary_{}.json", l1_batch_number));
+    scheduler_witness_keys.push(format!("basic_artifacts_manifest_{}.json", l1_batch_number));
+    // Same for the final node proof bundle, which may be missing if bundling is disabled.
+    keys_by_bucket
+        .entry(ProofBundle::BUCKET)
+        .or_default()
+        .push(ProofBundle::encode_key(l1_batch_number));
+
+    let prover_job_urls = prover_storage
+        .fri_prover_jobs_dal()
+        .get_blob_urls_for_l1_batch(l1_batch_number)
+        .await;
+    for (circuit_blob_url, proof_blob_url) in prover_job_urls {
+        keys_by_bucket
+            .entry(Bucket::ProverJobsFri)
+            .or_default()
+            .push(circuit_blob_url);
+        if let Some(proof_blob_url) = proof_blob_url {
+            keys_by_bucket
+                .entry(Bucket::ProofsFri)
+                .or_default()
+                .push(proof_blob_url);
+        }
+    }
+    keys_by_bucket
+}
+
+/// Removes all blobs produced by FRI witness generators and provers for the specified L1 batch
+/// (see [`get_l1_batch_blob_keys()`]). Blobs that are already removed are skipped, so the removal
+/// can be safely repeated. Returns the total size of the removed blobs.
+pub async fn remove_l1_batch_blobs(
+    object_store: &dyn ObjectStore,
+    prover_storage: &mut StorageProcessor<'_>,
+    l1_batch_number: L1BatchNumber,
+) -> Result<u64, ObjectStoreError> {
+    let keys_by_bucket = get_l1_batch_blob_keys(prover_storage, l1_batch_number).await;
+    let mut bytes_freed = 0;
+    for (bucket, keys) in &keys_by_bucket {
+        bytes_freed += stream::iter(keys)
+            .map(|key| async move {
+                match object_store.get_raw_size(*bucket, key).await {
+                    Err(ObjectStoreError::KeyNotFound(_)) => Ok(0),
+                    result => result,
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_SIZE_REQUESTS)
+            .try_fold(0, |acc, size| future::ready(Ok(acc + size)))
+            .await?;
+        object_store.remove_raw_many(*bucket, keys).await?;
+    }
+    Ok(bytes_freed)
+}
+
+#[async_trait]
+impl PeriodicJob for FriBlobCleaner {
+    const SERVICE_NAME: &'static str = "FriBlobCleaner";
+
+    async fn run_routine_task(&mut self) {
+        if let Err(err) = self.cleanup_blobs().await {
+            // The removal is idempotent, so it will be retried on the next run.
+            vlog::warn!("Failed removing FRI prover blobs: {}", err);
+        }
+    }
+
+    fn polling_interval_ms(&self) -> u64 {
+        self.cleaning_interval_ms
+    }
+}
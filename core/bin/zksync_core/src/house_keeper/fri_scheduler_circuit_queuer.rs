@@ -1,12 +1,23 @@
 use async_trait::async_trait;
-use zksync_dal::ConnectionPool;
+
+use std::time::Instant;
+
+use zksync_dal::{ConnectionPool, StorageProcessor};
+use zksync_object_store::{ObjectStore, ObjectStoreError, ProofBundle};
+use zksync_types::L1BatchNumber;
 
 use crate::house_keeper::periodic_job::PeriodicJob;
 
+/// Max number of final node proofs concurrently fetched when bundling proofs for an L1 batch.
+const MAX_CONCURRENT_PROOF_FETCHES: usize = 8;
+
 #[derive(Debug)]
 pub struct SchedulerCircuitQueuer {
     queuing_interval_ms: u64,
     pool: ConnectionPool,
+    /// Store to bundle final node proofs in before queuing scheduler jobs; if not set,
+    /// proofs are not bundled.
+    proof_bundle_store: Option<Box<dyn ObjectStore>>,
 }
 
 impl SchedulerCircuitQueuer {
@@ -14,9 +25,37 @@ impl SchedulerCircuitQueuer {
         Self {
             queuing_interval_ms,
             pool,
+            proof_bundle_store: None,
         }
     }
 
+    /// Enables bundling final node proofs for each L1 batch into a single blob, so that
+    /// the scheduler witness generator can fetch them in a single request.
+    pub fn with_proof_bundling(mut self, object_store: Box<dyn ObjectStore>) -> Self {
+        self.proof_bundle_store = Some(object_store);
+        self
+    }
+
+    async fn bundle_proofs(
+        object_store: &dyn ObjectStore,
+        conn: &mut StorageProcessor<'_>,
+        l1_batch_number: L1BatchNumber,
+    ) -> Result<(), ObjectStoreError> {
+        let started_at = Instant::now();
+        let proof_ids = conn
+            .fri_scheduler_dependency_tracker_dal()
+            .get_final_prover_job_ids_for(l1_batch_number)
+            .await;
+        let bundle =
+            ProofBundle::fetch(object_store, &proof_ids, MAX_CONCURRENT_PROOF_FETCHES).await?;
+        object_store.put(l1_batch_number, &bundle).await?;
+        metrics::histogram!(
+            "server.scheduler_fri_witness_generator.proof_bundling_time",
+            started_at.elapsed()
+        );
+        Ok(())
+    }
+
     pub async fn queue_scheduler_circuit_jobs(&mut self) {
         let mut conn = self.pool.access_storage().await;
         let l1_batch_numbers = conn
@@ -25,6 +64,19 @@ impl SchedulerCircuitQueuer {
             .await;
         let len = l1_batch_numbers.len();
         for &l1_batch_number in l1_batch_numbers.iter() {
+            if let Some(object_store) = &self.proof_bundle_store {
+                // The scheduler witness generator falls back to fetching proofs one by one
+                // if the bundle is missing, so a failure doesn't block queuing the job.
+                if let Err(err) =
+                    Self::bundle_proofs(&**object_store, &mut conn, l1_batch_number).await
+                {
+                    vlog::warn!(
+                        "Failed bundling final node proofs for l1_batch {}: {}",
+                        l1_batch_number,
+                        err
+                    );
+                }
+            }
             conn.fri_witness_generator_dal()
                 .mark_scheduler_jobs_as_queued(l1_batch_number)
                 .await;
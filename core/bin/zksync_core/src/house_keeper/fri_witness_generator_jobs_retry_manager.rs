@@ -2,95 +2,141 @@ use std::time::Duration;
 
 use crate::house_keeper::periodic_job::PeriodicJob;
 use async_trait::async_trait;
+use zksync_config::configs::FriWitnessGeneratorConfig;
 use zksync_dal::ConnectionPool;
+use zksync_types::proofs::{AggregationRound, StuckJobs};
+
+/// Rounds of witness generator jobs, in the order they are requeued.
+const ROUNDS: [AggregationRound; 4] = [
+    AggregationRound::BasicCircuits,
+    AggregationRound::LeafAggregation,
+    AggregationRound::NodeAggregation,
+    AggregationRound::Scheduler,
+];
+
+/// Processing timeout and max attempts for witness generator jobs of a single round.
+#[derive(Debug, Clone, Copy)]
+struct RetrySettings {
+    processing_timeout: Duration,
+    max_attempts: u32,
+}
+
+impl RetrySettings {
+    fn new(config: &FriWitnessGeneratorConfig, round: AggregationRound) -> Self {
+        let (processing_timeout, max_attempts) = match round {
+            AggregationRound::BasicCircuits => (
+                config.basic_generation_timeout(),
+                config.basic_max_attempts(),
+            ),
+            AggregationRound::LeafAggregation => {
+                (config.leaf_generation_timeout(), config.leaf_max_attempts())
+            }
+            AggregationRound::NodeAggregation => {
+                (config.node_generation_timeout(), config.node_max_attempts())
+            }
+            AggregationRound::Scheduler => (
+                config.scheduler_generation_timeout(),
+                config.scheduler_max_attempts(),
+            ),
+        };
+        Self {
+            processing_timeout,
+            max_attempts,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct FriWitnessGeneratorJobRetryManager {
     pool: ConnectionPool,
-    max_attempts: u32,
-    processing_timeout: Duration,
+    round_settings: Vec<(AggregationRound, RetrySettings)>,
     retry_interval_ms: u64,
 }
 
 impl FriWitnessGeneratorJobRetryManager {
     pub fn new(
-        max_attempts: u32,
-        processing_timeout: Duration,
+        config: &FriWitnessGeneratorConfig,
         retry_interval_ms: u64,
         pool: ConnectionPool,
     ) -> Self {
+        let round_settings = ROUNDS
+            .iter()
+            .map(|&round| (round, RetrySettings::new(config, round)))
+            .collect();
         Self {
-            max_attempts,
-            processing_timeout,
+            round_settings,
             retry_interval_ms,
             pool,
         }
     }
 
-    pub async fn requeue_stuck_witness_inputs_jobs(&mut self) {
-        let stuck_jobs = self
-            .pool
-            .access_storage()
-            .await
-            .fri_witness_generator_dal()
-            .requeue_stuck_jobs(self.processing_timeout, self.max_attempts)
-            .await;
-        let job_len = stuck_jobs.len();
-        for stuck_job in stuck_jobs {
-            vlog::info!("re-queuing fri witness input job {:?}", stuck_job);
-        }
-        metrics::counter!("server.witness_inputs_fri.requeued_jobs", job_len as u64);
-    }
+    /// Requeues stuck jobs of the round, or marks them as failed if they have exhausted
+    /// their attempts.
+    async fn requeue_stuck_jobs(&self, round: AggregationRound, settings: RetrySettings) {
+        let mut connection = self.pool.access_storage().await;
+        let mut dal = connection.fri_witness_generator_dal();
+        let RetrySettings {
+            processing_timeout,
+            max_attempts,
+        } = settings;
+        let stuck_jobs = match round {
+            AggregationRound::BasicCircuits => {
+                dal.requeue_stuck_basic_jobs(processing_timeout, max_attempts)
+                    .await
+            }
+            AggregationRound::LeafAggregation => {
+                dal.requeue_stuck_leaf_aggregations_jobs(processing_timeout, max_attempts)
+                    .await
+            }
+            AggregationRound::NodeAggregation => {
+                dal.requeue_stuck_node_aggregations_jobs(processing_timeout, max_attempts)
+                    .await
+            }
+            AggregationRound::Scheduler => {
+                dal.requeue_stuck_scheduler_jobs(processing_timeout, max_attempts)
+                    .await
+            }
+        };
 
-    pub async fn requeue_stuck_leaf_aggregations_jobs(&mut self) {
-        let stuck_jobs = self
-            .pool
-            .access_storage()
-            .await
-            .fri_witness_generator_dal()
-            .requeue_stuck_leaf_aggregations_jobs(self.processing_timeout, self.max_attempts)
-            .await;
-        let job_len = stuck_jobs.len();
-        for stuck_job in stuck_jobs {
-            vlog::info!("re-queuing fri witness input job {:?}", stuck_job);
+        let (exhausted_jobs, requeued_jobs): (Vec<StuckJobs>, _) = stuck_jobs
+            .into_iter()
+            .partition(|job| job.status == "failed");
+        for stuck_job in &requeued_jobs {
+            vlog::info!("re-queuing fri {:?} witness job {:?}", round, stuck_job);
         }
-        metrics::counter!(
-            "server.leaf_aggregations_jobs_fri.requeued_jobs",
-            job_len as u64
-        );
-    }
-
-    pub async fn requeue_stuck_node_aggregations_jobs(&mut self) {
-        let stuck_jobs = self
-            .pool
-            .access_storage()
-            .await
-            .fri_witness_generator_dal()
-            .requeue_stuck_node_aggregations_jobs(self.processing_timeout, self.max_attempts)
-            .await;
-        let job_len = stuck_jobs.len();
-        for stuck_job in stuck_jobs {
-            vlog::info!("re-queuing fri witness input job {:?}", stuck_job);
+        for exhausted_job in &exhausted_jobs {
+            vlog::warn!(
+                "fri {:?} witness job {:?} has exhausted {} attempts, marked it as failed",
+                round,
+                exhausted_job,
+                max_attempts
+            );
         }
-        metrics::counter!(
-            "server.node_aggregations_jobs_fri.requeued_jobs",
-            job_len as u64
-        );
+        let (requeued_metric, exhausted_metric) = metric_names(round);
+        metrics::counter!(requeued_metric, requeued_jobs.len() as u64);
+        metrics::counter!(exhausted_metric, exhausted_jobs.len() as u64);
     }
+}
 
-    pub async fn requeue_stuck_scheduler_jobs(&mut self) {
-        let stuck_jobs = self
-            .pool
-            .access_storage()
-            .await
-            .fri_witness_generator_dal()
-            .requeue_stuck_scheduler_jobs(self.processing_timeout, self.max_attempts)
-            .await;
-        let job_len = stuck_jobs.len();
-        for stuck_job in stuck_jobs {
-            vlog::info!("re-queuing fri witness input job {:?}", stuck_job);
-        }
-        metrics::counter!("server.scheduler_jobs_fri.requeued_jobs", job_len as u64);
+/// Returns names of the counters of requeued jobs and jobs with exhausted attempts for the round.
+fn metric_names(round: AggregationRound) -> (&'static str, &'static str) {
+    match round {
+        AggregationRound::BasicCircuits => (
+            "server.witness_inputs_fri.requeued_jobs",
+            "server.witness_inputs_fri.exhausted_attempts_jobs",
+        ),
+        AggregationRound::LeafAggregation => (
+            "server.leaf_aggregations_jobs_fri.requeued_jobs",
+            "server.leaf_aggregations_jobs_fri.exhausted_attempts_jobs",
+        ),
+        AggregationRound::NodeAggregation => (
+            "server.node_aggregations_jobs_fri.requeued_jobs",
+            "server.node_aggregations_jobs_fri.exhausted_attempts_jobs",
+        ),
+        AggregationRound::Scheduler => (
+            "server.scheduler_jobs_fri.requeued_jobs",
+            "server.scheduler_jobs_fri.exhausted_attempts_jobs",
+        ),
     }
 }
 
@@ -100,10 +146,9 @@ impl PeriodicJob for FriWitnessGeneratorJobRetryManager {
     const SERVICE_NAME: &'static str = "FriWitnessGeneratorJobRetryManager";
 
     async fn run_routine_task(&mut self) {
-        self.requeue_stuck_witness_inputs_jobs().await;
-        self.requeue_stuck_leaf_aggregations_jobs().await;
-        self.requeue_stuck_node_aggregations_jobs().await;
-        self.requeue_stuck_scheduler_jobs().await;
+        for &(round, settings) in &self.round_settings {
+            self.requeue_stuck_jobs(round, settings).await;
+        }
     }
 
     fn polling_interval_ms(&self) -> u64 {
@@ -78,6 +78,24 @@ fn emit_metrics_for_round(round: AggregationRound, stats: JobCountStatistics) {
     );
 }
 
+/// Node aggregation jobs are additionally reported for each depth of the aggregation tree under
+/// a separate metric, so that summing job counts by round isn't affected.
+fn emit_node_aggregation_metrics_for_depth(depth: u16, stats: JobCountStatistics) {
+    metrics::gauge!(
+        format!("server.{}.node_aggregation_jobs", FRI_WITNESS_GENERATOR_SERVICE_NAME),
+        stats.queued as f64,
+        "type" => "queued",
+        "depth" => depth.to_string()
+    );
+
+    metrics::gauge!(
+        format!("server.{}.node_aggregation_jobs", FRI_WITNESS_GENERATOR_SERVICE_NAME),
+        stats.in_progress as f64,
+        "type" => "in_progress",
+        "depth" => depth.to_string()
+    );
+}
+
 /// Invoked periodically to push job statistics to Prometheus
 /// Note: these values will be used for auto-scaling job processors
 #[async_trait]
@@ -91,6 +109,16 @@ impl PeriodicJob for FriWitnessGeneratorStatsReporter {
             emit_metrics_for_round(round, stats);
             aggregated = aggregated + stats;
         }
+        let node_stats_by_depth = self
+            .pool
+            .access_storage()
+            .await
+            .fri_witness_generator_dal()
+            .get_node_aggregation_jobs_stats_by_depth()
+            .await;
+        for (depth, stats) in node_stats_by_depth {
+            emit_node_aggregation_metrics_for_depth(depth, stats);
+        }
 
         if aggregated.queued > 0 {
             vlog::trace!(
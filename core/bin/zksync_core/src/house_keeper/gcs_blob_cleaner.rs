@@ -46,12 +46,12 @@ impl GcsBlobCleaner {
         store_factory: &ObjectStoreFactory,
         pool: ConnectionPool,
         cleaning_interval_ms: u64,
-    ) -> Self {
-        Self {
-            object_store: store_factory.create_store().await,
+    ) -> Result<Self, ObjectStoreError> {
+        Ok(Self {
+            object_store: store_factory.create_store().await?,
             cleaning_interval_ms,
             pool,
-        }
+        })
     }
 
     async fn cleanup_blobs(&mut self) {
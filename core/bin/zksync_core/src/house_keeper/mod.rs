@@ -1,4 +1,6 @@
 pub mod blocks_state_reporter;
+pub mod fri_blob_audit;
+pub mod fri_blob_cleaner;
 pub mod fri_prover_job_retry_manager;
 pub mod fri_prover_queue_monitor;
 pub mod fri_scheduler_circuit_queuer;
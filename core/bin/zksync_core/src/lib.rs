@@ -47,6 +47,7 @@ use crate::api_server::{
     tx_sender::{TxSender, TxSenderBuilder},
 };
 use crate::eth_sender::{Aggregator, EthTxManager};
+use crate::house_keeper::fri_blob_cleaner::FriBlobCleaner;
 use crate::house_keeper::fri_prover_job_retry_manager::FriProverJobRetryManager;
 use crate::house_keeper::fri_prover_queue_monitor::FriProverStatsReporter;
 use crate::house_keeper::fri_scheduler_circuit_queuer::SchedulerCircuitQueuer;
@@ -471,7 +472,7 @@ pub async fn initialize_components(
         &store_factory,
         &stop_receiver,
     )
-    .await;
+    .await?;
     add_witness_generator_to_task_futures(
         &mut task_futures,
         &components,
@@ -480,10 +481,10 @@ pub async fn initialize_components(
         &store_factory,
         &stop_receiver,
     )
-    .await;
+    .await?;
 
     if components.contains(&Component::Housekeeper) {
-        add_house_keeper_to_task_futures(&mut task_futures, &store_factory).await;
+        add_house_keeper_to_task_futures(&mut task_futures, &store_factory).await?;
     }
 
     // Run healthcheck server for all components.
@@ -559,7 +560,7 @@ async fn add_trees_to_task_futures(
     components: &[Component],
     store_factory: &ObjectStoreFactory,
     stop_receiver: &watch::Receiver<bool>,
-) {
+) -> anyhow::Result<()> {
     let db_config = DBConfig::from_env();
     let operation_config = OperationsManagerConfig::from_env();
     const COMPONENTS_TO_MODES: &[(Component, bool)] =
@@ -584,11 +585,12 @@ async fn add_trees_to_task_futures(
                 MetadataCalculatorModeConfig::Lightweight
             };
             let (future, tree_health_check) =
-                run_tree(&db_config, &operation_config, mode, stop_receiver.clone()).await;
+                run_tree(&db_config, &operation_config, mode, stop_receiver.clone()).await?;
             task_futures.push(future);
             healthchecks.push(Box::new(tree_health_check));
         }
     }
+    Ok(())
 }
 
 async fn run_tree(
@@ -596,7 +598,7 @@ async fn run_tree(
     operation_manager: &OperationsManagerConfig,
     mode: MetadataCalculatorModeConfig<'_>,
     stop_receiver: watch::Receiver<bool>,
-) -> (JoinHandle<()>, TreeHealthCheck) {
+) -> anyhow::Result<(JoinHandle<()>, TreeHealthCheck)> {
     let started_at = Instant::now();
     let mode_str = if matches!(mode, MetadataCalculatorModeConfig::Full { .. }) {
         "full"
@@ -606,7 +608,7 @@ async fn run_tree(
     vlog::info!("Initializing Merkle tree in {mode_str} mode");
 
     let config = MetadataCalculatorConfig::for_main_node(config, operation_manager, mode);
-    let metadata_calculator = MetadataCalculator::new(&config).await;
+    let metadata_calculator = MetadataCalculator::new(&config).await?;
     let tree_health_check = metadata_calculator.tree_health_check();
     let tree_tag = metadata_calculator.tree_tag();
     let pool = ConnectionPool::new(Some(1), DbVariant::Master).await;
@@ -623,7 +625,7 @@ async fn run_tree(
         "stage" => "tree",
         "tree" => tree_tag
     );
-    (future, tree_health_check)
+    Ok((future, tree_health_check))
 }
 
 async fn add_witness_generator_to_task_futures(
@@ -633,10 +635,10 @@ async fn add_witness_generator_to_task_futures(
     prover_connection_pool: &ConnectionPool,
     store_factory: &ObjectStoreFactory,
     stop_receiver: &watch::Receiver<bool>,
-) {
+) -> anyhow::Result<()> {
     // We don't want witness generator to run on local nodes, as it's CPU heavy.
     if std::env::var("ZKSYNC_LOCAL_SETUP") == Ok("true".to_owned()) {
-        return;
+        return Ok(());
     }
 
     let generator_params = components.iter().filter_map(|component| {
@@ -654,7 +656,7 @@ async fn add_witness_generator_to_task_futures(
         );
 
         let config = WitnessGeneratorConfig::from_env();
-        let task = match component_type {
+        let witness_generator_task = match component_type {
             AggregationRound::BasicCircuits => {
                 let witness_generator = BasicWitnessGenerator::new(
                     config,
@@ -662,8 +664,8 @@ async fn add_witness_generator_to_task_futures(
                     connection_pool.clone(),
                     prover_connection_pool.clone(),
                 )
-                .await;
-                tokio::spawn(witness_generator.run(stop_receiver.clone(), batch_size))
+                .await?;
+                witness_generator.run(stop_receiver.clone(), batch_size)
             }
             AggregationRound::LeafAggregation => {
                 let witness_generator = LeafAggregationWitnessGenerator::new(
@@ -672,8 +674,8 @@ async fn add_witness_generator_to_task_futures(
                     connection_pool.clone(),
                     prover_connection_pool.clone(),
                 )
-                .await;
-                tokio::spawn(witness_generator.run(stop_receiver.clone(), batch_size))
+                .await?;
+                witness_generator.run(stop_receiver.clone(), batch_size)
             }
             AggregationRound::NodeAggregation => {
                 let witness_generator = NodeAggregationWitnessGenerator::new(
@@ -682,8 +684,8 @@ async fn add_witness_generator_to_task_futures(
                     connection_pool.clone(),
                     prover_connection_pool.clone(),
                 )
-                .await;
-                tokio::spawn(witness_generator.run(stop_receiver.clone(), batch_size))
+                .await?;
+                witness_generator.run(stop_receiver.clone(), batch_size)
             }
             AggregationRound::Scheduler => {
                 let witness_generator = SchedulerWitnessGenerator::new(
@@ -692,10 +694,15 @@ async fn add_witness_generator_to_task_futures(
                     connection_pool.clone(),
                     prover_connection_pool.clone(),
                 )
-                .await;
-                tokio::spawn(witness_generator.run(stop_receiver.clone(), batch_size))
+                .await?;
+                witness_generator.run(stop_receiver.clone(), batch_size)
             }
         };
+        let task = tokio::spawn(async move {
+            witness_generator_task.await.unwrap_or_else(|err| {
+                panic!("{component_type:?} witness generator failed: {err:#}")
+            })
+        });
         task_futures.push(task);
 
         vlog::info!(
@@ -708,17 +715,18 @@ async fn add_witness_generator_to_task_futures(
             "stage" => format!("witness_generator_{component_type:?}")
         );
     }
+    Ok(())
 }
 
 async fn add_house_keeper_to_task_futures(
     task_futures: &mut Vec<JoinHandle<()>>,
     store_factory: &ObjectStoreFactory,
-) {
+) -> anyhow::Result<()> {
     let house_keeper_config = HouseKeeperConfig::from_env();
     let connection_pool = ConnectionPool::new(Some(1), DbVariant::Replica).await;
     let l1_batch_metrics_reporter = L1BatchMetricsReporter::new(
         house_keeper_config.l1_batch_metrics_reporting_interval_ms,
-        connection_pool,
+        connection_pool.clone(),
     );
 
     let prover_connection_pool = ConnectionPool::new(
@@ -755,7 +763,7 @@ async fn add_house_keeper_to_task_futures(
         prover_connection_pool.clone(),
         house_keeper_config.blob_cleaning_interval_ms,
     )
-    .await;
+    .await?;
 
     task_futures.push(tokio::spawn(gcs_blob_cleaner.run()));
     task_futures.push(tokio::spawn(witness_generator_stats_reporter.run()));
@@ -777,8 +785,7 @@ async fn add_house_keeper_to_task_futures(
 
     let fri_witness_gen_config = FriWitnessGeneratorConfig::from_env();
     let fri_witness_gen_job_retry_manager = FriWitnessGeneratorJobRetryManager::new(
-        fri_witness_gen_config.max_attempts,
-        fri_witness_gen_config.witness_generation_timeout(),
+        &fri_witness_gen_config,
         house_keeper_config.fri_witness_generator_job_retrying_interval_ms,
         prover_connection_pool.clone(),
     );
@@ -790,10 +797,14 @@ async fn add_house_keeper_to_task_futures(
     );
     task_futures.push(tokio::spawn(waiting_to_queued_fri_witness_job_mover.run()));
 
-    let scheduler_circuit_queuer = SchedulerCircuitQueuer::new(
+    let mut scheduler_circuit_queuer = SchedulerCircuitQueuer::new(
         house_keeper_config.fri_witness_job_moving_interval_ms,
         prover_connection_pool.clone(),
     );
+    if house_keeper_config.fri_proof_bundling() {
+        scheduler_circuit_queuer =
+            scheduler_circuit_queuer.with_proof_bundling(store_factory.create_store().await?);
+    }
     task_futures.push(tokio::spawn(scheduler_circuit_queuer.run()));
 
     let fri_witness_generator_stats_reporter = FriWitnessGeneratorStatsReporter::new(
@@ -807,6 +818,19 @@ async fn add_house_keeper_to_task_futures(
         prover_connection_pool.clone(),
     );
     task_futures.push(tokio::spawn(fri_prover_stats_reporter.run()));
+
+    if let Some(retention) = house_keeper_config.fri_blob_retention() {
+        let fri_blob_cleaner = FriBlobCleaner::new(
+            store_factory,
+            connection_pool,
+            prover_connection_pool,
+            retention,
+            house_keeper_config.fri_blob_cleaning_interval_ms(),
+        )
+        .await?;
+        task_futures.push(tokio::spawn(fri_blob_cleaner.run()));
+    }
+    Ok(())
 }
 
 async fn build_tx_sender<G: L1GasPriceProvider>(
@@ -9,7 +9,7 @@ use zksync_config::configs::chain::OperationsManagerConfig;
 use zksync_config::DBConfig;
 use zksync_dal::{ConnectionPool, StorageProcessor};
 use zksync_merkle_tree::domain::TreeMetadata;
-use zksync_object_store::ObjectStoreFactory;
+use zksync_object_store::{ObjectStoreError, ObjectStoreFactory};
 use zksync_types::{
     block::L1BatchHeader,
     commitment::{BlockCommitment, BlockMetadata, BlockWithMetadata},
@@ -112,12 +112,15 @@ pub struct MetadataCalculator {
 
 impl MetadataCalculator {
     /// Creates a calculator with the specified `config`.
-    pub async fn new(config: &MetadataCalculatorConfig<'_>) -> Self {
-
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object store for the full mode cannot be initialized.
+    pub async fn new(config: &MetadataCalculatorConfig<'_>) -> Result<Self, ObjectStoreError> {
         let mode = config.mode.to_mode();
         let object_store = match config.mode {
             MetadataCalculatorModeConfig::Full { store_factory } => {
-                Some(store_factory.create_store().await)
+                Some(store_factory.create_store().await?)
             }
             MetadataCalculatorModeConfig::Lightweight => None,
         };
@@ -128,12 +131,12 @@ impl MetadataCalculator {
             Duration::ZERO
         };
         let (status_sender, _) = watch::channel(MetadataCalculatorStatus::NotReady);
-        Self {
+        Ok(Self {
             updater,
             delayer: Delayer::new(config.delay_interval),
             throttler: Delayer::new(throttle_interval),
             status_sender,
-        }
+        })
     }
 
     /// Returns a health check for this calculator.
@@ -256,7 +256,7 @@ async fn setup_calculator(
     let mode = MetadataCalculatorModeConfig::Full { store_factory };
     let calculator =
         setup_calculator_with_options(&db_config, &operation_manager, &eth, pool, mode).await;
-    (calculator, store_factory.create_store().await)
+    (calculator, store_factory.create_store().await.unwrap())
 }
 
 async fn setup_lightweight_calculator(db_path: &Path, pool: &ConnectionPool) -> MetadataCalculator {
@@ -284,7 +284,7 @@ async fn setup_calculator_with_options(
 ) -> MetadataCalculator {
     let calculator_config =
         MetadataCalculatorConfig::for_main_node(db_config, operation_config, mode);
-    let metadata_calculator = MetadataCalculator::new(&calculator_config).await;
+    let metadata_calculator = MetadataCalculator::new(&calculator_config).await.unwrap();
 
     let mut storage = pool.access_storage_tagged("metadata_calculator").await;
     if storage.blocks_dal().is_genesis_needed().await {
@@ -4,6 +4,7 @@ use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use std::time::Instant;
 
+use anyhow::Context as _;
 use async_trait::async_trait;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -14,8 +15,10 @@ use vm::{memory::SimpleMemory, StorageOracle, MAX_CYCLES_FOR_TX};
 use zksync_config::configs::WitnessGeneratorConfig;
 use zksync_config::constants::BOOTLOADER_ADDRESS;
 use zksync_dal::ConnectionPool;
-use zksync_object_store::{Bucket, ObjectStore, ObjectStoreFactory, StoredObject};
-use zksync_queued_job_processor::JobProcessor;
+use zksync_object_store::{
+    Bucket, ObjectStore, ObjectStoreError, ObjectStoreFactory, StoredObject,
+};
+use zksync_queued_job_processor::{JobError, JobProcessor};
 use zksync_state::{PostgresStorage, StorageView};
 use zksync_types::zkevm_test_harness::toolset::GeometryConfig;
 use zksync_types::{
@@ -71,13 +74,13 @@ impl BasicWitnessGenerator {
         store_factory: &ObjectStoreFactory,
         connection_pool: ConnectionPool,
         prover_connection_pool: ConnectionPool,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, ObjectStoreError> {
+        Ok(Self {
             config,
-            object_store: store_factory.create_store().await.into(),
+            object_store: store_factory.create_store().await?.into(),
             connection_pool,
             prover_connection_pool,
-        }
+        })
     }
 
     async fn process_job_impl(
@@ -86,7 +89,7 @@ impl BasicWitnessGenerator {
         prover_connection_pool: ConnectionPool,
         basic_job: BasicWitnessGeneratorJob,
         started_at: Instant,
-    ) -> Option<BasicCircuitArtifacts> {
+    ) -> anyhow::Result<Option<BasicCircuitArtifacts>> {
         let config: WitnessGeneratorConfig = WitnessGeneratorConfig::from_env();
         let BasicWitnessGeneratorJob { block_number, job } = basic_job;
 
@@ -112,7 +115,7 @@ impl BasicWitnessGenerator {
                     .witness_generator_dal()
                     .mark_witness_job_as_skipped(block_number, AggregationRound::BasicCircuits)
                     .await;
-                return None;
+                return Ok(None);
             }
         }
 
@@ -123,17 +126,16 @@ impl BasicWitnessGenerator {
             block_number.0
         );
 
-        Some(
-            process_basic_circuits_job(
-                object_store,
-                config,
-                connection_pool,
-                started_at,
-                block_number,
-                job,
-            )
-            .await,
+        let artifacts = process_basic_circuits_job(
+            object_store,
+            config,
+            connection_pool,
+            started_at,
+            block_number,
+            job,
         )
+        .await?;
+        Ok(Some(artifacts))
     }
 }
 
@@ -146,7 +148,7 @@ impl JobProcessor for BasicWitnessGenerator {
 
     const SERVICE_NAME: &'static str = "basic_circuit_witness_generator";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
         let mut prover_connection = self.prover_connection_pool.access_storage().await;
         let last_l1_batch_to_process = self.config.last_l1_batch_to_process();
 
@@ -160,14 +162,19 @@ impl JobProcessor for BasicWitnessGenerator {
             .await
         {
             Some(metadata) => {
-                let job = get_artifacts(metadata.block_number, &self.object_store).await;
-                Some((job.block_number, job))
+                let job = get_artifacts(metadata.block_number, &self.object_store).await?;
+                Ok(Some((job.block_number, job)))
             }
-            None => None,
+            None => Ok(None),
         }
     }
 
-    async fn save_failure(&self, job_id: L1BatchNumber, started_at: Instant, error: String) -> () {
+    async fn save_failure(
+        &self,
+        job_id: L1BatchNumber,
+        started_at: Instant,
+        error: JobError,
+    ) -> () {
         let attempts = self
             .prover_connection_pool
             .access_storage()
@@ -177,7 +184,7 @@ impl JobProcessor for BasicWitnessGenerator {
                 AggregationRound::BasicCircuits,
                 job_id,
                 started_at.elapsed(),
-                error,
+                error.to_string(),
             )
             .await;
 
@@ -196,7 +203,7 @@ impl JobProcessor for BasicWitnessGenerator {
         &self,
         job: BasicWitnessGeneratorJob,
         started_at: Instant,
-    ) -> tokio::task::JoinHandle<Option<BasicCircuitArtifacts>> {
+    ) -> tokio::task::JoinHandle<anyhow::Result<Option<BasicCircuitArtifacts>>> {
         let object_store = Arc::clone(&self.object_store);
         tokio::spawn(Self::process_job_impl(
             object_store,
@@ -212,14 +219,17 @@ impl JobProcessor for BasicWitnessGenerator {
         job_id: L1BatchNumber,
         started_at: Instant,
         optional_artifacts: Option<BasicCircuitArtifacts>,
-    ) {
+    ) -> anyhow::Result<()> {
         match optional_artifacts {
             None => (),
             Some(artifacts) => {
-                let blob_urls = save_artifacts(job_id, artifacts, &self.object_store).await;
+                let blob_urls = save_artifacts(job_id, artifacts, &self.object_store)
+                    .await
+                    .context("failed saving basic circuit artifacts")?;
                 update_database(&self.prover_connection_pool, started_at, job_id, blob_urls).await;
             }
         }
+        Ok(())
     }
 }
 
@@ -230,12 +240,12 @@ pub async fn process_basic_circuits_job(
     started_at: Instant,
     block_number: L1BatchNumber,
     job: PrepareBasicCircuitsJob,
-) -> BasicCircuitArtifacts {
+) -> anyhow::Result<BasicCircuitArtifacts> {
     let witness_gen_input =
         build_basic_circuits_witness_generator_input(connection_pool.clone(), job, block_number)
             .await;
     let (basic_circuits, basic_circuits_inputs, scheduler_witness) =
-        generate_witness(object_store, config, connection_pool, witness_gen_input).await;
+        generate_witness(object_store, config, connection_pool, witness_gen_input).await?;
     let circuits = basic_circuits.clone().into_flattened_set();
 
     vlog::info!(
@@ -245,12 +255,12 @@ pub async fn process_basic_circuits_job(
         circuits.len()
     );
 
-    BasicCircuitArtifacts {
+    Ok(BasicCircuitArtifacts {
         basic_circuits,
         basic_circuits_inputs,
         scheduler_witness,
         circuits,
-    }
+    })
 }
 
 async fn update_database(
@@ -296,41 +306,41 @@ async fn update_database(
 async fn get_artifacts(
     block_number: L1BatchNumber,
     object_store: &dyn ObjectStore,
-) -> BasicWitnessGeneratorJob {
-    let job = object_store.get(block_number).await.unwrap();
-    BasicWitnessGeneratorJob { block_number, job }
+) -> anyhow::Result<BasicWitnessGeneratorJob> {
+    let job = object_store
+        .get(block_number)
+        .await
+        .with_context(|| format!("basic circuits input missing for l1 batch {}", block_number))?;
+    Ok(BasicWitnessGeneratorJob { block_number, job })
 }
 
 async fn save_artifacts(
     block_number: L1BatchNumber,
     artifacts: BasicCircuitArtifacts,
     object_store: &dyn ObjectStore,
-) -> BlobUrls {
+) -> Result<BlobUrls, ObjectStoreError> {
     let basic_circuits_url = object_store
         .put(block_number, &artifacts.basic_circuits)
-        .await
-        .unwrap();
+        .await?;
     let basic_circuits_inputs_url = object_store
         .put(block_number, &artifacts.basic_circuits_inputs)
-        .await
-        .unwrap();
+        .await?;
     let scheduler_witness_url = object_store
         .put(block_number, &artifacts.scheduler_witness)
-        .await
-        .unwrap();
+        .await?;
     let circuit_types_and_urls = save_prover_input_artifacts(
         block_number,
         &artifacts.circuits,
         object_store,
         AggregationRound::BasicCircuits,
     )
-    .await;
-    BlobUrls {
+    .await?;
+    Ok(BlobUrls {
         basic_circuits_url,
         basic_circuits_inputs_url,
         scheduler_witness_url,
         circuit_types_and_urls,
-    }
+    })
 }
 
 // If making changes to this method, consider moving this logic to the DAL layer and make
@@ -372,11 +382,11 @@ pub async fn generate_witness(
     config: WitnessGeneratorConfig,
     connection_pool: ConnectionPool,
     input: BasicCircuitWitnessGeneratorInput,
-) -> (
+) -> anyhow::Result<(
     BlockBasicCircuits<Bn256>,
     BlockBasicCircuitsPublicInputs<Bn256>,
     SchedulerCircuitInstanceWitness<Bn256>,
-) {
+)> {
     let mut connection = connection_pool.access_storage().await;
     let header = connection
         .blocks_dal()
@@ -455,25 +465,27 @@ pub async fn generate_witness(
             .dump_arguments_for_blocks
             .contains(&input.block_number.0)
         {
-            rt_handle.block_on(save_run_with_fixed_params_args_to_gcs(
-                object_store,
-                input.block_number.0,
-                last_miniblock_number.0,
-                Address::zero(),
-                BOOTLOADER_ADDRESS,
-                bootloader_code.clone(),
-                bootloader_contents.clone(),
-                false,
-                account_code_hash,
-                used_bytecodes.clone(),
-                Vec::default(),
-                MAX_CYCLES_FOR_TX as usize,
-                GEOMETRY_CONFIG,
-                tree.clone(),
-            ));
+            rt_handle
+                .block_on(save_run_with_fixed_params_args_to_gcs(
+                    object_store,
+                    input.block_number.0,
+                    last_miniblock_number.0,
+                    Address::zero(),
+                    BOOTLOADER_ADDRESS,
+                    bootloader_code.clone(),
+                    bootloader_contents.clone(),
+                    false,
+                    account_code_hash,
+                    used_bytecodes.clone(),
+                    Vec::default(),
+                    MAX_CYCLES_FOR_TX as usize,
+                    GEOMETRY_CONFIG,
+                    tree.clone(),
+                ))
+                .context("failed saving run_with_fixed_params arguments")?;
         }
 
-        zksync_types::zkevm_test_harness::external_calls::run_with_fixed_params(
+        let witness = zksync_types::zkevm_test_harness::external_calls::run_with_fixed_params(
             Address::zero(),
             BOOTLOADER_ADDRESS,
             bootloader_code,
@@ -487,7 +499,8 @@ pub async fn generate_witness(
             storage_oracle,
             memory,
             &mut tree,
-        )
+        );
+        Ok(witness)
     })
     .await
     .unwrap()
@@ -509,7 +522,7 @@ async fn save_run_with_fixed_params_args_to_gcs(
     cycle_limit: usize,
     geometry: GeometryConfig,
     tree: PrecalculatedMerklePathsProvider,
-) {
+) -> Result<(), ObjectStoreError> {
     let run_with_fixed_params_input = RunWithFixedParamsInput {
         l1_batch_number,
         last_miniblock_number,
@@ -527,8 +540,8 @@ async fn save_run_with_fixed_params_args_to_gcs(
     };
     object_store
         .put(L1BatchNumber(l1_batch_number), &run_with_fixed_params_input)
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -1,12 +1,13 @@
 use std::collections::HashMap;
 use std::time::Instant;
 
+use anyhow::Context as _;
 use async_trait::async_trait;
 
 use zksync_config::configs::WitnessGeneratorConfig;
 use zksync_dal::ConnectionPool;
-use zksync_object_store::{ObjectStore, ObjectStoreFactory};
-use zksync_queued_job_processor::JobProcessor;
+use zksync_object_store::{ObjectStore, ObjectStoreError, ObjectStoreFactory};
+use zksync_queued_job_processor::{JobError, JobProcessor};
 use zksync_types::{
     circuit::LEAF_SPLITTING_FACTOR,
     proofs::{AggregationRound, PrepareLeafAggregationCircuitsJob, WitnessGeneratorJobMetadata},
@@ -58,13 +59,13 @@ impl LeafAggregationWitnessGenerator {
         store_factory: &ObjectStoreFactory,
         connection_pool: ConnectionPool,
         prover_connection_pool: ConnectionPool,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, ObjectStoreError> {
+        Ok(Self {
             config,
-            object_store: store_factory.create_store().await,
+            object_store: store_factory.create_store().await?,
             connection_pool,
             prover_connection_pool,
-        }
+        })
     }
 
     fn process_job_sync(
@@ -90,7 +91,7 @@ impl JobProcessor for LeafAggregationWitnessGenerator {
 
     const SERVICE_NAME: &'static str = "leaf_aggregation_witness_generator";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
         let mut prover_connection = self.prover_connection_pool.access_storage().await;
         let last_l1_batch_to_process = self.config.last_l1_batch_to_process();
 
@@ -104,14 +105,19 @@ impl JobProcessor for LeafAggregationWitnessGenerator {
             .await
         {
             Some(metadata) => {
-                let job = get_artifacts(metadata, &*self.object_store).await;
-                Some((job.block_number, job))
+                let job = get_artifacts(metadata, &*self.object_store).await?;
+                Ok(Some((job.block_number, job)))
             }
-            None => None,
+            None => Ok(None),
         }
     }
 
-    async fn save_failure(&self, job_id: L1BatchNumber, started_at: Instant, error: String) -> () {
+    async fn save_failure(
+        &self,
+        job_id: L1BatchNumber,
+        started_at: Instant,
+        error: JobError,
+    ) -> () {
         let attempts = self
             .prover_connection_pool
             .access_storage()
@@ -121,7 +127,7 @@ impl JobProcessor for LeafAggregationWitnessGenerator {
                 AggregationRound::LeafAggregation,
                 job_id,
                 started_at.elapsed(),
-                error,
+                error.to_string(),
             )
             .await;
 
@@ -140,8 +146,8 @@ impl JobProcessor for LeafAggregationWitnessGenerator {
         &self,
         job: LeafAggregationWitnessGeneratorJob,
         started_at: Instant,
-    ) -> tokio::task::JoinHandle<LeafAggregationArtifacts> {
-        tokio::task::spawn_blocking(move || Self::process_job_sync(job, started_at))
+    ) -> tokio::task::JoinHandle<anyhow::Result<LeafAggregationArtifacts>> {
+        tokio::task::spawn_blocking(move || Ok(Self::process_job_sync(job, started_at)))
     }
 
     async fn save_result(
@@ -149,9 +155,11 @@ impl JobProcessor for LeafAggregationWitnessGenerator {
         job_id: L1BatchNumber,
         started_at: Instant,
         artifacts: LeafAggregationArtifacts,
-    ) {
+    ) -> anyhow::Result<()> {
         let leaf_circuits_len = artifacts.leaf_circuits.len();
-        let blob_urls = save_artifacts(job_id, artifacts, &*self.object_store).await;
+        let blob_urls = save_artifacts(job_id, artifacts, &*self.object_store)
+            .await
+            .context("failed saving leaf aggregation artifacts")?;
         update_database(
             &self.prover_connection_pool,
             started_at,
@@ -160,6 +168,7 @@ impl JobProcessor for LeafAggregationWitnessGenerator {
             blob_urls,
         )
         .await;
+        Ok(())
     }
 }
 
@@ -269,43 +278,47 @@ async fn update_database(
 async fn get_artifacts(
     metadata: WitnessGeneratorJobMetadata,
     object_store: &dyn ObjectStore,
-) -> LeafAggregationWitnessGeneratorJob {
-    let basic_circuits = object_store.get(metadata.block_number).await.unwrap();
-    let basic_circuits_inputs = object_store.get(metadata.block_number).await.unwrap();
+) -> anyhow::Result<LeafAggregationWitnessGeneratorJob> {
+    let basic_circuits = object_store
+        .get(metadata.block_number)
+        .await
+        .context("basic_circuits not found in queued `leaf_aggregation_witness_jobs` job")?;
+    let basic_circuits_inputs = object_store
+        .get(metadata.block_number)
+        .await
+        .context("basic_circuits_inputs not found in queued `leaf_aggregation_witness_jobs` job")?;
 
-    LeafAggregationWitnessGeneratorJob {
+    Ok(LeafAggregationWitnessGeneratorJob {
         block_number: metadata.block_number,
         job: PrepareLeafAggregationCircuitsJob {
             basic_circuits_inputs,
             basic_circuits_proofs: metadata.proofs,
             basic_circuits,
         },
-    }
+    })
 }
 
 async fn save_artifacts(
     block_number: L1BatchNumber,
     artifacts: LeafAggregationArtifacts,
     object_store: &dyn ObjectStore,
-) -> BlobUrls {
+) -> Result<BlobUrls, ObjectStoreError> {
     let leaf_layer_subqueues_url = object_store
         .put(block_number, &artifacts.leaf_layer_subqueues)
-        .await
-        .unwrap();
+        .await?;
     let aggregation_outputs_url = object_store
         .put(block_number, &artifacts.aggregation_outputs)
-        .await
-        .unwrap();
+        .await?;
     let circuit_types_and_urls = save_prover_input_artifacts(
         block_number,
         &artifacts.leaf_circuits,
         object_store,
         AggregationRound::LeafAggregation,
     )
-    .await;
-    BlobUrls {
+    .await?;
+    Ok(BlobUrls {
         leaf_layer_subqueues_url,
         aggregation_outputs_url,
         circuit_types_and_urls,
-    }
+    })
 }
@@ -2,12 +2,13 @@ use std::collections::HashMap;
 use std::env;
 use std::time::Instant;
 
+use anyhow::Context as _;
 use async_trait::async_trait;
 
 use zksync_config::configs::WitnessGeneratorConfig;
 use zksync_dal::ConnectionPool;
-use zksync_object_store::{ObjectStore, ObjectStoreFactory};
-use zksync_queued_job_processor::JobProcessor;
+use zksync_object_store::{ObjectStore, ObjectStoreError, ObjectStoreFactory};
+use zksync_queued_job_processor::{JobError, JobProcessor};
 use zksync_types::{
     circuit::{
         LEAF_CIRCUIT_INDEX, LEAF_SPLITTING_FACTOR, NODE_CIRCUIT_INDEX, NODE_SPLITTING_FACTOR,
@@ -65,13 +66,13 @@ impl NodeAggregationWitnessGenerator {
         store_factory: &ObjectStoreFactory,
         connection_pool: ConnectionPool,
         prover_connection_pool: ConnectionPool,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, ObjectStoreError> {
+        Ok(Self {
             config,
-            object_store: store_factory.create_store().await,
+            object_store: store_factory.create_store().await?,
             connection_pool,
             prover_connection_pool,
-        }
+        })
     }
 
     fn process_job_sync(
@@ -98,7 +99,7 @@ impl JobProcessor for NodeAggregationWitnessGenerator {
 
     const SERVICE_NAME: &'static str = "node_aggregation_witness_generator";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
         let mut prover_connection = self.prover_connection_pool.access_storage().await;
         let last_l1_batch_to_process = self.config.last_l1_batch_to_process();
 
@@ -112,14 +113,19 @@ impl JobProcessor for NodeAggregationWitnessGenerator {
             .await
         {
             Some(metadata) => {
-                let job = get_artifacts(metadata, &*self.object_store).await;
-                return Some((job.block_number, job));
+                let job = get_artifacts(metadata, &*self.object_store).await?;
+                Ok(Some((job.block_number, job)))
             }
-            None => None,
+            None => Ok(None),
         }
     }
 
-    async fn save_failure(&self, job_id: L1BatchNumber, started_at: Instant, error: String) -> () {
+    async fn save_failure(
+        &self,
+        job_id: L1BatchNumber,
+        started_at: Instant,
+        error: JobError,
+    ) -> () {
         let attempts = self
             .prover_connection_pool
             .access_storage()
@@ -129,7 +135,7 @@ impl JobProcessor for NodeAggregationWitnessGenerator {
                 AggregationRound::NodeAggregation,
                 job_id,
                 started_at.elapsed(),
-                error,
+                error.to_string(),
             )
             .await;
 
@@ -148,8 +154,8 @@ impl JobProcessor for NodeAggregationWitnessGenerator {
         &self,
         job: NodeAggregationWitnessGeneratorJob,
         started_at: Instant,
-    ) -> tokio::task::JoinHandle<NodeAggregationArtifacts> {
-        tokio::task::spawn_blocking(move || Self::process_job_sync(job, started_at))
+    ) -> tokio::task::JoinHandle<anyhow::Result<NodeAggregationArtifacts>> {
+        tokio::task::spawn_blocking(move || Ok(Self::process_job_sync(job, started_at)))
     }
 
     async fn save_result(
@@ -157,9 +163,12 @@ impl JobProcessor for NodeAggregationWitnessGenerator {
         job_id: L1BatchNumber,
         started_at: Instant,
         artifacts: NodeAggregationArtifacts,
-    ) {
-        let blob_urls = save_artifacts(job_id, artifacts, &*self.object_store).await;
+    ) -> anyhow::Result<()> {
+        let blob_urls = save_artifacts(job_id, artifacts, &*self.object_store)
+            .await
+            .context("failed saving node aggregation artifacts")?;
         update_database(&self.prover_connection_pool, started_at, job_id, blob_urls).await;
+        Ok(())
     }
 }
 
@@ -309,44 +318,43 @@ async fn update_database(
 async fn get_artifacts(
     metadata: WitnessGeneratorJobMetadata,
     object_store: &dyn ObjectStore,
-) -> NodeAggregationWitnessGeneratorJob {
+) -> anyhow::Result<NodeAggregationWitnessGeneratorJob> {
     let leaf_layer_subqueues = object_store
         .get(metadata.block_number)
         .await
-        .expect("leaf_layer_subqueues not found in queued `node_aggregation_witness_jobs` job");
+        .context("leaf_layer_subqueues not found in queued `node_aggregation_witness_jobs` job")?;
     let aggregation_outputs = object_store
         .get(metadata.block_number)
         .await
-        .expect("aggregation_outputs not found in queued `node_aggregation_witness_jobs` job");
+        .context("aggregation_outputs not found in queued `node_aggregation_witness_jobs` job")?;
 
-    NodeAggregationWitnessGeneratorJob {
+    Ok(NodeAggregationWitnessGeneratorJob {
         block_number: metadata.block_number,
         job: PrepareNodeAggregationCircuitJob {
             previous_level_proofs: metadata.proofs,
             previous_level_leafs_aggregations: aggregation_outputs,
             previous_sequence: leaf_layer_subqueues,
         },
-    }
+    })
 }
 
 async fn save_artifacts(
     block_number: L1BatchNumber,
     artifacts: NodeAggregationArtifacts,
     object_store: &dyn ObjectStore,
-) -> BlobUrls {
+) -> Result<BlobUrls, ObjectStoreError> {
     let node_aggregations_url = object_store
         .put(block_number, &artifacts.final_node_aggregation)
-        .await
-        .unwrap();
+        .await?;
     let circuit_types_and_urls = save_prover_input_artifacts(
         block_number,
         &artifacts.node_circuits,
         object_store,
         AggregationRound::NodeAggregation,
     )
-    .await;
-    BlobUrls {
+    .await?;
+    Ok(BlobUrls {
         node_aggregations_url,
         circuit_types_and_urls,
-    }
+    })
 }
@@ -2,12 +2,13 @@ use std::collections::HashMap;
 use std::slice;
 use std::time::Instant;
 
+use anyhow::Context as _;
 use async_trait::async_trait;
 
 use zksync_config::configs::WitnessGeneratorConfig;
 use zksync_dal::ConnectionPool;
-use zksync_object_store::{ObjectStore, ObjectStoreFactory};
-use zksync_queued_job_processor::JobProcessor;
+use zksync_object_store::{ObjectStore, ObjectStoreError, ObjectStoreFactory};
+use zksync_queued_job_processor::{JobError, JobProcessor};
 use zksync_types::{
     circuit::{
         LEAF_CIRCUIT_INDEX, LEAF_SPLITTING_FACTOR, NODE_CIRCUIT_INDEX, NODE_SPLITTING_FACTOR,
@@ -53,13 +54,13 @@ impl SchedulerWitnessGenerator {
         store_factory: &ObjectStoreFactory,
         connection_pool: ConnectionPool,
         prover_connection_pool: ConnectionPool,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, ObjectStoreError> {
+        Ok(Self {
             config,
-            object_store: store_factory.create_store().await,
+            object_store: store_factory.create_store().await?,
             connection_pool,
             prover_connection_pool,
-        }
+        })
     }
 
     fn process_job_sync(
@@ -85,7 +86,7 @@ impl JobProcessor for SchedulerWitnessGenerator {
 
     const SERVICE_NAME: &'static str = "scheduler_witness_generator";
 
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)> {
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
         let mut connection = self.connection_pool.access_storage().await;
         let mut prover_connection = self.prover_connection_pool.access_storage().await;
         let last_l1_batch_to_process = self.config.last_l1_batch_to_process();
@@ -115,14 +116,19 @@ impl JobProcessor for SchedulerWitnessGenerator {
                     previous_meta_hash,
                     &*self.object_store,
                 )
-                .await;
-                Some((job.block_number, job))
+                .await?;
+                Ok(Some((job.block_number, job)))
             }
-            None => None,
+            None => Ok(None),
         }
     }
 
-    async fn save_failure(&self, job_id: L1BatchNumber, started_at: Instant, error: String) -> () {
+    async fn save_failure(
+        &self,
+        job_id: L1BatchNumber,
+        started_at: Instant,
+        error: JobError,
+    ) -> () {
         let attempts = self
             .prover_connection_pool
             .access_storage()
@@ -132,7 +138,7 @@ impl JobProcessor for SchedulerWitnessGenerator {
                 AggregationRound::Scheduler,
                 job_id,
                 started_at.elapsed(),
-                error,
+                error.to_string(),
             )
             .await;
 
@@ -151,8 +157,8 @@ impl JobProcessor for SchedulerWitnessGenerator {
         &self,
         job: SchedulerWitnessGeneratorJob,
         started_at: Instant,
-    ) -> tokio::task::JoinHandle<SchedulerArtifacts> {
-        tokio::task::spawn_blocking(move || Self::process_job_sync(job, started_at))
+    ) -> tokio::task::JoinHandle<anyhow::Result<SchedulerArtifacts>> {
+        tokio::task::spawn_blocking(move || Ok(Self::process_job_sync(job, started_at)))
     }
 
     async fn save_result(
@@ -160,9 +166,11 @@ impl JobProcessor for SchedulerWitnessGenerator {
         job_id: L1BatchNumber,
         started_at: Instant,
         artifacts: SchedulerArtifacts,
-    ) {
+    ) -> anyhow::Result<()> {
         let circuit_types_and_urls =
-            save_artifacts(job_id, &artifacts.scheduler_circuit, &*self.object_store).await;
+            save_artifacts(job_id, &artifacts.scheduler_circuit, &*self.object_store)
+                .await
+                .context("failed saving scheduler artifacts")?;
         update_database(
             &self.connection_pool,
             &self.prover_connection_pool,
@@ -172,6 +180,7 @@ impl JobProcessor for SchedulerWitnessGenerator {
             circuit_types_and_urls,
         )
         .await;
+        Ok(())
     }
 }
 
@@ -317,7 +326,7 @@ async fn save_artifacts(
     block_number: L1BatchNumber,
     scheduler_circuit: &ZkSyncCircuit<Bn256, VmWitnessOracle<Bn256>>,
     object_store: &dyn ObjectStore,
-) -> Vec<(&'static str, String)> {
+) -> Result<Vec<(&'static str, String)>, ObjectStoreError> {
     save_prover_input_artifacts(
         block_number,
         slice::from_ref(scheduler_circuit),
@@ -332,11 +341,17 @@ async fn get_artifacts(
     previous_aux_hash: [u8; 32],
     previous_meta_hash: [u8; 32],
     object_store: &dyn ObjectStore,
-) -> SchedulerWitnessGeneratorJob {
-    let scheduler_witness = object_store.get(metadata.block_number).await.unwrap();
-    let final_node_aggregations = object_store.get(metadata.block_number).await.unwrap();
+) -> anyhow::Result<SchedulerWitnessGeneratorJob> {
+    let scheduler_witness = object_store
+        .get(metadata.block_number)
+        .await
+        .context("scheduler_witness not found in queued `scheduler_witness_jobs` job")?;
+    let final_node_aggregations = object_store
+        .get(metadata.block_number)
+        .await
+        .context("final_node_aggregations not found in queued `scheduler_witness_jobs` job")?;
 
-    SchedulerWitnessGeneratorJob {
+    Ok(SchedulerWitnessGeneratorJob {
         block_number: metadata.block_number,
         job: PrepareSchedulerCircuitJob {
             incomplete_scheduler_witness: scheduler_witness,
@@ -345,5 +360,5 @@ async fn get_artifacts(
             previous_aux_hash,
             previous_meta_hash,
         },
-    }
+    })
 }
@@ -1,5 +1,5 @@
 use vm::zk_evm::ethereum_types::U256;
-use zksync_object_store::{CircuitKey, ObjectStore};
+use zksync_object_store::{CircuitKey, ObjectStore, ObjectStoreError};
 use zksync_types::zkevm_test_harness::abstract_zksync_circuit::concrete_circuits::ZkSyncCircuit;
 use zksync_types::zkevm_test_harness::bellman::bn256::Bn256;
 use zksync_types::zkevm_test_harness::witness::oracle::VmWitnessOracle;
@@ -22,7 +22,7 @@ pub async fn save_prover_input_artifacts(
     circuits: &[ZkSyncCircuit<Bn256, VmWitnessOracle<Bn256>>],
     object_store: &dyn ObjectStore,
     aggregation_round: AggregationRound,
-) -> Vec<(&'static str, String)> {
+) -> Result<Vec<(&'static str, String)>, ObjectStoreError> {
     // We intentionally process circuits sequentially to not overwhelm the object store.
     let mut types_and_urls = Vec::with_capacity(circuits.len());
     for (sequence_number, circuit) in circuits.iter().enumerate() {
@@ -33,8 +33,8 @@ pub async fn save_prover_input_artifacts(
             circuit_type,
             aggregation_round,
         };
-        let blob_url = object_store.put(circuit_key, circuit).await.unwrap();
+        let blob_url = object_store.put(circuit_key, circuit).await?;
         types_and_urls.push((circuit_type, blob_url));
     }
-    types_and_urls
+    Ok(types_and_urls)
 }
@@ -19,6 +19,9 @@ pub struct FriProverConfig {
     pub recursive_layer_circuit_ids_to_be_verified: Vec<u8>,
     pub setup_load_mode: SetupLoadMode,
     pub specialized_group_id: u8,
+    // Whether the prover listens for Postgres notifications about new prover jobs while the job queue is empty,
+    // so that it doesn't wait for the next poll. The queue is still polled if notifications are missed.
+    pub listen_for_new_jobs: Option<bool>,
 }
 
 impl FriProverConfig {
@@ -29,6 +32,10 @@ impl FriProverConfig {
     pub fn proof_generation_timeout(&self) -> Duration {
         Duration::from_secs(self.generation_timeout_in_secs as u64)
     }
+
+    pub fn listen_for_new_jobs(&self) -> bool {
+        self.listen_for_new_jobs.unwrap_or(false)
+    }
 }
 
 #[cfg(test)]
@@ -47,6 +54,7 @@ mod tests {
             recursive_layer_circuit_ids_to_be_verified: vec![1, 2, 3],
             setup_load_mode: SetupLoadMode::FromDisk,
             specialized_group_id: 10,
+            listen_for_new_jobs: Some(true),
         }
     }
 
@@ -61,6 +69,7 @@ FRI_PROVER_BASE_LAYER_CIRCUIT_IDS_TO_BE_VERIFIED="1,5"
 FRI_PROVER_RECURSIVE_LAYER_CIRCUIT_IDS_TO_BE_VERIFIED="1,2,3"
 FRI_PROVER_SETUP_LOAD_MODE="FromDisk"
 FRI_PROVER_SPECIALIZED_GROUP_ID="10"
+FRI_PROVER_LISTEN_FOR_NEW_JOBS="true"
         "#;
         set_env(config);
         let actual = FriProverConfig::from_env();
@@ -1,8 +1,10 @@
+use std::ops::RangeInclusive;
 use std::time::Duration;
 
 // Built-in uses
 // External uses
-use serde::Deserialize;
+use serde::{de, Deserialize, Deserializer};
+use zksync_basic_types::L1BatchNumber;
 
 // Local uses
 use super::envy_load;
@@ -24,6 +26,147 @@ pub struct FriWitnessGeneratorConfig {
     pub last_l1_batch_to_process: Option<u32>,
     // Force process block with specified number when sampling is enabled.
     pub force_process_block: Option<u32>,
+    // Max attempts for processing a scheduler job in-process before its failure is persisted.
+    pub scheduler_retry_max_attempts: Option<u32>,
+    // Backoff before the first in-process retry of a scheduler job; doubled for every subsequent retry.
+    pub scheduler_retry_base_backoff_ms: Option<u64>,
+    // Max time for a single attempt to process a scheduler job, after which the job is aborted.
+    pub scheduler_job_timeout_in_secs: Option<u64>,
+    // Max number of node aggregation jobs processed concurrently by a single witness generator.
+    pub node_max_jobs_in_flight: Option<usize>,
+    // Same as `node_max_jobs_in_flight`, but for basic circuits jobs.
+    pub basic_max_jobs_in_flight: Option<usize>,
+    // Same as `node_max_jobs_in_flight`, but for leaf aggregation jobs.
+    pub leaf_max_jobs_in_flight: Option<usize>,
+    // Same as `node_max_jobs_in_flight`, but for scheduler jobs.
+    pub scheduler_max_jobs_in_flight: Option<usize>,
+    // Interval for bumping `updated_at` of the scheduler job being processed, so that it's not requeued as stuck.
+    pub scheduler_heartbeat_interval_in_secs: Option<u64>,
+    // Whether witness generator should stop picking new jobs after a job has panicked.
+    pub shutdown_on_job_panic: Option<bool>,
+    // Interval for polling scheduler jobs after the queue turned out to be empty.
+    pub scheduler_polling_interval_ms: Option<u64>,
+    // Cap on the interval for polling scheduler jobs while the queue stays empty.
+    pub scheduler_max_polling_interval_ms: Option<u64>,
+    // Multiplier applied to the scheduler polling interval after every empty poll.
+    pub scheduler_polling_backoff_multiplier: Option<u32>,
+    // Whether witness generator should process jobs without persisting their outcomes; only supported for scheduler.
+    pub dry_run: Option<bool>,
+    // Number of jobs after processing which witness generator exits, so that it can be restarted.
+    pub max_jobs_to_process: Option<u64>,
+    // Max number of job pickups per minute for a single witness generator; unlimited if not set.
+    pub max_job_pickups_per_minute: Option<u32>,
+    // Port of the HTTP server exposing liveness and readiness probes; the server is not started if not set.
+    pub health_check_port: Option<u16>,
+    // Max time without witness generator making progress, after which it's reported as not ready.
+    pub health_check_max_inactivity_secs: Option<u64>,
+    // Max number of leaf or node aggregation jobs claimed with a single DB query.
+    pub job_pickup_batch_size: Option<usize>,
+    // L1 batches, scheduler jobs for which are picked up before other queued jobs.
+    pub priority_batches: Option<Vec<L1BatchNumber>>,
+    // File with priority L1 batches separated by commas or whitespace; if set, it's re-read on SIGHUP
+    // and its contents replace `priority_batches`.
+    pub priority_batches_file: Option<String>,
+    // Min available memory for picking up a scheduler job; not checked if not set.
+    pub scheduler_min_available_memory_gb: Option<u64>,
+    // Min free space on the disk holding the working directory for picking up a scheduler job; not checked if not set.
+    pub scheduler_min_free_disk_space_gb: Option<u64>,
+    // Whether witnesses are generated on a dedicated thread pool rather than on the tokio blocking pool.
+    pub dedicated_compute_pool: Option<bool>,
+    // Number of threads in the dedicated compute pool; defaults to the max number of jobs processed concurrently.
+    pub compute_pool_size: Option<usize>,
+    // Max attempts for saving a scheduler job result if the object store or the DB fails with a transient error.
+    pub save_result_max_attempts: Option<u32>,
+    // Backoff before the first retry of saving a scheduler job result; doubled for every subsequent retry.
+    pub save_result_retry_backoff_ms: Option<u64>,
+    // Whether witness generator should exit once the job queue is drained, e.g. for one-off backfills.
+    pub drain: Option<bool>,
+    // Number of consecutive polls finding the job queue empty, after which the queue is considered drained.
+    pub drain_empty_polls: Option<u32>,
+    // Max time since the L1 batch was sealed, within which its scheduler job should be completed; not enforced if not set.
+    pub scheduler_deadline_in_hours: Option<u64>,
+    // Whether queued scheduler jobs past their deadline are skipped and marked as requiring manual intervention.
+    pub scheduler_strict_deadline: Option<bool>,
+    // Max number of proofs concurrently fetched from the object store for a leaf aggregation, node aggregation
+    // or scheduler job.
+    pub proof_fetch_concurrency: Option<usize>,
+    // Whether final node proofs for a scheduler job are loaded from a proof bundle if one is stored for the L1 batch.
+    pub load_proof_bundles: Option<bool>,
+    // Whether node proofs for a scheduler job are embedded without verifying them against the node VK.
+    pub scheduler_skip_proof_verification: Option<bool>,
+    // Protocol version of the verification keys in the setup data directory; recorded for scheduler jobs
+    // created by the basic witness generator. Not recorded if not set.
+    pub protocol_version: Option<u16>,
+    // Other protocol versions served by the scheduler witness generator, keys for which are stored in `v<version>`
+    // subdirectories of the setup data directory. Jobs for other versions are left queued.
+    pub supported_protocol_versions: Option<Vec<u16>>,
+    // Whether the scheduler circuit is checked to be satisfied by its witness before saving it. The check synthesizes
+    // the circuit, which roughly doubles CPU time of the scheduler round.
+    pub check_scheduler_circuit: Option<bool>,
+    // Interval between checks of commitments exposed by scheduler circuits against commitments computed by the server.
+    pub scheduler_commitments_check_interval_in_secs: Option<u64>,
+    // Inclusive range of L1 batches specified as `<first>,<last>`, jobs for which are picked up by the witness generator
+    // in all rounds, e.g. for backfills or canaries. Jobs for other batches are left for other instances.
+    #[serde(default, deserialize_with = "deserialize_batch_range")]
+    pub batch_range: Option<(L1BatchNumber, L1BatchNumber)>,
+    // Whether scheduler jobs with incomplete final node prover jobs in the dependency tracker are marked as failed;
+    // otherwise, they are left queued with a warning.
+    pub scheduler_fail_on_incomplete_dependencies: Option<bool>,
+    // Whether provers are notified with Postgres `NOTIFY` once a scheduler circuit is queued for proving, so that
+    // provers listening for notifications don't wait for the next poll of the job queue.
+    pub notify_new_prover_jobs: Option<bool>,
+    // Max number of child proofs aggregated by a single node aggregation circuit; defaults to the capacity of the node
+    // circuit. Recorded for L1 batches when their aggregation jobs are created, so it only affects new batches.
+    pub node_aggregation_fan_in: Option<usize>,
+    // Max time for a basic circuits job to be processed, after which it's requeued as stuck;
+    // `generation_timeout_in_secs` is used if not set.
+    pub basic_generation_timeout_in_secs: Option<u64>,
+    // Same as `basic_generation_timeout_in_secs`, but for leaf aggregation jobs.
+    pub leaf_generation_timeout_in_secs: Option<u64>,
+    // Same as `basic_generation_timeout_in_secs`, but for node aggregation jobs.
+    pub node_generation_timeout_in_secs: Option<u64>,
+    // Same as `basic_generation_timeout_in_secs`, but for scheduler jobs.
+    pub scheduler_generation_timeout_in_secs: Option<u64>,
+    // Max attempts for a basic circuits job, after which it's failed rather than requeued as stuck;
+    // `max_attempts` is used if not set.
+    pub basic_max_attempts: Option<u32>,
+    // Same as `basic_max_attempts`, but for leaf aggregation jobs.
+    pub leaf_max_attempts: Option<u32>,
+    // Same as `basic_max_attempts`, but for node aggregation jobs.
+    pub node_max_attempts: Option<u32>,
+    // Same as `basic_max_attempts`, but for scheduler jobs.
+    pub scheduler_max_attempts: Option<u32>,
+    // Whether witness generation for sampled leaf aggregation, node aggregation and scheduler jobs is run twice,
+    // and hashes of the produced artifacts are compared to detect nondeterminism. Doubles CPU time of sampled jobs.
+    pub determinism_check: Option<bool>,
+    // Percentage of jobs sampled for the determinism check in the range [0, 100]; all jobs are checked if not set.
+    pub determinism_check_percentage: Option<u8>,
+    // Whether artifacts produced by both runs are uploaded under the `debug/` prefix if their hashes differ.
+    pub determinism_check_upload_artifacts: Option<bool>,
+    // Whether artifacts of the leaf aggregation round for a circuit are removed from the object store
+    // once all node aggregation jobs for the circuit are successful. Removed artifacts are recorded
+    // in the `pruned_blobs_log` table.
+    pub prune_leaf_aggregation_artifacts: Option<bool>,
+    // Base layer circuit IDs, leaf and node aggregation jobs for which are picked up by the witness generator, e.g.
+    // to dedicate instances to heavy circuits. Node aggregation jobs are matched by the base layer circuit they
+    // aggregate. Jobs for all circuits are picked up if not set; basic circuits and scheduler jobs aren't filtered.
+    pub circuit_ids_allowlist: Option<Vec<u8>>,
+    // Base layer circuit IDs, leaf and node aggregation jobs for which are not picked up by the witness generator.
+    pub circuit_ids_denylist: Option<Vec<u8>>,
+}
+
+fn deserialize_batch_range<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<(L1BatchNumber, L1BatchNumber)>, D::Error> {
+    let bounds = Option::<Vec<L1BatchNumber>>::deserialize(deserializer)?;
+    bounds
+        .map(|bounds| match bounds.as_slice() {
+            &[first, last] if first <= last => Ok((first, last)),
+            _ => Err(de::Error::custom(format!(
+                "invalid L1 batch range {bounds:?}; expected `<first>,<last>` with first <= last"
+            ))),
+        })
+        .transpose()
 }
 
 impl FriWitnessGeneratorConfig {
@@ -35,9 +178,197 @@ impl FriWitnessGeneratorConfig {
         Duration::from_secs(self.generation_timeout_in_secs as u64)
     }
 
+    fn round_generation_timeout(&self, timeout_in_secs: Option<u64>) -> Duration {
+        timeout_in_secs.map_or_else(|| self.witness_generation_timeout(), Duration::from_secs)
+    }
+
+    pub fn basic_generation_timeout(&self) -> Duration {
+        self.round_generation_timeout(self.basic_generation_timeout_in_secs)
+    }
+
+    pub fn leaf_generation_timeout(&self) -> Duration {
+        self.round_generation_timeout(self.leaf_generation_timeout_in_secs)
+    }
+
+    pub fn node_generation_timeout(&self) -> Duration {
+        self.round_generation_timeout(self.node_generation_timeout_in_secs)
+    }
+
+    pub fn scheduler_generation_timeout(&self) -> Duration {
+        self.round_generation_timeout(self.scheduler_generation_timeout_in_secs)
+    }
+
+    pub fn basic_max_attempts(&self) -> u32 {
+        self.basic_max_attempts.unwrap_or(self.max_attempts)
+    }
+
+    pub fn leaf_max_attempts(&self) -> u32 {
+        self.leaf_max_attempts.unwrap_or(self.max_attempts)
+    }
+
+    pub fn node_max_attempts(&self) -> u32 {
+        self.node_max_attempts.unwrap_or(self.max_attempts)
+    }
+
+    pub fn scheduler_max_attempts(&self) -> u32 {
+        self.scheduler_max_attempts.unwrap_or(self.max_attempts)
+    }
+
     pub fn last_l1_batch_to_process(&self) -> u32 {
         self.last_l1_batch_to_process.unwrap_or(u32::MAX)
     }
+
+    pub fn scheduler_retry_max_attempts(&self) -> u32 {
+        self.scheduler_retry_max_attempts.unwrap_or(1)
+    }
+
+    pub fn scheduler_retry_base_backoff(&self) -> Duration {
+        Duration::from_millis(self.scheduler_retry_base_backoff_ms.unwrap_or(1_000))
+    }
+
+    pub fn scheduler_job_timeout(&self) -> Duration {
+        Duration::from_secs(self.scheduler_job_timeout_in_secs.unwrap_or(3_600))
+    }
+
+    pub fn node_max_jobs_in_flight(&self) -> usize {
+        self.node_max_jobs_in_flight.unwrap_or(1)
+    }
+
+    pub fn basic_max_jobs_in_flight(&self) -> usize {
+        self.basic_max_jobs_in_flight.unwrap_or(1)
+    }
+
+    pub fn leaf_max_jobs_in_flight(&self) -> usize {
+        self.leaf_max_jobs_in_flight.unwrap_or(1)
+    }
+
+    pub fn scheduler_max_jobs_in_flight(&self) -> usize {
+        self.scheduler_max_jobs_in_flight.unwrap_or(1)
+    }
+
+    pub fn scheduler_heartbeat_interval(&self) -> Duration {
+        Duration::from_secs(self.scheduler_heartbeat_interval_in_secs.unwrap_or(60))
+    }
+
+    pub fn shutdown_on_job_panic(&self) -> bool {
+        self.shutdown_on_job_panic.unwrap_or(false)
+    }
+
+    pub fn scheduler_polling_interval(&self) -> Duration {
+        Duration::from_millis(self.scheduler_polling_interval_ms.unwrap_or(1_000))
+    }
+
+    pub fn scheduler_max_polling_interval(&self) -> Duration {
+        Duration::from_millis(self.scheduler_max_polling_interval_ms.unwrap_or(30_000))
+    }
+
+    pub fn scheduler_polling_backoff_multiplier(&self) -> u32 {
+        self.scheduler_polling_backoff_multiplier.unwrap_or(2)
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run.unwrap_or(false)
+    }
+
+    pub fn health_check_max_inactivity(&self) -> Duration {
+        Duration::from_secs(self.health_check_max_inactivity_secs.unwrap_or(600))
+    }
+
+    pub fn job_pickup_batch_size(&self) -> usize {
+        self.job_pickup_batch_size.unwrap_or(1)
+    }
+
+    pub fn dedicated_compute_pool(&self) -> bool {
+        self.dedicated_compute_pool.unwrap_or(false)
+    }
+
+    pub fn save_result_max_attempts(&self) -> u32 {
+        self.save_result_max_attempts.unwrap_or(3)
+    }
+
+    pub fn save_result_retry_backoff(&self) -> Duration {
+        Duration::from_millis(self.save_result_retry_backoff_ms.unwrap_or(1_000))
+    }
+
+    pub fn drain_after_empty_polls(&self) -> Option<u32> {
+        if self.drain.unwrap_or(false) {
+            Some(self.drain_empty_polls.unwrap_or(3))
+        } else {
+            None
+        }
+    }
+
+    pub fn scheduler_job_deadline(&self) -> Option<Duration> {
+        self.scheduler_deadline_in_hours
+            .map(|hours| Duration::from_secs(hours * 3_600))
+    }
+
+    pub fn scheduler_strict_deadline(&self) -> bool {
+        self.scheduler_strict_deadline.unwrap_or(false)
+    }
+
+    pub fn proof_fetch_concurrency(&self) -> usize {
+        self.proof_fetch_concurrency.unwrap_or(16)
+    }
+
+    pub fn load_proof_bundles(&self) -> bool {
+        self.load_proof_bundles.unwrap_or(false)
+    }
+
+    pub fn scheduler_skip_proof_verification(&self) -> bool {
+        self.scheduler_skip_proof_verification.unwrap_or(false)
+    }
+
+    pub fn check_scheduler_circuit(&self) -> bool {
+        self.check_scheduler_circuit.unwrap_or(false)
+    }
+
+    pub fn scheduler_commitments_check_interval(&self) -> Duration {
+        Duration::from_secs(
+            self.scheduler_commitments_check_interval_in_secs
+                .unwrap_or(60),
+        )
+    }
+
+    pub fn priority_batches(&self) -> Vec<L1BatchNumber> {
+        self.priority_batches.clone().unwrap_or_default()
+    }
+
+    pub fn supported_protocol_versions(&self) -> Vec<u16> {
+        self.supported_protocol_versions.clone().unwrap_or_default()
+    }
+
+    pub fn batch_range(&self) -> RangeInclusive<L1BatchNumber> {
+        let (first, last) = self
+            .batch_range
+            .unwrap_or((L1BatchNumber(0), L1BatchNumber(u32::MAX)));
+        first..=last
+    }
+
+    pub fn scheduler_fail_on_incomplete_dependencies(&self) -> bool {
+        self.scheduler_fail_on_incomplete_dependencies
+            .unwrap_or(true)
+    }
+
+    pub fn notify_new_prover_jobs(&self) -> bool {
+        self.notify_new_prover_jobs.unwrap_or(false)
+    }
+
+    pub fn determinism_check_percentage(&self) -> Option<u8> {
+        if self.determinism_check.unwrap_or(false) {
+            Some(self.determinism_check_percentage.unwrap_or(100).min(100))
+        } else {
+            None
+        }
+    }
+
+    pub fn determinism_check_upload_artifacts(&self) -> bool {
+        self.determinism_check_upload_artifacts.unwrap_or(false)
+    }
+
+    pub fn prune_leaf_aggregation_artifacts(&self) -> bool {
+        self.prune_leaf_aggregation_artifacts.unwrap_or(false)
+    }
 }
 
 #[cfg(test)]
@@ -54,6 +385,61 @@ mod tests {
             dump_arguments_for_blocks: vec![2, 3],
             last_l1_batch_to_process: None,
             force_process_block: Some(1),
+            scheduler_retry_max_attempts: Some(3),
+            scheduler_retry_base_backoff_ms: None,
+            scheduler_job_timeout_in_secs: Some(1_800),
+            node_max_jobs_in_flight: Some(4),
+            basic_max_jobs_in_flight: None,
+            leaf_max_jobs_in_flight: Some(2),
+            scheduler_max_jobs_in_flight: None,
+            scheduler_heartbeat_interval_in_secs: None,
+            shutdown_on_job_panic: Some(false),
+            scheduler_polling_interval_ms: Some(1_000),
+            scheduler_max_polling_interval_ms: Some(30_000),
+            scheduler_polling_backoff_multiplier: None,
+            dry_run: None,
+            max_jobs_to_process: Some(1_000),
+            max_job_pickups_per_minute: Some(120),
+            health_check_port: Some(3074),
+            health_check_max_inactivity_secs: None,
+            job_pickup_batch_size: Some(16),
+            priority_batches: Some(vec![L1BatchNumber(5), L1BatchNumber(8)]),
+            priority_batches_file: None,
+            scheduler_min_available_memory_gb: Some(64),
+            scheduler_min_free_disk_space_gb: None,
+            dedicated_compute_pool: Some(true),
+            compute_pool_size: None,
+            save_result_max_attempts: Some(5),
+            save_result_retry_backoff_ms: None,
+            drain: Some(true),
+            drain_empty_polls: None,
+            scheduler_deadline_in_hours: Some(6),
+            scheduler_strict_deadline: None,
+            proof_fetch_concurrency: Some(16),
+            load_proof_bundles: Some(true),
+            scheduler_skip_proof_verification: Some(false),
+            protocol_version: Some(2),
+            supported_protocol_versions: Some(vec![1]),
+            check_scheduler_circuit: Some(true),
+            scheduler_commitments_check_interval_in_secs: Some(30),
+            batch_range: Some((L1BatchNumber(10_000), L1BatchNumber(10_500))),
+            scheduler_fail_on_incomplete_dependencies: Some(false),
+            notify_new_prover_jobs: Some(true),
+            node_aggregation_fan_in: Some(4),
+            basic_generation_timeout_in_secs: Some(3_600),
+            leaf_generation_timeout_in_secs: None,
+            node_generation_timeout_in_secs: None,
+            scheduler_generation_timeout_in_secs: Some(300),
+            basic_max_attempts: None,
+            leaf_max_attempts: None,
+            node_max_attempts: None,
+            scheduler_max_attempts: Some(2),
+            determinism_check: Some(true),
+            determinism_check_percentage: Some(5),
+            determinism_check_upload_artifacts: None,
+            prune_leaf_aggregation_artifacts: Some(true),
+            circuit_ids_allowlist: None,
+            circuit_ids_denylist: Some(vec![1, 2]),
         }
     }
 
@@ -65,6 +451,41 @@ mod tests {
         FRI_WITNESS_DUMP_ARGUMENTS_FOR_BLOCKS="2,3"
         FRI_WITNESS_BLOCKS_PROVING_PERCENTAGE="30"
         FRI_WITNESS_FORCE_PROCESS_BLOCK="1"
+        FRI_WITNESS_SCHEDULER_RETRY_MAX_ATTEMPTS="3"
+        FRI_WITNESS_SCHEDULER_JOB_TIMEOUT_IN_SECS="1800"
+        FRI_WITNESS_NODE_MAX_JOBS_IN_FLIGHT="4"
+        FRI_WITNESS_LEAF_MAX_JOBS_IN_FLIGHT="2"
+        FRI_WITNESS_SHUTDOWN_ON_JOB_PANIC="false"
+        FRI_WITNESS_SCHEDULER_POLLING_INTERVAL_MS="1000"
+        FRI_WITNESS_SCHEDULER_MAX_POLLING_INTERVAL_MS="30000"
+        FRI_WITNESS_MAX_JOBS_TO_PROCESS="1000"
+        FRI_WITNESS_MAX_JOB_PICKUPS_PER_MINUTE="120"
+        FRI_WITNESS_HEALTH_CHECK_PORT="3074"
+        FRI_WITNESS_JOB_PICKUP_BATCH_SIZE="16"
+        FRI_WITNESS_PRIORITY_BATCHES="5,8"
+        FRI_WITNESS_SCHEDULER_MIN_AVAILABLE_MEMORY_GB="64"
+        FRI_WITNESS_DEDICATED_COMPUTE_POOL="true"
+        FRI_WITNESS_SAVE_RESULT_MAX_ATTEMPTS="5"
+        FRI_WITNESS_DRAIN="true"
+        FRI_WITNESS_SCHEDULER_DEADLINE_IN_HOURS="6"
+        FRI_WITNESS_PROOF_FETCH_CONCURRENCY="16"
+        FRI_WITNESS_LOAD_PROOF_BUNDLES="true"
+        FRI_WITNESS_SCHEDULER_SKIP_PROOF_VERIFICATION="false"
+        FRI_WITNESS_PROTOCOL_VERSION="2"
+        FRI_WITNESS_SUPPORTED_PROTOCOL_VERSIONS="1"
+        FRI_WITNESS_CHECK_SCHEDULER_CIRCUIT="true"
+        FRI_WITNESS_SCHEDULER_COMMITMENTS_CHECK_INTERVAL_IN_SECS="30"
+        FRI_WITNESS_BATCH_RANGE="10000,10500"
+        FRI_WITNESS_SCHEDULER_FAIL_ON_INCOMPLETE_DEPENDENCIES="false"
+        FRI_WITNESS_NOTIFY_NEW_PROVER_JOBS="true"
+        FRI_WITNESS_NODE_AGGREGATION_FAN_IN="4"
+        FRI_WITNESS_BASIC_GENERATION_TIMEOUT_IN_SECS="3600"
+        FRI_WITNESS_SCHEDULER_GENERATION_TIMEOUT_IN_SECS="300"
+        FRI_WITNESS_SCHEDULER_MAX_ATTEMPTS="2"
+        FRI_WITNESS_DETERMINISM_CHECK="true"
+        FRI_WITNESS_DETERMINISM_CHECK_PERCENTAGE="5"
+        FRI_WITNESS_PRUNE_LEAF_AGGREGATION_ARTIFACTS="true"
+        FRI_WITNESS_CIRCUIT_IDS_DENYLIST="1,2"
         "#;
         set_env(config);
         let actual = FriWitnessGeneratorConfig::from_env();
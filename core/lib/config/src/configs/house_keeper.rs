@@ -1,5 +1,7 @@
 use serde::Deserialize;
 
+use std::time::Duration;
+
 use super::envy_load;
 
 /// Configuration for the house keeper.
@@ -17,12 +19,33 @@ pub struct HouseKeeperConfig {
     pub fri_witness_generator_job_retrying_interval_ms: u64,
     pub prover_db_pool_size: u32,
     pub fri_prover_stats_reporting_interval_ms: u64,
+    // Min time since an L1 batch was proven on L1, after which blobs produced by FRI provers for it
+    // are removed from the object store; blobs are never removed if not set.
+    pub fri_blob_retention_days: Option<u64>,
+    // Interval for removing FRI prover blobs; `blob_cleaning_interval_ms` is used if not set.
+    pub fri_blob_cleaning_interval_ms: Option<u64>,
+    // Whether final node proofs for an L1 batch are bundled into a single blob once its scheduler job is queued.
+    pub fri_proof_bundling: Option<bool>,
 }
 
 impl HouseKeeperConfig {
     pub fn from_env() -> Self {
         envy_load("house_keeper", "HOUSE_KEEPER_")
     }
+
+    pub fn fri_blob_retention(&self) -> Option<Duration> {
+        self.fri_blob_retention_days
+            .map(|days| Duration::from_secs(days * 86_400))
+    }
+
+    pub fn fri_blob_cleaning_interval_ms(&self) -> u64 {
+        self.fri_blob_cleaning_interval_ms
+            .unwrap_or(self.blob_cleaning_interval_ms)
+    }
+
+    pub fn fri_proof_bundling(&self) -> bool {
+        self.fri_proof_bundling.unwrap_or(false)
+    }
 }
 
 #[cfg(test)]
@@ -45,6 +68,9 @@ mod tests {
             fri_witness_generator_job_retrying_interval_ms: 30_000,
             prover_db_pool_size: 2,
             fri_prover_stats_reporting_interval_ms: 30_000,
+            fri_blob_retention_days: Some(7),
+            fri_blob_cleaning_interval_ms: None,
+            fri_proof_bundling: Some(true),
         }
     }
 
@@ -63,6 +89,8 @@ HOUSE_KEEPER_FRI_PROVER_JOB_RETRYING_INTERVAL_MS="30000"
 HOUSE_KEEPER_FRI_WITNESS_GENERATOR_JOB_RETRYING_INTERVAL_MS="30000"
 HOUSE_KEEPER_PROVER_DB_POOL_SIZE="2"
 HOUSE_KEEPER_FRI_PROVER_STATS_REPORTING_INTERVAL_MS="30000"
+HOUSE_KEEPER_FRI_BLOB_RETENTION_DAYS="7"
+HOUSE_KEEPER_FRI_PROOF_BUNDLING="true"
         "#;
         set_env(config);
         let actual = HouseKeeperConfig::from_env();
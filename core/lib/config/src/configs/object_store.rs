@@ -1,11 +1,30 @@
 use super::envy_load;
 use serde::Deserialize;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Eq, PartialEq, Clone, Copy)]
 pub enum ObjectStoreMode {
     GCS,
     GCSWithCredentialFile,
     FileBacked,
+    /// S3 or an S3-compatible store; `bucket_base_url` is the name of the S3 bucket. Credentials
+    /// are taken from the standard AWS provider chain.
+    S3,
+    /// Azure Blob Storage with a container for each bucket prefixed by `bucket_base_url`.
+    AzureBlob,
+    /// Blobs are written to both the primary and the secondary store and read from the primary
+    /// store, falling back to the secondary one; used to migrate between backends. Configurations
+    /// of the stores are specified in [`ObjectStoreConfig::mirror`].
+    Mirrored,
+}
+
+/// Configurations of the stores for the [`ObjectStoreMode::Mirrored`] mode. Only the backend
+/// settings (the mode, bucket URL, credentials, retries etc.) of these configurations are used;
+/// other settings (e.g., compression) are taken from the enclosing configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirroredStoresConfig {
+    pub primary: ObjectStoreConfig,
+    pub secondary: ObjectStoreConfig,
 }
 
 /// Configuration for the object store
@@ -16,15 +35,211 @@ pub struct ObjectStoreConfig {
     pub file_backed_base_path: String,
     pub gcs_credential_file_path: String,
     pub max_retries: u16,
+    // Delay before the first retry of a failed request; it is doubled for every subsequent retry.
+    pub retry_base_delay_ms: Option<u64>,
+    // Cap on the delay between retries of a failed request.
+    pub retry_max_delay_ms: Option<u64>,
+    // Whether delays between retries are randomized.
+    pub retry_jitter: Option<bool>,
+    // Timeout for a single request attempt; timed out attempts are retried. 60 seconds if not set.
+    pub request_timeout_sec: Option<u64>,
+    // Deadline for an entire operation including all retries; 5 minutes if not set.
+    pub operation_timeout_sec: Option<u64>,
+    // Max number of concurrent requests to the GCS-based store; 50 if not set. Streamed
+    // downloads hold a request slot until the blob is read.
+    pub max_concurrent_requests: Option<usize>,
+    // Number of consecutive transport failures of requests to a bucket after which requests
+    // to it fail fast for a cool-down period; 20 if not set. Set to 0 to disable.
+    pub circuit_breaker_threshold: Option<u32>,
+    // Cool-down period after the circuit breaker has tripped; 30 seconds if not set.
+    pub circuit_breaker_cooldown_sec: Option<u64>,
+    // Whether the file-backed store syncs blobs to disk before they become visible.
+    pub durable_writes: Option<bool>,
+    // Zstd compression level for stored blobs; blobs are stored uncompressed if not set.
+    pub compression_level: Option<i32>,
+    // Whether blobs are stored with a checksum. Checksums are verified on reads regardless
+    // of this setting.
+    pub write_checksums: Option<bool>,
+    // Max number of blobs cached in memory by cached stores; 1,000 if not set.
+    pub cache_max_entries: Option<usize>,
+    // Max total size of blobs cached in memory by cached stores; 128 MiB if not set.
+    pub cache_max_bytes: Option<usize>,
+    // Prefix prepended to all keys (e.g., the environment name or the chain ID), so that
+    // stores for different environments never see each other's blobs.
+    pub key_prefix: Option<String>,
+    // Client-side encryption keys in the `<key_id>:<hex-encoded 32-byte key>` format separated
+    // by commas; blobs are encrypted with the last key and can be decrypted with any of the keys.
+    pub encryption_keys: Option<String>,
+    // Path to a file with encryption keys (one key per line); used if `encryption_keys` is not set.
+    pub encryption_keys_path: Option<String>,
+    // Whether fetching unencrypted blobs fails if encryption is enabled. Should only be enabled
+    // once all blobs stored before encryption was enabled are removed.
+    pub require_encryption: Option<bool>,
+    // Whether blobs are stored by the hash of their contents with the logical keys pointing
+    // to them, so that identical blobs are stored once.
+    pub content_addressed: Option<bool>,
+    // Validity period of generated download URLs for blobs; 1 hour if not set.
+    pub download_url_expiry_sec: Option<u64>,
+    // Max bandwidth used to download blobs, in bytes per second. The limit is shared by all
+    // concurrent transfers of a store; downloads are not limited if not set.
+    pub max_download_bytes_per_sec: Option<u64>,
+    // Same as `max_download_bytes_per_sec`, but for uploads.
+    pub max_upload_bytes_per_sec: Option<u64>,
+    // Storage endpoints tried in order for reads by the GCS-based store before the default
+    // endpoint, e.g. regional endpoints of a dual-region bucket. Writes always go to the default
+    // endpoint.
+    pub gcs_read_endpoints: Option<Vec<String>>,
+    // Overrides the default storage endpoint of the GCS-based store, e.g. to use fake-gcs-server.
+    pub gcs_endpoint_override: Option<String>,
+    // Whether requests of the GCS-based store are not authenticated; only useful with emulators.
+    pub gcs_use_anonymous_credentials: Option<bool>,
+    // S3 region; if not set, it's taken from the standard AWS provider chain.
+    pub s3_region: Option<String>,
+    // Overrides the S3 endpoint, e.g. to use MinIO.
+    pub s3_endpoint: Option<String>,
+    // Azure Blob Storage connection string; if set, other Azure settings are ignored.
+    pub azure_connection_string: Option<String>,
+    // Azure storage account.
+    pub azure_account: Option<String>,
+    // SAS token for the Azure storage account; if not set, the managed identity is used.
+    pub azure_sas_token: Option<String>,
+    // Whether failures to write to or remove from the secondary store in the `Mirrored` mode
+    // fail the operation; otherwise, they are only logged.
+    pub mirror_strict_writes: Option<bool>,
+    // Configurations of the primary and the secondary store in the `Mirrored` mode. Loaded from
+    // the environment variables with the `PRIMARY_` and `SECONDARY_` infix after the config prefix
+    // (e.g., `OBJECT_STORE_PRIMARY_MODE`).
+    #[serde(skip)]
+    pub mirror: Option<Box<MirroredStoresConfig>>,
 }
 
 impl ObjectStoreConfig {
+    /// Creates a configuration with the specified mode and bucket base URL, e.g. for tests.
+    /// Other settings are unset, so that their defaults are used.
+    pub fn new(mode: ObjectStoreMode, bucket_base_url: impl Into<String>) -> Self {
+        Self {
+            bucket_base_url: bucket_base_url.into(),
+            mode,
+            file_backed_base_path: String::new(),
+            gcs_credential_file_path: String::new(),
+            max_retries: 0,
+            retry_base_delay_ms: None,
+            retry_max_delay_ms: None,
+            retry_jitter: None,
+            request_timeout_sec: None,
+            operation_timeout_sec: None,
+            max_concurrent_requests: None,
+            circuit_breaker_threshold: None,
+            circuit_breaker_cooldown_sec: None,
+            durable_writes: None,
+            compression_level: None,
+            write_checksums: None,
+            cache_max_entries: None,
+            cache_max_bytes: None,
+            key_prefix: None,
+            encryption_keys: None,
+            encryption_keys_path: None,
+            require_encryption: None,
+            content_addressed: None,
+            download_url_expiry_sec: None,
+            max_download_bytes_per_sec: None,
+            max_upload_bytes_per_sec: None,
+            gcs_read_endpoints: None,
+            gcs_endpoint_override: None,
+            gcs_use_anonymous_credentials: None,
+            s3_region: None,
+            s3_endpoint: None,
+            azure_connection_string: None,
+            azure_account: None,
+            azure_sas_token: None,
+            mirror_strict_writes: None,
+            mirror: None,
+        }
+    }
+
     pub fn from_env() -> Self {
-        envy_load("object_store", "OBJECT_STORE_")
+        Self::load_from_env("object_store", "OBJECT_STORE_")
     }
 
     pub fn public_from_env() -> Self {
-        envy_load("public_object_store", "PUBLIC_OBJECT_STORE_")
+        Self::load_from_env("public_object_store", "PUBLIC_OBJECT_STORE_")
+    }
+
+    fn load_from_env(name: &str, prefix: &str) -> Self {
+        let mut config: Self = envy_load(name, prefix);
+        if config.mode == ObjectStoreMode::Mirrored {
+            let primary = envy_load(&format!("{}_primary", name), &format!("{}PRIMARY_", prefix));
+            let secondary = envy_load(
+                &format!("{}_secondary", name),
+                &format!("{}SECONDARY_", prefix),
+            );
+            config.mirror = Some(Box::new(MirroredStoresConfig { primary, secondary }));
+        }
+        config
+    }
+
+    pub fn durable_writes(&self) -> bool {
+        self.durable_writes.unwrap_or(false)
+    }
+
+    pub fn write_checksums(&self) -> bool {
+        self.write_checksums.unwrap_or(false)
+    }
+
+    pub fn retry_base_delay(&self) -> Duration {
+        Duration::from_millis(self.retry_base_delay_ms.unwrap_or(1_000))
+    }
+
+    pub fn retry_max_delay(&self) -> Duration {
+        Duration::from_millis(self.retry_max_delay_ms.unwrap_or(60_000))
+    }
+
+    pub fn retry_jitter(&self) -> bool {
+        self.retry_jitter.unwrap_or(true)
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_sec.unwrap_or(60))
+    }
+
+    pub fn operation_timeout(&self) -> Duration {
+        Duration::from_secs(self.operation_timeout_sec.unwrap_or(300))
+    }
+
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.max_concurrent_requests.unwrap_or(50)
+    }
+
+    pub fn circuit_breaker_threshold(&self) -> u32 {
+        self.circuit_breaker_threshold.unwrap_or(20)
+    }
+
+    pub fn circuit_breaker_cooldown(&self) -> Duration {
+        Duration::from_secs(self.circuit_breaker_cooldown_sec.unwrap_or(30))
+    }
+
+    pub fn require_encryption(&self) -> bool {
+        self.require_encryption.unwrap_or(false)
+    }
+
+    pub fn content_addressed(&self) -> bool {
+        self.content_addressed.unwrap_or(false)
+    }
+
+    pub fn download_url_expiry(&self) -> Duration {
+        Duration::from_secs(self.download_url_expiry_sec.unwrap_or(3_600))
+    }
+
+    pub fn gcs_read_endpoints(&self) -> &[String] {
+        self.gcs_read_endpoints.as_deref().unwrap_or_default()
+    }
+
+    pub fn gcs_use_anonymous_credentials(&self) -> bool {
+        self.gcs_use_anonymous_credentials.unwrap_or(false)
+    }
+
+    pub fn mirror_strict_writes(&self) -> bool {
+        self.mirror_strict_writes.unwrap_or(false)
     }
 }
 
@@ -40,6 +255,40 @@ mod tests {
             file_backed_base_path: "artifacts".to_string(),
             gcs_credential_file_path: "/path/to/credentials.json".to_string(),
             max_retries: 5,
+            retry_base_delay_ms: Some(500),
+            retry_max_delay_ms: None,
+            retry_jitter: None,
+            request_timeout_sec: Some(30),
+            operation_timeout_sec: None,
+            max_concurrent_requests: Some(20),
+            circuit_breaker_threshold: Some(10),
+            circuit_breaker_cooldown_sec: None,
+            durable_writes: Some(true),
+            compression_level: Some(3),
+            write_checksums: Some(true),
+            cache_max_entries: Some(100),
+            cache_max_bytes: None,
+            key_prefix: Some("staging".to_string()),
+            encryption_keys: None,
+            encryption_keys_path: Some("/etc/zksync/object_store_keys".to_string()),
+            require_encryption: Some(true),
+            content_addressed: Some(true),
+            download_url_expiry_sec: Some(600),
+            max_download_bytes_per_sec: Some(104_857_600),
+            max_upload_bytes_per_sec: None,
+            gcs_read_endpoints: Some(vec![
+                "https://storage.europe-west3.rep.googleapis.com".to_string(),
+                "https://storage.googleapis.com".to_string(),
+            ]),
+            gcs_endpoint_override: Some("http://localhost:4443".to_string()),
+            gcs_use_anonymous_credentials: None,
+            s3_region: Some("eu-central-1".to_string()),
+            s3_endpoint: None,
+            azure_connection_string: None,
+            azure_account: Some("zksync".to_string()),
+            azure_sas_token: None,
+            mirror_strict_writes: None,
+            mirror: None,
         }
     }
 
@@ -51,6 +300,24 @@ OBJECT_STORE_MODE="FileBacked"
 OBJECT_STORE_FILE_BACKED_BASE_PATH="artifacts"
 OBJECT_STORE_GCS_CREDENTIAL_FILE_PATH="/path/to/credentials.json"
 OBJECT_STORE_MAX_RETRIES="5"
+OBJECT_STORE_RETRY_BASE_DELAY_MS="500"
+OBJECT_STORE_REQUEST_TIMEOUT_SEC="30"
+OBJECT_STORE_MAX_CONCURRENT_REQUESTS="20"
+OBJECT_STORE_CIRCUIT_BREAKER_THRESHOLD="10"
+OBJECT_STORE_DURABLE_WRITES="true"
+OBJECT_STORE_COMPRESSION_LEVEL="3"
+OBJECT_STORE_WRITE_CHECKSUMS="true"
+OBJECT_STORE_CACHE_MAX_ENTRIES="100"
+OBJECT_STORE_KEY_PREFIX="staging"
+OBJECT_STORE_ENCRYPTION_KEYS_PATH="/etc/zksync/object_store_keys"
+OBJECT_STORE_REQUIRE_ENCRYPTION="true"
+OBJECT_STORE_CONTENT_ADDRESSED="true"
+OBJECT_STORE_DOWNLOAD_URL_EXPIRY_SEC="600"
+OBJECT_STORE_MAX_DOWNLOAD_BYTES_PER_SEC="104857600"
+OBJECT_STORE_GCS_READ_ENDPOINTS="https://storage.europe-west3.rep.googleapis.com,https://storage.googleapis.com"
+OBJECT_STORE_GCS_ENDPOINT_OVERRIDE="http://localhost:4443"
+OBJECT_STORE_S3_REGION="eu-central-1"
+OBJECT_STORE_AZURE_ACCOUNT="zksync"
         "#;
         set_env(config);
         let actual = ObjectStoreConfig::from_env();
@@ -65,9 +332,78 @@ PUBLIC_OBJECT_STORE_MODE="FileBacked"
 PUBLIC_OBJECT_STORE_FILE_BACKED_BASE_PATH="artifacts"
 PUBLIC_OBJECT_STORE_GCS_CREDENTIAL_FILE_PATH="/path/to/credentials.json"
 PUBLIC_OBJECT_STORE_MAX_RETRIES="5"
+PUBLIC_OBJECT_STORE_RETRY_BASE_DELAY_MS="500"
+PUBLIC_OBJECT_STORE_REQUEST_TIMEOUT_SEC="30"
+PUBLIC_OBJECT_STORE_MAX_CONCURRENT_REQUESTS="20"
+PUBLIC_OBJECT_STORE_CIRCUIT_BREAKER_THRESHOLD="10"
+PUBLIC_OBJECT_STORE_DURABLE_WRITES="true"
+PUBLIC_OBJECT_STORE_COMPRESSION_LEVEL="3"
+PUBLIC_OBJECT_STORE_WRITE_CHECKSUMS="true"
+PUBLIC_OBJECT_STORE_CACHE_MAX_ENTRIES="100"
+PUBLIC_OBJECT_STORE_KEY_PREFIX="staging"
+PUBLIC_OBJECT_STORE_ENCRYPTION_KEYS_PATH="/etc/zksync/object_store_keys"
+PUBLIC_OBJECT_STORE_REQUIRE_ENCRYPTION="true"
+PUBLIC_OBJECT_STORE_CONTENT_ADDRESSED="true"
+PUBLIC_OBJECT_STORE_DOWNLOAD_URL_EXPIRY_SEC="600"
+PUBLIC_OBJECT_STORE_MAX_DOWNLOAD_BYTES_PER_SEC="104857600"
+PUBLIC_OBJECT_STORE_GCS_READ_ENDPOINTS="https://storage.europe-west3.rep.googleapis.com,https://storage.googleapis.com"
+PUBLIC_OBJECT_STORE_GCS_ENDPOINT_OVERRIDE="http://localhost:4443"
+PUBLIC_OBJECT_STORE_S3_REGION="eu-central-1"
+PUBLIC_OBJECT_STORE_AZURE_ACCOUNT="zksync"
         "#;
         set_env(config);
         let actual = ObjectStoreConfig::public_from_env();
         assert_eq!(actual, expected_config("/public_base_url"));
     }
+
+    #[test]
+    fn mirrored_config_from_env() {
+        let config = r#"
+MIRRORED_OBJECT_STORE_BUCKET_BASE_URL="unused"
+MIRRORED_OBJECT_STORE_MODE="Mirrored"
+MIRRORED_OBJECT_STORE_FILE_BACKED_BASE_PATH="artifacts"
+MIRRORED_OBJECT_STORE_GCS_CREDENTIAL_FILE_PATH="/path/to/credentials.json"
+MIRRORED_OBJECT_STORE_MAX_RETRIES="5"
+MIRRORED_OBJECT_STORE_MIRROR_STRICT_WRITES="true"
+MIRRORED_OBJECT_STORE_PRIMARY_BUCKET_BASE_URL="/base/url"
+MIRRORED_OBJECT_STORE_PRIMARY_MODE="FileBacked"
+MIRRORED_OBJECT_STORE_PRIMARY_FILE_BACKED_BASE_PATH="artifacts"
+MIRRORED_OBJECT_STORE_PRIMARY_GCS_CREDENTIAL_FILE_PATH="/path/to/credentials.json"
+MIRRORED_OBJECT_STORE_PRIMARY_MAX_RETRIES="5"
+MIRRORED_OBJECT_STORE_PRIMARY_RETRY_BASE_DELAY_MS="500"
+MIRRORED_OBJECT_STORE_PRIMARY_REQUEST_TIMEOUT_SEC="30"
+MIRRORED_OBJECT_STORE_PRIMARY_MAX_CONCURRENT_REQUESTS="20"
+MIRRORED_OBJECT_STORE_PRIMARY_CIRCUIT_BREAKER_THRESHOLD="10"
+MIRRORED_OBJECT_STORE_PRIMARY_DURABLE_WRITES="true"
+MIRRORED_OBJECT_STORE_PRIMARY_COMPRESSION_LEVEL="3"
+MIRRORED_OBJECT_STORE_PRIMARY_WRITE_CHECKSUMS="true"
+MIRRORED_OBJECT_STORE_PRIMARY_CACHE_MAX_ENTRIES="100"
+MIRRORED_OBJECT_STORE_PRIMARY_KEY_PREFIX="staging"
+MIRRORED_OBJECT_STORE_PRIMARY_ENCRYPTION_KEYS_PATH="/etc/zksync/object_store_keys"
+MIRRORED_OBJECT_STORE_PRIMARY_REQUIRE_ENCRYPTION="true"
+MIRRORED_OBJECT_STORE_PRIMARY_CONTENT_ADDRESSED="true"
+MIRRORED_OBJECT_STORE_PRIMARY_DOWNLOAD_URL_EXPIRY_SEC="600"
+MIRRORED_OBJECT_STORE_PRIMARY_MAX_DOWNLOAD_BYTES_PER_SEC="104857600"
+MIRRORED_OBJECT_STORE_PRIMARY_GCS_READ_ENDPOINTS="https://storage.europe-west3.rep.googleapis.com,https://storage.googleapis.com"
+MIRRORED_OBJECT_STORE_PRIMARY_GCS_ENDPOINT_OVERRIDE="http://localhost:4443"
+MIRRORED_OBJECT_STORE_PRIMARY_S3_REGION="eu-central-1"
+MIRRORED_OBJECT_STORE_PRIMARY_AZURE_ACCOUNT="zksync"
+MIRRORED_OBJECT_STORE_SECONDARY_BUCKET_BASE_URL="zksync-blobs"
+MIRRORED_OBJECT_STORE_SECONDARY_MODE="GCS"
+MIRRORED_OBJECT_STORE_SECONDARY_FILE_BACKED_BASE_PATH="artifacts"
+MIRRORED_OBJECT_STORE_SECONDARY_GCS_CREDENTIAL_FILE_PATH="/path/to/credentials.json"
+MIRRORED_OBJECT_STORE_SECONDARY_MAX_RETRIES="3"
+        "#;
+        set_env(config);
+        let actual =
+            ObjectStoreConfig::load_from_env("mirrored_object_store", "MIRRORED_OBJECT_STORE_");
+        assert_eq!(actual.mode, ObjectStoreMode::Mirrored);
+        assert!(actual.mirror_strict_writes());
+        let mirror = actual.mirror.unwrap();
+        assert_eq!(mirror.primary, expected_config("/base/url"));
+        assert_eq!(mirror.secondary.mode, ObjectStoreMode::GCS);
+        assert_eq!(mirror.secondary.bucket_base_url, "zksync-blobs");
+        assert_eq!(mirror.secondary.max_retries, 3);
+        assert!(mirror.secondary.mirror.is_none());
+    }
 }
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_scheduler_fri_status_updated_at_attempts;
@@ -0,0 +1,3 @@
+CREATE INDEX IF NOT EXISTS idx_scheduler_fri_status_updated_at_attempts
+    ON scheduler_witness_jobs_fri (updated_at, attempts)
+    WHERE status IN ('in_progress', 'failed');
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_scheduler_fri_queued_priority_l1_batch_number;
+
+ALTER TABLE scheduler_witness_jobs_fri DROP COLUMN IF EXISTS priority;
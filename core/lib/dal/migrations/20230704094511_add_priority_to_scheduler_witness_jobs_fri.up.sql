@@ -0,0 +1,5 @@
+ALTER TABLE scheduler_witness_jobs_fri ADD COLUMN IF NOT EXISTS priority INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_scheduler_fri_queued_priority_l1_batch_number
+    ON scheduler_witness_jobs_fri (priority DESC, l1_batch_number ASC)
+    WHERE status = 'queued';
@@ -0,0 +1 @@
+ALTER TABLE scheduler_witness_jobs_fri ADD COLUMN IF NOT EXISTS lease_id TEXT;
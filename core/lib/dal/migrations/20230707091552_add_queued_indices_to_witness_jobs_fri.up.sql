@@ -0,0 +1,7 @@
+CREATE INDEX IF NOT EXISTS idx_witness_inputs_fri_queued_order
+    ON witness_inputs_fri (l1_batch_number ASC)
+    WHERE status = 'queued';
+
+CREATE INDEX IF NOT EXISTS idx_scheduler_witness_jobs_fri_queued_order
+    ON scheduler_witness_jobs_fri (priority DESC, l1_batch_number ASC)
+    WHERE status = 'queued';
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_prover_jobs_fri_uncleaned_blobs;
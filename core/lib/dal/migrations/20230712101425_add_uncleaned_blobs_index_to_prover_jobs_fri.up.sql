@@ -0,0 +1,3 @@
+CREATE INDEX IF NOT EXISTS idx_prover_jobs_fri_uncleaned_blobs
+    ON prover_jobs_fri (l1_batch_number)
+    WHERE is_blob_cleaned IS NOT TRUE;
@@ -0,0 +1,2 @@
+ALTER TABLE prover_jobs_fri DROP COLUMN IF EXISTS circuit_blob_size;
+ALTER TABLE scheduler_witness_jobs_fri DROP COLUMN IF EXISTS scheduler_partial_input_blob_size;
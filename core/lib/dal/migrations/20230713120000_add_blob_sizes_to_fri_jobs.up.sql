@@ -0,0 +1,2 @@
+ALTER TABLE prover_jobs_fri ADD COLUMN IF NOT EXISTS circuit_blob_size BIGINT;
+ALTER TABLE scheduler_witness_jobs_fri ADD COLUMN IF NOT EXISTS scheduler_partial_input_blob_size BIGINT;
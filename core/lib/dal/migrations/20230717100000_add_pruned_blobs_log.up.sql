@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS pruned_blobs_log (
+    id BIGSERIAL PRIMARY KEY,
+    l1_batch_number BIGINT NOT NULL,
+    bucket TEXT NOT NULL,
+    blob_key TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    pruned_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS pruned_blobs_log_l1_batch_number_idx ON pruned_blobs_log (l1_batch_number);
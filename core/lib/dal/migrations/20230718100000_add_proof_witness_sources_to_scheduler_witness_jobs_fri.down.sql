@@ -0,0 +1 @@
+ALTER TABLE scheduler_witness_jobs_fri DROP COLUMN IF EXISTS proof_witness_sources;
@@ -0,0 +1 @@
+ALTER TABLE scheduler_witness_jobs_fri ADD COLUMN IF NOT EXISTS proof_witness_sources JSONB;
@@ -0,0 +1,2 @@
+ALTER TABLE scheduler_witness_jobs_fri DROP COLUMN IF EXISTS meta_parameters_hash;
+ALTER TABLE scheduler_witness_jobs_fri DROP COLUMN IF EXISTS pass_through_data_hash;
@@ -0,0 +1,2 @@
+ALTER TABLE scheduler_witness_jobs_fri ADD COLUMN IF NOT EXISTS meta_parameters_hash BYTEA;
+ALTER TABLE scheduler_witness_jobs_fri ADD COLUMN IF NOT EXISTS pass_through_data_hash BYTEA;
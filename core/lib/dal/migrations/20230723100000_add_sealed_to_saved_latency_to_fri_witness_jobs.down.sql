@@ -0,0 +1,4 @@
+ALTER TABLE witness_inputs_fri DROP COLUMN IF EXISTS sealed_to_saved_latency;
+ALTER TABLE leaf_aggregation_witness_jobs_fri DROP COLUMN IF EXISTS sealed_to_saved_latency;
+ALTER TABLE node_aggregation_witness_jobs_fri DROP COLUMN IF EXISTS sealed_to_saved_latency;
+ALTER TABLE scheduler_witness_jobs_fri DROP COLUMN IF EXISTS sealed_to_saved_latency;
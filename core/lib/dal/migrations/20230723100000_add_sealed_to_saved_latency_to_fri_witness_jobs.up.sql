@@ -0,0 +1,4 @@
+ALTER TABLE witness_inputs_fri ADD COLUMN IF NOT EXISTS sealed_to_saved_latency INTERVAL;
+ALTER TABLE leaf_aggregation_witness_jobs_fri ADD COLUMN IF NOT EXISTS sealed_to_saved_latency INTERVAL;
+ALTER TABLE node_aggregation_witness_jobs_fri ADD COLUMN IF NOT EXISTS sealed_to_saved_latency INTERVAL;
+ALTER TABLE scheduler_witness_jobs_fri ADD COLUMN IF NOT EXISTS sealed_to_saved_latency INTERVAL;
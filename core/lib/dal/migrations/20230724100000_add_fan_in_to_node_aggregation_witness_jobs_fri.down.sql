@@ -0,0 +1 @@
+ALTER TABLE node_aggregation_witness_jobs_fri DROP COLUMN IF EXISTS fan_in;
@@ -0,0 +1 @@
+ALTER TABLE node_aggregation_witness_jobs_fri ADD COLUMN IF NOT EXISTS fan_in INT;
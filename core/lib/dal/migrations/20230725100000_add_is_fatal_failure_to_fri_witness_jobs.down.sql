@@ -0,0 +1,4 @@
+ALTER TABLE witness_inputs_fri DROP COLUMN IF EXISTS is_fatal_failure;
+ALTER TABLE leaf_aggregation_witness_jobs_fri DROP COLUMN IF EXISTS is_fatal_failure;
+ALTER TABLE node_aggregation_witness_jobs_fri DROP COLUMN IF EXISTS is_fatal_failure;
+ALTER TABLE scheduler_witness_jobs_fri DROP COLUMN IF EXISTS is_fatal_failure;
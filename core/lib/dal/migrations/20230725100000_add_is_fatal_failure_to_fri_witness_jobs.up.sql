@@ -0,0 +1,4 @@
+ALTER TABLE witness_inputs_fri ADD COLUMN IF NOT EXISTS is_fatal_failure BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE leaf_aggregation_witness_jobs_fri ADD COLUMN IF NOT EXISTS is_fatal_failure BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE node_aggregation_witness_jobs_fri ADD COLUMN IF NOT EXISTS is_fatal_failure BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE scheduler_witness_jobs_fri ADD COLUMN IF NOT EXISTS is_fatal_failure BOOLEAN NOT NULL DEFAULT FALSE;
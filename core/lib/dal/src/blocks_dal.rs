@@ -1,21 +1,24 @@
 use std::{
     collections::HashMap,
     convert::{Into, TryInto},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use bigdecimal::{BigDecimal, FromPrimitive, ToPrimitive};
+use sqlx::types::chrono::{DateTime, Utc};
 use sqlx::Row;
 
 use zksync_types::{
     aggregated_operations::AggregatedActionType,
     block::{BlockGasCount, L1BatchHeader, MiniblockHeader},
     commitment::{BlockMetadata, BlockWithMetadata},
+    proofs::SchedulerCommitments,
     L1BatchNumber, MiniblockNumber, H256, MAX_GAS_PER_PUBDATA_BYTE,
 };
 
 use crate::{
     models::storage_block::{StorageBlock, StorageMiniblockHeader},
+    time_utils::pg_interval_from_duration,
     StorageProcessor,
 };
 
@@ -476,6 +479,26 @@ impl BlocksDal<'_, '_> {
         .map(|record| L1BatchNumber(record.number as u32))
     }
 
+    /// Returns the number of the last block for which an Ethereum prove tx was confirmed
+    /// at least `min_age` ago.
+    pub async fn get_number_of_last_block_proven_on_eth_before(
+        &mut self,
+        min_age: Duration,
+    ) -> Option<L1BatchNumber> {
+        sqlx::query!(
+            "SELECT number FROM l1_batches \
+            LEFT JOIN eth_txs_history AS prove_tx \
+                ON (l1_batches.eth_prove_tx_id = prove_tx.eth_tx_id) \
+            WHERE prove_tx.confirmed_at <= now() - $1::interval \
+            ORDER BY number DESC LIMIT 1",
+            pg_interval_from_duration(min_age)
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()
+        .map(|record| L1BatchNumber(record.number as u32))
+    }
+
     /// Returns the number of the last block for which an Ethereum execute tx was sent and confirmed.
     pub async fn get_number_of_last_block_executed_on_eth(&mut self) -> Option<L1BatchNumber> {
         sqlx::query!(
@@ -706,6 +729,48 @@ impl BlocksDal<'_, '_> {
         Some((H256::from_slice(&row.hash?), row.timestamp as u64))
     }
 
+    /// Returns the time when the L1 batch was sealed, i.e. inserted into the DB by the state keeper.
+    pub async fn get_l1_batch_sealed_at(&mut self, number: L1BatchNumber) -> Option<DateTime<Utc>> {
+        let row = sqlx::query!(
+            "SELECT created_at FROM l1_batches WHERE number = $1",
+            number.0 as i64
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()?;
+        Some(DateTime::<Utc>::from_utc(row.created_at, Utc))
+    }
+
+    /// Returns commitments that scheduler circuits for L1 batches in the specified range
+    /// are expected to expose. Batches without metadata are omitted.
+    pub async fn get_expected_scheduler_commitments(
+        &mut self,
+        first_number: L1BatchNumber,
+        last_number: L1BatchNumber,
+    ) -> HashMap<L1BatchNumber, SchedulerCommitments> {
+        sqlx::query!(
+            "
+                SELECT number, meta_parameters_hash, pass_through_data_hash FROM l1_batches
+                WHERE number BETWEEN $1 AND $2
+                    AND meta_parameters_hash IS NOT NULL AND pass_through_data_hash IS NOT NULL
+               ",
+            first_number.0 as i64,
+            last_number.0 as i64
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|row| {
+            let commitments = SchedulerCommitments {
+                meta_parameters_hash: H256::from_slice(&row.meta_parameters_hash?),
+                pass_through_data_hash: H256::from_slice(&row.pass_through_data_hash?),
+            };
+            Some((L1BatchNumber(row.number as u32), commitments))
+        })
+        .collect()
+    }
+
     pub async fn get_newest_block_header(&mut self) -> L1BatchHeader {
         let last_block = sqlx::query_as!(
             StorageBlock,
@@ -3,11 +3,14 @@ use std::convert::TryFrom;
 use std::time::{Duration, Instant};
 use zksync_config::configs::fri_prover_group::CircuitIdRoundTuple;
 
-use zksync_types::proofs::{AggregationRound, FriProverJobMetadata, JobCountStatistics, StuckJobs};
+use zksync_types::proofs::{
+    AggregationRound, DailyBlobSizes, FriProverJobMetadata, JobCountStatistics, StuckJobs,
+};
 use zksync_types::L1BatchNumber;
 
+use crate::fri_prover_job_notifications::{NewProverJobNotification, NEW_PROVER_JOBS_CHANNEL};
 use crate::time_utils::{duration_to_naive_time, pg_interval_from_duration};
-use crate::StorageProcessor;
+use crate::{SqlxError, StorageProcessor};
 
 #[derive(Debug)]
 pub struct FriProverDal<'a, 'c> {
@@ -208,6 +211,8 @@ impl FriProverDal<'_, '_> {
         }
     }
 
+    /// Queues a prover job for the circuit. If the job already exists, it's left intact
+    /// (only its `updated_at` is touched), so that saving circuits can be retried.
     #[allow(clippy::too_many_arguments)]
     pub async fn insert_prover_job(
         &mut self,
@@ -219,6 +224,31 @@ impl FriProverDal<'_, '_> {
         circuit_blob_url: &str,
         is_node_final_proof: bool,
     ) {
+        self.try_insert_prover_job(
+            l1_batch_number,
+            circuit_id,
+            depth,
+            sequence_number,
+            aggregation_round,
+            circuit_blob_url,
+            is_node_final_proof,
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Same as [`Self::insert_prover_job()`], but returns an error instead of panicking.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn try_insert_prover_job(
+        &mut self,
+        l1_batch_number: L1BatchNumber,
+        circuit_id: u8,
+        depth: u16,
+        sequence_number: usize,
+        aggregation_round: AggregationRound,
+        circuit_blob_url: &str,
+        is_node_final_proof: bool,
+    ) -> Result<(), SqlxError> {
         sqlx::query!(
                     "
                     INSERT INTO prover_jobs_fri (l1_batch_number, circuit_id, circuit_blob_url, aggregation_round, sequence_number, depth, is_node_final_proof, status, created_at, updated_at)
@@ -235,8 +265,91 @@ impl FriProverDal<'_, '_> {
                     is_node_final_proof,
                 )
             .execute(self.storage.conn())
-            .await
-            .unwrap();
+            .await?;
+        Ok(())
+    }
+
+    /// Notifies listeners on [`NEW_PROVER_JOBS_CHANNEL`] about a queued prover job. If called
+    /// within a transaction, the notification is delivered once the transaction is committed.
+    pub async fn try_notify_new_prover_job(
+        &mut self,
+        notification: NewProverJobNotification,
+    ) -> Result<(), SqlxError> {
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(NEW_PROVER_JOBS_CHANNEL)
+            .bind(notification.to_payload())
+            .execute(self.storage.conn())
+            .await?;
+        Ok(())
+    }
+
+    /// Records the size of the serialized circuit for a prover job, in bytes.
+    pub async fn try_set_circuit_blob_size(
+        &mut self,
+        l1_batch_number: L1BatchNumber,
+        circuit_id: u8,
+        depth: u16,
+        sequence_number: usize,
+        aggregation_round: AggregationRound,
+        circuit_blob_size: u64,
+    ) -> Result<(), SqlxError> {
+        sqlx::query!(
+            "
+            UPDATE prover_jobs_fri
+            SET circuit_blob_size = $6, updated_at = now()
+            WHERE l1_batch_number = $1 AND circuit_id = $2 AND depth = $3
+                AND sequence_number = $4 AND aggregation_round = $5
+            ",
+            l1_batch_number.0 as i64,
+            circuit_id as i16,
+            depth as i32,
+            sequence_number as i64,
+            aggregation_round as i16,
+            circuit_blob_size as i64,
+        )
+        .execute(self.storage.conn())
+        .await?;
+        Ok(())
+    }
+
+    /// Returns total sizes of circuit and input blobs per aggregation round and day
+    /// for jobs created within the `period`, ordered by day and round. Jobs without recorded
+    /// blob sizes are not taken into account.
+    pub async fn get_daily_blob_sizes(&mut self, period: Duration) -> Vec<DailyBlobSizes> {
+        sqlx::query!(
+            r#"
+            SELECT day AS "day!", aggregation_round AS "aggregation_round!",
+                SUM(circuit_blob_size)::BIGINT AS "circuit_blobs_size!",
+                SUM(input_blob_size)::BIGINT AS "input_blobs_size!"
+            FROM (
+                SELECT date_trunc('day', created_at) AS day, aggregation_round,
+                    circuit_blob_size, 0::BIGINT AS input_blob_size
+                FROM prover_jobs_fri
+                WHERE circuit_blob_size IS NOT NULL AND created_at >= now() - $1::interval
+                UNION ALL
+                SELECT date_trunc('day', created_at) AS day, $2::SMALLINT AS aggregation_round,
+                    0::BIGINT AS circuit_blob_size, scheduler_partial_input_blob_size AS input_blob_size
+                FROM scheduler_witness_jobs_fri
+                WHERE scheduler_partial_input_blob_size IS NOT NULL
+                    AND created_at >= now() - $1::interval
+            ) AS sizes
+            GROUP BY day, aggregation_round
+            ORDER BY day, aggregation_round
+            "#,
+            pg_interval_from_duration(period),
+            AggregationRound::Scheduler as i16,
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| DailyBlobSizes {
+            day: row.day.date(),
+            aggregation_round: AggregationRound::try_from(row.aggregation_round as i32).unwrap(),
+            circuit_blobs_size: row.circuit_blobs_size as u64,
+            input_blobs_size: row.input_blobs_size as u64,
+        })
+        .collect()
     }
 
     pub async fn get_prover_jobs_stats(&mut self) -> HashMap<(u8, u8), JobCountStatistics> {
@@ -295,4 +408,138 @@ impl FriProverDal<'_, '_> {
             .collect()
         }
     }
+
+    /// Returns the minimum number of an L1 batch with prover jobs, blobs for which haven't been
+    /// removed from the object store yet.
+    pub async fn min_l1_batch_with_uncleaned_blobs(&mut self) -> Option<L1BatchNumber> {
+        let row = sqlx::query!(
+            r#"
+                SELECT MIN(l1_batch_number) AS "l1_batch_number"
+                FROM prover_jobs_fri
+                WHERE is_blob_cleaned IS NOT TRUE
+            "#
+        )
+        .fetch_one(self.storage.conn())
+        .await
+        .unwrap();
+        row.l1_batch_number
+            .map(|number| L1BatchNumber(number as u32))
+    }
+
+    /// Returns circuit and proof blob URLs for all prover jobs of the specified L1 batch.
+    pub async fn get_blob_urls_for_l1_batch(
+        &mut self,
+        l1_batch_number: L1BatchNumber,
+    ) -> Vec<(String, Option<String>)> {
+        sqlx::query!(
+            r#"
+                SELECT circuit_blob_url, proof_blob_url
+                FROM prover_jobs_fri
+                WHERE l1_batch_number = $1
+            "#,
+            l1_batch_number.0 as i64
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.circuit_blob_url, row.proof_blob_url))
+        .collect()
+    }
+
+    /// Returns circuit blob URLs for all prover jobs of the specified aggregation round, together
+    /// with flags whether blobs for the jobs were removed from the object store.
+    pub async fn get_circuit_blob_urls_for_round(
+        &mut self,
+        aggregation_round: AggregationRound,
+    ) -> Vec<(String, bool)> {
+        sqlx::query!(
+            r#"
+                SELECT circuit_blob_url, is_blob_cleaned IS TRUE AS "is_blob_cleaned!"
+                FROM prover_jobs_fri
+                WHERE aggregation_round = $1
+            "#,
+            aggregation_round as i16
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.circuit_blob_url, row.is_blob_cleaned))
+        .collect()
+    }
+
+    /// Returns circuit IDs and proof blob URLs (if the proofs are saved) of the specified prover
+    /// jobs, keyed by job ID. Unknown jobs are omitted.
+    pub async fn get_circuit_ids_and_proof_blob_urls(
+        &mut self,
+        job_ids: &[u32],
+    ) -> HashMap<u32, (u8, Option<String>)> {
+        let job_ids: Vec<i64> = job_ids.iter().map(|&id| id as i64).collect();
+        sqlx::query!(
+            r#"
+                SELECT id, circuit_id, proof_blob_url FROM prover_jobs_fri
+                WHERE id = ANY($1)
+            "#,
+            &job_ids
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.id as u32, (row.circuit_id as u8, row.proof_blob_url)))
+        .collect()
+    }
+
+    /// Returns the status of the prover job for the scheduler circuit of the L1 batch, or `None`
+    /// if there's no such job, e.g. because the scheduler witness wasn't generated yet.
+    pub async fn get_scheduler_prover_job_status(
+        &mut self,
+        l1_batch_number: L1BatchNumber,
+    ) -> Option<String> {
+        sqlx::query!(
+            r#"
+                SELECT status FROM prover_jobs_fri
+                WHERE l1_batch_number = $1 AND aggregation_round = $2
+            "#,
+            l1_batch_number.0 as i64,
+            AggregationRound::Scheduler as i16
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()
+        .map(|row| row.status)
+    }
+
+    /// Removes the prover job for the scheduler circuit of the L1 batch, so that it's inserted anew
+    /// once the scheduler witness is regenerated. Returns `false` if there was no such job.
+    pub async fn remove_scheduler_prover_job(&mut self, l1_batch_number: L1BatchNumber) -> bool {
+        sqlx::query!(
+            r#"
+                DELETE FROM prover_jobs_fri
+                WHERE l1_batch_number = $1 AND aggregation_round = $2
+            "#,
+            l1_batch_number.0 as i64,
+            AggregationRound::Scheduler as i16
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap()
+        .rows_affected()
+            > 0
+    }
+
+    pub async fn mark_l1_batch_blobs_as_cleaned(&mut self, l1_batch_number: L1BatchNumber) {
+        sqlx::query!(
+            r#"
+                UPDATE prover_jobs_fri
+                SET is_blob_cleaned = TRUE
+                WHERE l1_batch_number = $1
+            "#,
+            l1_batch_number.0 as i64
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+    }
 }
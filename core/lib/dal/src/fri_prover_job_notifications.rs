@@ -0,0 +1,130 @@
+//! Notifications about queued FRI prover jobs sent with Postgres `NOTIFY`, which allow provers
+//! to pick up new jobs without waiting for the next poll of the job queue.
+
+use std::convert::{TryFrom, TryInto};
+use std::time::Duration;
+
+use sqlx::postgres::PgListener;
+use tokio::time::Instant;
+
+use zksync_types::proofs::AggregationRound;
+use zksync_types::L1BatchNumber;
+
+use crate::{ConnectionPool, SqlxError};
+
+/// Channel on which notifications about queued prover jobs are sent.
+pub const NEW_PROVER_JOBS_CHANNEL: &str = "fri_prover_jobs_new";
+
+/// Notification about a queued prover job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewProverJobNotification {
+    pub l1_batch_number: L1BatchNumber,
+    pub circuit_id: u8,
+    pub aggregation_round: AggregationRound,
+}
+
+impl NewProverJobNotification {
+    pub(crate) fn to_payload(self) -> String {
+        serde_json::json!({
+            "l1_batch_number": self.l1_batch_number.0,
+            "circuit_id": self.circuit_id,
+            "aggregation_round": self.aggregation_round as i32,
+        })
+        .to_string()
+    }
+
+    fn from_payload(payload: &str) -> Option<Self> {
+        let payload: serde_json::Value = serde_json::from_str(payload).ok()?;
+        let aggregation_round: i32 = payload["aggregation_round"].as_u64()?.try_into().ok()?;
+        Some(Self {
+            l1_batch_number: L1BatchNumber(payload["l1_batch_number"].as_u64()?.try_into().ok()?),
+            circuit_id: payload["circuit_id"].as_u64()?.try_into().ok()?,
+            aggregation_round: AggregationRound::try_from(aggregation_round).ok()?,
+        })
+    }
+}
+
+/// Listener of [`NewProverJobNotification`]s. The listener reconnects to the DB if
+/// the connection is lost, but notifications sent in the meantime are lost, so consumers
+/// should still poll the job queue, e.g. after [`Self::wait()`] times out.
+#[derive(Debug)]
+pub struct NewProverJobsListener {
+    inner: PgListener,
+}
+
+impl NewProverJobsListener {
+    /// Starts listening on [`NEW_PROVER_JOBS_CHANNEL`] over a dedicated connection. Returns
+    /// `Ok(None)` for test pools, since they never commit transactions, so notifications
+    /// are never delivered.
+    pub async fn new(pool: &ConnectionPool) -> Result<Option<Self>, SqlxError> {
+        let pool = match pool {
+            ConnectionPool::Real(pool) => pool,
+            ConnectionPool::Test(_) => return Ok(None),
+        };
+        let mut inner = PgListener::connect_with(pool).await?;
+        inner.listen(NEW_PROVER_JOBS_CHANNEL).await?;
+        Ok(Some(Self { inner }))
+    }
+
+    /// Waits for the next notification for up to `timeout`. Returns `Ok(None)` if there was
+    /// no notification in time. Notifications with malformed payloads are logged and skipped.
+    pub async fn wait(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<NewProverJobNotification>, SqlxError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let notification = match tokio::time::timeout_at(deadline, self.inner.recv()).await {
+                Ok(notification) => notification?,
+                Err(_) => return Ok(None),
+            };
+            match NewProverJobNotification::from_payload(notification.payload()) {
+                Some(notification) => return Ok(Some(notification)),
+                None => vlog::warn!(
+                    "Skipped malformed notification on channel {}: {:?}",
+                    NEW_PROVER_JOBS_CHANNEL,
+                    notification.payload()
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_payload_roundtrip() {
+        let notification = NewProverJobNotification {
+            l1_batch_number: L1BatchNumber(42),
+            circuit_id: 1,
+            aggregation_round: AggregationRound::Scheduler,
+        };
+        let payload = notification.to_payload();
+        assert_eq!(
+            NewProverJobNotification::from_payload(&payload),
+            Some(notification)
+        );
+        let payload = r#"{"l1_batch_number":42,"circuit_id":1,"aggregation_round":3}"#;
+        assert_eq!(
+            NewProverJobNotification::from_payload(payload),
+            Some(notification)
+        );
+
+        for malformed_payload in [
+            "",
+            "42",
+            r#"{"aggregation_round":4,"circuit_id":1,"l1_batch_number":42}"#,
+            r#"{"aggregation_round":3,"circuit_id":256,"l1_batch_number":42}"#,
+            r#"{"aggregation_round":3,"l1_batch_number":42}"#,
+        ] {
+            assert_eq!(
+                NewProverJobNotification::from_payload(malformed_payload),
+                None,
+                "{}",
+                malformed_payload
+            );
+        }
+    }
+}
@@ -76,11 +76,39 @@ impl FriSchedulerDependencyTrackerDal<'_, '_> {
             .unwrap();
     }
 
+    /// Returns `(circuit_id, final_prover_job_id)` pairs for all circuits of the L1 batch.
     pub async fn get_final_prover_job_ids_for(
         &mut self,
         l1_batch_number: L1BatchNumber,
-    ) -> [u32; 13] {
-        sqlx::query!(
+    ) -> [(u8, u32); 13] {
+        self.try_get_final_prover_job_ids_for(l1_batch_number)
+            .await
+            .unwrap()
+    }
+
+    /// Same as [`Self::get_final_prover_job_ids_for()`], but returns `None` if the L1 batch
+    /// is not tracked or some of its final prover jobs are unknown.
+    pub async fn try_get_final_prover_job_ids_for(
+        &mut self,
+        l1_batch_number: L1BatchNumber,
+    ) -> Option<[(u8, u32); 13]> {
+        let job_ids = self.get_final_prover_job_id_states(l1_batch_number).await?;
+        let mut final_job_ids = [(0, 0); 13];
+        for (final_job_id, (circuit_id, job_id)) in final_job_ids.iter_mut().zip(job_ids) {
+            *final_job_id = (circuit_id, job_id?);
+        }
+        Some(final_job_ids)
+    }
+
+    /// Returns `(circuit_id, final_prover_job_id)` pairs recorded for circuits of the L1 batch,
+    /// or `None` if the L1 batch is not tracked. IDs are `None` for circuits for which the final
+    /// node proof wasn't generated yet. Pairs are ordered by the circuit ID, but consumers should
+    /// match proofs to circuits by the returned circuit IDs rather than by their positions.
+    pub async fn get_final_prover_job_id_states(
+        &mut self,
+        l1_batch_number: L1BatchNumber,
+    ) -> Option<[(u8, Option<u32>); 13]> {
+        let row = sqlx::query!(
             r#"
                 SELECT * FROM scheduler_dependency_tracker_fri
                 WHERE l1_batch_number = $1
@@ -91,24 +119,24 @@ impl FriSchedulerDependencyTrackerDal<'_, '_> {
         .await
         .unwrap()
         .into_iter()
-        .next()
-        .map(|row| {
+        .next()?;
+        Some(
             [
-                row.circuit_1_final_prover_job_id.unwrap() as u32,
-                row.circuit_2_final_prover_job_id.unwrap() as u32,
-                row.circuit_3_final_prover_job_id.unwrap() as u32,
-                row.circuit_4_final_prover_job_id.unwrap() as u32,
-                row.circuit_5_final_prover_job_id.unwrap() as u32,
-                row.circuit_6_final_prover_job_id.unwrap() as u32,
-                row.circuit_7_final_prover_job_id.unwrap() as u32,
-                row.circuit_8_final_prover_job_id.unwrap() as u32,
-                row.circuit_9_final_prover_job_id.unwrap() as u32,
-                row.circuit_10_final_prover_job_id.unwrap() as u32,
-                row.circuit_11_final_prover_job_id.unwrap() as u32,
-                row.circuit_12_final_prover_job_id.unwrap() as u32,
-                row.circuit_13_final_prover_job_id.unwrap() as u32,
+                (1, row.circuit_1_final_prover_job_id),
+                (2, row.circuit_2_final_prover_job_id),
+                (3, row.circuit_3_final_prover_job_id),
+                (4, row.circuit_4_final_prover_job_id),
+                (5, row.circuit_5_final_prover_job_id),
+                (6, row.circuit_6_final_prover_job_id),
+                (7, row.circuit_7_final_prover_job_id),
+                (8, row.circuit_8_final_prover_job_id),
+                (9, row.circuit_9_final_prover_job_id),
+                (10, row.circuit_10_final_prover_job_id),
+                (11, row.circuit_11_final_prover_job_id),
+                (12, row.circuit_12_final_prover_job_id),
+                (13, row.circuit_13_final_prover_job_id),
             ]
-        })
-        .unwrap()
+            .map(|(circuit_id, job_id)| (circuit_id, job_id.map(|job_id| job_id as u32))),
+        )
     }
 }
@@ -1,15 +1,17 @@
 use sqlx::Row;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
 use std::time::{Duration, Instant};
 
 use zksync_types::proofs::{
-    AggregationRound, JobCountStatistics, LeafAggregationJobMetadata, NodeAggregationJobMetadata,
-    StuckJobs,
+    AggregationRound, JobCountStatistics, JobErrorHistoryEntry, LeafAggregationJobMetadata,
+    NodeAggregationJobMetadata, SchedulerCommitments, SchedulerJobLease,
+    SchedulerProofWitnessSource, StuckJobs,
 };
-use zksync_types::L1BatchNumber;
+use zksync_types::{L1BatchNumber, H256};
 
 use crate::time_utils::{duration_to_naive_time, pg_interval_from_duration};
-use crate::StorageProcessor;
+use crate::{SqlxError, StorageProcessor};
 
 #[derive(Debug)]
 pub struct FriWitnessGeneratorDal<'a, 'c> {
@@ -28,6 +30,8 @@ pub enum FriWitnessJobStatus {
     InProgress,
     #[strum(serialize = "queued")]
     Queued,
+    #[strum(serialize = "requires_manual_intervention")]
+    RequiresManualIntervention,
 }
 
 impl FriWitnessGeneratorDal<'_, '_> {
@@ -46,11 +50,13 @@ impl FriWitnessGeneratorDal<'_, '_> {
         }
     }
 
+    /// Picks the next queued basic circuit witness job for an L1 batch in the specified range,
+    /// returning its L1 batch along with the number of attempts including the current one.
     pub async fn get_next_basic_circuit_witness_job(
         &mut self,
-        last_l1_batch_to_process: u32,
-    ) -> Option<L1BatchNumber> {
-        let result: Option<L1BatchNumber> = sqlx::query!(
+        l1_batch_range: &RangeInclusive<L1BatchNumber>,
+    ) -> Option<(L1BatchNumber, u32)> {
+        let result: Option<(L1BatchNumber, u32)> = sqlx::query!(
             "
                 UPDATE witness_inputs_fri
                 SET status = 'in_progress', attempts = attempts + 1,
@@ -58,21 +64,28 @@ impl FriWitnessGeneratorDal<'_, '_> {
                 WHERE l1_batch_number = (
                     SELECT l1_batch_number
                     FROM witness_inputs_fri
-                    WHERE l1_batch_number <= $1
+                    WHERE l1_batch_number BETWEEN $1 AND $2
                     AND status = 'queued'
                     ORDER BY l1_batch_number ASC
                     LIMIT 1
                     FOR UPDATE
                     SKIP LOCKED
                 )
+                AND status = 'queued'
                 RETURNING witness_inputs_fri.*
                ",
-            last_l1_batch_to_process as i64
+            l1_batch_range.start().0 as i64,
+            l1_batch_range.end().0 as i64
         )
         .fetch_optional(self.storage.conn())
         .await
         .unwrap()
-        .map(|row| L1BatchNumber(row.l1_batch_number as u32));
+        .map(|row| {
+            (
+                L1BatchNumber(row.l1_batch_number as u32),
+                row.attempts as u32,
+            )
+        });
         result
     }
 
@@ -113,29 +126,46 @@ impl FriWitnessGeneratorDal<'_, '_> {
         .unwrap();
     }
 
-    pub async fn mark_witness_job_failed(&mut self, error: &str, block_number: L1BatchNumber) {
+    /// Marks the basic circuits job as failed. Unless the failure is `retryable`, the job is kept
+    /// failed for manual inspection rather than requeued by [`Self::requeue_stuck_basic_jobs()`].
+    pub async fn mark_witness_job_failed(
+        &mut self,
+        error: &str,
+        block_number: L1BatchNumber,
+        retryable: bool,
+    ) {
         sqlx::query!(
             "
-                UPDATE witness_inputs_fri SET status ='failed', error= $1, updated_at = now()
+                UPDATE witness_inputs_fri SET status ='failed', error= $1, is_fatal_failure = NOT $3, updated_at = now()
                 WHERE l1_batch_number = $2
                ",
             error,
-            block_number.0 as i64
+            block_number.0 as i64,
+            retryable
         )
         .execute(self.storage.conn())
         .await
         .unwrap();
     }
 
-    pub async fn mark_leaf_aggregation_job_failed(&mut self, error: &str, id: u32) {
+    /// Marks the leaf aggregation job as failed. Unless the failure is `retryable`, the job is kept
+    /// failed for manual inspection rather than requeued by
+    /// [`Self::requeue_stuck_leaf_aggregations_jobs()`].
+    pub async fn mark_leaf_aggregation_job_failed(
+        &mut self,
+        error: &str,
+        id: u32,
+        retryable: bool,
+    ) {
         sqlx::query!(
             "
                 UPDATE leaf_aggregation_witness_jobs_fri
-                SET status ='failed', error= $1, updated_at = now()
+                SET status ='failed', error= $1, is_fatal_failure = NOT $3, updated_at = now()
                 WHERE id = $2
                ",
             error,
-            id as i64
+            id as i64,
+            retryable
         )
         .execute(self.storage.conn())
         .await
@@ -157,19 +187,40 @@ impl FriWitnessGeneratorDal<'_, '_> {
         .unwrap();
     }
 
-    pub async fn requeue_stuck_jobs(
+    /// Returns the leaf aggregation job to the queue without counting the attempt, e.g. if it was
+    /// prefetched by a witness generator that has stopped before processing it.
+    pub async fn release_leaf_aggregation_job(&mut self, id: u32) {
+        sqlx::query!(
+            "
+                UPDATE leaf_aggregation_witness_jobs_fri
+                SET status = 'queued', attempts = attempts - 1, updated_at = now(),
+                    processing_started_at = NULL
+                WHERE id = $1 AND status = 'in_progress'
+               ",
+            id as i64
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+    }
+
+    /// Requeues basic circuits jobs that have failed with retryable errors or have been in progress
+    /// for longer than `processing_timeout`; fatal failures stay failed for manual inspection.
+    /// Jobs stuck in progress after `max_attempts` are marked as failed with an "exhausted attempts"
+    /// error instead, so that they aren't requeued forever.
+    pub async fn requeue_stuck_basic_jobs(
         &mut self,
         processing_timeout: Duration,
         max_attempts: u32,
     ) -> Vec<StuckJobs> {
         let processing_timeout = pg_interval_from_duration(processing_timeout);
-        sqlx::query!(
+        let mut stuck_jobs: Vec<_> = sqlx::query!(
                 "
                 UPDATE witness_inputs_fri
                 SET status = 'queued', attempts = attempts + 1, updated_at = now(), processing_started_at = now()
                 WHERE (status = 'in_progress' AND  processing_started_at <= now() - $1::interval AND attempts < $2)
                 OR (status = 'in_gpu_proof' AND  processing_started_at <= now() - $1::interval AND attempts < $2)
-                OR (status = 'failed' AND attempts < $2)
+                OR (status = 'failed' AND NOT is_fatal_failure AND attempts < $2)
                 RETURNING l1_batch_number, status, attempts
                 ",
                 &processing_timeout,
@@ -180,15 +231,41 @@ impl FriWitnessGeneratorDal<'_, '_> {
             .unwrap()
             .into_iter()
             .map(|row| StuckJobs { id: row.l1_batch_number as u64, status: row.status, attempts: row.attempts as u64 })
-            .collect()
+            .collect();
+        let exhausted_jobs = sqlx::query!(
+            "
+            UPDATE witness_inputs_fri
+            SET status = 'failed', error = 'exhausted attempts', updated_at = now()
+            WHERE status IN ('in_progress', 'in_gpu_proof')
+                AND processing_started_at <= now() - $1::interval AND attempts >= $2
+            RETURNING l1_batch_number, status, attempts
+            ",
+            &processing_timeout,
+            max_attempts as i32,
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| StuckJobs {
+            id: row.l1_batch_number as u64,
+            status: row.status,
+            attempts: row.attempts as u64,
+        });
+        stuck_jobs.extend(exhausted_jobs);
+        stuck_jobs
     }
 
+    /// Creates leaf, node and scheduler jobs for the L1 batch. If `scheduler_job_deadline` is set,
+    /// the scheduler job must be completed within this time since the basic witness job for the batch
+    /// was created, i.e. since the batch was sealed.
     pub async fn create_aggregation_jobs(
         &mut self,
         block_number: L1BatchNumber,
         closed_form_inputs_and_urls: &Vec<(u8, String, usize)>,
         scheduler_partial_input_blob_url: &str,
         base_layer_to_recursive_layer_circuit_id: fn(u8) -> u8,
+        scheduler_job_deadline: Option<Duration>,
     ) {
         {
             let started_at = Instant::now();
@@ -217,6 +294,7 @@ impl FriWitnessGeneratorDal<'_, '_> {
                     None,
                     0,
                     "",
+                    None,
                 )
                 .await;
             }
@@ -224,13 +302,17 @@ impl FriWitnessGeneratorDal<'_, '_> {
             sqlx::query!(
                     "
                     INSERT INTO scheduler_witness_jobs_fri
-                        (l1_batch_number, scheduler_partial_input_blob_url, status, created_at, updated_at)
-                    VALUES ($1, $2, 'waiting_for_proofs', now(), now())
+                        (l1_batch_number, scheduler_partial_input_blob_url, status, created_at, updated_at, deadline_at)
+                    VALUES (
+                        $1, $2, 'waiting_for_proofs', now(), now(),
+                        (SELECT created_at FROM witness_inputs_fri WHERE l1_batch_number = $1) + $3::interval
+                    )
                     ON CONFLICT(l1_batch_number)
                     DO UPDATE SET updated_at=now()
                     ",
                     block_number.0 as i64,
                     scheduler_partial_input_blob_url,
+                    scheduler_job_deadline.map(pg_interval_from_duration),
                 )
                 .execute(self.storage.conn())
                 .await
@@ -254,7 +336,33 @@ impl FriWitnessGeneratorDal<'_, '_> {
         }
     }
 
-    pub async fn get_next_leaf_aggregation_job(&mut self) -> Option<LeafAggregationJobMetadata> {
+    /// Records the size of the serialized scheduler partial input for an L1 batch, in bytes.
+    pub async fn set_scheduler_partial_input_blob_size(
+        &mut self,
+        block_number: L1BatchNumber,
+        blob_size: u64,
+    ) {
+        sqlx::query!(
+            "
+            UPDATE scheduler_witness_jobs_fri
+            SET scheduler_partial_input_blob_size = $2, updated_at = now()
+            WHERE l1_batch_number = $1
+            ",
+            block_number.0 as i64,
+            blob_size as i64,
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+    }
+
+    /// Picks the next queued leaf aggregation job for an L1 batch in the specified range.
+    pub async fn get_next_leaf_aggregation_job(
+        &mut self,
+        l1_batch_range: &RangeInclusive<L1BatchNumber>,
+        circuit_ids: Option<&[u8]>,
+    ) -> Option<LeafAggregationJobMetadata> {
+        let circuit_ids = circuit_ids_param(circuit_ids);
         let row = sqlx::query!(
             "
                 UPDATE leaf_aggregation_witness_jobs_fri
@@ -263,14 +371,19 @@ impl FriWitnessGeneratorDal<'_, '_> {
                 WHERE id = (
                     SELECT id
                     FROM leaf_aggregation_witness_jobs_fri
-                    WHERE status = 'queued'
+                    WHERE status = 'queued' AND l1_batch_number BETWEEN $1 AND $2
+                    AND ($3::smallint[] IS NULL OR circuit_id = ANY($3))
                     ORDER BY l1_batch_number ASC, id ASC
                     LIMIT 1
                     FOR UPDATE
                     SKIP LOCKED
                 )
+                AND status = 'queued'
                 RETURNING leaf_aggregation_witness_jobs_fri.*
                 ",
+            l1_batch_range.start().0 as i64,
+            l1_batch_range.end().0 as i64,
+            circuit_ids.as_deref()
         )
         .fetch_optional(self.storage.conn())
         .await
@@ -288,10 +401,71 @@ impl FriWitnessGeneratorDal<'_, '_> {
             id: row.id as u32,
             block_number,
             circuit_id: row.circuit_id as u8,
+            attempts: row.attempts as u32,
             prover_job_ids_for_proofs: proof_job_ids,
         })
     }
 
+    /// Atomically claims up to `limit` queued leaf aggregation jobs, in the same order
+    /// as [`Self::get_next_leaf_aggregation_job()`] would return them one by one.
+    pub async fn get_next_leaf_aggregation_jobs(
+        &mut self,
+        limit: usize,
+        l1_batch_range: &RangeInclusive<L1BatchNumber>,
+        circuit_ids: Option<&[u8]>,
+    ) -> Vec<LeafAggregationJobMetadata> {
+        let circuit_ids = circuit_ids_param(circuit_ids);
+        let mut rows = sqlx::query!(
+            "
+                UPDATE leaf_aggregation_witness_jobs_fri
+                SET status = 'in_progress', attempts = attempts + 1,
+                    updated_at = now(), processing_started_at = now()
+                WHERE id IN (
+                    SELECT id
+                    FROM leaf_aggregation_witness_jobs_fri
+                    WHERE status = 'queued' AND l1_batch_number BETWEEN $2 AND $3
+                    AND ($4::smallint[] IS NULL OR circuit_id = ANY($4))
+                    ORDER BY l1_batch_number ASC, id ASC
+                    LIMIT $1
+                    FOR UPDATE
+                    SKIP LOCKED
+                )
+                AND status = 'queued'
+                RETURNING leaf_aggregation_witness_jobs_fri.*
+                ",
+            limit as i64,
+            l1_batch_range.start().0 as i64,
+            l1_batch_range.end().0 as i64,
+            circuit_ids.as_deref()
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap();
+        // `RETURNING` doesn't preserve the order of the subquery.
+        rows.sort_unstable_by_key(|row| (row.l1_batch_number, row.id));
+
+        let mut jobs = Vec::with_capacity(rows.len());
+        for row in rows {
+            let block_number = L1BatchNumber(row.l1_batch_number as u32);
+            let proof_job_ids = self
+                .prover_job_ids_for(
+                    block_number,
+                    row.circuit_id as u8,
+                    AggregationRound::BasicCircuits,
+                    0,
+                )
+                .await;
+            jobs.push(LeafAggregationJobMetadata {
+                id: row.id as u32,
+                block_number,
+                circuit_id: row.circuit_id as u8,
+                attempts: row.attempts as u32,
+                prover_job_ids_for_proofs: proof_job_ids,
+            });
+        }
+        jobs
+    }
+
     async fn prover_job_ids_for(
         &mut self,
         block_number: L1BatchNumber,
@@ -376,7 +550,13 @@ impl FriWitnessGeneratorDal<'_, '_> {
         .unwrap();
     }
 
-    pub async fn get_next_node_aggregation_job(&mut self) -> Option<NodeAggregationJobMetadata> {
+    /// Picks the next queued node aggregation job for an L1 batch in the specified range.
+    pub async fn get_next_node_aggregation_job(
+        &mut self,
+        l1_batch_range: &RangeInclusive<L1BatchNumber>,
+        circuit_ids: Option<&[u8]>,
+    ) -> Option<NodeAggregationJobMetadata> {
+        let circuit_ids = circuit_ids_param(circuit_ids);
         let row = sqlx::query!(
             "
                 UPDATE node_aggregation_witness_jobs_fri
@@ -385,14 +565,19 @@ impl FriWitnessGeneratorDal<'_, '_> {
                 WHERE id = (
                     SELECT id
                     FROM node_aggregation_witness_jobs_fri
-                    WHERE status = 'queued'
+                    WHERE status = 'queued' AND l1_batch_number BETWEEN $1 AND $2
+                    AND ($3::smallint[] IS NULL OR circuit_id = ANY($3))
                     ORDER BY l1_batch_number ASC, depth ASC, id ASC
                     LIMIT 1
                     FOR UPDATE
                     SKIP LOCKED
                 )
+                AND status = 'queued'
                 RETURNING node_aggregation_witness_jobs_fri.*
                 ",
+            l1_batch_range.start().0 as i64,
+            l1_batch_range.end().0 as i64,
+            circuit_ids.as_deref()
         )
         .fetch_optional(self.storage.conn())
         .await
@@ -415,19 +600,92 @@ impl FriWitnessGeneratorDal<'_, '_> {
             block_number,
             circuit_id: row.circuit_id as u8,
             depth,
+            attempts: row.attempts as u32,
             prover_job_ids_for_proofs: prover_job_ids,
+            fan_in: row.fan_in.map(|fan_in| fan_in as usize),
         })
     }
 
-    pub async fn mark_node_aggregation_job_failed(&mut self, error: &str, id: u32) {
+    /// Atomically claims up to `limit` queued node aggregation jobs, in the same order
+    /// as [`Self::get_next_node_aggregation_job()`] would return them one by one.
+    pub async fn get_next_node_aggregation_jobs(
+        &mut self,
+        limit: usize,
+        l1_batch_range: &RangeInclusive<L1BatchNumber>,
+        circuit_ids: Option<&[u8]>,
+    ) -> Vec<NodeAggregationJobMetadata> {
+        let circuit_ids = circuit_ids_param(circuit_ids);
+        let mut rows = sqlx::query!(
+            "
+                UPDATE node_aggregation_witness_jobs_fri
+                SET status = 'in_progress', attempts = attempts + 1,
+                    updated_at = now(), processing_started_at = now()
+                WHERE id IN (
+                    SELECT id
+                    FROM node_aggregation_witness_jobs_fri
+                    WHERE status = 'queued' AND l1_batch_number BETWEEN $2 AND $3
+                    AND ($4::smallint[] IS NULL OR circuit_id = ANY($4))
+                    ORDER BY l1_batch_number ASC, depth ASC, id ASC
+                    LIMIT $1
+                    FOR UPDATE
+                    SKIP LOCKED
+                )
+                AND status = 'queued'
+                RETURNING node_aggregation_witness_jobs_fri.*
+                ",
+            limit as i64,
+            l1_batch_range.start().0 as i64,
+            l1_batch_range.end().0 as i64,
+            circuit_ids.as_deref()
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap();
+        // `RETURNING` doesn't preserve the order of the subquery.
+        rows.sort_unstable_by_key(|row| (row.l1_batch_number, row.depth, row.id));
+
+        let mut jobs = Vec::with_capacity(rows.len());
+        for row in rows {
+            let depth = row.depth as u16;
+            let round = match depth {
+                0 => AggregationRound::LeafAggregation,
+                _ => AggregationRound::NodeAggregation,
+            };
+            let block_number = L1BatchNumber(row.l1_batch_number as u32);
+            let prover_job_ids = self
+                .prover_job_ids_for(block_number, row.circuit_id as u8, round, depth)
+                .await;
+            jobs.push(NodeAggregationJobMetadata {
+                id: row.id as u32,
+                block_number,
+                circuit_id: row.circuit_id as u8,
+                depth,
+                attempts: row.attempts as u32,
+                prover_job_ids_for_proofs: prover_job_ids,
+                fan_in: row.fan_in.map(|fan_in| fan_in as usize),
+            });
+        }
+        jobs
+    }
+
+    /// Marks the node aggregation job as failed. Unless the failure is `retryable`, the job is kept
+    /// failed for manual inspection rather than requeued by
+    /// [`Self::requeue_stuck_node_aggregations_jobs()`].
+    pub async fn mark_node_aggregation_job_failed(
+        &mut self,
+        error: &str,
+        id: u32,
+        retryable: bool,
+    ) {
         sqlx::query!(
             "
                 UPDATE node_aggregation_witness_jobs_fri
-                SET status ='failed', error= $1, updated_at = now()
+                SET status ='failed', error= $1, is_fatal_failure = NOT $3, updated_at = now()
                 WHERE id = $2
                ",
             error,
-            id as i64
+            id as i64,
+            retryable
         )
         .execute(self.storage.conn())
         .await
@@ -449,6 +707,23 @@ impl FriWitnessGeneratorDal<'_, '_> {
         .unwrap();
     }
 
+    /// Returns the node aggregation job to the queue without counting the attempt, e.g. if it was
+    /// prefetched by a witness generator that has stopped before processing it.
+    pub async fn release_node_aggregation_job(&mut self, id: u32) {
+        sqlx::query!(
+            "
+                UPDATE node_aggregation_witness_jobs_fri
+                SET status = 'queued', attempts = attempts - 1, updated_at = now(),
+                    processing_started_at = NULL
+                WHERE id = $1 AND status = 'in_progress'
+               ",
+            id as i64
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+    }
+
     pub async fn insert_node_aggregation_jobs(
         &mut self,
         block_number: L1BatchNumber,
@@ -456,17 +731,19 @@ impl FriWitnessGeneratorDal<'_, '_> {
         number_of_dependent_jobs: Option<i32>,
         depth: u16,
         aggregations_url: &str,
+        fan_in: Option<usize>,
     ) {
         sqlx::query!(
-                "INSERT INTO node_aggregation_witness_jobs_fri (l1_batch_number, circuit_id, depth, aggregations_url, number_of_dependent_jobs, status, created_at, updated_at)
-                    VALUES ($1, $2, $3, $4, $5, 'waiting_for_proofs', now(), now())
+                "INSERT INTO node_aggregation_witness_jobs_fri (l1_batch_number, circuit_id, depth, aggregations_url, number_of_dependent_jobs, fan_in, status, created_at, updated_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, 'waiting_for_proofs', now(), now())
                     ON CONFLICT(l1_batch_number, circuit_id, depth)
                     DO UPDATE SET updated_at=now()",
                 block_number.0 as i64,
                 circuit_id as i16,
                 depth as i32,
                 aggregations_url,
-                number_of_dependent_jobs
+                number_of_dependent_jobs,
+                fan_in.map(|fan_in| fan_in as i32)
             )
             .fetch_optional(self.storage.conn())
             .await
@@ -530,18 +807,22 @@ impl FriWitnessGeneratorDal<'_, '_> {
             .collect()
     }
 
+    /// Requeues leaf aggregation jobs that have failed with retryable errors or have been in progress
+    /// for longer than `processing_timeout`; fatal failures stay failed for manual inspection.
+    /// Jobs stuck in progress after `max_attempts` are marked as failed with an "exhausted attempts"
+    /// error instead, so that they aren't requeued forever.
     pub async fn requeue_stuck_leaf_aggregations_jobs(
         &mut self,
         processing_timeout: Duration,
         max_attempts: u32,
     ) -> Vec<StuckJobs> {
         let processing_timeout = pg_interval_from_duration(processing_timeout);
-        sqlx::query!(
+        let mut stuck_jobs: Vec<_> = sqlx::query!(
                 "
                 UPDATE leaf_aggregation_witness_jobs_fri
                 SET status = 'queued', attempts = attempts + 1, updated_at = now(), processing_started_at = now()
                 WHERE (status = 'in_progress' AND  processing_started_at <= now() - $1::interval AND attempts < $2)
-                OR (status = 'failed' AND attempts < $2)
+                OR (status = 'failed' AND NOT is_fatal_failure AND attempts < $2)
                 RETURNING id, status, attempts
                 ",
                 &processing_timeout,
@@ -552,21 +833,46 @@ impl FriWitnessGeneratorDal<'_, '_> {
             .unwrap()
             .into_iter()
             .map(|row| StuckJobs { id: row.id as u64, status: row.status, attempts: row.attempts as u64 })
-            .collect()
+            .collect();
+        let exhausted_jobs = sqlx::query!(
+            "
+            UPDATE leaf_aggregation_witness_jobs_fri
+            SET status = 'failed', error = 'exhausted attempts', updated_at = now()
+            WHERE status = 'in_progress' AND processing_started_at <= now() - $1::interval AND attempts >= $2
+            RETURNING id, status, attempts
+            ",
+            &processing_timeout,
+            max_attempts as i32,
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| StuckJobs {
+            id: row.id as u64,
+            status: row.status,
+            attempts: row.attempts as u64,
+        });
+        stuck_jobs.extend(exhausted_jobs);
+        stuck_jobs
     }
 
+    /// Requeues node aggregation jobs that have failed with retryable errors or have been in progress
+    /// for longer than `processing_timeout`; fatal failures stay failed for manual inspection.
+    /// Jobs stuck in progress after `max_attempts` are marked as failed with an "exhausted attempts"
+    /// error instead, so that they aren't requeued forever.
     pub async fn requeue_stuck_node_aggregations_jobs(
         &mut self,
         processing_timeout: Duration,
         max_attempts: u32,
     ) -> Vec<StuckJobs> {
         let processing_timeout = pg_interval_from_duration(processing_timeout);
-        sqlx::query!(
+        let mut stuck_jobs: Vec<_> = sqlx::query!(
                 "
                 UPDATE node_aggregation_witness_jobs_fri
                 SET status = 'queued', attempts = attempts + 1, updated_at = now(), processing_started_at = now()
                 WHERE (status = 'in_progress' AND  processing_started_at <= now() - $1::interval AND attempts < $2)
-                OR (status = 'failed' AND attempts < $2)
+                OR (status = 'failed' AND NOT is_fatal_failure AND attempts < $2)
                 RETURNING id, status, attempts
                 ",
                 &processing_timeout,
@@ -577,7 +883,28 @@ impl FriWitnessGeneratorDal<'_, '_> {
             .unwrap()
             .into_iter()
             .map(|row| StuckJobs { id: row.id as u64, status: row.status, attempts: row.attempts as u64 })
-            .collect()
+            .collect();
+        let exhausted_jobs = sqlx::query!(
+            "
+            UPDATE node_aggregation_witness_jobs_fri
+            SET status = 'failed', error = 'exhausted attempts', updated_at = now()
+            WHERE status = 'in_progress' AND processing_started_at <= now() - $1::interval AND attempts >= $2
+            RETURNING id, status, attempts
+            ",
+            &processing_timeout,
+            max_attempts as i32,
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| StuckJobs {
+            id: row.id as u64,
+            status: row.status,
+            attempts: row.attempts as u64,
+        });
+        stuck_jobs.extend(exhausted_jobs);
+        stuck_jobs
     }
 
     pub async fn mark_scheduler_jobs_as_queued(&mut self, l1_batch_number: i64) {
@@ -596,18 +923,22 @@ impl FriWitnessGeneratorDal<'_, '_> {
         .unwrap();
     }
 
+    /// Requeues scheduler jobs that have failed with retryable errors or have been in progress
+    /// for longer than `processing_timeout`; fatal failures stay failed for manual inspection.
+    /// Jobs stuck in progress after `max_attempts` are marked as failed with an "exhausted attempts"
+    /// error instead, so that they aren't requeued forever.
     pub async fn requeue_stuck_scheduler_jobs(
         &mut self,
         processing_timeout: Duration,
         max_attempts: u32,
     ) -> Vec<StuckJobs> {
         let processing_timeout = pg_interval_from_duration(processing_timeout);
-        sqlx::query!(
+        let mut stuck_jobs: Vec<_> = sqlx::query!(
                 "
                 UPDATE scheduler_witness_jobs_fri
                 SET status = 'queued', attempts = attempts + 1, updated_at = now(), processing_started_at = now()
-                WHERE (status = 'in_progress' AND  processing_started_at <= now() - $1::interval AND attempts < $2)
-                OR (status = 'failed' AND attempts < $2)
+                WHERE (status = 'in_progress' AND  updated_at <= now() - $1::interval AND attempts < $2)
+                OR (status = 'failed' AND NOT is_fatal_failure AND attempts < $2)
                 RETURNING l1_batch_number, status, attempts
                 ",
                 &processing_timeout,
@@ -618,66 +949,816 @@ impl FriWitnessGeneratorDal<'_, '_> {
             .unwrap()
             .into_iter()
             .map(|row| StuckJobs { id: row.l1_batch_number as u64, status: row.status, attempts: row.attempts as u64 })
-            .collect()
+            .collect();
+        let exhausted_jobs = sqlx::query!(
+            "
+            UPDATE scheduler_witness_jobs_fri
+            SET status = 'failed', error = 'exhausted attempts', updated_at = now()
+            WHERE status = 'in_progress' AND updated_at <= now() - $1::interval AND attempts >= $2
+            RETURNING l1_batch_number, status, attempts
+            ",
+            &processing_timeout,
+            max_attempts as i32,
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| StuckJobs {
+            id: row.l1_batch_number as u64,
+            status: row.status,
+            attempts: row.attempts as u64,
+        });
+        stuck_jobs.extend(exhausted_jobs);
+        stuck_jobs
     }
 
-    pub async fn get_next_scheduler_witness_job(&mut self) -> Option<L1BatchNumber> {
-        let result: Option<L1BatchNumber> = sqlx::query!(
+    /// Picks the next queued scheduler job and takes a new lease on it. Jobs for L1 batches whose
+    /// basic witness job is skipped (e.g., because the batch was reverted or pruned upstream) are
+    /// never picked. Jobs for L1 batches with a tracked final prover job that isn't successful
+    /// (e.g., because its proof failed verification) are not picked until the prover job succeeds.
+    pub async fn get_next_scheduler_witness_job(&mut self) -> Option<SchedulerJobLease> {
+        let lease_id = uuid::Uuid::new_v4().to_string();
+        let result: Option<SchedulerJobLease> = sqlx::query!(
             "
                 UPDATE scheduler_witness_jobs_fri
                 SET status = 'in_progress', attempts = attempts + 1,
-                    updated_at = now(), processing_started_at = now()
+                    updated_at = now(), processing_started_at = now(), lease_id = $1
                 WHERE l1_batch_number = (
                     SELECT l1_batch_number
                     FROM scheduler_witness_jobs_fri
                     WHERE status = 'queued'
-                    ORDER BY l1_batch_number ASC
+                        AND NOT EXISTS (
+                            SELECT 1 FROM witness_inputs_fri
+                            WHERE witness_inputs_fri.l1_batch_number = scheduler_witness_jobs_fri.l1_batch_number
+                                AND witness_inputs_fri.status = 'skipped'
+                        )
+                        AND NOT EXISTS (
+                            SELECT 1 FROM scheduler_dependency_tracker_fri
+                            JOIN prover_jobs_fri ON prover_jobs_fri.id IN (
+                                circuit_1_final_prover_job_id, circuit_2_final_prover_job_id, circuit_3_final_prover_job_id,
+                                circuit_4_final_prover_job_id, circuit_5_final_prover_job_id, circuit_6_final_prover_job_id,
+                                circuit_7_final_prover_job_id, circuit_8_final_prover_job_id, circuit_9_final_prover_job_id,
+                                circuit_10_final_prover_job_id, circuit_11_final_prover_job_id, circuit_12_final_prover_job_id,
+                                circuit_13_final_prover_job_id
+                            )
+                            WHERE scheduler_dependency_tracker_fri.l1_batch_number = scheduler_witness_jobs_fri.l1_batch_number
+                                AND prover_jobs_fri.status != 'successful'
+                        )
+                    ORDER BY priority DESC, l1_batch_number ASC
                     LIMIT 1
                     FOR UPDATE
                     SKIP LOCKED
                 )
+                AND status = 'queued'
                 RETURNING scheduler_witness_jobs_fri.*
                ",
+            &lease_id
         )
         .fetch_optional(self.storage.conn())
         .await
         .unwrap()
-        .map(|row| L1BatchNumber(row.l1_batch_number as u32));
+        .map(|row| SchedulerJobLease {
+            l1_batch_number: L1BatchNumber(row.l1_batch_number as u32),
+            lease_id,
+            attempts: row.attempts as u32,
+            protocol_version: row.protocol_version.map(|version| version as u16),
+        });
         result
     }
 
-    pub async fn mark_scheduler_job_as_successful(
+    /// Same as [`Self::get_next_scheduler_witness_job()`], but only claims jobs for L1 batches
+    /// in the specified range with the specified protocol versions or without a recorded version.
+    pub async fn get_next_scheduler_witness_job_for_protocol_versions(
+        &mut self,
+        protocol_versions: &[u16],
+        l1_batch_range: &RangeInclusive<L1BatchNumber>,
+    ) -> Option<SchedulerJobLease> {
+        let lease_id = uuid::Uuid::new_v4().to_string();
+        let protocol_versions: Vec<i32> = protocol_versions.iter().map(|&v| v.into()).collect();
+        let result: Option<SchedulerJobLease> = sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET status = 'in_progress', attempts = attempts + 1,
+                    updated_at = now(), processing_started_at = now(), lease_id = $1
+                WHERE l1_batch_number = (
+                    SELECT l1_batch_number
+                    FROM scheduler_witness_jobs_fri
+                    WHERE status = 'queued' AND l1_batch_number BETWEEN $3 AND $4
+                        AND (protocol_version IS NULL OR protocol_version = ANY($2))
+                        AND NOT EXISTS (
+                            SELECT 1 FROM witness_inputs_fri
+                            WHERE witness_inputs_fri.l1_batch_number = scheduler_witness_jobs_fri.l1_batch_number
+                                AND witness_inputs_fri.status = 'skipped'
+                        )
+                        AND NOT EXISTS (
+                            SELECT 1 FROM scheduler_dependency_tracker_fri
+                            JOIN prover_jobs_fri ON prover_jobs_fri.id IN (
+                                circuit_1_final_prover_job_id, circuit_2_final_prover_job_id, circuit_3_final_prover_job_id,
+                                circuit_4_final_prover_job_id, circuit_5_final_prover_job_id, circuit_6_final_prover_job_id,
+                                circuit_7_final_prover_job_id, circuit_8_final_prover_job_id, circuit_9_final_prover_job_id,
+                                circuit_10_final_prover_job_id, circuit_11_final_prover_job_id, circuit_12_final_prover_job_id,
+                                circuit_13_final_prover_job_id
+                            )
+                            WHERE scheduler_dependency_tracker_fri.l1_batch_number = scheduler_witness_jobs_fri.l1_batch_number
+                                AND prover_jobs_fri.status != 'successful'
+                        )
+                    ORDER BY priority DESC, l1_batch_number ASC
+                    LIMIT 1
+                    FOR UPDATE
+                    SKIP LOCKED
+                )
+                AND status = 'queued'
+                RETURNING scheduler_witness_jobs_fri.*
+               ",
+            &lease_id,
+            &protocol_versions,
+            l1_batch_range.start().0 as i64,
+            l1_batch_range.end().0 as i64
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()
+        .map(|row| SchedulerJobLease {
+            l1_batch_number: L1BatchNumber(row.l1_batch_number as u32),
+            lease_id,
+            attempts: row.attempts as u32,
+            protocol_version: row.protocol_version.map(|version| version as u16),
+        });
+        result
+    }
+
+    /// Same as [`Self::get_next_scheduler_witness_job_for_protocol_versions()`], but only claims
+    /// jobs for the specified L1 batches (still restricted to `l1_batch_range`).
+    pub async fn get_next_scheduler_witness_job_for_batches(
+        &mut self,
+        l1_batch_numbers: &[L1BatchNumber],
+        protocol_versions: &[u16],
+        l1_batch_range: &RangeInclusive<L1BatchNumber>,
+    ) -> Option<SchedulerJobLease> {
+        let lease_id = uuid::Uuid::new_v4().to_string();
+        let l1_batch_numbers: Vec<i64> = l1_batch_numbers.iter().map(|n| n.0 as i64).collect();
+        let protocol_versions: Vec<i32> = protocol_versions.iter().map(|&v| v.into()).collect();
+        let result: Option<SchedulerJobLease> = sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET status = 'in_progress', attempts = attempts + 1,
+                    updated_at = now(), processing_started_at = now(), lease_id = $1
+                WHERE l1_batch_number = (
+                    SELECT l1_batch_number
+                    FROM scheduler_witness_jobs_fri
+                    WHERE status = 'queued' AND l1_batch_number = ANY($2)
+                        AND l1_batch_number BETWEEN $4 AND $5
+                        AND (protocol_version IS NULL OR protocol_version = ANY($3))
+                        AND NOT EXISTS (
+                            SELECT 1 FROM witness_inputs_fri
+                            WHERE witness_inputs_fri.l1_batch_number = scheduler_witness_jobs_fri.l1_batch_number
+                                AND witness_inputs_fri.status = 'skipped'
+                        )
+                        AND NOT EXISTS (
+                            SELECT 1 FROM scheduler_dependency_tracker_fri
+                            JOIN prover_jobs_fri ON prover_jobs_fri.id IN (
+                                circuit_1_final_prover_job_id, circuit_2_final_prover_job_id, circuit_3_final_prover_job_id,
+                                circuit_4_final_prover_job_id, circuit_5_final_prover_job_id, circuit_6_final_prover_job_id,
+                                circuit_7_final_prover_job_id, circuit_8_final_prover_job_id, circuit_9_final_prover_job_id,
+                                circuit_10_final_prover_job_id, circuit_11_final_prover_job_id, circuit_12_final_prover_job_id,
+                                circuit_13_final_prover_job_id
+                            )
+                            WHERE scheduler_dependency_tracker_fri.l1_batch_number = scheduler_witness_jobs_fri.l1_batch_number
+                                AND prover_jobs_fri.status != 'successful'
+                        )
+                    ORDER BY priority DESC, l1_batch_number ASC
+                    LIMIT 1
+                    FOR UPDATE
+                    SKIP LOCKED
+                )
+                AND status = 'queued'
+                RETURNING scheduler_witness_jobs_fri.*
+               ",
+            &lease_id,
+            &l1_batch_numbers,
+            &protocol_versions,
+            l1_batch_range.start().0 as i64,
+            l1_batch_range.end().0 as i64
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()
+        .map(|row| SchedulerJobLease {
+            l1_batch_number: L1BatchNumber(row.l1_batch_number as u32),
+            lease_id,
+            attempts: row.attempts as u32,
+            protocol_version: row.protocol_version.map(|version| version as u16),
+        });
+        result
+    }
+
+    /// Returns `true` if the scheduler job for the L1 batch has a deadline, and it has passed.
+    pub async fn is_scheduler_job_past_deadline(&mut self, l1_batch_number: L1BatchNumber) -> bool {
+        sqlx::query!(
+            r#"
+                SELECT COALESCE(deadline_at < now(), FALSE) AS "is_past_deadline!"
+                FROM scheduler_witness_jobs_fri
+                WHERE l1_batch_number = $1
+               "#,
+            l1_batch_number.0 as i64
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()
+        .map_or(false, |row| row.is_past_deadline)
+    }
+
+    /// Returns L1 batches, scheduler jobs for which are not completed and have passed their deadline.
+    pub async fn get_scheduler_jobs_past_deadline(&mut self) -> Vec<L1BatchNumber> {
+        sqlx::query!(
+            "
+                SELECT l1_batch_number
+                FROM scheduler_witness_jobs_fri
+                WHERE deadline_at < now() AND status NOT IN ('successful', 'skipped')
+                ORDER BY l1_batch_number ASC
+               ",
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| L1BatchNumber(row.l1_batch_number as u32))
+        .collect()
+    }
+
+    /// Moves queued scheduler jobs that have passed their deadline to the `requires_manual_intervention`
+    /// status, so that they are no longer picked up. Returns the L1 batches of the updated jobs.
+    pub async fn mark_queued_scheduler_jobs_past_deadline(&mut self) -> Vec<L1BatchNumber> {
+        let mut l1_batch_numbers: Vec<_> = sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET status = 'requires_manual_intervention', updated_at = now()
+                WHERE status = 'queued' AND deadline_at < now()
+                RETURNING l1_batch_number
+               ",
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| L1BatchNumber(row.l1_batch_number as u32))
+        .collect();
+        l1_batch_numbers.sort_unstable();
+        l1_batch_numbers
+    }
+
+    /// Returns the first queued scheduler job for an L1 batch in the specified range without
+    /// locking it. Only jobs for the specified protocol versions and jobs without a recorded version
+    /// are returned. As with [`Self::get_next_scheduler_witness_job()`], jobs for skipped L1 batches are ignored.
+    pub async fn peek_next_scheduler_witness_job(
+        &mut self,
+        l1_batch_range: &RangeInclusive<L1BatchNumber>,
+        protocol_versions: &[u16],
+    ) -> Option<(L1BatchNumber, Option<u16>)> {
+        let protocol_versions: Vec<i32> = protocol_versions.iter().map(|&v| v.into()).collect();
+        sqlx::query!(
+            "
+                SELECT l1_batch_number, protocol_version FROM scheduler_witness_jobs_fri
+                WHERE status = 'queued' AND l1_batch_number BETWEEN $1 AND $3
+                    AND (protocol_version IS NULL OR protocol_version = ANY($2))
+                    AND NOT EXISTS (
+                        SELECT 1 FROM witness_inputs_fri
+                        WHERE witness_inputs_fri.l1_batch_number = scheduler_witness_jobs_fri.l1_batch_number
+                            AND witness_inputs_fri.status = 'skipped'
+                    )
+                    AND NOT EXISTS (
+                        SELECT 1 FROM scheduler_dependency_tracker_fri
+                        JOIN prover_jobs_fri ON prover_jobs_fri.id IN (
+                            circuit_1_final_prover_job_id, circuit_2_final_prover_job_id, circuit_3_final_prover_job_id,
+                            circuit_4_final_prover_job_id, circuit_5_final_prover_job_id, circuit_6_final_prover_job_id,
+                            circuit_7_final_prover_job_id, circuit_8_final_prover_job_id, circuit_9_final_prover_job_id,
+                            circuit_10_final_prover_job_id, circuit_11_final_prover_job_id, circuit_12_final_prover_job_id,
+                            circuit_13_final_prover_job_id
+                        )
+                        WHERE scheduler_dependency_tracker_fri.l1_batch_number = scheduler_witness_jobs_fri.l1_batch_number
+                            AND prover_jobs_fri.status != 'successful'
+                    )
+                ORDER BY l1_batch_number ASC
+                LIMIT 1
+               ",
+            l1_batch_range.start().0 as i64,
+            &protocol_versions,
+            l1_batch_range.end().0 as i64
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()
+        .map(|row| {
+            let protocol_version = row.protocol_version.map(|version| version as u16);
+            (L1BatchNumber(row.l1_batch_number as u32), protocol_version)
+        })
+    }
+
+    /// Counts queued scheduler jobs that are not picked because of tracked final prover jobs that
+    /// aren't successful, grouped by the status of these prover jobs. A job blocked by prover jobs
+    /// with different statuses is counted for each status.
+    pub async fn count_blocked_scheduler_jobs(&mut self) -> HashMap<String, usize> {
+        sqlx::query!(
+            r#"
+                SELECT prover_jobs_fri.status,
+                    COUNT(DISTINCT scheduler_witness_jobs_fri.l1_batch_number) AS "count!"
+                FROM scheduler_witness_jobs_fri
+                JOIN scheduler_dependency_tracker_fri
+                    ON scheduler_dependency_tracker_fri.l1_batch_number = scheduler_witness_jobs_fri.l1_batch_number
+                JOIN prover_jobs_fri ON prover_jobs_fri.id IN (
+                    circuit_1_final_prover_job_id, circuit_2_final_prover_job_id, circuit_3_final_prover_job_id,
+                    circuit_4_final_prover_job_id, circuit_5_final_prover_job_id, circuit_6_final_prover_job_id,
+                    circuit_7_final_prover_job_id, circuit_8_final_prover_job_id, circuit_9_final_prover_job_id,
+                    circuit_10_final_prover_job_id, circuit_11_final_prover_job_id, circuit_12_final_prover_job_id,
+                    circuit_13_final_prover_job_id
+                )
+                WHERE scheduler_witness_jobs_fri.status = 'queued' AND prover_jobs_fri.status != 'successful'
+                GROUP BY prover_jobs_fri.status
+               "#,
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.status, row.count as usize))
+        .collect()
+    }
+
+    /// Records the protocol version of the L1 batch for its scheduler job.
+    pub async fn set_scheduler_job_protocol_version(
         &mut self,
         block_number: L1BatchNumber,
-        time_taken: Duration,
+        protocol_version: u16,
     ) {
         sqlx::query!(
             "
                 UPDATE scheduler_witness_jobs_fri
-                SET status = 'successful', updated_at = now(), time_taken = $1
-                WHERE l1_batch_number = $2
+                SET protocol_version = $2, updated_at = now()
+                WHERE l1_batch_number = $1
                ",
-            duration_to_naive_time(time_taken),
-            block_number.0 as i64
+            block_number.0 as i64,
+            i32::from(protocol_version)
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+    }
+
+    /// Records the fan-in of node aggregation circuits for the L1 batch. The fan-in is only
+    /// recorded once, so that node aggregation jobs already created for the batch are not
+    /// affected if the L1 batch is processed again with a different fan-in.
+    pub async fn set_node_aggregation_fan_in(
+        &mut self,
+        block_number: L1BatchNumber,
+        fan_in: usize,
+    ) {
+        sqlx::query!(
+            "
+                UPDATE node_aggregation_witness_jobs_fri
+                SET fan_in = $2, updated_at = now()
+                WHERE l1_batch_number = $1 AND fan_in IS NULL
+               ",
+            block_number.0 as i64,
+            fan_in as i32
         )
         .execute(self.storage.conn())
         .await
         .unwrap();
     }
 
-    pub async fn mark_scheduler_job_failed(&mut self, error: &str, block_number: L1BatchNumber) {
+    /// Returns `true` if all node aggregation jobs for the L1 batch and circuit are successful,
+    /// i.e., node aggregation for the circuit is complete. Returns `false` if there are no jobs.
+    pub async fn are_node_aggregation_jobs_successful(
+        &mut self,
+        block_number: L1BatchNumber,
+        circuit_id: u8,
+    ) -> bool {
+        sqlx::query!(
+            "
+                SELECT bool_and(status = 'successful') AS successful
+                FROM node_aggregation_witness_jobs_fri
+                WHERE l1_batch_number = $1 AND circuit_id = $2
+               ",
+            block_number.0 as i64,
+            circuit_id as i16
+        )
+        .fetch_one(self.storage.conn())
+        .await
+        .unwrap()
+        .successful
+        .unwrap_or(false)
+    }
+
+    /// Returns the number of queued scheduler jobs for each protocol version, including jobs
+    /// without a recorded version (keyed by `None`).
+    pub async fn count_queued_scheduler_jobs_by_protocol_version(
+        &mut self,
+    ) -> HashMap<Option<u16>, usize> {
+        sqlx::query!(
+            r#"
+                SELECT protocol_version, COUNT(*) AS "count!"
+                FROM scheduler_witness_jobs_fri
+                WHERE status = 'queued'
+                GROUP BY protocol_version
+               "#,
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            let protocol_version = row.protocol_version.map(|version| version as u16);
+            (protocol_version, row.count as usize)
+        })
+        .collect()
+    }
+
+    /// Marks the scheduler job as successful if the lease is still held.
+    /// Returns `false` if the lease was lost, in which case the job is not updated.
+    /// Marking an already successful job again keeps its original processing time, so that
+    /// saving the job result can be retried.
+    pub async fn mark_scheduler_job_as_successful(
+        &mut self,
+        lease: &SchedulerJobLease,
+        time_taken: Duration,
+    ) -> bool {
+        self.try_mark_scheduler_job_as_successful(lease, time_taken)
+            .await
+            .unwrap()
+    }
+
+    /// Same as [`Self::mark_scheduler_job_as_successful()`], but returns an error instead of panicking.
+    pub async fn try_mark_scheduler_job_as_successful(
+        &mut self,
+        lease: &SchedulerJobLease,
+        time_taken: Duration,
+    ) -> Result<bool, SqlxError> {
+        let result = sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET status = 'successful', updated_at = now(),
+                    time_taken = CASE WHEN status = 'successful' THEN time_taken ELSE $1 END
+                WHERE l1_batch_number = $2 AND lease_id = $3
+               ",
+            duration_to_naive_time(time_taken),
+            lease.l1_batch_number.0 as i64,
+            &lease.lease_id
+        )
+        .execute(self.storage.conn())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Records sources of the node proofs embedded into the scheduler circuit for the L1 batch.
+    pub async fn try_set_scheduler_proof_witness_sources(
+        &mut self,
+        block_number: L1BatchNumber,
+        sources: &[SchedulerProofWitnessSource],
+    ) -> Result<(), SqlxError> {
+        let sources =
+            serde_json::to_value(sources).expect("failed serializing proof witness sources");
         sqlx::query!(
             "
                 UPDATE scheduler_witness_jobs_fri
-                SET status ='failed', error= $1, updated_at = now()
+                SET proof_witness_sources = $1
                 WHERE l1_batch_number = $2
                ",
-            error,
+            sources,
+            block_number.0 as i64
+        )
+        .execute(self.storage.conn())
+        .await?;
+        Ok(())
+    }
+
+    /// Returns sources of the node proofs embedded into the scheduler circuit for the L1 batch,
+    /// or `None` if they are not recorded.
+    pub async fn get_scheduler_proof_witness_sources(
+        &mut self,
+        block_number: L1BatchNumber,
+    ) -> Option<Vec<SchedulerProofWitnessSource>> {
+        let sources = sqlx::query!(
+            "
+                SELECT proof_witness_sources FROM scheduler_witness_jobs_fri
+                WHERE l1_batch_number = $1
+               ",
+            block_number.0 as i64
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()?
+        .proof_witness_sources?;
+        Some(serde_json::from_value(sources).expect("invalid proof witness sources"))
+    }
+
+    /// Records commitments exposed by the scheduler circuit for the L1 batch.
+    pub async fn try_save_scheduler_commitments(
+        &mut self,
+        block_number: L1BatchNumber,
+        commitments: &SchedulerCommitments,
+    ) -> Result<(), SqlxError> {
+        sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET meta_parameters_hash = $1, pass_through_data_hash = $2
+                WHERE l1_batch_number = $3
+               ",
+            commitments.meta_parameters_hash.as_bytes(),
+            commitments.pass_through_data_hash.as_bytes(),
+            block_number.0 as i64
+        )
+        .execute(self.storage.conn())
+        .await?;
+        Ok(())
+    }
+
+    /// Returns commitments exposed by scheduler circuits for up to `limit` L1 batches starting
+    /// from `first_block_number`, ordered by the batch number. Batches without recorded
+    /// commitments are skipped.
+    pub async fn get_scheduler_commitments(
+        &mut self,
+        first_block_number: L1BatchNumber,
+        limit: usize,
+    ) -> Vec<(L1BatchNumber, SchedulerCommitments)> {
+        sqlx::query!(
+            "
+                SELECT l1_batch_number, meta_parameters_hash, pass_through_data_hash
+                FROM scheduler_witness_jobs_fri
+                WHERE l1_batch_number >= $1
+                    AND meta_parameters_hash IS NOT NULL AND pass_through_data_hash IS NOT NULL
+                ORDER BY l1_batch_number
+                LIMIT $2
+               ",
+            first_block_number.0 as i64,
+            limit as i64
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|row| {
+            let commitments = SchedulerCommitments {
+                meta_parameters_hash: H256::from_slice(&row.meta_parameters_hash?),
+                pass_through_data_hash: H256::from_slice(&row.pass_through_data_hash?),
+            };
+            Some((L1BatchNumber(row.l1_batch_number as u32), commitments))
+        })
+        .collect()
+    }
+
+    /// Returns the scheduler job to the queue without counting the attempt, e.g. if the witness
+    /// generator that has claimed it lacks resources to process it. Returns `false` if the lease
+    /// was lost, in which case the job is not updated.
+    pub async fn release_scheduler_job(&mut self, lease: &SchedulerJobLease) -> bool {
+        sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET status = 'queued', attempts = attempts - 1, updated_at = now(),
+                    processing_started_at = NULL, lease_id = NULL
+                WHERE l1_batch_number = $1 AND lease_id = $2 AND status = 'in_progress'
+               ",
+            lease.l1_batch_number.0 as i64,
+            &lease.lease_id
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap()
+        .rows_affected()
+            > 0
+    }
+
+    /// Bumps `updated_at` of the scheduler job being processed, so that it's not considered stuck.
+    /// Returns `false` if the lease was lost, in which case the job is not updated.
+    pub async fn touch_scheduler_job(&mut self, lease: &SchedulerJobLease) -> bool {
+        sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET updated_at = now()
+                WHERE l1_batch_number = $1 AND lease_id = $2 AND status = 'in_progress'
+               ",
+            lease.l1_batch_number.0 as i64,
+            &lease.lease_id
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap()
+        .rows_affected()
+            > 0
+    }
+
+    /// Marks the scheduler job as skipped, so that it's not picked up again. If the job is being
+    /// processed, its artifacts are discarded once the processing is finished.
+    pub async fn mark_scheduler_job_as_skipped(&mut self, block_number: L1BatchNumber) {
+        sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET status = 'skipped', updated_at = now()
+                WHERE l1_batch_number = $1 AND status != 'successful'
+               ",
+            block_number.0 as i64
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+    }
+
+    /// Marks witness jobs of all aggregation rounds for the L1 batch as skipped, e.g. if the batch
+    /// was reverted or pruned upstream, so that they're not picked up again. Successful jobs are
+    /// left intact. Returns the rounds for which jobs were skipped.
+    pub async fn mark_l1_batch_witness_jobs_as_skipped(
+        &mut self,
+        block_number: L1BatchNumber,
+    ) -> Vec<AggregationRound> {
+        let mut skipped_rounds = vec![];
+        let result = sqlx::query!(
+            "
+                UPDATE witness_inputs_fri
+                SET status = 'skipped', updated_at = now()
+                WHERE l1_batch_number = $1 AND status NOT IN ('successful', 'skipped')
+               ",
+            block_number.0 as i64
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+        if result.rows_affected() > 0 {
+            skipped_rounds.push(AggregationRound::BasicCircuits);
+        }
+        let result = sqlx::query!(
+            "
+                UPDATE leaf_aggregation_witness_jobs_fri
+                SET status = 'skipped', updated_at = now()
+                WHERE l1_batch_number = $1 AND status NOT IN ('successful', 'skipped')
+               ",
+            block_number.0 as i64
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+        if result.rows_affected() > 0 {
+            skipped_rounds.push(AggregationRound::LeafAggregation);
+        }
+        let result = sqlx::query!(
+            "
+                UPDATE node_aggregation_witness_jobs_fri
+                SET status = 'skipped', updated_at = now()
+                WHERE l1_batch_number = $1 AND status NOT IN ('successful', 'skipped')
+               ",
+            block_number.0 as i64
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+        if result.rows_affected() > 0 {
+            skipped_rounds.push(AggregationRound::NodeAggregation);
+        }
+        let result = sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET status = 'skipped', updated_at = now()
+                WHERE l1_batch_number = $1 AND status NOT IN ('successful', 'skipped')
+               ",
             block_number.0 as i64
         )
         .execute(self.storage.conn())
         .await
         .unwrap();
+        if result.rows_affected() > 0 {
+            skipped_rounds.push(AggregationRound::Scheduler);
+        }
+        skipped_rounds
+    }
+
+    pub async fn is_scheduler_job_cancelled(&mut self, block_number: L1BatchNumber) -> bool {
+        matches!(
+            self.get_scheduler_job_status(block_number).await,
+            Some(FriWitnessJobStatus::Skipped)
+        )
+    }
+
+    /// Returns the status of the scheduler job for the L1 batch, or `None` if there's no such job.
+    pub async fn get_scheduler_job_status(
+        &mut self,
+        block_number: L1BatchNumber,
+    ) -> Option<FriWitnessJobStatus> {
+        sqlx::query!(
+            "
+                SELECT status FROM scheduler_witness_jobs_fri
+                WHERE l1_batch_number = $1
+               ",
+            block_number.0 as i64
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()
+        .map(|row| row.status.parse().expect("invalid scheduler job status"))
+    }
+
+    /// Returns the scheduler job to the queue regardless of its status and resets its attempts
+    /// and error, so that the scheduler witness is regenerated. The lease is cleared, so that
+    /// the outcome of the job being processed, if any, is discarded. Returns `false` if there is
+    /// no scheduler job for the L1 batch.
+    pub async fn requeue_scheduler_job(&mut self, block_number: L1BatchNumber) -> bool {
+        sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET status = 'queued', attempts = 0, error = NULL, error_kind = NULL,
+                    is_fatal_failure = FALSE, updated_at = now(), processing_started_at = NULL, time_taken = NULL,
+                    lease_id = NULL
+                WHERE l1_batch_number = $1
+               ",
+            block_number.0 as i64
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap()
+        .rows_affected()
+            > 0
+    }
+
+    /// Sets the priority of the scheduler job; jobs with higher priority are picked up first.
+    /// Returns `false` if there is no unfinished scheduler job for the L1 batch.
+    pub async fn set_scheduler_job_priority(
+        &mut self,
+        block_number: L1BatchNumber,
+        priority: i32,
+    ) -> bool {
+        sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET priority = $1
+                WHERE l1_batch_number = $2 AND status != 'successful'
+               ",
+            priority,
+            block_number.0 as i64
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap()
+        .rows_affected()
+            > 0
+    }
+
+    /// Marks the scheduler job as failed if the lease is still held. The failure is appended
+    /// to the error history of the job, and its error and kind are stored as the latest error;
+    /// the kind is stored separately from the error so that failed jobs can be aggregated by it.
+    /// If the failure is `retryable` and the job has made less than `max_attempts` attempts,
+    /// the job is returned to the queue instead; the failed attempt stays counted.
+    /// Returns the new job status, or `None` if the lease was lost, in which case the job is not updated.
+    pub async fn mark_scheduler_job_failed(
+        &mut self,
+        failure: &JobErrorHistoryEntry,
+        retryable: bool,
+        max_attempts: u32,
+        lease: &SchedulerJobLease,
+    ) -> Option<FriWitnessJobStatus> {
+        let history_entry =
+            serde_json::to_value(failure).expect("failed serializing job error history entry");
+        sqlx::query!(
+            "
+                UPDATE scheduler_witness_jobs_fri
+                SET status = CASE WHEN $3 AND attempts < $4 THEN 'queued' ELSE 'failed' END,
+                    error = $1, error_kind = $2, is_fatal_failure = NOT $3, updated_at = now(),
+                    error_history = error_history || jsonb_build_array($7::jsonb),
+                    processing_started_at = CASE WHEN $3 AND attempts < $4 THEN NULL ELSE processing_started_at END,
+                    lease_id = CASE WHEN $3 AND attempts < $4 THEN NULL ELSE lease_id END
+                WHERE l1_batch_number = $5 AND lease_id = $6
+                RETURNING status
+               ",
+            &failure.error,
+            &failure.error_kind,
+            retryable,
+            max_attempts as i16,
+            lease.l1_batch_number.0 as i64,
+            &lease.lease_id,
+            history_entry
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()
+        .map(|row| row.status.parse().expect("invalid scheduler job status"))
+    }
+
+    /// Returns failed attempts of the scheduler job for the L1 batch, oldest first, or `None`
+    /// if there is no scheduler job for the batch. The history is retained when the job
+    /// is requeued.
+    pub async fn get_scheduler_job_error_history(
+        &mut self,
+        block_number: L1BatchNumber,
+    ) -> Option<Vec<JobErrorHistoryEntry>> {
+        let history = sqlx::query!(
+            "
+                SELECT error_history FROM scheduler_witness_jobs_fri
+                WHERE l1_batch_number = $1
+               ",
+            block_number.0 as i64
+        )
+        .fetch_optional(self.storage.conn())
+        .await
+        .unwrap()?
+        .error_history;
+        Some(serde_json::from_value(history).expect("invalid job error history"))
     }
 
     pub async fn get_witness_jobs_stats(
@@ -709,6 +1790,130 @@ impl FriWitnessGeneratorDal<'_, '_> {
         }
     }
 
+    /// Returns statistics of node aggregation jobs for each depth of the aggregation tree.
+    pub async fn get_node_aggregation_jobs_stats_by_depth(
+        &mut self,
+    ) -> BTreeMap<u16, JobCountStatistics> {
+        let rows = sqlx::query!(
+            r#"
+                SELECT depth, status, COUNT(*) AS "count!"
+                FROM node_aggregation_witness_jobs_fri
+                GROUP BY depth, status
+            "#
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap();
+
+        let mut stats_by_depth = BTreeMap::<_, JobCountStatistics>::new();
+        for row in rows {
+            let stats = stats_by_depth.entry(row.depth as u16).or_default();
+            let count = row.count as usize;
+            match row.status.as_str() {
+                "queued" => stats.queued = count,
+                "in_progress" => stats.in_progress = count,
+                "failed" => stats.failed = count,
+                "successful" => stats.successful = count,
+                _ => {}
+            }
+        }
+        stats_by_depth
+    }
+
+    /// Records the latency between sealing the L1 batch and saving the result of the witness job
+    /// in the specified round. `job_id` is the L1 batch number for basic circuits and scheduler jobs.
+    pub async fn set_sealed_to_saved_latency(
+        &mut self,
+        aggregation_round: AggregationRound,
+        job_id: u32,
+        latency: Duration,
+    ) {
+        let table_name = Self::input_table_name_for(aggregation_round);
+        let id_column = match aggregation_round {
+            AggregationRound::BasicCircuits | AggregationRound::Scheduler => "l1_batch_number",
+            AggregationRound::LeafAggregation | AggregationRound::NodeAggregation => "id",
+        };
+        let sql = format!(
+            "
+                UPDATE {}
+                SET sealed_to_saved_latency = $1
+                WHERE {} = $2
+                ",
+            table_name, id_column
+        );
+        sqlx::query(&sql)
+            .bind(pg_interval_from_duration(latency))
+            .bind(job_id as i64)
+            .execute(self.storage.conn())
+            .await
+            .unwrap();
+    }
+
+    /// Counts witness jobs with the specified status. Unlike [`Self::get_witness_jobs_stats()`],
+    /// it's cheap enough to be called on every poll for `queued` and `in_progress` statuses,
+    /// since jobs with these statuses are covered by partial indices.
+    pub async fn count_witness_jobs_with_status(
+        &mut self,
+        aggregation_round: AggregationRound,
+        status: FriWitnessJobStatus,
+    ) -> u64 {
+        let table_name = Self::input_table_name_for(aggregation_round);
+        let sql = format!(
+            r#"
+                SELECT COUNT(*) as "count"
+                FROM {}
+                WHERE status = $1
+                "#,
+            table_name
+        );
+        let count: i64 = sqlx::query(&sql)
+            .bind(status.as_ref())
+            .fetch_one(self.storage.conn())
+            .await
+            .unwrap()
+            .get("count");
+        count as u64
+    }
+
+    /// Returns URLs of the input blobs for witness jobs of the specified L1 batch
+    /// for all aggregation rounds.
+    pub async fn get_input_blob_urls_for_l1_batch(
+        &mut self,
+        l1_batch_number: L1BatchNumber,
+    ) -> Vec<(AggregationRound, String)> {
+        sqlx::query!(
+            r#"
+                SELECT 0 AS "aggregation_round!", merkle_tree_paths_blob_url AS "blob_url!"
+                FROM witness_inputs_fri
+                WHERE l1_batch_number = $1 AND merkle_tree_paths_blob_url IS NOT NULL
+                UNION ALL
+                SELECT 1, closed_form_inputs_blob_url
+                FROM leaf_aggregation_witness_jobs_fri
+                WHERE l1_batch_number = $1 AND closed_form_inputs_blob_url IS NOT NULL
+                UNION ALL
+                SELECT 2, aggregations_url
+                FROM node_aggregation_witness_jobs_fri
+                WHERE l1_batch_number = $1 AND aggregations_url IS NOT NULL
+                UNION ALL
+                SELECT 3, scheduler_partial_input_blob_url
+                FROM scheduler_witness_jobs_fri
+                WHERE l1_batch_number = $1
+            "#,
+            l1_batch_number.0 as i64
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            (
+                AggregationRound::from(row.aggregation_round as u8),
+                row.blob_url,
+            )
+        })
+        .collect()
+    }
+
     fn input_table_name_for(aggregation_round: AggregationRound) -> &'static str {
         match aggregation_round {
             AggregationRound::BasicCircuits => "witness_inputs_fri",
@@ -718,3 +1923,8 @@ impl FriWitnessGeneratorDal<'_, '_> {
         }
     }
 }
+
+/// Converts circuit IDs to the `smallint[]` parameter of pickup queries; `None` matches all circuits.
+fn circuit_ids_param(circuit_ids: Option<&[u8]>) -> Option<Vec<i16>> {
+    circuit_ids.map(|ids| ids.iter().map(|&id| id as i16).collect())
+}
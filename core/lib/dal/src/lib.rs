@@ -23,6 +23,7 @@ use crate::fri_scheduler_dependency_tracker_dal::FriSchedulerDependencyTrackerDa
 use crate::fri_witness_generator_dal::FriWitnessGeneratorDal;
 use crate::gpu_prover_queue_dal::GpuProverQueueDal;
 use crate::prover_dal::ProverDal;
+use crate::pruned_blobs_log_dal::PrunedBlobsLogDal;
 use crate::storage_dal::StorageDal;
 use crate::storage_logs_dal::StorageLogsDal;
 use crate::storage_logs_dedup_dal::StorageLogsDedupDal;
@@ -44,12 +45,14 @@ pub mod events_dal;
 pub mod events_web3_dal;
 pub mod explorer;
 pub mod fri_prover_dal;
+pub mod fri_prover_job_notifications;
 pub mod fri_scheduler_dependency_tracker_dal;
 pub mod fri_witness_generator_dal;
 pub mod gpu_prover_queue_dal;
 pub mod healthcheck;
 mod models;
 pub mod prover_dal;
+pub mod pruned_blobs_log_dal;
 pub mod storage_dal;
 pub mod storage_logs_dal;
 pub mod storage_logs_dedup_dal;
@@ -85,6 +88,26 @@ pub fn get_test_database_url() -> String {
     env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set")
 }
 
+/// Returns `true` if the error is likely to go away if the operation is retried, e.g. if the connection
+/// to the database was lost or the transaction was aborted because of a serialization failure.
+pub fn is_transient_error(err: &SqlxError) -> bool {
+    match err {
+        SqlxError::Io(_)
+        | SqlxError::Protocol(_)
+        | SqlxError::PoolTimedOut
+        | SqlxError::WorkerCrashed => true,
+        SqlxError::Database(err) => err.code().map_or(false, |code| {
+            // Connection exceptions, serialization failures, deadlocks and server shutdowns.
+            code.starts_with("08")
+                || matches!(
+                    code.as_ref(),
+                    "40001" | "40P01" | "57P01" | "57P02" | "57P03"
+                )
+        }),
+        _ => false,
+    }
+}
+
 /// Storage processor is the main storage interaction point.
 /// It holds down the connection (either direct or pooled) to the database
 /// and provide methods to obtain different storage schemas.
@@ -109,12 +132,19 @@ impl<'a> StorageProcessor<'a> {
     }
 
     pub async fn start_transaction<'c: 'b, 'b>(&'c mut self) -> StorageProcessor<'b> {
-        let transaction = self.conn().begin().await.unwrap();
+        self.try_start_transaction().await.unwrap()
+    }
+
+    /// Same as [`Self::start_transaction()`], but returns an error instead of panicking.
+    pub async fn try_start_transaction<'c: 'b, 'b>(
+        &'c mut self,
+    ) -> Result<StorageProcessor<'b>, SqlxError> {
+        let transaction = self.conn().begin().await?;
 
         let mut processor = StorageProcessor::from_transaction(transaction);
         processor.in_transaction = true;
 
-        processor
+        Ok(processor)
     }
 
     /// Checks if the `StorageProcessor` is currently within database transaction.
@@ -137,8 +167,13 @@ impl<'a> StorageProcessor<'a> {
     }
 
     pub async fn commit(self) {
+        self.try_commit().await.unwrap();
+    }
+
+    /// Same as [`Self::commit()`], but returns an error if the transaction cannot be committed.
+    pub async fn try_commit(self) -> Result<(), SqlxError> {
         if let ConnectionHolder::Transaction(transaction) = self.conn {
-            transaction.commit().await.unwrap();
+            transaction.commit().await
         } else {
             panic!("StorageProcessor::commit can only be invoked after calling StorageProcessor::begin_transaction");
         }
@@ -248,4 +283,8 @@ impl<'a> StorageProcessor<'a> {
     ) -> FriSchedulerDependencyTrackerDal<'_, 'a> {
         FriSchedulerDependencyTrackerDal { storage: self }
     }
+
+    pub fn pruned_blobs_log_dal(&mut self) -> PrunedBlobsLogDal<'_, 'a> {
+        PrunedBlobsLogDal { storage: self }
+    }
 }
@@ -0,0 +1,89 @@
+use crate::StorageProcessor;
+use zksync_types::L1BatchNumber;
+
+/// Blob removed from the object store by the blob pruner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrunedBlob {
+    pub bucket: String,
+    pub key: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug)]
+pub struct PrunedBlobsLogDal<'a, 'c> {
+    pub storage: &'a mut StorageProcessor<'c>,
+}
+
+impl PrunedBlobsLogDal<'_, '_> {
+    /// Records blobs removed from the object store for the specified L1 batch.
+    pub async fn insert_pruned_blobs(
+        &mut self,
+        l1_batch_number: L1BatchNumber,
+        blobs: &[PrunedBlob],
+    ) {
+        let buckets: Vec<_> = blobs.iter().map(|blob| blob.bucket.clone()).collect();
+        let keys: Vec<_> = blobs.iter().map(|blob| blob.key.clone()).collect();
+        let sizes: Vec<_> = blobs.iter().map(|blob| blob.size_bytes as i64).collect();
+        sqlx::query!(
+            r#"
+                INSERT INTO pruned_blobs_log (l1_batch_number, bucket, blob_key, size_bytes, pruned_at)
+                SELECT $1, u.bucket, u.blob_key, u.size_bytes, now()
+                FROM UNNEST($2::text[], $3::text[], $4::bigint[]) AS u(bucket, blob_key, size_bytes)
+            "#,
+            l1_batch_number.0 as i64,
+            &buckets,
+            &keys,
+            &sizes
+        )
+        .execute(self.storage.conn())
+        .await
+        .unwrap();
+    }
+
+    /// Returns `true` if the blob with the specified key was recorded as removed from the bucket.
+    pub async fn is_blob_pruned(
+        &mut self,
+        l1_batch_number: L1BatchNumber,
+        bucket: &str,
+        key: &str,
+    ) -> bool {
+        sqlx::query!(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM pruned_blobs_log
+                    WHERE l1_batch_number = $1 AND bucket = $2 AND blob_key = $3
+                ) AS "is_pruned!"
+            "#,
+            l1_batch_number.0 as i64,
+            bucket,
+            key
+        )
+        .fetch_one(self.storage.conn())
+        .await
+        .unwrap()
+        .is_pruned
+    }
+
+    /// Returns blobs recorded as removed for the specified L1 batch.
+    pub async fn get_pruned_blobs(&mut self, l1_batch_number: L1BatchNumber) -> Vec<PrunedBlob> {
+        sqlx::query!(
+            r#"
+                SELECT bucket, blob_key, size_bytes
+                FROM pruned_blobs_log
+                WHERE l1_batch_number = $1
+                ORDER BY id
+            "#,
+            l1_batch_number.0 as i64
+        )
+        .fetch_all(self.storage.conn())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| PrunedBlob {
+            bucket: row.bucket,
+            key: row.blob_key,
+            size_bytes: row.size_bytes as u64,
+        })
+        .collect()
+    }
+}
@@ -1,7 +1,10 @@
+use std::collections::HashMap;
 use std::fs;
+use std::ops::RangeInclusive;
 use std::time::Duration;
 
 use db_test_macro::db_test;
+use sqlx::types::chrono::Utc;
 use zksync_contracts::BaseSystemContractsHashes;
 use zksync_types::{
     block::{L1BatchHeader, MiniblockHeader},
@@ -9,7 +12,10 @@ use zksync_types::{
     helpers::unix_timestamp_ms,
     l1::{L1Tx, OpProcessingType, PriorityQueueType},
     l2::L2Tx,
-    proofs::AggregationRound,
+    proofs::{
+        AggregationRound, JobErrorHistoryEntry, SchedulerCommitments, SchedulerJobLease,
+        SchedulerProofWitnessSource,
+    },
     tx::{tx_execution_info::TxExecutionStatus, ExecutionMetrics, TransactionExecutionResult},
     Address, Execute, L1BatchNumber, L1BlockNumber, L1TxCommonData, L2ChainId, MiniblockNumber,
     PriorityOpId, H160, H256, MAX_GAS_PER_PUBDATA_BYTE, U256,
@@ -18,7 +24,10 @@ use zksync_utils::miniblock_hash;
 
 use crate::blocks_dal::BlocksDal;
 use crate::connection::ConnectionPool;
+use crate::fri_prover_job_notifications::{NewProverJobNotification, NewProverJobsListener};
+use crate::fri_witness_generator_dal::{FriWitnessGeneratorDal, FriWitnessJobStatus};
 use crate::prover_dal::{GetProverJobsParams, ProverDal};
+use crate::pruned_blobs_log_dal::PrunedBlob;
 use crate::transactions_dal::L2TxSubmissionResult;
 use crate::transactions_dal::TransactionsDal;
 use crate::transactions_web3_dal::TransactionsWeb3Dal;
@@ -26,6 +35,11 @@ use crate::witness_generator_dal::WitnessGeneratorDal;
 
 const DEFAULT_GAS_PER_PUBDATA: u32 = 100;
 
+/// Range of L1 batches not restricting the witness jobs picked up.
+fn all_l1_batches() -> RangeInclusive<L1BatchNumber> {
+    L1BatchNumber(0)..=L1BatchNumber(u32::MAX)
+}
+
 fn mock_tx_execution_metrics() -> TransactionExecutionMetrics {
     TransactionExecutionMetrics::default()
 }
@@ -545,21 +559,1925 @@ async fn test_move_scheduler_jobs_from_waiting_to_queued(connection_pool: Connec
     assert_eq!(l1_batch_number, job.unwrap().block_number);
 }
 
-fn get_default_prover_jobs_params(l1_batch_number: L1BatchNumber) -> GetProverJobsParams {
-    GetProverJobsParams {
-        statuses: None,
-        blocks: Some(std::ops::Range {
-            start: l1_batch_number,
-            end: l1_batch_number + 1,
-        }),
-        limit: None,
-        desc: false,
-        round: None,
+#[db_test(dal_crate)]
+async fn heartbeat_prevents_requeueing_scheduler_job(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    for block_number in [1, 2] {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
     }
+    let touched_lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    let stale_job = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap()
+        .l1_batch_number;
+
+    // `now()` is fixed within the test transaction, so the elapsed processing time is emulated
+    // by moving timestamps to the past.
+    sqlx::query(
+        "UPDATE scheduler_witness_jobs_fri SET updated_at = now() - interval '1 minute', processing_started_at = now() - interval '1 minute'",
+    )
+    .execute(fri_witness_generator_dal.storage.conn())
+    .await
+    .unwrap();
+    assert!(
+        fri_witness_generator_dal
+            .touch_scheduler_job(&touched_lease)
+            .await
+    );
+    // Heartbeats with a lost lease don't prevent requeueing the job.
+    let lost_lease = SchedulerJobLease {
+        l1_batch_number: stale_job,
+        lease_id: "stale".to_owned(),
+        attempts: 1,
+        protocol_version: None,
+    };
+    assert!(
+        !fri_witness_generator_dal
+            .touch_scheduler_job(&lost_lease)
+            .await
+    );
+
+    let requeued_jobs = fri_witness_generator_dal
+        .requeue_stuck_scheduler_jobs(Duration::from_secs(30), 10)
+        .await;
+    let requeued_ids: Vec<u64> = requeued_jobs.into_iter().map(|job| job.id).collect();
+    assert_eq!(requeued_ids, [stale_job.0 as u64]);
 }
 
-fn get_sample_proof() -> Vec<u8> {
-    let zksync_home = std::env::var("ZKSYNC_HOME").unwrap_or_else(|_| ".".into());
-    fs::read(format!("{}/etc/prover-test-data/proof.bin", zksync_home))
-        .expect("Failed reading test proof file")
+#[db_test(dal_crate)]
+async fn scheduler_jobs_are_picked_by_priority(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    for block_number in [1, 2, 3] {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+    assert!(
+        fri_witness_generator_dal
+            .set_scheduler_job_priority(L1BatchNumber(3), 10)
+            .await
+    );
+    assert!(
+        !fri_witness_generator_dal
+            .set_scheduler_job_priority(L1BatchNumber(4), 10)
+            .await
+    );
+
+    let mut picked_jobs = vec![];
+    while let Some(lease) = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+    {
+        picked_jobs.push(lease.l1_batch_number.0);
+    }
+    // Jobs with the same priority are picked in the order of batch numbers.
+    assert_eq!(picked_jobs, [3, 1, 2]);
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_jobs_for_priority_batches_are_claimed(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    for block_number in [1, 2, 3] {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+
+    let priority_batches = [L1BatchNumber(3), L1BatchNumber(2), L1BatchNumber(4)];
+    let mut picked_jobs = vec![];
+    while let Some(lease) = fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_batches(&priority_batches, &[], &all_l1_batches())
+        .await
+    {
+        picked_jobs.push(lease.l1_batch_number.0);
+    }
+    assert_eq!(picked_jobs, [2, 3]);
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(1));
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_jobs_are_claimed_once_by_concurrent_tasks(connection_pool: ConnectionPool) {
+    const JOB_COUNT: u32 = 20;
+
+    let mut storage = connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    for block_number in 1..=JOB_COUNT {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+    drop(storage);
+
+    let tasks: Vec<_> = (0..8)
+        .map(|_| {
+            let connection_pool = connection_pool.clone();
+            tokio::spawn(async move {
+                let mut claimed_jobs = vec![];
+                loop {
+                    let lease = connection_pool
+                        .access_test_storage()
+                        .await
+                        .fri_witness_generator_dal()
+                        .get_next_scheduler_witness_job()
+                        .await;
+                    match lease {
+                        Some(lease) => claimed_jobs.push(lease.l1_batch_number.0),
+                        None => break claimed_jobs,
+                    }
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+    let mut claimed_jobs = vec![];
+    for task in tasks {
+        claimed_jobs.extend(task.await.unwrap());
+    }
+    claimed_jobs.sort_unstable();
+    assert_eq!(claimed_jobs, (1..=JOB_COUNT).collect::<Vec<_>>());
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_job_deadlines(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    for block_number in 1..=3 {
+        fri_witness_generator_dal
+            .save_witness_inputs(L1BatchNumber(block_number), "witness_inputs.bin")
+            .await;
+    }
+    // Batch 1 was sealed 2 hours ago, and batch 2 was sealed 30 minutes ago.
+    sqlx::query(
+        "UPDATE witness_inputs_fri SET created_at = now() - interval '2 hours' WHERE l1_batch_number = 1",
+    )
+    .execute(fri_witness_generator_dal.storage.conn())
+    .await
+    .unwrap();
+    sqlx::query(
+        "UPDATE witness_inputs_fri SET created_at = now() - interval '30 minutes' WHERE l1_batch_number = 2",
+    )
+    .execute(fri_witness_generator_dal.storage.conn())
+    .await
+    .unwrap();
+    for block_number in 1..=3 {
+        // The batch 3 job has no deadline.
+        let deadline = (block_number < 3).then(|| Duration::from_secs(3_600));
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                deadline,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+
+    let (deadline_delta,): (f64,) = sqlx::query_as(
+        "SELECT EXTRACT(EPOCH FROM s.deadline_at - w.created_at)::FLOAT8 \
+         FROM scheduler_witness_jobs_fri s JOIN witness_inputs_fri w USING (l1_batch_number) \
+         WHERE l1_batch_number = 2",
+    )
+    .fetch_one(fri_witness_generator_dal.storage.conn())
+    .await
+    .unwrap();
+    assert_eq!(deadline_delta, 3_600.0);
+    assert!(
+        fri_witness_generator_dal
+            .is_scheduler_job_past_deadline(L1BatchNumber(1))
+            .await
+    );
+    assert!(
+        !fri_witness_generator_dal
+            .is_scheduler_job_past_deadline(L1BatchNumber(2))
+            .await
+    );
+    assert!(
+        !fri_witness_generator_dal
+            .is_scheduler_job_past_deadline(L1BatchNumber(3))
+            .await
+    );
+    assert_eq!(
+        fri_witness_generator_dal
+            .get_scheduler_jobs_past_deadline()
+            .await,
+        [L1BatchNumber(1)]
+    );
+
+    // In strict mode, the job past its deadline is skipped.
+    assert_eq!(
+        fri_witness_generator_dal
+            .mark_queued_scheduler_jobs_past_deadline()
+            .await,
+        [L1BatchNumber(1)]
+    );
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(2));
+    assert_eq!(
+        fri_witness_generator_dal
+            .count_witness_jobs_with_status(
+                AggregationRound::Scheduler,
+                FriWitnessJobStatus::RequiresManualIntervention
+            )
+            .await,
+        1
+    );
+    // Jobs requiring manual intervention are still reported as past their deadline.
+    assert_eq!(
+        fri_witness_generator_dal
+            .get_scheduler_jobs_past_deadline()
+            .await,
+        [L1BatchNumber(1)]
+    );
+}
+
+#[db_test(dal_crate)]
+async fn witness_jobs_are_counted_by_status(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    for block_number in [1, 2, 3] {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+    fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+
+    let queued_jobs = fri_witness_generator_dal
+        .count_witness_jobs_with_status(AggregationRound::Scheduler, FriWitnessJobStatus::Queued)
+        .await;
+    assert_eq!(queued_jobs, 2);
+    let in_progress_jobs = fri_witness_generator_dal
+        .count_witness_jobs_with_status(
+            AggregationRound::Scheduler,
+            FriWitnessJobStatus::InProgress,
+        )
+        .await;
+    assert_eq!(in_progress_jobs, 1);
+}
+
+#[db_test(dal_crate)]
+async fn skipped_scheduler_job_is_cancelled(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    fri_witness_generator_dal
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &Vec::new(),
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    fri_witness_generator_dal
+        .mark_scheduler_jobs_as_queued(1)
+        .await;
+    let block_number = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap()
+        .l1_batch_number;
+    assert!(
+        !fri_witness_generator_dal
+            .is_scheduler_job_cancelled(block_number)
+            .await
+    );
+
+    fri_witness_generator_dal
+        .mark_scheduler_job_as_skipped(block_number)
+        .await;
+    assert!(
+        fri_witness_generator_dal
+            .is_scheduler_job_cancelled(block_number)
+            .await
+    );
+    let requeued_jobs = fri_witness_generator_dal
+        .requeue_stuck_scheduler_jobs(Duration::ZERO, 10)
+        .await;
+    assert!(requeued_jobs.is_empty());
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_jobs_for_skipped_batches_are_not_picked(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    for block_number in [1, 2] {
+        fri_witness_generator_dal
+            .save_witness_inputs(
+                L1BatchNumber(block_number),
+                &format!("merkle_paths_{}.bin", block_number),
+            )
+            .await;
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &vec![(1, format!("closed_form_inputs_{}_1.bin", block_number), 1)],
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+    fri_witness_generator_dal
+        .mark_witness_job(FriWitnessJobStatus::Skipped, L1BatchNumber(1))
+        .await;
+
+    assert_eq!(
+        fri_witness_generator_dal
+            .peek_next_scheduler_witness_job(&all_l1_batches(), &[])
+            .await,
+        Some((L1BatchNumber(2), None))
+    );
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(2));
+    assert!(fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .is_none());
+
+    // Successful jobs are kept when skipping a batch.
+    fri_witness_generator_dal
+        .mark_witness_job_as_successful(L1BatchNumber(2), Duration::from_secs(1))
+        .await;
+    let skipped_rounds = fri_witness_generator_dal
+        .mark_l1_batch_witness_jobs_as_skipped(L1BatchNumber(2))
+        .await;
+    assert_eq!(
+        skipped_rounds,
+        [
+            AggregationRound::LeafAggregation,
+            AggregationRound::NodeAggregation,
+            AggregationRound::Scheduler
+        ]
+    );
+    assert!(
+        fri_witness_generator_dal
+            .is_scheduler_job_cancelled(L1BatchNumber(2))
+            .await
+    );
+    let skipped_rounds = fri_witness_generator_dal
+        .mark_l1_batch_witness_jobs_as_skipped(L1BatchNumber(2))
+        .await;
+    assert!(skipped_rounds.is_empty());
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_jobs_with_unsuccessful_final_prover_jobs_are_not_picked(
+    connection_pool: ConnectionPool,
+) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    for block_number in [1, 2] {
+        let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+
+    let mut prover_dal = storage.fri_prover_jobs_dal();
+    for circuit_id in 1..=13 {
+        prover_dal
+            .insert_prover_job(
+                L1BatchNumber(1),
+                circuit_id,
+                0,
+                0,
+                AggregationRound::NodeAggregation,
+                &format!("1_{}_node_final.bin", circuit_id),
+                true,
+            )
+            .await;
+    }
+    let job_ids: Vec<(i64, i16)> = sqlx::query_as(
+        "SELECT id, circuit_id FROM prover_jobs_fri WHERE l1_batch_number = 1 ORDER BY circuit_id",
+    )
+    .fetch_all(storage.conn())
+    .await
+    .unwrap();
+    assert_eq!(job_ids.len(), 13);
+    for &(job_id, circuit_id) in &job_ids {
+        storage
+            .fri_scheduler_dependency_tracker_dal()
+            .set_final_prover_job_id_for_l1_batch(circuit_id as u8, job_id as u32, L1BatchNumber(1))
+            .await;
+        let mut prover_dal = storage.fri_prover_jobs_dal();
+        if circuit_id == 3 {
+            prover_dal
+                .save_proof_error(job_id as u32, "proof failed verification".to_owned())
+                .await;
+        } else {
+            prover_dal
+                .save_proof(job_id as u32, Duration::from_secs(1), "proof.bin")
+                .await;
+        }
+    }
+
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    let blocked_jobs = fri_witness_generator_dal
+        .count_blocked_scheduler_jobs()
+        .await;
+    assert_eq!(blocked_jobs, HashMap::from([("failed".to_owned(), 1)]));
+    assert_eq!(
+        fri_witness_generator_dal
+            .peek_next_scheduler_witness_job(&all_l1_batches(), &[])
+            .await,
+        Some((L1BatchNumber(2), None))
+    );
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(2));
+    assert!(fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .is_none());
+
+    // The blocked job becomes eligible once the failed prover job succeeds.
+    let (failed_job_id, _) = job_ids[2];
+    storage
+        .fri_prover_jobs_dal()
+        .save_proof(failed_job_id as u32, Duration::from_secs(1), "proof.bin")
+        .await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    assert!(fri_witness_generator_dal
+        .count_blocked_scheduler_jobs()
+        .await
+        .is_empty());
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(1));
+}
+
+#[db_test(dal_crate)]
+async fn peeking_scheduler_job_does_not_lock_it(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    for block_number in [1, 2] {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+
+    let peeked_job = fri_witness_generator_dal
+        .peek_next_scheduler_witness_job(&all_l1_batches(), &[])
+        .await;
+    assert_eq!(peeked_job, Some((L1BatchNumber(1), None)));
+    let peeked_job = fri_witness_generator_dal
+        .peek_next_scheduler_witness_job(&(L1BatchNumber(2)..=L1BatchNumber(u32::MAX)), &[])
+        .await;
+    assert_eq!(peeked_job, Some((L1BatchNumber(2), None)));
+    let picked_job = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .map(|lease| lease.l1_batch_number);
+    assert_eq!(picked_job, Some(L1BatchNumber(1)));
+}
+
+#[db_test(dal_crate)]
+async fn outcome_of_scheduler_job_with_lost_lease_is_rejected(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    fri_witness_generator_dal
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &Vec::new(),
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    fri_witness_generator_dal
+        .mark_scheduler_jobs_as_queued(1)
+        .await;
+    let first_lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+
+    // The first replica is considered stuck, and the job is picked up by another replica.
+    fri_witness_generator_dal
+        .requeue_stuck_scheduler_jobs(Duration::ZERO, 10)
+        .await;
+    let second_lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(second_lease.l1_batch_number, first_lease.l1_batch_number);
+    assert_ne!(second_lease.lease_id, first_lease.lease_id);
+
+    assert!(
+        fri_witness_generator_dal
+            .mark_scheduler_job_as_successful(&second_lease, Duration::from_secs(1))
+            .await
+    );
+    // The first replica finishes processing after the second one; its outcomes are rejected.
+    assert!(
+        !fri_witness_generator_dal
+            .mark_scheduler_job_as_successful(&first_lease, Duration::from_secs(2))
+            .await
+    );
+    assert!(fri_witness_generator_dal
+        .mark_scheduler_job_failed(&job_failure("other", &first_lease), true, 10, &first_lease)
+        .await
+        .is_none());
+    let requeued_jobs = fri_witness_generator_dal
+        .requeue_stuck_scheduler_jobs(Duration::ZERO, 10)
+        .await;
+    assert!(requeued_jobs.is_empty());
+}
+
+fn job_failure(error_kind: &str, lease: &SchedulerJobLease) -> JobErrorHistoryEntry {
+    JobErrorHistoryEntry {
+        timestamp: Utc::now(),
+        attempt: Some(lease.attempts),
+        error_kind: error_kind.to_owned(),
+        error: format!("{} error", error_kind),
+        hostname: Some("witness-generator-0".to_owned()),
+    }
+}
+
+#[db_test(dal_crate)]
+async fn released_scheduler_job_is_queued_again(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    fri_witness_generator_dal
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &Vec::new(),
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    fri_witness_generator_dal
+        .mark_scheduler_jobs_as_queued(1)
+        .await;
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+
+    assert!(
+        fri_witness_generator_dal
+            .release_scheduler_job(&lease)
+            .await
+    );
+    assert!(
+        !fri_witness_generator_dal
+            .release_scheduler_job(&lease)
+            .await
+    );
+    let new_lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(new_lease.l1_batch_number, lease.l1_batch_number);
+
+    // The released attempt is not counted.
+    let (attempts,): (i16,) =
+        sqlx::query_as("SELECT attempts FROM scheduler_witness_jobs_fri WHERE l1_batch_number = 1")
+            .fetch_one(fri_witness_generator_dal.storage.conn())
+            .await
+            .unwrap();
+    assert_eq!(attempts, 1);
+}
+
+#[db_test(dal_crate)]
+async fn retryable_scheduler_job_failures_are_requeued(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    for l1_batch_number in 1..=2 {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(l1_batch_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{l1_batch_number}.bin"),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(l1_batch_number as i64)
+            .await;
+    }
+
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(1));
+    let status = fri_witness_generator_dal
+        .mark_scheduler_job_failed(&job_failure("missing_input", &lease), true, 2, &lease)
+        .await;
+    assert!(matches!(status, Some(FriWitnessJobStatus::Queued)));
+
+    // The requeued job is picked up again; it has exhausted its attempts after the second failure.
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(1));
+    let status = fri_witness_generator_dal
+        .mark_scheduler_job_failed(&job_failure("missing_input", &lease), true, 2, &lease)
+        .await;
+    assert!(matches!(status, Some(FriWitnessJobStatus::Failed)));
+
+    // Non-retryable failures are persisted right away.
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(2));
+    let status = fri_witness_generator_dal
+        .mark_scheduler_job_failed(&job_failure("invalid_input", &lease), false, 2, &lease)
+        .await;
+    assert!(matches!(status, Some(FriWitnessJobStatus::Failed)));
+    assert!(fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .is_none());
+}
+
+#[db_test(dal_crate)]
+async fn fatal_witness_job_failures_are_not_requeued(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    for l1_batch_number in 1..=2 {
+        fri_witness_generator_dal
+            .save_witness_inputs(L1BatchNumber(l1_batch_number), "witness_inputs.bin")
+            .await;
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(l1_batch_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{l1_batch_number}.bin"),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(l1_batch_number as i64)
+            .await;
+    }
+
+    // The job for batch 1 exhausts its in-process attempts; the job for batch 2 fails fatally.
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    let status = fri_witness_generator_dal
+        .mark_scheduler_job_failed(&job_failure("other", &lease), true, 1, &lease)
+        .await;
+    assert!(matches!(status, Some(FriWitnessJobStatus::Failed)));
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    let status = fri_witness_generator_dal
+        .mark_scheduler_job_failed(&job_failure("invalid_input", &lease), false, 1, &lease)
+        .await;
+    assert!(matches!(status, Some(FriWitnessJobStatus::Failed)));
+    fri_witness_generator_dal
+        .mark_witness_job_failed("other error", L1BatchNumber(1), true)
+        .await;
+    fri_witness_generator_dal
+        .mark_witness_job_failed("invalid_input error", L1BatchNumber(2), false)
+        .await;
+
+    // Only the retryable failures are requeued, and fatal ones stay failed on subsequent passes.
+    let requeued_jobs = fri_witness_generator_dal
+        .requeue_stuck_scheduler_jobs(Duration::ZERO, 10)
+        .await;
+    assert_eq!(requeued_jobs.len(), 1);
+    assert_eq!(requeued_jobs[0].id, 1);
+    let requeued_jobs = fri_witness_generator_dal
+        .requeue_stuck_basic_jobs(Duration::ZERO, 10)
+        .await;
+    assert_eq!(requeued_jobs.len(), 1);
+    assert_eq!(requeued_jobs[0].id, 1);
+    assert!(fri_witness_generator_dal
+        .requeue_stuck_scheduler_jobs(Duration::ZERO, 10)
+        .await
+        .is_empty());
+    assert!(fri_witness_generator_dal
+        .requeue_stuck_basic_jobs(Duration::ZERO, 10)
+        .await
+        .is_empty());
+
+    for table in ["scheduler_witness_jobs_fri", "witness_inputs_fri"] {
+        let query = format!("SELECT status FROM {table} WHERE l1_batch_number = 2");
+        let status: String = sqlx::query_scalar(&query)
+            .fetch_one(fri_witness_generator_dal.storage.conn())
+            .await
+            .unwrap();
+        assert_eq!(status, "failed", "{}", table);
+    }
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_job_failures_are_accumulated_in_error_history(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    fri_witness_generator_dal
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &Vec::new(),
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    fri_witness_generator_dal
+        .mark_scheduler_jobs_as_queued(1)
+        .await;
+    let history = fri_witness_generator_dal
+        .get_scheduler_job_error_history(L1BatchNumber(1))
+        .await;
+    assert_eq!(history, Some(vec![]));
+    assert_eq!(
+        fri_witness_generator_dal
+            .get_scheduler_job_error_history(L1BatchNumber(2))
+            .await,
+        None
+    );
+
+    let mut failures = vec![];
+    for error_kind in ["missing_input", "panic", "invalid_input"] {
+        let lease = fri_witness_generator_dal
+            .get_next_scheduler_witness_job()
+            .await
+            .unwrap();
+        let failure = job_failure(error_kind, &lease);
+        fri_witness_generator_dal
+            .mark_scheduler_job_failed(&failure, error_kind != "invalid_input", 10, &lease)
+            .await
+            .unwrap();
+        failures.push(failure);
+    }
+    let history = fri_witness_generator_dal
+        .get_scheduler_job_error_history(L1BatchNumber(1))
+        .await
+        .unwrap();
+    assert_eq!(history, failures);
+    let attempts: Vec<_> = history.iter().map(|entry| entry.attempt).collect();
+    assert_eq!(attempts, [Some(1), Some(2), Some(3)]);
+
+    // The latest error is kept in the job for compatibility.
+    let (error, error_kind): (String, String) = sqlx::query_as(
+        "SELECT error, error_kind FROM scheduler_witness_jobs_fri WHERE l1_batch_number = 1",
+    )
+    .fetch_one(fri_witness_generator_dal.storage.conn())
+    .await
+    .unwrap();
+    assert_eq!(error, "invalid_input error");
+    assert_eq!(error_kind, "invalid_input");
+
+    // Failures with a lost lease are not recorded, and the history survives requeueing.
+    let stale_lease = SchedulerJobLease {
+        l1_batch_number: L1BatchNumber(1),
+        lease_id: "stale".to_owned(),
+        attempts: 3,
+        protocol_version: None,
+    };
+    assert!(fri_witness_generator_dal
+        .mark_scheduler_job_failed(&job_failure("other", &stale_lease), true, 10, &stale_lease)
+        .await
+        .is_none());
+    fri_witness_generator_dal
+        .requeue_scheduler_job(L1BatchNumber(1))
+        .await;
+    let history = fri_witness_generator_dal
+        .get_scheduler_job_error_history(L1BatchNumber(1))
+        .await
+        .unwrap();
+    assert_eq!(history, failures);
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_job_is_requeued_for_regeneration(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    fri_witness_generator_dal
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &Vec::new(),
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    fri_witness_generator_dal
+        .mark_scheduler_jobs_as_queued(1)
+        .await;
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    fri_witness_generator_dal
+        .mark_scheduler_job_failed(&job_failure("invalid_input", &lease), false, 2, &lease)
+        .await;
+    assert!(matches!(
+        fri_witness_generator_dal
+            .get_scheduler_job_status(L1BatchNumber(1))
+            .await,
+        Some(FriWitnessJobStatus::Failed)
+    ));
+
+    assert!(
+        fri_witness_generator_dal
+            .requeue_scheduler_job(L1BatchNumber(1))
+            .await
+    );
+    assert!(
+        !fri_witness_generator_dal
+            .requeue_scheduler_job(L1BatchNumber(2))
+            .await
+    );
+    // The outcome of the previous attempt is discarded.
+    assert!(fri_witness_generator_dal
+        .mark_scheduler_job_failed(&job_failure("invalid_input", &lease), false, 2, &lease)
+        .await
+        .is_none());
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(1));
+    assert_eq!(lease.attempts, 1);
+
+    let mut prover_dal = storage.fri_prover_jobs_dal();
+    prover_dal
+        .insert_prover_job(
+            L1BatchNumber(1),
+            1,
+            0,
+            0,
+            AggregationRound::Scheduler,
+            "1_0_1_Scheduler_0.bin",
+            false,
+        )
+        .await;
+    assert_eq!(
+        prover_dal
+            .get_scheduler_prover_job_status(L1BatchNumber(1))
+            .await
+            .as_deref(),
+        Some("queued")
+    );
+    assert!(
+        prover_dal
+            .remove_scheduler_prover_job(L1BatchNumber(1))
+            .await
+    );
+    assert!(prover_dal
+        .get_scheduler_prover_job_status(L1BatchNumber(1))
+        .await
+        .is_none());
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_proof_witness_sources_are_persisted(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    fri_witness_generator_dal
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &Vec::new(),
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    assert!(fri_witness_generator_dal
+        .get_scheduler_proof_witness_sources(L1BatchNumber(1))
+        .await
+        .is_none());
+
+    let sources: Vec<_> = (0..13)
+        .map(|position| SchedulerProofWitnessSource {
+            position,
+            prover_job_id: 100 + position as u32,
+            circuit_id: position as u8 + 1,
+            proof_blob_url: format!("proof_{}.bin", 100 + position),
+        })
+        .collect();
+    fri_witness_generator_dal
+        .try_set_scheduler_proof_witness_sources(L1BatchNumber(1), &sources)
+        .await
+        .unwrap();
+    assert_eq!(
+        fri_witness_generator_dal
+            .get_scheduler_proof_witness_sources(L1BatchNumber(1))
+            .await,
+        Some(sources)
+    );
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_commitments_are_persisted(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    for block_number in 1..=3 {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+    }
+    assert!(fri_witness_generator_dal
+        .get_scheduler_commitments(L1BatchNumber(1), 10)
+        .await
+        .is_empty());
+
+    let commitments = |byte| SchedulerCommitments {
+        meta_parameters_hash: H256::repeat_byte(byte),
+        pass_through_data_hash: H256::repeat_byte(byte + 1),
+    };
+    for block_number in [1, 3] {
+        fri_witness_generator_dal
+            .try_save_scheduler_commitments(
+                L1BatchNumber(block_number),
+                &commitments(block_number as u8),
+            )
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        fri_witness_generator_dal
+            .get_scheduler_commitments(L1BatchNumber(1), 10)
+            .await,
+        [
+            (L1BatchNumber(1), commitments(1)),
+            (L1BatchNumber(3), commitments(3))
+        ]
+    );
+    assert_eq!(
+        fri_witness_generator_dal
+            .get_scheduler_commitments(L1BatchNumber(1), 1)
+            .await,
+        [(L1BatchNumber(1), commitments(1))]
+    );
+    assert_eq!(
+        fri_witness_generator_dal
+            .get_scheduler_commitments(L1BatchNumber(2), 10)
+            .await,
+        [(L1BatchNumber(3), commitments(3))]
+    );
+}
+
+#[db_test(dal_crate)]
+async fn saving_scheduler_job_result_is_idempotent(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    storage
+        .fri_witness_generator_dal()
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &Vec::new(),
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    storage
+        .fri_witness_generator_dal()
+        .mark_scheduler_jobs_as_queued(1)
+        .await;
+    let lease = storage
+        .fri_witness_generator_dal()
+        .get_next_scheduler_witness_job()
+        .await
+        .unwrap();
+
+    // Emulates the scheduler witness generator retrying to save the job result after
+    // the outcome of the first attempt was lost.
+    for time_taken in [Duration::from_secs(10), Duration::from_secs(20)] {
+        assert!(storage
+            .fri_witness_generator_dal()
+            .try_mark_scheduler_job_as_successful(&lease, time_taken)
+            .await
+            .unwrap());
+        storage
+            .fri_prover_jobs_dal()
+            .try_insert_prover_job(
+                L1BatchNumber(1),
+                1,
+                0,
+                0,
+                AggregationRound::Scheduler,
+                "1_0_1_Scheduler_0.bin",
+                false,
+            )
+            .await
+            .unwrap();
+        storage
+            .fri_prover_jobs_dal()
+            .try_set_circuit_blob_size(
+                L1BatchNumber(1),
+                1,
+                0,
+                0,
+                AggregationRound::Scheduler,
+                1_024,
+            )
+            .await
+            .unwrap();
+    }
+
+    let (prover_job_count,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM prover_jobs_fri WHERE l1_batch_number = 1")
+            .fetch_one(storage.conn())
+            .await
+            .unwrap();
+    assert_eq!(prover_job_count, 1);
+    assert_eq!(
+        storage
+            .fri_prover_jobs_dal()
+            .get_scheduler_prover_job_status(L1BatchNumber(1))
+            .await
+            .as_deref(),
+        Some("queued")
+    );
+    // The processing time recorded by the first attempt is kept.
+    let (time_taken,): (String,) = sqlx::query_as(
+        "SELECT time_taken::TEXT FROM scheduler_witness_jobs_fri WHERE l1_batch_number = 1",
+    )
+    .fetch_one(storage.conn())
+    .await
+    .unwrap();
+    assert_eq!(time_taken, "00:00:10");
+}
+
+#[db_test(dal_crate)]
+async fn scheduler_jobs_are_claimed_for_protocol_versions(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    for block_number in 1..=3 {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &Vec::new(),
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+    fri_witness_generator_dal
+        .set_scheduler_job_protocol_version(L1BatchNumber(1), 1)
+        .await;
+    fri_witness_generator_dal
+        .set_scheduler_job_protocol_version(L1BatchNumber(2), 2)
+        .await;
+
+    let counts = fri_witness_generator_dal
+        .count_queued_scheduler_jobs_by_protocol_version()
+        .await;
+    assert_eq!(
+        counts,
+        HashMap::from([(Some(1), 1), (Some(2), 1), (None, 1)])
+    );
+    let peeked_job = fri_witness_generator_dal
+        .peek_next_scheduler_witness_job(&all_l1_batches(), &[2])
+        .await;
+    assert_eq!(peeked_job, Some((L1BatchNumber(2), Some(2))));
+
+    // The job for version 1 is left queued.
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_protocol_versions(&[2], &all_l1_batches())
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(2));
+    assert_eq!(lease.protocol_version, Some(2));
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_protocol_versions(&[2], &all_l1_batches())
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(3));
+    assert_eq!(lease.protocol_version, None);
+    assert!(fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_protocol_versions(&[2], &all_l1_batches())
+        .await
+        .is_none());
+
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_batches(&[L1BatchNumber(1)], &[1], &all_l1_batches())
+        .await
+        .unwrap();
+    assert_eq!(lease.protocol_version, Some(1));
+}
+
+#[db_test(dal_crate)]
+async fn aggregation_jobs_are_claimed_in_batches(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = FriWitnessGeneratorDal { storage };
+    let closed_form_inputs: Vec<_> = (1..=3)
+        .map(|circuit_id| {
+            (
+                circuit_id,
+                format!("closed_form_inputs_{}.bin", circuit_id),
+                1,
+            )
+        })
+        .collect();
+    for block_number in [1, 2] {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &closed_form_inputs,
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+    }
+    for table in [
+        "leaf_aggregation_witness_jobs_fri",
+        "node_aggregation_witness_jobs_fri",
+    ] {
+        sqlx::query(&format!("UPDATE {} SET status = 'queued'", table))
+            .execute(fri_witness_generator_dal.storage.conn())
+            .await
+            .unwrap();
+    }
+
+    let leaf_jobs = fri_witness_generator_dal
+        .get_next_leaf_aggregation_jobs(4, &all_l1_batches(), None)
+        .await;
+    let leaf_jobs: Vec<_> = leaf_jobs
+        .iter()
+        .map(|job| (job.block_number.0, job.circuit_id))
+        .collect();
+    assert_eq!(leaf_jobs, [(1, 1), (1, 2), (1, 3), (2, 1)]);
+    let leaf_jobs = fri_witness_generator_dal
+        .get_next_leaf_aggregation_jobs(4, &all_l1_batches(), None)
+        .await;
+    assert_eq!(leaf_jobs.len(), 2);
+    let leaf_jobs = fri_witness_generator_dal
+        .get_next_leaf_aggregation_jobs(4, &all_l1_batches(), None)
+        .await;
+    assert!(leaf_jobs.is_empty());
+
+    let node_jobs = fri_witness_generator_dal
+        .get_next_node_aggregation_jobs(10, &all_l1_batches(), None)
+        .await;
+    let node_jobs: Vec<_> = node_jobs
+        .iter()
+        .map(|job| (job.block_number.0, job.circuit_id))
+        .collect();
+    assert_eq!(node_jobs, [(1, 1), (1, 2), (1, 3), (2, 1), (2, 2), (2, 3)]);
+    assert!(fri_witness_generator_dal
+        .get_next_node_aggregation_job(&all_l1_batches(), None)
+        .await
+        .is_none());
+}
+
+#[db_test(dal_crate)]
+async fn node_aggregation_fan_in_is_recorded_once_per_batch(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    let closed_form_inputs = vec![(1, "closed_form_inputs_1.bin".to_owned(), 1)];
+    for block_number in [1, 2] {
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &closed_form_inputs,
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+    }
+    fri_witness_generator_dal
+        .set_node_aggregation_fan_in(L1BatchNumber(1), 2)
+        .await;
+    // Processing the batch again with a different fan-in doesn't affect its jobs.
+    fri_witness_generator_dal
+        .set_node_aggregation_fan_in(L1BatchNumber(1), 32)
+        .await;
+    fri_witness_generator_dal
+        .insert_node_aggregation_jobs(L1BatchNumber(1), 1, Some(3), 1, "aggregations.bin", Some(2))
+        .await;
+    sqlx::query("UPDATE node_aggregation_witness_jobs_fri SET status = 'queued'")
+        .execute(fri_witness_generator_dal.storage.conn())
+        .await
+        .unwrap();
+
+    let node_jobs = fri_witness_generator_dal
+        .get_next_node_aggregation_jobs(10, &all_l1_batches(), None)
+        .await;
+    let node_jobs: Vec<_> = node_jobs
+        .iter()
+        .map(|job| (job.block_number.0, job.depth, job.fan_in))
+        .collect();
+    assert_eq!(node_jobs, [(1, 0, Some(2)), (1, 1, Some(2)), (2, 0, None)]);
+}
+
+#[db_test(dal_crate)]
+async fn node_aggregation_jobs_stats_are_reported_by_depth(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    let closed_form_inputs = vec![
+        (1, "closed_form_inputs_1.bin".to_owned(), 1),
+        (2, "closed_form_inputs_2.bin".to_owned(), 1),
+    ];
+    fri_witness_generator_dal
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &closed_form_inputs,
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    fri_witness_generator_dal
+        .insert_node_aggregation_jobs(L1BatchNumber(1), 1, Some(3), 1, "aggregations.bin", None)
+        .await;
+    sqlx::query(
+        "UPDATE node_aggregation_witness_jobs_fri SET status = 'queued' \
+         WHERE depth = 1 OR circuit_id = 2",
+    )
+    .execute(fri_witness_generator_dal.storage.conn())
+    .await
+    .unwrap();
+
+    let stats_by_depth = fri_witness_generator_dal
+        .get_node_aggregation_jobs_stats_by_depth()
+        .await;
+    let queued_by_depth: Vec<_> = stats_by_depth
+        .iter()
+        .map(|(&depth, stats)| (depth, stats.queued, stats.in_progress))
+        .collect();
+    assert_eq!(queued_by_depth, [(0, 1, 0), (1, 1, 0)]);
+}
+
+#[db_test(dal_crate)]
+async fn node_aggregation_is_complete_once_all_jobs_are_successful(
+    connection_pool: ConnectionPool,
+) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    let closed_form_inputs = vec![(1, "closed_form_inputs_1.bin".to_owned(), 1)];
+    fri_witness_generator_dal
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &closed_form_inputs,
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    fri_witness_generator_dal
+        .insert_node_aggregation_jobs(L1BatchNumber(1), 1, Some(3), 1, "aggregations.bin", None)
+        .await;
+    assert!(
+        !fri_witness_generator_dal
+            .are_node_aggregation_jobs_successful(L1BatchNumber(1), 1)
+            .await
+    );
+
+    sqlx::query(
+        "UPDATE node_aggregation_witness_jobs_fri SET status = 'successful' WHERE depth = 0",
+    )
+    .execute(fri_witness_generator_dal.storage.conn())
+    .await
+    .unwrap();
+    assert!(
+        !fri_witness_generator_dal
+            .are_node_aggregation_jobs_successful(L1BatchNumber(1), 1)
+            .await
+    );
+
+    sqlx::query("UPDATE node_aggregation_witness_jobs_fri SET status = 'successful'")
+        .execute(fri_witness_generator_dal.storage.conn())
+        .await
+        .unwrap();
+    assert!(
+        fri_witness_generator_dal
+            .are_node_aggregation_jobs_successful(L1BatchNumber(1), 1)
+            .await
+    );
+    // There are no jobs for other circuits.
+    assert!(
+        !fri_witness_generator_dal
+            .are_node_aggregation_jobs_successful(L1BatchNumber(1), 2)
+            .await
+    );
+}
+
+/// Requeues stuck witness jobs of the round; returns statuses and attempts of the affected jobs.
+async fn requeue_stuck_witness_jobs(
+    fri_witness_generator_dal: &mut FriWitnessGeneratorDal<'_, '_>,
+    round: AggregationRound,
+    processing_timeout: Duration,
+    max_attempts: u32,
+) -> Vec<(String, u64)> {
+    let stuck_jobs = match round {
+        AggregationRound::BasicCircuits => {
+            fri_witness_generator_dal
+                .requeue_stuck_basic_jobs(processing_timeout, max_attempts)
+                .await
+        }
+        AggregationRound::LeafAggregation => {
+            fri_witness_generator_dal
+                .requeue_stuck_leaf_aggregations_jobs(processing_timeout, max_attempts)
+                .await
+        }
+        AggregationRound::NodeAggregation => {
+            fri_witness_generator_dal
+                .requeue_stuck_node_aggregations_jobs(processing_timeout, max_attempts)
+                .await
+        }
+        AggregationRound::Scheduler => {
+            fri_witness_generator_dal
+                .requeue_stuck_scheduler_jobs(processing_timeout, max_attempts)
+                .await
+        }
+    };
+    stuck_jobs
+        .into_iter()
+        .map(|job| (job.status, job.attempts))
+        .collect()
+}
+
+/// Checks that stuck witness jobs of the round are requeued until they exhaust their attempts,
+/// after which they are marked as failed.
+async fn check_stuck_witness_jobs_are_requeued(
+    connection_pool: ConnectionPool,
+    round: AggregationRound,
+    table: &str,
+) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    let closed_form_inputs = vec![(1, "closed_form_inputs_1.bin".to_owned(), 1)];
+    for block_number in [1, 2, 3] {
+        fri_witness_generator_dal
+            .save_witness_inputs(L1BatchNumber(block_number), "witness_inputs.bin")
+            .await;
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &closed_form_inputs,
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+    }
+    // Jobs for batches 1 and 2 are stuck after 1 and 3 attempts respectively, while the job
+    // for batch 3 has just been picked up.
+    let stuck_jobs_queries = [
+        format!(
+            "UPDATE {table} SET status = 'in_progress', \
+             attempts = CASE WHEN l1_batch_number = 1 THEN 1 ELSE 3 END, \
+             processing_started_at = now() - interval '1 hour', \
+             updated_at = now() - interval '1 hour'"
+        ),
+        format!(
+            "UPDATE {table} SET processing_started_at = now(), updated_at = now() \
+             WHERE l1_batch_number = 3"
+        ),
+    ];
+    for query in &stuck_jobs_queries {
+        sqlx::query(query)
+            .execute(fri_witness_generator_dal.storage.conn())
+            .await
+            .unwrap();
+    }
+
+    let processing_timeout = Duration::from_secs(60);
+    let stuck_jobs =
+        requeue_stuck_witness_jobs(&mut fri_witness_generator_dal, round, processing_timeout, 3)
+            .await;
+    assert_eq!(
+        stuck_jobs,
+        [("queued".to_owned(), 2), ("failed".to_owned(), 3)],
+        "{:?}",
+        round
+    );
+    let error_query =
+        format!("SELECT error FROM {table} WHERE l1_batch_number = 2 AND status = 'failed'");
+    let error: Option<String> = sqlx::query_scalar(&error_query)
+        .fetch_one(fri_witness_generator_dal.storage.conn())
+        .await
+        .unwrap();
+    assert_eq!(error.as_deref(), Some("exhausted attempts"), "{:?}", round);
+
+    // Jobs with exhausted attempts are not requeued.
+    let stuck_jobs =
+        requeue_stuck_witness_jobs(&mut fri_witness_generator_dal, round, processing_timeout, 3)
+            .await;
+    assert!(stuck_jobs.is_empty(), "{:?}: {:?}", round, stuck_jobs);
+    // ...unless the max number of attempts is increased.
+    let stuck_jobs =
+        requeue_stuck_witness_jobs(&mut fri_witness_generator_dal, round, processing_timeout, 5)
+            .await;
+    assert_eq!(stuck_jobs, [("queued".to_owned(), 4)], "{:?}", round);
+}
+
+#[db_test(dal_crate)]
+async fn stuck_basic_jobs_are_requeued(connection_pool: ConnectionPool) {
+    let round = AggregationRound::BasicCircuits;
+    check_stuck_witness_jobs_are_requeued(connection_pool, round, "witness_inputs_fri").await;
+}
+
+#[db_test(dal_crate)]
+async fn stuck_leaf_aggregation_jobs_are_requeued(connection_pool: ConnectionPool) {
+    let round = AggregationRound::LeafAggregation;
+    let table = "leaf_aggregation_witness_jobs_fri";
+    check_stuck_witness_jobs_are_requeued(connection_pool, round, table).await;
+}
+
+#[db_test(dal_crate)]
+async fn stuck_node_aggregation_jobs_are_requeued(connection_pool: ConnectionPool) {
+    let round = AggregationRound::NodeAggregation;
+    let table = "node_aggregation_witness_jobs_fri";
+    check_stuck_witness_jobs_are_requeued(connection_pool, round, table).await;
+}
+
+#[db_test(dal_crate)]
+async fn stuck_scheduler_jobs_are_requeued(connection_pool: ConnectionPool) {
+    let round = AggregationRound::Scheduler;
+    let table = "scheduler_witness_jobs_fri";
+    check_stuck_witness_jobs_are_requeued(connection_pool, round, table).await;
+}
+
+#[db_test(dal_crate)]
+async fn ranged_pickups_do_not_claim_out_of_range_jobs(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    let closed_form_inputs = vec![(1, "closed_form_inputs_1.bin".to_owned(), 1)];
+    for block_number in 1..=4 {
+        fri_witness_generator_dal
+            .save_witness_inputs(L1BatchNumber(block_number), "witness_inputs.bin")
+            .await;
+        fri_witness_generator_dal
+            .create_aggregation_jobs(
+                L1BatchNumber(block_number),
+                &closed_form_inputs,
+                &format!("scheduler_partial_input_{}.bin", block_number),
+                |circuit_id| circuit_id,
+                None,
+            )
+            .await;
+        fri_witness_generator_dal
+            .mark_scheduler_jobs_as_queued(block_number as i64)
+            .await;
+    }
+    for table in [
+        "leaf_aggregation_witness_jobs_fri",
+        "node_aggregation_witness_jobs_fri",
+    ] {
+        sqlx::query(&format!("UPDATE {} SET status = 'queued'", table))
+            .execute(fri_witness_generator_dal.storage.conn())
+            .await
+            .unwrap();
+    }
+
+    let range = L1BatchNumber(2)..=L1BatchNumber(3);
+    let mut basic_batches = vec![];
+    while let Some((l1_batch_number, _)) = fri_witness_generator_dal
+        .get_next_basic_circuit_witness_job(&range)
+        .await
+    {
+        basic_batches.push(l1_batch_number.0);
+    }
+    assert_eq!(basic_batches, [2, 3]);
+
+    let leaf_jobs = fri_witness_generator_dal
+        .get_next_leaf_aggregation_jobs(10, &range, None)
+        .await;
+    let leaf_batches: Vec<_> = leaf_jobs.iter().map(|job| job.block_number.0).collect();
+    assert_eq!(leaf_batches, [2, 3]);
+    let node_job = fri_witness_generator_dal
+        .get_next_node_aggregation_job(&range, None)
+        .await
+        .unwrap();
+    assert_eq!(node_job.block_number, L1BatchNumber(2));
+    let node_jobs = fri_witness_generator_dal
+        .get_next_node_aggregation_jobs(10, &range, None)
+        .await;
+    let node_batches: Vec<_> = node_jobs.iter().map(|job| job.block_number.0).collect();
+    assert_eq!(node_batches, [3]);
+
+    let peeked_job = fri_witness_generator_dal
+        .peek_next_scheduler_witness_job(&range, &[])
+        .await;
+    assert_eq!(peeked_job, Some((L1BatchNumber(2), None)));
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_batches(&[L1BatchNumber(4)], &[], &range)
+        .await;
+    assert!(lease.is_none());
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_batches(
+            &[L1BatchNumber(2), L1BatchNumber(4)],
+            &[],
+            &range,
+        )
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(2));
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_protocol_versions(&[], &range)
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(3));
+    assert!(fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_protocol_versions(&[], &range)
+        .await
+        .is_none());
+
+    // Jobs outside the range are left untouched and can be claimed by other instances.
+    for table in [
+        "witness_inputs_fri",
+        "leaf_aggregation_witness_jobs_fri",
+        "node_aggregation_witness_jobs_fri",
+        "scheduler_witness_jobs_fri",
+    ] {
+        let (queued_batches,): (Vec<i64>,) = sqlx::query_as(&format!(
+            "SELECT COALESCE(array_agg(l1_batch_number ORDER BY l1_batch_number), '{{}}') \
+             FROM {} WHERE status = 'queued' AND attempts = 0",
+            table
+        ))
+        .fetch_one(fri_witness_generator_dal.storage.conn())
+        .await
+        .unwrap();
+        assert_eq!(queued_batches, [1, 4], "{}", table);
+    }
+    let lease = fri_witness_generator_dal
+        .get_next_scheduler_witness_job_for_protocol_versions(&[], &all_l1_batches())
+        .await
+        .unwrap();
+    assert_eq!(lease.l1_batch_number, L1BatchNumber(1));
+}
+
+#[db_test(dal_crate)]
+async fn filtered_pickups_only_claim_jobs_for_specified_circuits(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut fri_witness_generator_dal = storage.fri_witness_generator_dal();
+    let closed_form_inputs: Vec<_> = (1..=3)
+        .map(|circuit_id| {
+            (
+                circuit_id,
+                format!("closed_form_inputs_{}.bin", circuit_id),
+                1,
+            )
+        })
+        .collect();
+    fri_witness_generator_dal
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &closed_form_inputs,
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id + 2,
+            None,
+        )
+        .await;
+    for table in [
+        "leaf_aggregation_witness_jobs_fri",
+        "node_aggregation_witness_jobs_fri",
+    ] {
+        sqlx::query(&format!("UPDATE {} SET status = 'queued'", table))
+            .execute(fri_witness_generator_dal.storage.conn())
+            .await
+            .unwrap();
+    }
+
+    let leaf_job = fri_witness_generator_dal
+        .get_next_leaf_aggregation_job(&all_l1_batches(), Some(&[2, 3]))
+        .await
+        .unwrap();
+    assert_eq!(leaf_job.circuit_id, 2);
+    let leaf_jobs = fri_witness_generator_dal
+        .get_next_leaf_aggregation_jobs(10, &all_l1_batches(), Some(&[1, 2]))
+        .await;
+    let circuit_ids: Vec<_> = leaf_jobs.iter().map(|job| job.circuit_id).collect();
+    assert_eq!(circuit_ids, [1]);
+
+    let node_job = fri_witness_generator_dal
+        .get_next_node_aggregation_job(&all_l1_batches(), Some(&[5]))
+        .await
+        .unwrap();
+    assert_eq!(node_job.circuit_id, 5);
+    assert!(fri_witness_generator_dal
+        .get_next_node_aggregation_job(&all_l1_batches(), Some(&[1, 5]))
+        .await
+        .is_none());
+    let node_jobs = fri_witness_generator_dal
+        .get_next_node_aggregation_jobs(10, &all_l1_batches(), None)
+        .await;
+    let circuit_ids: Vec<_> = node_jobs.iter().map(|job| job.circuit_id).collect();
+    assert_eq!(circuit_ids, [3, 4]);
+}
+
+#[db_test(dal_crate)]
+async fn partially_filled_dependency_tracker_row(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    storage
+        .fri_witness_generator_dal()
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &Vec::new(),
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    let mut tracker_dal = storage.fri_scheduler_dependency_tracker_dal();
+    assert!(tracker_dal
+        .get_final_prover_job_id_states(L1BatchNumber(2))
+        .await
+        .is_none());
+
+    for circuit_id in 1..=13 {
+        if circuit_id != 4 && circuit_id != 11 {
+            tracker_dal
+                .set_final_prover_job_id_for_l1_batch(
+                    circuit_id,
+                    u32::from(circuit_id) * 10,
+                    L1BatchNumber(1),
+                )
+                .await;
+        }
+    }
+    let job_ids = tracker_dal
+        .get_final_prover_job_id_states(L1BatchNumber(1))
+        .await
+        .unwrap();
+    for (i, &(circuit_id, job_id)) in job_ids.iter().enumerate() {
+        assert_eq!(usize::from(circuit_id), i + 1);
+        let circuit_id = u32::from(circuit_id);
+        if circuit_id == 4 || circuit_id == 11 {
+            assert_eq!(job_id, None, "circuit {}", circuit_id);
+        } else {
+            assert_eq!(job_id, Some(circuit_id * 10), "circuit {}", circuit_id);
+        }
+    }
+    assert!(tracker_dal
+        .try_get_final_prover_job_ids_for(L1BatchNumber(1))
+        .await
+        .is_none());
+
+    for circuit_id in [4, 11] {
+        tracker_dal
+            .set_final_prover_job_id_for_l1_batch(circuit_id, 0, L1BatchNumber(1))
+            .await;
+    }
+    let job_ids = tracker_dal
+        .try_get_final_prover_job_ids_for(L1BatchNumber(1))
+        .await
+        .unwrap();
+    assert_eq!(job_ids[3], (4, 0));
+    assert_eq!(job_ids[10], (11, 0));
+    assert_eq!(job_ids[12], (13, 130));
+}
+
+#[db_test(dal_crate)]
+async fn new_prover_job_notifications_are_sent_in_transaction(connection_pool: ConnectionPool) {
+    // Test pools never commit transactions, so notifications cannot be received.
+    assert!(NewProverJobsListener::new(&connection_pool)
+        .await
+        .unwrap()
+        .is_none());
+
+    let storage = &mut connection_pool.access_test_storage().await;
+    let mut transaction = storage.start_transaction().await;
+    transaction
+        .fri_prover_jobs_dal()
+        .try_notify_new_prover_job(NewProverJobNotification {
+            l1_batch_number: L1BatchNumber(1),
+            circuit_id: 1,
+            aggregation_round: AggregationRound::Scheduler,
+        })
+        .await
+        .unwrap();
+    transaction.commit().await;
+}
+
+fn get_default_prover_jobs_params(l1_batch_number: L1BatchNumber) -> GetProverJobsParams {
+    GetProverJobsParams {
+        statuses: None,
+        blocks: Some(std::ops::Range {
+            start: l1_batch_number,
+            end: l1_batch_number + 1,
+        }),
+        limit: None,
+        desc: false,
+        round: None,
+    }
+}
+
+fn get_sample_proof() -> Vec<u8> {
+    let zksync_home = std::env::var("ZKSYNC_HOME").unwrap_or_else(|_| ".".into());
+    fs::read(format!("{}/etc/prover-test-data/proof.bin", zksync_home))
+        .expect("Failed reading test proof file")
+}
+
+#[db_test(dal_crate)]
+async fn blob_sizes_are_summed_per_round_and_day(connection_pool: ConnectionPool) {
+    let storage = &mut connection_pool.access_test_storage().await;
+    storage
+        .fri_witness_generator_dal()
+        .create_aggregation_jobs(
+            L1BatchNumber(1),
+            &Vec::new(),
+            "scheduler_partial_input_1.bin",
+            |circuit_id| circuit_id,
+            None,
+        )
+        .await;
+    storage
+        .fri_witness_generator_dal()
+        .set_scheduler_partial_input_blob_size(L1BatchNumber(1), 100)
+        .await;
+
+    let mut prover_dal = storage.fri_prover_jobs_dal();
+    let jobs = [
+        (AggregationRound::BasicCircuits, 0, Some(10)),
+        (AggregationRound::BasicCircuits, 1, Some(20)),
+        (AggregationRound::BasicCircuits, 2, None),
+        (AggregationRound::Scheduler, 0, Some(50)),
+    ];
+    for (aggregation_round, sequence_number, blob_size) in jobs {
+        prover_dal
+            .insert_prover_job(
+                L1BatchNumber(1),
+                1,
+                0,
+                sequence_number,
+                aggregation_round,
+                &format!("circuit_{:?}_{}.bin", aggregation_round, sequence_number),
+                false,
+            )
+            .await;
+        if let Some(blob_size) = blob_size {
+            prover_dal
+                .try_set_circuit_blob_size(
+                    L1BatchNumber(1),
+                    1,
+                    0,
+                    sequence_number,
+                    aggregation_round,
+                    blob_size,
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    let sizes = prover_dal
+        .get_daily_blob_sizes(Duration::from_secs(86_400))
+        .await;
+    assert_eq!(sizes.len(), 2, "{:?}", sizes);
+    assert_eq!(sizes[0].day, sizes[1].day);
+    assert_eq!(sizes[0].aggregation_round, AggregationRound::BasicCircuits);
+    assert_eq!(sizes[0].circuit_blobs_size, 30);
+    assert_eq!(sizes[0].input_blobs_size, 0);
+    assert_eq!(sizes[1].aggregation_round, AggregationRound::Scheduler);
+    assert_eq!(sizes[1].circuit_blobs_size, 50);
+    assert_eq!(sizes[1].input_blobs_size, 100);
+}
+
+#[db_test(dal_crate)]
+async fn pruned_blobs_are_logged(connection_pool: ConnectionPool) {
+    let mut storage = connection_pool.access_test_storage().await;
+    let blobs = vec![
+        PrunedBlob {
+            bucket: "prover_jobs_fri".to_owned(),
+            key: "1_0_1_BasicCircuits_0.bin".to_owned(),
+            size_bytes: 1_024,
+        },
+        PrunedBlob {
+            bucket: "proofs_fri".to_owned(),
+            key: "proof_1.bin".to_owned(),
+            size_bytes: 256,
+        },
+    ];
+    storage
+        .pruned_blobs_log_dal()
+        .insert_pruned_blobs(L1BatchNumber(1), &blobs)
+        .await;
+
+    let logged_blobs = storage
+        .pruned_blobs_log_dal()
+        .get_pruned_blobs(L1BatchNumber(1))
+        .await;
+    assert_eq!(logged_blobs, blobs);
+    let logged_blobs = storage
+        .pruned_blobs_log_dal()
+        .get_pruned_blobs(L1BatchNumber(2))
+        .await;
+    assert!(logged_blobs.is_empty());
+
+    assert!(
+        storage
+            .pruned_blobs_log_dal()
+            .is_blob_pruned(L1BatchNumber(1), "proofs_fri", "proof_1.bin")
+            .await
+    );
+    assert!(
+        !storage
+            .pruned_blobs_log_dal()
+            .is_blob_pruned(L1BatchNumber(2), "proofs_fri", "proof_1.bin")
+            .await
+    );
 }
@@ -0,0 +1,110 @@
+//! Compares peak memory usage of buffered and streamed object store operations. Peak RSS
+//! is a per-process metric, so each mode should be run in a separate process:
+//!
+//! ```text
+//! cargo run --release -p zksync_object_store --example streaming_memory -- buffered 500
+//! cargo run --release -p zksync_object_store --example streaming_memory -- streamed 500
+//! ```
+//!
+//! The first argument is the mode, and the second one is the object size in MiB. The example
+//! only works on Linux, since it reads peak RSS from `/proc/self/status`.
+
+use tempdir::TempDir;
+
+use std::{env, error::Error, fs, io, sync::Arc, time::Instant};
+
+use zksync_config::{configs::object_store::ObjectStoreMode, ObjectStoreConfig};
+use zksync_object_store::{bincode, Bucket, ObjectStoreFactory, StoredObject};
+
+type BoxedError = Box<dyn Error + Send + Sync>;
+
+/// Object serialized with `bincode`, similar to witness blobs.
+struct LargeObject(Vec<u64>);
+
+impl StoredObject for LargeObject {
+    const BUCKET: Bucket = Bucket::WitnessInput;
+    type Key<'a> = u32;
+
+    fn encode_key(key: Self::Key<'_>) -> String {
+        format!("large_object_{key}.bin")
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
+        bincode::serialize(&self.0).map_err(From::from)
+    }
+
+    fn deserialize(bytes: Vec<u8>) -> Result<Self, BoxedError> {
+        bincode::deserialize(&bytes).map(Self).map_err(From::from)
+    }
+
+    fn serialize_into(&self, writer: &mut dyn io::Write) -> Result<(), BoxedError> {
+        bincode::serialize_into(writer, &self.0).map_err(From::from)
+    }
+
+    fn deserialize_from(reader: &mut dyn io::Read) -> Result<Self, BoxedError> {
+        bincode::deserialize_from(reader)
+            .map(Self)
+            .map_err(From::from)
+    }
+}
+
+/// Returns the peak resident set size of the process in KiB.
+fn peak_rss_kib() -> u64 {
+    let status = fs::read_to_string("/proc/self/status").expect("failed reading process status");
+    let line = status
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))
+        .expect("no peak RSS in process status");
+    line.trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .expect("invalid peak RSS")
+}
+
+#[tokio::main]
+async fn main() {
+    let mut args = env::args().skip(1);
+    let mode = args.next().unwrap_or_else(|| "streamed".to_owned());
+    let size_mib: usize = args
+        .next()
+        .map_or(500, |size| size.parse().expect("invalid size"));
+
+    let dir = TempDir::new("streaming-memory").unwrap();
+    let mut config = ObjectStoreConfig::new(ObjectStoreMode::FileBacked, "");
+    config.file_backed_base_path = dir.path().to_str().unwrap().to_owned();
+    let store = ObjectStoreFactory::new(config)
+        .create_store()
+        .await
+        .unwrap();
+    let object = LargeObject((0..(size_mib << 17) as u64).collect());
+    let baseline_rss = peak_rss_kib();
+    println!(
+        "Peak RSS with the object created: {} MiB",
+        baseline_rss >> 10
+    );
+
+    let started_at = Instant::now();
+    let object = match mode.as_str() {
+        "buffered" => {
+            store.put(1, &object).await.unwrap();
+            drop(object);
+            store.get::<LargeObject>(1).await.unwrap()
+        }
+        "streamed" => {
+            store.put_streamed(1, Arc::new(object)).await.unwrap();
+            store.get_streamed::<LargeObject>(1).await.unwrap()
+        }
+        _ => panic!("unknown mode `{mode}`; use `buffered` or `streamed`"),
+    };
+    assert_eq!(object.0.len(), size_mib << 17);
+
+    let peak_rss = peak_rss_kib();
+    println!(
+        "Mode `{mode}`: put + get took {:?}, peak RSS {} MiB (+{} MiB)",
+        started_at.elapsed(),
+        peak_rss >> 10,
+        (peak_rss - baseline_rss) >> 10
+    );
+}
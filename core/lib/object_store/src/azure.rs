@@ -0,0 +1,361 @@
+//! Azure Blob Storage-based [`ObjectStore`] implementation.
+
+use async_trait::async_trait;
+use azure_core::{
+    error::{Error as AzureError, ErrorKind},
+    prelude::NextMarker,
+    RetryOptions,
+};
+use azure_identity::DefaultAzureCredential;
+use azure_storage::{ConnectionString, StorageCredentials};
+use azure_storage_blobs::{
+    blob::{BlobBlockType, BlockList},
+    prelude::{BlobClient, BlockId, ClientBuilder, ContainerClient},
+};
+use bytes::Bytes;
+use futures::StreamExt;
+
+use std::{fmt, future::IntoFuture, sync::Arc, time::Instant};
+
+use crate::{
+    raw::{Bucket, ObjectMeta, ObjectStore, ObjectStoreError},
+    retries::{is_transient_status_code, RetryPolicy},
+};
+
+/// Blobs larger than this size are uploaded block by block rather than with a single request.
+const MAX_SINGLE_UPLOAD_SIZE: usize = 64 * 1_024 * 1_024;
+/// Size of blocks for block-by-block uploads.
+const BLOCK_SIZE: usize = 32 * 1_024 * 1_024;
+
+/// Credentials for Azure Blob Storage.
+#[derive(Debug)]
+pub(crate) enum AzureBlobCredentials {
+    /// Connection string containing the account name and the account key or a SAS token.
+    ConnectionString(String),
+    /// SAS token for the specified storage account.
+    SasToken { account: String, token: String },
+    /// Managed identity (or other credentials discoverable from the environment)
+    /// for the specified storage account.
+    ManagedIdentity { account: String },
+}
+
+/// Store keeping objects in a separate container for each [`Bucket`]. Keys of the objects are
+/// the same as in the GCS store, so blobs migrated from GCS remain readable.
+pub struct AzureBlobStorage {
+    container_prefix: String,
+    retry_policy: RetryPolicy,
+    client_builder: ClientBuilder,
+}
+
+impl fmt::Debug for AzureBlobStorage {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("AzureBlobStorage")
+            .field("container_prefix", &self.container_prefix)
+            .field("retry_policy", &self.retry_policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AzureBlobStorage {
+    pub(crate) fn new(
+        credentials: AzureBlobCredentials,
+        container_prefix: String,
+        retry_policy: RetryPolicy,
+    ) -> Result<Self, ObjectStoreError> {
+        let (account, credentials) = match credentials {
+            AzureBlobCredentials::ConnectionString(connection_string) => {
+                let connection_string = ConnectionString::new(&connection_string)
+                    .map_err(|err| config_error("failed parsing Azure connection string", err))?;
+                let account = connection_string
+                    .account_name
+                    .ok_or_else(|| {
+                        let message = "Azure connection string doesn't specify account name";
+                        ObjectStoreError::Other(message.into())
+                    })?
+                    .to_owned();
+                let credentials = connection_string.storage_credentials().map_err(|err| {
+                    config_error("Azure connection string doesn't specify credentials", err)
+                })?;
+                (account, credentials)
+            }
+            AzureBlobCredentials::SasToken { account, token } => {
+                let credentials = StorageCredentials::sas_token(token)
+                    .map_err(|err| config_error("failed parsing Azure SAS token", err))?;
+                (account, credentials)
+            }
+            AzureBlobCredentials::ManagedIdentity { account } => {
+                let credential = Arc::new(DefaultAzureCredential::default());
+                (account, StorageCredentials::token_credential(credential))
+            }
+        };
+        // Requests are retried in the same way as for other stores.
+        let client_builder = ClientBuilder::new(account, credentials).retry(RetryOptions::none());
+
+        Ok(Self {
+            container_prefix,
+            retry_policy,
+            client_builder,
+        })
+    }
+
+    /// Container names may only contain lowercase letters, digits and hyphens.
+    fn container_name(&self, bucket: Bucket) -> String {
+        let bucket = bucket.as_str().replace('_', "-");
+        if self.container_prefix.is_empty() {
+            bucket
+        } else {
+            format!("{}-{bucket}", self.container_prefix)
+        }
+    }
+
+    fn container_client(&self, bucket: Bucket) -> ContainerClient {
+        self.client_builder
+            .clone()
+            .container_client(self.container_name(bucket))
+    }
+
+    fn blob_client(&self, bucket: Bucket, key: &str) -> BlobClient {
+        self.container_client(bucket).blob_client(key)
+    }
+
+    async fn put_in_blocks(
+        &self,
+        bucket: Bucket,
+        blob: &BlobClient,
+        value: Bytes,
+    ) -> Result<(), ObjectStoreError> {
+        let mut block_list = BlockList::default();
+        for (i, offset) in (0..value.len()).step_by(BLOCK_SIZE).enumerate() {
+            let block = value.slice(offset..value.len().min(offset + BLOCK_SIZE));
+            // All block IDs of a blob must have the same length.
+            let block_id = BlockId::new(format!("{i:08}"));
+            self.retry_policy
+                .retry("put_block", Some(bucket), is_transient_error, || {
+                    blob.put_block(block_id.clone(), block.clone())
+                        .into_future()
+                })
+                .await?;
+            block_list
+                .blocks
+                .push(BlobBlockType::new_uncommitted(block_id));
+        }
+        self.retry_policy
+            .retry("put_block_list", Some(bucket), is_transient_error, || {
+                blob.put_block_list(block_list.clone()).into_future()
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+fn config_error(message: &str, err: AzureError) -> ObjectStoreError {
+    ObjectStoreError::Other(format!("{message}: {err}").into())
+}
+
+/// Returns `true` if the request may succeed if retried. Similarly to the GCS store, only requests
+/// that were rate-limited, timed out or failed because of a server or network error are retried.
+fn is_transient_error(err: &AzureError) -> bool {
+    err.as_http_error()
+        .map_or(true, |err| is_transient_status_code(err.status() as u16))
+}
+
+impl From<AzureError> for ObjectStoreError {
+    fn from(err: AzureError) -> Self {
+        match err.kind() {
+            ErrorKind::HttpResponse { status, .. } => {
+                let code = *status as u16;
+                ObjectStoreError::from_status_code(code, err)
+            }
+            ErrorKind::Io => ObjectStoreError::Transport(err.into()),
+            ErrorKind::Credential => ObjectStoreError::Auth(err.into()),
+            _ => ObjectStoreError::Other(err.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for AzureBlobStorage {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let started_at = Instant::now();
+        let blob = self.blob_client(bucket, key);
+        vlog::trace!(
+            "Fetching data from Azure for key {key} from container {}",
+            self.container_name(bucket)
+        );
+
+        let value = self
+            .retry_policy
+            .retry("get", Some(bucket), is_transient_error, || {
+                blob.get_content()
+            })
+            .await;
+
+        vlog::trace!(
+            "Fetched data from Azure for key {key} from bucket {bucket} and it took: {:?}",
+            started_at.elapsed()
+        );
+        metrics::histogram!(
+            "server.object_store.fetching_time",
+            started_at.elapsed(),
+            "bucket" => bucket.as_str()
+        );
+        value
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        let started_at = Instant::now();
+        let blob = self.blob_client(bucket, key);
+        vlog::trace!(
+            "Storing data to Azure for key {key} from container {}",
+            self.container_name(bucket)
+        );
+
+        let value = Bytes::from(value);
+        let result = if value.len() > MAX_SINGLE_UPLOAD_SIZE {
+            self.put_in_blocks(bucket, &blob, value).await
+        } else {
+            self.retry_policy
+                .retry("put", Some(bucket), is_transient_error, || {
+                    blob.put_block_blob(value.clone()).into_future()
+                })
+                .await
+                .map(drop)
+        };
+
+        vlog::trace!(
+            "Stored data to Azure for key {key} from bucket {bucket} and it took: {:?}",
+            started_at.elapsed()
+        );
+        metrics::histogram!(
+            "server.object_store.storing_time",
+            started_at.elapsed(),
+            "bucket" => bucket.as_str()
+        );
+        result
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        let blob = self.blob_client(bucket, key);
+        vlog::trace!(
+            "Removing data from Azure for key {key} from container {}",
+            self.container_name(bucket)
+        );
+
+        self.retry_policy
+            .retry("remove", Some(bucket), is_transient_error, || {
+                blob.delete().into_future()
+            })
+            .await
+            .map(drop)
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        let blob = self.blob_client(bucket, key);
+        let response = self
+            .retry_policy
+            .retry("get_size", Some(bucket), is_transient_error, || {
+                blob.get_properties().into_future()
+            })
+            .await?;
+        Ok(response.blob.properties.content_length)
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        let container = self.container_client(bucket);
+        let page = self
+            .retry_policy
+            .retry("list", Some(bucket), is_transient_error, || {
+                let mut request = container.list_blobs().prefix(prefix.to_owned());
+                if let Some(token) = &continuation_token {
+                    request = request.marker(NextMarker::new(token.clone()));
+                }
+                // The stream yields pages lazily, so only the first page is requested.
+                async move { request.into_stream().next().await.transpose() }
+            })
+            .await?;
+        let Some(page) = page else {
+            return Ok((vec![], None));
+        };
+
+        let objects = page.blobs.blobs().map(|blob| ObjectMeta {
+            key: blob.name.clone(),
+            size: blob.properties.content_length,
+            last_modified: Some(blob.properties.last_modified.into()),
+        });
+        let next_token = page.next_marker.map(|marker| marker.as_str().to_owned());
+        Ok((objects.collect(), next_token))
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        let container = self.container_client(bucket);
+        let result = self
+            .retry_policy
+            .retry("health_check", Some(bucket), is_transient_error, || {
+                container.get_properties().into_future()
+            })
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(ObjectStoreError::KeyNotFound(_)) => {
+                let message = format!(
+                    "Azure container `{}` does not exist",
+                    self.container_name(bucket)
+                );
+                Err(ObjectStoreError::Other(message.into()))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn container_names_are_valid() {
+        let store = AzureBlobStorage::new(
+            AzureBlobCredentials::SasToken {
+                account: "account".to_owned(),
+                token: "sv=2022-11-02&sig=signature".to_owned(),
+            },
+            "zksync".to_owned(),
+            RetryPolicy {
+                max_retries: 0,
+                base_delay: Duration::ZERO,
+                max_delay: Duration::ZERO,
+                jitter: false,
+                request_timeout: Duration::from_secs(60),
+                operation_timeout: Duration::from_secs(300),
+                circuit_breaker: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            store.container_name(Bucket::ProverJobsFri),
+            "zksync-prover-jobs-fri"
+        );
+
+        let store = AzureBlobStorage {
+            container_prefix: String::new(),
+            ..store
+        };
+        assert_eq!(
+            store.container_name(Bucket::LeafAggregationWitnessJobsFri),
+            "leaf-aggregation-witness-jobs-fri"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Bundles of final node proofs for an L1 batch, allowing to fetch all inputs of a scheduler job
+//! in a single request.
+
+use futures::{stream, StreamExt, TryStreamExt};
+use zksync_types::L1BatchNumber;
+
+use std::ops;
+
+use crate::{
+    objects::StoredObject,
+    raw::{BoxedError, Bucket, ObjectStore, ObjectStoreError},
+    versioning::deserialize_versioned,
+};
+
+/// Magic prepended to serialized bundles. It's followed by the number of bundled proofs
+/// (4 bytes, little-endian), the index and the concatenated proofs.
+const MAGIC: &[u8] = b"ZKB\x01";
+/// Length of an index entry: circuit ID (1 byte), prover job ID (4 bytes, little-endian)
+/// and proof length (8 bytes, little-endian).
+const INDEX_ENTRY_LEN: usize = 13;
+
+/// Returns the key of the proof produced by the prover job with the specified ID.
+/// Must match the key of the FRI proof wrapper in witness generators.
+pub fn proof_key(job_id: u32) -> String {
+    format!("proof_{job_id}.bin")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct BundledProof {
+    circuit_id: u8,
+    job_id: u32,
+    range: ops::Range<usize>,
+}
+
+/// Archive of the final node proofs for an L1 batch keyed by the circuit ID. Proofs are bundled
+/// as they are stored in the [`Bucket::ProofsFri`] bucket, i.e. without deserializing them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProofBundle {
+    index: Vec<BundledProof>,
+    proofs: Vec<u8>,
+}
+
+impl ProofBundle {
+    /// Fetches proofs for the specified `(circuit_id, job_id)` pairs, with at most `concurrency`
+    /// proofs being fetched at a time, and bundles them in the order of `proof_ids`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the proofs fails to be fetched.
+    pub async fn fetch(
+        object_store: &dyn ObjectStore,
+        proof_ids: &[(u8, u32)],
+        concurrency: usize,
+    ) -> Result<Self, ObjectStoreError> {
+        let fetches = stream::iter(proof_ids).map(|&(circuit_id, job_id)| async move {
+            let proof = object_store
+                .get_raw(Bucket::ProofsFri, &proof_key(job_id))
+                .await?;
+            Ok::<_, ObjectStoreError>((circuit_id, job_id, proof))
+        });
+        let mut fetches = fetches.buffered(concurrency.max(1));
+        let mut bundle = Self::default();
+        while let Some((circuit_id, job_id, proof)) = fetches.try_next().await? {
+            bundle.push(circuit_id, job_id, &proof);
+        }
+        Ok(bundle)
+    }
+
+    /// Adds a serialized proof produced by the prover job with the specified ID.
+    pub fn push(&mut self, circuit_id: u8, job_id: u32, proof: &[u8]) {
+        let start = self.proofs.len();
+        self.proofs.extend_from_slice(proof);
+        self.index.push(BundledProof {
+            circuit_id,
+            job_id,
+            range: start..self.proofs.len(),
+        });
+    }
+
+    /// Returns the number of bundled proofs.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Checks whether the bundle is empty.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns IDs of the prover jobs that have produced the bundled proofs, in the bundle order.
+    pub fn job_ids(&self) -> Vec<u32> {
+        self.index.iter().map(|proof| proof.job_id).collect()
+    }
+
+    /// Returns the ID of the prover job and the serialized proof for the specified circuit.
+    pub fn get(&self, circuit_id: u8) -> Option<(u32, &[u8])> {
+        let proof = self
+            .index
+            .iter()
+            .find(|proof| proof.circuit_id == circuit_id)?;
+        Some((proof.job_id, &self.proofs[proof.range.clone()]))
+    }
+
+    /// Deserializes all bundled proofs in the bundle order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the proofs fails to be deserialized.
+    pub fn deserialize_proofs<V: StoredObject>(&self) -> Result<Vec<V>, ObjectStoreError> {
+        self.index
+            .iter()
+            .map(|proof| deserialize_versioned(self.proofs[proof.range.clone()].to_vec()))
+            .collect()
+    }
+}
+
+impl StoredObject for ProofBundle {
+    const BUCKET: Bucket = Bucket::ProofsFri;
+    type Key<'a> = L1BatchNumber;
+
+    fn encode_key(key: Self::Key<'_>) -> String {
+        format!("node_proofs_bundle_{key}.bin")
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
+        let index_len = MAGIC.len() + 4 + self.index.len() * INDEX_ENTRY_LEN;
+        let mut bytes = Vec::with_capacity(index_len + self.proofs.len());
+        bytes.extend_from_slice(MAGIC);
+        let len = u32::try_from(self.index.len())?;
+        bytes.extend_from_slice(&len.to_le_bytes());
+        for proof in &self.index {
+            bytes.push(proof.circuit_id);
+            bytes.extend_from_slice(&proof.job_id.to_le_bytes());
+            bytes.extend_from_slice(&(proof.range.len() as u64).to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.proofs);
+        Ok(bytes)
+    }
+
+    fn deserialize(mut bytes: Vec<u8>) -> Result<Self, BoxedError> {
+        let header = bytes
+            .get(..MAGIC.len() + 4)
+            .ok_or("truncated proof bundle")?;
+        let len = header
+            .strip_prefix(MAGIC)
+            .ok_or("invalid proof bundle header")?;
+        let len = u32::from_le_bytes(len.try_into()?) as usize;
+        let index_end = len
+            .checked_mul(INDEX_ENTRY_LEN)
+            .and_then(|index_len| index_len.checked_add(header.len()))
+            .ok_or("invalid proof bundle length")?;
+        let index_bytes = bytes
+            .get(header.len()..index_end)
+            .ok_or("truncated proof bundle index")?;
+
+        let mut index = Vec::with_capacity(len);
+        let mut start = 0_usize;
+        for entry in index_bytes.chunks_exact(INDEX_ENTRY_LEN) {
+            let job_id = u32::from_le_bytes(entry[1..5].try_into()?);
+            let proof_len = usize::try_from(u64::from_le_bytes(entry[5..].try_into()?))?;
+            let end = start
+                .checked_add(proof_len)
+                .ok_or("invalid bundled proof length")?;
+            index.push(BundledProof {
+                circuit_id: entry[0],
+                job_id,
+                range: start..end,
+            });
+            start = end;
+        }
+        if start != bytes.len() - index_end {
+            return Err("bundled proofs do not match proof bundle index".into());
+        }
+        bytes.drain(..index_end);
+        Ok(Self {
+            index,
+            proofs: bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockStore;
+
+    #[test]
+    fn test_bundle_serialization_roundtrip() {
+        let mut bundle = ProofBundle::default();
+        bundle.push(1, 10, &[1, 2, 3]);
+        bundle.push(2, 11, &[]);
+        bundle.push(3, 12, &[4; 100]);
+
+        let bytes = bundle.serialize().unwrap();
+        assert_eq!(bytes.len(), MAGIC.len() + 4 + 3 * INDEX_ENTRY_LEN + 103);
+        let restored = ProofBundle::deserialize(bytes.clone()).unwrap();
+        assert_eq!(restored, bundle);
+        assert_eq!(restored.job_ids(), [10, 11, 12]);
+        assert_eq!(restored.get(1), Some((10, &[1, 2, 3][..])));
+        assert_eq!(restored.get(2), Some((11, &[][..])));
+        assert_eq!(restored.get(4), None);
+
+        let err = ProofBundle::deserialize(bytes[..bytes.len() - 1].to_vec()).unwrap_err();
+        assert!(err.to_string().contains("do not match"), "{err}");
+        let err = ProofBundle::deserialize(bytes[..10].to_vec()).unwrap_err();
+        assert!(err.to_string().contains("truncated"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_bundled_proofs_are_fetched_and_deserialized() {
+        let store = MockStore::default();
+        for job_id in [5_u32, 7] {
+            let proof = bincode::serialize(&job_id).unwrap();
+            store
+                .put_raw(Bucket::ProofsFri, &proof_key(job_id), proof)
+                .await
+                .unwrap();
+        }
+
+        let bundle = ProofBundle::fetch(&store, &[(2, 7), (1, 5)], 2)
+            .await
+            .unwrap();
+        assert_eq!(bundle.job_ids(), [7, 5]);
+        let proofs: Vec<TestProof> = bundle.deserialize_proofs().unwrap();
+        assert_eq!(proofs, [TestProof(7), TestProof(5)]);
+
+        let err = ProofBundle::fetch(&store, &[(1, 5), (2, 8)], 2)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+    }
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct TestProof(u32);
+
+    crate::impl_stored_object!(TestProof {
+        bucket: ProofsFri,
+        key: u32 as job_id,
+        key_format: "proof_{job_id}.bin",
+        codec: bincode,
+    });
+}
@@ -0,0 +1,380 @@
+//! Read-through in-memory caching of blobs.
+
+use async_trait::async_trait;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use crate::raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome};
+
+type CacheKey = (Bucket, String);
+
+#[derive(Debug)]
+struct CacheEntry {
+    value: Vec<u8>,
+    last_used_at: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Cache keys ordered by the logical time of the last access.
+    lru: BTreeMap<u64, CacheKey>,
+    total_bytes: usize,
+    clock: u64,
+    /// Incremented on every change of the cached keys. A value fetched from the store is only
+    /// cached if the generation hasn't changed since the fetch has started, so that a concurrent
+    /// `put()` cannot be overwritten by a stale value.
+    generation: u64,
+}
+
+impl CacheState {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn get(&mut self, key: &CacheKey) -> Option<Vec<u8>> {
+        let now = self.tick();
+        let entry = self.entries.get_mut(key)?;
+        let key = self.lru.remove(&entry.last_used_at)?;
+        entry.last_used_at = now;
+        self.lru.insert(now, key);
+        Some(entry.value.clone())
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_used_at);
+            self.total_bytes -= entry.value.len();
+        }
+    }
+
+    /// Removes the `key` from the cache and returns the new generation.
+    fn invalidate(&mut self, key: &CacheKey) -> u64 {
+        self.remove(key);
+        self.generation += 1;
+        self.generation
+    }
+
+    fn insert(&mut self, key: CacheKey, value: Vec<u8>, max_entries: usize, max_bytes: usize) {
+        self.remove(&key);
+        if max_entries == 0 || value.len() > max_bytes {
+            return;
+        }
+
+        let now = self.tick();
+        self.total_bytes += value.len();
+        self.lru.insert(now, key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                last_used_at: now,
+            },
+        );
+        while self.entries.len() > max_entries || self.total_bytes > max_bytes {
+            let Some((_, evicted_key)) = self.lru.pop_first() else {
+                break;
+            };
+            let entry = self.entries.remove(&evicted_key).unwrap();
+            self.total_bytes -= entry.value.len();
+        }
+    }
+}
+
+/// Store wrapper caching fetched blobs in memory, with the least recently used blobs evicted
+/// once the cache exceeds its capacity. Blobs stored via the wrapper replace cached values,
+/// but changes made to the wrapped store bypassing the wrapper (e.g., by other processes)
+/// are not tracked; use [`Self::invalidate_prefix()`] to drop the affected blobs explicitly.
+///
+/// Streamed reads use cached blobs if possible, but blobs fetched as a stream are not cached.
+#[derive(Debug)]
+pub struct CachedObjectStore {
+    inner: Box<dyn ObjectStore>,
+    max_entries: usize,
+    max_bytes: usize,
+    state: Mutex<CacheState>,
+}
+
+impl CachedObjectStore {
+    pub(crate) const DEFAULT_MAX_ENTRIES: usize = 1_000;
+    pub(crate) const DEFAULT_MAX_BYTES: usize = 128 << 20;
+
+    /// Wraps the provided store. The cache holds at most `max_entries` blobs with the total size
+    /// of at most `max_bytes`; larger blobs are not cached.
+    pub fn new(inner: Box<dyn ObjectStore>, max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            inner,
+            max_entries,
+            max_bytes,
+            state: Mutex::default(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().expect("object store cache is poisoned")
+    }
+
+    /// Removes blobs with keys starting with `prefix` in the `bucket` from the cache.
+    pub fn invalidate_prefix(&self, bucket: Bucket, prefix: &str) {
+        let mut state = self.state();
+        let keys: Vec<_> = state
+            .entries
+            .keys()
+            .filter(|(key_bucket, key)| *key_bucket == bucket && key.starts_with(prefix))
+            .cloned()
+            .collect();
+        for key in &keys {
+            state.remove(key);
+        }
+        state.generation += 1;
+    }
+
+    fn report_access(bucket: Bucket, is_hit: bool) {
+        metrics::increment_counter!(
+            "server.object_store.cache_requests",
+            "bucket" => bucket.as_str(),
+            "kind" => if is_hit { "hit" } else { "miss" }
+        );
+    }
+}
+
+#[async_trait]
+impl ObjectStore for CachedObjectStore {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let cache_key = (bucket, key.to_owned());
+        let (cached_value, generation) = {
+            let mut state = self.state();
+            (state.get(&cache_key), state.generation)
+        };
+        Self::report_access(bucket, cached_value.is_some());
+        if let Some(value) = cached_value {
+            return Ok(value);
+        }
+
+        let value = self.inner.get_raw(bucket, key).await?;
+        if value.len() <= self.max_bytes {
+            let mut state = self.state();
+            if state.generation == generation {
+                state.insert(cache_key, value.clone(), self.max_entries, self.max_bytes);
+            }
+        }
+        Ok(value)
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        let cache_key = (bucket, key.to_owned());
+        let generation = self.state().invalidate(&cache_key);
+        if value.len() > self.max_bytes {
+            return self.inner.put_raw(bucket, key, value).await;
+        }
+
+        self.inner.put_raw(bucket, key, value.clone()).await?;
+        let mut state = self.state();
+        if state.generation == generation {
+            state.insert(cache_key, value, self.max_entries, self.max_bytes);
+        }
+        Ok(())
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        self.state().invalidate(&(bucket, key.to_owned()));
+        self.inner.remove_raw(bucket, key).await
+    }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        let cache_key = (bucket, key.to_owned());
+        let generation = self.state().invalidate(&cache_key);
+        if value.len() > self.max_bytes {
+            return self.inner.put_raw_if_absent(bucket, key, value).await;
+        }
+
+        let outcome = self
+            .inner
+            .put_raw_if_absent(bucket, key, value.clone())
+            .await?;
+        // If the value already exists, it's cached once it's fetched.
+        if outcome == PutOutcome::Created {
+            let mut state = self.state();
+            if state.generation == generation {
+                state.insert(cache_key, value, self.max_entries, self.max_bytes);
+            }
+        }
+        Ok(outcome)
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        self.inner.get_raw_size(bucket, key).await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        self.inner.exists_raw(bucket, key).await
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        self.inner
+            .list_raw(bucket, prefix, continuation_token)
+            .await
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        self.inner.health_check(bucket).await
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        self.inner.generate_download_url(bucket, key, expiry).await
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let cached_value = self.state().get(&(bucket, key.to_owned()));
+        Self::report_access(bucket, cached_value.is_some());
+        match cached_value {
+            Some(value) => Ok(Box::new(io::Cursor::new(value))),
+            None => self.inner.get_raw_stream(bucket, key).await,
+        }
+    }
+
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        self.state().invalidate(&(bucket, key.to_owned()));
+        self.inner.put_raw_stream(bucket, key, reader).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mock::MockStore;
+
+    async fn put_directly(store: &MockStore, key: &str, value: Vec<u8>) {
+        store.put_raw(Bucket::ProofsFri, key, value).await.unwrap();
+    }
+
+    fn is_cached(state: &CacheState, key: &str) -> bool {
+        state
+            .entries
+            .contains_key(&(Bucket::ProofsFri, key.to_owned()))
+    }
+
+    #[tokio::test]
+    async fn test_fetched_blobs_are_cached() {
+        let inner = Arc::new(MockStore::default());
+        put_directly(&inner, "vk.bin", vec![1, 2, 3]).await;
+        let store = CachedObjectStore::new(Box::new(Arc::clone(&inner)), 10, 1_024);
+        let blob = store.get_raw(Bucket::ProofsFri, "vk.bin").await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+
+        // The change bypasses the cache, so it's not visible until the blob is invalidated.
+        put_directly(&inner, "vk.bin", vec![4, 5]).await;
+        let blob = store.get_raw(Bucket::ProofsFri, "vk.bin").await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+
+        store.invalidate_prefix(Bucket::ProverJobsFri, "vk");
+        let blob = store.get_raw(Bucket::ProofsFri, "vk.bin").await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+        store.invalidate_prefix(Bucket::ProofsFri, "vk");
+        let blob = store.get_raw(Bucket::ProofsFri, "vk.bin").await.unwrap();
+        assert_eq!(blob, [4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_stored_blobs_replace_cached_ones() {
+        let inner = Arc::new(MockStore::default());
+        let store = CachedObjectStore::new(Box::new(Arc::clone(&inner)), 10, 1_024);
+        store
+            .put_raw(Bucket::ProofsFri, "vk.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+        let blob = store.get_raw(Bucket::ProofsFri, "vk.bin").await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+
+        store
+            .put_raw(Bucket::ProofsFri, "vk.bin", vec![4, 5])
+            .await
+            .unwrap();
+        let blob = store.get_raw(Bucket::ProofsFri, "vk.bin").await.unwrap();
+        assert_eq!(blob, [4, 5]);
+
+        let reader: BlobReader = Box::new(io::Cursor::new(vec![6]));
+        store
+            .put_raw_stream(Bucket::ProofsFri, "vk.bin", reader)
+            .await
+            .unwrap();
+        let blob = store.get_raw(Bucket::ProofsFri, "vk.bin").await.unwrap();
+        assert_eq!(blob, [6]);
+
+        store.remove_raw(Bucket::ProofsFri, "vk.bin").await.unwrap();
+        let err = store
+            .get_raw(Bucket::ProofsFri, "vk.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_blobs_are_evicted() {
+        let inner = Arc::new(MockStore::default());
+        for key in ["a", "b", "c"] {
+            put_directly(&inner, key, vec![0; 10]).await;
+        }
+        let store = CachedObjectStore::new(Box::new(Arc::clone(&inner)), 2, 25);
+        store.get_raw(Bucket::ProofsFri, "a").await.unwrap();
+        store.get_raw(Bucket::ProofsFri, "b").await.unwrap();
+        store.get_raw(Bucket::ProofsFri, "a").await.unwrap();
+        // Evicts "b" since it's used less recently than "a".
+        store.get_raw(Bucket::ProofsFri, "c").await.unwrap();
+        {
+            let state = store.state();
+            assert_eq!(state.entries.len(), 2);
+            assert_eq!(state.total_bytes, 20);
+            assert!(is_cached(&state, "a"));
+            assert!(is_cached(&state, "c"));
+        }
+
+        // Evicts both "a" and "c" to fit into the size limit.
+        put_directly(&inner, "d", vec![0; 20]).await;
+        store.get_raw(Bucket::ProofsFri, "d").await.unwrap();
+        // Too large to be cached.
+        put_directly(&inner, "e", vec![0; 30]).await;
+        store.get_raw(Bucket::ProofsFri, "e").await.unwrap();
+        let state = store.state();
+        assert_eq!(state.entries.len(), 1);
+        assert_eq!(state.total_bytes, 20);
+        assert!(is_cached(&state, "d"));
+    }
+}
@@ -0,0 +1,357 @@
+//! Integrity protection of blobs with SHA-256 checksums.
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+use std::{
+    io, mem,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use crate::{
+    raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome},
+    streams::{prepend, read_prefix},
+};
+
+/// Header prepended to blobs with a checksum. It's followed by the SHA-256 digest
+/// of the remaining blob contents.
+const HEADER: &[u8] = b"ZKH\x01";
+const CHECKSUM_LEN: usize = 32;
+
+/// Store wrapper storing blobs with a checksum and verifying checksums of fetched blobs.
+/// Blobs without a checksum are returned as is.
+#[derive(Debug)]
+pub(crate) struct ChecksummingObjectStore {
+    inner: Box<dyn ObjectStore>,
+    write_checksums: bool,
+}
+
+impl ChecksummingObjectStore {
+    pub fn new(inner: Box<dyn ObjectStore>, write_checksums: bool) -> Self {
+        Self {
+            inner,
+            write_checksums,
+        }
+    }
+
+    fn with_checksum(&self, value: Vec<u8>) -> Vec<u8> {
+        if !self.write_checksums {
+            return value;
+        }
+        let mut blob = Vec::with_capacity(HEADER.len() + CHECKSUM_LEN + value.len());
+        blob.extend_from_slice(HEADER);
+        blob.extend_from_slice(&Sha256::digest(&value));
+        blob.extend_from_slice(&value);
+        blob
+    }
+
+    fn verify(bucket: Bucket, key: &str, mut blob: Vec<u8>) -> Result<Vec<u8>, ObjectStoreError> {
+        let Some(contents) = blob.strip_prefix(HEADER) else {
+            metrics::counter!(
+                "server.object_store.unchecked_reads",
+                1,
+                "bucket" => bucket.as_str()
+            );
+            return Ok(blob);
+        };
+        if contents.len() < CHECKSUM_LEN {
+            let message = format!("blob {key} in bucket {bucket} is truncated");
+            return Err(ObjectStoreError::ChecksumMismatch(message.into()));
+        }
+        let (checksum, payload) = contents.split_at(CHECKSUM_LEN);
+        if Sha256::digest(payload).as_slice() != checksum {
+            metrics::counter!(
+                "server.object_store.checksum_mismatches",
+                1,
+                "bucket" => bucket.as_str()
+            );
+            let message = format!("blob {key} in bucket {bucket} is corrupted");
+            return Err(ObjectStoreError::ChecksumMismatch(message.into()));
+        }
+        blob.drain(..HEADER.len() + CHECKSUM_LEN);
+        Ok(blob)
+    }
+}
+
+/// Reader verifying the checksum of the read data once the inner reader is exhausted.
+struct VerifyingReader {
+    inner: BlobReader,
+    hasher: Sha256,
+    expected_checksum: Vec<u8>,
+    description: String,
+}
+
+impl AsyncRead for VerifyingReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled_len = buf.filled().len();
+        let has_capacity = buf.remaining() > 0;
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+
+        let new_data = &buf.filled()[filled_len..];
+        if !new_data.is_empty() {
+            this.hasher.update(new_data);
+        } else if has_capacity && !this.expected_checksum.is_empty() {
+            // The inner reader is exhausted.
+            let expected_checksum = mem::take(&mut this.expected_checksum);
+            if mem::take(&mut this.hasher).finalize().as_slice() != expected_checksum {
+                metrics::counter!("server.object_store.checksum_mismatches", 1);
+                let message = format!("{} is corrupted", this.description);
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, message)));
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[async_trait]
+impl ObjectStore for ChecksummingObjectStore {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let blob = self.inner.get_raw(bucket, key).await?;
+        Self::verify(bucket, key, blob)
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        let value = self.with_checksum(value);
+        self.inner.put_raw(bucket, key, value).await
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        self.inner.remove_raw(bucket, key).await
+    }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        let value = self.with_checksum(value);
+        self.inner.put_raw_if_absent(bucket, key, value).await
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        // The stored blob is measured, since it's what occupies the space in the wrapped store.
+        self.inner.get_raw_size(bucket, key).await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        self.inner.exists_raw(bucket, key).await
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        self.inner
+            .list_raw(bucket, prefix, continuation_token)
+            .await
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        self.inner.health_check(bucket).await
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        self.inner.generate_download_url(bucket, key, expiry).await
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let mut reader = self.inner.get_raw_stream(bucket, key).await?;
+        let prefix = read_prefix(&mut reader, HEADER.len() + CHECKSUM_LEN)
+            .await
+            .map_err(|err| ObjectStoreError::Transport(err.into()))?;
+        let Some(checksum) = prefix.strip_prefix(HEADER) else {
+            metrics::counter!(
+                "server.object_store.unchecked_reads",
+                1,
+                "bucket" => bucket.as_str()
+            );
+            return Ok(prepend(prefix, reader));
+        };
+        if checksum.len() < CHECKSUM_LEN {
+            let message = format!("blob {key} in bucket {bucket} is truncated");
+            return Err(ObjectStoreError::ChecksumMismatch(message.into()));
+        }
+        Ok(Box::new(VerifyingReader {
+            inner: reader,
+            hasher: Sha256::default(),
+            expected_checksum: checksum.to_vec(),
+            description: format!("blob {key} in bucket {bucket}"),
+        }))
+    }
+
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        mut reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        if !self.write_checksums {
+            return self.inner.put_raw_stream(bucket, key, reader).await;
+        }
+        // The checksum precedes the blob contents, so the contents need to be buffered.
+        let mut value = vec![];
+        reader
+            .read_to_end(&mut value)
+            .await
+            .map_err(|err| ObjectStoreError::Other(err.into()))?;
+        self.put_raw(bucket, key, value).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mock::MockStore;
+
+    #[tokio::test]
+    async fn test_checksummed_blobs_roundtrip() {
+        let inner = Arc::new(MockStore::default());
+        let store = ChecksummingObjectStore::new(Box::new(Arc::clone(&inner)), true);
+        store
+            .put_raw(Bucket::ProofsFri, "test.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        let stored = inner.get_raw(Bucket::ProofsFri, "test.bin").await.unwrap();
+        assert_eq!(stored.len(), HEADER.len() + CHECKSUM_LEN + 3);
+        let blob = store.get_raw(Bucket::ProofsFri, "test.bin").await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_blobs_without_checksums_are_readable() {
+        let inner = Arc::new(MockStore::default());
+        let store = ChecksummingObjectStore::new(Box::new(Arc::clone(&inner)), false);
+        store
+            .put_raw(Bucket::ProofsFri, "legacy.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        let stored = inner
+            .get_raw(Bucket::ProofsFri, "legacy.bin")
+            .await
+            .unwrap();
+        assert_eq!(stored, [1, 2, 3]);
+        let blob = store
+            .get_raw(Bucket::ProofsFri, "legacy.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_blobs_are_detected() {
+        let inner = Arc::new(MockStore::default());
+        let store = ChecksummingObjectStore::new(Box::new(Arc::clone(&inner)), true);
+        store
+            .put_raw(Bucket::ProofsFri, "test.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+        let mut stored = inner.get_raw(Bucket::ProofsFri, "test.bin").await.unwrap();
+        *stored.last_mut().unwrap() ^= 1;
+        inner
+            .put_raw(Bucket::ProofsFri, "test.bin", stored)
+            .await
+            .unwrap();
+
+        let err = store
+            .get_raw(Bucket::ProofsFri, "test.bin")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ObjectStoreError::ChecksumMismatch(_)),
+            "{err}"
+        );
+        assert!(err.is_transient());
+
+        inner
+            .put_raw(Bucket::ProofsFri, "test.bin", HEADER.to_vec())
+            .await
+            .unwrap();
+        let err = store
+            .get_raw(Bucket::ProofsFri, "test.bin")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ObjectStoreError::ChecksumMismatch(_)),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streamed_blobs_are_verified() {
+        let inner = Arc::new(MockStore::default());
+        let store = ChecksummingObjectStore::new(Box::new(Arc::clone(&inner)), true);
+        let reader: BlobReader = Box::new(io::Cursor::new(vec![1, 2, 3]));
+        store
+            .put_raw_stream(Bucket::ProofsFri, "test.bin", reader)
+            .await
+            .unwrap();
+
+        let mut reader = store
+            .get_raw_stream(Bucket::ProofsFri, "test.bin")
+            .await
+            .unwrap();
+        let mut blob = vec![];
+        reader.read_to_end(&mut blob).await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+
+        let mut stored = inner.get_raw(Bucket::ProofsFri, "test.bin").await.unwrap();
+        *stored.last_mut().unwrap() ^= 1;
+        inner
+            .put_raw(Bucket::ProofsFri, "test.bin", stored)
+            .await
+            .unwrap();
+        let mut reader = store
+            .get_raw_stream(Bucket::ProofsFri, "test.bin")
+            .await
+            .unwrap();
+        let err = reader.read_to_end(&mut vec![]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_blobs_without_checksums_are_readable() {
+        let inner = Arc::new(MockStore::default());
+        inner
+            .put_raw(Bucket::ProofsFri, "legacy.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        let store = ChecksummingObjectStore::new(Box::new(inner), true);
+        let mut reader = store
+            .get_raw_stream(Bucket::ProofsFri, "legacy.bin")
+            .await
+            .unwrap();
+        let mut blob = vec![];
+        reader.read_to_end(&mut blob).await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,263 @@
+//! Circuit breaker failing requests fast while the store is unavailable.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::raw::{Bucket, ObjectStoreError};
+
+/// State of the [`CircuitBreaker`] for a bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CircuitState {
+    /// Requests are performed as usual.
+    Closed,
+    /// Requests fail with [`ObjectStoreError::CircuitOpen`] without reaching the store.
+    Open,
+    /// The cool-down period has elapsed; a single probe request is allowed to check whether
+    /// the store has recovered.
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// Returns the value of the state reported as a gauge.
+    fn as_metric(self) -> f64 {
+        match self {
+            Self::Closed => 0.0,
+            Self::HalfOpen => 1.0,
+            Self::Open => 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BucketCircuit {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    is_probing: bool,
+}
+
+/// Circuit breaker tracking consecutive transport failures (including timeouts) of requests
+/// to each bucket. Once `failure_threshold` requests in a row have failed, the circuit opens,
+/// and requests fail fast for the `cooldown` period. After that, the circuit is half-open:
+/// a probe request is let through, and the circuit closes if it succeeds or opens again
+/// if it fails. Errors returned by a reachable store (e.g., missing objects) are not failures.
+///
+/// The state for each bucket is reported as the `server.object_store.circuit_state` gauge:
+/// 0 if the circuit is closed, 1 if it's half-open, and 2 if it's open.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    circuits: Mutex<HashMap<Bucket, BucketCircuit>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            circuits: Mutex::default(),
+        }
+    }
+
+    /// Returns the current state of the circuit for the `bucket`.
+    #[cfg(test)]
+    pub fn state(&self, bucket: Bucket) -> CircuitState {
+        let circuits = self.circuits.lock().expect("circuit breaker is poisoned");
+        circuits
+            .get(&bucket)
+            .map_or(CircuitState::Closed, |circuit| self.circuit_state(circuit))
+    }
+
+    fn circuit_state(&self, circuit: &BucketCircuit) -> CircuitState {
+        match circuit.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Starts a request attempt for the `bucket`. The outcome of the attempt must be reported
+    /// with [`Attempt::finish()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ObjectStoreError::CircuitOpen`] if the circuit is open, or if it's half-open
+    /// and another probe request is in progress.
+    pub fn start_attempt(
+        &self,
+        bucket: Bucket,
+        operation: &'static str,
+    ) -> Result<Attempt<'_>, ObjectStoreError> {
+        let mut circuits = self.circuits.lock().expect("circuit breaker is poisoned");
+        let circuit = circuits.entry(bucket).or_default();
+        let is_probe = match self.circuit_state(circuit) {
+            CircuitState::Closed => false,
+            CircuitState::HalfOpen if !circuit.is_probing => {
+                vlog::info!("Probing object store bucket `{bucket}` with request {operation}");
+                circuit.is_probing = true;
+                report_state(bucket, CircuitState::HalfOpen);
+                true
+            }
+            CircuitState::Open | CircuitState::HalfOpen => {
+                metrics::increment_counter!(
+                    "server.object_store.circuit_rejected_requests",
+                    "bucket" => bucket.as_str(),
+                    "operation" => operation
+                );
+                let message = format!(
+                    "requests to bucket `{bucket}` are suspended after {} consecutive failures",
+                    circuit.consecutive_failures
+                );
+                return Err(ObjectStoreError::CircuitOpen(message.into()));
+            }
+        };
+        Ok(Attempt {
+            breaker: self,
+            bucket,
+            is_probe,
+        })
+    }
+
+    fn finish_attempt(&self, bucket: Bucket, is_probe: bool, is_failure: bool) {
+        let mut circuits = self.circuits.lock().expect("circuit breaker is poisoned");
+        let circuit = circuits.entry(bucket).or_default();
+        if is_probe {
+            circuit.is_probing = false;
+        }
+        if !is_failure {
+            if circuit.opened_at.is_some() {
+                vlog::info!("Object store bucket `{bucket}` has recovered, closing the circuit");
+                report_state(bucket, CircuitState::Closed);
+            }
+            *circuit = BucketCircuit::default();
+            return;
+        }
+
+        circuit.consecutive_failures = circuit.consecutive_failures.saturating_add(1);
+        let should_open = if circuit.opened_at.is_some() {
+            // Failures of requests started before the circuit has opened are ignored.
+            is_probe
+        } else {
+            circuit.consecutive_failures >= self.failure_threshold
+        };
+        if should_open {
+            vlog::warn!(
+                "Requests to object store bucket `{bucket}` have failed {} times in a row; \
+                 suspending requests for {:?}",
+                circuit.consecutive_failures,
+                self.cooldown
+            );
+            circuit.opened_at = Some(Instant::now());
+            report_state(bucket, CircuitState::Open);
+        }
+    }
+}
+
+fn report_state(bucket: Bucket, state: CircuitState) {
+    metrics::gauge!(
+        "server.object_store.circuit_state",
+        state.as_metric(),
+        "bucket" => bucket.as_str()
+    );
+}
+
+/// Request attempt started with [`CircuitBreaker::start_attempt()`]. If the attempt is dropped
+/// without being finished (e.g., because the operation has timed out), it doesn't affect
+/// the circuit state.
+#[derive(Debug)]
+pub(crate) struct Attempt<'a> {
+    breaker: &'a CircuitBreaker,
+    bucket: Bucket,
+    is_probe: bool,
+}
+
+impl Attempt<'_> {
+    /// Records the outcome of the attempt. Only transport errors and timeouts are considered
+    /// failures.
+    pub fn finish(mut self, err: Option<&ObjectStoreError>) {
+        let is_failure = matches!(
+            err,
+            Some(ObjectStoreError::Transport(_) | ObjectStoreError::Timeout(_))
+        );
+        self.breaker
+            .finish_attempt(self.bucket, self.is_probe, is_failure);
+        self.is_probe = false;
+    }
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        if self.is_probe {
+            let mut circuits = self
+                .breaker
+                .circuits
+                .lock()
+                .expect("circuit breaker is poisoned");
+            if let Some(circuit) = circuits.get_mut(&self.bucket) {
+                circuit.is_probing = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn transport_error() -> ObjectStoreError {
+        ObjectStoreError::Transport("connection reset".into())
+    }
+
+    #[test]
+    fn test_circuit_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        for _ in 0..2 {
+            let attempt = breaker.start_attempt(Bucket::ProverJobsFri, "get").unwrap();
+            attempt.finish(Some(&transport_error()));
+        }
+        // A response from the store resets the failure counter.
+        let attempt = breaker.start_attempt(Bucket::ProverJobsFri, "get").unwrap();
+        attempt.finish(Some(&ObjectStoreError::KeyNotFound("missing".into())));
+        for _ in 0..3 {
+            let attempt = breaker.start_attempt(Bucket::ProverJobsFri, "get").unwrap();
+            attempt.finish(Some(&transport_error()));
+        }
+
+        assert_eq!(breaker.state(Bucket::ProverJobsFri), CircuitState::Open);
+        let err = breaker
+            .start_attempt(Bucket::ProverJobsFri, "get")
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::CircuitOpen(_)), "{err}");
+        assert_eq!(err.kind(), "circuit_open");
+        // Circuits for other buckets are unaffected.
+        assert_eq!(breaker.state(Bucket::ProofsFri), CircuitState::Closed);
+        breaker.start_attempt(Bucket::ProofsFri, "get").unwrap();
+    }
+
+    #[test]
+    fn test_half_open_circuit_is_probed() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        let attempt = breaker.start_attempt(Bucket::ProverJobsFri, "get").unwrap();
+        attempt.finish(Some(&transport_error()));
+        assert_eq!(breaker.state(Bucket::ProverJobsFri), CircuitState::HalfOpen);
+
+        let probe = breaker.start_attempt(Bucket::ProverJobsFri, "get").unwrap();
+        // Only a single probe is allowed at a time.
+        let err = breaker
+            .start_attempt(Bucket::ProverJobsFri, "put")
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::CircuitOpen(_)), "{err}");
+        probe.finish(Some(&transport_error()));
+        assert_eq!(breaker.state(Bucket::ProverJobsFri), CircuitState::HalfOpen);
+
+        // A dropped probe allows another probe.
+        drop(breaker.start_attempt(Bucket::ProverJobsFri, "get").unwrap());
+        let probe = breaker.start_attempt(Bucket::ProverJobsFri, "get").unwrap();
+        probe.finish(None);
+        assert_eq!(breaker.state(Bucket::ProverJobsFri), CircuitState::Closed);
+        breaker.start_attempt(Bucket::ProverJobsFri, "get").unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! Transparent zstd compression of blobs.
+
+use async_compression::{
+    tokio::bufread::{ZstdDecoder, ZstdEncoder},
+    Level,
+};
+use async_trait::async_trait;
+use tokio::io::BufReader;
+
+use std::time::Duration;
+
+use crate::{
+    raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome},
+    streams::{prepend, read_prefix},
+};
+
+/// Header prepended to compressed blobs. Blobs without the header are returned as is,
+/// so that blobs stored before compression was enabled remain readable.
+const HEADER: &[u8] = b"ZKC\x01";
+
+/// Store wrapper compressing blobs with zstd before storing them in the wrapped store.
+#[derive(Debug)]
+pub(crate) struct CompressingObjectStore {
+    inner: Box<dyn ObjectStore>,
+    level: i32,
+}
+
+impl CompressingObjectStore {
+    pub fn new(inner: Box<dyn ObjectStore>, level: i32) -> Self {
+        Self { inner, level }
+    }
+
+    #[allow(clippy::cast_precision_loss)] // blob sizes are reported approximately
+    fn compress(&self, bucket: Bucket, value: &[u8]) -> Result<Vec<u8>, ObjectStoreError> {
+        let mut compressed = HEADER.to_vec();
+        zstd::stream::copy_encode(value, &mut compressed, self.level)
+            .map_err(|err| ObjectStoreError::Serialization(err.into()))?;
+        metrics::histogram!(
+            "server.object_store.raw_blob_size",
+            value.len() as f64,
+            "bucket" => bucket.as_str()
+        );
+        metrics::histogram!(
+            "server.object_store.compressed_blob_size",
+            compressed.len() as f64,
+            "bucket" => bucket.as_str()
+        );
+        Ok(compressed)
+    }
+
+    fn decompress(blob: Vec<u8>) -> Result<Vec<u8>, ObjectStoreError> {
+        match blob.strip_prefix(HEADER) {
+            Some(compressed) => zstd::stream::decode_all(compressed)
+                .map_err(|err| ObjectStoreError::Serialization(err.into())),
+            None => Ok(blob),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for CompressingObjectStore {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let blob = self.inner.get_raw(bucket, key).await?;
+        Self::decompress(blob)
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        let compressed = self.compress(bucket, &value)?;
+        self.inner.put_raw(bucket, key, compressed).await
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        self.inner.remove_raw(bucket, key).await
+    }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        let compressed = self.compress(bucket, &value)?;
+        self.inner.put_raw_if_absent(bucket, key, compressed).await
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        // The stored blob is measured, since it's what occupies the space in the wrapped store.
+        self.inner.get_raw_size(bucket, key).await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        self.inner.exists_raw(bucket, key).await
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        self.inner
+            .list_raw(bucket, prefix, continuation_token)
+            .await
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        self.inner.health_check(bucket).await
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        self.inner.generate_download_url(bucket, key, expiry).await
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let mut reader = self.inner.get_raw_stream(bucket, key).await?;
+        let prefix = read_prefix(&mut reader, HEADER.len())
+            .await
+            .map_err(|err| ObjectStoreError::Transport(err.into()))?;
+        if prefix == HEADER {
+            Ok(Box::new(ZstdDecoder::new(BufReader::new(reader))))
+        } else {
+            Ok(prepend(prefix, reader))
+        }
+    }
+
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        let encoder = ZstdEncoder::with_quality(BufReader::new(reader), Level::Precise(self.level));
+        let reader = prepend(HEADER.to_vec(), encoder);
+        self.inner.put_raw_stream(bucket, key, reader).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::AsyncReadExt;
+
+    use std::{io, sync::Arc};
+
+    use super::*;
+    use crate::mock::MockStore;
+
+    #[tokio::test]
+    async fn test_compressed_blobs_roundtrip() {
+        let inner = Arc::new(MockStore::default());
+        let store = CompressingObjectStore::new(Box::new(Arc::clone(&inner)), 3);
+        let value = vec![42; 10_000];
+        store
+            .put_raw(Bucket::ProofsFri, "test.bin", value.clone())
+            .await
+            .unwrap();
+
+        let stored = inner.get_raw(Bucket::ProofsFri, "test.bin").await.unwrap();
+        assert!(stored.starts_with(HEADER));
+        assert!(stored.len() < value.len());
+        let blob = store.get_raw(Bucket::ProofsFri, "test.bin").await.unwrap();
+        assert_eq!(blob, value);
+    }
+
+    #[tokio::test]
+    async fn test_uncompressed_blobs_are_readable() {
+        let inner = Arc::new(MockStore::default());
+        inner
+            .put_raw(Bucket::ProofsFri, "legacy.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        let store = CompressingObjectStore::new(Box::new(inner), 3);
+        let blob = store
+            .get_raw(Bucket::ProofsFri, "legacy.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_blob() {
+        let inner = Arc::new(MockStore::default());
+        let mut corrupted = HEADER.to_vec();
+        corrupted.extend_from_slice(&[1, 2, 3]);
+        inner
+            .put_raw(Bucket::ProofsFri, "corrupted.bin", corrupted)
+            .await
+            .unwrap();
+
+        let store = CompressingObjectStore::new(Box::new(inner), 3);
+        let err = store
+            .get_raw(Bucket::ProofsFri, "corrupted.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Serialization(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_streamed_blobs_are_compatible() {
+        let inner = Arc::new(MockStore::default());
+        let store = CompressingObjectStore::new(Box::new(Arc::clone(&inner)), 3);
+        let value = vec![42; 10_000];
+        let reader: BlobReader = Box::new(io::Cursor::new(value.clone()));
+        store
+            .put_raw_stream(Bucket::ProofsFri, "streamed.bin", reader)
+            .await
+            .unwrap();
+        let blob = store
+            .get_raw(Bucket::ProofsFri, "streamed.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, value);
+
+        store
+            .put_raw(Bucket::ProofsFri, "test.bin", value.clone())
+            .await
+            .unwrap();
+        inner
+            .put_raw(Bucket::ProofsFri, "legacy.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+        for (key, expected) in [("test.bin", value), ("legacy.bin", vec![1, 2, 3])] {
+            let mut reader = store.get_raw_stream(Bucket::ProofsFri, key).await.unwrap();
+            let mut blob = vec![];
+            reader.read_to_end(&mut blob).await.unwrap();
+            assert_eq!(blob, expected);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Content-addressed storage of blobs, deduplicating identical blobs stored under different keys.
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use zksync_types::H256;
+
+use std::time::Duration;
+
+use crate::{
+    raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome},
+    streams::{prepend, read_prefix},
+};
+
+/// Header of aliases stored under logical keys. It's followed by the SHA-256 digest
+/// of the blob contents.
+const ALIAS_HEADER: &[u8] = b"ZKA\x01";
+const HASH_LEN: usize = 32;
+/// Prefix of keys under which blob contents are stored.
+const CONTENT_KEY_PREFIX: &str = "cas/";
+
+fn content_key(hash: H256) -> String {
+    format!("{CONTENT_KEY_PREFIX}{}", hex::encode(hash.as_bytes()))
+}
+
+fn alias(hash: H256) -> Vec<u8> {
+    let mut alias = Vec::with_capacity(ALIAS_HEADER.len() + HASH_LEN);
+    alias.extend_from_slice(ALIAS_HEADER);
+    alias.extend_from_slice(hash.as_bytes());
+    alias
+}
+
+/// Parses the hash from an alias. Returns `None` if the blob is not an alias.
+fn parse_alias(blob: &[u8]) -> Option<H256> {
+    let hash = blob.strip_prefix(ALIAS_HEADER)?;
+    (hash.len() == HASH_LEN).then(|| H256::from_slice(hash))
+}
+
+/// Store wrapper storing blob contents under the SHA-256 hash of the contents and an alias
+/// pointing to the hash under the logical key, so that identical blobs are uploaded and stored
+/// once. Blobs stored without an alias (e.g., before the wrapper was enabled) are returned as is.
+///
+/// Removing a blob only removes its alias, since its contents may be shared with other keys.
+/// Listing doesn't return content blobs; listed sizes are sizes of aliases.
+#[derive(Debug)]
+pub(crate) struct ContentAddressedObjectStore {
+    inner: Box<dyn ObjectStore>,
+}
+
+impl ContentAddressedObjectStore {
+    pub fn new(inner: Box<dyn ObjectStore>) -> Self {
+        Self { inner }
+    }
+
+    /// Stores the blob under the hash of its contents and returns the hash. The upload
+    /// is skipped if a blob with the same hash is already stored.
+    pub async fn put_cas(&self, bucket: Bucket, value: Vec<u8>) -> Result<H256, ObjectStoreError> {
+        let hash = H256::from_slice(&Sha256::digest(&value));
+        let key = content_key(hash);
+        if self.inner.exists_raw(bucket, &key).await? {
+            metrics::increment_counter!(
+                "server.object_store.cas_dedup_hits",
+                "bucket" => bucket.as_str()
+            );
+            metrics::counter!(
+                "server.object_store.cas_saved_bytes",
+                value.len() as u64,
+                "bucket" => bucket.as_str()
+            );
+        } else {
+            self.inner.put_raw(bucket, &key, value).await?;
+        }
+        Ok(hash)
+    }
+
+    /// Returns a reader for the blob contents, resolving the alias stored under `key` if any.
+    async fn resolve_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<(String, BlobReader), ObjectStoreError> {
+        let mut reader = self.inner.get_raw_stream(bucket, key).await?;
+        // An extra byte is read to distinguish aliases from longer blobs starting with the header.
+        let prefix = read_prefix(&mut reader, ALIAS_HEADER.len() + HASH_LEN + 1)
+            .await
+            .map_err(|err| ObjectStoreError::Transport(err.into()))?;
+        Ok(match parse_alias(&prefix) {
+            Some(hash) => {
+                let key = content_key(hash);
+                let reader = self.inner.get_raw_stream(bucket, &key).await?;
+                (key, reader)
+            }
+            None => (key.to_owned(), prepend(prefix, reader)),
+        })
+    }
+}
+
+#[async_trait]
+impl ObjectStore for ContentAddressedObjectStore {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let blob = self.inner.get_raw(bucket, key).await?;
+        match parse_alias(&blob) {
+            Some(hash) => self.inner.get_raw(bucket, &content_key(hash)).await,
+            None => Ok(blob),
+        }
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        let hash = self.put_cas(bucket, value).await?;
+        self.inner.put_raw(bucket, key, alias(hash)).await
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        self.inner.remove_raw(bucket, key).await
+    }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        // The check is an optimization only: the alias below is stored atomically, but uploading
+        // contents for a key that already exists would be wasted.
+        if self.inner.exists_raw(bucket, key).await? {
+            return Ok(PutOutcome::AlreadyExists);
+        }
+        let hash = self.put_cas(bucket, value).await?;
+        self.inner.put_raw_if_absent(bucket, key, alias(hash)).await
+    }
+
+    async fn remove_raw_many(
+        &self,
+        bucket: Bucket,
+        keys: &[String],
+    ) -> Result<(), ObjectStoreError> {
+        self.inner.remove_raw_many(bucket, keys).await
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        let (key, _) = self.resolve_stream(bucket, key).await?;
+        self.inner.get_raw_size(bucket, &key).await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        self.inner.exists_raw(bucket, key).await
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        let (mut objects, next_token) = self
+            .inner
+            .list_raw(bucket, prefix, continuation_token)
+            .await?;
+        objects.retain(|object| !object.key.starts_with(CONTENT_KEY_PREFIX));
+        Ok((objects, next_token))
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        self.inner.health_check(bucket).await
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        let (key, _) = self.resolve_stream(bucket, key).await?;
+        self.inner.generate_download_url(bucket, &key, expiry).await
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let (_, reader) = self.resolve_stream(bucket, key).await?;
+        Ok(reader)
+    }
+
+    // `put_raw_stream()` uses the default implementation, since the hash of the contents
+    // must be known before uploading them; thus, streamed blobs are buffered in memory.
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::AsyncReadExt;
+
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mock::MockStore;
+
+    #[tokio::test]
+    async fn test_identical_blobs_are_stored_once() {
+        let inner = Arc::new(MockStore::default());
+        let store = ContentAddressedObjectStore::new(Box::new(Arc::clone(&inner)));
+        for key in ["circuit_1.bin", "circuit_2.bin"] {
+            store
+                .put_raw(Bucket::ProverJobsFri, key, vec![1, 2, 3])
+                .await
+                .unwrap();
+        }
+        assert_eq!(inner.keys(Bucket::ProverJobsFri).await.len(), 3);
+
+        let blob = store
+            .get_raw(Bucket::ProverJobsFri, "circuit_2.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+        let size = store
+            .get_raw_size(Bucket::ProverJobsFri, "circuit_2.bin")
+            .await
+            .unwrap();
+        assert_eq!(size, 3);
+        let mut reader = store
+            .get_raw_stream(Bucket::ProverJobsFri, "circuit_1.bin")
+            .await
+            .unwrap();
+        let mut blob = vec![];
+        reader.read_to_end(&mut blob).await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+
+        let (objects, _) = store
+            .list_raw(Bucket::ProverJobsFri, "", None)
+            .await
+            .unwrap();
+        let mut keys: Vec<_> = objects.into_iter().map(|object| object.key).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["circuit_1.bin", "circuit_2.bin"]);
+
+        // Contents are retained while they are referenced by other keys.
+        store
+            .remove_raw(Bucket::ProverJobsFri, "circuit_1.bin")
+            .await
+            .unwrap();
+        let blob = store
+            .get_raw(Bucket::ProverJobsFri, "circuit_2.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+        let err = store
+            .get_raw(Bucket::ProverJobsFri, "circuit_1.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_blobs_without_alias_are_readable() {
+        let inner = Arc::new(MockStore::default());
+        let store = ContentAddressedObjectStore::new(Box::new(Arc::clone(&inner)));
+        // A blob starting with the alias header, but longer than an alias.
+        let mut legacy_blob = alias(H256::repeat_byte(1));
+        legacy_blob.push(0);
+        inner
+            .put_raw(Bucket::ProverJobsFri, "legacy.bin", legacy_blob.clone())
+            .await
+            .unwrap();
+
+        let blob = store
+            .get_raw(Bucket::ProverJobsFri, "legacy.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, legacy_blob);
+        let mut reader = store
+            .get_raw_stream(Bucket::ProverJobsFri, "legacy.bin")
+            .await
+            .unwrap();
+        let mut blob = vec![];
+        reader.read_to_end(&mut blob).await.unwrap();
+        assert_eq!(blob, legacy_blob);
+        let size = store
+            .get_raw_size(Bucket::ProverJobsFri, "legacy.bin")
+            .await
+            .unwrap();
+        assert_eq!(size, legacy_blob.len() as u64);
+    }
+}
@@ -0,0 +1,432 @@
+//! Client-side AES-256-GCM encryption of blobs.
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use async_trait::async_trait;
+
+use std::{collections::HashMap, fmt, time::Duration};
+
+use crate::raw::{Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome};
+
+/// Header prepended to encrypted blobs. It's followed by the length of the key ID (1 byte),
+/// the key ID and the nonce; the remaining blob contents are the ciphertext followed
+/// by the authentication tag.
+const HEADER: &[u8] = b"ZKE\x01";
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// Store wrapper encrypting blobs with AES-256-GCM before storing them in the wrapped store.
+/// Blobs are encrypted with the newest key, and can be decrypted with any of the configured keys,
+/// so that keys can be rotated. The header and the blob location are authenticated together
+/// with the blob contents, so a blob copied to another key cannot be decrypted.
+///
+/// Since the whole blob is authenticated at once, streamed blobs are buffered in memory.
+pub(crate) struct EncryptedObjectStore {
+    inner: Box<dyn ObjectStore>,
+    ciphers: HashMap<String, Aes256Gcm>,
+    /// ID of the key used for encryption.
+    current_key_id: String,
+    /// Whether blobs without encryption are rejected.
+    require_encryption: bool,
+}
+
+impl fmt::Debug for EncryptedObjectStore {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("EncryptedObjectStore")
+            .field("inner", &self.inner)
+            .field("current_key_id", &self.current_key_id)
+            .field("require_encryption", &self.require_encryption)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptedObjectStore {
+    /// Wraps the provided store. `keys` are specified as `<key_id>:<hex-encoded 32-byte key>`
+    /// entries separated by commas or newlines; the last key is used for encryption.
+    /// If `require_encryption` is set, blobs without encryption are not returned.
+    pub fn new(
+        inner: Box<dyn ObjectStore>,
+        keys: &str,
+        require_encryption: bool,
+    ) -> Result<Self, ObjectStoreError> {
+        let mut ciphers = HashMap::new();
+        let mut current_key_id = None;
+        let entries = keys.split([',', '\n']).map(str::trim);
+        for entry in entries.filter(|entry| !entry.is_empty()) {
+            let (key_id, key) = Self::parse_key(entry)?;
+            if ciphers.insert(key_id.to_owned(), key).is_some() {
+                return Err(config_error(format!("duplicate encryption key `{key_id}`")));
+            }
+            current_key_id = Some(key_id.to_owned());
+        }
+        let current_key_id =
+            current_key_id.ok_or_else(|| config_error("no encryption keys specified".into()))?;
+
+        Ok(Self {
+            inner,
+            ciphers,
+            current_key_id,
+            require_encryption,
+        })
+    }
+
+    fn parse_key(entry: &str) -> Result<(&str, Aes256Gcm), ObjectStoreError> {
+        let (key_id, key) = entry.split_once(':').ok_or_else(|| {
+            config_error("encryption keys must have the `<key_id>:<hex key>` format".into())
+        })?;
+        if key_id.is_empty() || key_id.len() > usize::from(u8::MAX) {
+            let message = format!("encryption key ID `{key_id}` must have 1 to 255 bytes");
+            return Err(config_error(message));
+        }
+        let key = hex::decode(key.strip_prefix("0x").unwrap_or(key)).map_err(|err| {
+            config_error(format!("failed decoding encryption key `{key_id}`: {err}"))
+        })?;
+        if key.len() != KEY_LEN {
+            let message = format!("encryption key `{key_id}` must have {KEY_LEN} bytes");
+            return Err(config_error(message));
+        }
+        Ok((key_id, Aes256Gcm::new_from_slice(&key).unwrap()))
+    }
+
+    /// Additional authenticated data binding the ciphertext to its header and location.
+    fn associated_data(header: &[u8], bucket: Bucket, key: &str) -> Vec<u8> {
+        let mut data = header.to_vec();
+        data.extend_from_slice(format!("{bucket}/{key}").as_bytes());
+        data
+    }
+
+    fn encrypt(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: &[u8],
+    ) -> Result<Vec<u8>, ObjectStoreError> {
+        let cipher = &self.ciphers[&self.current_key_id];
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let key_id = self.current_key_id.as_bytes();
+        let mut blob = Vec::with_capacity(HEADER.len() + 1 + key_id.len() + NONCE_LEN);
+        blob.extend_from_slice(HEADER);
+        blob.push(u8::try_from(key_id.len()).expect("key ID length is checked on creation"));
+        blob.extend_from_slice(key_id);
+        blob.extend_from_slice(&nonce);
+
+        let payload = Payload {
+            msg: value,
+            aad: &Self::associated_data(&blob, bucket, key),
+        };
+        let ciphertext = cipher.encrypt(&nonce, payload).map_err(|err| {
+            ObjectStoreError::Other(format!("failed encrypting blob {key}: {err}").into())
+        })?;
+        blob.extend_from_slice(&ciphertext);
+        Ok(blob)
+    }
+
+    fn decrypt(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        blob: Vec<u8>,
+    ) -> Result<Vec<u8>, ObjectStoreError> {
+        let Some(contents) = blob.strip_prefix(HEADER) else {
+            if self.require_encryption {
+                let message = format!("blob {key} in bucket {bucket} is not encrypted");
+                return Err(ObjectStoreError::DecryptionFailed(message.into()));
+            }
+            metrics::counter!(
+                "server.object_store.unencrypted_reads",
+                1,
+                "bucket" => bucket.as_str()
+            );
+            return Ok(blob);
+        };
+
+        let truncated_err = || {
+            let message = format!("blob {key} in bucket {bucket} is truncated");
+            ObjectStoreError::DecryptionFailed(message.into())
+        };
+        let (&key_id_len, contents) = contents.split_first().ok_or_else(truncated_err)?;
+        let key_id_len = usize::from(key_id_len);
+        if contents.len() < key_id_len + NONCE_LEN {
+            return Err(truncated_err());
+        }
+        let (key_id, contents) = contents.split_at(key_id_len);
+        let (nonce, ciphertext) = contents.split_at(NONCE_LEN);
+        let header_len = HEADER.len() + 1 + key_id_len + NONCE_LEN;
+        let key_id = String::from_utf8_lossy(key_id);
+        let cipher = self.ciphers.get(key_id.as_ref()).ok_or_else(|| {
+            let message =
+                format!("blob {key} in bucket {bucket} is encrypted with unknown key `{key_id}`");
+            ObjectStoreError::DecryptionFailed(message.into())
+        })?;
+
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &Self::associated_data(&blob[..header_len], bucket, key),
+        };
+        cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| {
+                metrics::counter!(
+                    "server.object_store.decryption_failures",
+                    1,
+                    "bucket" => bucket.as_str()
+                );
+                let message = format!("blob {key} in bucket {bucket} failed authentication");
+                ObjectStoreError::DecryptionFailed(message.into())
+            })
+    }
+}
+
+fn config_error(message: String) -> ObjectStoreError {
+    ObjectStoreError::Other(message.into())
+}
+
+#[async_trait]
+impl ObjectStore for EncryptedObjectStore {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let blob = self.inner.get_raw(bucket, key).await?;
+        self.decrypt(bucket, key, blob)
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        let blob = self.encrypt(bucket, key, &value)?;
+        self.inner.put_raw(bucket, key, blob).await
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        self.inner.remove_raw(bucket, key).await
+    }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        let blob = self.encrypt(bucket, key, &value)?;
+        self.inner.put_raw_if_absent(bucket, key, blob).await
+    }
+
+    async fn remove_raw_many(
+        &self,
+        bucket: Bucket,
+        keys: &[String],
+    ) -> Result<(), ObjectStoreError> {
+        self.inner.remove_raw_many(bucket, keys).await
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        // The stored blob is measured, since it's what occupies the space in the wrapped store.
+        self.inner.get_raw_size(bucket, key).await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        self.inner.exists_raw(bucket, key).await
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        self.inner
+            .list_raw(bucket, prefix, continuation_token)
+            .await
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        self.inner.health_check(bucket).await
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        self.inner.generate_download_url(bucket, key, expiry).await
+    }
+
+    // Streaming methods use the default implementations buffering blobs in memory.
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mock::MockStore;
+
+    const OLD_KEY: &str =
+        "2023-01:0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const NEW_KEY: &str =
+        "2023-07:202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f";
+
+    fn encrypted_store(inner: &Arc<MockStore>, keys: &str, strict: bool) -> EncryptedObjectStore {
+        EncryptedObjectStore::new(Box::new(Arc::clone(inner)), keys, strict).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_blobs_roundtrip() {
+        let inner = Arc::new(MockStore::default());
+        let store = encrypted_store(&inner, OLD_KEY, true);
+        store
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        let stored = inner
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert!(stored.starts_with(HEADER));
+        assert!(!stored.ends_with(&[1, 2, 3]));
+        let blob = store
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_key_rotation() {
+        let inner = Arc::new(MockStore::default());
+        let old_store = encrypted_store(&inner, OLD_KEY, true);
+        old_store
+            .put_raw(Bucket::ProofsFri, "old.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        let new_store = encrypted_store(&inner, &format!("{OLD_KEY},{NEW_KEY}"), true);
+        new_store
+            .put_raw(Bucket::ProofsFri, "new.bin", vec![4, 5])
+            .await
+            .unwrap();
+        let blob = new_store
+            .get_raw(Bucket::ProofsFri, "old.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+        let blob = new_store
+            .get_raw(Bucket::ProofsFri, "new.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [4, 5]);
+
+        let err = old_store
+            .get_raw(Bucket::ProofsFri, "new.bin")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ObjectStoreError::DecryptionFailed(_)),
+            "{err}"
+        );
+        assert!(err.to_string().contains("unknown key `2023-07`"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_tampered_blobs_are_rejected() {
+        let inner = Arc::new(MockStore::default());
+        let store = encrypted_store(&inner, OLD_KEY, false);
+        store
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+        let mut stored = inner
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        *stored.last_mut().unwrap() ^= 1;
+        inner
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", stored.clone())
+            .await
+            .unwrap();
+
+        let err = store
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ObjectStoreError::DecryptionFailed(_)),
+            "{err}"
+        );
+        assert!(!err.is_transient());
+
+        // A valid blob copied to another key is rejected as well.
+        *stored.last_mut().unwrap() ^= 1;
+        inner
+            .put_raw(Bucket::ProofsFri, "proof_2.bin", stored)
+            .await
+            .unwrap();
+        let err = store
+            .get_raw(Bucket::ProofsFri, "proof_2.bin")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ObjectStoreError::DecryptionFailed(_)),
+            "{err}"
+        );
+
+        inner
+            .put_raw(Bucket::ProofsFri, "truncated.bin", HEADER.to_vec())
+            .await
+            .unwrap();
+        let err = store
+            .get_raw(Bucket::ProofsFri, "truncated.bin")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("truncated"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_unencrypted_blobs_depending_on_strictness() {
+        let inner = Arc::new(MockStore::default());
+        inner
+            .put_raw(Bucket::ProofsFri, "legacy.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        let store = encrypted_store(&inner, OLD_KEY, false);
+        let blob = store
+            .get_raw(Bucket::ProofsFri, "legacy.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+
+        let strict_store = encrypted_store(&inner, OLD_KEY, true);
+        let err = strict_store
+            .get_raw(Bucket::ProofsFri, "legacy.bin")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ObjectStoreError::DecryptionFailed(_)),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let invalid_keys = [
+            "",
+            "2023-01",
+            ":000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "2023-01:0001",
+            "2023-01:not-a-hex-key",
+            &format!("{OLD_KEY},{OLD_KEY}"),
+        ];
+        for keys in invalid_keys {
+            let err =
+                EncryptedObjectStore::new(Box::new(MockStore::default()), keys, false).unwrap_err();
+            assert!(matches!(err, ObjectStoreError::Other(_)), "{err}");
+        }
+    }
+}
@@ -1,26 +1,44 @@
 use async_trait::async_trait;
-use tokio::{fs, io};
+use tokio::{
+    fs,
+    io::{self, AsyncRead},
+};
 
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-use crate::raw::{Bucket, ObjectStore, ObjectStoreError};
+use crate::raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome};
 
 impl From<io::Error> for ObjectStoreError {
     fn from(err: io::Error) -> Self {
         match err.kind() {
             io::ErrorKind::NotFound => ObjectStoreError::KeyNotFound(err.into()),
+            io::ErrorKind::PermissionDenied => ObjectStoreError::Auth(err.into()),
             _ => ObjectStoreError::Other(err.into()),
         }
     }
 }
 
+/// Max number of blobs listed in a single page.
+const LIST_PAGE_SIZE: usize = 1_000;
+
+/// Counter making names of temporary files unique within the process.
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Store saving blobs as separate files. Blobs are written to a temporary file in the bucket
+/// directory and then renamed, so that an interrupted write never leaves a truncated blob.
 #[derive(Debug)]
 pub(crate) struct FileBackedObjectStore {
     base_dir: String,
+    /// Whether written blobs are synced to disk before they become visible.
+    durable_writes: bool,
 }
 
 impl FileBackedObjectStore {
-    pub async fn new(base_dir: String) -> Self {
+    pub async fn new(base_dir: String, durable_writes: bool) -> Result<Self, ObjectStoreError> {
         for bucket in &[
             Bucket::ProverJobs,
             Bucket::WitnessInput,
@@ -34,25 +52,108 @@ impl FileBackedObjectStore {
             Bucket::ProofsFri,
         ] {
             let bucket_path = format!("{base_dir}/{bucket}");
-            fs::create_dir_all(&bucket_path)
-                .await
-                .unwrap_or_else(|err| {
-                    panic!("failed creating bucket `{bucket_path}`: {err}");
-                });
+            fs::create_dir_all(&bucket_path).await.map_err(|err| {
+                let message = format!("failed creating bucket `{bucket_path}`: {err}");
+                ObjectStoreError::Other(message.into())
+            })?;
         }
-        FileBackedObjectStore { base_dir }
+        Ok(FileBackedObjectStore {
+            base_dir,
+            durable_writes,
+        })
     }
 
     fn filename(&self, bucket: Bucket, key: &str) -> String {
         format!("{}/{bucket}/{key}", self.base_dir)
     }
+
+    /// Temporary files don't collide with keys, since keys never have the `.tmp` extension.
+    fn temp_filename(filename: &str) -> String {
+        let counter = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        format!("{filename}.{}-{counter}.tmp", process::id())
+    }
+
+    async fn write_file<R>(&self, filename: &str, reader: &mut R) -> io::Result<()>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+    {
+        let mut file = fs::File::create(filename).await?;
+        tokio::io::copy(reader, &mut file).await?;
+        if self.durable_writes {
+            file.sync_all().await?;
+        }
+        Ok(())
+    }
+
+    async fn put_from_reader<R>(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        reader: &mut R,
+    ) -> Result<(), ObjectStoreError>
+    where
+        R: AsyncRead + Unpin + Send + ?Sized,
+    {
+        let filename = self.filename(bucket, key);
+        self.create_key_dir(bucket, key).await?;
+        let temp_filename = Self::temp_filename(&filename);
+        let result = match self.write_file(&temp_filename, reader).await {
+            Ok(()) => fs::rename(&temp_filename, &filename).await,
+            Err(err) => Err(err),
+        };
+        if result.is_err() {
+            fs::remove_file(&temp_filename).await.ok();
+        }
+        result.map_err(From::from)
+    }
+
+    async fn create_key_dir(&self, bucket: Bucket, key: &str) -> io::Result<()> {
+        if let Some((dir, _)) = key.rsplit_once('/') {
+            // Prefixed keys are stored in subdirectories of the bucket directory.
+            fs::create_dir_all(format!("{}/{bucket}/{dir}", self.base_dir)).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns keys of all blobs in the `bucket` starting with `prefix` in no particular order.
+    async fn list_keys(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+    ) -> Result<Vec<String>, ObjectStoreError> {
+        let bucket_path = format!("{}/{bucket}", self.base_dir);
+        let mut keys = vec![];
+        // Prefixed keys are stored in subdirectories, so the bucket directory is walked recursively.
+        let mut pending_dirs = vec![String::new()];
+        while let Some(dir) = pending_dirs.pop() {
+            let mut entries = fs::read_dir(format!("{bucket_path}/{dir}")).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue; // Keys are always valid UTF-8.
+                };
+                let key = format!("{dir}{name}");
+                if entry.file_type().await?.is_dir() {
+                    pending_dirs.push(format!("{key}/"));
+                } else if !key.ends_with(".tmp") && key.starts_with(prefix) {
+                    keys.push(key);
+                }
+            }
+        }
+        Ok(keys)
+    }
 }
 
 #[async_trait]
 impl ObjectStore for FileBackedObjectStore {
     async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
         let filename = self.filename(bucket, key);
-        fs::read(filename).await.map_err(From::from)
+        let bytes = fs::read(&filename).await?;
+        if bytes.is_empty() {
+            // Blobs are never empty, so the file must have been truncated.
+            let message = format!("file `{filename}` is empty");
+            return Err(ObjectStoreError::Serialization(message.into()));
+        }
+        Ok(bytes)
     }
 
     async fn put_raw(
@@ -61,19 +162,129 @@ impl ObjectStore for FileBackedObjectStore {
         key: &str,
         value: Vec<u8>,
     ) -> Result<(), ObjectStoreError> {
-        let filename = self.filename(bucket, key);
-        fs::write(filename, value).await.map_err(From::from)
+        self.put_from_reader(bucket, key, &mut value.as_slice())
+            .await
     }
 
     async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
         let filename = self.filename(bucket, key);
         fs::remove_file(filename).await.map_err(From::from)
     }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        let filename = self.filename(bucket, key);
+        self.create_key_dir(bucket, key).await?;
+        // Creating the blob file with `create_new` would expose a partially written blob
+        // to concurrent readers. Instead, the blob is written to a temporary file, which is then
+        // hard-linked to the blob file; linking fails atomically if the blob file exists.
+        let temp_filename = Self::temp_filename(&filename);
+        let result = match self.write_file(&temp_filename, &mut value.as_slice()).await {
+            Ok(()) => fs::hard_link(&temp_filename, &filename).await,
+            Err(err) => Err(err),
+        };
+        fs::remove_file(&temp_filename).await.ok();
+        match result {
+            Ok(()) => Ok(PutOutcome::Created),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Ok(PutOutcome::AlreadyExists),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        let filename = self.filename(bucket, key);
+        Ok(fs::metadata(filename).await?.len())
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        // Keys are listed in the lexicographic order; the continuation token is the last key
+        // of the previous page.
+        let mut keys = self.list_keys(bucket, prefix).await?;
+        if let Some(last_key) = &continuation_token {
+            keys.retain(|key| key > last_key);
+        }
+        keys.sort_unstable();
+        let has_more_keys = keys.len() > LIST_PAGE_SIZE;
+        keys.truncate(LIST_PAGE_SIZE);
+        let next_token = if has_more_keys {
+            keys.last().cloned()
+        } else {
+            None
+        };
+
+        let mut objects = Vec::with_capacity(keys.len());
+        for key in keys {
+            let metadata = match fs::metadata(self.filename(bucket, &key)).await {
+                Ok(metadata) => metadata,
+                // The blob was removed after it was listed.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            objects.push(ObjectMeta {
+                key,
+                size: metadata.len(),
+                last_modified: metadata.modified().ok(),
+            });
+        }
+        Ok((objects, next_token))
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        let bucket_path = format!("{}/{bucket}", self.base_dir);
+        let metadata = fs::metadata(&bucket_path).await.map_err(|err| {
+            let message = format!("cannot access bucket `{bucket_path}`: {err}");
+            ObjectStoreError::Other(message.into())
+        })?;
+        if metadata.is_dir() {
+            Ok(())
+        } else {
+            let message = format!("bucket `{bucket_path}` is not a directory");
+            Err(ObjectStoreError::Other(message.into()))
+        }
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let filename = self.filename(bucket, key);
+        let file = fs::File::open(&filename).await?;
+        if file.metadata().await?.len() == 0 {
+            let message = format!("file `{filename}` is empty");
+            return Err(ObjectStoreError::Serialization(message.into()));
+        }
+        Ok(Box::new(file))
+    }
+
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        mut reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        self.put_from_reader(bucket, key, &mut reader).await
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use bytes::Bytes;
+    use futures::stream;
     use tempdir::TempDir;
+    use tokio::io::AsyncReadExt;
+    use tokio_util::io::StreamReader;
+
+    use std::time::Duration;
 
     use super::*;
 
@@ -81,7 +292,7 @@ mod test {
     async fn test_get() {
         let dir = TempDir::new("test-data").unwrap();
         let path = dir.into_path().into_os_string().into_string().unwrap();
-        let object_store = FileBackedObjectStore::new(path).await;
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
         let expected = vec![9, 0, 8, 9, 0, 7];
         let result = object_store
             .put_raw(Bucket::ProverJobs, "test-key.bin", expected.clone())
@@ -98,7 +309,7 @@ mod test {
     async fn test_put() {
         let dir = TempDir::new("test-data").unwrap();
         let path = dir.into_path().into_os_string().into_string().unwrap();
-        let object_store = FileBackedObjectStore::new(path).await;
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
         let bytes = vec![9, 0, 8, 9, 0, 7];
         let result = object_store
             .put_raw(Bucket::ProverJobs, "test-key.bin", bytes)
@@ -106,18 +317,333 @@ mod test {
         assert!(result.is_ok(), "result must be OK");
     }
 
+    #[tokio::test]
+    async fn test_put_with_prefixed_key() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        object_store
+            .put_raw(Bucket::ProofsFri, "staging/test-key.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+        let bytes = object_store
+            .get_raw(Bucket::ProofsFri, "staging/test-key.bin")
+            .await
+            .unwrap();
+        assert_eq!(bytes, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_put_if_absent() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        for key in ["test-key.bin", "staging/test-key.bin"] {
+            let outcome = object_store
+                .put_raw_if_absent(Bucket::ProofsFri, key, vec![1, 2, 3])
+                .await
+                .unwrap();
+            assert_eq!(outcome, PutOutcome::Created);
+            let outcome = object_store
+                .put_raw_if_absent(Bucket::ProofsFri, key, vec![4, 5])
+                .await
+                .unwrap();
+            assert_eq!(outcome, PutOutcome::AlreadyExists);
+            let bytes = object_store.get_raw(Bucket::ProofsFri, key).await.unwrap();
+            assert_eq!(bytes, [1, 2, 3]);
+        }
+
+        // Temporary files are cleaned up.
+        let (objects, _) = object_store
+            .list_raw(Bucket::ProofsFri, "", None)
+            .await
+            .unwrap();
+        assert_eq!(objects.len(), 2, "{objects:?}");
+        let mut entries = fs::read_dir(object_store.filename(Bucket::ProofsFri, ""))
+            .await
+            .unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            let name = entry.file_name().into_string().unwrap();
+            assert!(!name.ends_with(".tmp"), "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        for key in [
+            "proof_1.bin",
+            "proof_2.bin",
+            "staging/proof_1.bin",
+            "vk.bin",
+        ] {
+            object_store
+                .put_raw(Bucket::ProofsFri, key, vec![1, 2, 3])
+                .await
+                .unwrap();
+        }
+        let filename = object_store.filename(Bucket::ProofsFri, "proof_3.bin");
+        fs::write(FileBackedObjectStore::temp_filename(&filename), [1_u8])
+            .await
+            .unwrap();
+
+        let (objects, next_token) = object_store
+            .list_raw(Bucket::ProofsFri, "", None)
+            .await
+            .unwrap();
+        assert!(next_token.is_none());
+        let keys: Vec<_> = objects.iter().map(|object| object.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "proof_1.bin",
+                "proof_2.bin",
+                "staging/proof_1.bin",
+                "vk.bin"
+            ]
+        );
+        for object in &objects {
+            assert_eq!(object.size, 3);
+            assert!(object.last_modified.is_some());
+        }
+
+        let (objects, _) = object_store
+            .list_raw(Bucket::ProofsFri, "proof_", None)
+            .await
+            .unwrap();
+        let keys: Vec<_> = objects.iter().map(|object| object.key.as_str()).collect();
+        assert_eq!(keys, ["proof_1.bin", "proof_2.bin"]);
+        let (objects, _) = object_store
+            .list_raw(Bucket::ProofsFri, "staging/", None)
+            .await
+            .unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].key, "staging/proof_1.bin");
+        let (objects, _) = object_store
+            .list_raw(Bucket::ProverJobs, "", None)
+            .await
+            .unwrap();
+        assert!(objects.is_empty(), "{objects:?}");
+    }
+
+    #[tokio::test]
+    async fn test_list_pagination() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        for i in 0..=LIST_PAGE_SIZE {
+            object_store
+                .put_raw(Bucket::ProofsFri, &format!("proof_{i:04}.bin"), vec![1])
+                .await
+                .unwrap();
+        }
+
+        let (objects, next_token) = object_store
+            .list_raw(Bucket::ProofsFri, "", None)
+            .await
+            .unwrap();
+        assert_eq!(objects.len(), LIST_PAGE_SIZE);
+        assert_eq!(objects[0].key, "proof_0000.bin");
+        assert!(next_token.is_some());
+        let (objects, next_token) = object_store
+            .list_raw(Bucket::ProofsFri, "", next_token)
+            .await
+            .unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].key, format!("proof_{LIST_PAGE_SIZE:04}.bin"));
+        assert!(next_token.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.path().to_str().unwrap().to_owned();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        object_store.health_check(Bucket::ProofsFri).await.unwrap();
+
+        fs::remove_dir(dir.path().join("proofs_fri")).await.unwrap();
+        let err = object_store
+            .health_check(Bucket::ProofsFri)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("proofs_fri"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_download_urls_are_unsupported() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        object_store
+            .put_raw(Bucket::ProverJobsFri, "test-key.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+        let err = object_store
+            .generate_download_url(
+                Bucket::ProverJobsFri,
+                "test-key.bin",
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Unsupported(_)), "{err}");
+        assert!(!err.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_remove_many_skips_missing_blobs() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        for key in ["a.bin", "b.bin"] {
+            object_store
+                .put_raw(Bucket::ProofsFri, key, vec![1, 2, 3])
+                .await
+                .unwrap();
+        }
+        let size = object_store
+            .get_raw_size(Bucket::ProofsFri, "a.bin")
+            .await
+            .unwrap();
+        assert_eq!(size, 3);
+
+        let keys = ["a.bin", "missing.bin", "b.bin"].map(String::from);
+        object_store
+            .remove_raw_many(Bucket::ProofsFri, &keys)
+            .await
+            .unwrap();
+        for key in ["a.bin", "b.bin"] {
+            let err = object_store
+                .get_raw_size(Bucket::ProofsFri, key)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+        }
+        // Removal can be repeated.
+        object_store
+            .remove_raw_many(Bucket::ProofsFri, &keys)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_put_is_atomic() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, true).await.unwrap();
+
+        // Simulate a write interrupted before the temporary file is renamed.
+        let filename = object_store.filename(Bucket::ProverJobs, "test-key.bin");
+        let temp_filename = FileBackedObjectStore::temp_filename(&filename);
+        fs::write(&temp_filename, [9_u8, 0]).await.unwrap();
+        let err = object_store
+            .get_raw(Bucket::ProverJobs, "test-key.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+
+        object_store
+            .put_raw(Bucket::ProverJobs, "test-key.bin", vec![9, 0, 8])
+            .await
+            .unwrap();
+        let bytes = object_store
+            .get_raw(Bucket::ProverJobs, "test-key.bin")
+            .await
+            .unwrap();
+        assert_eq!(bytes, [9, 0, 8]);
+    }
+
+    #[tokio::test]
+    async fn test_get_empty_file() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        let filename = object_store.filename(Bucket::ProverJobs, "test-key.bin");
+        fs::write(&filename, b"").await.unwrap();
+
+        let err = object_store
+            .get_raw(Bucket::ProverJobs, "test-key.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Serialization(_)), "{err}");
+    }
+
     #[tokio::test]
     async fn test_remove() {
         let dir = TempDir::new("test-data").unwrap();
         let path = dir.into_path().into_os_string().into_string().unwrap();
-        let object_store = FileBackedObjectStore::new(path).await;
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
         let result = object_store
             .put_raw(Bucket::ProverJobs, "test-key.bin", vec![0, 1])
             .await;
         assert!(result.is_ok(), "result must be OK");
+        let exists = object_store
+            .exists_raw(Bucket::ProverJobs, "test-key.bin")
+            .await;
+        assert!(exists.unwrap());
         let result = object_store
             .remove_raw(Bucket::ProverJobs, "test-key.bin")
             .await;
         assert!(result.is_ok(), "result must be OK");
+        let exists = object_store
+            .exists_raw(Bucket::ProverJobs, "test-key.bin")
+            .await;
+        assert!(!exists.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_stream_roundtrip() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        let reader: BlobReader = Box::new(io::Cursor::new(vec![9, 0, 8]));
+        object_store
+            .put_raw_stream(Bucket::ProverJobs, "test-key.bin", reader)
+            .await
+            .unwrap();
+
+        let mut reader = object_store
+            .get_raw_stream(Bucket::ProverJobs, "test-key.bin")
+            .await
+            .unwrap();
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await.unwrap();
+        assert_eq!(bytes, [9, 0, 8]);
+
+        let err = object_store
+            .get_raw_stream(Bucket::ProverJobs, "missing.bin")
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_failed_stream_put_keeps_previous_value() {
+        let dir = TempDir::new("test-data").unwrap();
+        let path = dir.into_path().into_os_string().into_string().unwrap();
+        let object_store = FileBackedObjectStore::new(path, false).await.unwrap();
+        object_store
+            .put_raw(Bucket::ProverJobs, "test-key.bin", vec![9, 0, 8])
+            .await
+            .unwrap();
+
+        let chunks = vec![
+            Ok(Bytes::from_static(&[1, 2])),
+            Err(io::Error::new(io::ErrorKind::Other, "serialization failed")),
+        ];
+        let reader: BlobReader = Box::new(StreamReader::new(stream::iter(chunks)));
+        object_store
+            .put_raw_stream(Bucket::ProverJobs, "test-key.bin", reader)
+            .await
+            .unwrap_err();
+
+        let bytes = object_store
+            .get_raw(Bucket::ProverJobs, "test-key.bin")
+            .await
+            .unwrap();
+        assert_eq!(bytes, [9, 0, 8]);
     }
 }
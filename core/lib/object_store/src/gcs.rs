@@ -1,6 +1,7 @@
 //! GCS-based [`ObjectStore`] implementation.
 
 use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
 use google_cloud_auth::{credentials::CredentialsFile, error::Error};
 use google_cloud_storage::{
     client::{Client, ClientConfig},
@@ -8,47 +9,69 @@ use google_cloud_storage::{
         delete::DeleteObjectRequest,
         download::Range,
         get::GetObjectRequest,
+        list::ListObjectsRequest,
         upload::{Media, UploadObjectRequest, UploadType},
     },
     http::Error as HttpError,
+    sign::{SignedURLError, SignedURLMethod, SignedURLOptions},
 };
-use http::StatusCode;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::{ReaderStream, StreamReader};
 
 use std::{
     fmt,
     future::Future,
-    time::{Duration, Instant},
+    io,
+    time::{Duration, Instant, SystemTime},
 };
 
-use crate::raw::{Bucket, ObjectStore, ObjectStoreError};
-
-async fn retry<T, E, Fut, F>(max_retries: u16, mut f: F) -> Result<T, E>
-where
-    Fut: Future<Output = Result<T, E>>,
-    F: FnMut() -> Fut,
-{
-    let mut retries = 1;
-    let mut backoff = 1;
-    loop {
-        match f().await {
-            Ok(result) => return Ok(result),
-            Err(err) => {
-                vlog::warn!("Failed gcs request {retries}/{max_retries}, retrying.");
-                if retries > max_retries {
-                    return Err(err);
-                }
-                retries += 1;
-                tokio::time::sleep(Duration::from_secs(backoff)).await;
-                backoff *= 2;
-            }
-        }
-    }
+use crate::{
+    limiter::{PermitReader, RequestLimiter},
+    raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome},
+    retries::{is_transient_status_code, RetryPolicy},
+};
+
+/// Size of chunks in which streamed blobs are uploaded.
+const UPLOAD_CHUNK_SIZE: usize = 1 << 20;
+/// Maximum number of chunks read ahead of the upload.
+const UPLOAD_CHANNEL_CAPACITY: usize = 4;
+
+/// Source of credentials for the GCS-based store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum GcsCredentials {
+    /// Credentials are discovered from the environment (e.g., the metadata server).
+    Default,
+    /// Credentials are loaded from the file at the specified path.
+    File(String),
+    /// Requests are not authenticated; used with GCS emulators such as fake-gcs-server.
+    Anonymous,
+}
+
+/// Loaded [`GcsCredentials`].
+#[derive(Clone)]
+enum ClientCredentials {
+    Default,
+    File(CredentialsFile),
+    Anonymous,
+}
+
+/// Client for a preferred read endpoint.
+struct ReadEndpoint {
+    url: String,
+    client: Client,
 }
 
 pub struct GoogleCloudStorage {
     bucket_prefix: String,
-    max_retries: u16,
+    retry_policy: RetryPolicy,
+    limiter: RequestLimiter,
     client: Client,
+    /// Endpoints tried in order for reads before the default endpoint.
+    read_endpoints: Vec<ReadEndpoint>,
+    /// Policy for requests to read endpoints. Requests are not retried, since the default
+    /// endpoint is used as a fallback, and don't affect the circuit breaker.
+    read_endpoint_policy: RetryPolicy,
 }
 
 impl fmt::Debug for GoogleCloudStorage {
@@ -56,40 +79,102 @@ impl fmt::Debug for GoogleCloudStorage {
         formatter
             .debug_struct("GoogleCloudStorage")
             .field("bucket_prefix", &self.bucket_prefix)
-            .field("max_retries", &self.max_retries)
+            .field("retry_policy", &self.retry_policy)
+            .field("limiter", &self.limiter)
+            .field(
+                "read_endpoints",
+                &self
+                    .read_endpoints
+                    .iter()
+                    .map(|endpoint| &endpoint.url)
+                    .collect::<Vec<_>>(),
+            )
             .finish()
     }
 }
 
 impl GoogleCloudStorage {
+    /// Creates a store. If `endpoint_override` is set, it's used as the default storage endpoint
+    /// instead of the public GCS endpoint (e.g., to point the store at a GCS emulator).
     pub async fn new(
-        credential_file_path: Option<String>,
+        credentials: GcsCredentials,
         bucket_prefix: String,
-        max_retries: u16,
-    ) -> Self {
-        let client_config = retry(max_retries, || {
-            Self::get_client_config(credential_file_path.clone())
-        })
-        .await
-        .expect("failed fetching GCS client config after retries");
+        retry_policy: RetryPolicy,
+        max_concurrent_requests: usize,
+        endpoint_override: Option<String>,
+        read_endpoints: &[String],
+    ) -> Result<Self, ObjectStoreError> {
+        let credentials = match credentials {
+            GcsCredentials::Default => ClientCredentials::Default,
+            GcsCredentials::File(path) => {
+                let cred_file = CredentialsFile::new_from_file(path.clone()).await;
+                let cred_file = cred_file.map_err(|err| {
+                    let message = format!("failed loading GCS credential file `{path}`: {err}");
+                    ObjectStoreError::Auth(message.into())
+                })?;
+                ClientCredentials::File(cred_file)
+            }
+            GcsCredentials::Anonymous => ClientCredentials::Anonymous,
+        };
+        let mut client_config = Self::create_client_config(&credentials, &retry_policy).await?;
+        if let Some(endpoint) = endpoint_override {
+            client_config.storage_endpoint = endpoint;
+        }
+        let mut read_clients = Vec::with_capacity(read_endpoints.len());
+        for url in read_endpoints {
+            let mut client_config = Self::create_client_config(&credentials, &retry_policy).await?;
+            client_config.storage_endpoint = url.clone();
+            read_clients.push(ReadEndpoint {
+                url: url.clone(),
+                client: Client::new(client_config),
+            });
+        }
 
-        Self {
+        Ok(Self {
             client: Client::new(client_config),
             bucket_prefix,
-            max_retries,
-        }
+            read_endpoint_policy: RetryPolicy {
+                max_retries: 0,
+                circuit_breaker: None,
+                ..retry_policy.clone()
+            },
+            retry_policy,
+            limiter: RequestLimiter::new(max_concurrent_requests),
+            read_endpoints: read_clients,
+        })
     }
 
-    async fn get_client_config(
-        credential_file_path: Option<String>,
-    ) -> Result<ClientConfig, Error> {
-        if let Some(path) = credential_file_path {
-            let cred_file = CredentialsFile::new_from_file(path)
-                .await
-                .expect("failed loading GCS credential file");
-            ClientConfig::default().with_credentials(cred_file).await
-        } else {
-            ClientConfig::default().with_auth().await
+    async fn create_client_config(
+        credentials: &ClientCredentials,
+        retry_policy: &RetryPolicy,
+    ) -> Result<ClientConfig, ObjectStoreError> {
+        retry_policy
+            .retry(
+                "get_client_config",
+                None,
+                |_| true,
+                || async {
+                    let config = Self::get_client_config(credentials.clone()).await;
+                    config.map_err(|err| ObjectStoreError::Auth(err.into()))
+                },
+            )
+            .await
+            .map_err(|err| match err {
+                ObjectStoreError::Timeout(_) => err,
+                _ => {
+                    let message = format!("failed fetching GCS client config after retries: {err}");
+                    ObjectStoreError::Auth(message.into())
+                }
+            })
+    }
+
+    async fn get_client_config(credentials: ClientCredentials) -> Result<ClientConfig, Error> {
+        match credentials {
+            ClientCredentials::Default => ClientConfig::default().with_auth().await,
+            ClientCredentials::File(cred_file) => {
+                ClientConfig::default().with_credentials(cred_file).await
+            }
+            ClientCredentials::Anonymous => Ok(ClientConfig::default().anonymous()),
         }
     }
 
@@ -97,16 +182,72 @@ impl GoogleCloudStorage {
         format!("{bucket}/{filename}")
     }
 
+    /// Runs a request once a permit from the limiter is acquired. Since the permit is acquired
+    /// for each attempt, waiting for it counts towards the request timeout.
+    async fn limited<T>(
+        &self,
+        bucket: Bucket,
+        operation: &'static str,
+        request: impl Future<Output = T>,
+    ) -> T {
+        let _permit = self.limiter.acquire(bucket, operation).await;
+        request.await
+    }
+
+    /// Performs a read request trying the preferred read endpoints in order, and then the default
+    /// endpoint. Requests to read endpoints are made once; if a request fails with a transient
+    /// error (including a timeout), it falls back to the next endpoint. Fallbacks are reported
+    /// as a counter labeled with the `operation`, the `bucket` and the endpoint.
+    async fn read<'a, T, Fut>(
+        &'a self,
+        bucket: Bucket,
+        operation: &'static str,
+        request: impl Fn(&'a Client) -> Fut,
+    ) -> Result<T, ObjectStoreError>
+    where
+        Fut: Future<Output = Result<T, HttpError>>,
+    {
+        for endpoint in &self.read_endpoints {
+            let result = self
+                .read_endpoint_policy
+                .retry(operation, Some(bucket), is_transient_error, || {
+                    request(&endpoint.client)
+                })
+                .await;
+            match result {
+                Err(err) if err.is_transient() => {
+                    vlog::warn!(
+                        "Request {operation} for bucket {bucket} to GCS endpoint {} has failed, \
+                         falling back to the next endpoint: {err}",
+                        endpoint.url
+                    );
+                    metrics::increment_counter!(
+                        "server.object_store.read_endpoint_fallbacks",
+                        "bucket" => bucket.as_str(),
+                        "operation" => operation,
+                        "endpoint" => endpoint.url.clone()
+                    );
+                }
+                result => return result,
+            }
+        }
+        self.retry_policy
+            .retry(operation, Some(bucket), is_transient_error, || {
+                request(&self.client)
+            })
+            .await
+    }
+
     // For some bizzare reason, `async fn` doesn't work here, failing with the following error:
     //
     // > hidden type for `impl std::future::Future<Output = Result<(), ObjectStoreError>>`
     // > captures lifetime that does not appear in bounds
     fn remove_inner(
         &self,
-        bucket: &'static str,
+        bucket: Bucket,
         key: &str,
     ) -> impl Future<Output = Result<(), ObjectStoreError>> + '_ {
-        let filename = Self::filename(bucket, key);
+        let filename = Self::filename(bucket.as_str(), key);
         vlog::trace!(
             "Removing data from GCS for key {filename} from bucket {}",
             self.bucket_prefix
@@ -118,27 +259,40 @@ impl GoogleCloudStorage {
             ..DeleteObjectRequest::default()
         };
         async move {
-            retry(self.max_retries, || self.client.delete_object(&request))
+            self.retry_policy
+                .retry("remove", Some(bucket), is_transient_error, || {
+                    self.limited(bucket, "remove", self.client.delete_object(&request))
+                })
                 .await
-                .map_err(ObjectStoreError::from)
         }
     }
 }
 
+/// Returns `true` if the request may succeed if retried. Requests that were rate-limited,
+/// timed out or failed because of a server or network error are retried; other failures,
+/// e.g. missing objects or authentication failures, are not.
+fn is_transient_error(err: &HttpError) -> bool {
+    match err {
+        HttpError::HttpClient(err) => err
+            .status()
+            .map_or(true, |status| is_transient_status_code(status.as_u16())),
+        HttpError::Response(response) => is_transient_status_code(response.code),
+        HttpError::TokenSource(_) => false,
+    }
+}
+
 impl From<HttpError> for ObjectStoreError {
     fn from(err: HttpError) -> Self {
-        let is_not_found = match &err {
-            HttpError::HttpClient(err) => err
-                .status()
-                .map_or(false, |status| matches!(status, StatusCode::NOT_FOUND)),
-            HttpError::Response(response) => response.code == StatusCode::NOT_FOUND.as_u16(),
-            HttpError::TokenSource(_) => false,
+        let status_code = match &err {
+            HttpError::HttpClient(err) => err.status().map(|status| status.as_u16()),
+            HttpError::Response(response) => Some(response.code),
+            // The client has failed to obtain an access token.
+            HttpError::TokenSource(_) => return ObjectStoreError::Auth(err.into()),
         };
-
-        if is_not_found {
-            ObjectStoreError::KeyNotFound(err.into())
-        } else {
-            ObjectStoreError::Other(err.into())
+        match status_code {
+            Some(code) => ObjectStoreError::from_status_code(code, err),
+            // The request hasn't reached the server, or the connection has broken.
+            None => ObjectStoreError::Transport(err.into()),
         }
     }
 }
@@ -159,10 +313,11 @@ impl ObjectStore for GoogleCloudStorage {
             ..GetObjectRequest::default()
         };
         let range = Range::default();
-        let blob = retry(self.max_retries, || {
-            self.client.download_object(&request, &range)
-        })
-        .await;
+        let blob = self
+            .read(bucket, "get", |client| {
+                self.limited(bucket, "get", client.download_object(&request, &range))
+            })
+            .await;
 
         vlog::trace!(
             "Fetched data from GCS for key {key} from bucket {bucket} and it took: {:?}",
@@ -173,7 +328,7 @@ impl ObjectStore for GoogleCloudStorage {
             started_at.elapsed(),
             "bucket" => bucket.as_str()
         );
-        blob.map_err(ObjectStoreError::from)
+        blob
     }
 
     async fn put_raw(
@@ -194,11 +349,15 @@ impl ObjectStore for GoogleCloudStorage {
             bucket: self.bucket_prefix.clone(),
             ..Default::default()
         };
-        let object = retry(self.max_retries, || {
-            self.client
-                .upload_object(&request, value.clone(), &upload_type)
-        })
-        .await;
+        let object = self
+            .retry_policy
+            .retry("put", Some(bucket), is_transient_error, || {
+                let upload = self
+                    .client
+                    .upload_object(&request, value.clone(), &upload_type);
+                self.limited(bucket, "put", upload)
+            })
+            .await;
 
         vlog::trace!(
             "Stored data to GCS for key {key} from bucket {bucket} and it took: {:?}",
@@ -209,50 +368,289 @@ impl ObjectStore for GoogleCloudStorage {
             started_at.elapsed(),
             "bucket" => bucket.as_str()
         );
-        object.map(drop).map_err(ObjectStoreError::from)
+        object.map(drop)
+    }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        let started_at = Instant::now();
+        let filename = Self::filename(bucket.as_str(), key);
+        let upload_type = UploadType::Simple(Media::new(filename));
+        // A zero generation precondition is only satisfied if there is no live object with the key.
+        let request = UploadObjectRequest {
+            bucket: self.bucket_prefix.clone(),
+            if_generation_match: Some(0),
+            ..Default::default()
+        };
+        let outcome = self
+            .retry_policy
+            .retry("put_if_absent", Some(bucket), is_transient_error, || {
+                let upload = self
+                    .client
+                    .upload_object(&request, value.clone(), &upload_type);
+                let upload = self.limited(bucket, "put_if_absent", upload);
+                async move {
+                    match upload.await {
+                        Ok(_) => Ok(PutOutcome::Created),
+                        // 412 Precondition Failed
+                        Err(HttpError::Response(response)) if response.code == 412 => {
+                            Ok(PutOutcome::AlreadyExists)
+                        }
+                        Err(err) => Err(err),
+                    }
+                }
+            })
+            .await;
+
+        vlog::trace!(
+            "Stored data to GCS for key {key} from bucket {bucket} with outcome {outcome:?} \
+             and it took: {:?}",
+            started_at.elapsed()
+        );
+        metrics::histogram!(
+            "server.object_store.storing_time",
+            started_at.elapsed(),
+            "bucket" => bucket.as_str()
+        );
+        outcome
     }
 
     async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
-        self.remove_inner(bucket.as_str(), key).await
+        self.remove_inner(bucket, key).await
     }
-}
 
-#[cfg(test)]
-mod test {
-    use std::sync::atomic::{AtomicU16, Ordering};
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        let request = GetObjectRequest {
+            bucket: self.bucket_prefix.clone(),
+            object: Self::filename(bucket.as_str(), key),
+            ..GetObjectRequest::default()
+        };
+        let object = self
+            .read(bucket, "get_size", |client| {
+                self.limited(bucket, "get_size", client.get_object(&request))
+            })
+            .await?;
+        u64::try_from(object.size).map_err(|err| ObjectStoreError::Other(err.into()))
+    }
 
-    use super::*;
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        let bucket_prefix = Self::filename(bucket.as_str(), "");
+        let request = ListObjectsRequest {
+            bucket: self.bucket_prefix.clone(),
+            prefix: Some(Self::filename(bucket.as_str(), prefix)),
+            page_token: continuation_token,
+            ..ListObjectsRequest::default()
+        };
+        let response = self
+            .read(bucket, "list", |client| {
+                self.limited(bucket, "list", client.list_objects(&request))
+            })
+            .await?;
 
-    #[tokio::test]
-    async fn test_retry_success_immediate() {
-        let result = retry(2, || async { Ok::<_, ()>(42) }).await;
-        assert_eq!(result, Ok(42));
+        let objects = response.items.unwrap_or_default().into_iter();
+        let objects = objects.filter_map(|object| {
+            let key = object.name.strip_prefix(&bucket_prefix)?;
+            Some(ObjectMeta {
+                key: key.to_owned(),
+                size: u64::try_from(object.size).unwrap_or(0),
+                last_modified: object.updated.map(SystemTime::from),
+            })
+        });
+        Ok((objects.collect(), response.next_page_token))
     }
 
-    #[tokio::test]
-    async fn test_retry_failure_exhausted() {
-        let result = retry(2, || async { Err::<i32, _>(()) }).await;
-        assert_eq!(result, Err(()));
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        // Objects of all buckets are stored in a single GCS bucket, so only its existence
+        // can be checked. Listing objects only requires the same permissions as reading them.
+        let request = ListObjectsRequest {
+            bucket: self.bucket_prefix.clone(),
+            prefix: Some(format!("{bucket}/")),
+            max_results: Some(1),
+            ..ListObjectsRequest::default()
+        };
+        let result = self
+            .retry_policy
+            .retry("health_check", Some(bucket), is_transient_error, || {
+                self.limited(bucket, "health_check", self.client.list_objects(&request))
+            })
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(ObjectStoreError::KeyNotFound(_)) => {
+                let message = format!("GCS bucket `{}` does not exist", self.bucket_prefix);
+                Err(ObjectStoreError::Other(message.into()))
+            }
+            Err(err) => Err(err),
+        }
     }
 
-    async fn retry_success_after_n_retries(n: u16) -> Result<u32, String> {
-        let retries = AtomicU16::new(0);
-        let result = retry(n, || async {
-            let retries = retries.fetch_add(1, Ordering::Relaxed);
-            if retries + 1 == n {
-                Ok(42)
-            } else {
-                Err(())
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        let filename = Self::filename(bucket.as_str(), key);
+        vlog::trace!(
+            "Generating download URL for key {filename} from bucket {}",
+            self.bucket_prefix
+        );
+
+        let options = SignedURLOptions {
+            method: SignedURLMethod::GET,
+            expires: expiry,
+            ..SignedURLOptions::default()
+        };
+        // The URL is signed by the credentials the client was created with, i.e., either
+        // the private key from the credential file, or the service account of the environment.
+        self.client
+            .signed_url(&self.bucket_prefix, &filename, None, None, options)
+            .await
+            .map_err(|err| match err {
+                SignedURLError::InvalidOption(_) => ObjectStoreError::Other(err.into()),
+                _ => ObjectStoreError::Auth(err.into()),
+            })
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let filename = Self::filename(bucket.as_str(), key);
+        vlog::trace!(
+            "Streaming data from GCS for key {filename} from bucket {}",
+            self.bucket_prefix
+        );
+
+        let request = GetObjectRequest {
+            bucket: self.bucket_prefix.clone(),
+            object: filename,
+            ..GetObjectRequest::default()
+        };
+        let (request, range) = (&request, &Range::default());
+        // Only the initial request is retried; errors while streaming are returned by the reader.
+        // The request permit is held by the reader until the blob is read.
+        let (stream, permit) = self
+            .read(bucket, "get_stream", |client| async move {
+                let permit = self
+                    .limiter
+                    .acquire_for_download(bucket, "get_stream")
+                    .await;
+                let stream = client.download_streamed_object(request, range).await?;
+                Ok::<_, HttpError>((stream, permit))
+            })
+            .await?;
+        let stream = stream.map_err(|err| io::Error::new(io::ErrorKind::Other, err));
+        let reader = Box::new(StreamReader::new(Box::pin(stream)));
+        Ok(Box::new(PermitReader::new(reader, permit)))
+    }
+
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        let started_at = Instant::now();
+        let filename = Self::filename(bucket.as_str(), key);
+        vlog::trace!(
+            "Streaming data to GCS for key {filename} from bucket {}",
+            self.bucket_prefix
+        );
+
+        let upload_type = UploadType::Simple(Media::new(filename));
+        let request = UploadObjectRequest {
+            bucket: self.bucket_prefix.clone(),
+            ..Default::default()
+        };
+        // The client requires the uploaded stream to be `Sync`, so chunks are read from
+        // the `reader` concurrently with the upload and passed through a channel.
+        let (chunks_sender, chunks_receiver) = mpsc::channel(UPLOAD_CHANNEL_CAPACITY);
+        let read_chunks = async move {
+            let mut chunks = ReaderStream::with_capacity(reader, UPLOAD_CHUNK_SIZE);
+            while let Some(chunk) = chunks.next().await {
+                let is_err = chunk.is_err();
+                if chunks_sender.send(chunk).await.is_err() || is_err {
+                    break; // The upload has failed, or there's nothing more to upload.
+                }
             }
-        })
-        .await;
+        };
+        let upload = self.client.upload_streamed_object(
+            &request,
+            ReceiverStream::new(chunks_receiver),
+            &upload_type,
+        );
+        // The upload waits on the `reader`, which may be a download from this store. This cannot
+        // deadlock since downloads never hold all permits.
+        let upload = async {
+            let _permit = self.limiter.acquire(bucket, "put_stream").await;
+            let (object, ()) = tokio::join!(upload, read_chunks);
+            object.map_err(ObjectStoreError::from)
+        };
+        // The upload cannot be retried, but it's still limited by the operation timeout.
+        let object = self
+            .retry_policy
+            .with_deadline("put_stream", Some(bucket), upload)
+            .await;
 
-        result.map_err(|_| "Retry failed".to_string())
+        vlog::trace!(
+            "Streamed data to GCS for key {key} from bucket {bucket} and it took: {:?}",
+            started_at.elapsed()
+        );
+        metrics::histogram!(
+            "server.object_store.storing_time",
+            started_at.elapsed(),
+            "bucket" => bucket.as_str()
+        );
+        object.map(drop)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use google_cloud_storage::http::error::ErrorResponse;
+
+    use super::*;
+
+    fn response_error(code: u16) -> HttpError {
+        HttpError::Response(ErrorResponse {
+            code,
+            errors: vec![],
+            message: format!("test error with code {code}"),
+        })
     }
 
-    #[tokio::test]
-    async fn test_retry_success_after_retry() {
-        let result = retry(2, || retry_success_after_n_retries(2)).await;
-        assert_eq!(result, Ok(42));
+    #[test]
+    fn test_error_mapping() {
+        let err = ObjectStoreError::from(response_error(404));
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+        for code in [401, 403] {
+            let err = ObjectStoreError::from(response_error(code));
+            assert!(matches!(err, ObjectStoreError::Auth(_)), "{err}");
+        }
+        for code in [408, 429, 500, 503] {
+            let err = ObjectStoreError::from(response_error(code));
+            assert!(matches!(err, ObjectStoreError::Transport(_)), "{err}");
+            assert!(err.is_transient());
+        }
+        for code in [400, 412] {
+            let err = ObjectStoreError::from(response_error(code));
+            assert!(matches!(err, ObjectStoreError::Other(_)), "{err}");
+            assert!(!err.is_transient());
+        }
+
+        let err = ObjectStoreError::from(HttpError::TokenSource("invalid grant".into()));
+        assert!(matches!(err, ObjectStoreError::Auth(_)), "{err}");
+        assert!(!err.is_transient());
     }
 }
@@ -0,0 +1,31 @@
+use zksync_health_check::{CheckHealth, CheckHealthStatus};
+
+use crate::raw::{Bucket, ObjectStore};
+
+/// Health check verifying that the object store is reachable using
+/// [`ObjectStore::health_check()`].
+#[derive(Debug)]
+pub struct ObjectStoreHealthCheck {
+    store: Box<dyn ObjectStore>,
+    bucket: Bucket,
+}
+
+impl ObjectStoreHealthCheck {
+    /// Creates a health check for the specified `bucket`.
+    pub fn new(store: Box<dyn ObjectStore>, bucket: Bucket) -> Self {
+        Self { store, bucket }
+    }
+}
+
+#[zksync_health_check::async_trait]
+impl CheckHealth for ObjectStoreHealthCheck {
+    async fn check_health(&self) -> CheckHealthStatus {
+        match self.store.health_check(self.bucket).await {
+            Ok(()) => CheckHealthStatus::Ready,
+            Err(err) => CheckHealthStatus::NotReady(format!(
+                "object store bucket `{}` is unreachable: {err}",
+                self.bucket
+            )),
+        }
+    }
+}
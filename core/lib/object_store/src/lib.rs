@@ -3,16 +3,41 @@
 //!
 //! - File-based storage saving blobs as separate files in the local filesystem
 //! - GCS-based storage
+//! - S3-based storage, which can also be used with S3-compatible stores (e.g., MinIO)
+//! - Azure Blob Storage-based storage
 //!
 //! These implementations are not exposed externally. Instead, a store trait object
 //! can be constructed using an [`ObjectStoreFactory`] based on the configuration.
 //! The configuration can be provided explicitly (see [`ObjectStoreFactory::new()`])
 //! or obtained from the environment (see [`ObjectStoreFactory::from_env()`]).
+//! If the configuration specifies a compression level, blobs are transparently compressed
+//! with zstd; blobs stored without compression remain readable. Similarly, blobs can be stored
+//! with a SHA-256 checksum, which is verified when the blob is fetched. A key prefix can be
+//! configured to namespace all keys, e.g. by the environment name. Blobs can be encrypted
+//! client-side with AES-256-GCM if encryption keys are configured. Small blobs that are
+//! fetched repeatedly (e.g., verification keys) can be cached in memory using a [`CachedObjectStore`].
+//! Requests to the underlying store are reported as metrics labelled by bucket and operation.
+//! The download and upload bandwidth of a store can be limited.
+//! To migrate between backends without a downtime, blobs can be mirrored to two stores.
+//! Requests to network-backed stores are retried, failing fast with
+//! a [`CircuitOpen`](ObjectStoreError::CircuitOpen) error while the store is unavailable.
+//! Identical blobs stored under different keys can be deduplicated by storing them
+//! by the hash of their contents.
 //!
 //! Besides the lower-level storage abstraction, the crate provides high-level
 //! typesafe `<dyn ObjectStore>::get()` and `<dyn ObjectStore>::put()` methods
 //! to store [(de)serializable objects](StoredObject). Prefer using these methods
-//! whenever possible.
+//! whenever possible. New object types can implement [`StoredObject`] using
+//! the [`impl_stored_object!`] macro; objects are serialized with bincode by default, but can use
+//! CBOR or JSON instead (see [`Codec`]). For large objects, `<dyn ObjectStore>::get_streamed()` and
+//! `<dyn ObjectStore>::put_streamed()` (de)serialize objects while they are being transferred,
+//! without buffering serialized objects in memory. Objects can be stored with a format version,
+//! so that objects written in an older format are migrated or rejected with
+//! an [`UnsupportedVersion`](ObjectStoreError::UnsupportedVersion) error. Final node proofs
+//! for an L1 batch can be archived in a single [`ProofBundle`] to fetch them in one request.
+//!
+//! The [`migration`] module provides helpers to copy blobs between stores, e.g. to migrate them
+//! to another backend.
 
 // Linter settings.
 #![warn(missing_debug_implementations, bare_trait_objects)]
@@ -23,21 +48,51 @@
     clippy::doc_markdown
 )]
 
+mod azure;
+mod bundle;
+mod cache;
+mod checksum;
+mod circuit_breaker;
+mod compression;
+mod content_addressed;
+mod encryption;
 mod file;
 mod gcs;
+mod healthcheck;
+mod limiter;
+mod metered;
+pub mod migration;
+mod mirror;
 mod mock;
 mod objects;
+mod prefix;
 mod raw;
+mod retries;
+mod s3;
+mod streams;
+mod throttle;
+mod versioning;
 
 // Re-export `bincode` crate so that client binaries can conveniently use it.
 pub use bincode;
 
-#[doc(hidden)] // used by the `serialize_using_bincode!` macro
+#[doc(hidden)] // used by the `serialize_using_*!` macros
 pub mod _reexports {
     pub use crate::raw::BoxedError;
+    pub use ciborium;
+    pub use serde_json;
 }
 
 pub use self::{
-    objects::{AggregationsKey, CircuitKey, ClosedFormInputKey, FriCircuitKey, StoredObject},
-    raw::{Bucket, ObjectStore, ObjectStoreError, ObjectStoreFactory},
+    bundle::{proof_key, ProofBundle},
+    cache::CachedObjectStore,
+    healthcheck::ObjectStoreHealthCheck,
+    objects::{
+        AggregationsKey, CircuitKey, ClosedFormInputKey, Codec, FriCircuitKey, StoredObject,
+    },
+    raw::{
+        BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, ObjectStoreFactory,
+        PutOutcome,
+    },
+    versioning::{deserialize_versioned, serialize_versioned},
 };
@@ -0,0 +1,189 @@
+//! Limiting the number of concurrent requests to an object store.
+
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+
+use std::{
+    fmt, io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use crate::raw::{BlobReader, Bucket};
+
+/// Limits the number of concurrent requests made by a store.
+///
+/// Streamed downloads hold a permit until the blob is read by the caller, who may make other
+/// requests to the same store in the meantime (e.g., copying blobs between keys). To prevent
+/// a deadlock, downloads can hold at most all permits but one, and the remaining permits are
+/// used by requests that complete without waiting on the caller.
+#[derive(Debug)]
+pub(crate) struct RequestLimiter {
+    request_permits: Arc<Semaphore>,
+    download_permits: Arc<Semaphore>,
+}
+
+/// Permit to make a request returned by [`RequestLimiter`]. The permit is released on drop.
+#[derive(Debug)]
+#[must_use = "request permit is released immediately if not used"]
+pub(crate) struct RequestPermit {
+    _request: OwnedSemaphorePermit,
+    _download: Option<OwnedSemaphorePermit>,
+}
+
+impl RequestLimiter {
+    /// Creates a limiter with the specified max number of concurrent requests. At least
+    /// 2 concurrent requests are allowed, so that a request doesn't wait for a download.
+    pub fn new(max_concurrent_requests: usize) -> Self {
+        let max_concurrent_requests = max_concurrent_requests.max(2);
+        Self {
+            request_permits: Arc::new(Semaphore::new(max_concurrent_requests)),
+            download_permits: Arc::new(Semaphore::new(max_concurrent_requests - 1)),
+        }
+    }
+
+    /// Waits for a permit to make a request. The waiting time is reported as a histogram
+    /// labeled with the `operation` and the `bucket`.
+    pub async fn acquire(&self, bucket: Bucket, operation: &'static str) -> RequestPermit {
+        let started_at = Instant::now();
+        let permit = RequestPermit {
+            _request: Self::acquire_from(&self.request_permits).await,
+            _download: None,
+        };
+        Self::report_wait_time(bucket, operation, started_at);
+        permit
+    }
+
+    /// Waits for a permit to make a streamed download. The permit should be held until
+    /// the downloaded blob is read; see [`PermitReader`].
+    pub async fn acquire_for_download(
+        &self,
+        bucket: Bucket,
+        operation: &'static str,
+    ) -> RequestPermit {
+        let started_at = Instant::now();
+        // The download permit must be acquired first, so that downloads waiting for a permit
+        // don't hold request permits.
+        let download = Self::acquire_from(&self.download_permits).await;
+        let permit = RequestPermit {
+            _request: Self::acquire_from(&self.request_permits).await,
+            _download: Some(download),
+        };
+        Self::report_wait_time(bucket, operation, started_at);
+        permit
+    }
+
+    async fn acquire_from(semaphore: &Arc<Semaphore>) -> OwnedSemaphorePermit {
+        Arc::clone(semaphore)
+            .acquire_owned()
+            .await
+            .expect("request semaphore is never closed")
+    }
+
+    fn report_wait_time(bucket: Bucket, operation: &'static str, started_at: Instant) {
+        metrics::histogram!(
+            "server.object_store.permit_wait_time",
+            started_at.elapsed(),
+            "bucket" => bucket.as_str(),
+            "operation" => operation
+        );
+    }
+}
+
+/// Reader holding a [`RequestPermit`] until the wrapped reader is exhausted, fails,
+/// or is dropped.
+pub(crate) struct PermitReader {
+    inner: BlobReader,
+    permit: Option<RequestPermit>,
+}
+
+impl fmt::Debug for PermitReader {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("PermitReader")
+            .field("permit", &self.permit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PermitReader {
+    pub fn new(inner: BlobReader, permit: RequestPermit) -> Self {
+        Self {
+            inner,
+            permit: Some(permit),
+        }
+    }
+}
+
+impl AsyncRead for PermitReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled_len = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let is_finished = match &poll {
+            Poll::Ready(Ok(())) => buf.filled().len() == filled_len && buf.remaining() > 0,
+            Poll::Ready(Err(_)) => true,
+            Poll::Pending => false,
+        };
+        if is_finished {
+            self.permit = None;
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::FutureExt;
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_downloads_leave_permits_for_requests() {
+        let limiter = RequestLimiter::new(3);
+        let _downloads = [
+            limiter
+                .acquire_for_download(Bucket::ProverJobsFri, "get_stream")
+                .await,
+            limiter
+                .acquire_for_download(Bucket::ProverJobsFri, "get_stream")
+                .await,
+        ];
+        let download = limiter.acquire_for_download(Bucket::ProverJobsFri, "get_stream");
+        assert!(download.now_or_never().is_none());
+        assert_eq!(limiter.request_permits.available_permits(), 1);
+
+        let request = limiter.acquire(Bucket::ProverJobsFri, "put").await;
+        let next_request = limiter.acquire(Bucket::ProverJobsFri, "put");
+        assert!(next_request.now_or_never().is_none());
+        drop(request);
+        assert_eq!(limiter.request_permits.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reader_releases_permit_once_exhausted() {
+        let limiter = RequestLimiter::new(2);
+        let permit = limiter
+            .acquire_for_download(Bucket::ProverJobsFri, "get_stream")
+            .await;
+        let mut reader = PermitReader::new(Box::new(io::Cursor::new(vec![1; 1_024])), permit);
+        assert_eq!(limiter.download_permits.available_permits(), 0);
+
+        let mut buffer = [0; 16];
+        reader.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(limiter.download_permits.available_permits(), 0);
+        let mut blob = vec![];
+        reader.read_to_end(&mut blob).await.unwrap();
+        assert_eq!(blob.len(), 1_024 - 16);
+        assert_eq!(limiter.download_permits.available_permits(), 1);
+        assert_eq!(limiter.request_permits.available_permits(), 2);
+    }
+}
@@ -0,0 +1,262 @@
+//! Per-bucket metrics for requests to the underlying store.
+
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+use crate::raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome};
+
+/// Store wrapper reporting the number of requests, errors (by error kind), request latency
+/// and the number of transferred bytes, labelled by bucket and operation.
+///
+/// The wrapper is placed directly on top of the backend, so that it reports blobs as they are
+/// transferred (i.e., compressed, encrypted etc.) rather than as they are seen by the caller.
+#[derive(Debug)]
+pub(crate) struct MeteredObjectStore {
+    inner: Box<dyn ObjectStore>,
+}
+
+impl MeteredObjectStore {
+    pub fn new(inner: Box<dyn ObjectStore>) -> Self {
+        Self { inner }
+    }
+}
+
+/// Reports metrics for a single request to the store.
+async fn observe<T>(
+    bucket: Bucket,
+    operation: &'static str,
+    request: impl Future<Output = Result<T, ObjectStoreError>> + Send,
+) -> Result<T, ObjectStoreError> {
+    let started_at = Instant::now();
+    let result = request.await;
+    metrics::histogram!(
+        "server.object_store.request_latency",
+        started_at.elapsed(),
+        "bucket" => bucket.as_str(),
+        "operation" => operation
+    );
+    metrics::increment_counter!(
+        "server.object_store.requests",
+        "bucket" => bucket.as_str(),
+        "operation" => operation
+    );
+    if let Err(err) = &result {
+        metrics::increment_counter!(
+            "server.object_store.errors",
+            "bucket" => bucket.as_str(),
+            "operation" => operation,
+            "kind" => err.kind()
+        );
+    }
+    result
+}
+
+fn report_transferred_bytes(bucket: Bucket, operation: &'static str, byte_count: u64) {
+    metrics::counter!(
+        "server.object_store.transferred_bytes",
+        byte_count,
+        "bucket" => bucket.as_str(),
+        "operation" => operation
+    );
+}
+
+/// Reader counting the read bytes. The count is reported when the reader is dropped,
+/// so that a streamed transfer is reported once regardless of its size.
+struct CountingReader {
+    inner: BlobReader,
+    bucket: Bucket,
+    operation: &'static str,
+    byte_count: u64,
+}
+
+impl CountingReader {
+    fn new(inner: BlobReader, bucket: Bucket, operation: &'static str) -> Self {
+        Self {
+            inner,
+            bucket,
+            operation,
+            byte_count: 0,
+        }
+    }
+}
+
+impl AsyncRead for CountingReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled_len = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.byte_count += (buf.filled().len() - filled_len) as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for CountingReader {
+    fn drop(&mut self) {
+        report_transferred_bytes(self.bucket, self.operation, self.byte_count);
+    }
+}
+
+#[async_trait]
+impl ObjectStore for MeteredObjectStore {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let blob = observe(bucket, "get", self.inner.get_raw(bucket, key)).await?;
+        report_transferred_bytes(bucket, "get", blob.len() as u64);
+        Ok(blob)
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        let byte_count = value.len() as u64;
+        observe(bucket, "put", self.inner.put_raw(bucket, key, value)).await?;
+        report_transferred_bytes(bucket, "put", byte_count);
+        Ok(())
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        observe(bucket, "remove", self.inner.remove_raw(bucket, key)).await
+    }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        let byte_count = value.len() as u64;
+        let request = self.inner.put_raw_if_absent(bucket, key, value);
+        let outcome = observe(bucket, "put_if_absent", request).await?;
+        if outcome == PutOutcome::Created {
+            report_transferred_bytes(bucket, "put_if_absent", byte_count);
+        }
+        Ok(outcome)
+    }
+
+    async fn remove_raw_many(
+        &self,
+        bucket: Bucket,
+        keys: &[String],
+    ) -> Result<(), ObjectStoreError> {
+        let request = self.inner.remove_raw_many(bucket, keys);
+        observe(bucket, "remove_many", request).await
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        observe(bucket, "get_size", self.inner.get_raw_size(bucket, key)).await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        observe(bucket, "exists", self.inner.exists_raw(bucket, key)).await
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        let request = self.inner.list_raw(bucket, prefix, continuation_token);
+        observe(bucket, "list", request).await
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        observe(bucket, "health_check", self.inner.health_check(bucket)).await
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        let request = self.inner.generate_download_url(bucket, key, expiry);
+        observe(bucket, "generate_download_url", request).await
+    }
+
+    // The reported latency is the time until the blob contents start being streamed.
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let request = self.inner.get_raw_stream(bucket, key);
+        let reader = observe(bucket, "get_stream", request).await?;
+        Ok(Box::new(CountingReader::new(reader, bucket, "get_stream")))
+    }
+
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        let reader = Box::new(CountingReader::new(reader, bucket, "put_stream"));
+        let request = self.inner.put_raw_stream(bucket, key, reader);
+        observe(bucket, "put_stream", request).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::mock::MockStore;
+
+    #[tokio::test]
+    async fn test_metered_store_forwards_requests() {
+        let store = MeteredObjectStore::new(Box::new(MockStore::default()));
+        let reader: BlobReader = Box::new(io::Cursor::new(vec![1, 2, 3]));
+        store
+            .put_raw_stream(Bucket::ProofsFri, "proof_1.bin", reader)
+            .await
+            .unwrap();
+
+        let mut reader = store
+            .get_raw_stream(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        let mut blob = vec![];
+        reader.read_to_end(&mut blob).await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+        let size = store
+            .get_raw_size(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert_eq!(size, 3);
+        assert!(store
+            .exists_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap());
+
+        store
+            .remove_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        let err = store
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+        assert_eq!(err.kind(), "key_not_found");
+        assert!(!store
+            .exists_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap());
+    }
+}
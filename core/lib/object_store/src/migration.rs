@@ -0,0 +1,255 @@
+//! Copying blobs between stores, e.g. to migrate blobs from a file-backed store to GCS.
+//!
+//! Blobs are streamed from the source to the destination store, so that large blobs don't need
+//! to be buffered in memory. Blobs are fetched and stored via the [`ObjectStore`] interface;
+//! thus, if the stores are created by an [`ObjectStoreFactory`](crate::ObjectStoreFactory),
+//! blobs are decompressed and verified when read from the source store, and compressed and
+//! checksummed according to the destination configuration when stored.
+
+use futures::{stream, StreamExt};
+
+use crate::raw::{Bucket, ObjectStore, ObjectStoreError};
+
+/// Progress of a [`migrate_prefix()`] run, reported after each processed blob.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationProgress {
+    /// Number of blobs listed so far. Blobs are listed page by page while the migration
+    /// progresses, so this number grows until the last page is listed.
+    pub listed: usize,
+    /// Number of blobs copied to the destination store.
+    pub copied: usize,
+    /// Number of blobs skipped because they are already present in the destination store.
+    pub skipped: usize,
+    /// Number of blobs that have failed to be copied.
+    pub failed: usize,
+}
+
+impl MigrationProgress {
+    /// Returns the number of processed blobs.
+    pub fn processed(&self) -> usize {
+        self.copied + self.skipped + self.failed
+    }
+}
+
+/// Results of a completed [`migrate_prefix()`] run.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// Number of blobs copied to the destination store.
+    pub copied: usize,
+    /// Number of blobs skipped because they are already present in the destination store.
+    pub skipped: usize,
+    /// Keys of the blobs that have failed to be copied together with the corresponding errors,
+    /// in no particular order.
+    pub failures: Vec<(String, ObjectStoreError)>,
+}
+
+impl MigrationReport {
+    /// Checks whether all blobs were migrated successfully.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+#[derive(Debug)]
+enum CopyOutcome {
+    Copied,
+    Skipped,
+}
+
+/// Copies a blob with the given key from the `src` to the `dst` store, replacing the blob
+/// in `dst` if it exists.
+///
+/// # Errors
+///
+/// Returns an error if the blob cannot be fetched from `src` or stored in `dst`.
+pub async fn copy_object(
+    src: &dyn ObjectStore,
+    dst: &dyn ObjectStore,
+    bucket: Bucket,
+    key: &str,
+) -> Result<(), ObjectStoreError> {
+    let reader = src.get_raw_stream(bucket, key).await?;
+    dst.put_raw_stream(bucket, key, reader).await
+}
+
+async fn copy_object_if_missing(
+    src: &dyn ObjectStore,
+    dst: &dyn ObjectStore,
+    bucket: Bucket,
+    key: &str,
+) -> Result<CopyOutcome, ObjectStoreError> {
+    match dst.get_raw_size(bucket, key).await {
+        Ok(_) => return Ok(CopyOutcome::Skipped),
+        Err(ObjectStoreError::KeyNotFound(_)) => { /* the blob needs to be copied */ }
+        Err(err) => return Err(err),
+    }
+    copy_object(src, dst, bucket, key).await?;
+    Ok(CopyOutcome::Copied)
+}
+
+/// Copies all blobs with keys starting with `prefix` in the given bucket from the `src`
+/// to the `dst` store, copying at most `max_concurrency` blobs at a time. Blobs already present
+/// in `dst` are skipped, so an interrupted migration can be resumed by repeating it.
+///
+/// Blobs are listed and copied page by page, so that the migration can start without listing
+/// the entire bucket. `on_progress` is called after each processed blob. A failure to copy a blob
+/// doesn't abort the migration; instead, failed keys are collected in the returned report.
+///
+/// # Errors
+///
+/// Returns an error if the blobs in `src` cannot be listed. Blobs from the previously listed pages
+/// may be copied in this case.
+///
+/// # Panics
+///
+/// Panics if `max_concurrency` is zero.
+pub async fn migrate_prefix(
+    src: &dyn ObjectStore,
+    dst: &dyn ObjectStore,
+    bucket: Bucket,
+    prefix: &str,
+    max_concurrency: usize,
+    mut on_progress: impl FnMut(&MigrationProgress) + Send,
+) -> Result<MigrationReport, ObjectStoreError> {
+    assert!(max_concurrency > 0, "`max_concurrency` must be positive");
+
+    vlog::info!("Migrating blobs with prefix `{prefix}` in bucket {bucket}");
+    let mut progress = MigrationProgress::default();
+    let mut report = MigrationReport::default();
+    let mut continuation_token = None;
+    loop {
+        let (objects, next_token) = src.list_raw(bucket, prefix, continuation_token).await?;
+        progress.listed += objects.len();
+
+        let mut results = stream::iter(objects)
+            .map(|object| async move {
+                let result = copy_object_if_missing(src, dst, bucket, &object.key).await;
+                (object.key, result)
+            })
+            .buffer_unordered(max_concurrency);
+        while let Some((key, result)) = results.next().await {
+            match result {
+                Ok(CopyOutcome::Copied) => progress.copied += 1,
+                Ok(CopyOutcome::Skipped) => progress.skipped += 1,
+                Err(err) => {
+                    vlog::warn!("Failed migrating blob {key} in bucket {bucket}: {err}");
+                    progress.failed += 1;
+                    report.failures.push((key, err));
+                }
+            }
+            on_progress(&progress);
+        }
+
+        continuation_token = next_token;
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+
+    report.copied = progress.copied;
+    report.skipped = progress.skipped;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{mock::MockStore, raw::ObjectMeta};
+
+    /// Store failing to fetch blobs with keys containing "broken".
+    #[derive(Debug, Default)]
+    struct PartiallyBrokenStore(MockStore);
+
+    #[async_trait]
+    impl ObjectStore for PartiallyBrokenStore {
+        async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+            if key.contains("broken") {
+                return Err(ObjectStoreError::Transport("connection reset".into()));
+            }
+            self.0.get_raw(bucket, key).await
+        }
+
+        async fn put_raw(
+            &self,
+            bucket: Bucket,
+            key: &str,
+            value: Vec<u8>,
+        ) -> Result<(), ObjectStoreError> {
+            self.0.put_raw(bucket, key, value).await
+        }
+
+        async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+            self.0.remove_raw(bucket, key).await
+        }
+
+        async fn list_raw(
+            &self,
+            bucket: Bucket,
+            prefix: &str,
+            continuation_token: Option<String>,
+        ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+            self.0.list_raw(bucket, prefix, continuation_token).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copying_object() {
+        let src = MockStore::default();
+        let dst = MockStore::default();
+        src.put_raw(Bucket::ProofsFri, "proof_1.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+        copy_object(&src, &dst, Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        let blob = dst.get_raw(Bucket::ProofsFri, "proof_1.bin").await.unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+
+        let err = copy_object(&src, &dst, Bucket::ProofsFri, "missing.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_migrating_prefix() {
+        let src = PartiallyBrokenStore::default();
+        for key in ["proof_1.bin", "proof_2.bin", "proof_broken.bin", "vk.bin"] {
+            src.put_raw(Bucket::ProofsFri, key, key.as_bytes().to_vec())
+                .await
+                .unwrap();
+        }
+        let dst = MockStore::default();
+        dst.put_raw(Bucket::ProofsFri, "proof_2.bin", vec![0])
+            .await
+            .unwrap();
+
+        let mut reported_progress = vec![];
+        let report = migrate_prefix(&src, &dst, Bucket::ProofsFri, "proof_", 2, |progress| {
+            reported_progress.push(progress.clone());
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(report.copied, 1);
+        assert_eq!(report.skipped, 1);
+        assert!(!report.is_complete());
+        assert_eq!(report.failures.len(), 1);
+        let (failed_key, err) = &report.failures[0];
+        assert_eq!(failed_key, "proof_broken.bin");
+        assert!(matches!(err, ObjectStoreError::Transport(_)), "{err}");
+
+        assert_eq!(reported_progress.len(), 3);
+        for (i, progress) in reported_progress.iter().enumerate() {
+            assert_eq!(progress.listed, 3);
+            assert_eq!(progress.processed(), i + 1);
+        }
+        // The existing blob is not overwritten.
+        let blob = dst.get_raw(Bucket::ProofsFri, "proof_2.bin").await.unwrap();
+        assert_eq!(blob, [0]);
+        let keys = dst.keys(Bucket::ProofsFri).await;
+        assert_eq!(keys, ["proof_1.bin", "proof_2.bin"]);
+    }
+}
@@ -0,0 +1,377 @@
+//! Mirroring of blobs to two stores, e.g. to migrate between backends without a downtime.
+
+use async_trait::async_trait;
+use futures::future;
+
+use std::{future::Future, time::Duration};
+
+use crate::raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome};
+
+/// Store writing blobs to both the primary and the secondary store, and reading them from
+/// the primary store with a fallback to the secondary one if a blob is missing. Removals
+/// are performed in both stores.
+///
+/// Failures of the secondary store on writes and removals are logged and reported as metrics;
+/// they only fail the operation if `strict_writes` is set. Listing and download URLs only
+/// use the primary store.
+#[derive(Debug)]
+pub(crate) struct MirroredObjectStore {
+    primary: Box<dyn ObjectStore>,
+    secondary: Box<dyn ObjectStore>,
+    strict_writes: bool,
+}
+
+impl MirroredObjectStore {
+    pub fn new(
+        primary: Box<dyn ObjectStore>,
+        secondary: Box<dyn ObjectStore>,
+        strict_writes: bool,
+    ) -> Self {
+        Self {
+            primary,
+            secondary,
+            strict_writes,
+        }
+    }
+
+    /// Handles the result of a write or removal in the secondary store.
+    fn check_secondary(
+        &self,
+        bucket: Bucket,
+        operation: &'static str,
+        result: Result<(), ObjectStoreError>,
+    ) -> Result<(), ObjectStoreError> {
+        let Err(err) = result else {
+            return Ok(());
+        };
+        metrics::increment_counter!(
+            "server.object_store.mirror_secondary_failures",
+            "bucket" => bucket.as_str(),
+            "operation" => operation
+        );
+        vlog::warn!("Failed {operation} request to the secondary store for bucket {bucket}: {err}");
+        if self.strict_writes {
+            Err(err)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returns the result of the `primary` read, falling back to the `secondary` read
+/// if the blob is missing in the primary store.
+async fn read_with_fallback<T, Fut>(
+    bucket: Bucket,
+    operation: &'static str,
+    primary: impl Future<Output = Result<T, ObjectStoreError>>,
+    secondary: impl FnOnce() -> Fut,
+) -> Result<T, ObjectStoreError>
+where
+    Fut: Future<Output = Result<T, ObjectStoreError>>,
+{
+    let err = match primary.await {
+        Err(ObjectStoreError::KeyNotFound(err)) => err,
+        result => return result,
+    };
+    match secondary().await {
+        Ok(value) => {
+            report_fallback_read(bucket, operation);
+            Ok(value)
+        }
+        Err(ObjectStoreError::KeyNotFound(_)) => Err(ObjectStoreError::KeyNotFound(err)),
+        Err(err) => Err(err),
+    }
+}
+
+fn report_fallback_read(bucket: Bucket, operation: &'static str) {
+    metrics::increment_counter!(
+        "server.object_store.mirror_fallback_reads",
+        "bucket" => bucket.as_str(),
+        "operation" => operation
+    );
+}
+
+#[async_trait]
+impl ObjectStore for MirroredObjectStore {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let primary = self.primary.get_raw(bucket, key);
+        read_with_fallback(bucket, "get", primary, || {
+            self.secondary.get_raw(bucket, key)
+        })
+        .await
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        let (primary_result, secondary_result) = future::join(
+            self.primary.put_raw(bucket, key, value.clone()),
+            self.secondary.put_raw(bucket, key, value),
+        )
+        .await;
+        primary_result?;
+        self.check_secondary(bucket, "put", secondary_result)
+    }
+
+    /// The outcome is determined by the primary store. The value is stored in the secondary
+    /// store if it's absent there, regardless of the outcome for the primary store.
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        let (primary_result, secondary_result) = future::join(
+            self.primary.put_raw_if_absent(bucket, key, value.clone()),
+            self.secondary.put_raw_if_absent(bucket, key, value),
+        )
+        .await;
+        let outcome = primary_result?;
+        self.check_secondary(bucket, "put_if_absent", secondary_result.map(drop))?;
+        Ok(outcome)
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        let (primary_result, secondary_result) = future::join(
+            self.primary.remove_raw(bucket, key),
+            self.secondary.remove_raw(bucket, key),
+        )
+        .await;
+        primary_result?;
+        let secondary_result = match secondary_result {
+            Err(ObjectStoreError::KeyNotFound(_)) => Ok(()),
+            result => result,
+        };
+        self.check_secondary(bucket, "remove", secondary_result)
+    }
+
+    async fn remove_raw_many(
+        &self,
+        bucket: Bucket,
+        keys: &[String],
+    ) -> Result<(), ObjectStoreError> {
+        let (primary_result, secondary_result) = future::join(
+            self.primary.remove_raw_many(bucket, keys),
+            self.secondary.remove_raw_many(bucket, keys),
+        )
+        .await;
+        primary_result?;
+        self.check_secondary(bucket, "remove_many", secondary_result)
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        let primary = self.primary.get_raw_size(bucket, key);
+        read_with_fallback(bucket, "get_size", primary, || {
+            self.secondary.get_raw_size(bucket, key)
+        })
+        .await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        if self.primary.exists_raw(bucket, key).await? {
+            return Ok(true);
+        }
+        let exists = self.secondary.exists_raw(bucket, key).await?;
+        if exists {
+            report_fallback_read(bucket, "exists");
+        }
+        Ok(exists)
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        self.primary
+            .list_raw(bucket, prefix, continuation_token)
+            .await
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        let (primary_result, secondary_result) = future::join(
+            self.primary.health_check(bucket),
+            self.secondary.health_check(bucket),
+        )
+        .await;
+        primary_result.and(secondary_result)
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        self.primary
+            .generate_download_url(bucket, key, expiry)
+            .await
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let primary = self.primary.get_raw_stream(bucket, key);
+        read_with_fallback(bucket, "get_stream", primary, || {
+            self.secondary.get_raw_stream(bucket, key)
+        })
+        .await
+    }
+
+    // `put_raw_stream()` uses the default implementation, since the reader cannot be consumed
+    // by both stores; thus, streamed blobs are buffered in memory.
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mock::MockStore;
+
+    /// Store failing all requests.
+    #[derive(Debug)]
+    struct BrokenStore;
+
+    #[async_trait]
+    impl ObjectStore for BrokenStore {
+        async fn get_raw(&self, _bucket: Bucket, _key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+            Err(ObjectStoreError::Transport("connection reset".into()))
+        }
+
+        async fn put_raw(
+            &self,
+            _bucket: Bucket,
+            _key: &str,
+            _value: Vec<u8>,
+        ) -> Result<(), ObjectStoreError> {
+            Err(ObjectStoreError::Transport("connection reset".into()))
+        }
+
+        async fn remove_raw(&self, _bucket: Bucket, _key: &str) -> Result<(), ObjectStoreError> {
+            Err(ObjectStoreError::Transport("connection reset".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reads_fall_back_to_secondary_store() {
+        let primary = Arc::new(MockStore::default());
+        let secondary = Arc::new(MockStore::default());
+        let store = MirroredObjectStore::new(
+            Box::new(Arc::clone(&primary)),
+            Box::new(Arc::clone(&secondary)),
+            false,
+        );
+        secondary
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        let blob = store
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+        let size = store
+            .get_raw_size(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert_eq!(size, 3);
+        assert!(store
+            .exists_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap());
+        assert!(!store
+            .exists_raw(Bucket::ProofsFri, "missing.bin")
+            .await
+            .unwrap());
+        let err = store
+            .get_raw(Bucket::ProofsFri, "missing.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+
+        // Blobs in the primary store take precedence.
+        primary
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", vec![4, 5])
+            .await
+            .unwrap();
+        let blob = store
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_writes_and_removals_are_mirrored() {
+        let primary = Arc::new(MockStore::default());
+        let secondary = Arc::new(MockStore::default());
+        let store = MirroredObjectStore::new(
+            Box::new(Arc::clone(&primary)),
+            Box::new(Arc::clone(&secondary)),
+            true,
+        );
+        store
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+        store
+            .put_raw(Bucket::ProofsFri, "proof_2.bin", vec![4, 5])
+            .await
+            .unwrap();
+        assert_eq!(primary.keys(Bucket::ProofsFri).await.len(), 2);
+        assert_eq!(secondary.keys(Bucket::ProofsFri).await.len(), 2);
+
+        store
+            .remove_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert_eq!(primary.keys(Bucket::ProofsFri).await, ["proof_2.bin"]);
+        assert_eq!(secondary.keys(Bucket::ProofsFri).await, ["proof_2.bin"]);
+    }
+
+    #[tokio::test]
+    async fn test_secondary_write_failures() {
+        let primary = Arc::new(MockStore::default());
+        let store =
+            MirroredObjectStore::new(Box::new(Arc::clone(&primary)), Box::new(BrokenStore), false);
+        store
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+        let blob = store
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+        store
+            .remove_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        // The primary store is not affected by failures of the secondary store.
+        assert!(primary.keys(Bucket::ProofsFri).await.is_empty());
+        // Since the blob may be present in the secondary store, it cannot be reported as missing.
+        let err = store
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Transport(_)), "{err}");
+
+        let strict_store =
+            MirroredObjectStore::new(Box::new(Arc::clone(&primary)), Box::new(BrokenStore), true);
+        let err = strict_store
+            .put_raw(Bucket::ProofsFri, "proof_2.bin", vec![4, 5])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Transport(_)), "{err}");
+        // The blob is still written to the primary store.
+        assert_eq!(primary.keys(Bucket::ProofsFri).await, ["proof_2.bin"]);
+    }
+}
@@ -5,7 +5,7 @@ use tokio::sync::Mutex;
 
 use std::collections::HashMap;
 
-use crate::raw::{Bucket, ObjectStore, ObjectStoreError};
+use crate::raw::{Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome};
 
 type BucketMap = HashMap<String, Vec<u8>>;
 
@@ -14,6 +14,19 @@ pub(crate) struct MockStore {
     inner: Mutex<HashMap<Bucket, BucketMap>>,
 }
 
+impl MockStore {
+    /// Returns keys of the objects stored in the `bucket` in the alphabetical order.
+    pub async fn keys(&self, bucket: Bucket) -> Vec<String> {
+        let lock = self.inner.lock().await;
+        let mut keys: Vec<_> = lock
+            .get(&bucket)
+            .map(|bucket_map| bucket_map.keys().cloned().collect())
+            .unwrap_or_default();
+        keys.sort_unstable();
+        keys
+    }
+}
+
 #[async_trait]
 impl ObjectStore for MockStore {
     async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
@@ -37,6 +50,21 @@ impl ObjectStore for MockStore {
         Ok(())
     }
 
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        let mut lock = self.inner.lock().await;
+        let bucket_map = lock.entry(bucket).or_default();
+        if bucket_map.contains_key(key) {
+            return Ok(PutOutcome::AlreadyExists);
+        }
+        bucket_map.insert(key.to_owned(), value);
+        Ok(PutOutcome::Created)
+    }
+
     async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
         let mut lock = self.inner.lock().await;
         let Some(bucket_map) = lock.get_mut(&bucket) else {
@@ -45,4 +73,24 @@ impl ObjectStore for MockStore {
         bucket_map.remove(key);
         Ok(())
     }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        _continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        let lock = self.inner.lock().await;
+        let Some(bucket_map) = lock.get(&bucket) else {
+            return Ok((vec![], None));
+        };
+        let objects = bucket_map.iter().filter(|(key, _)| key.starts_with(prefix));
+        let objects = objects.map(|(key, value)| ObjectMeta {
+            key: key.clone(),
+            size: value.len() as u64,
+            last_modified: None,
+        });
+        // All blobs are returned in a single page.
+        Ok((objects.collect(), None))
+    }
 }
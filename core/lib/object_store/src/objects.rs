@@ -14,12 +14,84 @@ use zksync_types::{
     L1BatchNumber,
 };
 
-use crate::raw::{BoxedError, Bucket, ObjectStore, ObjectStoreError};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::{StreamReader, SyncIoBridge};
+
+use std::{
+    fmt,
+    io::{self, Read as _, Write as _},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    raw::{BoxedError, Bucket, ObjectStore, ObjectStoreError, PutOutcome},
+    streams::ChannelWriter,
+    versioning,
+};
+
+/// Size of chunks in which objects are serialized by `<dyn ObjectStore>::put_streamed()`.
+const STREAM_CHUNK_SIZE: usize = 1 << 20;
+/// Maximum number of serialized chunks buffered before they are uploaded.
+const STREAM_CHANNEL_CAPACITY: usize = 4;
+
+/// Codec used to serialize a [`StoredObject`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// Compact binary encoding; used for the vast majority of objects.
+    Bincode,
+    /// CBOR, a binary encoding that can be inspected with standard tools.
+    Cbor,
+    /// JSON; intended for small objects that are inspected manually (e.g., debug artifacts).
+    Json,
+}
+
+impl Codec {
+    pub(crate) const fn to_byte(self) -> u8 {
+        match self {
+            Self::Bincode => 0,
+            Self::Cbor => 1,
+            Self::Json => 2,
+        }
+    }
+
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        Some(match byte {
+            0 => Self::Bincode,
+            1 => Self::Cbor,
+            2 => Self::Json,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::Bincode => "bincode",
+            Self::Cbor => "cbor",
+            Self::Json => "json",
+        })
+    }
+}
 
 /// Object that can be stored in an [`ObjectStore`].
 pub trait StoredObject: Sized {
     /// Bucket in which values are stored.
     const BUCKET: Bucket;
+    /// Codec used by [`Self::serialize()`] and [`Self::deserialize()`]. Values encoded
+    /// with a codec other than bincode are stored with a header specifying the codec, and values
+    /// encoded with another codec are rejected on deserialization. Set by the `serialize_using_*!`
+    /// macros.
+    const CODEC: Codec = Codec::Bincode;
+    /// Version of the format produced by [`Self::serialize()`]. Values with a positive version
+    /// are stored with a header specifying the version; values without the header have version 0.
+    /// The version should be bumped whenever the serialization format changes.
+    const FORMAT_VERSION: u16 = 0;
+    /// Oldest format version that can be deserialized (using [`Self::migrate()`] for versions
+    /// older than [`Self::FORMAT_VERSION`]).
+    const MIN_FORMAT_VERSION: u16 = Self::FORMAT_VERSION;
     /// Logical unique key for the object. The lifetime param allows defining keys
     /// that borrow data; see [`CircuitKey`] for an example.
     type Key<'a>: Copy;
@@ -40,13 +112,50 @@ pub trait StoredObject: Sized {
     ///
     /// Returns an error if deserialization fails.
     fn deserialize(bytes: Vec<u8>) -> Result<Self, BoxedError>;
+
+    /// Serializes a value into the `writer`. The default implementation serializes the value
+    /// to a blob using [`Self::serialize()`]; objects that can be serialized incrementally
+    /// should override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing fails.
+    fn serialize_into(&self, writer: &mut dyn io::Write) -> Result<(), BoxedError> {
+        writer.write_all(&self.serialize()?)?;
+        Ok(())
+    }
+
+    /// Deserializes a value from the `reader`. The default implementation reads the entire
+    /// blob and deserializes it using [`Self::deserialize()`]; objects that can be deserialized
+    /// incrementally should override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or deserialization fails.
+    fn deserialize_from(reader: &mut dyn io::Read) -> Result<Self, BoxedError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        Self::deserialize(bytes)
+    }
+
+    /// Deserializes a value from a blob with an older format `version` in the
+    /// `MIN_FORMAT_VERSION..FORMAT_VERSION` range. The default implementation returns an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if deserialization fails.
+    fn migrate(version: u16, _bytes: Vec<u8>) -> Result<Self, BoxedError> {
+        Err(format!("migration from format v{version} is not implemented").into())
+    }
 }
 
-/// Derives [`StoredObject::serialize()`] and [`StoredObject::deserialize()`] using
-/// the `bincode` (de)serializer. Should be used in `impl StoredObject` blocks.
+/// Derives [`StoredObject::serialize()`], [`StoredObject::deserialize()`] and their incremental
+/// counterparts using the `bincode` (de)serializer. Should be used in `impl StoredObject` blocks.
 #[macro_export]
 macro_rules! serialize_using_bincode {
     () => {
+        const CODEC: $crate::Codec = $crate::Codec::Bincode;
+
         fn serialize(
             &self,
         ) -> std::result::Result<std::vec::Vec<u8>, $crate::_reexports::BoxedError> {
@@ -58,6 +167,272 @@ macro_rules! serialize_using_bincode {
         ) -> std::result::Result<Self, $crate::_reexports::BoxedError> {
             $crate::bincode::deserialize(&bytes).map_err(std::convert::From::from)
         }
+
+        fn serialize_into(
+            &self,
+            writer: &mut dyn std::io::Write,
+        ) -> std::result::Result<(), $crate::_reexports::BoxedError> {
+            $crate::bincode::serialize_into(writer, self).map_err(std::convert::From::from)
+        }
+
+        fn deserialize_from(
+            reader: &mut dyn std::io::Read,
+        ) -> std::result::Result<Self, $crate::_reexports::BoxedError> {
+            $crate::bincode::deserialize_from(reader).map_err(std::convert::From::from)
+        }
+    };
+}
+
+/// Same as [`serialize_using_bincode!`], but uses the CBOR (de)serializer.
+#[macro_export]
+macro_rules! serialize_using_cbor {
+    () => {
+        const CODEC: $crate::Codec = $crate::Codec::Cbor;
+
+        fn serialize(
+            &self,
+        ) -> std::result::Result<std::vec::Vec<u8>, $crate::_reexports::BoxedError> {
+            let mut bytes = std::vec::Vec::new();
+            $crate::_reexports::ciborium::ser::into_writer(self, &mut bytes)?;
+            std::result::Result::Ok(bytes)
+        }
+
+        fn deserialize(
+            bytes: std::vec::Vec<u8>,
+        ) -> std::result::Result<Self, $crate::_reexports::BoxedError> {
+            $crate::_reexports::ciborium::de::from_reader(bytes.as_slice())
+                .map_err(std::convert::From::from)
+        }
+
+        fn serialize_into(
+            &self,
+            writer: &mut dyn std::io::Write,
+        ) -> std::result::Result<(), $crate::_reexports::BoxedError> {
+            $crate::_reexports::ciborium::ser::into_writer(self, writer)
+                .map_err(std::convert::From::from)
+        }
+
+        fn deserialize_from(
+            reader: &mut dyn std::io::Read,
+        ) -> std::result::Result<Self, $crate::_reexports::BoxedError> {
+            $crate::_reexports::ciborium::de::from_reader(reader).map_err(std::convert::From::from)
+        }
+    };
+}
+
+/// Same as [`serialize_using_bincode!`], but uses the JSON (de)serializer. Values are serialized
+/// in the pretty-printed form, so that they can be read without additional tools.
+#[macro_export]
+macro_rules! serialize_using_json {
+    () => {
+        const CODEC: $crate::Codec = $crate::Codec::Json;
+
+        fn serialize(
+            &self,
+        ) -> std::result::Result<std::vec::Vec<u8>, $crate::_reexports::BoxedError> {
+            $crate::_reexports::serde_json::to_vec_pretty(self).map_err(std::convert::From::from)
+        }
+
+        fn deserialize(
+            bytes: std::vec::Vec<u8>,
+        ) -> std::result::Result<Self, $crate::_reexports::BoxedError> {
+            $crate::_reexports::serde_json::from_slice(&bytes).map_err(std::convert::From::from)
+        }
+
+        fn serialize_into(
+            &self,
+            writer: &mut dyn std::io::Write,
+        ) -> std::result::Result<(), $crate::_reexports::BoxedError> {
+            $crate::_reexports::serde_json::to_writer_pretty(writer, self)
+                .map_err(std::convert::From::from)
+        }
+
+        fn deserialize_from(
+            reader: &mut dyn std::io::Read,
+        ) -> std::result::Result<Self, $crate::_reexports::BoxedError> {
+            $crate::_reexports::serde_json::from_reader(reader).map_err(std::convert::From::from)
+        }
+    };
+}
+
+/// Implements [`StoredObject`] for a type based on the bucket, the key format and the codec.
+/// The key is destructured into the listed fields (or bound to a variable for non-struct keys),
+/// which are then interpolated into the key format. All key fields must be listed, and all
+/// of them must be used in the format, so that keys with different fields cannot be encoded
+/// to the same string. Supported codecs are `bincode`, `cbor` and `json`; see [`Codec`].
+///
+/// Keys borrowing data (such as [`CircuitKey`]) are not supported; implement [`StoredObject`]
+/// manually for such keys.
+///
+/// # Examples
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// use zksync_object_store::{impl_stored_object, ClosedFormInputKey, StoredObject};
+/// # use zksync_types::L1BatchNumber;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct ClosedFormInputs(Vec<u8>);
+///
+/// impl_stored_object!(ClosedFormInputs {
+///     bucket: LeafAggregationWitnessJobsFri,
+///     key: ClosedFormInputKey { block_number, circuit_id },
+///     key_format: "closed_form_inputs_{block_number}_{circuit_id}.bin",
+///     codec: bincode,
+/// });
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Proof(Vec<u8>);
+///
+/// impl_stored_object!(Proof {
+///     bucket: ProofsFri,
+///     key: u32 as job_id,
+///     key_format: "proof_{job_id}.bin",
+///     codec: bincode,
+/// });
+///
+/// let key = ClosedFormInputKey {
+///     block_number: L1BatchNumber(1),
+///     circuit_id: 2,
+/// };
+/// assert_eq!(ClosedFormInputs::encode_key(key), "closed_form_inputs_1_2.bin");
+/// assert_eq!(Proof::encode_key(3), "proof_3.bin");
+/// ```
+///
+/// Key fields that are not used in the format are rejected:
+///
+/// ```compile_fail
+/// # use serde::{Deserialize, Serialize};
+/// # use zksync_object_store::{impl_stored_object, ClosedFormInputKey};
+/// # #[derive(Serialize, Deserialize)]
+/// # struct ClosedFormInputs(Vec<u8>);
+/// impl_stored_object!(ClosedFormInputs {
+///     bucket: LeafAggregationWitnessJobsFri,
+///     key: ClosedFormInputKey { block_number, circuit_id },
+///     key_format: "closed_form_inputs_{block_number}.bin",
+///     codec: bincode,
+/// });
+/// ```
+///
+/// ...as well as key fields missing from the list:
+///
+/// ```compile_fail,E0027
+/// # use serde::{Deserialize, Serialize};
+/// # use zksync_object_store::{impl_stored_object, ClosedFormInputKey};
+/// # #[derive(Serialize, Deserialize)]
+/// # struct ClosedFormInputs(Vec<u8>);
+/// impl_stored_object!(ClosedFormInputs {
+///     bucket: LeafAggregationWitnessJobsFri,
+///     key: ClosedFormInputKey { block_number },
+///     key_format: "closed_form_inputs_{block_number}.bin",
+///     codec: bincode,
+/// });
+/// ```
+///
+/// ...unknown fields in the format:
+///
+/// ```compile_fail
+/// # use serde::{Deserialize, Serialize};
+/// # use zksync_object_store::impl_stored_object;
+/// # #[derive(Serialize, Deserialize)]
+/// # struct Proof(Vec<u8>);
+/// impl_stored_object!(Proof {
+///     bucket: ProofsFri,
+///     key: u32 as job_id,
+///     key_format: "proof_{id}.bin",
+///     codec: bincode,
+/// });
+/// ```
+///
+/// ...unknown buckets:
+///
+/// ```compile_fail,E0599
+/// # use serde::{Deserialize, Serialize};
+/// # use zksync_object_store::impl_stored_object;
+/// # #[derive(Serialize, Deserialize)]
+/// # struct Proof(Vec<u8>);
+/// impl_stored_object!(Proof {
+///     bucket: Proofs,
+///     key: u32 as job_id,
+///     key_format: "proof_{job_id}.bin",
+///     codec: bincode,
+/// });
+/// ```
+///
+/// ...and unsupported codecs:
+///
+/// ```compile_fail
+/// # use serde::{Deserialize, Serialize};
+/// # use zksync_object_store::impl_stored_object;
+/// # #[derive(Serialize, Deserialize)]
+/// # struct Proof(Vec<u8>);
+/// impl_stored_object!(Proof {
+///     bucket: ProofsFri,
+///     key: u32 as job_id,
+///     key_format: "proof_{job_id}.bin",
+///     codec: protobuf,
+/// });
+/// ```
+#[macro_export]
+macro_rules! impl_stored_object {
+    (@codec bincode) => {
+        $crate::serialize_using_bincode!();
+    };
+    (@codec cbor) => {
+        $crate::serialize_using_cbor!();
+    };
+    (@codec json) => {
+        $crate::serialize_using_json!();
+    };
+    (@codec $codec:ident) => {
+        std::compile_error!(std::concat!(
+            "unsupported codec `",
+            std::stringify!($codec),
+            "`; supported codecs are `bincode`, `cbor` and `json`"
+        ));
+    };
+
+    (
+        $ty:ty {
+            bucket: $bucket:ident,
+            key: $key_ty:ident { $($field:ident),+ $(,)? },
+            key_format: $format:literal,
+            codec: $codec:ident $(,)?
+        }
+    ) => {
+        impl $crate::StoredObject for $ty {
+            const BUCKET: $crate::Bucket = $crate::Bucket::$bucket;
+            type Key<'a> = $key_ty;
+
+            #[deny(unused_variables)]
+            fn encode_key(key: Self::Key<'_>) -> std::string::String {
+                let $key_ty { $($field),+ } = key;
+                std::format!($format)
+            }
+
+            $crate::impl_stored_object!(@codec $codec);
+        }
+    };
+
+    (
+        $ty:ty {
+            bucket: $bucket:ident,
+            key: $key_ty:ty as $key:ident,
+            key_format: $format:literal,
+            codec: $codec:ident $(,)?
+        }
+    ) => {
+        impl $crate::StoredObject for $ty {
+            const BUCKET: $crate::Bucket = $crate::Bucket::$bucket;
+            type Key<'a> = $key_ty;
+
+            #[deny(unused_variables)]
+            fn encode_key($key: Self::Key<'_>) -> std::string::String {
+                std::format!($format)
+            }
+
+            $crate::impl_stored_object!(@codec $codec);
+        }
     };
 }
 
@@ -197,9 +572,54 @@ impl dyn ObjectStore + '_ {
     /// Returns an error if an object with the `key` does not exist, cannot be accessed,
     /// or cannot be deserialized.
     pub async fn get<V: StoredObject>(&self, key: V::Key<'_>) -> Result<V, ObjectStoreError> {
+        let (value, _) = self.get_with_size(key).await?;
+        Ok(value)
+    }
+
+    /// Same as [`Self::get()`], but also returns the size of the serialized value in bytes.
+    /// The size doesn't account for compression or encryption applied by the store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object with the `key` does not exist, cannot be accessed,
+    /// or cannot be deserialized.
+    pub async fn get_with_size<V: StoredObject>(
+        &self,
+        key: V::Key<'_>,
+    ) -> Result<(V, u64), ObjectStoreError> {
         let key = V::encode_key(key);
         let bytes = self.get_raw(V::BUCKET, &key).await?;
-        V::deserialize(bytes).map_err(ObjectStoreError::Serialization)
+        let size = bytes.len() as u64;
+        Ok((versioning::deserialize_versioned(bytes)?, size))
+    }
+
+    /// Fetches the value for the given key, returning `None` if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object with the `key` cannot be accessed or cannot be deserialized.
+    pub async fn get_opt<V: StoredObject>(
+        &self,
+        key: V::Key<'_>,
+    ) -> Result<Option<V>, ObjectStoreError> {
+        match self.get(key).await {
+            Ok(value) => Ok(Some(value)),
+            Err(ObjectStoreError::KeyNotFound(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Checks whether a value for the given key exists without fetching it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be accessed.
+    pub async fn contains<V: StoredObject>(
+        &self,
+        key: V::Key<'_>,
+    ) -> Result<bool, ObjectStoreError> {
+        let key = V::encode_key(key);
+        self.exists_raw(V::BUCKET, &key).await
     }
 
     /// Stores the value associating it with the key. If the key already exists,
@@ -213,9 +633,202 @@ impl dyn ObjectStore + '_ {
         key: V::Key<'_>,
         value: &V,
     ) -> Result<String, ObjectStoreError> {
+        let (key, _) = self.put_with_size(key, value).await?;
+        Ok(key)
+    }
+
+    /// Same as [`Self::put()`], but also returns the size of the serialized value in bytes.
+    /// The size doesn't account for compression or encryption applied by the store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or the insertion / replacement operation fails.
+    pub async fn put_with_size<V: StoredObject>(
+        &self,
+        key: V::Key<'_>,
+        value: &V,
+    ) -> Result<(String, u64), ObjectStoreError> {
         let key = V::encode_key(key);
-        let bytes = value.serialize().map_err(ObjectStoreError::Serialization)?;
+        let bytes = versioning::serialize_versioned(value)?;
+        let size = bytes.len() as u64;
         self.put_raw(V::BUCKET, &key, bytes).await?;
+        Ok((key, size))
+    }
+
+    /// Stores the value associating it with the key only if the key doesn't exist; an existing
+    /// value is never replaced. This makes uploads of deterministic artifacts idempotent.
+    ///
+    /// If a value already exists, it's fetched and compared with the provided one. If the values
+    /// differ, a warning is logged, the `server.object_store.put_if_absent_conflicts` counter
+    /// is incremented, and [`PutOutcome::Conflict`] is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization, the insertion operation or fetching the existing value
+    /// fails.
+    pub async fn put_if_absent<V: StoredObject>(
+        &self,
+        key: V::Key<'_>,
+        value: &V,
+    ) -> Result<(String, PutOutcome), ObjectStoreError> {
+        let (key, _, outcome) = self.put_if_absent_with_size(key, value).await?;
+        Ok((key, outcome))
+    }
+
+    /// Same as [`Self::put_if_absent()`], but also returns the size of the serialized value
+    /// in bytes. The size doesn't account for compression or encryption applied by the store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization, the insertion operation or fetching the existing value
+    /// fails.
+    pub async fn put_if_absent_with_size<V: StoredObject>(
+        &self,
+        key: V::Key<'_>,
+        value: &V,
+    ) -> Result<(String, u64, PutOutcome), ObjectStoreError> {
+        let key = V::encode_key(key);
+        let bytes = versioning::serialize_versioned(value)?;
+        let size = bytes.len() as u64;
+        let outcome = self
+            .put_raw_if_absent(V::BUCKET, &key, bytes.clone())
+            .await?;
+        if outcome == PutOutcome::Created {
+            return Ok((key, size, outcome));
+        }
+
+        let existing_bytes = self.get_raw(V::BUCKET, &key).await?;
+        if existing_bytes == bytes {
+            return Ok((key, size, PutOutcome::AlreadyExists));
+        }
+        vlog::warn!(
+            "Value for key `{key}` in bucket `{}` differs from the stored one \
+             ({} bytes vs {} bytes stored); the stored value is kept",
+            V::BUCKET,
+            bytes.len(),
+            existing_bytes.len()
+        );
+        metrics::increment_counter!(
+            "server.object_store.put_if_absent_conflicts",
+            "bucket" => V::BUCKET.as_str()
+        );
+        Ok((key, size, PutOutcome::Conflict))
+    }
+
+    /// Fetches the value for the given key, deserializing it while it's downloaded. Unlike
+    /// [`Self::get()`], this doesn't require buffering the serialized value in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object with the `key` does not exist or cannot be accessed.
+    /// Errors occurring while the value is downloaded are returned as serialization errors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if deserialization panics.
+    pub async fn get_streamed<V>(&self, key: V::Key<'_>) -> Result<V, ObjectStoreError>
+    where
+        V: StoredObject + Send + 'static,
+    {
+        let key = V::encode_key(key);
+        let reader = self.get_raw_stream(V::BUCKET, &key).await?;
+        let mut reader = SyncIoBridge::new(reader);
+        tokio::task::spawn_blocking(move || versioning::deserialize_versioned_from(&mut reader))
+            .await
+            .expect("deserialization panicked")
+    }
+
+    /// Stores the value associating it with the key, serializing it while it's uploaded.
+    /// Unlike [`Self::put()`], this doesn't require buffering the serialized value in memory.
+    /// If the key already exists, the value is replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or the insertion / replacement operation fails.
+    /// If serialization fails, the value is not replaced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if serialization panics.
+    pub async fn put_streamed<V>(
+        &self,
+        key: V::Key<'_>,
+        value: Arc<V>,
+    ) -> Result<String, ObjectStoreError>
+    where
+        V: StoredObject + Send + Sync + 'static,
+    {
+        let key = V::encode_key(key);
+        let (chunks_sender, chunks_receiver) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+        let serialization = tokio::task::spawn_blocking(move || {
+            let mut writer =
+                io::BufWriter::with_capacity(STREAM_CHUNK_SIZE, ChannelWriter(chunks_sender));
+            let header = versioning::header_for::<V>();
+            let result = header
+                .map_or(Ok(()), |header| writer.write_all(&header))
+                .map_err(From::from)
+                .and_then(|()| value.serialize_into(&mut writer))
+                .and_then(|()| writer.flush().map_err(From::from));
+            let writer = writer.get_ref();
+            match result {
+                Ok(()) => Ok(()),
+                // The upload has failed; its error is returned instead.
+                Err(_) if writer.is_closed() => Ok(()),
+                Err(err) => {
+                    // Make the upload fail, so that the value is not replaced.
+                    let io_err = io::Error::new(io::ErrorKind::Other, err.to_string());
+                    writer.0.blocking_send(Err(io_err)).ok();
+                    Err(err)
+                }
+            }
+        });
+
+        let reader = StreamReader::new(ReceiverStream::new(chunks_receiver));
+        let upload_result = self.put_raw_stream(V::BUCKET, &key, Box::new(reader)).await;
+        serialization
+            .await
+            .expect("serialization panicked")
+            .map_err(ObjectStoreError::Serialization)?;
+        upload_result?;
         Ok(key)
     }
+
+    /// Generates a URL allowing to download the value associated with the key without
+    /// credentials until `expiry` elapses. See [`ObjectStore::generate_download_url()`]
+    /// for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store doesn't support download URLs, or the URL cannot be signed.
+    pub async fn download_url<V: StoredObject>(
+        &self,
+        key: V::Key<'_>,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        let key = V::encode_key(key);
+        self.generate_download_url(V::BUCKET, &key, expiry).await
+    }
+
+    /// Removes the value associated with the key if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if removal fails.
+    pub async fn remove<V: StoredObject>(&self, key: V::Key<'_>) -> Result<(), ObjectStoreError> {
+        let key = V::encode_key(key);
+        self.remove_raw(V::BUCKET, &key).await
+    }
+
+    /// Removes the values associated with the `keys`. Keys without an associated value are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if removal of any of the values fails.
+    pub async fn remove_many<'a, V: StoredObject>(
+        &self,
+        keys: impl IntoIterator<Item = V::Key<'a>> + Send,
+    ) -> Result<(), ObjectStoreError> {
+        let keys: Vec<_> = keys.into_iter().map(V::encode_key).collect();
+        self.remove_raw_many(V::BUCKET, &keys).await
+    }
 }
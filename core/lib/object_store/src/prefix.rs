@@ -0,0 +1,199 @@
+//! Namespacing of blob keys with a common prefix.
+
+use async_trait::async_trait;
+
+use std::time::Duration;
+
+use crate::raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome};
+
+/// Store wrapper prepending a fixed prefix (e.g., the environment name or the chain ID)
+/// to all keys, so that stores with different prefixes never see each other's blobs
+/// even if they share buckets.
+#[derive(Debug)]
+pub(crate) struct PrefixedObjectStore {
+    inner: Box<dyn ObjectStore>,
+    prefix: String,
+}
+
+impl PrefixedObjectStore {
+    /// Wraps the provided store.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `prefix` is empty or contains a `/` char.
+    pub fn new(inner: Box<dyn ObjectStore>, prefix: &str) -> Self {
+        assert!(
+            !prefix.is_empty() && !prefix.contains('/'),
+            "object store key prefix `{prefix}` must be non-empty and must not contain `/`"
+        );
+        Self {
+            inner,
+            prefix: format!("{prefix}/"),
+        }
+    }
+
+    fn prefixed(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for PrefixedObjectStore {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        self.inner.get_raw(bucket, &self.prefixed(key)).await
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        self.inner.put_raw(bucket, &self.prefixed(key), value).await
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        self.inner.remove_raw(bucket, &self.prefixed(key)).await
+    }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        self.inner
+            .put_raw_if_absent(bucket, &self.prefixed(key), value)
+            .await
+    }
+
+    async fn remove_raw_many(
+        &self,
+        bucket: Bucket,
+        keys: &[String],
+    ) -> Result<(), ObjectStoreError> {
+        let keys: Vec<_> = keys.iter().map(|key| self.prefixed(key)).collect();
+        self.inner.remove_raw_many(bucket, &keys).await
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        self.inner.get_raw_size(bucket, &self.prefixed(key)).await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        self.inner.exists_raw(bucket, &self.prefixed(key)).await
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        let (objects, next_token) = self
+            .inner
+            .list_raw(bucket, &self.prefixed(prefix), continuation_token)
+            .await?;
+        let objects = objects.into_iter().filter_map(|object| {
+            let key = object.key.strip_prefix(&self.prefix)?.to_owned();
+            Some(ObjectMeta { key, ..object })
+        });
+        Ok((objects.collect(), next_token))
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        self.inner.health_check(bucket).await
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        self.inner
+            .generate_download_url(bucket, &self.prefixed(key), expiry)
+            .await
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        self.inner.get_raw_stream(bucket, &self.prefixed(key)).await
+    }
+
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        self.inner
+            .put_raw_stream(bucket, &self.prefixed(key), reader)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mock::MockStore;
+
+    #[tokio::test]
+    async fn test_stores_with_different_prefixes_are_isolated() {
+        let inner = Arc::new(MockStore::default());
+        let staging = PrefixedObjectStore::new(Box::new(Arc::clone(&inner)), "staging");
+        let mainnet = PrefixedObjectStore::new(Box::new(Arc::clone(&inner)), "mainnet");
+        staging
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        let err = mainnet
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+        mainnet
+            .put_raw(Bucket::ProofsFri, "proof_1.bin", vec![4, 5])
+            .await
+            .unwrap();
+        let blob = staging
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [1, 2, 3]);
+        let blob = mainnet
+            .get_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        assert_eq!(blob, [4, 5]);
+
+        mainnet
+            .remove_raw(Bucket::ProofsFri, "proof_1.bin")
+            .await
+            .unwrap();
+        let keys = inner.keys(Bucket::ProofsFri).await;
+        assert_eq!(keys, ["staging/proof_1.bin"]);
+        let (objects, next_token) = staging
+            .list_raw(Bucket::ProofsFri, "proof_", None)
+            .await
+            .unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].key, "proof_1.bin");
+        assert_eq!(objects[0].size, 3);
+        assert!(next_token.is_none());
+        let (objects, _) = mainnet.list_raw(Bucket::ProofsFri, "", None).await.unwrap();
+        assert!(objects.is_empty(), "{objects:?}");
+    }
+
+    #[test]
+    #[should_panic(expected = "must not contain")]
+    fn test_prefix_with_slash_is_rejected() {
+        PrefixedObjectStore::new(Box::new(MockStore::default()), "staging/v2");
+    }
+}
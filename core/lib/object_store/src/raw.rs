@@ -1,8 +1,31 @@
 use async_trait::async_trait;
+use futures::{stream, StreamExt, TryStreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt};
 
-use std::{error, fmt, sync::Arc};
+use std::{
+    error, fmt, io,
+    ops::RangeInclusive,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
-use crate::{file::FileBackedObjectStore, gcs::GoogleCloudStorage, mock::MockStore};
+use crate::{
+    azure::{AzureBlobCredentials, AzureBlobStorage},
+    cache::CachedObjectStore,
+    checksum::ChecksummingObjectStore,
+    compression::CompressingObjectStore,
+    content_addressed::ContentAddressedObjectStore,
+    encryption::EncryptedObjectStore,
+    file::FileBackedObjectStore,
+    gcs::{GcsCredentials, GoogleCloudStorage},
+    metered::MeteredObjectStore,
+    mirror::MirroredObjectStore,
+    mock::MockStore,
+    prefix::PrefixedObjectStore,
+    retries::{is_transient_status_code, RetryPolicy},
+    s3::S3Storage,
+    throttle::ThrottledObjectStore,
+};
 use zksync_config::configs::object_store::ObjectStoreMode;
 use zksync_config::ObjectStoreConfig;
 
@@ -45,6 +68,14 @@ impl fmt::Display for Bucket {
     }
 }
 
+/// Max number of concurrent requests in the default [`ObjectStore::remove_raw_many()`]
+/// implementation.
+const MAX_CONCURRENT_REMOVALS: usize = 16;
+
+/// Key of a blob that is requested by the default [`ObjectStore::health_check()`] implementation.
+/// The blob doesn't need to exist; a missing blob is a valid response from a reachable store.
+const HEALTH_CHECK_KEY: &str = "health_check.bin";
+
 /// Thread-safe boxed error.
 pub type BoxedError = Box<dyn error::Error + Send + Sync>;
 
@@ -55,15 +86,96 @@ pub enum ObjectStoreError {
     KeyNotFound(BoxedError),
     /// Object (de)serialization failed.
     Serialization(BoxedError),
-    /// Other error has occurred when accessing the store (e.g., a network error).
+    /// The object is stored in a format version that cannot be deserialized, e.g. because
+    /// it was written by a newer binary, or migration from an old version is no longer supported.
+    UnsupportedVersion {
+        /// Format version of the stored object.
+        found: u16,
+        /// Format versions that can be deserialized.
+        supported: RangeInclusive<u16>,
+    },
+    /// The checksum stored with the object doesn't match its contents, i.e., the object
+    /// was corrupted.
+    ChecksumMismatch(BoxedError),
+    /// The store cannot be reached or has failed to process the request, e.g. because of
+    /// a network error, a timeout, rate limiting or a server error.
+    Transport(BoxedError),
+    /// The request or the entire operation (including retries) hasn't completed in time.
+    Timeout(BoxedError),
+    /// The request wasn't sent because the store has been failing with transport errors;
+    /// requests are suspended for a cool-down period. Operations failed with this error
+    /// should be retried later rather than considered failed.
+    CircuitOpen(BoxedError),
+    /// The store has rejected the credentials, or they don't grant access to the object.
+    Auth(BoxedError),
+    /// An encrypted object cannot be decrypted, e.g. because it was tampered with or encrypted
+    /// with an unknown key, or an unencrypted object was fetched while encryption is required.
+    DecryptionFailed(BoxedError),
+    /// The operation is not supported by the store.
+    Unsupported(BoxedError),
+    /// Other error has occurred when accessing the store.
     Other(BoxedError),
 }
 
+impl ObjectStoreError {
+    /// Classifies an error with the specified HTTP status `code` returned by the store.
+    pub(crate) fn from_status_code(code: u16, err: impl Into<BoxedError>) -> Self {
+        let err = err.into();
+        match code {
+            404 => Self::KeyNotFound(err),
+            401 | 403 => Self::Auth(err),
+            code if is_transient_status_code(code) => Self::Transport(err),
+            _ => Self::Other(err),
+        }
+    }
+
+    /// Returns `true` if the operation that has failed with this error may succeed
+    /// if it is retried (e.g., the error was caused by a network failure, or the object
+    /// was corrupted during download).
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Transport(_) | Self::Timeout(_) | Self::ChecksumMismatch(_)
+        )
+    }
+
+    /// Returns the kind of this error used as a metric label.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Self::KeyNotFound(_) => "key_not_found",
+            Self::Serialization(_) => "serialization",
+            Self::UnsupportedVersion { .. } => "unsupported_version",
+            Self::ChecksumMismatch(_) => "checksum_mismatch",
+            Self::Transport(_) => "transport",
+            Self::Timeout(_) => "timeout",
+            Self::CircuitOpen(_) => "circuit_open",
+            Self::Auth(_) => "auth",
+            Self::DecryptionFailed(_) => "decryption_failed",
+            Self::Unsupported(_) => "unsupported",
+            Self::Other(_) => "other",
+        }
+    }
+}
+
 impl fmt::Display for ObjectStoreError {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::KeyNotFound(err) => write!(formatter, "key not found: {err}"),
             Self::Serialization(err) => write!(formatter, "serialization error: {err}"),
+            Self::UnsupportedVersion { found, supported } => write!(
+                formatter,
+                "unsupported format version: object is format v{found}, supported formats are \
+                 v{}..=v{}",
+                supported.start(),
+                supported.end()
+            ),
+            Self::ChecksumMismatch(err) => write!(formatter, "checksum mismatch: {err}"),
+            Self::Transport(err) => write!(formatter, "transport error: {err}"),
+            Self::Timeout(err) => write!(formatter, "timeout: {err}"),
+            Self::CircuitOpen(err) => write!(formatter, "circuit open: {err}"),
+            Self::Auth(err) => write!(formatter, "authentication error: {err}"),
+            Self::DecryptionFailed(err) => write!(formatter, "decryption failed: {err}"),
+            Self::Unsupported(err) => write!(formatter, "unsupported operation: {err}"),
             Self::Other(err) => write!(formatter, "other error: {err}"),
         }
     }
@@ -72,13 +184,47 @@ impl fmt::Display for ObjectStoreError {
 impl error::Error for ObjectStoreError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
-            Self::KeyNotFound(err) | Self::Serialization(err) | Self::Other(err) => {
-                Some(err.as_ref())
-            }
+            Self::KeyNotFound(err)
+            | Self::Serialization(err)
+            | Self::ChecksumMismatch(err)
+            | Self::Transport(err)
+            | Self::Timeout(err)
+            | Self::CircuitOpen(err)
+            | Self::Auth(err)
+            | Self::DecryptionFailed(err)
+            | Self::Unsupported(err)
+            | Self::Other(err) => Some(err.as_ref()),
+            Self::UnsupportedVersion { .. } => None,
         }
     }
 }
 
+/// Metadata of a blob returned by [`ObjectStore::list_raw()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectMeta {
+    /// Key of the blob.
+    pub key: String,
+    /// Size of the blob as stored, in bytes.
+    pub size: u64,
+    /// Time of the last modification of the blob, if reported by the store.
+    pub last_modified: Option<SystemTime>,
+}
+
+/// Outcome of storing a value only if it's absent, e.g. using [`ObjectStore::put_raw_if_absent()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PutOutcome {
+    /// The value was stored.
+    Created,
+    /// A value for the key already exists; it was left intact.
+    AlreadyExists,
+    /// A value with different contents already exists for the key; it was left intact.
+    /// Only returned by `put_if_absent()` methods comparing the stored value with the provided one.
+    Conflict,
+}
+
+/// Reader of blob contents returned by [`ObjectStore::get_raw_stream()`].
+pub type BlobReader = Box<dyn AsyncRead + Send + Unpin>;
+
 /// Functionality to fetch and store byte blobs from an object store (AWS S3, Google Cloud Storage,
 /// Azure Blobstore etc).
 ///
@@ -114,6 +260,178 @@ pub trait ObjectStore: fmt::Debug + Send + Sync {
     ///
     /// Returns an error if removal fails.
     async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError>;
+
+    /// Stores the value associating it with the key into the given bucket unless a value
+    /// for the key already exists, in which case the existing value is left intact.
+    /// Never returns [`PutOutcome::Conflict`]. The default implementation checks whether the key
+    /// exists using [`Self::exists_raw()`] and then stores the value using [`Self::put_raw()`];
+    /// unlike implementations for the GCS-based and file-backed stores, it's not atomic.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be accessed or the insertion fails.
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        if self.exists_raw(bucket, key).await? {
+            return Ok(PutOutcome::AlreadyExists);
+        }
+        self.put_raw(bucket, key, value).await?;
+        Ok(PutOutcome::Created)
+    }
+
+    /// Removes the values associated with the `keys` from the given bucket. Keys without
+    /// an associated value are skipped, so that removal can be safely repeated. The default
+    /// implementation removes values concurrently using [`Self::remove_raw()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if removal of any of the values fails. Some of the other values
+    /// may be removed in this case.
+    async fn remove_raw_many(
+        &self,
+        bucket: Bucket,
+        keys: &[String],
+    ) -> Result<(), ObjectStoreError> {
+        stream::iter(keys)
+            .map(|key| async move {
+                match self.remove_raw(bucket, key).await {
+                    Err(ObjectStoreError::KeyNotFound(_)) => Ok(()),
+                    result => result,
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_REMOVALS)
+            .try_collect()
+            .await
+    }
+
+    /// Returns the size of the value for the given key from the given bucket in bytes.
+    /// The default implementation fetches the entire value using [`Self::get_raw()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object with the `key` does not exist or cannot be accessed.
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        let value = self.get_raw(bucket, key).await?;
+        Ok(value.len() as u64)
+    }
+
+    /// Checks whether a value for the given key exists in the given bucket without fetching it.
+    /// The default implementation uses [`Self::get_raw_size()`], which only requests
+    /// object metadata for all stores (e.g., a HEAD request for S3, or file metadata
+    /// for the file-backed store).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bucket cannot be accessed.
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        match self.get_raw_size(bucket, key).await {
+            Ok(_) => Ok(true),
+            Err(ObjectStoreError::KeyNotFound(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Lists a page of blobs in the given bucket with keys starting with `prefix`; an empty
+    /// `prefix` lists the entire bucket. The first page is requested with no `continuation_token`;
+    /// subsequent pages are requested with the token returned together with the previous page.
+    /// `None` is returned instead of the token for the last page. The order of blobs
+    /// and the page size are store-specific. The default implementation returns
+    /// an [`ObjectStoreError::Unsupported`] error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store doesn't support listing, or the bucket cannot be accessed.
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        _prefix: &str,
+        _continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        let message = format!("listing keys is not supported for bucket {bucket}");
+        Err(ObjectStoreError::Unsupported(message.into()))
+    }
+
+    /// Checks that the store is reachable and the given bucket exists, so that a misconfigured
+    /// store can be detected on startup. The default implementation requests the size
+    /// of a (generally missing) blob using [`Self::get_raw_size()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be reached, the credentials are rejected,
+    /// or the bucket doesn't exist.
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        match self.get_raw_size(bucket, HEALTH_CHECK_KEY).await {
+            Ok(_) | Err(ObjectStoreError::KeyNotFound(_)) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Generates a URL allowing to download the value for the given key from the given bucket
+    /// without credentials until `expiry` elapses, e.g. to hand it to an external prover.
+    /// The URL serves the value as stored, i.e., compressed and / or with a checksum header
+    /// if these are enabled for the store. The default implementation returns
+    /// an [`ObjectStoreError::Unsupported`] error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store doesn't support download URLs, or the URL cannot be signed.
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        _key: &str,
+        _expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        let message = format!("download URLs are not supported for bucket {bucket}");
+        Err(ObjectStoreError::Unsupported(message.into()))
+    }
+
+    /// Fetches the value for the given key from the given bucket as a stream of bytes,
+    /// so that the value doesn't need to be buffered in memory. The default implementation
+    /// fetches the entire value using [`Self::get_raw()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object with the `key` does not exist or cannot be accessed.
+    /// Errors occurring after the request has started (e.g., a broken connection) are returned
+    /// as I/O errors by the reader.
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let value = self.get_raw(bucket, key).await?;
+        Ok(Box::new(io::Cursor::new(value)))
+    }
+
+    /// Stores the value read from the `reader` associating it with the key into the given bucket,
+    /// so that the value doesn't need to be buffered in memory. If the key already exists,
+    /// the value is replaced. The default implementation reads the entire value and stores it
+    /// using [`Self::put_raw()`].
+    ///
+    /// Unlike [`Self::put_raw()`], the operation cannot be retried by the store, since the reader
+    /// cannot be rewound.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from `reader` or the insertion / replacement operation fails.
+    /// In the former case, the value is not replaced.
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        mut reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        let mut value = vec![];
+        reader
+            .read_to_end(&mut value)
+            .await
+            .map_err(|err| ObjectStoreError::Other(err.into()))?;
+        self.put_raw(bucket, key, value).await
+    }
 }
 
 #[async_trait]
@@ -134,6 +452,70 @@ impl<T: ObjectStore + ?Sized> ObjectStore for Arc<T> {
     async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
         (**self).remove_raw(bucket, key).await
     }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        (**self).put_raw_if_absent(bucket, key, value).await
+    }
+
+    async fn remove_raw_many(
+        &self,
+        bucket: Bucket,
+        keys: &[String],
+    ) -> Result<(), ObjectStoreError> {
+        (**self).remove_raw_many(bucket, keys).await
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        (**self).get_raw_size(bucket, key).await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        (**self).exists_raw(bucket, key).await
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        (**self).list_raw(bucket, prefix, continuation_token).await
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        (**self).health_check(bucket).await
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        (**self).generate_download_url(bucket, key, expiry).await
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        (**self).get_raw_stream(bucket, key).await
+    }
+
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        (**self).put_raw_stream(bucket, key, reader).await
+    }
 }
 
 #[derive(Debug)]
@@ -176,45 +558,242 @@ impl ObjectStoreFactory {
         }
     }
 
-    /// Creates an [`ObjectStore`].
-    pub async fn create_store(&self) -> Box<dyn ObjectStore> {
+    /// Returns keys of the objects stored in the `bucket` of the mock store in the alphabetical
+    /// order, e.g. to check which objects were stored by the tested code.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the factory was not created with [`Self::mock()`].
+    pub async fn mock_keys(&self, bucket: Bucket) -> Vec<String> {
+        match &self.origin {
+            ObjectStoreOrigin::Mock(store) => store.keys(bucket).await,
+            ObjectStoreOrigin::Config(_) => panic!("object store factory is not a mock one"),
+        }
+    }
+
+    /// Creates an [`ObjectStore`]. The store is not checked to be reachable; use
+    /// [`ObjectStore::health_check()`] to check it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be initialized, e.g. if its credentials cannot
+    /// be loaded or the configuration is invalid.
+    pub async fn create_store(&self) -> Result<Box<dyn ObjectStore>, ObjectStoreError> {
         match &self.origin {
             ObjectStoreOrigin::Config(config) => Self::create_from_config(config).await,
-            ObjectStoreOrigin::Mock(store) => Box::new(Arc::clone(store)),
+            ObjectStoreOrigin::Mock(store) => Ok(Box::new(Arc::clone(store))),
+        }
+    }
+
+    /// Creates an [`ObjectStore`] caching fetched blobs in memory. Cache capacity is taken
+    /// from the configuration; for mock factories, the default capacity is used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying store cannot be initialized.
+    pub async fn create_cached_store(&self) -> Result<CachedObjectStore, ObjectStoreError> {
+        let (max_entries, max_bytes) = match &self.origin {
+            ObjectStoreOrigin::Config(config) => (config.cache_max_entries, config.cache_max_bytes),
+            ObjectStoreOrigin::Mock(_) => (None, None),
+        };
+        let max_entries = max_entries.unwrap_or(CachedObjectStore::DEFAULT_MAX_ENTRIES);
+        let max_bytes = max_bytes.unwrap_or(CachedObjectStore::DEFAULT_MAX_BYTES);
+        vlog::trace!(
+            "Initialized object store cache with capacity of {max_entries} blobs / {max_bytes} bytes"
+        );
+        let store = self.create_store().await?;
+        Ok(CachedObjectStore::new(store, max_entries, max_bytes))
+    }
+
+    async fn create_from_config(
+        config: &ObjectStoreConfig,
+    ) -> Result<Box<dyn ObjectStore>, ObjectStoreError> {
+        let mut store = Self::create_backend(config).await?;
+        // Bandwidth is limited for blobs as they are transferred, i.e. compressed and encrypted.
+        let (max_download_rate, max_upload_rate) = (
+            config.max_download_bytes_per_sec,
+            config.max_upload_bytes_per_sec,
+        );
+        if max_download_rate == Some(0) || max_upload_rate == Some(0) {
+            let message = "bandwidth limits must be positive";
+            return Err(ObjectStoreError::Other(message.into()));
+        }
+        if max_download_rate.is_some() || max_upload_rate.is_some() {
+            vlog::trace!(
+                "Enabled bandwidth limits for Object store: {max_download_rate:?} bytes/s \
+                 for downloads, {max_upload_rate:?} bytes/s for uploads"
+            );
+            store = Box::new(ThrottledObjectStore::new(
+                store,
+                max_download_rate,
+                max_upload_rate,
+            ));
+        }
+        if let Some(prefix) = &config.key_prefix {
+            vlog::trace!("Enabled key prefix `{prefix}` for Object store");
+            store = Box::new(PrefixedObjectStore::new(store, prefix));
+        }
+        // Checksums are verified even if they are not written, so that writing them can be
+        // enabled after all readers are updated.
+        let mut store: Box<dyn ObjectStore> = Box::new(ChecksummingObjectStore::new(
+            store,
+            config.write_checksums(),
+        ));
+        // Blobs are compressed before they are encrypted, since ciphertexts are incompressible.
+        if let Some(keys) = Self::encryption_keys(config).await? {
+            vlog::trace!("Enabled encryption for Object store");
+            let require_encryption = config.require_encryption();
+            store = Box::new(EncryptedObjectStore::new(store, &keys, require_encryption)?);
+        }
+        if let Some(level) = config.compression_level {
+            vlog::trace!("Enabled zstd compression with level {level} for Object store");
+            store = Box::new(CompressingObjectStore::new(store, level));
+        }
+        // Blobs are hashed before they are compressed and encrypted, since encrypting identical
+        // blobs produces different ciphertexts.
+        if config.content_addressed() {
+            vlog::trace!("Enabled content-addressed storage for Object store");
+            store = Box::new(ContentAddressedObjectStore::new(store));
         }
+        Ok(store)
     }
 
-    async fn create_from_config(config: &ObjectStoreConfig) -> Box<dyn ObjectStore> {
-        let gcs_credential_file_path = match config.mode {
-            ObjectStoreMode::GCSWithCredentialFile => Some(config.gcs_credential_file_path.clone()),
-            _ => None,
+    /// Creates a (possibly mirrored) backend. Requests to backends are metered before any other
+    /// wrappers, so that transferred blobs are reported as they are stored.
+    async fn create_backend(
+        config: &ObjectStoreConfig,
+    ) -> Result<Box<dyn ObjectStore>, ObjectStoreError> {
+        if config.mode != ObjectStoreMode::Mirrored {
+            let store = Self::create_single_backend(config).await?;
+            return Ok(Box::new(MeteredObjectStore::new(store)));
+        }
+
+        let mirror = config.mirror.as_deref().ok_or_else(|| {
+            let message = "configurations of primary and secondary stores must be specified";
+            ObjectStoreError::Other(message.into())
+        })?;
+        let primary = Self::create_single_backend(&mirror.primary).await?;
+        let secondary = Self::create_single_backend(&mirror.secondary).await?;
+        vlog::trace!(
+            "Initialized Mirrored Object store with {:?} primary and {:?} secondary stores",
+            mirror.primary.mode,
+            mirror.secondary.mode
+        );
+        Ok(Box::new(MirroredObjectStore::new(
+            Box::new(MeteredObjectStore::new(primary)),
+            Box::new(MeteredObjectStore::new(secondary)),
+            config.mirror_strict_writes(),
+        )))
+    }
+
+    async fn create_single_backend(
+        config: &ObjectStoreConfig,
+    ) -> Result<Box<dyn ObjectStore>, ObjectStoreError> {
+        let gcs_credentials = if config.gcs_use_anonymous_credentials() {
+            GcsCredentials::Anonymous
+        } else if config.mode == ObjectStoreMode::GCSWithCredentialFile {
+            GcsCredentials::File(config.gcs_credential_file_path.clone())
+        } else {
+            GcsCredentials::Default
         };
-        match config.mode {
+        let store: Box<dyn ObjectStore> = match config.mode {
             ObjectStoreMode::GCS => {
                 vlog::trace!("Initialized GoogleCloudStorage Object store without credential file");
                 let store = GoogleCloudStorage::new(
-                    gcs_credential_file_path,
+                    gcs_credentials,
                     config.bucket_base_url.clone(),
-                    config.max_retries,
+                    RetryPolicy::new(config),
+                    config.max_concurrent_requests(),
+                    config.gcs_endpoint_override.clone(),
+                    config.gcs_read_endpoints(),
                 )
-                .await;
+                .await?;
                 Box::new(store)
             }
             ObjectStoreMode::GCSWithCredentialFile => {
                 vlog::trace!("Initialized GoogleCloudStorage Object store with credential file");
                 let store = GoogleCloudStorage::new(
-                    gcs_credential_file_path,
+                    gcs_credentials,
                     config.bucket_base_url.clone(),
-                    config.max_retries,
+                    RetryPolicy::new(config),
+                    config.max_concurrent_requests(),
+                    config.gcs_endpoint_override.clone(),
+                    config.gcs_read_endpoints(),
+                )
+                .await?;
+                Box::new(store)
+            }
+            ObjectStoreMode::S3 => {
+                vlog::trace!("Initialized S3 Object store");
+                let store = S3Storage::new(
+                    config.bucket_base_url.clone(),
+                    config.s3_region.clone(),
+                    config.s3_endpoint.clone(),
+                    RetryPolicy::new(config),
                 )
                 .await;
                 Box::new(store)
             }
+            ObjectStoreMode::AzureBlob => {
+                vlog::trace!("Initialized AzureBlob Object store");
+                let store = AzureBlobStorage::new(
+                    Self::azure_credentials(config)?,
+                    config.bucket_base_url.clone(),
+                    RetryPolicy::new(config),
+                )?;
+                Box::new(store)
+            }
             ObjectStoreMode::FileBacked => {
                 vlog::trace!("Initialized FileBacked Object store");
-                let store = FileBackedObjectStore::new(config.file_backed_base_path.clone()).await;
+                let store = FileBackedObjectStore::new(
+                    config.file_backed_base_path.clone(),
+                    config.durable_writes(),
+                )
+                .await?;
                 Box::new(store)
             }
+            ObjectStoreMode::Mirrored => {
+                let message = "mirrored stores cannot be nested";
+                return Err(ObjectStoreError::Other(message.into()));
+            }
+        };
+        Ok(store)
+    }
+
+    async fn encryption_keys(
+        config: &ObjectStoreConfig,
+    ) -> Result<Option<String>, ObjectStoreError> {
+        if config.encryption_keys.is_some() {
+            return Ok(config.encryption_keys.clone());
+        }
+        let Some(path) = &config.encryption_keys_path else {
+            return Ok(None);
+        };
+        let keys = tokio::fs::read_to_string(path).await.map_err(|err| {
+            let message = format!("failed reading encryption keys from `{path}`: {err}");
+            ObjectStoreError::Other(message.into())
+        })?;
+        Ok(Some(keys))
+    }
+
+    fn azure_credentials(
+        config: &ObjectStoreConfig,
+    ) -> Result<AzureBlobCredentials, ObjectStoreError> {
+        if let Some(connection_string) = &config.azure_connection_string {
+            return Ok(AzureBlobCredentials::ConnectionString(
+                connection_string.clone(),
+            ));
         }
+        let account = config.azure_account.clone().ok_or_else(|| {
+            let message = "Azure storage account or connection string must be specified";
+            ObjectStoreError::Other(message.into())
+        })?;
+        Ok(match &config.azure_sas_token {
+            Some(token) => AzureBlobCredentials::SasToken {
+                account,
+                token: token.clone(),
+            },
+            None => AzureBlobCredentials::ManagedIdentity { account },
+        })
     }
 }
@@ -0,0 +1,379 @@
+//! Retries for object store requests shared by the network-backed stores.
+
+use rand::Rng;
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use zksync_config::ObjectStoreConfig;
+
+use crate::{
+    circuit_breaker::{Attempt, CircuitBreaker},
+    raw::{Bucket, ObjectStoreError},
+};
+
+/// Returns `true` if a request failed with the HTTP status `code` may succeed if retried.
+pub(crate) fn is_transient_status_code(code: u16) -> bool {
+    matches!(code, 408 | 429 | 500..=599)
+}
+
+/// Policy for retrying failed object store requests with an exponential backoff.
+#[derive(Debug, Clone)]
+pub(crate) struct RetryPolicy {
+    /// Maximum number of retries after the first attempt.
+    pub max_retries: u16,
+    /// Delay before the first retry; it is doubled for every subsequent retry.
+    pub base_delay: Duration,
+    /// Cap on the delay between retries.
+    pub max_delay: Duration,
+    /// Whether delays are randomized, so that requests failed at the same time aren't retried
+    /// at the same time as well. If set, each delay is uniformly distributed in `[0, delay]`.
+    pub jitter: bool,
+    /// Timeout for a single attempt; attempts that have timed out are retried.
+    pub request_timeout: Duration,
+    /// Deadline for the entire operation including all retries.
+    pub operation_timeout: Duration,
+    /// Circuit breaker shared by all operations of the store, if enabled. Operations
+    /// without a bucket are not affected by the breaker.
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &ObjectStoreConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            base_delay: config.retry_base_delay(),
+            max_delay: config.retry_max_delay(),
+            jitter: config.retry_jitter(),
+            request_timeout: config.request_timeout(),
+            operation_timeout: config.operation_timeout(),
+            circuit_breaker: (config.circuit_breaker_threshold() > 0).then(|| {
+                let breaker = CircuitBreaker::new(
+                    config.circuit_breaker_threshold(),
+                    config.circuit_breaker_cooldown(),
+                );
+                Arc::new(breaker)
+            }),
+        }
+    }
+
+    /// Starts an attempt of the operation, failing fast if the circuit for the `bucket` is open.
+    fn start_attempt(
+        &self,
+        operation: &'static str,
+        bucket: Option<Bucket>,
+    ) -> Result<Option<Attempt<'_>>, ObjectStoreError> {
+        match (&self.circuit_breaker, bucket) {
+            (Some(breaker), Some(bucket)) => breaker.start_attempt(bucket, operation).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the delay before the specified (1-based) retry.
+    fn delay(&self, retry: u16) -> Duration {
+        let multiplier = 2_u32.saturating_pow(u32::from(retry).saturating_sub(1));
+        let delay = self
+            .base_delay
+            .saturating_mul(multiplier)
+            .min(self.max_delay);
+        if self.jitter {
+            delay.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+        } else {
+            delay
+        }
+    }
+
+    /// Runs `f` until it succeeds, fails with an error that is not `is_transient`, or the retries
+    /// are exhausted. Each attempt is limited by [`Self::request_timeout`], and the entire
+    /// operation by [`Self::operation_timeout`]; in both cases, an [`ObjectStoreError::Timeout`]
+    /// is returned once the timeout elapses, and timed out attempts are retried. The number
+    /// of performed retries is reported as a histogram labeled with the `operation`
+    /// and the `bucket`. If the circuit breaker for the `bucket` opens, the operation fails
+    /// with an [`ObjectStoreError::CircuitOpen`] without further retries.
+    pub async fn retry<T, E, Fut, F>(
+        &self,
+        operation: &'static str,
+        bucket: Option<Bucket>,
+        is_transient: impl Fn(&E) -> bool,
+        mut f: F,
+    ) -> Result<T, ObjectStoreError>
+    where
+        E: Into<ObjectStoreError>,
+        Fut: Future<Output = Result<T, E>>,
+        F: FnMut() -> Fut,
+    {
+        let mut retries = 0;
+        let attempts = async {
+            loop {
+                let attempt = self.start_attempt(operation, bucket)?;
+                let (err, is_transient) = match tokio::time::timeout(self.request_timeout, f())
+                    .await
+                {
+                    Ok(Ok(result)) => {
+                        if let Some(attempt) = attempt {
+                            attempt.finish(None);
+                        }
+                        return Ok(result);
+                    }
+                    Ok(Err(err)) => {
+                        let is_transient = is_transient(&err);
+                        (err.into(), is_transient)
+                    }
+                    Err(_) => {
+                        report_timeout(operation, bucket, "request");
+                        let timeout = self.request_timeout;
+                        let message = format!("request {operation} timed out after {timeout:?}");
+                        (ObjectStoreError::Timeout(message.into()), true)
+                    }
+                };
+                if let Some(attempt) = attempt {
+                    attempt.finish(Some(&err));
+                }
+                if !is_transient {
+                    return Err(err);
+                }
+                if retries >= self.max_retries {
+                    return Err(err);
+                }
+                retries += 1;
+                let delay = self.delay(retries);
+                vlog::warn!(
+                    "Failed object store request {operation} {retries}/{}, retrying in {delay:?}: {err}",
+                    self.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+        };
+        let result = self.with_deadline(operation, bucket, attempts).await;
+        metrics::histogram!(
+            "server.object_store.request_retries",
+            f64::from(retries),
+            "operation" => operation,
+            "bucket" => bucket.map_or("none", Bucket::as_str)
+        );
+        result
+    }
+
+    /// Runs an operation that cannot be retried (e.g., streaming a blob) limiting it
+    /// by [`Self::operation_timeout`]. Like with [`Self::retry()`], the operation fails fast
+    /// if the circuit breaker for the `bucket` is open.
+    pub async fn with_deadline<T>(
+        &self,
+        operation: &'static str,
+        bucket: Option<Bucket>,
+        f: impl Future<Output = Result<T, ObjectStoreError>>,
+    ) -> Result<T, ObjectStoreError> {
+        let attempt = self.start_attempt(operation, bucket)?;
+        let result = if let Ok(result) = tokio::time::timeout(self.operation_timeout, f).await {
+            result
+        } else {
+            report_timeout(operation, bucket, "operation");
+            let timeout = self.operation_timeout;
+            let message = format!("operation {operation} has not completed in {timeout:?}");
+            Err(ObjectStoreError::Timeout(message.into()))
+        };
+        if let Some(attempt) = attempt {
+            attempt.finish(result.as_ref().err());
+        }
+        result
+    }
+}
+
+pub(crate) fn report_timeout(operation: &'static str, bucket: Option<Bucket>, scope: &'static str) {
+    metrics::increment_counter!(
+        "server.object_store.timeouts",
+        "operation" => operation,
+        "bucket" => bucket.map_or("none", Bucket::as_str),
+        "scope" => scope
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU16, Ordering};
+
+    use super::*;
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        max_retries: 2,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(3),
+        jitter: false,
+        request_timeout: Duration::from_secs(10),
+        operation_timeout: Duration::from_secs(60),
+        circuit_breaker: None,
+    };
+
+    async fn retry_with_failures(policy: RetryPolicy, failures: u16) -> (Result<u32, ()>, u16) {
+        let attempts = AtomicU16::new(0);
+        let result = policy
+            .retry(
+                "test",
+                None,
+                |_| true,
+                || async {
+                    let attempt = attempts.fetch_add(1, Ordering::Relaxed);
+                    if attempt < failures {
+                        Err(ObjectStoreError::Transport("connection reset".into()))
+                    } else {
+                        Ok(42)
+                    }
+                },
+            )
+            .await;
+        (result.map_err(drop), attempts.into_inner())
+    }
+
+    #[tokio::test]
+    async fn test_retry_success_immediate() {
+        let result = retry_with_failures(POLICY, 0).await;
+        assert_eq!(result, (Ok(42), 1));
+    }
+
+    #[tokio::test]
+    async fn test_retry_success_after_retry() {
+        let result = retry_with_failures(POLICY, 2).await;
+        assert_eq!(result, (Ok(42), 3));
+    }
+
+    #[tokio::test]
+    async fn test_retry_failure_exhausted() {
+        let result = retry_with_failures(POLICY, 3).await;
+        assert_eq!(result, (Err(()), 3));
+    }
+
+    #[tokio::test]
+    async fn test_permanent_errors_are_not_retried() {
+        let attempts = AtomicU16::new(0);
+        let result = POLICY
+            .retry(
+                "test",
+                Some(Bucket::ProofsFri),
+                |_| false,
+                || async {
+                    attempts.fetch_add(1, Ordering::Relaxed);
+                    Err::<u32, _>(ObjectStoreError::Other("bad request".into()))
+                },
+            )
+            .await;
+        let err = result.unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Other(_)), "{err}");
+        assert_eq!(attempts.into_inner(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_requests_are_retried() {
+        let policy = RetryPolicy {
+            request_timeout: Duration::from_millis(10),
+            ..POLICY
+        };
+        let attempts = AtomicU16::new(0);
+        let result = policy
+            .retry(
+                "test",
+                None,
+                |_: &ObjectStoreError| false,
+                || async {
+                    if attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                    }
+                    Ok(42)
+                },
+            )
+            .await;
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.into_inner(), 2);
+
+        let result = policy
+            .retry(
+                "test",
+                None,
+                |_: &ObjectStoreError| false,
+                || async {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    Ok(42)
+                },
+            )
+            .await;
+        let err = result.unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Timeout(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_operation_timeout_covers_retries() {
+        let policy = RetryPolicy {
+            max_retries: u16::MAX,
+            operation_timeout: Duration::from_millis(50),
+            ..POLICY
+        };
+        let started_at = std::time::Instant::now();
+        let result = policy
+            .retry(
+                "test",
+                None,
+                |_| true,
+                || async { Err::<u32, _>(ObjectStoreError::Transport("connection reset".into())) },
+            )
+            .await;
+        let err = result.unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Timeout(_)), "{err}");
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_stops_retries() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            circuit_breaker: Some(Arc::new(CircuitBreaker::new(2, Duration::from_secs(60)))),
+            ..POLICY
+        };
+        let attempts = AtomicU16::new(0);
+        let result = policy
+            .retry(
+                "test",
+                Some(Bucket::ProofsFri),
+                |_| true,
+                || async {
+                    attempts.fetch_add(1, Ordering::Relaxed);
+                    Err::<u32, _>(ObjectStoreError::Transport("connection reset".into()))
+                },
+            )
+            .await;
+        let err = result.unwrap_err();
+        assert!(matches!(err, ObjectStoreError::CircuitOpen(_)), "{err}");
+        assert_eq!(attempts.into_inner(), 2);
+
+        let result = policy
+            .with_deadline("test_stream", Some(Bucket::ProofsFri), async { Ok(()) })
+            .await;
+        let err = result.unwrap_err();
+        assert!(matches!(err, ObjectStoreError::CircuitOpen(_)), "{err}");
+        // Operations without a bucket bypass the breaker.
+        let (result, attempts) = retry_with_failures(policy, 1).await;
+        assert_eq!((result, attempts), (Ok(42), 2));
+    }
+
+    #[test]
+    fn test_transient_status_codes() {
+        for code in [408, 429, 500, 502, 503] {
+            assert!(is_transient_status_code(code), "{code}");
+        }
+        for code in [200, 400, 401, 403, 404] {
+            assert!(!is_transient_status_code(code), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_retry_delays() {
+        let delays: Vec<_> = (1..=4).map(|retry| POLICY.delay(retry)).collect();
+        let expected_delays = [1, 2, 3, 3].map(Duration::from_millis);
+        assert_eq!(delays, expected_delays);
+
+        let policy = RetryPolicy {
+            jitter: true,
+            ..POLICY
+        };
+        for retry in 1..=4 {
+            assert!(policy.delay(retry) <= POLICY.delay(retry));
+        }
+    }
+}
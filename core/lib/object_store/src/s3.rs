@@ -0,0 +1,289 @@
+//! S3-based [`ObjectStore`] implementation. Besides AWS S3, it can be used with S3-compatible
+//! stores (e.g., MinIO) by overriding the endpoint.
+
+use async_trait::async_trait;
+use aws_config::{retry::RetryConfig, timeout::TimeoutConfig};
+use aws_sdk_s3::{
+    config::{Builder as S3ConfigBuilder, Region},
+    error::{ProvideErrorMetadata, SdkError},
+    operation::{
+        get_object::GetObjectError, head_bucket::HeadBucketError, head_object::HeadObjectError,
+    },
+    primitives::ByteStream,
+    Client,
+};
+
+use std::{
+    error::Error,
+    fmt,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    raw::{Bucket, ObjectMeta, ObjectStore, ObjectStoreError},
+    retries::{report_timeout, RetryPolicy},
+};
+
+/// Store keeping all objects in a single S3 bucket. Similarly to the GCS store, objects are prefixed
+/// with the name of their [`Bucket`], so that blobs can be copied between the stores without
+/// changing their keys.
+pub struct S3Storage {
+    bucket_name: String,
+    client: Client,
+    /// Timeout for reading the body of a fetched object, which is not covered by SDK timeouts.
+    read_timeout: Duration,
+}
+
+impl fmt::Debug for S3Storage {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("S3Storage")
+            .field("bucket_name", &self.bucket_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl S3Storage {
+    /// Creates a store with credentials taken from the standard AWS provider chain
+    /// (environment variables, profile files, or the instance profile). Requests are retried
+    /// by the SDK; only the number of retries and timeouts are taken from the `retry_policy`.
+    pub async fn new(
+        bucket_name: String,
+        region: Option<String>,
+        endpoint: Option<String>,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        let mut sdk_config = aws_config::from_env();
+        if let Some(region) = region {
+            sdk_config = sdk_config.region(Region::new(region));
+        }
+        let sdk_config = sdk_config.load().await;
+
+        let max_attempts = u32::from(retry_policy.max_retries) + 1;
+        let retry_config = RetryConfig::standard().with_max_attempts(max_attempts);
+        let timeout_config = TimeoutConfig::builder()
+            .operation_attempt_timeout(retry_policy.request_timeout)
+            .operation_timeout(retry_policy.operation_timeout)
+            .build();
+        let mut config = S3ConfigBuilder::from(&sdk_config)
+            .retry_config(retry_config)
+            .timeout_config(timeout_config);
+        if let Some(endpoint) = endpoint {
+            // S3-compatible stores generally don't support virtual-hosted-style requests.
+            config = config.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        Self {
+            bucket_name,
+            client: Client::from_conf(config.build()),
+            read_timeout: retry_policy.operation_timeout,
+        }
+    }
+
+    fn filename(bucket: &str, filename: &str) -> String {
+        format!("{bucket}/{filename}")
+    }
+}
+
+/// Error codes returned by S3 if the credentials are invalid or don't grant access to an object.
+const AUTH_ERROR_CODES: &[&str] = &[
+    "AccessDenied",
+    "InvalidAccessKeyId",
+    "SignatureDoesNotMatch",
+    "ExpiredToken",
+];
+
+/// Classifies an S3 SDK error returned by the `operation` in the `bucket`; `is_not_found` checks
+/// whether a service error means that the requested object is missing.
+fn sdk_error<E, R>(
+    err: SdkError<E, R>,
+    operation: &'static str,
+    bucket: Bucket,
+    is_not_found: fn(&E) -> bool,
+) -> ObjectStoreError
+where
+    E: ProvideErrorMetadata + Error + Send + Sync + 'static,
+    R: fmt::Debug + Send + Sync + 'static,
+{
+    match &err {
+        SdkError::ServiceError(service_err) if is_not_found(service_err.err()) => {
+            ObjectStoreError::KeyNotFound(err.into())
+        }
+        SdkError::ServiceError(service_err)
+            if service_err
+                .err()
+                .code()
+                .map_or(false, |code| AUTH_ERROR_CODES.contains(&code)) =>
+        {
+            ObjectStoreError::Auth(err.into())
+        }
+        // The SDK has already retried these errors.
+        SdkError::TimeoutError(_) => {
+            report_timeout(operation, Some(bucket), "sdk");
+            ObjectStoreError::Timeout(err.into())
+        }
+        SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => {
+            ObjectStoreError::Transport(err.into())
+        }
+        _ => ObjectStoreError::Other(err.into()),
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3Storage {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let started_at = Instant::now();
+        let filename = Self::filename(bucket.as_str(), key);
+        vlog::trace!(
+            "Fetching data from S3 for key {filename} from bucket {}",
+            self.bucket_name
+        );
+
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(filename)
+            .send()
+            .await
+            .map_err(|err| sdk_error(err, "get", bucket, GetObjectError::is_no_such_key))?;
+        let blob = tokio::time::timeout(self.read_timeout, output.body.collect()).await;
+        let Ok(blob) = blob else {
+            report_timeout("get", Some(bucket), "operation");
+            let message = format!("reading blob {key} from bucket {bucket} has timed out");
+            return Err(ObjectStoreError::Timeout(message.into()));
+        };
+        let blob = blob.map_err(|err| ObjectStoreError::Transport(err.into()))?;
+
+        vlog::trace!(
+            "Fetched data from S3 for key {key} from bucket {bucket} and it took: {:?}",
+            started_at.elapsed()
+        );
+        metrics::histogram!(
+            "server.object_store.fetching_time",
+            started_at.elapsed(),
+            "bucket" => bucket.as_str()
+        );
+        Ok(blob.into_bytes().to_vec())
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        let started_at = Instant::now();
+        let filename = Self::filename(bucket.as_str(), key);
+        vlog::trace!(
+            "Storing data to S3 for key {filename} from bucket {}",
+            self.bucket_name
+        );
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket_name)
+            .key(filename)
+            .body(ByteStream::from(value))
+            .send()
+            .await
+            .map_err(|err| sdk_error(err, "put", bucket, |_| false))?;
+
+        vlog::trace!(
+            "Stored data to S3 for key {key} from bucket {bucket} and it took: {:?}",
+            started_at.elapsed()
+        );
+        metrics::histogram!(
+            "server.object_store.storing_time",
+            started_at.elapsed(),
+            "bucket" => bucket.as_str()
+        );
+        Ok(())
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        let filename = Self::filename(bucket.as_str(), key);
+        vlog::trace!(
+            "Removing data from S3 for key {filename} from bucket {}",
+            self.bucket_name
+        );
+
+        // Removing a non-existing object is not an error in S3.
+        self.client
+            .delete_object()
+            .bucket(&self.bucket_name)
+            .key(filename)
+            .send()
+            .await
+            .map(drop)
+            .map_err(|err| sdk_error(err, "remove", bucket, |_| false))
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        let output = self
+            .client
+            .head_object()
+            .bucket(&self.bucket_name)
+            .key(Self::filename(bucket.as_str(), key))
+            .send()
+            .await
+            .map_err(|err| sdk_error(err, "get_size", bucket, HeadObjectError::is_not_found))?;
+        u64::try_from(output.content_length()).map_err(|err| ObjectStoreError::Other(err.into()))
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        let bucket_prefix = Self::filename(bucket.as_str(), "");
+        let output = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket_name)
+            .prefix(Self::filename(bucket.as_str(), prefix))
+            .set_continuation_token(continuation_token)
+            .send()
+            .await
+            .map_err(|err| sdk_error(err, "list", bucket, |_| false))?;
+
+        let objects = output.contents().unwrap_or_default().iter();
+        let objects = objects.filter_map(|object| {
+            let key = object.key()?.strip_prefix(&bucket_prefix)?;
+            Some(ObjectMeta {
+                key: key.to_owned(),
+                size: u64::try_from(object.size()).unwrap_or(0),
+                last_modified: object
+                    .last_modified()
+                    .and_then(|time| SystemTime::try_from(*time).ok()),
+            })
+        });
+        let objects = objects.collect();
+        let next_token = output.next_continuation_token().map(str::to_owned);
+        Ok((objects, next_token))
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        // Objects of all buckets are stored in a single S3 bucket, so only its existence
+        // can be checked.
+        let result = self
+            .client
+            .head_bucket()
+            .bucket(&self.bucket_name)
+            .send()
+            .await
+            .map_err(|err| sdk_error(err, "health_check", bucket, HeadBucketError::is_not_found));
+        match result {
+            Ok(_) => Ok(()),
+            Err(ObjectStoreError::KeyNotFound(_)) => {
+                let message = format!(
+                    "S3 bucket `{}` for {bucket} does not exist",
+                    self.bucket_name
+                );
+                Err(ObjectStoreError::Other(message.into()))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Utilities for streaming blobs.
+
+use bytes::Bytes;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    sync::mpsc,
+};
+
+use std::io;
+
+use crate::raw::BlobReader;
+
+/// Reads up to `len` bytes from the start of the `reader`. Fewer bytes are returned
+/// only if the reader is exhausted.
+pub(crate) async fn read_prefix(reader: &mut BlobReader, len: usize) -> io::Result<Vec<u8>> {
+    let mut prefix = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut prefix).await?;
+    Ok(prefix)
+}
+
+/// Returns a reader yielding `prefix` followed by the contents of the `reader`.
+pub(crate) fn prepend(
+    prefix: Vec<u8>,
+    reader: impl AsyncRead + Send + Unpin + 'static,
+) -> BlobReader {
+    Box::new(io::Cursor::new(prefix).chain(reader))
+}
+
+/// Synchronous writer sending the written data to a channel. Used to serialize objects
+/// on a blocking thread while they are uploaded.
+#[derive(Debug)]
+pub(crate) struct ChannelWriter(pub mpsc::Sender<io::Result<Bytes>>);
+
+impl ChannelWriter {
+    /// Returns `true` if the receiving side was dropped, e.g. because the upload has failed.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+}
+
+impl io::Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "blob upload was aborted"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
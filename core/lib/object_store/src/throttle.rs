@@ -0,0 +1,319 @@
+//! Bandwidth limits for blob transfers.
+
+use async_trait::async_trait;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, ReadBuf},
+    time::Sleep,
+};
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+use crate::raw::{BlobReader, Bucket, ObjectMeta, ObjectStore, ObjectStoreError, PutOutcome};
+
+/// Max duration of transfers at the limit that can be made without a delay after the limiter
+/// was idle, i.e. the capacity of the token bucket.
+const MAX_BURST: Duration = Duration::from_secs(1);
+/// Min duration of a window over which the throughput is measured.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct LimiterState {
+    /// Time at which all bytes transferred so far are paid off at the limit. If it's in the past,
+    /// the limiter has been idle.
+    paid_off_at: Instant,
+    throughput_window_start: Instant,
+    throughput_window_bytes: u64,
+}
+
+/// Token bucket limiting the bandwidth of transfers in a single direction. Bytes are accounted
+/// after they are transferred (so a single large read may exceed the limit), and the following
+/// transfers are delayed to compensate. The limiter is shared by all transfers of a store.
+#[derive(Debug)]
+pub(crate) struct BandwidthLimiter {
+    direction: &'static str,
+    bytes_per_sec: u64,
+    state: Mutex<LimiterState>,
+}
+
+impl BandwidthLimiter {
+    pub fn new(direction: &'static str, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "bandwidth limit must be positive");
+        let now = Instant::now();
+        Self {
+            direction,
+            bytes_per_sec,
+            state: Mutex::new(LimiterState {
+                paid_off_at: now,
+                throughput_window_start: now,
+                throughput_window_bytes: 0,
+            }),
+        }
+    }
+
+    /// Accounts for `byte_count` transferred bytes and returns the delay before the next transfer.
+    #[allow(clippy::cast_precision_loss)] // fine for reporting throughput
+    fn record(&self, byte_count: u64, now: Instant) -> Duration {
+        let cost = Duration::from_secs_f64(byte_count as f64 / self.bytes_per_sec as f64);
+        let mut state = self.state.lock().expect("bandwidth limiter is poisoned");
+        state.paid_off_at = state.paid_off_at.max(now) + cost;
+
+        state.throughput_window_bytes += byte_count;
+        let elapsed = now.duration_since(state.throughput_window_start);
+        if elapsed >= THROUGHPUT_WINDOW {
+            let throughput = state.throughput_window_bytes as f64 / elapsed.as_secs_f64();
+            metrics::gauge!(
+                "server.object_store.throughput_bytes_per_sec",
+                throughput,
+                "direction" => self.direction
+            );
+            state.throughput_window_start = now;
+            state.throughput_window_bytes = 0;
+        }
+        state
+            .paid_off_at
+            .saturating_duration_since(now)
+            .saturating_sub(MAX_BURST)
+    }
+
+    /// Accounts for `byte_count` transferred bytes and waits until the next transfer is allowed.
+    pub async fn throttle(&self, byte_count: u64) {
+        let delay = self.record(byte_count, Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Reader pacing reads from the wrapped reader according to a [`BandwidthLimiter`].
+struct ThrottledReader {
+    inner: BlobReader,
+    limiter: Arc<BandwidthLimiter>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl ThrottledReader {
+    fn new(inner: BlobReader, limiter: Arc<BandwidthLimiter>) -> Self {
+        Self {
+            inner,
+            limiter,
+            delay: None,
+        }
+    }
+}
+
+impl AsyncRead for ThrottledReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(delay) = &mut this.delay {
+            ready!(delay.as_mut().poll(cx));
+            this.delay = None;
+        }
+
+        let filled_len = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        let byte_count = (buf.filled().len() - filled_len) as u64;
+        if byte_count > 0 {
+            let delay = this.limiter.record(byte_count, Instant::now());
+            if !delay.is_zero() {
+                this.delay = Some(Box::pin(tokio::time::sleep(delay)));
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Store wrapper limiting the download and / or upload bandwidth.
+///
+/// Streamed transfers are paced as they are read. Buffered downloads are streamed from
+/// the underlying store, so that they are paced as well; as a consequence, only the initial
+/// request of such a download is retried. Buffered uploads are retried as usual, so they
+/// are accounted as a whole before they start; they are therefore only limited on average.
+#[derive(Debug)]
+pub(crate) struct ThrottledObjectStore {
+    inner: Box<dyn ObjectStore>,
+    download_limiter: Option<Arc<BandwidthLimiter>>,
+    upload_limiter: Option<Arc<BandwidthLimiter>>,
+}
+
+impl ThrottledObjectStore {
+    pub fn new(
+        inner: Box<dyn ObjectStore>,
+        max_download_bytes_per_sec: Option<u64>,
+        max_upload_bytes_per_sec: Option<u64>,
+    ) -> Self {
+        let new_limiter = |direction, limit| Arc::new(BandwidthLimiter::new(direction, limit));
+        Self {
+            inner,
+            download_limiter: max_download_bytes_per_sec
+                .map(|limit| new_limiter("download", limit)),
+            upload_limiter: max_upload_bytes_per_sec.map(|limit| new_limiter("upload", limit)),
+        }
+    }
+
+    async fn throttle_upload(&self, byte_count: usize) {
+        if let Some(limiter) = &self.upload_limiter {
+            limiter.throttle(byte_count as u64).await;
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for ThrottledObjectStore {
+    async fn get_raw(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        if self.download_limiter.is_none() {
+            return self.inner.get_raw(bucket, key).await;
+        }
+        let mut reader = self.get_raw_stream(bucket, key).await?;
+        let mut blob = vec![];
+        reader
+            .read_to_end(&mut blob)
+            .await
+            .map_err(|err| ObjectStoreError::Transport(err.into()))?;
+        Ok(blob)
+    }
+
+    async fn put_raw(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ObjectStoreError> {
+        self.throttle_upload(value.len()).await;
+        self.inner.put_raw(bucket, key, value).await
+    }
+
+    async fn remove_raw(&self, bucket: Bucket, key: &str) -> Result<(), ObjectStoreError> {
+        self.inner.remove_raw(bucket, key).await
+    }
+
+    async fn put_raw_if_absent(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<PutOutcome, ObjectStoreError> {
+        self.throttle_upload(value.len()).await;
+        self.inner.put_raw_if_absent(bucket, key, value).await
+    }
+
+    async fn remove_raw_many(
+        &self,
+        bucket: Bucket,
+        keys: &[String],
+    ) -> Result<(), ObjectStoreError> {
+        self.inner.remove_raw_many(bucket, keys).await
+    }
+
+    async fn get_raw_size(&self, bucket: Bucket, key: &str) -> Result<u64, ObjectStoreError> {
+        self.inner.get_raw_size(bucket, key).await
+    }
+
+    async fn exists_raw(&self, bucket: Bucket, key: &str) -> Result<bool, ObjectStoreError> {
+        self.inner.exists_raw(bucket, key).await
+    }
+
+    async fn list_raw(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+        continuation_token: Option<String>,
+    ) -> Result<(Vec<ObjectMeta>, Option<String>), ObjectStoreError> {
+        self.inner
+            .list_raw(bucket, prefix, continuation_token)
+            .await
+    }
+
+    async fn health_check(&self, bucket: Bucket) -> Result<(), ObjectStoreError> {
+        self.inner.health_check(bucket).await
+    }
+
+    async fn generate_download_url(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expiry: Duration,
+    ) -> Result<String, ObjectStoreError> {
+        self.inner.generate_download_url(bucket, key, expiry).await
+    }
+
+    async fn get_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<BlobReader, ObjectStoreError> {
+        let reader = self.inner.get_raw_stream(bucket, key).await?;
+        Ok(match &self.download_limiter {
+            Some(limiter) => Box::new(ThrottledReader::new(reader, Arc::clone(limiter))),
+            None => reader,
+        })
+    }
+
+    async fn put_raw_stream(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        reader: BlobReader,
+    ) -> Result<(), ObjectStoreError> {
+        let reader: BlobReader = match &self.upload_limiter {
+            Some(limiter) => Box::new(ThrottledReader::new(reader, Arc::clone(limiter))),
+            None => reader,
+        };
+        self.inner.put_raw_stream(bucket, key, reader).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockStore;
+
+    #[test]
+    fn test_limiter_allows_bursts_and_delays_subsequent_transfers() {
+        let limiter = BandwidthLimiter::new("download", 1_000);
+        let start = Instant::now();
+        assert_eq!(limiter.record(500, start), Duration::ZERO);
+        assert_eq!(limiter.record(500, start), Duration::ZERO);
+        // The burst capacity is exhausted.
+        assert_eq!(limiter.record(250, start), Duration::from_millis(250));
+        let now = start + Duration::from_millis(250);
+        assert_eq!(limiter.record(500, now), Duration::from_millis(500));
+
+        // After an idle period, the entire burst capacity is available again.
+        let now = start + Duration::from_secs(10);
+        assert_eq!(limiter.record(1_000, now), Duration::ZERO);
+        assert_eq!(limiter.record(100, now), Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_limiter_is_shared_by_transfers() {
+        let store = ThrottledObjectStore::new(Box::new(MockStore::default()), Some(2_000), None);
+        for key in ["proof_1.bin", "proof_2.bin"] {
+            store
+                .put_raw(Bucket::ProofsFri, key, vec![1; 1_500])
+                .await
+                .unwrap();
+        }
+
+        let started_at = Instant::now();
+        let (first_blob, second_blob) = tokio::join!(
+            store.get_raw(Bucket::ProofsFri, "proof_1.bin"),
+            store.get_raw(Bucket::ProofsFri, "proof_2.bin")
+        );
+        assert_eq!(first_blob.unwrap().len(), 1_500);
+        assert_eq!(second_blob.unwrap().len(), 1_500);
+        // 3,000 bytes at 2,000 bytes/s with a 1-second burst take at least 0.5 seconds.
+        let elapsed = started_at.elapsed();
+        assert!(elapsed >= Duration::from_millis(500), "{elapsed:?}");
+    }
+}
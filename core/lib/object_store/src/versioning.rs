@@ -0,0 +1,363 @@
+//! Versioned envelopes for serialized [`StoredObject`]s.
+//!
+//! Objects with a positive [format version](StoredObject::FORMAT_VERSION) are stored with
+//! a header specifying the version, so that blobs written by older binaries can be detected
+//! and migrated (or rejected with an actionable error) rather than failing to deserialize.
+//! Objects without the header (e.g., ones stored before versioning was introduced) have version 0.
+//!
+//! Objects encoded with a [codec](StoredObject::CODEC) other than bincode are always stored
+//! with a header specifying both the version and the codec, so that readers never need to guess
+//! the encoding. Bincode-encoded objects keep the original header (or no header) for compatibility.
+
+use std::io::{self, Read as _};
+
+use crate::{
+    objects::{Codec, StoredObject},
+    raw::ObjectStoreError,
+};
+
+/// Magic prepended to bincode-encoded objects with a positive format version. It's followed
+/// by the version (2 bytes, little-endian) and the serialized object.
+const MAGIC: &[u8] = b"ZKV\x01";
+const HEADER_LEN: usize = MAGIC.len() + 2;
+/// Magic prepended to objects encoded with other codecs. It's followed by the version (2 bytes,
+/// little-endian), the codec (1 byte) and the serialized object.
+const CODEC_MAGIC: &[u8] = b"ZKV\x02";
+const CODEC_HEADER_LEN: usize = CODEC_MAGIC.len() + 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    version: u16,
+    codec: u8,
+    len: usize,
+}
+
+impl Header {
+    /// Header of blobs stored without one.
+    const NONE: Self = Self {
+        version: 0,
+        codec: Codec::Bincode.to_byte(),
+        len: 0,
+    };
+}
+
+/// Returns the header prepended to serialized values of type `V`, or `None` if values
+/// are stored without a header.
+pub(crate) fn header_for<V: StoredObject>() -> Option<Vec<u8>> {
+    let version = V::FORMAT_VERSION.to_le_bytes();
+    if V::CODEC != Codec::Bincode {
+        let mut header = Vec::with_capacity(CODEC_HEADER_LEN);
+        header.extend_from_slice(CODEC_MAGIC);
+        header.extend_from_slice(&version);
+        header.push(V::CODEC.to_byte());
+        return Some(header);
+    }
+    if V::FORMAT_VERSION == 0 {
+        return None;
+    }
+    Some([MAGIC, &version[..]].concat())
+}
+
+fn parse_header(prefix: &[u8]) -> Header {
+    if let Some(&[version_lo, version_hi, codec, ..]) = prefix.strip_prefix(CODEC_MAGIC) {
+        return Header {
+            version: u16::from_le_bytes([version_lo, version_hi]),
+            codec,
+            len: CODEC_HEADER_LEN,
+        };
+    }
+    if let Some(&[version_lo, version_hi, ..]) = prefix.strip_prefix(MAGIC) {
+        return Header {
+            version: u16::from_le_bytes([version_lo, version_hi]),
+            codec: Codec::Bincode.to_byte(),
+            len: HEADER_LEN,
+        };
+    }
+    Header::NONE
+}
+
+fn check_header<V: StoredObject>(header: Header) -> Result<(), ObjectStoreError> {
+    match Codec::from_byte(header.codec) {
+        Some(codec) if codec == V::CODEC => {}
+        Some(codec) => {
+            let message = format!("object is encoded with {codec}, expected {}", V::CODEC);
+            return Err(ObjectStoreError::Serialization(message.into()));
+        }
+        None => {
+            let message = format!("object is encoded with unknown codec #{}", header.codec);
+            return Err(ObjectStoreError::Serialization(message.into()));
+        }
+    }
+
+    let supported = V::MIN_FORMAT_VERSION..=V::FORMAT_VERSION;
+    if supported.contains(&header.version) {
+        Ok(())
+    } else {
+        Err(ObjectStoreError::UnsupportedVersion {
+            found: header.version,
+            supported,
+        })
+    }
+}
+
+/// Serializes the value, prepending the format version header if necessary.
+///
+/// # Errors
+///
+/// Returns an error if serialization fails.
+pub fn serialize_versioned<V: StoredObject>(value: &V) -> Result<Vec<u8>, ObjectStoreError> {
+    let Some(mut blob) = header_for::<V>() else {
+        return value.serialize().map_err(ObjectStoreError::Serialization);
+    };
+    value
+        .serialize_into(&mut blob)
+        .map_err(ObjectStoreError::Serialization)?;
+    Ok(blob)
+}
+
+/// Deserializes a value from the blob based on its format version. Values with older versions
+/// are deserialized using [`StoredObject::migrate()`].
+///
+/// # Errors
+///
+/// Returns an error if the format version or the codec of the blob is not supported, or if
+/// deserialization fails.
+pub fn deserialize_versioned<V: StoredObject>(mut blob: Vec<u8>) -> Result<V, ObjectStoreError> {
+    let header = parse_header(&blob);
+    check_header::<V>(header)?;
+    blob.drain(..header.len);
+    let value = if header.version == V::FORMAT_VERSION {
+        V::deserialize(blob)
+    } else {
+        V::migrate(header.version, blob)
+    };
+    value.map_err(ObjectStoreError::Serialization)
+}
+
+/// Same as [`deserialize_versioned()`], but reads the blob from the `reader`. Values with
+/// the current format version are deserialized incrementally; values that need to be
+/// migrated are buffered in memory.
+pub(crate) fn deserialize_versioned_from<V: StoredObject>(
+    reader: &mut dyn io::Read,
+) -> Result<V, ObjectStoreError> {
+    let mut prefix = Vec::with_capacity(CODEC_HEADER_LEN);
+    (&mut *reader)
+        .take(CODEC_HEADER_LEN as u64)
+        .read_to_end(&mut prefix)
+        .map_err(|err| ObjectStoreError::Serialization(err.into()))?;
+    let header = parse_header(&prefix);
+    check_header::<V>(header)?;
+    prefix.drain(..header.len);
+    let mut reader = io::Cursor::new(prefix).chain(reader);
+    let value = if header.version == V::FORMAT_VERSION {
+        V::deserialize_from(&mut reader)
+    } else {
+        let mut bytes = vec![];
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|err| ObjectStoreError::Serialization(err.into()))?;
+        V::migrate(header.version, bytes)
+    };
+    value.map_err(ObjectStoreError::Serialization)
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use std::fmt;
+
+    use super::*;
+    use crate::{raw::BoxedError, Bucket};
+
+    /// Object whose format has changed from a little-endian `u32` (v0) to a big-endian `u64` (v2).
+    /// Migration from v1 is no longer supported.
+    #[derive(Debug, PartialEq)]
+    struct Counter(u64);
+
+    impl StoredObject for Counter {
+        const BUCKET: Bucket = Bucket::ProofsFri;
+        const FORMAT_VERSION: u16 = 2;
+        const MIN_FORMAT_VERSION: u16 = 0;
+        type Key<'a> = u32;
+
+        fn encode_key(key: Self::Key<'_>) -> String {
+            format!("counter_{key}.bin")
+        }
+
+        fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
+            Ok(self.0.to_be_bytes().to_vec())
+        }
+
+        fn deserialize(bytes: Vec<u8>) -> Result<Self, BoxedError> {
+            let bytes = bytes.try_into().map_err(|_| "invalid counter")?;
+            Ok(Self(u64::from_be_bytes(bytes)))
+        }
+
+        fn migrate(version: u16, bytes: Vec<u8>) -> Result<Self, BoxedError> {
+            match version {
+                0 => {
+                    let bytes = bytes.try_into().map_err(|_| "invalid legacy counter")?;
+                    Ok(Self(u32::from_le_bytes(bytes).into()))
+                }
+                _ => Err(format!("cannot migrate counter from v{version}").into()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_versioned_serialization_roundtrip() {
+        let blob = serialize_versioned(&Counter(42)).unwrap();
+        assert!(blob.starts_with(MAGIC));
+        assert_eq!(blob.len(), HEADER_LEN + 8);
+        assert_eq!(
+            deserialize_versioned::<Counter>(blob.clone()).unwrap(),
+            Counter(42)
+        );
+        let counter: Counter = deserialize_versioned_from(&mut blob.as_slice()).unwrap();
+        assert_eq!(counter, Counter(42));
+    }
+
+    #[test]
+    fn test_legacy_blobs_are_migrated() {
+        let blob = 42_u32.to_le_bytes().to_vec();
+        assert_eq!(
+            deserialize_versioned::<Counter>(blob.clone()).unwrap(),
+            Counter(42)
+        );
+        let counter: Counter = deserialize_versioned_from(&mut blob.as_slice()).unwrap();
+        assert_eq!(counter, Counter(42));
+
+        let mut blob = MAGIC.to_vec();
+        blob.extend_from_slice(&1_u16.to_le_bytes());
+        blob.extend_from_slice(&[0; 4]);
+        let err = deserialize_versioned::<Counter>(blob).unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Serialization(_)), "{err}");
+    }
+
+    #[test]
+    fn test_unsupported_versions() {
+        let mut blob = MAGIC.to_vec();
+        blob.extend_from_slice(&3_u16.to_le_bytes());
+        blob.extend_from_slice(&[0; 8]);
+        let err = deserialize_versioned::<Counter>(blob.clone()).unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                ObjectStoreError::UnsupportedVersion { found: 3, supported }
+                    if *supported == (0..=2)
+            ),
+            "{err}"
+        );
+        let err = deserialize_versioned_from::<Counter>(&mut blob.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), "unsupported_version");
+        assert_eq!(
+            err.to_string(),
+            "unsupported format version: object is format v3, supported formats are v0..=v2"
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Artifact {
+        l1_batch_number: u32,
+        keys: Vec<String>,
+    }
+
+    impl Artifact {
+        fn new() -> Self {
+            Self {
+                l1_batch_number: 42,
+                keys: vec!["proof_1.bin".to_owned(), "proof_2.bin".to_owned()],
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct BincodeArtifact(Artifact);
+
+    crate::impl_stored_object!(BincodeArtifact {
+        bucket: ProofsFri,
+        key: u32 as id,
+        key_format: "artifact_{id}.bin",
+        codec: bincode,
+    });
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct CborArtifact(Artifact);
+
+    crate::impl_stored_object!(CborArtifact {
+        bucket: ProofsFri,
+        key: u32 as id,
+        key_format: "artifact_{id}.cbor",
+        codec: cbor,
+    });
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct JsonArtifact(Artifact);
+
+    crate::impl_stored_object!(JsonArtifact {
+        bucket: ProofsFri,
+        key: u32 as id,
+        key_format: "artifact_{id}.json",
+        codec: json,
+    });
+
+    fn assert_roundtrip<V: StoredObject + PartialEq + fmt::Debug>(value: &V) -> Vec<u8> {
+        let blob = serialize_versioned(value).unwrap();
+        assert_eq!(deserialize_versioned::<V>(blob.clone()).unwrap(), *value);
+        let deserialized: V = deserialize_versioned_from(&mut blob.as_slice()).unwrap();
+        assert_eq!(deserialized, *value);
+        blob
+    }
+
+    #[test]
+    fn test_codec_roundtrips() {
+        // Bincode-encoded blobs are stored without a header as before.
+        let blob = assert_roundtrip(&BincodeArtifact(Artifact::new()));
+        assert_eq!(blob, bincode::serialize(&Artifact::new()).unwrap());
+
+        let blob = assert_roundtrip(&CborArtifact(Artifact::new()));
+        assert_eq!(blob[..CODEC_MAGIC.len()], *CODEC_MAGIC);
+        assert_eq!(blob[CODEC_MAGIC.len()..CODEC_HEADER_LEN], [0, 0, 1]);
+        let artifact: Artifact = ciborium::de::from_reader(&blob[CODEC_HEADER_LEN..]).unwrap();
+        assert_eq!(artifact, Artifact::new());
+
+        let blob = assert_roundtrip(&JsonArtifact(Artifact::new()));
+        assert_eq!(blob[CODEC_MAGIC.len()..CODEC_HEADER_LEN], [0, 0, 2]);
+        let json: serde_json::Value = serde_json::from_slice(&blob[CODEC_HEADER_LEN..]).unwrap();
+        assert_eq!(json["l1_batch_number"], 42);
+        assert_eq!(json["keys"][1], "proof_2.bin");
+    }
+
+    #[test]
+    fn test_codec_mismatch() {
+        let blob = serialize_versioned(&JsonArtifact(Artifact::new())).unwrap();
+        let err = deserialize_versioned::<CborArtifact>(blob.clone()).unwrap_err();
+        assert!(matches!(err, ObjectStoreError::Serialization(_)), "{err}");
+        assert!(
+            err.to_string().contains("encoded with json, expected cbor"),
+            "{err}"
+        );
+        let err = deserialize_versioned::<BincodeArtifact>(blob).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("encoded with json, expected bincode"),
+            "{err}"
+        );
+
+        // Blobs without a header are bincode-encoded.
+        let blob = serialize_versioned(&BincodeArtifact(Artifact::new())).unwrap();
+        let err = deserialize_versioned_from::<JsonArtifact>(&mut blob.as_slice()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("encoded with bincode, expected json"),
+            "{err}"
+        );
+
+        let mut blob = CODEC_MAGIC.to_vec();
+        blob.extend_from_slice(&[0, 0, 9]);
+        blob.extend_from_slice(b"{}");
+        let err = deserialize_versioned::<JsonArtifact>(blob).unwrap_err();
+        assert!(err.to_string().contains("unknown codec #9"), "{err}");
+    }
+}
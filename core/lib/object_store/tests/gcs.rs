@@ -0,0 +1,84 @@
+//! Integration tests for the GCS-based object store run against a GCS emulator. The tests
+//! require the emulator and are ignored by default. To run them against fake-gcs-server in Docker:
+//!
+//! ```text
+//! mkdir -p /tmp/gcs-data/zksync-test
+//! docker run -d -p 4443:4443 -v /tmp/gcs-data:/data fsouza/fake-gcs-server \
+//!     -scheme http -public-host localhost:4443
+//! cargo test -p zksync_object_store --test gcs -- --ignored
+//! ```
+//!
+//! The endpoint and the bucket can be overridden with `GCS_TEST_ENDPOINT` and `GCS_TEST_BUCKET`
+//! env variables respectively.
+
+use std::env;
+
+use zksync_config::{configs::object_store::ObjectStoreMode, ObjectStoreConfig};
+use zksync_object_store::{Bucket, ObjectStore, ObjectStoreError, ObjectStoreFactory};
+use zksync_types::{proofs::PrepareBasicCircuitsJob, L1BatchNumber};
+
+async fn create_store() -> Box<dyn ObjectStore> {
+    let endpoint =
+        env::var("GCS_TEST_ENDPOINT").unwrap_or_else(|_| "http://localhost:4443".to_owned());
+    let bucket = env::var("GCS_TEST_BUCKET").unwrap_or_else(|_| "zksync-test".to_owned());
+    let mut config = ObjectStoreConfig::new(ObjectStoreMode::GCS, bucket);
+    config.max_retries = 2;
+    config.gcs_endpoint_override = Some(endpoint);
+    config.gcs_use_anonymous_credentials = Some(true);
+    ObjectStoreFactory::new(config)
+        .create_store()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires a GCS emulator"]
+async fn blobs_roundtrip() {
+    let store = create_store().await;
+    let key = "1_0_1_BasicCircuits_0.bin";
+    store
+        .put_raw(Bucket::ProverJobsFri, key, vec![1, 2, 3])
+        .await
+        .unwrap();
+    let blob = store.get_raw(Bucket::ProverJobsFri, key).await.unwrap();
+    assert_eq!(blob, [1, 2, 3]);
+    let size = store
+        .get_raw_size(Bucket::ProverJobsFri, key)
+        .await
+        .unwrap();
+    assert_eq!(size, 3);
+
+    store
+        .put_raw(Bucket::ProverJobsFri, key, vec![4, 5])
+        .await
+        .unwrap();
+    let blob = store.get_raw(Bucket::ProverJobsFri, key).await.unwrap();
+    assert_eq!(blob, [4, 5]);
+
+    store.remove_raw(Bucket::ProverJobsFri, key).await.unwrap();
+    let err = store.get_raw(Bucket::ProverJobsFri, key).await.unwrap_err();
+    assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+}
+
+#[tokio::test]
+#[ignore = "requires a GCS emulator"]
+async fn missing_objects_are_reported_as_not_found() {
+    let store = create_store().await;
+    let err = store
+        .get::<PrepareBasicCircuitsJob>(L1BatchNumber(u32::MAX))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+    let err = store
+        .get_raw_size(Bucket::WitnessInput, "missing.bin")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+
+    // Missing objects are skipped when removing objects in bulk.
+    let keys = ["missing.bin".to_owned()];
+    store
+        .remove_raw_many(Bucket::WitnessInput, &keys)
+        .await
+        .unwrap();
+}
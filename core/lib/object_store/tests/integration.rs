@@ -2,7 +2,9 @@
 
 use tokio::fs;
 
-use zksync_object_store::{Bucket, ObjectStoreFactory};
+use std::sync::Arc;
+
+use zksync_object_store::{Bucket, ObjectStoreError, ObjectStoreFactory, PutOutcome};
 use zksync_types::{
     proofs::{PrepareBasicCircuitsJob, StorageLogMetadata},
     L1BatchNumber,
@@ -17,7 +19,7 @@ async fn prepare_basic_circuits_job_serialization() {
     let snapshot = fs::read("./tests/snapshots/prepare-basic-circuits-job-full.bin")
         .await
         .unwrap();
-    let store = ObjectStoreFactory::mock().create_store().await;
+    let store = ObjectStoreFactory::mock().create_store().await.unwrap();
     store
         .put_raw(
             Bucket::WitnessInput,
@@ -38,6 +40,24 @@ async fn prepare_basic_circuits_job_serialization() {
     );
 }
 
+#[tokio::test]
+async fn blob_sizes_are_reported() {
+    let snapshot = fs::read("./tests/snapshots/prepare-basic-circuits-job-full.bin")
+        .await
+        .unwrap();
+    let job: PrepareBasicCircuitsJob = bincode::deserialize(&snapshot).unwrap();
+    let store = ObjectStoreFactory::mock().create_store().await.unwrap();
+
+    let (key, size) = store.put_with_size(L1BatchNumber(1), &job).await.unwrap();
+    assert_eq!(key, "merkel_tree_paths_1.bin");
+    assert_eq!(size, snapshot.len() as u64);
+    let (_, size) = store
+        .get_with_size::<PrepareBasicCircuitsJob>(L1BatchNumber(1))
+        .await
+        .unwrap();
+    assert_eq!(size, snapshot.len() as u64);
+}
+
 fn assert_job_integrity(next_enumeration_index: u64, merkle_paths: Vec<StorageLogMetadata>) {
     assert_eq!(next_enumeration_index, 1);
     assert_eq!(merkle_paths.len(), 3);
@@ -65,3 +85,124 @@ async fn prepare_basic_circuits_job_compatibility() {
 
     assert_job_integrity(job_tuple.1, job_tuple.0);
 }
+
+#[tokio::test]
+async fn mock_store_reports_missing_keys_and_lists_stored_keys() {
+    let factory = ObjectStoreFactory::mock();
+    let store = factory.create_store().await.unwrap();
+    let err = store
+        .get_raw(Bucket::ProofsFri, "proof_1.bin")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+    assert!(factory.mock_keys(Bucket::ProofsFri).await.is_empty());
+
+    for key in ["proof_2.bin", "proof_1.bin"] {
+        store
+            .put_raw(Bucket::ProofsFri, key, vec![1])
+            .await
+            .unwrap();
+    }
+    // Stores created by the same factory share the data.
+    let other_store = factory.create_store().await.unwrap();
+    other_store
+        .remove_raw(Bucket::ProofsFri, "proof_2.bin")
+        .await
+        .unwrap();
+    assert_eq!(factory.mock_keys(Bucket::ProofsFri).await, ["proof_1.bin"]);
+    assert!(factory.mock_keys(Bucket::WitnessInput).await.is_empty());
+}
+
+#[tokio::test]
+async fn put_if_absent_keeps_stored_values() {
+    let store = ObjectStoreFactory::mock().create_store().await.unwrap();
+    let job = PrepareBasicCircuitsJob::new(1);
+    let (key, outcome) = store.put_if_absent(L1BatchNumber(1), &job).await.unwrap();
+    assert_eq!(key, "merkel_tree_paths_1.bin");
+    assert_eq!(outcome, PutOutcome::Created);
+    let stored_bytes = store.get_raw(Bucket::WitnessInput, &key).await.unwrap();
+
+    // The second put with the same value is a no-op.
+    let (_, size, outcome) = store
+        .put_if_absent_with_size(L1BatchNumber(1), &job)
+        .await
+        .unwrap();
+    assert_eq!(outcome, PutOutcome::AlreadyExists);
+    assert_eq!(size, stored_bytes.len() as u64);
+    let bytes = store.get_raw(Bucket::WitnessInput, &key).await.unwrap();
+    assert_eq!(bytes, stored_bytes);
+
+    // A differing value is reported as a conflict, and the stored value is kept.
+    let other_job = PrepareBasicCircuitsJob::new(2);
+    let (_, outcome) = store
+        .put_if_absent(L1BatchNumber(1), &other_job)
+        .await
+        .unwrap();
+    assert_eq!(outcome, PutOutcome::Conflict);
+    let job: PrepareBasicCircuitsJob = store.get(L1BatchNumber(1)).await.unwrap();
+    assert_eq!(job.next_enumeration_index(), 1);
+}
+
+#[tokio::test]
+async fn optional_objects_and_removal() {
+    let store = ObjectStoreFactory::mock().create_store().await.unwrap();
+    let job = store
+        .get_opt::<PrepareBasicCircuitsJob>(L1BatchNumber(1))
+        .await
+        .unwrap();
+    assert!(job.is_none());
+
+    store
+        .put_raw(Bucket::WitnessInput, "merkel_tree_paths_1.bin", vec![1, 2])
+        .await
+        .unwrap();
+    let err = store
+        .get_opt::<PrepareBasicCircuitsJob>(L1BatchNumber(1))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ObjectStoreError::Serialization(_)), "{err}");
+
+    store
+        .remove::<PrepareBasicCircuitsJob>(L1BatchNumber(1))
+        .await
+        .unwrap();
+    let job = store
+        .get_opt::<PrepareBasicCircuitsJob>(L1BatchNumber(1))
+        .await
+        .unwrap();
+    assert!(job.is_none());
+}
+
+#[tokio::test]
+async fn streamed_objects_are_compatible_with_buffered_ones() {
+    let snapshot = fs::read("./tests/snapshots/prepare-basic-circuits-job-full.bin")
+        .await
+        .unwrap();
+    let store = ObjectStoreFactory::mock().create_store().await.unwrap();
+    store
+        .put_raw(
+            Bucket::WitnessInput,
+            "merkel_tree_paths_1.bin",
+            snapshot.clone(),
+        )
+        .await
+        .unwrap();
+
+    let job: PrepareBasicCircuitsJob = store.get_streamed(L1BatchNumber(1)).await.unwrap();
+    let next_enumeration_index = job.next_enumeration_index();
+    let key = store
+        .put_streamed(L1BatchNumber(2), Arc::new(job))
+        .await
+        .unwrap();
+    let serialized_job = store.get_raw(Bucket::WitnessInput, &key).await.unwrap();
+    assert_eq!(serialized_job, snapshot);
+
+    let job: PrepareBasicCircuitsJob = store.get(L1BatchNumber(2)).await.unwrap();
+    assert_eq!(job.next_enumeration_index(), next_enumeration_index);
+
+    let err = store
+        .get_streamed::<PrepareBasicCircuitsJob>(L1BatchNumber(3))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+}
@@ -0,0 +1,75 @@
+//! Integration tests for the S3-based object store. The tests require an S3-compatible store
+//! and are ignored by default. To run them against MinIO in Docker:
+//!
+//! ```text
+//! docker run -d -p 9000:9000 -e MINIO_ROOT_USER=minioadmin -e MINIO_ROOT_PASSWORD=minioadmin \
+//!     minio/minio server /data
+//! docker run --rm --network host --entrypoint sh minio/mc -c \
+//!     "mc alias set local http://localhost:9000 minioadmin minioadmin && mc mb local/zksync-test"
+//! AWS_ACCESS_KEY_ID=minioadmin AWS_SECRET_ACCESS_KEY=minioadmin \
+//!     cargo test -p zksync_object_store --test s3 -- --ignored
+//! ```
+//!
+//! The endpoint and the bucket can be overridden with `S3_TEST_ENDPOINT` and `S3_TEST_BUCKET`
+//! env variables respectively.
+
+use std::env;
+
+use zksync_config::{configs::object_store::ObjectStoreMode, ObjectStoreConfig};
+use zksync_object_store::{Bucket, ObjectStore, ObjectStoreError, ObjectStoreFactory};
+use zksync_types::{proofs::PrepareBasicCircuitsJob, L1BatchNumber};
+
+async fn create_store() -> Box<dyn ObjectStore> {
+    let endpoint =
+        env::var("S3_TEST_ENDPOINT").unwrap_or_else(|_| "http://localhost:9000".to_owned());
+    let bucket = env::var("S3_TEST_BUCKET").unwrap_or_else(|_| "zksync-test".to_owned());
+    let mut config = ObjectStoreConfig::new(ObjectStoreMode::S3, bucket);
+    config.max_retries = 2;
+    config.s3_region = Some("us-east-1".to_owned());
+    config.s3_endpoint = Some(endpoint);
+    ObjectStoreFactory::new(config)
+        .create_store()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires an S3-compatible store"]
+async fn blobs_roundtrip() {
+    let store = create_store().await;
+    let key = "1_0_1_BasicCircuits_0.bin";
+    store
+        .put_raw(Bucket::ProverJobsFri, key, vec![1, 2, 3])
+        .await
+        .unwrap();
+    let blob = store.get_raw(Bucket::ProverJobsFri, key).await.unwrap();
+    assert_eq!(blob, [1, 2, 3]);
+
+    store
+        .put_raw(Bucket::ProverJobsFri, key, vec![4, 5])
+        .await
+        .unwrap();
+    let blob = store.get_raw(Bucket::ProverJobsFri, key).await.unwrap();
+    assert_eq!(blob, [4, 5]);
+
+    store.remove_raw(Bucket::ProverJobsFri, key).await.unwrap();
+    let err = store.get_raw(Bucket::ProverJobsFri, key).await.unwrap_err();
+    assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+}
+
+#[tokio::test]
+#[ignore = "requires an S3-compatible store"]
+async fn missing_objects_are_reported_as_not_found() {
+    let store = create_store().await;
+    let err = store
+        .get::<PrepareBasicCircuitsJob>(L1BatchNumber(u32::MAX))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ObjectStoreError::KeyNotFound(_)), "{err}");
+
+    // Removing a missing object is not an error.
+    store
+        .remove_raw(Bucket::WitnessInput, "missing.bin")
+        .await
+        .unwrap();
+}
@@ -0,0 +1,138 @@
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Context of a job processing attempt passed to
+/// [`JobProcessor::process_job_with_context()`](crate::JobProcessor::process_job_with_context()).
+///
+/// Synchronous processing cannot be interrupted by the runner, so long-running jobs should check
+/// [`Self::should_abort()`] at natural phase boundaries and return [`JobAborted`] once it's set.
+#[derive(Debug, Clone, Default)]
+pub struct JobContext {
+    abort_requested: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    metadata: JobMetadata,
+}
+
+impl JobContext {
+    /// Creates a context for an attempt that should be aborted after the `deadline`, if any.
+    pub fn new(deadline: Option<Instant>, metadata: JobMetadata) -> Self {
+        Self {
+            abort_requested: Arc::default(),
+            deadline,
+            metadata,
+        }
+    }
+
+    /// Returns the metadata of the job recorded when it was picked up.
+    pub fn metadata(&self) -> &JobMetadata {
+        &self.metadata
+    }
+
+    /// Returns `true` if processing should be stopped, i.e. the processor is shutting down
+    /// or the attempt has exceeded its deadline.
+    pub fn should_abort(&self) -> bool {
+        self.abort_requested.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .map_or(false, |deadline| Instant::now() >= deadline)
+    }
+
+    /// Returns an error if processing should be stopped after the specified `phase`.
+    pub fn check_abort(&self, phase: &'static str) -> Result<(), JobAborted> {
+        if self.should_abort() {
+            Err(JobAborted { phase })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn request_abort(&self) {
+        self.abort_requested.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Metadata of a job recorded when it was picked up, e.g. to adapt processing on retries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobMetadata {
+    /// Number of times the job was picked up from the job queue, including the current pickup,
+    /// or `None` if the job queue doesn't track it.
+    pub attempts: Option<u32>,
+    /// Time when the job was picked up.
+    pub picked_at: Instant,
+}
+
+impl JobMetadata {
+    /// Creates metadata for a job picked up just now.
+    pub fn new(attempts: Option<u32>) -> Self {
+        Self {
+            attempts,
+            picked_at: Instant::now(),
+        }
+    }
+}
+
+impl Default for JobMetadata {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Context of a failed job passed to
+/// [`JobProcessor::save_failure_with_context()`](crate::JobProcessor::save_failure_with_context()),
+/// e.g. to record the history of job failures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobFailureContext {
+    /// Metadata of the job recorded when it was picked up.
+    pub metadata: JobMetadata,
+    /// Number of in-process attempts made to process the job, including the failed one.
+    pub attempts: u32,
+    /// Host name of the worker that processed the job, or `None` if it cannot be determined.
+    pub hostname: Option<String>,
+}
+
+impl JobFailureContext {
+    /// Creates a context for a job failed on this worker.
+    pub fn new(metadata: JobMetadata, attempts: u32) -> Self {
+        Self {
+            metadata,
+            attempts,
+            hostname: current_hostname(),
+        }
+    }
+}
+
+/// Returns the host name from the `HOSTNAME` env var (in Kubernetes, it's set to the pod name),
+/// falling back to the kernel host name on Linux.
+pub(crate) fn current_hostname() -> Option<String> {
+    let hostname = env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())?;
+    let hostname = hostname.trim();
+    if hostname.is_empty() {
+        None
+    } else {
+        Some(hostname.to_owned())
+    }
+}
+
+/// Error returned by a job processing attempt stopped in response to [`JobContext::should_abort()`].
+/// Aborted jobs are released back to the queue with
+/// [`JobProcessor::release_job()`](crate::JobProcessor::release_job()) rather than failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobAborted {
+    /// Last phase of the job completed before the processing was aborted.
+    pub phase: &'static str,
+}
+
+impl fmt::Display for JobAborted {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "job aborted after {}", self.phase)
+    }
+}
+
+impl error::Error for JobAborted {}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::TaskFailure;
+
+/// Kind of a job failure. Kinds are persisted separately from error messages where supported,
+/// so that failed jobs can be aggregated by the failure kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobErrorKind {
+    /// Job input (e.g., a blob in the object store) is missing.
+    MissingInput,
+    /// Job input is present, but it's not what the job expects.
+    InvalidInput,
+    /// The verification key required by the job cannot be loaded.
+    VerificationKey,
+    /// Job input or artifacts cannot be (de)serialized.
+    Serialization,
+    /// The task processing the job has panicked.
+    Panic,
+    /// The task processing the job didn't finish in time.
+    Timeout,
+    /// The task processing the job was cancelled.
+    Cancelled,
+    /// A service required by the job (e.g., the object store) is temporarily unavailable.
+    /// Such jobs are released back to the queue rather than failed.
+    Unavailable,
+    /// Any other error.
+    Other,
+}
+
+impl JobErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MissingInput => "missing_input",
+            Self::InvalidInput => "invalid_input",
+            Self::VerificationKey => "verification_key",
+            Self::Serialization => "serialization",
+            Self::Panic => "panic",
+            Self::Timeout => "timeout",
+            Self::Cancelled => "cancelled",
+            Self::Unavailable => "unavailable",
+            Self::Other => "other",
+        }
+    }
+
+    /// Returns `true` if jobs failed with this kind of error may succeed on another attempt.
+    pub fn is_retryable(self) -> bool {
+        match self {
+            // Blobs may become available later, e.g. if the object store is eventually consistent.
+            Self::MissingInput | Self::Panic | Self::Unavailable | Self::Other => true,
+            // Timed out jobs are not retried since they are likely to time out again.
+            Self::InvalidInput
+            | Self::VerificationKey
+            | Self::Serialization
+            | Self::Timeout
+            | Self::Cancelled => false,
+        }
+    }
+}
+
+impl fmt::Display for JobErrorKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+/// Error persisted with [`JobProcessor::save_failure()`](crate::JobProcessor::save_failure()).
+///
+/// Job processors may return `JobError`s (possibly wrapped with `anyhow` context) from
+/// [`JobProcessor::process_job()`](crate::JobProcessor::process_job()) tasks to classify
+/// their failures; other errors are classified as [`JobErrorKind::Other`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JobError {
+    pub kind: JobErrorKind,
+    pub message: String,
+    /// Whether the job may succeed on another attempt. Retryable failures are retried in-process
+    /// and then requeued; non-retryable ones are persisted right away for manual inspection.
+    pub retryable: bool,
+    /// URL of the blob the error relates to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_url: Option<String>,
+    /// ID of the circuit the error relates to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circuit_id: Option<u8>,
+}
+
+impl JobError {
+    pub fn new(kind: JobErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            retryable: kind.is_retryable(),
+            blob_url: None,
+            circuit_id: None,
+        }
+    }
+
+    pub fn with_blob_url(mut self, blob_url: impl Into<String>) -> Self {
+        self.blob_url = Some(blob_url.into());
+        self
+    }
+
+    pub fn with_circuit_id(mut self, circuit_id: u8) -> Self {
+        self.circuit_id = Some(circuit_id);
+        self
+    }
+
+    /// Serializes this error to JSON, e.g. to persist it in the DB.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("failed serializing job error")
+    }
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for JobError {}
+
+impl From<anyhow::Error> for JobError {
+    fn from(err: anyhow::Error) -> Self {
+        // The message includes the context the error was wrapped with.
+        let message = format!("{:#}", err);
+        match err.downcast::<JobError>() {
+            Ok(job_error) => Self {
+                message,
+                ..job_error
+            },
+            Err(_) => Self::new(JobErrorKind::Other, message),
+        }
+    }
+}
+
+impl From<TaskFailure> for JobError {
+    fn from(failure: TaskFailure) -> Self {
+        let message = failure.to_string();
+        match failure {
+            TaskFailure::Error(job_error) => job_error,
+            TaskFailure::Panic(_) => Self::new(JobErrorKind::Panic, message),
+            TaskFailure::Timeout(_) => Self::new(JobErrorKind::Timeout, message),
+            TaskFailure::Cancelled | TaskFailure::Aborted => {
+                Self::new(JobErrorKind::Cancelled, message)
+            }
+        }
+    }
+}
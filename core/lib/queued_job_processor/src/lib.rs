@@ -1,17 +1,281 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
 use std::fmt::Debug;
 use std::time::{Duration, Instant};
 
 pub use async_trait::async_trait;
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{FuturesUnordered, StreamExt};
 use tokio::sync::watch;
-use tokio::task::JoinHandle;
+use tokio::task::{JoinError, JoinHandle};
 use tokio::time::sleep;
+use tracing::Instrument;
 
 use zksync_utils::panic_extractor::try_extract_panic_message;
 
+pub use crate::context::{JobAborted, JobContext, JobFailureContext, JobMetadata};
+pub use crate::error::{JobError, JobErrorKind};
+use crate::rate_limiter::TokenBucket;
+use crate::status::update_status;
+pub use crate::status::{JobProcessorStatus, SharedJobProcessorStatus};
+
+mod context;
+mod error;
+mod rate_limiter;
+mod status;
+#[cfg(test)]
+mod tests;
+
+/// Reason why the task processing a job didn't produce job artifacts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskFailure {
+    /// The task has panicked; contains the panic message.
+    Panic(String),
+    /// The task has returned an error; contains the error message along with its causes.
+    Error(JobError),
+    /// The task didn't finish within [`JobProcessor::job_timeout()`] and was aborted.
+    Timeout(Duration),
+    /// The task was cancelled, e.g. because the runtime is shutting down.
+    Cancelled,
+    /// The task has stopped in response to [`JobContext::should_abort()`] because the processor
+    /// is shutting down.
+    Aborted,
+}
+
+impl From<JoinError> for TaskFailure {
+    fn from(err: JoinError) -> Self {
+        if err.is_panic() {
+            Self::Panic(try_extract_panic_message(err))
+        } else {
+            Self::Cancelled
+        }
+    }
+}
+
+impl fmt::Display for TaskFailure {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panic(message) => formatter.write_str(message),
+            Self::Error(err) => fmt::Display::fmt(err, formatter),
+            Self::Timeout(timeout) => write!(
+                formatter,
+                "timeout: job exceeded time limit of {:?}",
+                timeout
+            ),
+            Self::Cancelled => formatter.write_str("Cancelled task"),
+            Self::Aborted => formatter.write_str("Aborted task"),
+        }
+    }
+}
+
+/// Policy for retrying failed jobs in-process before their failure is persisted
+/// with [`JobProcessor::save_failure()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts to process a job, including the first one.
+    pub max_attempts: u32,
+    /// Backoff before the second attempt; it is doubled for every subsequent attempt.
+    pub base_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Policy that persists the first failure of a job right away.
+    pub const fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            base_backoff: Duration::ZERO,
+        }
+    }
+
+    /// Returns the backoff to wait after the specified (1-based) failed attempt.
+    pub fn backoff_after(&self, attempt: u32) -> Duration {
+        let multiplier = 2_u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_backoff.saturating_mul(multiplier)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::no_retries()
+    }
+}
+
+/// Strategy for polling the job queue while it's empty: the polling interval grows
+/// with every empty poll and is reset once a job is picked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollingBackoff {
+    /// Interval before the first poll after an empty one.
+    pub initial_interval: Duration,
+    /// Cap on the polling interval.
+    pub max_interval: Duration,
+    /// Multiplier applied to the polling interval after every empty poll.
+    pub multiplier: u32,
+}
+
+impl PollingBackoff {
+    /// Returns the interval to wait after an empty poll following the specified interval.
+    pub fn next_interval(&self, interval: Duration) -> Duration {
+        interval
+            .saturating_mul(self.multiplier)
+            .min(self.max_interval)
+    }
+}
+
+/// Names of the metrics recorded by [`JobProcessor`] around its methods. All metrics are labeled
+/// with the service name and, if specified, with the aggregation round. Counters of completed jobs
+/// are additionally labeled with the number of [job attempts](JobMetadata::attempts), if known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobMetrics {
+    /// Histogram for the time taken by [`JobProcessor::get_next_jobs()`] to return jobs.
+    pub fetch_time: &'static str,
+    /// Histogram for the time taken to process a job successfully, including failed attempts.
+    pub processing_time: &'static str,
+    /// Histogram for the time taken by [`JobProcessor::save_result()`].
+    pub save_time: &'static str,
+    /// Gauge for the number of jobs returned by [`JobProcessor::pending_jobs_count()`],
+    /// additionally labeled with the job status.
+    pub pending_jobs: &'static str,
+    /// Value of the `aggregation_round` label.
+    pub aggregation_round: Option<&'static str>,
+}
+
+impl JobMetrics {
+    pub const DEFAULT: Self = Self {
+        fetch_time: "job_processor.fetch_time",
+        processing_time: "job_processor.processing_time",
+        save_time: "job_processor.save_time",
+        pending_jobs: "job_processor.pending_jobs",
+        aggregation_round: None,
+    };
+
+    /// Metrics reported by FRI witness generators for the specified aggregation round.
+    pub const fn fri_witness_generation(aggregation_round: &'static str) -> Self {
+        Self {
+            fetch_time: "prover_fri.witness_generation.blob_fetch_time",
+            processing_time: "prover_fri.witness_generation.witness_generation_time",
+            save_time: "prover_fri.witness_generation.blob_save_time",
+            pending_jobs: "prover_fri.witness_generation.queued_jobs",
+            aggregation_round: Some(aggregation_round),
+        }
+    }
+
+    fn report_time(&self, name: &'static str, service_name: &'static str, elapsed: Duration) {
+        match self.aggregation_round {
+            Some(round) => metrics::histogram!(
+                name,
+                elapsed,
+                "service_name" => service_name,
+                "aggregation_round" => round
+            ),
+            None => metrics::histogram!(name, elapsed, "service_name" => service_name),
+        }
+    }
+
+    fn report_pending_jobs(&self, service_name: &'static str, count: PendingJobsCount) {
+        let counts = [("queued", count.queued), ("in_progress", count.in_progress)];
+        for (status, count) in counts {
+            match self.aggregation_round {
+                Some(round) => metrics::gauge!(
+                    self.pending_jobs,
+                    count as f64,
+                    "service_name" => service_name,
+                    "aggregation_round" => round,
+                    "status" => status
+                ),
+                None => metrics::gauge!(
+                    self.pending_jobs,
+                    count as f64,
+                    "service_name" => service_name,
+                    "status" => status
+                ),
+            }
+        }
+    }
+
+    fn report_attempt(&self, service_name: &'static str, attempt: u32) {
+        let attempt = attempt.to_string();
+        match self.aggregation_round {
+            Some(round) => metrics::counter!(
+                "job_processor.job_attempts",
+                1,
+                "service_name" => service_name,
+                "aggregation_round" => round,
+                "attempt" => attempt
+            ),
+            None => metrics::counter!(
+                "job_processor.job_attempts",
+                1,
+                "service_name" => service_name,
+                "attempt" => attempt
+            ),
+        }
+    }
+
+    fn report_failed_job(&self, service_name: &'static str, retryable: bool) {
+        let retryable = if retryable { "true" } else { "false" };
+        match self.aggregation_round {
+            Some(round) => metrics::counter!(
+                "job_processor.failed_jobs",
+                1,
+                "service_name" => service_name,
+                "aggregation_round" => round,
+                "retryable" => retryable
+            ),
+            None => metrics::counter!(
+                "job_processor.failed_jobs",
+                1,
+                "service_name" => service_name,
+                "retryable" => retryable
+            ),
+        }
+    }
+
+    fn report_completed_job(
+        &self,
+        service_name: &'static str,
+        metadata: &JobMetadata,
+        outcome: &'static str,
+    ) {
+        let attempts = metadata
+            .attempts
+            .map_or_else(|| "unknown".to_owned(), |attempts| attempts.to_string());
+        match self.aggregation_round {
+            Some(round) => metrics::counter!(
+                "job_processor.completed_jobs",
+                1,
+                "service_name" => service_name,
+                "aggregation_round" => round,
+                "outcome" => outcome,
+                "attempts" => attempts
+            ),
+            None => metrics::counter!(
+                "job_processor.completed_jobs",
+                1,
+                "service_name" => service_name,
+                "outcome" => outcome,
+                "attempts" => attempts
+            ),
+        }
+    }
+}
+
+/// Number of jobs in the queue of a [`JobProcessor`] that are not completed yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PendingJobsCount {
+    pub queued: u64,
+    pub in_progress: u64,
+}
+
+impl Default for JobMetrics {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 #[async_trait]
 pub trait JobProcessor: Sync + Send {
-    type Job: Send + 'static;
-    type JobId: Send + Debug + 'static;
+    type Job: Clone + Send + 'static;
+    type JobId: Send + Sync + Debug + 'static;
     type JobArtifacts: Send + 'static;
 
     const POLLING_INTERVAL_MS: u64 = 1000;
@@ -22,98 +286,850 @@ pub trait JobProcessor: Sync + Send {
     /// Returns None when there is no pending job
     /// Otherwise, returns Some(job_id, job)
     /// Note: must be concurrency-safe - that is, one job must not be returned in two parallel processes
-    async fn get_next_job(&self) -> Option<(Self::JobId, Self::Job)>;
+    /// Errors are considered transient: the job pickup is retried after a backoff.
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>>;
 
-    /// Invoked when `process_job` panics
-    /// Should mark the job as failed
-    async fn save_failure(&self, job_id: Self::JobId, started_at: Instant, error: String);
+    /// Same as [`Self::get_next_job()`], but additionally returns the metadata of the picked job,
+    /// which is passed to [`Self::process_job_with_context()`]. Job queues tracking the number
+    /// of job attempts should override this method. By default, the number of attempts is unknown.
+    async fn get_next_job_with_metadata(
+        &self,
+    ) -> anyhow::Result<Option<(Self::JobId, Self::Job, JobMetadata)>> {
+        let next_job = self.get_next_job().await?;
+        Ok(next_job.map(|(job_id, job)| (job_id, job, JobMetadata::new(None))))
+    }
+
+    /// Returns up to `limit` pending jobs, or an empty `Vec` when there are no pending jobs.
+    /// Same as for [`Self::get_next_job()`], jobs must not be returned in two parallel processes.
+    /// By default, returns at most one job fetched with [`Self::get_next_job_with_metadata()`].
+    async fn get_next_jobs(
+        &self,
+        _limit: usize,
+    ) -> anyhow::Result<Vec<(Self::JobId, Self::Job, JobMetadata)>> {
+        Ok(self
+            .get_next_job_with_metadata()
+            .await?
+            .into_iter()
+            .collect())
+    }
+
+    /// Invoked when `process_job` panics or returns an error, and the job cannot be retried in-process.
+    /// Should mark the job as failed if the error is not [retryable](JobError::retryable), or requeue
+    /// the job unless it has exhausted its attempts.
+    async fn save_failure(&self, job_id: Self::JobId, started_at: Instant, error: JobError);
+
+    /// Same as [`Self::save_failure()`], but additionally receives the context of the failure,
+    /// such as the number of attempts and the host name of the worker. Job queues recording
+    /// the history of job failures should override this method. By default, the context is ignored.
+    async fn save_failure_with_context(
+        &self,
+        job_id: Self::JobId,
+        started_at: Instant,
+        error: JobError,
+        _context: JobFailureContext,
+    ) {
+        self.save_failure(job_id, started_at, error).await;
+    }
 
     /// Function that processes a job
+    /// Invoked within the job span; spawned tasks don't inherit it, so implementations should
+    /// pass `tracing::Span::current()` to the task and enter it there.
     async fn process_job(
         &self,
         job: Self::Job,
         started_at: Instant,
-    ) -> JoinHandle<Self::JobArtifacts>;
+    ) -> JoinHandle<anyhow::Result<Self::JobArtifacts>>;
+
+    /// Same as [`Self::process_job()`], but additionally receives the context of the attempt,
+    /// including the [metadata](JobContext::metadata()) of the job. Long-running jobs should override this method and return [`JobAborted`] from the task
+    /// once [`JobContext::should_abort()`] is set, so that shutdown and timeouts take effect
+    /// without waiting for the job to complete. By default, the context is ignored.
+    async fn process_job_with_context(
+        &self,
+        job: Self::Job,
+        started_at: Instant,
+        _context: JobContext,
+    ) -> JoinHandle<anyhow::Result<Self::JobArtifacts>> {
+        self.process_job(job, started_at).await
+    }
+
+    /// Returns the strategy for polling the job queue while it's empty. By default, it's defined
+    /// by [`Self::POLLING_INTERVAL_MS`], [`Self::MAX_BACKOFF_MS`] and [`Self::BACKOFF_MULTIPLIER`].
+    fn polling_backoff(&self) -> PollingBackoff {
+        PollingBackoff {
+            initial_interval: Duration::from_millis(Self::POLLING_INTERVAL_MS),
+            max_interval: Duration::from_millis(Self::MAX_BACKOFF_MS),
+            multiplier: Self::BACKOFF_MULTIPLIER as u32,
+        }
+    }
+
+    /// Waits before the job queue is polled again after it was found empty; `timeout` is
+    /// the current polling interval. Implementations may return early, e.g. once they are
+    /// notified about new jobs, in which case the queue is polled right away. By default,
+    /// sleeps for `timeout`.
+    async fn wait_for_new_jobs(&self, timeout: Duration) {
+        sleep(timeout).await;
+    }
+
+    /// Returns the names of the metrics recorded around job processing.
+    fn job_metrics(&self) -> JobMetrics {
+        JobMetrics::default()
+    }
+
+    /// Returns the policy for retrying failed jobs in-process. By default, failures are not retried.
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Returns the wall-clock time a single attempt to process a job may take, after which the job
+    /// is aborted and its failure is persisted. By default, jobs may take unlimited time.
+    fn job_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Returns the interval between [`Self::heartbeat()`] calls while a job is processed.
+    /// By default, heartbeats are disabled.
+    fn heartbeat_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Invoked periodically while the job is processed, e.g. to prevent the job
+    /// from being considered stuck and requeued.
+    async fn heartbeat(&self, _job_id: &Self::JobId) {}
+
+    /// Invoked before a fetched job is processed. Returns `false` if the processor cannot take
+    /// the job right now, e.g. because the machine lacks resources to process it; such jobs are
+    /// returned to the queue with [`Self::release_job()`]. By default, all jobs are accepted.
+    async fn can_accept_job(&self, _job_id: &Self::JobId) -> bool {
+        true
+    }
+
+    /// Returns the job rejected by [`Self::can_accept_job()`] to the queue, so that it can be
+    /// picked up by another processor. Must be implemented if `can_accept_job()` is overridden;
+    /// otherwise, rejected jobs are left in progress until they are requeued as stuck.
+    /// Jobs failed with [`JobErrorKind::Unavailable`] errors (either while being processed,
+    /// or while saving their results) are released as well, and job pickup is paused
+    /// with the [polling backoff](Self::polling_backoff()). Jobs fetched by
+    /// [`Self::get_next_jobs()`] but left unprocessed when the processor stops are released, too.
+    async fn release_job(&self, _job_id: Self::JobId) {}
+
+    /// Returns `true` if jobs are processed without persisting their outcomes, e.g. to debug
+    /// job processing against production data. In this mode, [`Self::get_next_job()`] must not
+    /// lock jobs, so that no job is left in progress.
+    fn is_dry_run(&self) -> bool {
+        false
+    }
+
+    /// Returns the size of job artifacts in bytes, if known; it's logged in dry-run mode.
+    fn artifacts_size(&self, _artifacts: &Self::JobArtifacts) -> Option<u64> {
+        None
+    }
+
+    /// Returns `true` if the job was cancelled while it was processed, e.g. because its batch was
+    /// skipped by an operator. Artifacts of cancelled jobs are discarded, and [`Self::save_cancelled()`]
+    /// is invoked instead of [`Self::save_result()`]. By default, jobs cannot be cancelled.
+    async fn is_job_cancelled(&self, _job_id: &Self::JobId) -> bool {
+        false
+    }
+
+    /// Invoked instead of [`Self::save_result()`] if the job was cancelled while it was processed.
+    async fn save_cancelled(&self, _job_id: Self::JobId, _started_at: Instant) {}
+
+    /// Returns the number of jobs after processing which the processor stops as if the stop signal
+    /// was received, e.g. to let it be restarted periodically. By default, the number is unlimited.
+    fn max_jobs_to_process(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns the number of consecutive polls finding the job queue empty, after which the processor
+    /// stops picking up jobs and exits once in-flight jobs are saved, e.g. to terminate a backfill.
+    /// By default, the processor keeps polling an empty queue indefinitely.
+    fn drain_after_empty_polls(&self) -> Option<u32> {
+        None
+    }
+
+    /// Returns the max number of jobs fetched with a single [`Self::get_next_jobs()`] call.
+    /// Fetched jobs are picked up one by one before the job queue is polled again; a pickup
+    /// attempt limited by [`Self::max_pickups_per_minute()`] corresponds to a single fetch.
+    /// By default, jobs are fetched one at a time.
+    fn job_pickup_batch_size(&self) -> usize {
+        1
+    }
+
+    /// Returns the max number of job pickup attempts per minute, including attempts that find
+    /// no job. Pickups are limited with a token bucket holding a minute worth of pickups; jobs
+    /// that are already being processed are unaffected. By default, pickups are unlimited.
+    fn max_pickups_per_minute(&self) -> Option<u32> {
+        None
+    }
+
+    /// Returns the status that the processor publishes while running, e.g. for health checks.
+    /// By default, the status is not published.
+    fn status(&self) -> Option<&SharedJobProcessorStatus> {
+        None
+    }
+
+    /// Returns the number of pending jobs in the queue; it's published as a gauge every time
+    /// the queue is polled, so it should be cheap to compute. By default, it's not published.
+    async fn pending_jobs_count(&self) -> Option<PendingJobsCount> {
+        None
+    }
+
+    /// Returns `true` if the processor should stop picking up new jobs after a job has panicked.
+    /// By default, the processor persists the failure and proceeds with subsequent jobs.
+    fn exit_on_panic(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if the failure must be persisted right away without retrying the job in-process,
+    /// e.g. because the job input cannot be deserialized and the job would fail on every attempt.
+    /// By default, failures are fatal if their [`JobErrorKind`] is not retryable.
+    fn is_fatal_failure(&self, error: &JobError) -> bool {
+        !error.kind.is_retryable()
+    }
 
     /// `iterations_left`:
     /// To run indefinitely, pass `None`,
     /// To process one job, pass `Some(1)`,
     /// To process a batch, pass `Some(batch_size)`.
-    async fn run(self, stop_receiver: watch::Receiver<bool>, mut iterations_left: Option<usize>)
+    ///
+    /// Returns an error if the outcome of a job cannot be saved; such errors are considered fatal.
+    async fn run(
+        self,
+        stop_receiver: watch::Receiver<bool>,
+        iterations_left: Option<usize>,
+    ) -> anyhow::Result<()>
     where
         Self: Sized,
     {
-        let mut backoff: u64 = Self::POLLING_INTERVAL_MS;
-        while iterations_left.map_or(true, |i| i > 0) {
-            if *stop_receiver.borrow() {
+        self.run_concurrent(stop_receiver, iterations_left, 1).await
+    }
+
+    /// Same as [`Self::run()`], but keeps up to `max_in_flight` jobs processed concurrently.
+    /// Outcomes of the jobs are saved as soon as each job completes, i.e. not necessarily
+    /// in the order the jobs were picked up.
+    async fn run_concurrent(
+        self,
+        stop_receiver: watch::Receiver<bool>,
+        mut iterations_left: Option<usize>,
+        max_in_flight: usize,
+    ) -> anyhow::Result<()>
+    where
+        Self: Sized,
+    {
+        let max_in_flight = max_in_flight.max(1);
+        let max_jobs_to_process = self.max_jobs_to_process();
+        let run_started_at = Instant::now();
+        let mut picked_jobs: u64 = 0;
+        let mut processed_jobs: u64 = 0;
+        let mut in_flight = FuturesUnordered::new();
+        // Jobs are identified by the debug representation of their ids.
+        let mut in_flight_job_ids = HashSet::new();
+        let polling_backoff = self.polling_backoff();
+        let job_metrics = self.job_metrics();
+        let mut backoff = polling_backoff.initial_interval;
+        let job_pickup_batch_size = self.job_pickup_batch_size().max(1);
+        let drain_after_empty_polls = self.drain_after_empty_polls();
+        let mut empty_polls: u32 = 0;
+        let mut prefetched_jobs = VecDeque::new();
+        let mut pickup_limiter = self
+            .max_pickups_per_minute()
+            .map(|max_pickups| TokenBucket::per_minute(max_pickups, Instant::now()));
+        let mut is_picking_jobs = true;
+        let mut fatal_error = None;
+        // Job pickup is paused while jobs fail because a required service is unavailable.
+        let mut unavailable_backoff = polling_backoff.initial_interval;
+        let mut paused_until: Option<Instant> = None;
+        loop {
+            if is_picking_jobs && *stop_receiver.borrow() {
                 vlog::warn!(
                     "Stop signal received, shutting down {} component while waiting for a new job",
                     Self::SERVICE_NAME
                 );
-                return;
+                is_picking_jobs = false;
+            }
+            if is_picking_jobs && iterations_left == Some(0) {
+                vlog::info!("Requested number of jobs is processed. Server can stop now.");
+                is_picking_jobs = false;
             }
-            if let Some((job_id, job)) = Self::get_next_job(&self).await {
-                let started_at = Instant::now();
-                backoff = Self::POLLING_INTERVAL_MS;
-                iterations_left = iterations_left.map(|i| i - 1);
-
-                vlog::debug!(
-                    "Spawning thread processing {:?} job with id {:?}",
-                    Self::SERVICE_NAME,
-                    job_id
+            if is_picking_jobs && max_jobs_to_process.map_or(false, |max| picked_jobs >= max) {
+                vlog::info!(
+                    "Max number of {} jobs to process is reached, shutting down",
+                    Self::SERVICE_NAME
                 );
-                let task = self.process_job(job, started_at).await;
+                is_picking_jobs = false;
+            }
+
+            let mut is_idle = false;
+            // Set if the processor is idle because the job queue is empty, rather than because
+            // of an error or a rejected job.
+            let mut is_queue_empty = false;
+            let mut pickup_delay = None;
+            let can_pick_job = is_picking_jobs && in_flight.len() < max_in_flight;
+            // The job queue is polled only once all prefetched jobs are picked up.
+            if can_pick_job && prefetched_jobs.is_empty() {
+                if let Some(until) = paused_until {
+                    let now = Instant::now();
+                    if until > now {
+                        pickup_delay = Some(until - now);
+                    } else {
+                        paused_until = None;
+                    }
+                }
+            }
+            if can_pick_job && prefetched_jobs.is_empty() && pickup_delay.is_none() {
+                if let Some(limiter) = &mut pickup_limiter {
+                    pickup_delay = limiter.try_acquire(Instant::now()).err();
+                    metrics::gauge!(
+                        "job_processor.remaining_pickup_tokens",
+                        limiter.remaining_tokens() as f64,
+                        "service_name" => Self::SERVICE_NAME
+                    );
+                }
+            }
+            if can_pick_job && prefetched_jobs.is_empty() && pickup_delay.is_none() {
+                // Jobs that wouldn't be processed by this run are not fetched.
+                let mut limit = job_pickup_batch_size;
+                if let Some(iterations_left) = iterations_left {
+                    limit = limit.min(iterations_left);
+                }
+                if let Some(max_jobs_to_process) = max_jobs_to_process {
+                    limit = limit.min((max_jobs_to_process - picked_jobs) as usize);
+                }
+
+                let fetch_started_at = Instant::now();
+                let (next_jobs, mut fetch_span) = if limit == 1 {
+                    // The span is created before the job is fetched, so that logs emitted while
+                    // the job is fetched are attributed to it; `job_id` is recorded once the job
+                    // is known.
+                    let job_span = new_job_span(Self::SERVICE_NAME, job_metrics.aggregation_round);
+                    let next_jobs = Self::get_next_jobs(&self, limit)
+                        .instrument(job_span.clone())
+                        .await;
+                    (next_jobs, Some(job_span))
+                } else {
+                    (Self::get_next_jobs(&self, limit).await, None)
+                };
+                if let Some(count) = Self::pending_jobs_count(&self).await {
+                    job_metrics.report_pending_jobs(Self::SERVICE_NAME, count);
+                }
+                match next_jobs {
+                    Ok(next_jobs) if !next_jobs.is_empty() => {
+                        update_status(self.status(), JobProcessorStatus::record_activity);
+                        job_metrics.report_time(
+                            job_metrics.fetch_time,
+                            Self::SERVICE_NAME,
+                            fetch_started_at.elapsed(),
+                        );
+                        backoff = polling_backoff.initial_interval;
+                        empty_polls = 0;
+                        for (job_id, job, metadata) in next_jobs {
+                            let job_span = fetch_span.take().unwrap_or_else(|| {
+                                new_job_span(Self::SERVICE_NAME, job_metrics.aggregation_round)
+                            });
+                            if let Some(attempts) = metadata.attempts {
+                                job_span.record("attempts", &attempts);
+                            }
+                            prefetched_jobs.push_back((job_id, job, metadata, job_span));
+                        }
+                    }
+                    Ok(_) if iterations_left.is_some() => {
+                        vlog::info!("No more jobs to process. Server can stop now.");
+                        is_picking_jobs = false;
+                    }
+                    Ok(_) => {
+                        empty_polls += 1;
+                        if drain_after_empty_polls.map_or(false, |max| empty_polls >= max) {
+                            vlog::info!(
+                                "{} job queue is drained after {} empty polls, shutting down \
+                                 once {} in-flight jobs are saved",
+                                Self::SERVICE_NAME,
+                                empty_polls,
+                                in_flight.len()
+                            );
+                            is_picking_jobs = false;
+                        } else {
+                            is_idle = true;
+                            is_queue_empty = true;
+                        }
+                    }
+                    Err(err) => {
+                        vlog::error!(
+                            "Failed to get next {} job, retrying after backoff: {:#}",
+                            Self::SERVICE_NAME,
+                            err
+                        );
+                        update_status(self.status(), |status| {
+                            status.record_error(format!("failed getting next job: {:#}", err));
+                        });
+                        metrics::counter!(
+                            "job_processor.get_next_job_errors",
+                            1,
+                            "service_name" => Self::SERVICE_NAME
+                        );
+                        is_idle = true;
+                    }
+                }
+            }
+            if can_pick_job {
+                if let Some((job_id, job, metadata, job_span)) = prefetched_jobs.pop_front() {
+                    let job_key = format!("{:?}", job_id);
+                    job_span.record("job_id", &job_key.as_str());
+                    if !in_flight_job_ids.insert(job_key.clone()) {
+                        vlog::error!(
+                            "{} job {} is already being processed, skipping it",
+                            Self::SERVICE_NAME,
+                            job_key
+                        );
+                        is_idle = prefetched_jobs.is_empty();
+                    } else if !Self::can_accept_job(&self, &job_id)
+                        .instrument(job_span.clone())
+                        .await
+                    {
+                        vlog::info!(
+                            "{} job {} is rejected by pre-flight check, releasing it",
+                            Self::SERVICE_NAME,
+                            job_key
+                        );
+                        in_flight_job_ids.remove(&job_key);
+                        Self::release_job(&self, job_id).instrument(job_span).await;
+                        metrics::counter!(
+                            "job_processor.rejected_jobs",
+                            1,
+                            "service_name" => Self::SERVICE_NAME
+                        );
+                        // Other jobs are likely to be rejected as well until resources are freed.
+                        is_idle = true;
+                    } else {
+                        let started_at = Instant::now();
+                        iterations_left = iterations_left.map(|i| i - 1);
+                        picked_jobs += 1;
+
+                        // In-flight jobs are always brought to completion, even if the stop
+                        // signal is received while they are being processed.
+                        let this = &self;
+                        let stop_receiver = &stop_receiver;
+                        in_flight.push(
+                            async move {
+                                let outcome = this
+                                    .process_with_retries(
+                                        job_id,
+                                        job,
+                                        metadata,
+                                        started_at,
+                                        stop_receiver,
+                                    )
+                                    .await;
+                                (job_key, outcome)
+                            }
+                            .instrument(job_span),
+                        );
+                        report_jobs_in_flight(Self::SERVICE_NAME, in_flight.len());
+                        continue;
+                    }
+                }
+            }
 
-                self.wait_for_task(job_id, started_at, task).await
-            } else if iterations_left.is_some() {
-                vlog::info!("No more jobs to process. Server can stop now.");
-                return;
+            let idle_wait: Option<BoxFuture<'_, ()>> = if let Some(pickup_delay) = pickup_delay {
+                // The delay is capped, so that the stop signal is checked regularly.
+                vlog::trace!("Job pickup is rate-limited for {:?}", pickup_delay);
+                Some(sleep(pickup_delay.min(polling_backoff.initial_interval)).boxed())
+            } else if is_idle {
+                vlog::trace!("Backing off for {:?}", backoff);
+                let interval = backoff;
+                backoff = polling_backoff.next_interval(backoff);
+                Some(if is_queue_empty {
+                    Self::wait_for_new_jobs(&self, interval)
+                } else {
+                    sleep(interval).boxed()
+                })
             } else {
-                vlog::trace!("Backing off for {} ms", backoff);
-                sleep(Duration::from_millis(backoff)).await;
-                backoff = (backoff * Self::BACKOFF_MULTIPLIER).min(Self::MAX_BACKOFF_MS);
+                None
+            };
+            let completed_job = if let Some(idle_wait) = idle_wait {
+                if in_flight.is_empty() {
+                    idle_wait.await;
+                    None
+                } else {
+                    tokio::select! {
+                        completed_job = in_flight.next() => completed_job,
+                        _ = idle_wait => None,
+                    }
+                }
+            } else {
+                match in_flight.next().await {
+                    Some(completed_job) => Some(completed_job),
+                    // No jobs are in flight, and no more jobs will be picked up.
+                    None => {
+                        if !prefetched_jobs.is_empty() {
+                            vlog::info!(
+                                "Releasing {} prefetched {} jobs left unprocessed",
+                                prefetched_jobs.len(),
+                                Self::SERVICE_NAME
+                            );
+                        }
+                        // Otherwise, prefetched jobs would be left in progress until they are
+                        // requeued as stuck.
+                        for (job_id, _, _, job_span) in prefetched_jobs.drain(..) {
+                            Self::release_job(&self, job_id).instrument(job_span).await;
+                        }
+                        vlog::info!(
+                            "{} component processed {} jobs in {:?}",
+                            Self::SERVICE_NAME,
+                            processed_jobs,
+                            run_started_at.elapsed()
+                        );
+                        return fatal_error.map_or(Ok(()), Err);
+                    }
+                }
+            };
+            if let Some((job_key, outcome)) = completed_job {
+                processed_jobs += 1;
+                in_flight_job_ids.remove(&job_key);
+                report_jobs_in_flight(Self::SERVICE_NAME, in_flight.len());
+                match outcome {
+                    Ok(Some(TaskFailure::Error(err))) if err.kind == JobErrorKind::Unavailable => {
+                        vlog::info!(
+                            "Pausing {} job pickup for {:?} until required services recover",
+                            Self::SERVICE_NAME,
+                            unavailable_backoff
+                        );
+                        paused_until = Some(Instant::now() + unavailable_backoff);
+                        unavailable_backoff = polling_backoff.next_interval(unavailable_backoff);
+                    }
+                    Ok(None) => {
+                        unavailable_backoff = polling_backoff.initial_interval;
+                    }
+                    Ok(Some(TaskFailure::Panic(_))) if is_picking_jobs && self.exit_on_panic() => {
+                        vlog::error!(
+                            "{} job {} panicked, shutting down {} component",
+                            Self::SERVICE_NAME,
+                            job_key,
+                            Self::SERVICE_NAME
+                        );
+                        is_picking_jobs = false;
+                    }
+                    Err(err) => {
+                        vlog::error!(
+                            "Failed to save outcome of {} job {}, shutting down {} component: {:#}",
+                            Self::SERVICE_NAME,
+                            job_key,
+                            Self::SERVICE_NAME,
+                            err
+                        );
+                        is_picking_jobs = false;
+                        fatal_error.get_or_insert(err);
+                    }
+                    Ok(_) => {}
+                }
             }
         }
-        vlog::info!("Requested number of jobs is processed. Server can stop now.")
     }
 
-    /// Polls task handle, saving its outcome.
-    async fn wait_for_task(
+    /// Processes the job, retrying failed attempts according to [`Self::retry_policy()`],
+    /// and saves its outcome. Failed attempts are not retried once the stop signal is received.
+    /// Returns the failure of the last attempt if the job has failed, or an error
+    /// if the job result cannot be saved.
+    async fn process_with_retries(
         &self,
         job_id: Self::JobId,
+        job: Self::Job,
+        metadata: JobMetadata,
         started_at: Instant,
-        task: JoinHandle<Self::JobArtifacts>,
-    ) {
+        stop_receiver: &watch::Receiver<bool>,
+    ) -> anyhow::Result<Option<TaskFailure>> {
+        let retry_policy = self.retry_policy();
+        let job_metrics = self.job_metrics();
+        let is_dry_run = self.is_dry_run();
+        let max_attempts = retry_policy.max_attempts.max(1);
+        let mut job = Some(job);
+        let mut attempt = 1;
         loop {
-            vlog::trace!(
-                "Polling {} task with id {:?}. Is finished: {}",
+            // The job is only cloned if it may be needed for a subsequent attempt.
+            let attempt_job = if attempt < max_attempts {
+                job.clone()
+            } else {
+                job.take()
+            };
+            let attempt_job = attempt_job.expect("job is consumed only by the last attempt");
+
+            vlog::debug!(
+                "Spawning thread processing {:?} job with id {:?} (attempt {}/{})",
                 Self::SERVICE_NAME,
                 job_id,
-                task.is_finished()
+                attempt,
+                max_attempts
             );
-            if task.is_finished() {
-                let result = task.await;
-                match result {
-                    Ok(data) => {
-                        vlog::debug!(
-                            "{} Job {:?} finished successfully",
+            job_metrics.report_attempt(Self::SERVICE_NAME, attempt);
+            let context = JobContext::new(
+                self.job_timeout()
+                    .map(|job_timeout| Instant::now() + job_timeout),
+                metadata,
+            );
+            let task = self
+                .process_job_with_context(attempt_job, started_at, context.clone())
+                .await;
+
+            match self
+                .wait_for_task(&job_id, task, &context, stop_receiver)
+                .await
+            {
+                Ok(data) => {
+                    vlog::debug!(
+                        "{} Job {:?} finished successfully",
+                        Self::SERVICE_NAME,
+                        job_id
+                    );
+                    if is_dry_run {
+                        vlog::info!(
+                            "DRY RUN: {} job {:?} processed in {:?}, artifacts size: {:?} bytes; discarding artifacts",
+                            Self::SERVICE_NAME,
+                            job_id,
+                            started_at.elapsed(),
+                            self.artifacts_size(&data)
+                        );
+                        return Ok(None);
+                    }
+                    if self.is_job_cancelled(&job_id).await {
+                        vlog::warn!(
+                            "{} job {:?} was cancelled while being processed, discarding its artifacts",
                             Self::SERVICE_NAME,
                             job_id
                         );
-                        self.save_result(job_id, started_at, data).await;
+                        metrics::counter!(
+                            "job_processor.cancelled_jobs",
+                            1,
+                            "service_name" => Self::SERVICE_NAME
+                        );
+                        self.save_cancelled(job_id, started_at).await;
+                        job_metrics.report_completed_job(
+                            Self::SERVICE_NAME,
+                            &metadata,
+                            "cancelled",
+                        );
+                        return Ok(None);
                     }
-                    Err(error) => {
-                        let error_message = try_extract_panic_message(error);
-                        vlog::error!(
-                            "Error occurred while processing {} job {:?}: {:?}",
+                    job_metrics.report_time(
+                        job_metrics.processing_time,
+                        Self::SERVICE_NAME,
+                        started_at.elapsed(),
+                    );
+                    let save_started_at = Instant::now();
+                    if let Err(err) = self.save_result(job_id, started_at, data).await {
+                        if matches!(
+                            err.downcast_ref::<JobError>(),
+                            Some(error) if error.kind == JobErrorKind::Unavailable
+                        ) {
+                            // The job will be processed from scratch once the service recovers.
+                            let job_error = JobError::from(err);
+                            vlog::warn!(
+                                "{} job {:?} result cannot be saved while a required service is \
+                                 unavailable, releasing the job: {}",
+                                Self::SERVICE_NAME,
+                                job_id,
+                                job_error
+                            );
+                            metrics::counter!(
+                                "job_processor.unavailable_jobs",
+                                1,
+                                "service_name" => Self::SERVICE_NAME
+                            );
+                            self.release_job(job_id).await;
+                            job_metrics.report_completed_job(
+                                Self::SERVICE_NAME,
+                                &metadata,
+                                "unavailable",
+                            );
+                            return Ok(Some(TaskFailure::Error(job_error)));
+                        }
+                        update_status(self.status(), |status| {
+                            status.record_error(format!("failed saving job result: {:#}", err));
+                        });
+                        return Err(err);
+                    }
+                    update_status(self.status(), JobProcessorStatus::record_saved_job);
+                    job_metrics.report_time(
+                        job_metrics.save_time,
+                        Self::SERVICE_NAME,
+                        save_started_at.elapsed(),
+                    );
+                    job_metrics.report_completed_job(Self::SERVICE_NAME, &metadata, "success");
+                    return Ok(None);
+                }
+                Err(TaskFailure::Aborted) => {
+                    vlog::warn!(
+                        "{} job {:?} was aborted on shutdown after {:?}, releasing it",
+                        Self::SERVICE_NAME,
+                        job_id,
+                        started_at.elapsed()
+                    );
+                    metrics::counter!(
+                        "job_processor.aborted_jobs",
+                        1,
+                        "service_name" => Self::SERVICE_NAME
+                    );
+                    if !is_dry_run {
+                        self.release_job(job_id).await;
+                    }
+                    job_metrics.report_completed_job(Self::SERVICE_NAME, &metadata, "aborted");
+                    return Ok(Some(TaskFailure::Aborted));
+                }
+                Err(failure) => {
+                    vlog::error!(
+                        "Error occurred while processing {} job {:?} (attempt {}/{}): {:?}",
+                        Self::SERVICE_NAME,
+                        job_id,
+                        attempt,
+                        max_attempts,
+                        failure
+                    );
+                    if let TaskFailure::Panic(_) = &failure {
+                        metrics::counter!(
+                            "job_processor.job_panics",
+                            1,
+                            "service_name" => Self::SERVICE_NAME
+                        );
+                    }
+                    let mut job_error = JobError::from(failure.clone());
+                    if job_error.kind == JobErrorKind::Unavailable {
+                        // Retrying in-process is pointless until the service recovers.
+                        vlog::warn!(
+                            "{} job {:?} cannot be processed while a required service is \
+                             unavailable, releasing it: {}",
                             Self::SERVICE_NAME,
                             job_id,
-                            error_message
+                            job_error
                         );
-                        self.save_failure(job_id, started_at, error_message).await;
+                        update_status(self.status(), |status| {
+                            status.record_error(job_error.to_string());
+                        });
+                        metrics::counter!(
+                            "job_processor.unavailable_jobs",
+                            1,
+                            "service_name" => Self::SERVICE_NAME
+                        );
+                        if !is_dry_run {
+                            self.release_job(job_id).await;
+                        }
+                        job_metrics.report_completed_job(
+                            Self::SERVICE_NAME,
+                            &metadata,
+                            "unavailable",
+                        );
+                        return Ok(Some(failure));
+                    }
+                    if self.is_fatal_failure(&job_error) {
+                        job_error.retryable = false;
+                    }
+                    if attempt >= max_attempts || !job_error.retryable || *stop_receiver.borrow() {
+                        update_status(self.status(), |status| {
+                            status.record_error(job_error.to_string());
+                        });
+                        if is_dry_run {
+                            vlog::info!(
+                                "DRY RUN: {} job {:?} failed in {:?}, not saving the failure",
+                                Self::SERVICE_NAME,
+                                job_id,
+                                started_at.elapsed()
+                            );
+                            return Ok(Some(failure));
+                        }
+                        job_metrics.report_failed_job(Self::SERVICE_NAME, job_error.retryable);
+                        let failure_context = JobFailureContext::new(metadata, attempt);
+                        self.save_failure_with_context(
+                            job_id,
+                            started_at,
+                            job_error,
+                            failure_context,
+                        )
+                        .await;
+                        update_status(self.status(), JobProcessorStatus::record_activity);
+                        job_metrics.report_completed_job(Self::SERVICE_NAME, &metadata, "failure");
+                        return Ok(Some(failure));
+                    }
+                    let backoff = retry_policy.backoff_after(attempt);
+                    vlog::warn!(
+                        "Retrying {} job {:?} in {:?}",
+                        Self::SERVICE_NAME,
+                        job_id,
+                        backoff
+                    );
+                    sleep(backoff).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Polls task handle, returning its outcome or the reason why the task has failed.
+    /// Aborts the task if it doesn't finish within [`Self::job_timeout()`]. Requests the task
+    /// to abort via `context` once the stop signal is received.
+    async fn wait_for_task(
+        &self,
+        job_id: &Self::JobId,
+        task: JoinHandle<anyhow::Result<Self::JobArtifacts>>,
+        context: &JobContext,
+        stop_receiver: &watch::Receiver<bool>,
+    ) -> Result<Self::JobArtifacts, TaskFailure> {
+        let job_timeout = self.job_timeout();
+        let heartbeat_interval = self.heartbeat_interval();
+        let attempt_started_at = Instant::now();
+        let mut last_heartbeat_at = attempt_started_at;
+        loop {
+            vlog::trace!(
+                "Polling {} task with id {:?}. Is finished: {}",
+                Self::SERVICE_NAME,
+                job_id,
+                task.is_finished()
+            );
+            if task.is_finished() {
+                return match task.await {
+                    Ok(Ok(artifacts)) => Ok(artifacts),
+                    Ok(Err(err)) if err.is::<JobAborted>() => {
+                        // The task may notice the deadline before the timeout is checked below.
+                        match job_timeout {
+                            Some(job_timeout) if attempt_started_at.elapsed() >= job_timeout => {
+                                Err(TaskFailure::Timeout(job_timeout))
+                            }
+                            _ => Err(TaskFailure::Aborted),
+                        }
                     }
+                    Ok(Err(err)) => Err(TaskFailure::Error(err.into())),
+                    Err(err) => Err(err.into()),
+                };
+            }
+            if *stop_receiver.borrow() {
+                context.request_abort();
+            }
+            if let Some(job_timeout) = job_timeout {
+                if attempt_started_at.elapsed() >= job_timeout {
+                    vlog::error!(
+                        "{} job {:?} timed out, aborting it",
+                        Self::SERVICE_NAME,
+                        job_id
+                    );
+                    metrics::counter!(
+                        "job_processor.job_timeouts",
+                        1,
+                        "service_name" => Self::SERVICE_NAME
+                    );
+                    // Blocking tasks cannot be aborted, but they may stop on their own.
+                    context.request_abort();
+                    task.abort();
+                    watch_aborted_task(Self::SERVICE_NAME, format!("{:?}", job_id), task);
+                    return Err(TaskFailure::Timeout(job_timeout));
+                }
+            }
+            if let Some(heartbeat_interval) = heartbeat_interval {
+                if last_heartbeat_at.elapsed() >= heartbeat_interval {
+                    vlog::trace!(
+                        "Sending heartbeat for {} job {:?}",
+                        Self::SERVICE_NAME,
+                        job_id
+                    );
+                    self.heartbeat(job_id).await;
+                    last_heartbeat_at = Instant::now();
                 }
-                break;
             }
             sleep(Duration::from_millis(Self::POLLING_INTERVAL_MS)).await;
         }
@@ -125,5 +1141,56 @@ pub trait JobProcessor: Sync + Send {
         job_id: Self::JobId,
         started_at: Instant,
         artifacts: Self::JobArtifacts,
+    ) -> anyhow::Result<()>;
+}
+
+/// Watches the aborted task in background. Tasks spawned with `spawn_blocking` cannot be aborted
+/// once started, so their threads stay occupied until the job finishes; this is reported in logs.
+fn watch_aborted_task<T: Send + 'static>(
+    service_name: &'static str,
+    job_id: String,
+    task: JoinHandle<T>,
+) {
+    tokio::spawn(async move {
+        let aborted_at = Instant::now();
+        // Give the abort a chance to take effect for async tasks.
+        sleep(Duration::from_millis(100)).await;
+        if task.is_finished() {
+            return;
+        }
+        vlog::warn!(
+            "{} job {} cannot be aborted; its blocking thread is orphaned until the job finishes",
+            service_name,
+            job_id
+        );
+        if task.await.is_ok() {
+            vlog::warn!(
+                "Orphaned {} job {} finished {:?} after it was aborted; its outcome is discarded",
+                service_name,
+                job_id,
+                aborted_at.elapsed()
+            );
+        }
+    });
+}
+
+fn new_job_span(
+    service_name: &'static str,
+    aggregation_round: Option<&'static str>,
+) -> tracing::Span {
+    tracing::info_span!(
+        "job",
+        service_name = service_name,
+        aggregation_round = aggregation_round,
+        job_id = tracing::field::Empty,
+        attempts = tracing::field::Empty
+    )
+}
+
+fn report_jobs_in_flight(service_name: &'static str, count: usize) {
+    metrics::gauge!(
+        "job_processor.jobs_in_flight",
+        count as f64,
+        "service_name" => service_name
     );
 }
@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+/// Token bucket limiting the rate of job pickups. The bucket holds up to a minute worth of tokens,
+/// so that an idle processor may pick up a burst of jobs right away.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn per_minute(max_tokens_per_minute: u32, now: Instant) -> Self {
+        let capacity = f64::from(max_tokens_per_minute.max(1));
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            last_refill_at: now,
+        }
+    }
+
+    /// Returns the number of whole tokens left in the bucket.
+    pub fn remaining_tokens(&self) -> u64 {
+        self.tokens as u64
+    }
+
+    /// Takes a token from the bucket. If the bucket is empty, returns the time until a token
+    /// becomes available.
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing_tokens = 1.0 - self.tokens;
+            Err(Duration::from_secs_f64(
+                missing_tokens / self.refill_per_sec,
+            ))
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill_at);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.refill_per_sec).min(self.capacity);
+        self.last_refill_at = now;
+    }
+}
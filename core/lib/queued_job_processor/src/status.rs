@@ -0,0 +1,62 @@
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, SystemTime};
+
+/// Status of a [`JobProcessor`](crate::JobProcessor) published for external observers,
+/// e.g. health checks.
+#[derive(Debug, Clone)]
+pub struct JobProcessorStatus {
+    /// Last time the processor has made progress, i.e. fetched a job, saved a job result or saved
+    /// a job failure. Polling an empty job queue or waiting for a job to complete doesn't count.
+    pub last_activity_at: SystemTime,
+    /// Last time an outcome of a job was successfully saved.
+    pub last_saved_job_at: Option<SystemTime>,
+    /// Last error that occurred while picking up, processing or saving a job.
+    pub last_error: Option<String>,
+}
+
+impl Default for JobProcessorStatus {
+    fn default() -> Self {
+        Self {
+            last_activity_at: SystemTime::now(),
+            last_saved_job_at: None,
+            last_error: None,
+        }
+    }
+}
+
+impl JobProcessorStatus {
+    /// Returns `true` if the processor has made no progress for longer than `max_inactivity`.
+    pub fn is_stale(&self, max_inactivity: Duration) -> bool {
+        self.last_activity_at
+            .elapsed()
+            .map_or(false, |inactivity| inactivity > max_inactivity)
+    }
+
+    pub(crate) fn record_activity(&mut self) {
+        self.last_activity_at = SystemTime::now();
+    }
+
+    pub(crate) fn record_saved_job(&mut self) {
+        let now = SystemTime::now();
+        self.last_activity_at = now;
+        self.last_saved_job_at = Some(now);
+    }
+
+    pub(crate) fn record_error(&mut self, error: String) {
+        self.last_error = Some(error);
+    }
+}
+
+/// [`JobProcessorStatus`] shared between a job processor and its observers.
+pub type SharedJobProcessorStatus = Arc<RwLock<JobProcessorStatus>>;
+
+pub(crate) fn update_status(
+    status: Option<&SharedJobProcessorStatus>,
+    update: impl FnOnce(&mut JobProcessorStatus),
+) {
+    if let Some(status) = status {
+        // Status updates cannot leave the status inconsistent, so it's fine to ignore poisoning.
+        let mut status = status.write().unwrap_or_else(PoisonError::into_inner);
+        update(&mut status);
+    }
+}
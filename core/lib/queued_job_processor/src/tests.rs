@@ -0,0 +1,1100 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use anyhow::Context as _;
+
+use super::rate_limiter::TokenBucket;
+use super::{
+    async_trait, JobContext, JobError, JobErrorKind, JobFailureContext, JobMetadata, JobProcessor,
+    PendingJobsCount, PollingBackoff, RetryPolicy, SharedJobProcessorStatus,
+};
+
+#[derive(Debug, Default)]
+struct MockState {
+    picked_jobs: AtomicU32,
+    job_fetches: AtomicU32,
+    attempts: AtomicU32,
+    running_jobs: AtomicU32,
+    max_running_jobs: AtomicU32,
+    heartbeats: AtomicU32,
+    is_cancelled: AtomicBool,
+    results: Mutex<Vec<(u32, u32)>>,
+    failures: Mutex<Vec<(u32, String)>>,
+    failure_kinds: Mutex<Vec<JobErrorKind>>,
+    retryable_failures: Mutex<Vec<bool>>,
+    failure_contexts: Mutex<Vec<JobFailureContext>>,
+    cancelled_jobs: Mutex<Vec<u32>>,
+    acceptance_checks: AtomicU32,
+    released_jobs: Mutex<Vec<u32>>,
+    pending_job_counts: AtomicU32,
+    processed_attempts: Mutex<Vec<Option<u32>>>,
+    new_job_waits: Mutex<Vec<Duration>>,
+}
+
+#[derive(Debug)]
+struct MockProcessor {
+    /// If set, all picked jobs have this id; otherwise, jobs are numbered sequentially from 1.
+    fixed_job_id: Option<u32>,
+    /// Number of attempts that fail before an attempt succeeds.
+    failing_attempts: u32,
+    /// If set, failing attempts return an error instead of panicking.
+    return_errors: bool,
+    /// If set, errors returned by failing attempts are classified with this kind.
+    error_kind: Option<JobErrorKind>,
+    panic_message: &'static str,
+    /// Number of job pickups that return an error before a job is returned.
+    failing_pickups: u32,
+    /// If set, the job queue becomes empty after this number of jobs is picked up.
+    queued_jobs: Option<u32>,
+    /// Number of attempts reported by the job queue for picked jobs.
+    pickup_attempts: Option<u32>,
+    drain_after_empty_polls: Option<u32>,
+    fail_saving_results: bool,
+    /// If set, jobs are cancelled while being processed.
+    cancel_during_processing: bool,
+    /// If non-zero, jobs are processed in this number of phases checking the job context
+    /// after each phase; each phase takes `processing_time`.
+    phases: u32,
+    /// Number of pre-flight checks that reject the job before a job is accepted.
+    rejected_jobs: u32,
+    dry_run: bool,
+    max_jobs_to_process: Option<u64>,
+    max_pickups_per_minute: Option<u32>,
+    job_pickup_batch_size: usize,
+    status: Option<SharedJobProcessorStatus>,
+    processing_time: Duration,
+    job_timeout: Option<Duration>,
+    heartbeat_interval: Option<Duration>,
+    exit_on_panic: bool,
+    /// If set, waiting for new jobs returns right away, as if the processor was notified.
+    notify_new_jobs: bool,
+    retry_policy: RetryPolicy,
+    state: Arc<MockState>,
+}
+
+impl MockProcessor {
+    fn new(failing_attempts: u32, max_attempts: u32) -> Self {
+        Self {
+            fixed_job_id: None,
+            failing_attempts,
+            return_errors: false,
+            error_kind: None,
+            panic_message: "transient failure",
+            failing_pickups: 0,
+            queued_jobs: None,
+            pickup_attempts: None,
+            drain_after_empty_polls: None,
+            fail_saving_results: false,
+            cancel_during_processing: false,
+            phases: 0,
+            rejected_jobs: 0,
+            dry_run: false,
+            max_jobs_to_process: None,
+            max_pickups_per_minute: None,
+            job_pickup_batch_size: 1,
+            status: None,
+            processing_time: Duration::ZERO,
+            job_timeout: None,
+            heartbeat_interval: None,
+            exit_on_panic: false,
+            notify_new_jobs: false,
+            retry_policy: RetryPolicy {
+                max_attempts,
+                base_backoff: Duration::from_millis(1),
+            },
+            state: Arc::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl JobProcessor for MockProcessor {
+    type Job = u32;
+    type JobId = u32;
+    type JobArtifacts = u32;
+
+    const POLLING_INTERVAL_MS: u64 = 10;
+    const SERVICE_NAME: &'static str = "mock_processor";
+
+    async fn get_next_job(&self) -> anyhow::Result<Option<(Self::JobId, Self::Job)>> {
+        let job_number = self.state.picked_jobs.fetch_add(1, Ordering::SeqCst) + 1;
+        if job_number <= self.failing_pickups {
+            anyhow::bail!("connection refused");
+        }
+        if let Some(queued_jobs) = self.queued_jobs {
+            if job_number > self.failing_pickups + queued_jobs {
+                return Ok(None);
+            }
+        }
+        Ok(Some((self.fixed_job_id.unwrap_or(job_number), 42)))
+    }
+
+    async fn get_next_job_with_metadata(
+        &self,
+    ) -> anyhow::Result<Option<(Self::JobId, Self::Job, JobMetadata)>> {
+        let next_job = self.get_next_job().await?;
+        let metadata = JobMetadata::new(self.pickup_attempts);
+        Ok(next_job.map(|(job_id, job)| (job_id, job, metadata)))
+    }
+
+    async fn get_next_jobs(
+        &self,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(Self::JobId, Self::Job, JobMetadata)>> {
+        self.state.job_fetches.fetch_add(1, Ordering::SeqCst);
+        let mut jobs = Vec::with_capacity(limit);
+        for _ in 0..limit {
+            jobs.extend(self.get_next_job_with_metadata().await?);
+        }
+        Ok(jobs)
+    }
+
+    async fn save_failure(&self, job_id: Self::JobId, _started_at: Instant, error: JobError) {
+        self.state.failure_kinds.lock().unwrap().push(error.kind);
+        self.state
+            .retryable_failures
+            .lock()
+            .unwrap()
+            .push(error.retryable);
+        self.state
+            .failures
+            .lock()
+            .unwrap()
+            .push((job_id, error.message));
+    }
+
+    async fn save_failure_with_context(
+        &self,
+        job_id: Self::JobId,
+        started_at: Instant,
+        error: JobError,
+        context: JobFailureContext,
+    ) {
+        self.state.failure_contexts.lock().unwrap().push(context);
+        self.save_failure(job_id, started_at, error).await;
+    }
+
+    async fn process_job(
+        &self,
+        job: Self::Job,
+        _started_at: Instant,
+    ) -> JoinHandle<anyhow::Result<Self::JobArtifacts>> {
+        let attempt = self.state.attempts.fetch_add(1, Ordering::SeqCst) + 1;
+        let failing_attempts = self.failing_attempts;
+        let return_errors = self.return_errors;
+        let error_kind = self.error_kind;
+        let panic_message = self.panic_message;
+        let processing_time = self.processing_time;
+        let cancel_during_processing = self.cancel_during_processing;
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            let running_jobs = state.running_jobs.fetch_add(1, Ordering::SeqCst) + 1;
+            state
+                .max_running_jobs
+                .fetch_max(running_jobs, Ordering::SeqCst);
+            if cancel_during_processing {
+                state.is_cancelled.store(true, Ordering::SeqCst);
+            }
+            tokio::time::sleep(processing_time).await;
+            state.running_jobs.fetch_sub(1, Ordering::SeqCst);
+            if attempt <= failing_attempts {
+                if let Some(kind) = error_kind {
+                    return Err(JobError::new(kind, panic_message)).context("failed loading input");
+                }
+                if return_errors {
+                    anyhow::bail!("{}", panic_message);
+                }
+                panic!("{}", panic_message);
+            }
+            Ok(job * 2)
+        })
+    }
+
+    async fn process_job_with_context(
+        &self,
+        job: Self::Job,
+        started_at: Instant,
+        context: JobContext,
+    ) -> JoinHandle<anyhow::Result<Self::JobArtifacts>> {
+        self.state
+            .processed_attempts
+            .lock()
+            .unwrap()
+            .push(context.metadata().attempts);
+        if self.phases == 0 {
+            return self.process_job(job, started_at).await;
+        }
+        self.state.attempts.fetch_add(1, Ordering::SeqCst);
+        let phases = self.phases;
+        let processing_time = self.processing_time;
+        tokio::spawn(async move {
+            for _ in 0..phases {
+                tokio::time::sleep(processing_time).await;
+                context.check_abort("phase")?;
+            }
+            Ok(job * 2)
+        })
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    fn job_timeout(&self) -> Option<Duration> {
+        self.job_timeout
+    }
+
+    fn heartbeat_interval(&self) -> Option<Duration> {
+        self.heartbeat_interval
+    }
+
+    fn exit_on_panic(&self) -> bool {
+        self.exit_on_panic
+    }
+
+    async fn heartbeat(&self, _job_id: &Self::JobId) {
+        self.state.heartbeats.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn max_jobs_to_process(&self) -> Option<u64> {
+        self.max_jobs_to_process
+    }
+
+    fn max_pickups_per_minute(&self) -> Option<u32> {
+        self.max_pickups_per_minute
+    }
+
+    fn job_pickup_batch_size(&self) -> usize {
+        self.job_pickup_batch_size
+    }
+
+    fn drain_after_empty_polls(&self) -> Option<u32> {
+        self.drain_after_empty_polls
+    }
+
+    fn status(&self) -> Option<&SharedJobProcessorStatus> {
+        self.status.as_ref()
+    }
+
+    async fn can_accept_job(&self, _job_id: &Self::JobId) -> bool {
+        let check_number = self.state.acceptance_checks.fetch_add(1, Ordering::SeqCst) + 1;
+        check_number > self.rejected_jobs
+    }
+
+    async fn release_job(&self, job_id: Self::JobId) {
+        self.state.released_jobs.lock().unwrap().push(job_id);
+    }
+
+    async fn wait_for_new_jobs(&self, timeout: Duration) {
+        self.state.new_job_waits.lock().unwrap().push(timeout);
+        if !self.notify_new_jobs {
+            tokio::time::sleep(timeout).await;
+        }
+    }
+
+    async fn pending_jobs_count(&self) -> Option<PendingJobsCount> {
+        self.state.pending_job_counts.fetch_add(1, Ordering::SeqCst);
+        Some(PendingJobsCount::default())
+    }
+
+    async fn is_job_cancelled(&self, _job_id: &Self::JobId) -> bool {
+        self.state.is_cancelled.load(Ordering::SeqCst)
+    }
+
+    async fn save_cancelled(&self, job_id: Self::JobId, _started_at: Instant) {
+        self.state.cancelled_jobs.lock().unwrap().push(job_id);
+    }
+
+    async fn save_result(
+        &self,
+        job_id: Self::JobId,
+        _started_at: Instant,
+        artifacts: Self::JobArtifacts,
+    ) -> anyhow::Result<()> {
+        if self.fail_saving_results {
+            anyhow::bail!("failed uploading artifacts");
+        }
+        self.state.results.lock().unwrap().push((job_id, artifacts));
+        Ok(())
+    }
+}
+
+async fn run_single_job(processor: MockProcessor) -> Arc<MockState> {
+    run_jobs(processor, 1).await
+}
+
+async fn run_jobs(processor: MockProcessor, job_count: usize) -> Arc<MockState> {
+    let state = processor.state.clone();
+    let (_stop_sender, stop_receiver) = watch::channel(false);
+    processor.run(stop_receiver, Some(job_count)).await.unwrap();
+    state
+}
+
+#[test]
+fn retry_backoff_is_exponential() {
+    let policy = RetryPolicy {
+        max_attempts: 5,
+        base_backoff: Duration::from_millis(100),
+    };
+    assert_eq!(policy.backoff_after(1), Duration::from_millis(100));
+    assert_eq!(policy.backoff_after(2), Duration::from_millis(200));
+    assert_eq!(policy.backoff_after(4), Duration::from_millis(800));
+    assert_eq!(RetryPolicy::default(), RetryPolicy::no_retries());
+}
+
+#[test]
+fn polling_interval_grows_until_capped() {
+    let polling_backoff = PollingBackoff {
+        initial_interval: Duration::from_secs(1),
+        max_interval: Duration::from_secs(30),
+        multiplier: 2,
+    };
+    let intervals: Vec<_> =
+        std::iter::successors(Some(polling_backoff.initial_interval), |&interval| {
+            Some(polling_backoff.next_interval(interval))
+        })
+        .take(7)
+        .map(|interval| interval.as_secs())
+        .collect();
+    assert_eq!(intervals, [1, 2, 4, 8, 16, 30, 30]);
+}
+
+#[test]
+fn default_polling_backoff_is_defined_by_constants() {
+    let polling_backoff = MockProcessor::new(0, 1).polling_backoff();
+    assert_eq!(
+        polling_backoff,
+        PollingBackoff {
+            initial_interval: Duration::from_millis(10),
+            max_interval: Duration::from_secs(60),
+            multiplier: 2,
+        }
+    );
+}
+
+#[tokio::test]
+async fn job_succeeds_after_transient_failures() {
+    let state = run_single_job(MockProcessor::new(2, 3)).await;
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 3);
+    assert_eq!(*state.results.lock().unwrap(), [(1, 84)]);
+    assert!(state.failures.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn job_metadata_is_passed_to_every_attempt() {
+    let processor = MockProcessor {
+        pickup_attempts: Some(3),
+        ..MockProcessor::new(1, 2)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(*state.results.lock().unwrap(), [(1, 84)]);
+    assert_eq!(
+        *state.processed_attempts.lock().unwrap(),
+        [Some(3), Some(3)]
+    );
+
+    let state = run_single_job(MockProcessor::new(0, 1)).await;
+    assert_eq!(*state.processed_attempts.lock().unwrap(), [None]);
+}
+
+#[tokio::test]
+async fn failure_is_saved_after_exhausting_attempts() {
+    let state = run_single_job(MockProcessor::new(u32::MAX, 3)).await;
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 3);
+    assert!(state.results.lock().unwrap().is_empty());
+    let failures = state.failures.lock().unwrap();
+    assert_eq!(failures.len(), 1);
+    assert!(
+        failures[0].1.contains("transient failure"),
+        "{:?}",
+        failures
+    );
+}
+
+#[tokio::test]
+async fn failure_is_saved_with_its_context() {
+    let processor = MockProcessor {
+        pickup_attempts: Some(2),
+        ..MockProcessor::new(u32::MAX, 3)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(state.failures.lock().unwrap().len(), 1);
+    let contexts = state.failure_contexts.lock().unwrap();
+    assert_eq!(contexts.len(), 1);
+    assert_eq!(contexts[0].metadata.attempts, Some(2));
+    assert_eq!(contexts[0].attempts, 3);
+    assert_eq!(contexts[0].hostname, super::context::current_hostname());
+}
+
+#[tokio::test]
+async fn fatal_failure_is_not_retried() {
+    let processor = MockProcessor {
+        error_kind: Some(JobErrorKind::Serialization),
+        panic_message: "invalid bincode payload",
+        ..MockProcessor::new(u32::MAX, 3)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 1);
+    assert!(state.results.lock().unwrap().is_empty());
+    assert_eq!(state.failures.lock().unwrap().len(), 1);
+    assert_eq!(
+        *state.failure_kinds.lock().unwrap(),
+        [JobErrorKind::Serialization]
+    );
+    assert_eq!(*state.retryable_failures.lock().unwrap(), [false]);
+}
+
+#[tokio::test]
+async fn failures_are_classified_by_kind_rather_than_message() {
+    let processor = MockProcessor {
+        panic_message: "deserialization of job input failed",
+        ..MockProcessor::new(u32::MAX, 3)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 3);
+    assert_eq!(*state.failure_kinds.lock().unwrap(), [JobErrorKind::Panic]);
+    assert_eq!(*state.retryable_failures.lock().unwrap(), [true]);
+}
+
+#[tokio::test]
+async fn failures_are_not_retried_by_default() {
+    let processor = MockProcessor {
+        retry_policy: RetryPolicy::default(),
+        ..MockProcessor::new(1, 1)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 1);
+    assert!(state.results.lock().unwrap().is_empty());
+    assert_eq!(state.failures.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn in_flight_job_is_saved_on_stop_signal() {
+    let processor = MockProcessor {
+        processing_time: Duration::from_millis(100),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (stop_sender, stop_receiver) = watch::channel(false);
+    let run_handle = tokio::spawn(processor.run(stop_receiver, None));
+
+    while state.attempts.load(Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    stop_sender.send(true).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), run_handle)
+        .await
+        .expect("processor didn't stop after receiving stop signal")
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(state.picked_jobs.load(Ordering::SeqCst), 1);
+    assert_eq!(*state.results.lock().unwrap(), [(1, 84)]);
+    assert!(state.failures.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn job_aborted_between_phases_on_stop_signal_is_released() {
+    let processor = MockProcessor {
+        processing_time: Duration::from_millis(20),
+        phases: 100,
+        ..MockProcessor::new(0, 3)
+    };
+    let state = processor.state.clone();
+    let (stop_sender, stop_receiver) = watch::channel(false);
+    let run_handle = tokio::spawn(processor.run(stop_receiver, None));
+
+    while state.attempts.load(Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    stop_sender.send(true).unwrap();
+    // The job would take 2 seconds to complete if it wasn't aborted.
+    tokio::time::timeout(Duration::from_secs(1), run_handle)
+        .await
+        .expect("job was not aborted after receiving stop signal")
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 1);
+    assert_eq!(*state.released_jobs.lock().unwrap(), [1]);
+    assert!(state.results.lock().unwrap().is_empty());
+    assert!(state.failures.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn job_aborted_after_deadline_is_timed_out() {
+    let processor = MockProcessor {
+        processing_time: Duration::from_millis(20),
+        phases: 100,
+        job_timeout: Some(Duration::from_millis(50)),
+        ..MockProcessor::new(0, 3)
+    };
+    let state = tokio::time::timeout(Duration::from_secs(1), run_single_job(processor))
+        .await
+        .expect("job was not aborted after its deadline");
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 1);
+    assert!(state.released_jobs.lock().unwrap().is_empty());
+    assert_eq!(
+        *state.failure_kinds.lock().unwrap(),
+        [JobErrorKind::Timeout]
+    );
+}
+
+#[test]
+fn job_context_is_aborted_after_deadline() {
+    let context = JobContext::new(
+        Some(Instant::now() + Duration::from_secs(3_600)),
+        JobMetadata::default(),
+    );
+    assert!(!context.should_abort());
+    context.check_abort("test").unwrap();
+
+    let context = JobContext::new(Some(Instant::now()), JobMetadata::default());
+    assert!(context.should_abort());
+    assert_eq!(context.check_abort("test").unwrap_err().phase, "test");
+    assert!(!JobContext::default().should_abort());
+}
+
+#[tokio::test]
+async fn timed_out_jobs_are_aborted_and_not_retried() {
+    let processor = MockProcessor {
+        processing_time: Duration::from_secs(3_600),
+        job_timeout: Some(Duration::from_millis(50)),
+        ..MockProcessor::new(0, 3)
+    };
+    let state = tokio::time::timeout(Duration::from_secs(5), run_jobs(processor, 2))
+        .await
+        .expect("timed out jobs were not aborted");
+
+    // Each job is attempted once, and the processor moves on to the next job after a timeout.
+    assert_eq!(state.picked_jobs.load(Ordering::SeqCst), 2);
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 2);
+    assert!(state.results.lock().unwrap().is_empty());
+    let failures = state.failures.lock().unwrap();
+    assert_eq!(failures.len(), 2);
+    assert!(failures
+        .iter()
+        .all(|(_, error)| error.starts_with("timeout")));
+}
+
+#[tokio::test]
+async fn jobs_are_processed_concurrently() {
+    let processor = MockProcessor {
+        processing_time: Duration::from_millis(50),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (_stop_sender, stop_receiver) = watch::channel(false);
+    processor
+        .run_concurrent(stop_receiver, Some(6), 3)
+        .await
+        .unwrap();
+
+    assert_eq!(state.picked_jobs.load(Ordering::SeqCst), 6);
+    assert_eq!(state.max_running_jobs.load(Ordering::SeqCst), 3);
+    let mut results = state.results.lock().unwrap().clone();
+    results.sort_unstable();
+    assert_eq!(
+        results,
+        [(1, 84), (2, 84), (3, 84), (4, 84), (5, 84), (6, 84)]
+    );
+}
+
+#[tokio::test]
+async fn processor_exits_after_draining_queue() {
+    let processor = MockProcessor {
+        processing_time: Duration::from_millis(50),
+        queued_jobs: Some(3),
+        drain_after_empty_polls: Some(3),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (_stop_sender, stop_receiver) = watch::channel(false);
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        processor.run_concurrent(stop_receiver, None, 2),
+    )
+    .await
+    .expect("processor didn't exit after draining the queue")
+    .unwrap();
+
+    // Jobs that were in flight when the queue was drained are saved as well.
+    let mut results = state.results.lock().unwrap().clone();
+    results.sort_unstable();
+    assert_eq!(results, [(1, 84), (2, 84), (3, 84)]);
+    assert_eq!(state.job_fetches.load(Ordering::SeqCst), 6);
+}
+
+#[tokio::test]
+async fn processor_waits_for_new_jobs_while_queue_is_empty() {
+    let processor = MockProcessor {
+        queued_jobs: Some(1),
+        drain_after_empty_polls: Some(3),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (_stop_sender, stop_receiver) = watch::channel(false);
+    processor.run(stop_receiver, None).await.unwrap();
+
+    assert_eq!(*state.results.lock().unwrap(), [(1, 84)]);
+    let waits = state.new_job_waits.lock().unwrap().clone();
+    assert_eq!(
+        waits,
+        [Duration::from_millis(10), Duration::from_millis(20)]
+    );
+}
+
+#[tokio::test]
+async fn notified_processor_polls_queue_right_away() {
+    let processor = MockProcessor {
+        queued_jobs: Some(0),
+        drain_after_empty_polls: Some(5),
+        notify_new_jobs: true,
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (_stop_sender, stop_receiver) = watch::channel(false);
+    let started_at = Instant::now();
+    processor.run(stop_receiver, None).await.unwrap();
+
+    // Without notifications, polling would take 10 + 20 + 40 + 80 = 150ms.
+    assert!(started_at.elapsed() < Duration::from_millis(150));
+    assert_eq!(state.job_fetches.load(Ordering::SeqCst), 5);
+}
+
+#[tokio::test]
+async fn in_flight_job_is_not_processed_twice() {
+    let processor = MockProcessor {
+        fixed_job_id: Some(1),
+        processing_time: Duration::from_millis(300),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (stop_sender, stop_receiver) = watch::channel(false);
+    let run_handle = tokio::spawn(processor.run_concurrent(stop_receiver, None, 2));
+
+    while state.picked_jobs.load(Ordering::SeqCst) < 2 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    stop_sender.send(true).unwrap();
+    run_handle.await.unwrap().unwrap();
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 1);
+    assert_eq!(*state.results.lock().unwrap(), [(1, 84)]);
+}
+
+#[tokio::test]
+async fn heartbeats_are_sent_while_job_is_processed() {
+    let processor = MockProcessor {
+        processing_time: Duration::from_millis(200),
+        heartbeat_interval: Some(Duration::from_millis(30)),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = run_single_job(processor).await;
+
+    assert!(state.heartbeats.load(Ordering::SeqCst) >= 2);
+    assert_eq!(*state.results.lock().unwrap(), [(1, 84)]);
+}
+
+#[tokio::test]
+async fn heartbeats_are_disabled_by_default() {
+    let processor = MockProcessor {
+        processing_time: Duration::from_millis(100),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(state.heartbeats.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn panicked_job_is_saved_as_failed_and_next_job_is_processed() {
+    let state = run_jobs(MockProcessor::new(1, 1), 2).await;
+
+    let failures = state.failures.lock().unwrap();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0], (1, "transient failure".to_owned()));
+    assert_eq!(*state.results.lock().unwrap(), [(2, 84)]);
+}
+
+#[tokio::test]
+async fn processor_stops_after_panic_if_configured() {
+    let processor = MockProcessor {
+        exit_on_panic: true,
+        ..MockProcessor::new(1, 1)
+    };
+    let state = processor.state.clone();
+    let (_stop_sender, stop_receiver) = watch::channel(false);
+    tokio::time::timeout(Duration::from_secs(5), processor.run(stop_receiver, None))
+        .await
+        .expect("processor didn't stop after job panic")
+        .unwrap();
+
+    assert_eq!(state.picked_jobs.load(Ordering::SeqCst), 1);
+    assert_eq!(state.failures.lock().unwrap().len(), 1);
+    assert!(state.results.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn job_errors_are_saved_as_failures() {
+    let processor = MockProcessor {
+        return_errors: true,
+        ..MockProcessor::new(u32::MAX, 2)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        *state.failures.lock().unwrap(),
+        [(1, "transient failure".to_owned())]
+    );
+    assert_eq!(*state.failure_kinds.lock().unwrap(), [JobErrorKind::Other]);
+    // The job has exhausted its in-process attempts, but it may still be requeued.
+    assert_eq!(*state.retryable_failures.lock().unwrap(), [true]);
+}
+
+#[tokio::test]
+async fn classified_job_errors_are_saved_with_their_kind() {
+    let processor = MockProcessor {
+        error_kind: Some(JobErrorKind::MissingInput),
+        ..MockProcessor::new(u32::MAX, 2)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        *state.failures.lock().unwrap(),
+        [(1, "failed loading input: transient failure".to_owned())]
+    );
+    assert_eq!(
+        *state.failure_kinds.lock().unwrap(),
+        [JobErrorKind::MissingInput]
+    );
+}
+
+#[tokio::test]
+async fn non_retryable_job_errors_are_saved_right_away() {
+    let processor = MockProcessor {
+        error_kind: Some(JobErrorKind::InvalidInput),
+        ..MockProcessor::new(u32::MAX, 3)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        *state.failure_kinds.lock().unwrap(),
+        [JobErrorKind::InvalidInput]
+    );
+    assert_eq!(*state.retryable_failures.lock().unwrap(), [false]);
+}
+
+#[tokio::test]
+async fn jobs_failed_because_of_unavailable_services_are_released() {
+    let processor = MockProcessor {
+        error_kind: Some(JobErrorKind::Unavailable),
+        ..MockProcessor::new(1, 3)
+    };
+    let started_at = Instant::now();
+    let state = run_jobs(processor, 2).await;
+
+    // The job is not retried in-process, and its failure is not saved.
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(*state.released_jobs.lock().unwrap(), [1]);
+    assert!(state.failures.lock().unwrap().is_empty());
+    assert_eq!(*state.results.lock().unwrap(), [(2, 84)]);
+    // Job pickup is paused after the failure.
+    assert!(started_at.elapsed() >= Duration::from_millis(10));
+}
+
+#[test]
+fn job_error_is_serialized_to_json() {
+    let error = JobError::new(JobErrorKind::MissingInput, "blob is missing")
+        .with_blob_url("scheduler_witness_jobs_fri/1.bin");
+    assert_eq!(
+        error.to_json(),
+        r#"{"kind":"missing_input","message":"blob is missing","retryable":true,"blob_url":"scheduler_witness_jobs_fri/1.bin"}"#
+    );
+}
+
+#[tokio::test]
+async fn job_pickup_is_retried_after_error() {
+    let processor = MockProcessor {
+        failing_pickups: 2,
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (stop_sender, stop_receiver) = watch::channel(false);
+    let run_handle = tokio::spawn(processor.run(stop_receiver, None));
+
+    while state.results.lock().unwrap().is_empty() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    stop_sender.send(true).unwrap();
+    run_handle.await.unwrap().unwrap();
+
+    assert_eq!(state.results.lock().unwrap()[0], (3, 84));
+}
+
+#[tokio::test]
+async fn error_saving_result_is_fatal() {
+    let processor = MockProcessor {
+        fail_saving_results: true,
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (_stop_sender, stop_receiver) = watch::channel(false);
+    let err = tokio::time::timeout(Duration::from_secs(5), processor.run(stop_receiver, None))
+        .await
+        .expect("processor didn't stop after fatal error")
+        .unwrap_err();
+
+    assert!(
+        err.to_string().contains("failed uploading artifacts"),
+        "{}",
+        err
+    );
+    assert_eq!(state.picked_jobs.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn artifacts_of_job_cancelled_during_processing_are_discarded() {
+    let processor = MockProcessor {
+        cancel_during_processing: true,
+        ..MockProcessor::new(0, 1)
+    };
+    let state = run_single_job(processor).await;
+
+    assert!(state.results.lock().unwrap().is_empty());
+    assert!(state.failures.lock().unwrap().is_empty());
+    assert_eq!(*state.cancelled_jobs.lock().unwrap(), [1]);
+}
+
+#[tokio::test]
+async fn outcomes_are_not_saved_in_dry_run() {
+    let processor = MockProcessor {
+        dry_run: true,
+        ..MockProcessor::new(1, 1)
+    };
+    let state = run_jobs(processor, 2).await;
+
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 2);
+    assert!(state.results.lock().unwrap().is_empty());
+    assert!(state.failures.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn processor_stops_after_max_jobs_to_process() {
+    let processor = MockProcessor {
+        max_jobs_to_process: Some(3),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (_stop_sender, stop_receiver) = watch::channel(false);
+    tokio::time::timeout(Duration::from_secs(5), processor.run(stop_receiver, None))
+        .await
+        .expect("processor didn't stop after processing max number of jobs")
+        .unwrap();
+
+    assert_eq!(state.picked_jobs.load(Ordering::SeqCst), 3);
+    assert_eq!(*state.results.lock().unwrap(), [(1, 84), (2, 84), (3, 84)]);
+}
+
+#[test]
+fn token_bucket_is_refilled_over_time() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::per_minute(2, start);
+    assert_eq!(bucket.remaining_tokens(), 2);
+    bucket.try_acquire(start).unwrap();
+    bucket.try_acquire(start).unwrap();
+    assert_eq!(bucket.remaining_tokens(), 0);
+    assert_eq!(bucket.try_acquire(start), Err(Duration::from_secs(30)));
+
+    let later = start + Duration::from_secs(45);
+    bucket.try_acquire(later).unwrap();
+    assert_eq!(bucket.try_acquire(later), Err(Duration::from_secs(15)));
+    // Tokens are not accumulated beyond the bucket capacity.
+    let much_later = start + Duration::from_secs(3_600);
+    bucket.try_acquire(much_later).unwrap();
+    assert_eq!(bucket.remaining_tokens(), 1);
+}
+
+#[tokio::test]
+async fn job_pickups_are_rate_limited() {
+    let processor = MockProcessor {
+        max_pickups_per_minute: Some(3),
+        processing_time: Duration::from_millis(10),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (stop_sender, stop_receiver) = watch::channel(false);
+    let run_handle = tokio::spawn(processor.run(stop_receiver, None));
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    stop_sender.send(true).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), run_handle)
+        .await
+        .expect("rate-limited processor didn't stop")
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(state.picked_jobs.load(Ordering::SeqCst), 3);
+    assert_eq!(*state.results.lock().unwrap(), [(1, 84), (2, 84), (3, 84)]);
+}
+
+#[tokio::test]
+async fn jobs_are_fetched_in_batches() {
+    let processor = MockProcessor {
+        job_pickup_batch_size: 4,
+        ..MockProcessor::new(0, 1)
+    };
+    let state = run_jobs(processor, 10).await;
+
+    // The last batch is limited by the number of jobs left to process.
+    assert_eq!(state.job_fetches.load(Ordering::SeqCst), 3);
+    // Pending jobs are counted once per poll.
+    assert_eq!(state.pending_job_counts.load(Ordering::SeqCst), 3);
+    assert_eq!(state.picked_jobs.load(Ordering::SeqCst), 10);
+    let job_ids: Vec<_> = state
+        .results
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|&(id, _)| id)
+        .collect();
+    assert_eq!(job_ids, (1..=10).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn batched_pickup_reduces_queue_roundtrips() {
+    const SEEDED_JOBS: usize = 10_000;
+
+    let state = run_jobs(MockProcessor::new(0, 1), SEEDED_JOBS).await;
+    assert_eq!(state.job_fetches.load(Ordering::SeqCst), 10_000);
+
+    let processor = MockProcessor {
+        job_pickup_batch_size: 100,
+        ..MockProcessor::new(0, 1)
+    };
+    let state = run_jobs(processor, SEEDED_JOBS).await;
+    assert_eq!(state.job_fetches.load(Ordering::SeqCst), 100);
+    assert_eq!(state.results.lock().unwrap().len(), SEEDED_JOBS);
+}
+
+#[tokio::test]
+async fn prefetched_jobs_are_released_on_stop_signal() {
+    let processor = MockProcessor {
+        job_pickup_batch_size: 3,
+        processing_time: Duration::from_millis(100),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (stop_sender, stop_receiver) = watch::channel(false);
+    let run_handle = tokio::spawn(processor.run(stop_receiver, None));
+
+    while state.attempts.load(Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    stop_sender.send(true).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), run_handle)
+        .await
+        .expect("processor didn't stop after receiving stop signal")
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(state.job_fetches.load(Ordering::SeqCst), 1);
+    assert_eq!(*state.results.lock().unwrap(), [(1, 84)]);
+    assert_eq!(*state.released_jobs.lock().unwrap(), [2, 3]);
+}
+
+#[tokio::test]
+async fn batched_jobs_are_processed_concurrently() {
+    let processor = MockProcessor {
+        job_pickup_batch_size: 3,
+        processing_time: Duration::from_millis(50),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (_stop_sender, stop_receiver) = watch::channel(false);
+    processor
+        .run_concurrent(stop_receiver, Some(3), 3)
+        .await
+        .unwrap();
+
+    assert_eq!(state.job_fetches.load(Ordering::SeqCst), 1);
+    assert_eq!(state.max_running_jobs.load(Ordering::SeqCst), 3);
+    assert_eq!(state.results.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn rejected_jobs_are_released() {
+    let processor = MockProcessor {
+        rejected_jobs: 2,
+        ..MockProcessor::new(0, 1)
+    };
+    let state = run_single_job(processor).await;
+
+    assert_eq!(*state.released_jobs.lock().unwrap(), [1, 2]);
+    assert_eq!(*state.results.lock().unwrap(), [(3, 84)]);
+    assert_eq!(state.attempts.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn processor_status_is_published() {
+    let status = SharedJobProcessorStatus::default();
+    let processor = MockProcessor {
+        status: Some(status.clone()),
+        ..MockProcessor::new(1, 1)
+    };
+    let started_at = SystemTime::now();
+    run_jobs(processor, 2).await;
+
+    let status = status.read().unwrap();
+    assert!(status.last_activity_at >= started_at);
+    assert!(status.last_saved_job_at.unwrap() >= started_at);
+    assert_eq!(status.last_error.as_deref(), Some("transient failure"));
+    assert!(!status.is_stale(Duration::from_secs(60)));
+}
+
+#[tokio::test]
+async fn processor_status_is_stale_while_job_is_stuck() {
+    let status = SharedJobProcessorStatus::default();
+    let processor = MockProcessor {
+        status: Some(status.clone()),
+        processing_time: Duration::from_millis(300),
+        ..MockProcessor::new(0, 1)
+    };
+    let state = processor.state.clone();
+    let (stop_sender, stop_receiver) = watch::channel(false);
+    let run_handle = tokio::spawn(processor.run(stop_receiver, None));
+
+    while state.attempts.load(Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(status.read().unwrap().is_stale(Duration::from_millis(100)));
+
+    stop_sender.send(true).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), run_handle)
+        .await
+        .expect("processor didn't stop after receiving stop signal")
+        .unwrap()
+        .unwrap();
+    // Saving the job result is progress.
+    assert!(!status.read().unwrap().is_stale(Duration::from_millis(100)));
+}
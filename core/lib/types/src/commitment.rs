@@ -298,11 +298,27 @@ struct RootState {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct BlockPassThroughData {
+pub(crate) struct BlockPassThroughData {
     shared_states: Vec<RootState>,
 }
 
 impl BlockPassThroughData {
+    pub(crate) fn new(rollup_last_leaf_index: u64, rollup_root_hash: H256) -> Self {
+        Self {
+            shared_states: vec![
+                RootState {
+                    last_leaf_index: rollup_last_leaf_index,
+                    root_hash: rollup_root_hash,
+                },
+                // Despite the fact, that zk_porter is not available we have to add params about it.
+                RootState {
+                    last_leaf_index: 0,
+                    root_hash: H256::zero(),
+                },
+            ],
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         // We assume that currently we have only two shared state: Rollup and ZkPorter where porter is always zero
         const SERIALIZED_SIZE: usize = 8 + 32 + 8 + 32;
@@ -356,19 +372,7 @@ impl BlockCommitment {
         };
 
         Self {
-            pass_through_data: BlockPassThroughData {
-                shared_states: vec![
-                    RootState {
-                        last_leaf_index: rollup_last_leaf_index,
-                        root_hash: rollup_root_hash,
-                    },
-                    // Despite the fact, that zk_porter is not available we have to add params about it.
-                    RootState {
-                        last_leaf_index: 0,
-                        root_hash: H256::zero(),
-                    },
-                ],
-            },
+            pass_through_data: BlockPassThroughData::new(rollup_last_leaf_index, rollup_root_hash),
             auxiliary_output: BlockAuxiliaryOutput::new(
                 l2_to_l1_logs,
                 initial_writes,
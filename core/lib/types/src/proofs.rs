@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use std::ops::Add;
 use std::str::FromStr;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, Bytes};
 use zkevm_test_harness::abstract_zksync_circuit::concrete_circuits::ZkSyncCircuit;
@@ -21,6 +21,8 @@ use zkevm_test_harness::{
 
 use zksync_basic_types::{L1BatchNumber, H256, U256};
 
+use crate::commitment::{BlockMetaParameters, BlockPassThroughData};
+
 const HASH_LEN: usize = H256::len_bytes();
 
 /// Metadata emitted by a Merkle tree after processing single storage log.
@@ -271,6 +273,8 @@ pub struct LeafAggregationJobMetadata {
     pub id: u32,
     pub block_number: L1BatchNumber,
     pub circuit_id: u8,
+    /// Number of attempts to process the job, including the current one.
+    pub attempts: u32,
     pub prover_job_ids_for_proofs: Vec<u32>,
 }
 
@@ -280,7 +284,12 @@ pub struct NodeAggregationJobMetadata {
     pub block_number: L1BatchNumber,
     pub circuit_id: u8,
     pub depth: u16,
+    /// Number of attempts to process the job, including the current one.
+    pub attempts: u32,
     pub prover_job_ids_for_proofs: Vec<u32>,
+    /// Max number of child proofs aggregated by a single node circuit, recorded for the L1 batch;
+    /// `None` for jobs created before fan-in was recorded.
+    pub fan_in: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -421,6 +430,18 @@ impl Add for JobCountStatistics {
     }
 }
 
+/// Total size of FRI blobs created on a certain day in a certain aggregation round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyBlobSizes {
+    pub day: NaiveDate,
+    pub aggregation_round: AggregationRound,
+    /// Total size of the serialized circuits for the prover jobs in this round, in bytes.
+    pub circuit_blobs_size: u64,
+    /// Total size of the serialized witness inputs for this round, in bytes. Only recorded
+    /// for scheduler partial inputs; zero for other rounds.
+    pub input_blobs_size: u64,
+}
+
 #[derive(Debug)]
 pub struct StuckJobs {
     pub id: u64,
@@ -428,9 +449,72 @@ pub struct StuckJobs {
     pub attempts: u64,
 }
 
+/// Lease of a scheduler witness job held by the witness generator processing it. Outcomes of the job
+/// are only persisted while the lease is held, i.e. until the job is requeued and picked up again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerJobLease {
+    pub l1_batch_number: L1BatchNumber,
+    pub lease_id: String,
+    /// Number of attempts to process the job, including the one holding the lease.
+    pub attempts: u32,
+    /// Protocol version of the L1 batch, or `None` if it wasn't recorded when the job was created.
+    pub protocol_version: Option<u16>,
+}
+
+/// Source of a node proof embedded into the scheduler circuit. Sources are persisted, so that
+/// the prover job supplying each proof can be found if the scheduler proof fails verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchedulerProofWitnessSource {
+    /// Position of the proof in the proof witnesses of the scheduler circuit.
+    pub position: usize,
+    pub prover_job_id: u32,
+    pub circuit_id: u8,
+    pub proof_blob_url: String,
+}
+
+/// Commitments to the L1 batch exposed by the scheduler circuit, computed from its witness.
+/// They are persisted with the scheduler job, so that they can be compared with the commitments
+/// computed by the server before the L1 batch is submitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchedulerCommitments {
+    pub meta_parameters_hash: H256,
+    pub pass_through_data_hash: H256,
+}
+
+impl SchedulerCommitments {
+    /// Computes commitments in the same way as [`BlockCommitment`](crate::commitment::BlockCommitment).
+    /// `rollup_last_leaf_index` is the enumeration index of the next leaf inserted into
+    /// the Merkle tree after the L1 batch.
+    pub fn new(
+        meta_parameters: &BlockMetaParameters,
+        rollup_last_leaf_index: u64,
+        rollup_root_hash: H256,
+    ) -> Self {
+        let pass_through_data = BlockPassThroughData::new(rollup_last_leaf_index, rollup_root_hash);
+        Self {
+            meta_parameters_hash: meta_parameters.hash(),
+            pass_through_data_hash: pass_through_data.hash(),
+        }
+    }
+}
+
+/// Failed attempt of a scheduler job. Failures are appended to the error history of the job,
+/// while the job itself only keeps the latest error.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobErrorHistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    /// Number of the failed attempt, or `None` if the job processor didn't report it.
+    pub attempt: Option<u32>,
+    pub error_kind: String,
+    pub error: String,
+    /// Host name of the worker that made the attempt, if known.
+    pub hostname: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commitment::BlockCommitment;
 
     #[test]
     fn prepare_basic_circuits_job_roundtrip() {
@@ -466,4 +550,40 @@ mod tests {
         let logs_from_job: Vec<_> = job.into_merkle_paths().collect();
         assert_eq!(logs_from_job, logs);
     }
+
+    #[test]
+    fn scheduler_commitments_match_block_commitment() {
+        let root_hash = H256::repeat_byte(0x12);
+        let bootloader_code_hash = H256::repeat_byte(0x34);
+        let default_aa_code_hash = H256::repeat_byte(0x56);
+        let block_commitment = BlockCommitment::new(
+            vec![],
+            42,
+            root_hash,
+            vec![],
+            vec![],
+            bootloader_code_hash,
+            default_aa_code_hash,
+        );
+        let expected = block_commitment.hash();
+
+        let commitments =
+            SchedulerCommitments::new(&block_commitment.meta_parameters(), 42, root_hash);
+        assert_eq!(commitments.meta_parameters_hash, expected.meta_parameters);
+        assert_eq!(
+            commitments.pass_through_data_hash,
+            expected.pass_through_data
+        );
+
+        let other_commitments =
+            SchedulerCommitments::new(&block_commitment.meta_parameters(), 43, root_hash);
+        assert_eq!(
+            other_commitments.meta_parameters_hash,
+            expected.meta_parameters
+        );
+        assert_ne!(
+            other_commitments.pass_through_data_hash,
+            expected.pass_through_data
+        );
+    }
 }